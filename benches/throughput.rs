@@ -0,0 +1,132 @@
+// Vazão do motor com um servidor em memória (`cargo bench --bench throughput`)
+//
+// Mede só o caminho rede → disco do motor: o servidor entrega fatias de um único
+// buffer em pedaços de FRAME_SIZE, como uma conexão TCP. A vazão absoluta varia
+// demais entre máquinas (e entre runners do CI) para servir de limite: com
+// KEEPERS_THROUGHPUT_OUT os resultados são gravados em JSON e, com
+// KEEPERS_THROUGHPUT_BASELINE apontando para um desses arquivos (o commit base,
+// medido na mesma máquina), o benchmark falha se alguma quantidade de conexões
+// ficar mais que KEEPERS_MAX_REGRESSION (fração, padrão 0.30) abaixo dele.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use keepers::benchmark::run_benchmark;
+use keepers::http::{HttpBackend, HttpError, HttpMethod, HttpRequest, HttpResponse, SharedBackend};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::{StatusCode, Version};
+
+const URL: &str = "http://memoria/arquivo.bin";
+const FILE_SIZE: usize = 128 * 1024 * 1024;
+const FRAME_SIZE: usize = 16 * 1024;
+const CONNECTION_COUNTS: [usize; 3] = [1, 4, 8];
+const DEFAULT_MAX_REGRESSION: f64 = 0.30;
+
+// Vazão (MB/s) por quantidade de conexões: {"1": 812.5, "4": 1530.2, ...}
+type Speeds = BTreeMap<String, f64>;
+
+fn read_baseline(path: &str) -> Option<Speeds> {
+    let text = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+struct MemoryBackend {
+    data: Bytes,
+}
+
+impl HttpBackend for MemoryBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        let len = self.data.len();
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        let (status, body) = match request.header_value(RANGE.as_str()) {
+            Some(range) => {
+                let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+                let start: usize = start.parse().unwrap();
+                let end = if end.is_empty() { len - 1 } else { end.parse::<usize>().unwrap().min(len - 1) };
+                headers.insert(
+                    CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)).unwrap(),
+                );
+                (StatusCode::PARTIAL_CONTENT, self.data.slice(start..=end))
+            }
+            None => (StatusCode::OK, self.data.clone()),
+        };
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len() as u64));
+        let body = if request.method == HttpMethod::Head { Bytes::new() } else { body };
+        let frames: Vec<Result<Bytes, HttpError>> =
+            (0..body.len()).step_by(FRAME_SIZE).map(|at| Ok(body.slice(at..(at + FRAME_SIZE).min(body.len())))).collect();
+        async move {
+            Ok(HttpResponse { status, version: Version::HTTP_11, headers, body: futures_util::stream::iter(frames).boxed() })
+        }
+        .boxed()
+    }
+}
+
+fn main() {
+    let data: Vec<u8> = (0..FILE_SIZE).map(|i| (i % 251) as u8).collect();
+    let backend: SharedBackend = Arc::new(MemoryBackend { data: Bytes::from(data) });
+    let dir = tempfile::tempdir().expect("pasta temporária");
+    let runtime = tokio::runtime::Runtime::new().expect("runtime do tokio");
+
+    let results = match runtime.block_on(run_benchmark(&backend, URL, &CONNECTION_COUNTS, dir.path())) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Erro: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Sem referência (primeira medição, commit base sem o arquivo): só informa
+    let baseline = std::env::var("KEEPERS_THROUGHPUT_BASELINE").ok().and_then(|path| {
+        let baseline = read_baseline(&path);
+        if baseline.is_none() {
+            eprintln!("Sem referência em {}: os resultados são só informativos", path);
+        }
+        baseline
+    });
+    let max_regression = std::env::var("KEEPERS_MAX_REGRESSION")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_REGRESSION);
+
+    let mut failed = false;
+    let mut speeds = Speeds::new();
+    println!("{:>10}  {:>10}  {:>12}  {:>12}", "conexões", "tempo", "vazão", "referência");
+    for result in &results {
+        let speed = result.speed() / (1024.0 * 1024.0);
+        speeds.insert(result.connections.to_string(), speed);
+        let reference = baseline.as_ref().and_then(|baseline| baseline.get(&result.connections.to_string()).copied());
+        let reference_text = reference.map(|reference| format!("{:.1} MB/s", reference)).unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:>10}  {:>9.2}s  {:>7.1} MB/s  {:>12}",
+            result.connections,
+            result.elapsed.as_secs_f64(),
+            speed,
+            reference_text
+        );
+        if result.bytes != FILE_SIZE as u64 {
+            eprintln!("{} conexões: arquivo incompleto ({} bytes)", result.connections, result.bytes);
+            failed = true;
+        }
+        if reference.is_some_and(|reference| speed < reference * (1.0 - max_regression)) {
+            eprintln!(
+                "{} conexões: {:.1} MB/s, mais de {:.0}% abaixo da referência",
+                result.connections,
+                speed,
+                max_regression * 100.0
+            );
+            failed = true;
+        }
+    }
+    if let Ok(path) = std::env::var("KEEPERS_THROUGHPUT_OUT") {
+        let json = serde_json::to_string_pretty(&speeds).expect("resultados em JSON");
+        if let Err(e) = std::fs::write(&path, json) {
+            eprintln!("Erro ao gravar {}: {}", path, e);
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,116 @@
+// Arquivos de controle do aria2 ("<nome>.aria2")
+//
+// O aria2 (e o uGet, que usa o aria2 por baixo) grava o arquivo parcial com o
+// nome final e, ao lado, um controle binário com o tamanho das peças e um
+// bitfield das peças já baixadas. Convertido em mapa de chunks, o download
+// continua no Keepers sem baixar de novo o que já estava no disco.
+//
+// Formato: versão (u16), extensão (u32), hash (u32 + bytes, vazio fora do
+// BitTorrent), tamanho da peça (u32), tamanho total (u64), enviado (u64),
+// bitfield (u32 + bytes) e as peças em andamento, ignoradas aqui. Na versão 1
+// os números são big-endian; na 0, na ordem da máquina que gravou (little-endian
+// nas plataformas comuns).
+use std::path::{Path, PathBuf};
+
+use crate::engine::ChunkState;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFile {
+    pub piece_length: u64,
+    pub total_length: u64,
+    pub bitfield: Vec<u8>, // Bit mais significativo primeiro: peça 0 = 0x80 do primeiro byte
+}
+
+pub fn control_file_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.as_os_str().to_owned();
+    name.push(".aria2");
+    PathBuf::from(name)
+}
+
+pub fn parse_control_file(bytes: &[u8]) -> Result<ControlFile, String> {
+    let mut reader = Reader { bytes, pos: 0, big_endian: true };
+    let version = reader.take(2)?;
+    reader.big_endian = match version {
+        [0, 1] => true,
+        [0, 0] => false,
+        _ => return Err(format!("Versão de controle do aria2 desconhecida: {:?}", version)),
+    };
+    reader.take(4)?; // Extensão
+    let hash_length = reader.u32()? as usize;
+    reader.take(hash_length)?;
+    let piece_length = reader.u32()? as u64;
+    let total_length = reader.u64()?;
+    reader.u64()?; // Enviado (BitTorrent)
+    let bitfield_length = reader.u32()? as usize;
+    let bitfield = reader.take(bitfield_length)?.to_vec();
+
+    if piece_length == 0 || total_length == 0 {
+        return Err("Controle do aria2 sem tamanho de peça ou total".to_string());
+    }
+    if (bitfield.len() as u64) < total_length.div_ceil(piece_length).div_ceil(8) {
+        return Err("Bitfield do aria2 menor que o número de peças".to_string());
+    }
+    Ok(ControlFile { piece_length, total_length, bitfield })
+}
+
+impl ControlFile {
+    pub fn has_piece(&self, index: u64) -> bool {
+        self.bitfield
+            .get((index / 8) as usize)
+            .is_some_and(|byte| byte & (0x80 >> (index % 8)) != 0)
+    }
+
+    // Bytes em peças completas
+    pub fn completed_bytes(&self) -> u64 {
+        self.chunks().iter().map(|chunk| chunk.downloaded).sum()
+    }
+
+    // Uma sequência de peças iguais (baixadas ou não) vira um chunk
+    pub fn chunks(&self) -> Vec<ChunkState> {
+        let pieces = self.total_length.div_ceil(self.piece_length);
+        let mut chunks: Vec<ChunkState> = Vec::new();
+        for index in 0..pieces {
+            let start = index * self.piece_length;
+            let end = (start + self.piece_length).min(self.total_length) - 1;
+            let done = self.has_piece(index);
+            match chunks.last_mut() {
+                Some(last) if last.is_complete() == done => {
+                    last.end = end;
+                    if done {
+                        last.downloaded = end - last.start + 1;
+                    }
+                }
+                _ => chunks.push(ChunkState { start, end, downloaded: if done { end - start + 1 } else { 0 } }),
+            }
+        }
+        chunks
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err("Controle do aria2 truncado".to_string());
+        };
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes: [u8; 4] = self.take(4)?.try_into().unwrap_or_default();
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let bytes: [u8; 8] = self.take(8)?.try_into().unwrap_or_default();
+        Ok(if self.big_endian { u64::from_be_bytes(bytes) } else { u64::from_le_bytes(bytes) })
+    }
+}
@@ -0,0 +1,34 @@
+// Novas tentativas automáticas de downloads que falharam
+//
+// Só falhas passageiras (rede, servidor sobrecarregado, espelho instável) são
+// tentadas de novo, com espera dobrando a cada vez: 3min, 6min, 12min... até
+// RETRY_MAX_DELAY_SECS. Passado o número de tentativas configurado, o download
+// fica como falhou até o usuário agir.
+use chrono::{DateTime, Duration, Utc};
+use crate::failure::FailureKind;
+
+pub const RETRY_BASE_DELAY_SECS: i64 = 180;
+pub const RETRY_MAX_DELAY_SECS: i64 = 4 * 3600;
+pub const MAX_AUTO_RETRIES: u32 = 20;
+
+// Espera antes da tentativa de número `attempt` (0 = a primeira)
+pub fn retry_delay(attempt: u32) -> Duration {
+    let secs = RETRY_BASE_DELAY_SECS.saturating_mul(1i64 << attempt.min(16));
+    Duration::seconds(secs.min(RETRY_MAX_DELAY_SECS))
+}
+
+// Horário da próxima tentativa; None se a falha não é passageira ou as tentativas acabaram
+pub fn next_retry(failure: FailureKind, attempts: u32, budget: u32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    (failure.is_transient() && attempts < budget).then(|| now + retry_delay(attempts))
+}
+
+// "próxima tentativa em 12min"
+pub fn retry_text(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = ((at - now).num_seconds().max(0) as u64).div_ceil(60);
+    match minutes {
+        0 => "próxima tentativa em instantes".to_string(),
+        1..=59 => format!("próxima tentativa em {}min", minutes),
+        _ if minutes.is_multiple_of(60) => format!("próxima tentativa em {}h", minutes / 60),
+        _ => format!("próxima tentativa em {}h {}min", minutes / 60, minutes % 60),
+    }
+}
@@ -0,0 +1,54 @@
+// Salvamento automático do progresso de todos os downloads
+//
+// Roda numa thread própria, fora da interface: os avisos de progresso param de
+// chegar ao registro quando a janela fica oculta ou o card é removido, mas o
+// motor continua anotando em cada tarefa o quanto já está no disco. A cada
+// AUTOSAVE_INTERVAL_SECS esse ponto (e o histórico de eventos) vai para a lista
+// de downloads; o mapa de chunks o próprio motor grava enquanto baixa.
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::engine::DownloadTask;
+use crate::storage::{save_downloads, DownloadRecord, DownloadStatus};
+
+pub const AUTOSAVE_INTERVAL_SECS: u64 = 5;
+
+// Copia para os registros em andamento o ponto gravado e os eventos de cada
+// tarefa. Retorna true se algum registro mudou.
+pub fn snapshot_tasks(tasks: &[Arc<Mutex<DownloadTask>>], records: &mut [DownloadRecord]) -> bool {
+    let mut changed = false;
+    for task in tasks {
+        let Ok(task) = task.lock() else {
+            continue;
+        };
+        let Some(record) = records.iter_mut().find(|r| r.id == task.id && r.status == DownloadStatus::InProgress) else {
+            continue;
+        };
+        if task.downloaded > 0 && record.downloaded_bytes != task.downloaded {
+            record.downloaded_bytes = task.downloaded;
+            changed = true;
+        }
+        if record.events != task.events {
+            record.events = task.events.clone();
+            changed = true;
+        }
+    }
+    changed
+}
+
+// Grava a cada `interval` enquanto o app estiver aberto; `tasks` devolve os
+// downloads da sessão no momento
+pub fn start_autosave(
+    tasks: impl Fn() -> Vec<Arc<Mutex<DownloadTask>>> + Send + 'static,
+    records: Arc<Mutex<Vec<DownloadRecord>>>,
+    interval: Duration,
+) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        let tasks = tasks();
+        if let Ok(mut records) = records.lock() {
+            if snapshot_tasks(&tasks, &mut records) {
+                save_downloads(&records);
+            }
+        }
+    });
+}
@@ -0,0 +1,116 @@
+// Início automático no login: entrada XDG em ~/.config/autostart (Linux), valor na
+// chave Run do registro (Windows) ou LaunchAgent (macOS). Quem escolhe entre eles
+// é `platform::set_autostart`.
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Argumento incluído no Exec da entrada para o app saber que foi aberto no login
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+pub fn autostart_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("autostart"))
+}
+
+pub fn entry_path(dir: &Path, app_id: &str) -> PathBuf {
+    dir.join(format!("{}.desktop", app_id))
+}
+
+// Conteúdo da entrada .desktop que abre `exec` com AUTOSTART_ARG
+pub fn desktop_entry(exec: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Keepers\n\
+         Comment=Gerenciador de downloads\n\
+         Exec={} {}\n\
+         Icon=folder-download-symbolic\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        quote_exec_arg(&exec.to_string_lossy()),
+        AUTOSTART_ARG
+    )
+}
+
+// Aspas exigidas pela especificação de desktop entries para argumentos com espaços
+// ou caracteres reservados
+fn quote_exec_arg(arg: &str) -> String {
+    let reserved = |c: char| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c);
+    if !arg.contains(reserved) {
+        return arg.replace('%', "%%");
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub fn is_enabled(dir: &Path, app_id: &str) -> bool {
+    entry_path(dir, app_id).exists()
+}
+
+// Instala (ou remove) a entrada; remover uma entrada inexistente não é erro
+pub fn set_enabled(dir: &Path, app_id: &str, exec: &Path, enabled: bool) -> io::Result<()> {
+    let path = entry_path(dir, app_id);
+    if enabled {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&path, desktop_entry(exec))
+    } else {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Chave do registro lida pelo Windows no login do usuário
+pub const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+// Comando gravado no valor da chave Run
+pub fn run_key_command(exec: &Path) -> String {
+    format!("\"{}\" {}", exec.display(), AUTOSTART_ARG)
+}
+
+pub fn launch_agents_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|dir| dir.join("Library").join("LaunchAgents"))
+}
+
+pub fn launch_agent_path(dir: &Path, app_id: &str) -> PathBuf {
+    dir.join(format!("{}.plist", app_id))
+}
+
+// LaunchAgent que o launchd abre no login com AUTOSTART_ARG
+pub fn launch_agent_plist(app_id: &str, exec: &Path) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \t<key>Label</key>\n\
+         \t<string>{}</string>\n\
+         \t<key>ProgramArguments</key>\n\
+         \t<array>\n\
+         \t\t<string>{}</string>\n\
+         \t\t<string>{}</string>\n\
+         \t</array>\n\
+         \t<key>RunAtLoad</key>\n\
+         \t<true/>\n\
+         </dict>\n\
+         </plist>\n",
+        escape_xml(app_id),
+        escape_xml(&exec.to_string_lossy()),
+        AUTOSTART_ARG
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
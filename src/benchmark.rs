@@ -0,0 +1,81 @@
+// Medição do motor com números fixos de conexões (`keepers benchmark <url>`)
+//
+// Baixa o mesmo arquivo uma vez para cada quantidade de conexões, numa pasta
+// temporária, e mede a velocidade. Serve para escolher o padrão de conexões e
+// para comparar mudanças no motor; não aparece na interface.
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::engine::{run_download, DownloadMessage, DownloadTask};
+use crate::http::SharedBackend;
+use crate::storage::new_download_id;
+
+pub const DEFAULT_CONNECTION_COUNTS: [usize; 5] = [1, 2, 4, 8, 16];
+const BENCHMARK_FILE: &str = "benchmark.bin";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkResult {
+    pub connections: usize,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchmarkResult {
+    pub fn speed(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64().max(0.001)
+    }
+}
+
+// Uma rodada por quantidade de conexões; o arquivo é apagado entre as rodadas
+pub async fn run_benchmark(
+    backend: &SharedBackend,
+    url: &str,
+    connection_counts: &[usize],
+    work_dir: &Path,
+) -> Result<Vec<BenchmarkResult>, String> {
+    let mut results = Vec::new();
+    for &connections in connection_counts {
+        let connections = connections.max(1);
+        let task = Arc::new(Mutex::new(DownloadTask::new(new_download_id())));
+        if let Ok(mut task) = task.lock() {
+            // Conexões fixas: começa e fica em `connections`, sem scripts nem resolvedores
+            task.initial_connections = Some(connections);
+            task.max_connections = connections;
+            task.resolvers = Vec::new();
+        }
+        let (tx, rx) = async_channel::unbounded();
+        let records = Arc::new(Mutex::new(Vec::new()));
+
+        let started = Instant::now();
+        run_download(backend.clone(), url, BENCHMARK_FILE, work_dir, &tx, &task, &records).await;
+        let elapsed = started.elapsed();
+
+        let mut error = None;
+        while let Ok(message) = rx.try_recv() {
+            if let DownloadMessage::Error(e) = message {
+                error = Some(e);
+            }
+        }
+        let file_path = work_dir.join(BENCHMARK_FILE);
+        let bytes = std::fs::metadata(&file_path).map(|meta| meta.len()).unwrap_or(0);
+        let _ = std::fs::remove_file(&file_path);
+        if let Some(e) = error {
+            return Err(format!("{} conexões: {}", connections, e));
+        }
+        results.push(BenchmarkResult { connections, bytes, elapsed });
+    }
+    Ok(results)
+}
+
+// Argumentos depois da URL: quantidades de conexões ("1 4 8"); vazio = padrão
+pub fn parse_connection_counts(args: &[String]) -> Result<Vec<usize>, String> {
+    if args.is_empty() {
+        return Ok(DEFAULT_CONNECTION_COUNTS.to_vec());
+    }
+    args.iter()
+        .map(|arg| match arg.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(format!("Número de conexões inválido: {}", arg)),
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+// Somas SHA-256 publicadas ao lado do arquivo (`arquivo.iso.sha256` ou `SHA256SUMS`)
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use sha2::{Digest, Sha256};
+
+// Tamanho máximo aceito para uma lista de somas
+pub const MAX_SUMS_SIZE: usize = 1024 * 1024;
+
+// Nome do arquivo na URL (sem query/fragmento), como aparece nas listas de somas
+pub fn remote_filename(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let name = path.rsplit('/').next()?;
+    if name.is_empty() { None } else { Some(name) }
+}
+
+// URLs onde procurar a soma, na ordem: arquivo próprio e depois a lista da pasta
+pub fn checksum_urls(url: &str) -> Vec<String> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let mut urls = vec![format!("{}.sha256", path)];
+    if let Some(slash) = path.rfind('/') {
+        if remote_filename(url).is_some() {
+            urls.push(format!("{}SHA256SUMS", &path[..=slash]));
+        }
+    }
+    urls
+}
+
+// Procura a soma de `filename` no formato do sha256sum ("<hash>  nome" ou
+// "<hash> *nome"), no formato BSD ("SHA256 (nome) = <hash>") ou só o hash
+pub fn find_checksum(text: &str, filename: &str) -> Option<String> {
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).collect();
+
+    for line in &lines {
+        if let Some(rest) = line.strip_prefix("SHA256 (") {
+            if let Some((name, hash)) = rest.split_once(") = ") {
+                if name == filename && is_sha256(hash) {
+                    return Some(hash.to_ascii_lowercase());
+                }
+            }
+            continue;
+        }
+        if let Some((hash, name)) = line.split_once(char::is_whitespace) {
+            let name = name.trim_start().trim_start_matches('*');
+            let name = name.strip_prefix("./").unwrap_or(name);
+            if name == filename && is_sha256(hash) {
+                return Some(hash.to_ascii_lowercase());
+            }
+        }
+    }
+
+    // Arquivo .sha256 com apenas o hash
+    match lines.as_slice() {
+        [hash] if is_sha256(hash) => Some(hash.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
@@ -0,0 +1,111 @@
+// Corpos com Content-Encoding (gzip, deflate, br) descompactados ao terminar
+//
+// O Content-Length e os intervalos de Range valem para o corpo compactado, então
+// o .part guarda os bytes como vieram (chunks paralelos e retomada funcionam como
+// sempre) e só o arquivo completo é descompactado. Um .tar.gz servido com
+// "Content-Encoding: gzip" (servidor mal configurado) fica como veio, como nos
+// navegadores; a opção de manter a codificação vale para todos.
+use std::io::{Read, Write};
+use std::path::Path;
+use reqwest::header::HeaderMap;
+
+// Buffer interno do descompactador de brotli
+const BROTLI_BUFFER_SIZE: usize = 64 * 1024;
+
+// "gzip", "br"...; None sem codificação ou com "identity"
+pub fn content_encoding(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty() && v != "identity")
+}
+
+// Nome ou tipo do arquivo já dizem que ele é compactado com `encoding`: o
+// Content-Encoding descreve o próprio arquivo e não deve ser desfeito
+pub fn names_encoding(encoding: &str, filename: &str, content_type: Option<&str>) -> bool {
+    let (extensions, types): (&[&str], &[&str]) = match encoding {
+        "gzip" | "x-gzip" => (&[".gz", ".tgz", ".svgz"], &["application/gzip", "application/x-gzip", "application/x-gtar", "application/x-tgz"]),
+        "deflate" => (&[".zz", ".zlib"], &["application/zlib", "application/x-deflate"]),
+        "br" => (&[".br"], &["application/x-brotli", "application/brotli"]),
+        _ => (&[], &[]),
+    };
+    let filename = filename.to_ascii_lowercase();
+    extensions.iter().any(|extension| filename.ends_with(extension))
+        || content_type.is_some_and(|content_type| types.iter().any(|t| content_type.eq_ignore_ascii_case(t)))
+}
+
+// Descompacta `src` (o corpo inteiro como veio) em `dest`; devolve o tamanho descompactado
+pub fn decode_file(encoding: &str, src: &Path, dest: &Path) -> std::io::Result<u64> {
+    let mut decoder = ContentDecoder::for_encoding(encoding)
+        .ok_or_else(|| std::io::Error::other(format!("codificação {} sem suporte", encoding)))?;
+    let mut input = std::fs::File::open(src)?;
+    let mut output = std::io::BufWriter::new(std::fs::File::create(dest)?);
+    let mut buffer = vec![0; BROTLI_BUFFER_SIZE];
+    let mut written = 0u64;
+    loop {
+        let read = input.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let data = decoder.decode(&buffer[..read])?;
+        output.write_all(&data)?;
+        written += data.len() as u64;
+    }
+    let rest = decoder.finish()?;
+    output.write_all(&rest)?;
+    output.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok(written + rest.len() as u64)
+}
+
+pub enum ContentDecoder {
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibDecoder<Vec<u8>>),
+    Brotli(Box<brotli_decompressor::DecompressorWriter<Vec<u8>>>),
+}
+
+impl ContentDecoder {
+    // None para codificações sem suporte (zstd, compress, várias encadeadas):
+    // o corpo fica como veio
+    pub fn for_encoding(encoding: &str) -> Option<Self> {
+        match encoding {
+            "gzip" | "x-gzip" => Some(ContentDecoder::Gzip(flate2::write::GzDecoder::new(Vec::new()))),
+            "deflate" => Some(ContentDecoder::Deflate(flate2::write::ZlibDecoder::new(Vec::new()))),
+            "br" => Some(ContentDecoder::Brotli(Box::new(brotli_decompressor::DecompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+            )))),
+            _ => None,
+        }
+    }
+
+    // Recebe um pedaço compactado e devolve o que já deu para descompactar
+    pub fn decode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            ContentDecoder::Gzip(decoder) => {
+                decoder.write_all(data)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            ContentDecoder::Deflate(decoder) => {
+                decoder.write_all(data)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            ContentDecoder::Brotli(decoder) => {
+                decoder.write_all(data)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+
+    // Fim do corpo: o que ainda estava retido no descompactador
+    pub fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            ContentDecoder::Gzip(decoder) => decoder.finish(),
+            ContentDecoder::Deflate(decoder) => decoder.finish(),
+            ContentDecoder::Brotli(mut decoder) => {
+                decoder.close()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// Modelo de um download exibido na lista
+//
+// Guarda apenas o que a interface mostra; o `DownloadRow` se liga a estas
+// propriedades e se atualiza sozinho quando o loop de mensagens as altera.
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use keepers::auto_retry::retry_text;
+use keepers::engine::{is_streamable_media, DownloadState, WaitReason};
+use keepers::failure::FailureKind;
+use keepers::storage::{DownloadRecord, DownloadStatus, FileIntegrity, ScanResult};
+use uuid::Uuid;
+use chrono::{Local, Utc};
+
+// Texto do card de um arquivo marcado pelo antivírus
+pub const INFECTED_TEXT: &str = "Infectado · abrir bloqueado";
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::DownloadObject)]
+    pub struct DownloadObject {
+        // Id do DownloadRecord em texto (chave de todas as buscas)
+        #[property(get, set)]
+        id: RefCell<String>,
+        #[property(get, set)]
+        url: RefCell<String>,
+        #[property(get, set)]
+        filename: RefCell<String>,
+        // Classe CSS de um `DownloadState` (ver download_state)
+        #[property(get, set)]
+        state: RefCell<String>,
+        #[property(get, set)]
+        status_text: RefCell<String>,
+        #[property(get, set)]
+        progress: Cell<f64>,
+        #[property(get, set)]
+        size_text: RefCell<String>,
+        #[property(get, set)]
+        speed_text: RefCell<String>,
+        #[property(get, set)]
+        eta_text: RefCell<String>,
+        #[property(get, set)]
+        date_text: RefCell<String>,
+        #[property(get, set)]
+        parallel: Cell<bool>,
+        // Conexões de chunk abertas agora (mostradas na tag do modo paralelo)
+        #[property(get, set)]
+        connections: Cell<u32>,
+        #[property(get, set)]
+        resuming: Cell<bool>,
+        // Há uma thread de download ativa controlando este item
+        #[property(get, set)]
+        active: Cell<bool>,
+        // Caminho final do arquivo (vazio enquanto não concluir)
+        #[property(get, set)]
+        file_path: RefCell<String>,
+        // Modo sincronização (pode ser baixado de novo se o arquivo remoto mudar)
+        #[property(get, set)]
+        sync: Cell<bool>,
+        // Próxima execução de um download recorrente (vazio se não for recorrente)
+        #[property(get, set)]
+        next_run_text: RefCell<String>,
+        // Vídeo/áudio que pode ser aberto pelo .part enquanto baixa
+        #[property(get, set)]
+        media: Cell<bool>,
+        // Chunks baixados em ordem (permite a pré-visualização no modo paralelo)
+        #[property(get, set)]
+        sequential_priority: Cell<bool>,
+        // Concluído, mas menor que o baixado: dá para buscar só o final
+        #[property(get, set)]
+        truncated: Cell<bool>,
+        // Antivírus encontrou ameaça: o arquivo não é aberto pelo card
+        #[property(get, set)]
+        infected: Cell<bool>,
+        // Servidor não informou o tamanho: a barra pulsa em vez da porcentagem
+        #[property(get, set)]
+        indeterminate: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for DownloadObject {
+        const NAME: &'static str = "KeepersDownloadObject";
+        type Type = super::DownloadObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for DownloadObject {}
+}
+
+glib::wrapper! {
+    pub struct DownloadObject(ObjectSubclass<imp::DownloadObject>);
+}
+
+impl DownloadObject {
+    // Novo download ativo, ainda sem informações do servidor
+    pub fn new(id: Uuid, url: &str, filename: &str) -> Self {
+        glib::Object::builder()
+            .property("id", id.to_string())
+            .property("url", url)
+            .property("filename", filename)
+            .property("state", DownloadState::Connecting.css_class())
+            .property("status-text", "Iniciando...")
+            .property("active", true)
+            .property("media", is_streamable_media(filename))
+            .build()
+    }
+
+    // Item do histórico carregado do arquivo de dados
+    pub fn from_record(record: &DownloadRecord) -> Self {
+        // Pausa com prazo: mostra quando volta (retomada pelo agendador, ver run_due_downloads)
+        let paused_text = match record.resume_at {
+            Some(at) => WaitReason::Scheduled(at).text(),
+            None => "Pausado".to_string(),
+        };
+        // Falha passageira com nova tentativa marcada mostra quando ela acontece
+        let failed_label = record.failure.map_or("Falhou", FailureKind::label);
+        let failed_text = match record.retry_at {
+            Some(at) => format!("{} · {}", failed_label, retry_text(at, Utc::now())),
+            None => failed_label.to_string(),
+        };
+        let (state, status_text) = match record.status {
+            DownloadStatus::InProgress if record.was_paused => (DownloadState::Paused, paused_text.as_str()),
+            // Interrompido com o app fechado: volta para a fila ao abrir
+            DownloadStatus::InProgress => (DownloadState::Queued, "Na fila"),
+            // Verificação que encontrou problema continua visível até a próxima
+            DownloadStatus::Completed => match (record.integrity, record.scan) {
+                (_, Some(ScanResult::Infected)) => (DownloadState::Done, INFECTED_TEXT),
+                (Some(integrity), _) if integrity != FileIntegrity::Intact => (DownloadState::Done, integrity.label()),
+                _ => (DownloadState::Done, "Concluído"),
+            },
+            DownloadStatus::Failed => (DownloadState::Failed, failed_text.as_str()),
+            DownloadStatus::Cancelled => (DownloadState::Cancelled, "Cancelado"),
+        };
+
+        let progress = match record.status {
+            DownloadStatus::InProgress if record.total_bytes > 0 => {
+                record.downloaded_bytes as f64 / record.total_bytes as f64
+            }
+            DownloadStatus::Completed => 1.0,
+            _ => 0.0,
+        };
+
+        let size_text = if record.total_bytes > 0 {
+            crate::format_file_size(record.total_bytes)
+        } else {
+            "Desconhecido".to_string()
+        };
+
+        glib::Object::builder()
+            .property("id", record.id.to_string())
+            .property("url", &record.url)
+            .property("filename", &record.filename)
+            .property("state", state.css_class())
+            .property("status-text", status_text)
+            .property("progress", progress)
+            .property("size-text", size_text)
+            .property("date-text", record.date_added.format("%d/%m/%Y %H:%M").to_string())
+            .property("file-path", record.file_path.clone().unwrap_or_default())
+            .property("sync", record.options.sync)
+            .property("next-run-text", next_run_text(record))
+            .property("media", is_streamable_media(&record.filename))
+            .property("sequential-priority", record.options.sequential_priority)
+            .property("truncated", record.integrity == Some(FileIntegrity::Truncated))
+            .property("infected", record.scan == Some(ScanResult::Infected))
+            .property("indeterminate", record.status == DownloadStatus::InProgress && record.total_bytes == 0)
+            .build()
+    }
+
+    pub fn refresh_next_run(&self, record: &DownloadRecord) {
+        self.set_next_run_text(next_run_text(record));
+    }
+
+    pub fn download_id(&self) -> Uuid {
+        Uuid::parse_str(&self.id()).unwrap_or_default()
+    }
+
+    pub fn download_state(&self) -> DownloadState {
+        DownloadState::from_css_class(&self.state()).unwrap_or(DownloadState::Connecting)
+    }
+
+    // Aplica um novo estado junto com o texto do badge
+    pub fn set_download_state(&self, state: DownloadState, status_text: &str) {
+        self.set_status_text(status_text);
+        self.set_state(state.css_class());
+    }
+
+    // O .part já pode ser aberto no player (sequencial, ou chunks em ordem)
+    pub fn can_preview(&self) -> bool {
+        self.media() && self.is_unfinished() && (!self.parallel() || self.sequential_priority())
+    }
+
+    // Em andamento ou pausado (ainda não chegou a um estado final)
+    pub fn is_unfinished(&self) -> bool {
+        !self.download_state().is_final()
+    }
+}
+
+// "Próxima: 19/10 às 03:00" para downloads recorrentes
+pub fn next_run_text(record: &DownloadRecord) -> String {
+    match (&record.options.recurrence, record.next_run) {
+        (Some(_), Some(next)) => format!("Próxima: {}", next.with_timezone(&Local).format("%d/%m às %H:%M")),
+        _ => String::new(),
+    }
+}
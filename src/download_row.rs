@@ -0,0 +1,758 @@
+// Card de um download na lista
+//
+// Widget composto: monta a estrutura uma única vez e se liga às propriedades
+// de um `DownloadObject`. Quem cria o card só conecta os botões e altera o
+// objeto; título, barra, badge, metadados e visibilidade dos botões seguem
+// automaticamente. Em telas estreitas (modo compacto) os metadados vão para
+// baixo do status e as ações secundárias para um menu; na lista densa o card
+// perde espaçamento e metadados. Um concluído pode ser arrastado para fora
+// (Nautilus, apps de chat): o arrasto leva o próprio arquivo.
+use std::path::PathBuf;
+use gtk4::{gdk, gio, glib};
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{Box as GtkBox, Button, Label, Orientation};
+
+use keepers::engine::DownloadState;
+
+use crate::download_object::DownloadObject;
+use crate::{SPACING_MEDIUM, SPACING_SMALL, SPACING_TINY};
+
+// Passo da barra pulsando (mesmo ritmo dos avisos de progresso do motor)
+const PULSE_INTERVAL_MS: u64 = 200;
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+    use super::*;
+
+    #[derive(Default)]
+    pub struct DownloadRow {
+        pub title_label: Label,
+        pub parallel_tag: GtkBox,
+        pub parallel_label: Label,
+        pub resume_tag: GtkBox,
+        pub schedule_tag: GtkBox,
+        pub schedule_label: Label,
+        pub progress_bar: gtk4::ProgressBar,
+        pub status_badge: GtkBox,
+        pub status_icon: gtk4::Image,
+        pub status_label: Label,
+        pub size_label: Label,
+        pub speed_label: Label,
+        pub eta_label: Label,
+        pub date_label: Label,
+        pub open_button: Button,
+        pub open_folder_button: Button,
+        pub resume_button: Button,
+        pub restart_button: Button,
+        pub sync_button: Button,
+        pub verify_button: Button,
+        pub repair_button: Button,
+        pub preview_button: Button,
+        pub pause_button: Button,
+        pub pause_timer_button: Button,
+        pub info_button: Button,
+        pub copy_command_button: Button,
+        pub copy_file_button: Button,
+        pub share_button: Button,
+        pub cancel_button: Button,
+        pub delete_button: Button,
+        pub info_box: GtkBox,
+        pub metadata_box: GtkBox,
+        pub primary_actions_box: GtkBox,
+        pub overflow_box: GtkBox,
+        pub overflow_button: gtk4::MenuButton,
+        pub compact: Cell<bool>,
+        pub dense: Cell<bool>,
+        pub object: RefCell<Option<DownloadObject>>,
+        pub bindings: RefCell<Vec<glib::Binding>>,
+        pub handlers: RefCell<Vec<glib::SignalHandlerId>>,
+        // Timer da barra pulsando (download de tamanho desconhecido)
+        pub pulse_source: RefCell<Option<glib::SourceId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for DownloadRow {
+        const NAME: &'static str = "KeepersDownloadRow";
+        type Type = super::DownloadRow;
+        type ParentType = GtkBox;
+    }
+
+    impl ObjectImpl for DownloadRow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().build_layout();
+        }
+    }
+
+    impl WidgetImpl for DownloadRow {}
+    impl BoxImpl for DownloadRow {}
+}
+
+glib::wrapper! {
+    pub struct DownloadRow(ObjectSubclass<imp::DownloadRow>)
+        @extends GtkBox, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget, gtk4::Orientable;
+}
+
+impl Default for DownloadRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownloadRow {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", Orientation::Vertical)
+            .property("spacing", SPACING_MEDIUM)
+            .build()
+    }
+
+    fn build_layout(&self) {
+        let imp = self.imp();
+
+        self.set_margin_top(SPACING_MEDIUM);
+        self.set_margin_bottom(SPACING_MEDIUM);
+        self.set_margin_start(SPACING_MEDIUM);
+        self.set_margin_end(SPACING_MEDIUM);
+        self.add_css_class("download-card");
+
+        // Header com título e tags de modo de download
+        let title_box = GtkBox::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(SPACING_MEDIUM)
+            .halign(gtk4::Align::Start)
+            .build();
+
+        imp.title_label.set_halign(gtk4::Align::Start);
+        imp.title_label.set_hexpand(true);
+        imp.title_label.add_css_class("title-2");
+        imp.title_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        imp.title_label.set_use_markup(true);
+
+        setup_tag_with_label(
+            &imp.parallel_tag,
+            "network-transmit-receive-symbolic",
+            &imp.parallel_label,
+            "Download otimizado: arquivo baixado em múltiplas partes simultâneas",
+        );
+        setup_tag(
+            &imp.resume_tag,
+            "media-skip-forward-symbolic",
+            "Retomando",
+            "Continuando download de onde parou",
+        );
+
+        setup_tag_with_label(
+            &imp.schedule_tag,
+            "alarm-symbolic",
+            &imp.schedule_label,
+            "Download recorrente: será baixado de novo automaticamente",
+        );
+
+        title_box.append(&imp.title_label);
+        title_box.append(&imp.parallel_tag);
+        title_box.append(&imp.resume_tag);
+        title_box.append(&imp.schedule_tag);
+
+        // Barra de progresso
+        imp.progress_bar.set_hexpand(true);
+        imp.progress_bar.set_show_text(true);
+        imp.progress_bar.add_css_class("download-progress");
+
+        // Box de status e metadados
+        let info_box = &imp.info_box;
+        info_box.set_orientation(Orientation::Horizontal);
+        info_box.set_spacing(SPACING_MEDIUM);
+
+        let status_box = GtkBox::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(SPACING_SMALL)
+            .halign(gtk4::Align::Start)
+            .hexpand(true)
+            .build();
+
+        // Badge colorido para status
+        imp.status_badge.set_orientation(Orientation::Horizontal);
+        imp.status_badge.set_spacing(SPACING_SMALL);
+        imp.status_badge.set_halign(gtk4::Align::Start);
+        imp.status_badge.add_css_class("status-badge");
+
+        imp.status_icon.set_pixel_size(16);
+        imp.status_label.set_halign(gtk4::Align::Start);
+        imp.status_label.set_use_markup(true);
+
+        imp.status_badge.append(&imp.status_icon);
+        imp.status_badge.append(&imp.status_label);
+        status_box.append(&imp.status_badge);
+
+        // Metadados (tamanho, velocidade, ETA e data) - layout horizontal minimalista
+        let metadata_box = &imp.metadata_box;
+        metadata_box.set_orientation(Orientation::Horizontal);
+        metadata_box.set_spacing(SPACING_SMALL);
+        metadata_box.set_halign(gtk4::Align::End);
+        metadata_box.add_css_class("metadata-group");
+
+        for label in [&imp.size_label, &imp.speed_label, &imp.eta_label, &imp.date_label] {
+            label.set_halign(gtk4::Align::End);
+            label.set_use_markup(true);
+            metadata_box.append(label);
+        }
+        imp.eta_label.add_css_class("dim-label");
+        imp.date_label.add_css_class("dim-label");
+
+        info_box.append(&status_box);
+        info_box.append(metadata_box);
+
+        // Botões - a visibilidade de cada um depende do estado (ver sync_state)
+        setup_button(&imp.open_button, "document-open-symbolic", "Abrir arquivo", None);
+        setup_button(&imp.open_folder_button, "folder-open-symbolic", "Abrir pasta no explorador", None);
+        setup_button(&imp.resume_button, "media-playback-start-symbolic", "Retomar download", Some("suggested-action"));
+        setup_button(&imp.restart_button, "view-refresh-symbolic", "Reiniciar download do zero", Some("suggested-action"));
+        setup_button(&imp.sync_button, "emblem-synchronizing-symbolic", "Sincronizar (baixa só se houver versão nova)", None);
+        setup_button(&imp.verify_button, "security-high-symbolic", "Verificar integridade do arquivo", None);
+        setup_button(&imp.repair_button, "document-revert-symbolic", "Reparar (baixa só o final que falta)", Some("suggested-action"));
+        setup_button(&imp.preview_button, "video-x-generic-symbolic", "Pré-visualizar (abre o arquivo parcial no player)", None);
+        setup_button(&imp.pause_button, "media-playback-pause-symbolic", "Pausar", None);
+        setup_button(&imp.pause_timer_button, "alarm-symbolic", "Pausar por um tempo", None);
+        setup_button(&imp.info_button, "info-symbolic", "Ver estatísticas e detalhes", None);
+        setup_button(&imp.copy_command_button, "utilities-terminal-symbolic", "Copiar como comando curl", None);
+        setup_button(&imp.share_button, "send-to-symbolic", "Compartilhar...", None);
+        setup_button(&imp.copy_file_button, "edit-copy-symbolic", "Copiar arquivo (para colar no gerenciador de arquivos ou num chat)", None);
+        setup_button(&imp.cancel_button, "process-stop-symbolic", "Cancelar", Some("destructive-action"));
+        setup_button(&imp.delete_button, "user-trash-symbolic", "Remover da lista", Some("destructive-action"));
+
+        let buttons_box = GtkBox::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(SPACING_MEDIUM)
+            .halign(gtk4::Align::End)
+            .build();
+
+        // Ações primárias à esquerda
+        let primary_actions_box = &imp.primary_actions_box;
+        primary_actions_box.set_orientation(Orientation::Horizontal);
+        primary_actions_box.set_spacing(SPACING_SMALL);
+        primary_actions_box.set_hexpand(true);
+        primary_actions_box.set_halign(gtk4::Align::Start);
+
+        for button in self.primary_buttons() {
+            primary_actions_box.append(button);
+        }
+
+        // Menu com as ações secundárias, só usado no modo compacto
+        imp.overflow_box.set_orientation(Orientation::Vertical);
+        imp.overflow_box.set_spacing(SPACING_TINY);
+        let popover = gtk4::Popover::builder().child(&imp.overflow_box).build();
+        for button in self.overflow_buttons() {
+            button.connect_clicked(glib::clone!(#[weak] popover, move |_| popover.popdown()));
+        }
+        imp.overflow_button.set_icon_name("view-more-symbolic");
+        imp.overflow_button.set_tooltip_text(Some("Mais ações"));
+        imp.overflow_button.set_valign(gtk4::Align::Center);
+        imp.overflow_button.set_popover(Some(&popover));
+        imp.overflow_button.set_visible(false);
+
+        // Ações destrutivas à direita
+        let destructive_actions_box = GtkBox::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(SPACING_SMALL)
+            .halign(gtk4::Align::End)
+            .build();
+
+        destructive_actions_box.append(&imp.cancel_button);
+        destructive_actions_box.append(&imp.delete_button);
+
+        buttons_box.append(primary_actions_box);
+        buttons_box.append(&imp.overflow_button);
+        buttons_box.append(&destructive_actions_box);
+
+        self.append(&title_box);
+        self.append(&imp.progress_bar);
+        self.append(info_box);
+        self.append(&buttons_box);
+
+        let drag_source = gtk4::DragSource::builder().actions(gdk::DragAction::COPY).build();
+        drag_source.connect_prepare(glib::clone!(
+            #[weak(rename_to = row)]
+            self,
+            #[upgrade_or]
+            None,
+            move |_, _, _| row.file_content()
+        ));
+        self.add_controller(drag_source);
+    }
+
+    // O arquivo de um download concluído (para arrastar ou copiar). None enquanto
+    // baixa, se o antivírus o marcou ou se ele não está mais no disco.
+    pub fn file_content(&self) -> Option<gdk::ContentProvider> {
+        let object = self.object()?;
+        if object.download_state() != DownloadState::Done || object.active() || object.infected() {
+            return None;
+        }
+        let path = PathBuf::from(object.file_path());
+        if object.file_path().is_empty() || !path.exists() {
+            return None;
+        }
+        Some(gdk::ContentProvider::for_value(&gio::File::for_path(path).to_value()))
+    }
+
+    // Liga o card a um objeto (desfaz ligações anteriores)
+    pub fn bind(&self, object: &DownloadObject) {
+        self.unbind();
+        let imp = self.imp();
+        let mut bindings = imp.bindings.borrow_mut();
+
+        bindings.push(
+            object
+                .bind_property("filename", &imp.title_label, "label")
+                .transform_to(|_, text: String| Some(markup_title(&text)))
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            object
+                .bind_property("progress", &imp.progress_bar, "fraction")
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            object
+                .bind_property("progress", &imp.progress_bar, "text")
+                .transform_to(|_, progress: f64| Some(format!("{:.0}%", progress * 100.0)))
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            object
+                .bind_property("status-text", &imp.status_label, "label")
+                .transform_to(|_, text: String| Some(markup_status(&text)))
+                .sync_create()
+                .build(),
+        );
+        for (property, label) in [("size-text", &imp.size_label), ("speed-text", &imp.speed_label)] {
+            bindings.push(
+                object
+                    .bind_property(property, label, "label")
+                    .transform_to(|_, text: String| Some(markup_metadata_primary(&text)))
+                    .sync_create()
+                    .build(),
+            );
+        }
+        for (property, label) in [("eta-text", &imp.eta_label), ("date-text", &imp.date_label)] {
+            bindings.push(
+                object
+                    .bind_property(property, label, "label")
+                    .transform_to(|_, text: String| Some(markup_metadata_secondary(&text)))
+                    .sync_create()
+                    .build(),
+            );
+        }
+        bindings.push(
+            object
+                .bind_property("parallel", &imp.parallel_tag, "visible")
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            object
+                .bind_property("connections", &imp.parallel_label, "label")
+                .transform_to(|_, connections: u32| Some(connections_text(connections)))
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            object
+                .bind_property("resuming", &imp.resume_tag, "visible")
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            object
+                .bind_property("next-run-text", &imp.schedule_label, "label")
+                .sync_create()
+                .build(),
+        );
+        bindings.push(
+            object
+                .bind_property("next-run-text", &imp.schedule_tag, "visible")
+                .transform_to(|_, text: String| Some(!text.is_empty()))
+                .sync_create()
+                .build(),
+        );
+        drop(bindings);
+
+        // Estado e atividade afetam classes CSS, ícones e botões ao mesmo tempo
+        let mut handlers = imp.handlers.borrow_mut();
+        for property in ["state", "active", "sync", "parallel", "truncated", "infected", "indeterminate"] {
+            handlers.push(object.connect_notify_local(
+                Some(property),
+                glib::clone!(#[weak(rename_to = row)] self, move |_, _| row.sync_state()),
+            ));
+        }
+        drop(handlers);
+
+        imp.object.replace(Some(object.clone()));
+        self.sync_state();
+    }
+
+    pub fn unbind(&self) {
+        let imp = self.imp();
+        self.set_pulsing(false);
+        for binding in imp.bindings.borrow_mut().drain(..) {
+            binding.unbind();
+        }
+        if let Some(object) = imp.object.take() {
+            for handler in imp.handlers.borrow_mut().drain(..) {
+                object.disconnect(handler);
+            }
+        }
+    }
+
+    pub fn object(&self) -> Option<DownloadObject> {
+        self.imp().object.borrow().clone()
+    }
+
+    // Modo compacto: metadados empilhados e ações secundárias no menu "⋯"
+    pub fn set_compact(&self, compact: bool) {
+        let imp = self.imp();
+        if imp.compact.replace(compact) == compact {
+            return;
+        }
+
+        if compact {
+            imp.info_box.set_orientation(Orientation::Vertical);
+            imp.info_box.set_spacing(SPACING_TINY);
+            imp.metadata_box.set_halign(gtk4::Align::Start);
+        } else {
+            imp.info_box.set_orientation(Orientation::Horizontal);
+            imp.info_box.set_spacing(SPACING_MEDIUM);
+            imp.metadata_box.set_halign(gtk4::Align::End);
+        }
+
+        // Recoloca as ações na ordem original, separando as do menu
+        let overflow = self.overflow_buttons();
+        for button in self.primary_buttons() {
+            if let Some(parent) = button.parent().and_downcast::<GtkBox>() {
+                parent.remove(button);
+            }
+            let in_menu = compact && overflow.contains(&button);
+            set_button_label_visible(button, in_menu);
+            if in_menu {
+                imp.overflow_box.append(button);
+            } else {
+                imp.primary_actions_box.append(button);
+            }
+        }
+        self.sync_state();
+    }
+
+    // Lista densa: menos espaçamento, barra mais baixa e sem a linha de metadados
+    pub fn set_dense(&self, dense: bool) {
+        let imp = self.imp();
+        if imp.dense.replace(dense) == dense {
+            return;
+        }
+
+        let margin = if dense { SPACING_SMALL } else { SPACING_MEDIUM };
+        self.set_margin_top(margin);
+        self.set_margin_bottom(margin);
+        self.set_spacing(if dense { SPACING_SMALL } else { SPACING_MEDIUM });
+        imp.metadata_box.set_visible(!dense);
+        if dense {
+            self.add_css_class("dense");
+        } else {
+            self.remove_css_class("dense");
+        }
+    }
+
+    fn primary_buttons(&self) -> [&Button; 14] {
+        let imp = self.imp();
+        [
+            &imp.resume_button,
+            &imp.restart_button,
+            &imp.open_button,
+            &imp.open_folder_button,
+            &imp.sync_button,
+            &imp.verify_button,
+            &imp.repair_button,
+            &imp.preview_button,
+            &imp.pause_button,
+            &imp.pause_timer_button,
+            &imp.info_button,
+            &imp.copy_command_button,
+            &imp.copy_file_button,
+            &imp.share_button,
+        ]
+    }
+
+    // Ações que vão para o menu no modo compacto (nenhuma muda de ícone com o estado)
+    fn overflow_buttons(&self) -> [&Button; 10] {
+        let imp = self.imp();
+        [
+            &imp.open_folder_button,
+            &imp.sync_button,
+            &imp.verify_button,
+            &imp.repair_button,
+            &imp.preview_button,
+            &imp.pause_timer_button,
+            &imp.info_button,
+            &imp.copy_command_button,
+            &imp.copy_file_button,
+            &imp.share_button,
+        ]
+    }
+
+    fn sync_state(&self) {
+        let Some(object) = self.object() else {
+            return;
+        };
+        let imp = self.imp();
+        let state = object.download_state();
+        let active = object.active();
+        let unfinished = object.is_unfinished();
+
+        for other in DownloadState::ALL {
+            imp.status_badge.remove_css_class(other.css_class());
+            imp.progress_bar.remove_css_class(other.css_class());
+        }
+        imp.status_badge.add_css_class(state.css_class());
+        imp.progress_bar.add_css_class(state.css_class());
+
+        // Cancelado fica opaco, mas com título normal (melhor legibilidade)
+        if state == DownloadState::Cancelled {
+            self.add_css_class("cancelled-download");
+        } else {
+            self.remove_css_class("cancelled-download");
+        }
+
+        imp.status_icon.set_icon_name(Some(status_icon_name(state)));
+
+        if state == DownloadState::Paused {
+            imp.pause_button.set_icon_name("media-playback-start-symbolic");
+            imp.pause_button.set_tooltip_text(Some("Retomar"));
+        } else {
+            imp.pause_button.set_icon_name("media-playback-pause-symbolic");
+            imp.pause_button.set_tooltip_text(Some("Pausar"));
+        }
+
+        let completed = state == DownloadState::Done;
+        // Infectado: dá para ver a pasta, mas não abrir o arquivo pelo card
+        imp.open_button.set_visible(completed && !object.infected());
+        imp.open_folder_button.set_visible(completed);
+        imp.copy_file_button.set_visible(completed && !active && !object.infected());
+        imp.share_button.set_visible(completed && !active && !object.infected());
+        imp.restart_button.set_visible(state == DownloadState::Cancelled);
+        imp.sync_button.set_visible(completed && !active && object.sync());
+        imp.verify_button.set_visible(completed && !active);
+        imp.repair_button.set_visible(completed && !active && object.truncated());
+        imp.preview_button.set_visible(active && object.can_preview());
+        imp.pause_button.set_visible(active && unfinished);
+        imp.pause_timer_button.set_visible(active && unfinished);
+        imp.cancel_button.set_visible(active && unfinished);
+        // Falhas também podem ser retomadas (o .part e o mapa de chunks são mantidos)
+        imp.resume_button.set_visible(!active && (unfinished || state == DownloadState::Failed));
+        imp.delete_button.set_visible(!(active && unfinished));
+
+        let has_overflow = self.overflow_buttons().iter().any(|button| button.is_visible());
+        imp.overflow_button.set_visible(imp.compact.get() && has_overflow);
+
+        // Sem tamanho não há porcentagem: a barra pulsa enquanto chegam dados
+        imp.progress_bar.set_show_text(!object.indeterminate());
+        self.set_pulsing(object.indeterminate() && state == DownloadState::Downloading);
+    }
+
+    fn set_pulsing(&self, pulsing: bool) {
+        let imp = self.imp();
+        if pulsing == imp.pulse_source.borrow().is_some() {
+            return;
+        }
+        if pulsing {
+            let source = glib::timeout_add_local(
+                std::time::Duration::from_millis(PULSE_INTERVAL_MS),
+                glib::clone!(#[weak(rename_to = bar)] imp.progress_bar, #[upgrade_or] glib::ControlFlow::Break, move || {
+                    bar.pulse();
+                    glib::ControlFlow::Continue
+                }),
+            );
+            imp.pulse_source.replace(Some(source));
+        } else if let Some(source) = imp.pulse_source.take() {
+            source.remove();
+            // Volta a mostrar a fração do objeto (0, ou 1 ao concluir)
+            if let Some(object) = self.object() {
+                imp.progress_bar.set_fraction(object.progress());
+            }
+        }
+    }
+
+    pub fn open_button(&self) -> Button {
+        self.imp().open_button.clone()
+    }
+
+    pub fn open_folder_button(&self) -> Button {
+        self.imp().open_folder_button.clone()
+    }
+
+    pub fn resume_button(&self) -> Button {
+        self.imp().resume_button.clone()
+    }
+
+    pub fn restart_button(&self) -> Button {
+        self.imp().restart_button.clone()
+    }
+
+    pub fn sync_button(&self) -> Button {
+        self.imp().sync_button.clone()
+    }
+
+    pub fn verify_button(&self) -> Button {
+        self.imp().verify_button.clone()
+    }
+
+    pub fn repair_button(&self) -> Button {
+        self.imp().repair_button.clone()
+    }
+
+    pub fn preview_button(&self) -> Button {
+        self.imp().preview_button.clone()
+    }
+
+    pub fn pause_button(&self) -> Button {
+        self.imp().pause_button.clone()
+    }
+
+    pub fn pause_timer_button(&self) -> Button {
+        self.imp().pause_timer_button.clone()
+    }
+
+    pub fn info_button(&self) -> Button {
+        self.imp().info_button.clone()
+    }
+
+    pub fn copy_command_button(&self) -> Button {
+        self.imp().copy_command_button.clone()
+    }
+
+    pub fn copy_file_button(&self) -> Button {
+        self.imp().copy_file_button.clone()
+    }
+
+    pub fn share_button(&self) -> Button {
+        self.imp().share_button.clone()
+    }
+
+    pub fn cancel_button(&self) -> Button {
+        self.imp().cancel_button.clone()
+    }
+
+    pub fn delete_button(&self) -> Button {
+        self.imp().delete_button.clone()
+    }
+}
+
+fn setup_tag(tag: &GtkBox, icon_name: &str, text: &str, tooltip: &str) {
+    setup_tag_with_label(tag, icon_name, &Label::new(Some(text)), tooltip);
+}
+
+// Tag cujo texto muda (o label fica acessível para bindings)
+fn setup_tag_with_label(tag: &GtkBox, icon_name: &str, label: &Label, tooltip: &str) {
+    tag.set_orientation(Orientation::Horizontal);
+    tag.set_spacing(SPACING_TINY);
+    tag.set_halign(gtk4::Align::Start);
+    tag.set_visible(false);
+    tag.set_tooltip_text(Some(tooltip));
+
+    let icon = gtk4::Image::builder()
+        .icon_name(icon_name)
+        .pixel_size(12)
+        .build();
+
+    label.add_css_class("caption");
+    label.add_css_class("dim-label");
+
+    tag.append(&icon);
+    tag.append(label);
+}
+
+fn setup_button(button: &Button, icon_name: &str, tooltip: &str, css_class: Option<&str>) {
+    button.set_icon_name(icon_name);
+    button.set_tooltip_text(Some(tooltip));
+    if let Some(class) = css_class {
+        button.add_css_class(class);
+    }
+}
+
+// No menu o botão mostra o texto da dica ao lado do ícone
+fn set_button_label_visible(button: &Button, visible: bool) {
+    let content = button.child().and_downcast::<libadwaita::ButtonContent>();
+    match (visible, content) {
+        (true, None) => {
+            let content = libadwaita::ButtonContent::builder()
+                .icon_name(button.icon_name().unwrap_or_default())
+                .label(button.tooltip_text().unwrap_or_default())
+                .halign(gtk4::Align::Start)
+                .build();
+            button.set_child(Some(&content));
+            button.add_css_class("flat");
+        }
+        (false, Some(content)) => {
+            button.set_icon_name(&content.icon_name());
+            button.remove_css_class("flat");
+        }
+        _ => {}
+    }
+}
+
+// Número ao vivo de conexões; antes da primeira medição, só o modo
+fn connections_text(connections: u32) -> String {
+    match connections {
+        0 => "Chunks Paralelos".to_string(),
+        1 => "1 conexão".to_string(),
+        n => format!("{} conexões", n),
+    }
+}
+
+fn status_icon_name(state: DownloadState) -> &'static str {
+    match state {
+        DownloadState::Queued => "view-list-symbolic",
+        DownloadState::Connecting => "network-transmit-receive-symbolic",
+        DownloadState::Downloading => "folder-download-symbolic",
+        DownloadState::Paused => "media-playback-pause-symbolic",
+        DownloadState::Stalled => "network-idle-symbolic",
+        DownloadState::Retrying => "view-refresh-symbolic",
+        DownloadState::Verifying => "security-high-symbolic",
+        DownloadState::Completing => "document-save-symbolic",
+        DownloadState::Done => "emblem-ok-symbolic",
+        DownloadState::Cancelled => "process-stop-symbolic",
+        DownloadState::Failed => "dialog-error-symbolic",
+    }
+}
+
+// Funções auxiliares para markup Pango padronizado
+fn markup_title(text: &str) -> String {
+    format!(
+        "<span weight='bold' size='large'>{}</span>",
+        glib::markup_escape_text(text)
+    )
+}
+
+fn markup_status(text: &str) -> String {
+    format!(
+        "<span weight='600'>{}</span>",
+        glib::markup_escape_text(text)
+    )
+}
+
+fn markup_metadata_primary(text: &str) -> String {
+    format!(
+        "<span weight='600'>{}</span>",
+        glib::markup_escape_text(text)
+    )
+}
+
+fn markup_metadata_secondary(text: &str) -> String {
+    format!(
+        "<span size='small' weight='normal'>{}</span>",
+        glib::markup_escape_text(text)
+    )
+}
@@ -0,0 +1,3371 @@
+// Motor de downloads: estratégia sequencial/paralela, resume e progresso
+use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use futures_util::StreamExt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use tokio::sync::Mutex as AsyncMutex;
+use crate::http::{
+    content_disposition_filename, busy_delay, retry_request_notify, retry_request_until, send_request, sleep_unless, MAX_BUSY_WAITS, MAX_RETRY_AFTER_SECS, HttpError, HttpErrorKind, HttpRequest, ClientOptions,
+    ReqwestBackend, tor_proxy_url, ClientPool, HeadersBackend, HttpResponse, SharedBackend,
+};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use crate::storage::{
+    find_network_profile, get_download_directory, get_flush_interval, get_max_connections, NetworkProfile, record_directory, save_downloads, AppConfig, DownloadOptions, DownloadRecord, FileIntegrity,
+    push_event, DownloadEvent, ProvenanceSettings, ResponseDetails, Validators, version_naming, finished_directory, ScanResult, VersionNaming,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Local, Utc};
+use crate::aria2;
+use crate::checksum;
+use crate::decoding;
+use crate::events::{self, EventBus};
+use crate::failure::{Failure, FailureKind};
+use crate::ftp::{self, FtpBackend};
+use crate::provenance;
+use crate::resolve::{self, SharedResolver};
+use crate::ipfs::{self, IpfsBackend};
+use crate::listing::{self, IndexEntry, PageLink};
+use crate::mirrors::{self, MirrorPool};
+use crate::platform;
+use crate::s3::{S3Backend, S3Config};
+use crate::scan;
+use crate::schedule::version_path;
+use crate::scripting::{self, SharedHooks};
+use crate::slots::DownloadSlots;
+use crate::throttle::SpeedLimiter;
+use crate::usage::DataUsage;
+use crate::watchdog::{TransferWatch, WATCH_INTERVAL_SECS};
+use crate::zsync::{self, ControlFile};
+
+pub const INITIAL_CONNECTIONS: usize = 2; // Conexões abertas no início de um download paralelo
+pub const DEFAULT_MAX_CONNECTIONS: usize = 8; // Limite padrão de conexões por download
+pub const MAX_CONNECTIONS_LIMIT: usize = 16; // Maior valor aceito na configuração
+pub const MIN_CHUNK_SIZE: u64 = 1024 * 1024; // 1MB - tamanho mínimo por chunk
+pub const MAX_SIZE_CORRECTIONS: u32 = 2; // Replanejamentos por tamanho errado antes de desistir
+pub const FORCED_MIN_CHUNK_SIZE: u64 = 128 * 1024; // Chunk mínimo em servidores que limitam cada conexão
+pub const SEQUENTIAL_PIECE_SIZE: u64 = 4 * MIN_CHUNK_SIZE; // Pedaços baixados em ordem na prioridade sequencial
+pub const MIN_SPEEDUP: f64 = 0.1; // Ganho mínimo (10%) para manter uma conexão extra
+pub const TUNE_INTERVAL_MS: u64 = 2000; // Intervalo entre medições de velocidade
+pub const LINK_CHECK_CONCURRENCY: usize = 8; // Links consultados ao mesmo tempo na verificação
+pub const MAX_REDIRECTOR_PAGES: usize = 3; // Páginas intermediárias seguidas até o arquivo real
+pub const MAX_RETRIES: u32 = 3; // Número máximo de tentativas em caso de erro de conexão
+pub const RETRY_DELAY_SECS: u64 = 2; // Delay entre tentativas em segundos
+pub const WRITE_BUFFER_SIZE: usize = 1024 * 1024; // Buffer de escrita do download sequencial
+pub const DEFAULT_FLUSH_INTERVAL_SECS: u64 = 2; // Intervalo padrão para descarregar o buffer no disco
+pub const MAX_FLUSH_INTERVAL_SECS: u64 = 60;
+pub const SLOT_POLL_MS: u64 = 250; // Intervalo entre consultas à fila de downloads simultâneos
+pub const STALLED_AFTER_SECS: u64 = 10; // Sem receber dados por este tempo, o download aparece como parado
+pub const CHUNK_BUFFER_SIZE: usize = 256 * 1024; // Buffer de cada conexão de chunk (junta os pedaços pequenos da rede)
+
+#[derive(Clone, Debug)]
+pub enum DownloadMessage {
+    Progress(ProgressUpdate),
+    Complete,
+    Error(Failure), // Categoria decidida onde o erro aconteceu (ver `failure`)
+    Status(String), // Aviso temporário no badge (ex.: aguardando limite de requisições)
+    NotModified,    // Sincronização: o arquivo local já é a versão mais recente
+    Renamed(String), // Nome definido pelo servidor (links de compartilhamento)
+    Waiting(WaitReason), // Ainda não está transferindo; o card mostra o motivo
+    State(DownloadState), // O download mudou de estado (badge, ícone e cor do card)
+    Cancelled,
+}
+
+// Progresso de um download em números; quem mostra (card, log) formata
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate {
+    pub bytes: u64,            // Já baixados
+    pub total: u64,            // 0 = tamanho desconhecido
+    pub speed_bps: u64,        // Bytes por segundo desde a última atualização
+    pub eta: Option<Duration>, // None sem tamanho ou sem velocidade
+    pub connections: usize,    // Conexões de chunk abertas agora
+    pub parallel: bool,        // Chunks paralelos (false = um fluxo só)
+    pub state: DownloadState,
+}
+
+impl ProgressUpdate {
+    pub fn new(bytes: u64, total: u64, speed_bps: f64, connections: usize, parallel: bool, state: DownloadState) -> Self {
+        let speed_bps = speed_bps.max(0.0) as u64;
+        let eta = (total > bytes && speed_bps > 0).then(|| Duration::from_secs_f64((total - bytes) as f64 / speed_bps as f64));
+        ProgressUpdate { bytes, total, speed_bps, eta, connections, parallel, state }
+    }
+
+    // Fração concluída (0 com tamanho desconhecido)
+    pub fn fraction(&self) -> f64 {
+        if self.total > 0 {
+            self.bytes as f64 / self.total as f64
+        } else {
+            0.0
+        }
+    }
+
+    // "12.00 MB/100.00 MB"; só "12.00 MB" com tamanho desconhecido
+    pub fn status_text(&self) -> String {
+        if self.total > 0 {
+            format!("{}/{}", format_bytes(self.bytes), format_bytes(self.total))
+        } else {
+            format_bytes(self.bytes)
+        }
+    }
+
+    // Vazio antes da primeira medição
+    pub fn speed_text(&self) -> String {
+        if self.speed_bps > 0 {
+            format_speed(self.speed_bps as f64)
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn eta_text(&self) -> String {
+        self.eta.map(|eta| format_eta(eta.as_secs_f64())).unwrap_or_default()
+    }
+}
+
+// Estado de um download. O motor avança entre os estados de trabalho; pausar e
+// cancelar vêm da interface. Cada estado tem a sua classe CSS no card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DownloadState {
+    Queued, // Esperando a vez (limite de downloads simultâneos)
+    Connecting, // Consulta inicial ao servidor
+    Downloading,
+    Paused,
+    Stalled,    // Conectado, mas sem receber dados há STALLED_AFTER_SECS
+    Retrying,   // Esperando para tentar de novo (rede, limite de requisições)
+    Verifying,  // Conferindo SHA-256 ou comparando com a cópia local
+    Completing, // Renomeando o .part, gravando metadados, rodando scripts
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl DownloadState {
+    pub const ALL: [DownloadState; 11] = [
+        DownloadState::Queued,
+        DownloadState::Connecting,
+        DownloadState::Downloading,
+        DownloadState::Paused,
+        DownloadState::Stalled,
+        DownloadState::Retrying,
+        DownloadState::Verifying,
+        DownloadState::Completing,
+        DownloadState::Done,
+        DownloadState::Failed,
+        DownloadState::Cancelled,
+    ];
+
+    pub fn css_class(self) -> &'static str {
+        match self {
+            DownloadState::Queued => "queued",
+            DownloadState::Connecting => "connecting",
+            DownloadState::Downloading => "downloading",
+            DownloadState::Paused => "paused",
+            DownloadState::Stalled => "stalled",
+            DownloadState::Retrying => "retrying",
+            DownloadState::Verifying => "verifying",
+            DownloadState::Completing => "completing",
+            DownloadState::Done => "done",
+            DownloadState::Failed => "failed",
+            DownloadState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_css_class(class: &str) -> Option<DownloadState> {
+        DownloadState::ALL.into_iter().find(|state| state.css_class() == class)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DownloadState::Queued => "Na fila",
+            DownloadState::Connecting => "Conectando",
+            DownloadState::Downloading => "Baixando",
+            DownloadState::Paused => "Pausado",
+            DownloadState::Stalled => "Sem resposta do servidor",
+            DownloadState::Retrying => "Tentando novamente",
+            DownloadState::Verifying => "Verificando",
+            DownloadState::Completing => "Finalizando",
+            DownloadState::Done => "Concluído",
+            DownloadState::Failed => "Falhou",
+            DownloadState::Cancelled => "Cancelado",
+        }
+    }
+
+    // Concluído, com falha ou cancelado: o motor não sai mais daqui
+    pub fn is_final(self) -> bool {
+        matches!(self, DownloadState::Done | DownloadState::Failed | DownloadState::Cancelled)
+    }
+
+    // Nem pausado nem terminado (a fila e as novas tentativas contam)
+    pub fn is_running(self) -> bool {
+        !self.is_final() && self != DownloadState::Paused
+    }
+}
+
+// Por que um download está parado esperando (em vez de um status genérico)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitReason {
+    Queued { position: usize }, // Limite de downloads simultâneos; 1 = o próximo
+    Scheduled(DateTime<Utc>),   // Pausa com prazo: continua sozinho neste horário
+    Network,                    // Falha de conexão, nova tentativa em seguida
+    ServerBusy(DateTime<Utc>),  // 503 (manutenção): nova tentativa neste horário
+}
+
+impl WaitReason {
+    pub fn text(&self) -> String {
+        match self {
+            WaitReason::Queued { position } => format!("Na fila — posição {}", position),
+            WaitReason::Scheduled(at) => {
+                let at = at.with_timezone(&Local);
+                if at.date_naive() == Local::now().date_naive() {
+                    format!("Aguardando {}", at.format("%H:%M"))
+                } else {
+                    format!("Aguardando {}", at.format("%d/%m %H:%M"))
+                }
+            }
+            WaitReason::Network => "Aguardando rede".to_string(),
+            WaitReason::ServerBusy(_) => "Aguardando (servidor ocupado)".to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DownloadTask {
+    pub id: Uuid, // Mesmo id do DownloadRecord correspondente
+    pub state: DownloadState, // Mude com pause/resume/cancel (interface) ou set_state (motor)
+    pub resumed_state: DownloadState, // Estado de trabalho para onde a pausa volta
+    pub last_data: Option<Instant>, // Último pedaço recebido (detecta o download parado)
+    pub resume_at: Option<DateTime<Utc>>, // Pausa com prazo: o download continua sozinho neste horário
+    pub integrity: Option<FileIntegrity>, // Arquivo final menor que o esperado (ver finish_download)
+    pub file_path: Option<PathBuf>,
+    pub finished_dir: Option<PathBuf>, // Pasta para onde o arquivo vai ao concluir (None = fica na de downloads)
+    pub max_connections: usize, // Lido a cada medição, pode mudar durante o download
+    pub connections: usize, // Conexões de chunk abertas agora (0 fora do modo paralelo)
+    pub conditional: Option<Validators>, // Baixa só se o arquivo remoto mudou desde esta versão
+    pub validators: Validators, // ETag/Last-Modified recebidos (para a próxima sincronização)
+    pub response: Option<ResponseDetails>, // Protocolo e headers da consulta inicial
+    pub events: Vec<DownloadEvent>, // Linha do tempo, copiada para o registro ao salvar
+    pub seed_file: Option<PathBuf>, // Versão local anterior usada na transferência delta
+    pub version_naming: Option<VersionNaming>, // Recorrente/sincronizado: nome de cada versão ao concluir
+    pub verify_checksum: bool, // Procura SHA256SUMS/.sha256 no servidor ao concluir
+    pub keep_encoding: bool, // Grava o corpo compactado como veio, sem descompactar
+    pub decode_body: Option<String>, // Content-Encoding desfeito ao concluir (o .part guarda o corpo como veio)
+    pub data_saver: bool, // Economia de dados: uma conexão, sem chunks paralelos nem sondagem de espelhos
+    pub checksum: Option<String>, // SHA-256 conferido com o publicado pelo servidor
+    pub scanner: Vec<String>, // Antivírus rodado no arquivo concluído (vazio = nenhum)
+    pub scan: Option<ScanResult>, // Resultado do antivírus
+    pub provenance: ProvenanceSettings, // Metadados gravados no arquivo ao concluir
+    pub sequential_priority: bool, // Chunks em ordem, para a mídia poder ser aberta antes de terminar
+    pub temp_path: Option<PathBuf>, // Arquivo .part em andamento (pré-visualização)
+    pub shutdown: bool,  // App fechando: para sem apagar o .part (diferente de cancelar)
+    pub abort_reason: Option<String>, // Parado pela política de tempo/velocidade: termina como falha
+    pub downloaded: u64, // Bytes já gravados no disco (a cada descarga, pausa e no encerramento)
+    pub finished: bool,  // A thread do download terminou
+    pub referer: Option<String>, // Página de origem do link (gravada junto com a URL no arquivo)
+    pub resolvers: Vec<SharedResolver>, // Transformam links de serviços de arquivos em endereços diretos
+    pub hooks: SharedHooks, // Scripts do usuário (antes da requisição e ao concluir)
+    pub usage: Option<Arc<Mutex<DataUsage>>>, // Consumo mensal (cota), compartilhado entre downloads
+    pub speed_limiter: Option<Arc<SpeedLimiter>>, // Limite de velocidade global
+    pub mirrors: Vec<String>, // Outros endereços do mesmo arquivo (chunks em paralelo)
+    pub initial_connections: Option<usize>, // Conexões iniciais sugeridas pelo histórico do servidor
+    pub force_segmented: bool, // Servidor limita cada conexão: chunks mesmo abaixo de MIN_CHUNK_SIZE
+    pub supports_range: Option<bool>, // Resposta da consulta inicial (para o histórico do servidor)
+    pub peak_connections: usize, // Maior número de conexões de chunk abertas ao mesmo tempo
+    pub flush_interval: Duration, // Buffer de escrita vai para o disco (com fsync) neste intervalo
+    pub clients: Option<Arc<ClientPool>>, // Client compartilhado entre downloads (None = client próprio)
+    pub extra_headers: Vec<(String, String)>, // Headers do perfil de rede, em todas as requisições
+    pub download_limiter: Option<Arc<SpeedLimiter>>, // Limite só deste download (perfil de rede ou modelo)
+    pub slots: Option<Arc<DownloadSlots>>, // Limite de downloads simultâneos (None = começa na hora)
+    pub bus: Option<EventBus>, // Onde o motor publica os eventos do download (None = ninguém acompanha)
+}
+
+impl DownloadTask {
+    pub fn new(id: Uuid) -> Self {
+        DownloadTask {
+            id,
+            state: DownloadState::Connecting,
+            resumed_state: DownloadState::Connecting,
+            last_data: None,
+            resume_at: None,
+            integrity: None,
+            file_path: None,
+            finished_dir: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            connections: 0,
+            conditional: None,
+            validators: Validators::default(),
+            response: None,
+            events: Vec::new(),
+            seed_file: None,
+            version_naming: None,
+            verify_checksum: false,
+            keep_encoding: false,
+            decode_body: None,
+            data_saver: false,
+            checksum: None,
+            scanner: Vec::new(),
+            scan: None,
+            provenance: ProvenanceSettings::default(),
+            sequential_priority: false,
+            temp_path: None,
+            shutdown: false,
+            abort_reason: None,
+            downloaded: 0,
+            finished: false,
+            referer: None,
+            resolvers: resolve::builtin_resolvers(),
+            hooks: SharedHooks::default(),
+            usage: None,
+            speed_limiter: None,
+            mirrors: Vec::new(),
+            initial_connections: None,
+            force_segmented: false,
+            supports_range: None,
+            peak_connections: 0,
+            flush_interval: Duration::from_secs(DEFAULT_FLUSH_INTERVAL_SECS),
+            clients: None,
+            extra_headers: Vec::new(),
+            download_limiter: None,
+            slots: None,
+            bus: None,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state == DownloadState::Paused
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state == DownloadState::Cancelled
+    }
+
+    // Pausa a partir de qualquer estado de trabalho; false se já terminou
+    pub fn pause(&mut self) -> bool {
+        if self.state.is_final() {
+            return false;
+        }
+        if self.state != DownloadState::Paused {
+            self.resumed_state = self.state;
+            self.state = DownloadState::Paused;
+        }
+        true
+    }
+
+    // Volta ao estado de antes da pausa
+    pub fn resume(&mut self) {
+        if self.state == DownloadState::Paused {
+            self.state = self.resumed_state;
+        }
+        self.resume_at = None;
+        self.last_data = None; // O tempo pausado não conta como download parado
+    }
+
+    pub fn cancel(&mut self) {
+        if !self.state.is_final() {
+            self.state = DownloadState::Cancelled;
+        }
+    }
+
+    // Para como no fechamento do app (mantém .part e mapa de chunks); ao sair
+    // do motor o download falha com o motivo (ver `watchdog`)
+    pub fn abort(&mut self, reason: impl Into<String>) {
+        let reason = reason.into();
+        self.log(reason.clone());
+        self.abort_reason = Some(reason);
+        self.shutdown = true;
+    }
+
+    // Transição feita pelo motor. Um estado final não muda mais, e durante a
+    // pausa o novo estado fica guardado para a retomada. Retorna true se o
+    // estado visível mudou.
+    pub fn set_state(&mut self, state: DownloadState) -> bool {
+        if self.state.is_final() {
+            return false;
+        }
+        if self.state == DownloadState::Paused && !state.is_final() {
+            self.resumed_state = state;
+            return false;
+        }
+        let changed = self.state != state;
+        self.state = state;
+        changed
+    }
+
+    // Pausado agora? Um prazo de pausa vencido retoma o download
+    pub fn poll_paused(&mut self) -> bool {
+        if self.is_paused() && self.resume_at.is_some_and(|at| Utc::now() >= at) {
+            self.resume();
+            self.log("Retomado automaticamente");
+        }
+        self.is_paused()
+    }
+
+    pub fn log(&mut self, text: impl Into<String>) {
+        push_event(&mut self.events, text);
+    }
+}
+
+pub fn start_download(
+    url: &str,
+    filename: &str,
+    tx: async_channel::Sender<DownloadMessage>,
+    download_task: Arc<Mutex<DownloadTask>>,
+    state_records: Arc<Mutex<Vec<DownloadRecord>>>,
+    config: Arc<Mutex<AppConfig>>,
+) {
+    let url = url.to_string();
+    let filename = filename.to_string();
+
+    std::thread::spawn(move || {
+        // O download roda nesta thread, mas as conexões do client compartilhado
+        // ficam no runtime comum e sobrevivem ao fim do download
+        download_runtime().block_on(async {
+            // Diretório de download usando configuração
+            let mut client_options = ClientOptions::default();
+            let mut s3_config = S3Config::default();
+            let mut ipfs_gateways = Vec::new();
+            let mut network_profiles = Vec::new();
+            let mut configured_naming = None;
+            let mut configured_finished = None;
+            let mut watch = None;
+            let mut download_dir = if let Ok(config_guard) = config.lock() {
+                if let Ok(mut task) = download_task.lock() {
+                    task.max_connections = get_max_connections(&config_guard);
+                    task.flush_interval = Duration::from_secs(get_flush_interval(&config_guard));
+                    task.provenance = config_guard.provenance;
+                    task.keep_encoding = config_guard.keep_content_encoding;
+                    task.data_saver = config_guard.data_saver;
+                    task.resolvers = resolve::installed_resolvers();
+                    task.hooks = Arc::new(scripting::installed_hooks());
+                    task.scanner = scan::scanner_command(config_guard.scanner_command.as_deref().unwrap_or_default());
+                }
+                client_options = config_client_options(&config_guard);
+                network_profiles = config_guard.network_profiles.clone();
+                s3_config = S3Config::from_settings(&config_guard.s3);
+                ipfs_gateways = config_guard.ipfs.gateways.clone();
+                configured_naming = config_guard.version_naming;
+                configured_finished = config_guard.finished_directory.clone();
+                get_download_directory(&config_guard)
+            } else {
+                dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
+            };
+
+            // PAC configurado: baixa (ou renova) antes de montar o client
+            let pac_url = config.lock().map(|config| config.proxy.pac_url.trim().to_string()).unwrap_or_default();
+            let mut pac_error = None;
+            if !pac_url.is_empty() {
+                match crate::pac::load(&pac_url).await {
+                    Ok(pac) => client_options.pac = Some(pac),
+                    Err(e) => pac_error = Some(e),
+                }
+            }
+
+            // Opções escolhidas para este download
+            let shared_options = client_options.clone();
+            let id = download_task.lock().map(|task| task.id).ok();
+            if let Ok(records) = state_records.lock() {
+                if let Some(record) = records.iter().find(|r| Some(r.id) == id) {
+                    let profile = record.options.network_profile.as_deref().map(|name| (name, find_network_profile(&network_profiles, name)));
+                    apply_record_options(record, profile.and_then(|(_, profile)| profile), &mut client_options);
+                    download_dir = record_directory(record, &download_dir);
+                    if let Ok(mut task) = download_task.lock() {
+                        if record.options.sync && !record.validators.is_empty() {
+                            task.conditional = Some(record.validators.clone());
+                        }
+                        // Recorrentes gravam em outro nome; a última versão serve de base para o delta
+                        task.seed_file = record.versions.last().map(PathBuf::from);
+                        task.version_naming = version_naming(&record.options, configured_naming);
+                        task.finished_dir = finished_directory(&record.options, configured_finished.as_deref());
+                        task.verify_checksum = record.options.verify_checksum;
+                        task.sequential_priority = record.options.sequential_priority;
+                        task.referer = record.options.referer.clone();
+                        task.mirrors = record.options.mirrors.clone();
+                        task.events = record.events.clone();
+                        // Privado: a URL também não fica gravada no arquivo
+                        if record.options.private {
+                            task.provenance.origin_xattrs = false;
+                        }
+                    }
+                    // Depois do histórico de eventos, que o perfil também registra
+                    if let Some((name, profile)) = profile {
+                        apply_network_profile(name, profile, &download_task);
+                    }
+                    apply_download_limits(&record.options, &download_task);
+                    watch = TransferWatch::for_options(&record.options, record.downloaded_bytes);
+                }
+            }
+            if let Some(e) = pac_error {
+                log_event(&download_task, format!("PAC indisponível ({}), usando o proxy manual", e));
+            }
+
+            // Client compartilhado, a não ser que o download tenha opções próprias (Tor, referer...)
+            let clients = download_task.lock().ok().and_then(|task| task.clients.clone());
+            let client = match clients {
+                Some(clients) if client_options == shared_options => clients.get(&client_options),
+                _ => ReqwestBackend::with_options(&client_options),
+            };
+            let proxied = client_options.proxy.is_some() || client_options.pac.is_some();
+            let backend = match client.map(|client| wrap_backend(Arc::new(client), s3_config, ipfs_gateways, proxied)) {
+                Ok(b) => b,
+                Err(e) => {
+                    fail(&tx, &download_task, Failure::other(format!("Erro ao criar client: {}", e))).await;
+                    return;
+                }
+            };
+
+            // Acima do limite de downloads simultâneos: espera a vez antes de conectar
+            let slots = download_task.lock().ok().and_then(|task| task.slots.clone());
+            if let (Some(slots), Some(id)) = (&slots, id) {
+                if !wait_for_slot(slots, id, &tx, &download_task).await {
+                    slots.release(id);
+                    return;
+                }
+            }
+
+            // Tempo máximo e velocidade mínima contam a partir daqui, já com a vaga
+            let watcher = watch.map(|watch| tokio::spawn(watch_transfer(download_task.clone(), watch)));
+            run_download(backend, &url, &filename, &download_dir, &tx, &download_task, &state_records).await;
+            if let Some(watcher) = watcher {
+                watcher.abort();
+            }
+            if let (Some(slots), Some(id)) = (&slots, id) {
+                slots.release(id);
+            }
+            let abort_reason = download_task.lock().ok().and_then(|mut task| task.abort_reason.take());
+            if let Some(reason) = abort_reason {
+                fail(&tx, &download_task, Failure::other(reason)).await;
+            }
+        });
+
+        if let Ok(mut task) = download_task.lock() {
+            task.finished = true;
+        }
+    });
+}
+
+// Confere a política do download a cada WATCH_INTERVAL_SECS; pausa e fila não
+// contam como tempo ativo
+async fn watch_transfer(download_task: Arc<Mutex<DownloadTask>>, mut watch: TransferWatch) {
+    let mut last = Instant::now();
+    loop {
+        tokio::time::sleep(Duration::from_secs(WATCH_INTERVAL_SECS)).await;
+        let elapsed = last.elapsed();
+        last = Instant::now();
+        let Ok(mut task) = download_task.lock() else {
+            return;
+        };
+        if task.state.is_final() || task.shutdown {
+            return;
+        }
+        if task.is_paused() || task.state == DownloadState::Queued {
+            continue;
+        }
+        if let Some(reason) = watch.tick(elapsed, task.downloaded) {
+            task.abort(reason);
+            return;
+        }
+    }
+}
+
+// Opções de conexão escolhidas para o download, por cima das da configuração:
+// certificados, referer, o proxy do perfil de rede e, vencendo todos, o Tor.
+// Valem também para o reparo, a verificação e a troca de endereço
+fn apply_record_options(record: &DownloadRecord, profile: Option<&NetworkProfile>, client_options: &mut ClientOptions) {
+    client_options.accept_invalid_certs = record.options.accept_invalid_certs;
+    client_options.referer = record.options.referer.clone();
+    if let Some(proxy) = profile.map(|profile| profile.proxy.trim()).filter(|proxy| !proxy.is_empty()) {
+        client_options.proxy = Some(proxy.to_string());
+        client_options.pac = None;
+    }
+    if record.options.use_tor {
+        // Um circuito por download (isolado pelo id)
+        client_options.proxy = Some(tor_proxy_url(&record.id.simple().to_string()));
+        client_options.pac = None;
+    }
+}
+
+// Perfil de rede do download: headers e credenciais vão para todas as requisições
+// e o limite vale só para ele (o proxy fica em `apply_record_options`)
+fn apply_network_profile(name: &str, profile: Option<&NetworkProfile>, download_task: &Arc<Mutex<DownloadTask>>) {
+    let Some(profile) = profile else {
+        log_event(download_task, format!("Perfil de rede \"{}\" não existe mais", name));
+        return;
+    };
+    if let Ok(mut task) = download_task.lock() {
+        task.extra_headers = profile.request_headers();
+        task.download_limiter = profile.speed_limit.map(|limit| Arc::new(SpeedLimiter::new(Some(limit))));
+        task.log(format!("Perfil de rede: {}", name));
+    }
+}
+
+// Publica no barramento de eventos, se o download tiver um
+fn publish(download_task: &Arc<Mutex<DownloadTask>>, event: impl FnOnce(Uuid) -> events::DownloadEvent) {
+    let bus = download_task.lock().ok().and_then(|task| task.bus.clone().map(|bus| (bus, task.id)));
+    if let Some((bus, id)) = bus {
+        bus.publish(event(id));
+    }
+}
+
+// Transição feita pelo motor, avisando o card quando o estado visível muda
+fn enter_state(download_task: &Arc<Mutex<DownloadTask>>, tx: &async_channel::Sender<DownloadMessage>, state: DownloadState) {
+    let changed = download_task.lock().map(|mut task| task.set_state(state)).unwrap_or(false);
+    if changed {
+        let _ = tx.try_send(DownloadMessage::State(state));
+        publish(download_task, |id| events::DownloadEvent::StateChanged(id, state));
+    }
+}
+
+// Fim com erro: estado Failed e o motivo para o card
+async fn fail(tx: &async_channel::Sender<DownloadMessage>, download_task: &Arc<Mutex<DownloadTask>>, failure: Failure) {
+    enter_state(download_task, tx, DownloadState::Failed);
+    publish(download_task, |id| events::DownloadEvent::Failed { id, error: failure.message.clone() });
+    let _ = tx.send(DownloadMessage::Error(failure)).await;
+}
+
+// Espera uma vaga, avisando o card a cada mudança de posição na fila.
+// Retorna false se o download foi cancelado ou o app está fechando.
+async fn wait_for_slot(
+    slots: &DownloadSlots,
+    id: Uuid,
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: &Arc<Mutex<DownloadTask>>,
+) -> bool {
+    let mut last_position = None;
+    while !slots.try_acquire(id) {
+        let stopped = download_task.lock().map(|task| task.is_cancelled() || task.shutdown).unwrap_or(true);
+        if stopped {
+            return false;
+        }
+        let position = slots.position(id);
+        if position != last_position {
+            if let Some(position) = position {
+                if last_position.is_none() {
+                    log_event(download_task, format!("Na fila (posição {})", position));
+                    enter_state(download_task, tx, DownloadState::Queued);
+                }
+                let _ = tx.send(DownloadMessage::Waiting(WaitReason::Queued { position })).await;
+            }
+            last_position = position;
+        }
+        tokio::time::sleep(Duration::from_millis(SLOT_POLL_MS)).await;
+    }
+    if last_position.is_some() {
+        log_event(download_task, "Saiu da fila");
+        enter_state(download_task, tx, DownloadState::Connecting);
+    }
+    true
+}
+
+// Conexões e limite do próprio download (vindos de um modelo). Com perfil de
+// rede e modelo limitando a velocidade, vale o menor dos dois.
+fn apply_download_limits(options: &DownloadOptions, download_task: &Arc<Mutex<DownloadTask>>) {
+    let Ok(mut task) = download_task.lock() else {
+        return;
+    };
+    if let Some(connections) = options.max_connections {
+        task.max_connections = connections.clamp(1, MAX_CONNECTIONS_LIMIT);
+    }
+    if let Some(limit) = options.speed_limit.filter(|limit| *limit > 0) {
+        let limit = match task.download_limiter.as_ref().and_then(|limiter| limiter.limit()) {
+            Some(profile_limit) => profile_limit.min(limit),
+            None => limit,
+        };
+        task.download_limiter = Some(Arc::new(SpeedLimiter::new(Some(limit))));
+    }
+    if let Some(pattern) = &options.template {
+        task.log(format!("Modelo: {}", pattern));
+    }
+}
+
+// Pede que os downloads parem (mantendo .part e mapa de chunks) e espera, até
+// `timeout`, que todos gravem o que já receberam. Retorna false se algum não parou a tempo.
+pub fn shutdown_downloads(tasks: &[Arc<Mutex<DownloadTask>>], timeout: Duration) -> bool {
+    for task in tasks {
+        if let Ok(mut task) = task.lock() {
+            task.shutdown = true;
+        }
+    }
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        let running = tasks
+            .iter()
+            .any(|task| task.lock().map(|task| !task.finished).unwrap_or(false));
+        if !running {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// Mostra no card o motivo da espera antes da próxima tentativa: limite de
+// requisições do servidor (com o tempo) ou falha de conexão. Com a tarefa, o
+// download também passa para o estado Retrying.
+fn retry_notice<'a>(
+    tx: &'a async_channel::Sender<DownloadMessage>,
+    download_task: Option<&'a Arc<Mutex<DownloadTask>>>,
+) -> impl Fn(&HttpError, Duration) + 'a {
+    move |error, delay| {
+        if let Some(download_task) = download_task {
+            enter_state(download_task, tx, DownloadState::Retrying);
+        }
+        match error.kind {
+            HttpErrorKind::RateLimited => {
+                let _ = tx.try_send(DownloadMessage::Status(format!(
+                    "Limite de requisições, tentando novamente em {}s",
+                    delay.as_secs()
+                )));
+            }
+            HttpErrorKind::ServerBusy => {
+                let until = Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+                let _ = tx.try_send(DownloadMessage::Waiting(WaitReason::ServerBusy(until)));
+            }
+            HttpErrorKind::Connect | HttpErrorKind::Timeout => {
+                let _ = tx.try_send(DownloadMessage::Waiting(WaitReason::Network));
+            }
+            _ => {}
+        }
+    }
+}
+
+// Pausado, cancelado ou com o app fechando: as esperas entre tentativas param
+fn task_stopped(download_task: &Arc<Mutex<DownloadTask>>) -> bool {
+    download_task
+        .lock()
+        .map(|mut task| task.is_cancelled() || task.shutdown || task.poll_paused())
+        .unwrap_or(false)
+}
+
+// `retry_request_notify` de um download: a espera entre tentativas para assim que
+// ele é pausado, cancelado ou o app fecha. Depois de uma pausa tenta de novo; se
+// foi cancelado (ou o app está fechando), devolve o erro `Interrupted`
+async fn retry_task_request<F, Fut, T>(
+    request_fn: F,
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: Option<&Arc<Mutex<DownloadTask>>>,
+) -> Result<T, HttpError>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, HttpError>>,
+{
+    let Some(task) = download_task else {
+        return retry_request_notify(request_fn, MAX_RETRIES, RETRY_DELAY_SECS, retry_notice(tx, None)).await;
+    };
+    loop {
+        let result = retry_request_until(&request_fn, MAX_RETRIES, RETRY_DELAY_SECS, retry_notice(tx, download_task), || {
+            task_stopped(task)
+        })
+        .await;
+        match result {
+            Err(e) if e.kind == HttpErrorKind::Interrupted => {
+                if wait_while_paused(task).await {
+                    return Err(e);
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+// Informações do arquivo remoto usadas para escolher a estratégia
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteInfo {
+    pub size: u64, // 0 quando desconhecido
+    pub supports_range: bool,
+    pub validators: Validators,
+    pub not_modified: bool, // 304 para uma consulta condicional
+    pub filename: Option<String>, // Nome sugerido pelo servidor (Content-Disposition)
+    pub content_type: Option<String>, // Tipo MIME, sem parâmetros (ex.: "video/mp4")
+    pub content_encoding: Option<String>, // Corpo compactado pelo servidor (ex.: "gzip")
+    pub response: ResponseDetails, // Protocolo e headers, mostrados nas informações do download
+}
+
+impl RemoteInfo {
+    fn from_response(url: &str, response: &HttpResponse, size: u64, supports_range: bool) -> Self {
+        let headers = response.headers();
+        RemoteInfo {
+            size,
+            supports_range,
+            validators: validators_from(headers),
+            not_modified: false,
+            filename: headers
+                .get(reqwest::header::CONTENT_DISPOSITION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(content_disposition_filename),
+            content_type: headers
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(';').next())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            content_encoding: decoding::content_encoding(headers),
+            response: response_details(url, response),
+        }
+    }
+
+    // Mesmo critério de `run_download` para baixar em chunks paralelos
+    pub fn uses_parallel_chunks(&self) -> bool {
+        self.supports_range && self.size >= MIN_CHUNK_SIZE
+    }
+
+    fn not_modified(url: &str, response: &HttpResponse) -> Self {
+        RemoteInfo {
+            size: 0,
+            supports_range: false,
+            validators: validators_from(response.headers()),
+            not_modified: true,
+            filename: None,
+            content_type: None,
+            content_encoding: None,
+            response: response_details(url, response),
+        }
+    }
+}
+
+// Headers guardados para depuração; cookies ficam de fora
+pub fn response_details(url: &str, response: &HttpResponse) -> ResponseDetails {
+    ResponseDetails {
+        url: url.to_string(),
+        protocol: format!("{:?}", response.version()),
+        headers: response
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != reqwest::header::SET_COOKIE)
+            .map(|(name, value)| (name.as_str().to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect(),
+    }
+}
+
+// Resultado da verificação de um link antes de baixar
+#[derive(Debug, Clone)]
+pub struct LinkReport {
+    pub url: String,
+    pub filename: String, // Do Content-Disposition ou, na falta dele, da URL
+    pub result: Result<RemoteInfo, String>,
+}
+
+// Consulta um link sem baixar o conteúdo (mesma consulta feita ao iniciar um download)
+pub async fn check_link(backend: &SharedBackend, url: &str) -> LinkReport {
+    // Avisos de limite de requisições não são exibidos na verificação
+    let (tx, _rx) = async_channel::unbounded();
+    let result = probe_remote(backend, url, None, &tx, None).await.map_err(|e| e.to_string());
+    let filename = result
+        .as_ref()
+        .ok()
+        .and_then(|info| info.filename.clone())
+        .or_else(|| checksum::remote_filename(url).map(str::to_string))
+        .unwrap_or_else(|| "download".to_string());
+    LinkReport {
+        url: url.to_string(),
+        filename,
+        result,
+    }
+}
+
+// Verifica vários links em paralelo; cada resultado é enviado com o índice do link
+pub async fn check_links(
+    backend: &SharedBackend,
+    urls: Vec<String>,
+    tx: &async_channel::Sender<(usize, LinkReport)>,
+) {
+    futures_util::stream::iter(urls.into_iter().enumerate())
+        .map(|(index, url)| async move { (index, check_link(backend, &url).await) })
+        .buffer_unordered(LINK_CHECK_CONCURRENCY)
+        .for_each(|report| async move {
+            let _ = tx.send(report).await;
+        })
+        .await;
+}
+
+// Roda `check_links` em uma thread própria, com o client da configuração atual
+pub fn start_link_check(
+    urls: Vec<String>,
+    config: Arc<Mutex<AppConfig>>,
+    tx: async_channel::Sender<(usize, LinkReport)>,
+) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let backend = match config_backend(&config) {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("Erro ao criar client: {}", e);
+                    return;
+                }
+            };
+            check_links(&backend, urls, &tx).await;
+        });
+    });
+}
+
+// Baixa uma página HTML (até `MAX_INDEX_SIZE`)
+async fn fetch_html(backend: &SharedBackend, url: &str) -> Result<String, String> {
+    let response = send_request(backend.as_ref(), HttpRequest::get(url))
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("text/html") || value.starts_with("application/xhtml"))
+        .unwrap_or(true);
+    if !is_html {
+        return Err("O endereço não é uma página HTML".to_string());
+    }
+
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+        if body.len() > listing::MAX_INDEX_SIZE {
+            return Err("Página grande demais".to_string());
+        }
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+// Baixa a página de índice de uma pasta (ou, no FTP, a saída do LIST) e lista o que há nela
+pub async fn list_directory(backend: &SharedBackend, url: &str) -> Result<Vec<IndexEntry>, String> {
+    let url = listing::directory_url(url);
+    let page = fetch_html(backend, &url).await?;
+    if ftp::is_ftp_url(&url) {
+        return Ok(listing::parse_ftp_list(&page, &url));
+    }
+    Ok(listing::parse_index(&page, &url))
+}
+
+// Todos os links de uma página qualquer
+pub async fn page_links(backend: &SharedBackend, url: &str) -> Result<Vec<PageLink>, String> {
+    let html = fetch_html(backend, url).await?;
+    Ok(listing::extract_links(&html, url))
+}
+
+// Todos os arquivos de uma pasta e das subpastas (até `MAX_WALK_DEPTH` níveis).
+// Subpastas que falharem são ignoradas
+pub async fn walk_directory(backend: &SharedBackend, url: &str) -> Result<Vec<IndexEntry>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![(listing::directory_url(url), 0)];
+    let mut first = true;
+    while let Some((folder, depth)) = pending.pop() {
+        let entries = match list_directory(backend, &folder).await {
+            Ok(entries) => entries,
+            Err(e) if first => return Err(e),
+            Err(_) => continue,
+        };
+        first = false;
+        for entry in entries {
+            if !entry.is_dir {
+                files.push(entry);
+            } else if depth < listing::MAX_WALK_DEPTH {
+                pending.push((entry.url, depth + 1));
+            }
+        }
+    }
+    Ok(files)
+}
+
+// Confere um arquivo já concluído: com a soma SHA-256 guardada, recalcula o hash;
+// sem ela, compara o tamanho com o registrado no download ou, na falta dele, com o
+// Content-Length atual do servidor. Err quando não há com o que comparar.
+pub async fn verify_file(
+    backend: &SharedBackend,
+    path: &Path,
+    url: &str,
+    expected_size: u64,
+    sha256: Option<&str>,
+) -> Result<FileIntegrity, String> {
+    let Ok(metadata) = tokio::fs::metadata(path).await else {
+        return Ok(FileIntegrity::Missing);
+    };
+    let size = metadata.len();
+
+    if let Some(expected) = sha256 {
+        let path = path.to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || checksum::sha256_file(&path))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Erro ao ler arquivo: {}", e))?;
+        return Ok(if actual.eq_ignore_ascii_case(expected) {
+            FileIntegrity::Intact
+        } else if expected_size > 0 && size < expected_size {
+            FileIntegrity::Truncated
+        } else {
+            FileIntegrity::Modified
+        });
+    }
+
+    let expected_size = if expected_size > 0 {
+        expected_size
+    } else {
+        let (tx, _rx) = async_channel::unbounded();
+        let info = probe_remote(backend, url, None, &tx, None).await.map_err(|e| e.to_string())?;
+        if info.size == 0 {
+            return Err("O servidor não informa o tamanho do arquivo".to_string());
+        }
+        info.size
+    };
+    Ok(match size.cmp(&expected_size) {
+        std::cmp::Ordering::Equal => FileIntegrity::Intact,
+        std::cmp::Ordering::Less => FileIntegrity::Truncated,
+        std::cmp::Ordering::Greater => FileIntegrity::Modified,
+    })
+}
+
+// Completa um arquivo truncado pedindo só o que falta (Range a partir do tamanho
+// atual). Com validadores, manda If-Range: se o arquivo mudou no servidor, o
+// final não serve e o reparo é recusado. Retorna quantos bytes foram acrescentados.
+pub async fn repair_truncated(
+    backend: &SharedBackend,
+    url: &str,
+    path: &Path,
+    total_size: u64,
+    validators: &Validators,
+) -> Result<u64, String> {
+    let size = std::fs::metadata(path).map_err(|e| format!("Erro ao ler arquivo: {}", e))?.len();
+    if size >= total_size {
+        return Ok(0);
+    }
+
+    let mut request = HttpRequest::get(url).range(size, Some(total_size - 1));
+    if let Some(validator) = validators.etag.as_ref().or(validators.last_modified.as_ref()) {
+        request = request.header(reqwest::header::IF_RANGE.as_str(), validator);
+    }
+    let (tx, _rx) = async_channel::unbounded();
+    let response = retry_request_notify(
+        || send_request(backend.as_ref(), request.clone()),
+        MAX_RETRIES,
+        RETRY_DELAY_SECS,
+        retry_notice(&tx, None),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    match response.status() {
+        StatusCode::PARTIAL_CONTENT => {}
+        StatusCode::OK => return Err("O servidor não aceita continuar (ou o arquivo mudou)".to_string()),
+        status => return Err(format!("Status HTTP: {}", status)),
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Erro ao abrir arquivo: {}", e))?;
+    let mut stream = response.bytes_stream();
+    let mut written = 0u64;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        let chunk = &chunk[..chunk.len().min((total_size - size - written) as usize)];
+        file.write_all(chunk).map_err(|e| format!("Erro ao escrever: {}", e))?;
+        written += chunk.len() as u64;
+    }
+    file.sync_data().map_err(|e| format!("Erro ao descarregar arquivo: {}", e))?;
+    if size + written < total_size {
+        return Err(format!("Conexão encerrada com {} de {}", format_bytes(size + written), format_bytes(total_size)));
+    }
+    Ok(written)
+}
+
+// Roda `repair_truncated` em uma thread própria
+pub fn start_repair(
+    record: DownloadRecord,
+    path: PathBuf,
+    config: Arc<Mutex<AppConfig>>,
+    tx: async_channel::Sender<Result<u64, String>>,
+) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let result = match record_backend(&config, &record) {
+                Ok(backend) => {
+                    repair_truncated(&backend, &record.url, &path, record.total_bytes, &record.validators).await
+                }
+                Err(e) => Err(format!("Erro ao criar client: {}", e)),
+            };
+            let _ = tx.send(result).await;
+        });
+    });
+}
+
+// Roda `verify_file` em uma thread própria
+pub fn start_verify(
+    record: DownloadRecord,
+    path: PathBuf,
+    config: Arc<Mutex<AppConfig>>,
+    tx: async_channel::Sender<Result<FileIntegrity, String>>,
+) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let result = match record_backend(&config, &record) {
+                Ok(backend) => {
+                    verify_file(&backend, &path, &record.url, record.total_bytes, record.sha256.as_deref()).await
+                }
+                Err(e) => Err(format!("Erro ao criar client: {}", e)),
+            };
+            let _ = tx.send(result).await;
+        });
+    });
+}
+
+// Confere se um novo endereço entrega o mesmo arquivo que o .part já começou:
+// tamanho, ETag e Last-Modified, os que os dois lados informam, precisam bater,
+// e pelo menos um deles tem que ter sido comparado
+pub async fn check_same_content(
+    backend: &SharedBackend,
+    url: &str,
+    total_size: u64,
+    expected: &Validators,
+) -> Result<(), String> {
+    let (tx, _rx) = async_channel::unbounded();
+    let info = probe_remote(backend, url, None, &tx, None).await.map_err(|e| e.to_string())?;
+    let mut compared = false;
+    if total_size > 0 && info.size > 0 {
+        if info.size != total_size {
+            return Err(format!("tamanho diferente ({} em vez de {})", format_bytes(info.size), format_bytes(total_size)));
+        }
+        compared = true;
+    }
+    if let (Some(expected), Some(found)) = (expected.etag.as_deref(), info.validators.etag.as_deref()) {
+        if expected != found {
+            return Err("o ETag não confere, o arquivo não é o mesmo".to_string());
+        }
+        compared = true;
+    }
+    if let (Some(expected), Some(found)) = (expected.last_modified.as_deref(), info.validators.last_modified.as_deref()) {
+        if expected != found {
+            return Err("a data de modificação não confere, o arquivo não é o mesmo".to_string());
+        }
+        compared = true;
+    }
+    if !compared {
+        return Err("o novo endereço não informa tamanho, ETag nem data para comparar".to_string());
+    }
+    Ok(())
+}
+
+// Roda `check_same_content` em uma thread própria, comparando `url` com a resposta
+// guardada no registro
+pub fn start_url_check(
+    record: DownloadRecord,
+    url: String,
+    config: Arc<Mutex<AppConfig>>,
+    tx: async_channel::Sender<Result<(), String>>,
+) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let header = |name| record.response.as_ref().and_then(|response| response.header(name)).map(str::to_string);
+            let expected = Validators { etag: header("etag"), last_modified: header("last-modified") };
+            let result = match record_backend(&config, &record) {
+                Ok(backend) => check_same_content(&backend, &url, record.total_bytes, &expected).await,
+                Err(e) => Err(format!("Erro ao criar client: {}", e)),
+            };
+            let _ = tx.send(result).await;
+        });
+    });
+}
+
+// Roda `list_directory` (ou `walk_directory`, com `recursive`) em uma thread própria
+pub fn start_directory_listing(
+    url: String,
+    recursive: bool,
+    config: Arc<Mutex<AppConfig>>,
+    tx: async_channel::Sender<Result<Vec<IndexEntry>, String>>,
+) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let result = match config_backend(&config) {
+                Ok(backend) if recursive => walk_directory(&backend, &url).await,
+                Ok(backend) => list_directory(&backend, &url).await,
+                Err(e) => Err(format!("Erro ao criar client: {}", e)),
+            };
+            let _ = tx.send(result).await;
+        });
+    });
+}
+
+// Roda `page_links` em uma thread própria
+pub fn start_page_scan(
+    url: String,
+    config: Arc<Mutex<AppConfig>>,
+    tx: async_channel::Sender<Result<Vec<PageLink>, String>>,
+) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let result = match config_backend(&config) {
+                Ok(backend) => page_links(&backend, &url).await,
+                Err(e) => Err(format!("Erro ao criar client: {}", e)),
+            };
+            let _ = tx.send(result).await;
+        });
+    });
+}
+
+// Client com as opções, credenciais e gateways da configuração atual
+fn config_backend(config: &Arc<Mutex<AppConfig>>) -> Result<SharedBackend, HttpError> {
+    match config.lock() {
+        Ok(config) => backend_for_config(&config),
+        Err(_) => backend_for_config(&AppConfig::default()),
+    }
+}
+
+// Client de uma operação avulsa sobre um download (reparo, verificação, troca de
+// endereço) com as opções dele: Tor, certificados, referer e perfil de rede
+fn record_backend(config: &Arc<Mutex<AppConfig>>, record: &DownloadRecord) -> Result<SharedBackend, HttpError> {
+    let config = config.lock().map(|config| config.clone()).unwrap_or_default();
+    let profile = record
+        .options
+        .network_profile
+        .as_deref()
+        .and_then(|name| find_network_profile(&config.network_profiles, name));
+    let mut client_options = config_client_options(&config);
+    apply_record_options(record, profile, &mut client_options);
+    let backend = build_backend(&client_options, S3Config::from_settings(&config.s3), config.ipfs.gateways.clone())?;
+    let headers = profile.map(NetworkProfile::request_headers).unwrap_or_default();
+    if headers.is_empty() {
+        return Ok(backend);
+    }
+    Ok(Arc::new(HeadersBackend::new(backend, headers)))
+}
+
+pub fn backend_for_config(config: &AppConfig) -> Result<SharedBackend, HttpError> {
+    build_backend(
+        &config_client_options(config),
+        S3Config::from_settings(&config.s3),
+        config.ipfs.gateways.clone(),
+    )
+}
+
+// Client HTTP real; URLs s3:// são traduzidas e assinadas antes de sair,
+// URLs ipfs:// vão para os gateways configurados e ftp:// fala FTP direto
+pub fn build_backend(
+    options: &ClientOptions,
+    s3_config: S3Config,
+    ipfs_gateways: Vec<String>,
+) -> Result<SharedBackend, HttpError> {
+    let client = Arc::new(ReqwestBackend::with_options(options)?);
+    Ok(wrap_backend(client, s3_config, ipfs_gateways, options.proxy.is_some() || options.pac.is_some()))
+}
+
+// `proxied`: o client sai por proxy, PAC ou Tor, que o FTP não atravessa
+fn wrap_backend(client: SharedBackend, s3_config: S3Config, ipfs_gateways: Vec<String>, proxied: bool) -> SharedBackend {
+    let inner: SharedBackend = Arc::new(IpfsBackend::new(client, ipfs_gateways));
+    let inner: SharedBackend = Arc::new(S3Backend::new(inner, s3_config));
+    Arc::new(FtpBackend::new(inner).proxied(proxied))
+}
+
+// Runtime dos downloads, criado no primeiro uso. Cada download roda na sua thread
+// com block_on; as tarefas das conexões do client compartilhado ficam aqui.
+pub fn download_runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Runtime::new().expect("runtime dos downloads"))
+}
+
+// Opções de conexão vindas da configuração (cada download ainda pode acrescentar as suas)
+pub fn config_client_options(config: &AppConfig) -> ClientOptions {
+    ClientOptions {
+        ip_preference: config.ip_preference,
+        ca_bundle: config.ca_bundle_path.as_ref().map(PathBuf::from),
+        keep_alive: config.keep_alive,
+        proxy: Some(config.proxy.manual.trim().to_string()).filter(|proxy| !proxy.is_empty()),
+        pac: Some(config.proxy.pac_url.trim()).filter(|url| !url.is_empty()).and_then(crate::pac::cached),
+        ..ClientOptions::default()
+    }
+}
+
+// Consulta tamanho e suporte a Range. Alguns servidores recusam HEAD (403/405);
+// nesse caso tenta um GET de um único byte e lê o total do Content-Range.
+// Com `conditional`, envia If-None-Match/If-Modified-Since e aceita 304.
+pub async fn probe_remote(
+    backend: &SharedBackend,
+    url: &str,
+    conditional: Option<&Validators>,
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: Option<&Arc<Mutex<DownloadTask>>>,
+) -> Result<RemoteInfo, HttpError> {
+    let head = retry_task_request(
+        || send_request(backend.as_ref(), conditional_request(HttpRequest::head(url), conditional)),
+        tx,
+        download_task,
+    )
+    .await;
+    // Ainda em manutenção depois das esperas: o GET receberia o mesmo 503
+    if let Err(e) = &head {
+        if matches!(e.kind, HttpErrorKind::ServerBusy | HttpErrorKind::Interrupted) {
+            return Err(e.clone());
+        }
+    }
+    if let Ok(resp) = &head {
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Ok(RemoteInfo::not_modified(url, resp));
+        }
+        if resp.status().is_success() {
+            return Ok(RemoteInfo::from_response(
+                url,
+                resp,
+                content_length(resp.headers()),
+                accepts_ranges(resp.headers()),
+            ));
+        }
+    }
+
+    let resp = match retry_task_request(
+        || send_request(backend.as_ref(), conditional_request(HttpRequest::get(url).range(0, Some(0)), conditional)),
+        tx,
+        download_task,
+    )
+    .await
+    {
+        Ok(resp) => resp,
+        Err(e) if e.kind == HttpErrorKind::Interrupted => return Err(e),
+        // Se o GET também falhar, o erro do HEAD costuma ser o mais informativo
+        Err(e) => return Err(head.err().unwrap_or(e)),
+    };
+
+    // O corpo não é lido: a resposta é descartada logo após ler os headers
+    match resp.status() {
+        StatusCode::NOT_MODIFIED => Ok(RemoteInfo::not_modified(url, &resp)),
+        StatusCode::PARTIAL_CONTENT => Ok(RemoteInfo::from_response(
+            url,
+            &resp,
+            content_range_total(resp.headers()).unwrap_or(0),
+            true,
+        )),
+        status if status.is_success() => Ok(RemoteInfo::from_response(
+            url,
+            &resp,
+            content_length(resp.headers()),
+            accepts_ranges(resp.headers()),
+        )),
+        status => Err(HttpError::from_status(HttpErrorKind::Other, status)),
+    }
+}
+
+fn conditional_request(request: HttpRequest, conditional: Option<&Validators>) -> HttpRequest {
+    let Some(validators) = conditional else {
+        return request;
+    };
+    let mut request = request;
+    if let Some(etag) = &validators.etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH.as_str(), etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(reqwest::header::IF_MODIFIED_SINCE.as_str(), last_modified);
+    }
+    request
+}
+
+fn validators_from(headers: &HeaderMap) -> Validators {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+    Validators {
+        etag: header(reqwest::header::ETAG),
+        last_modified: header(reqwest::header::LAST_MODIFIED),
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> u64 {
+    headers
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0)
+}
+
+fn accepts_ranges(headers: &HeaderMap) -> bool {
+    headers
+        .get(reqwest::header::ACCEPT_RANGES)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v == "bytes")
+        .unwrap_or(false)
+}
+
+// Total a partir de "Content-Range: bytes 0-0/12345" ("*" = desconhecido)
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit('/').next())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+// Tamanho real do arquivo segundo a resposta de um chunk: o total do
+// Content-Range ou, num 200 com o arquivo inteiro, o Content-Length
+fn response_total(response: &HttpResponse) -> Option<u64> {
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        content_range_total(response.headers())
+    } else {
+        Some(content_length(response.headers())).filter(|length| *length > 0)
+    }
+}
+
+fn size_correction_text(real: u64, reported: u64) -> String {
+    format!("Tamanho corrigido pela resposta: {} em vez de {}", format_bytes(real), format_bytes(reported))
+}
+
+// Corpo com Content-Encoding: decide se ele é descompactado ao concluir (ver `decoding`).
+// Fica como veio com a opção de manter, sem suporte à codificação ou quando o nome
+// ou o tipo já dizem que o arquivo é compactado (.tar.gz com "Content-Encoding: gzip")
+fn note_body_encoding(download_task: &Arc<Mutex<DownloadTask>>, encoding: Option<&str>, filename: &str, content_type: Option<&str>) {
+    let Some(encoding) = encoding else {
+        return;
+    };
+    let Ok(mut task) = download_task.lock() else {
+        return;
+    };
+    if task.keep_encoding {
+        task.log(format!("Corpo {} gravado como veio", encoding));
+    } else if decoding::names_encoding(encoding, filename, content_type) {
+        task.log(format!("Corpo {}: o arquivo já é compactado, fica como veio", encoding));
+    } else if decoding::ContentDecoder::for_encoding(encoding).is_none() {
+        task.log(format!("Codificação {} sem suporte: o arquivo fica como veio", encoding));
+    } else {
+        task.log(format!("Corpo {}: descompactado ao concluir", encoding));
+        task.decode_body = Some(encoding.to_string());
+    }
+}
+
+// Executa o download completo (consulta, escolha de estratégia e finalização) usando o backend informado
+pub async fn run_download(
+    backend: SharedBackend,
+    url: &str,
+    filename: &str,
+    download_dir: &Path,
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: &Arc<Mutex<DownloadTask>>,
+    state_records: &Arc<Mutex<Vec<DownloadRecord>>>,
+) {
+    log_event(download_task, "Iniciado");
+    publish(download_task, events::DownloadEvent::Started);
+    enter_state(download_task, tx, DownloadState::Connecting);
+
+    // Scripts do usuário podem trocar a URL e acrescentar headers
+    let hooks = download_task.lock().map(|task| task.hooks.clone()).unwrap_or_default();
+    // Fora da thread do runtime: os scripts podem chamar programas externos
+    let (hooked_url, hook_headers) = if hooks.is_empty() {
+        (url.to_string(), Vec::new())
+    } else {
+        let original = url.to_string();
+        tokio::task::spawn_blocking(move || hooks.before_request(&original))
+            .await
+            .unwrap_or_else(|_| (url.to_string(), Vec::new()))
+    };
+    let mut headers = download_task.lock().map(|task| task.extra_headers.clone()).unwrap_or_default();
+    headers.extend(hook_headers);
+    let url = hooked_url.as_str();
+
+    // Links de serviços de arquivos (Google Drive, Dropbox, plugins...) viram
+    // endereços diretos, às vezes com headers obrigatórios
+    let resolvers = download_task.lock().map(|task| task.resolvers.clone()).unwrap_or_default();
+    let resolved = match resolve::resolve_url(&resolvers, url).await {
+        Some((_, Ok(resolved))) => Some(resolved),
+        Some((name, Err(e))) => {
+            fail(tx, download_task, Failure::other(format!("Erro no resolvedor \"{}\": {}", name, e))).await;
+            return;
+        }
+        None => None,
+    };
+    if let Some(resolved) = &resolved {
+        headers.extend(resolved.headers.iter().cloned());
+    }
+    let backend: SharedBackend = if headers.is_empty() {
+        backend
+    } else {
+        Arc::new(HeadersBackend::new(backend, headers))
+    };
+    let candidates = resolved.as_ref().map(|resolved| resolved.urls.clone()).unwrap_or_else(|| vec![url.to_string()]);
+
+    // Descobre tamanho total e suporte a Range (HEAD, ou GET 0-0 se o HEAD for bloqueado)
+    // Sincronização: só faz sentido condicionar se a cópia local ainda existe
+    // (com versões em nomes próprios, a última delas)
+    let (conditional, seed_file, finished_dir) = download_task
+        .lock()
+        .map(|task| (task.conditional.clone(), task.seed_file.clone(), task.finished_dir.clone()))
+        .unwrap_or_default();
+    // Concluídos vão para a pasta de concluídos, se houver; o .part fica na de downloads
+    let final_dir = finished_dir.as_deref().unwrap_or(download_dir);
+    let conditional = conditional
+        .filter(|_| final_dir.join(filename).exists() || seed_file.is_some_and(|seed| seed.exists()));
+    // Com vários endereços, vale o primeiro que responder
+    let mut probed = None;
+    let mut last_error = None;
+    for candidate in candidates {
+        match probe_remote(&backend, &candidate, conditional.as_ref(), tx, Some(download_task)).await {
+            Ok(info) => {
+                probed = Some((candidate, info));
+                break;
+            }
+            Err(e) if e.kind == HttpErrorKind::Interrupted => {
+                stop_interrupted(tx, download_task, None).await;
+                return;
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let Some((url, info)) = probed else {
+        let context = format!("Erro ao obter info após {} tentativas", MAX_RETRIES);
+        let failure = match last_error {
+            Some(e) => Failure::http(&context, &e),
+            None => Failure::other(context),
+        };
+        fail(tx, download_task, failure).await;
+        return;
+    };
+    let url = url.as_str();
+    // Páginas intermediárias ("o download começará em instantes") levam ao arquivo real
+    let redirected = follow_redirector_pages(&backend, url, &info, tx).await;
+    let (url, info) = match &redirected {
+        Some((target, target_info)) => (target.as_str(), target_info.clone()),
+        None => (url, info),
+    };
+
+    // O nome tirado de um link de compartilhamento ("view", "uc") não diz nada:
+    // vale o nome que o servidor mandou
+    let renamed = if resolved.is_some() || redirected.is_some() {
+        server_filename(&info, filename, download_dir)
+    } else {
+        None
+    };
+    if let Some(name) = &renamed {
+        let _ = tx.send(DownloadMessage::Renamed(name.clone())).await;
+    }
+    let filename = renamed.as_deref().unwrap_or(filename);
+
+    // No Windows, caminhos acima do limite de 260 caracteres levam o prefixo \\?\
+    let file_path = platform::long_path(&final_dir.join(filename));
+    let temp_path = platform::long_path(&download_dir.join(format!("{}.part", filename)));
+    // Arquivos de uma pasta remota mantêm a estrutura de subpastas
+    for parent in [file_path.parent(), temp_path.parent()].into_iter().flatten() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            fail(tx, download_task, Failure::disk(format!("Erro ao criar pasta: {}", e))).await;
+            return;
+        }
+    }
+    let sequential_priority = match download_task.lock() {
+        Ok(mut task) => {
+            task.temp_path = Some(temp_path.clone());
+            task.sequential_priority
+        }
+        Err(_) => false,
+    };
+
+    if info.not_modified {
+        enter_state(download_task, tx, DownloadState::Done);
+        publish(download_task, |id| events::DownloadEvent::Completed { id, path: file_path.clone() });
+        let _ = tx.send(DownloadMessage::NotModified).await;
+        return;
+    }
+    if let Ok(mut task) = download_task.lock() {
+        task.validators = info.validators.clone();
+        task.response = Some(info.response.clone());
+        task.decode_body = None;
+        let size = if info.size > 0 { format_bytes(info.size) } else { "tamanho desconhecido".to_string() };
+        task.log(format!("Servidor respondeu ({}, {})", info.response.protocol, size));
+    }
+    note_body_encoding(download_task, info.content_encoding.as_deref(), filename, info.content_type.as_deref());
+    let RemoteInfo { size: total_size, supports_range, response, .. } = info;
+    // Servidor que limita cada conexão: já começa com todas as conexões. Na
+    // economia de dados, uma só (também ao retomar um mapa de chunks)
+    let (initial_connections, force_segmented, data_saver) = match download_task.lock() {
+        Ok(mut task) => {
+            task.supports_range = Some(supports_range);
+            if task.data_saver {
+                task.max_connections = 1;
+                task.initial_connections = Some(1);
+            } else if task.force_segmented {
+                task.initial_connections = Some(task.max_connections);
+            }
+            (task.initial_connections.unwrap_or(INITIAL_CONNECTIONS).max(1), task.force_segmented && !task.data_saver, task.data_saver)
+        }
+        Err(_) => (INITIAL_CONNECTIONS, false, false),
+    };
+    let min_parallel_size = if force_segmented { FORCED_MIN_CHUNK_SIZE } else { MIN_CHUNK_SIZE };
+
+    // Atualiza total_bytes (quando disponível) e a resposta no registro; o ETag
+    // dela confere um novo link do mesmo arquivo (ver `check_same_content`)
+    let id = download_task.lock().map(|task| task.id).ok();
+    if let Ok(mut records) = state_records.lock() {
+        if let Some(record) = records.iter_mut().find(|r| Some(r.id) == id) {
+            if total_size > 0 {
+                record.total_bytes = total_size;
+            }
+            record.response = Some(response);
+            save_downloads(&records);
+        }
+    }
+
+    // Download parcial do aria2/uGet: assume o arquivo e o mapa de peças
+    if supports_range && total_size > 0 && !temp_path.exists() {
+        match adopt_aria2_download(&download_dir.join(filename), &temp_path, total_size) {
+            Ok(Some(completed)) => log_event(
+                download_task,
+                format!("Importado do controle do aria2 ({} já baixados)", format_bytes(completed)),
+            ),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("Controle do aria2 ignorado: {}", e);
+                log_event(download_task, format!("Controle do aria2 ignorado: {}", e));
+            }
+        }
+    }
+
+    // Delta (zsync): com uma cópia antiga do arquivo e um .zsync no servidor,
+    // baixa só os blocos que mudaram
+    if supports_range && total_size > 0 && !temp_path.exists() {
+        let seed = if file_path.exists() {
+            Some(file_path.clone())
+        } else {
+            download_task
+                .lock()
+                .ok()
+                .and_then(|task| task.seed_file.clone())
+                .filter(|path| path.exists())
+        };
+        if let Some(seed) = seed {
+            match delta_download(&backend, url, &seed, &temp_path, total_size, tx, download_task).await {
+                Ok(DeltaOutcome::Done) => {
+                    finish_download(&backend, url, &temp_path, &file_path, total_size, tx, download_task).await;
+                    return;
+                }
+                Ok(DeltaOutcome::Cancelled) => {
+                    let _ = std::fs::remove_file(&temp_path);
+                    let _ = tx.send(DownloadMessage::Cancelled).await;
+                    return;
+                }
+                // O .part fica com o mapa dos intervalos que faltam
+                Ok(DeltaOutcome::Interrupted) => return,
+                Ok(DeltaOutcome::Unavailable) => {}
+                Err(e) => {
+                    // Continua com o download normal do arquivo inteiro
+                    eprintln!("Transferência delta falhou: {}", e);
+                    log_event(download_task, format!("Transferência delta falhou: {}", e));
+                    let _ = std::fs::remove_file(&temp_path);
+                }
+            }
+        }
+    }
+
+    // Progresso de um download paralelo interrompido (por chunk)
+    let map_path = chunk_map_path(&temp_path);
+    let saved_chunks = if temp_path.exists() && map_path.exists() {
+        let chunks = load_chunk_map(&map_path, total_size).filter(|_| supports_range);
+        if chunks.is_none() {
+            // O .part é de um download paralelo (pré-alocado com o tamanho total), mas o
+            // mapa não serve mais: retomar pelo tamanho do arquivo deixaria buracos
+            log_event(download_task, "Mapa de chunks ilegível ou de outro tamanho: recomeçando do zero");
+            let _ = std::fs::remove_file(&temp_path);
+            let _ = std::fs::remove_file(&map_path);
+        }
+        chunks
+    } else {
+        None
+    };
+
+    // Verifica se já existe arquivo .part (download pausado/interrompido)
+    let is_resume = temp_path.exists();
+
+    // Se não suporta Range, tamanho desconhecido, arquivo pequeno, economia de dados ou é
+    // resume de um download sequencial, usa download sequencial. Um .part com mapa de
+    // chunks retoma em paralelo.
+    let sequential = saved_chunks.is_none() && (data_saver || !supports_range || total_size < min_parallel_size || is_resume);
+    if sequential {
+        // Download sequencial (código original)
+        if data_saver {
+            log_event(download_task, "Economia de dados: uma conexão só");
+        }
+        log_event(download_task, if is_resume { "Download sequencial, retomando o .part" } else { "Download sequencial" });
+        download_sequential(&backend, url, &temp_path, &file_path, total_size, tx, download_task).await;
+        return;
+    }
+
+    log_event(
+        download_task,
+        if saved_chunks.is_some() { "Download em chunks paralelos, retomando o mapa salvo" } else { "Download em chunks paralelos" },
+    );
+    if force_segmented && total_size < MIN_CHUNK_SIZE {
+        log_event(download_task, "Servidor limita cada conexão: arquivo pequeno também vai em chunks");
+    }
+    // O plano é refeito se os chunks mostrarem outro tamanho (ver `response_total`)
+    let mut saved_chunks = saved_chunks;
+    let mut total_size = total_size;
+    let mut corrections = 0;
+    loop {
+        let chunks = match saved_chunks.take() {
+            Some(chunks) => chunks,
+            None => {
+                // Download paralelo em chunks: começa com poucas conexões e o controlador
+                // abre mais enquanto elas aumentarem a velocidade total
+                // Na prioridade sequencial, pedaços pequenos em ordem: as conexões sempre
+                // pegam o próximo pedaço, então o início do arquivo termina primeiro
+                let initial = if sequential_priority {
+                    total_size.div_ceil(SEQUENTIAL_PIECE_SIZE).max(1)
+                } else {
+                    (total_size / min_parallel_size).clamp(1, initial_connections as u64)
+                };
+                let chunks = split_chunks(total_size, initial);
+
+                // O mapa vem antes do .part: um .part pré-alocado sem mapa não diz o que já
+                // foi baixado (o tamanho dele já é o total)
+                if let Err(e) = save_chunk_map(&map_path, total_size, &chunks).await {
+                    fail(tx, download_task, Failure::disk(format!("Erro ao salvar mapa de chunks: {}", e))).await;
+                    return;
+                }
+
+                // Cria arquivo vazio
+                let file_handle = match tokio::fs::File::create(&temp_path).await {
+                    Ok(f) => f,
+                    Err(e) => {
+                        fail(tx, download_task, Failure::disk(format!("Erro ao criar arquivo: {}", e))).await;
+                        return;
+                    }
+                };
+
+                // Pre-aloca espaço no arquivo
+                if let Err(e) = file_handle.set_len(total_size).await {
+                    fail(tx, download_task, Failure::disk(format!("Erro ao pre-alocar arquivo: {}", e))).await;
+                    return;
+                }
+                mark_partial_origin(&temp_path, url, download_task);
+                chunks
+            }
+        };
+
+        // Abre arquivo para escrita paralela
+        let file = match ChunkFile::open(&temp_path).await {
+            Ok(f) => {
+                if f.is_uring() {
+                    log_event(download_task, "Gravação pelo io_uring");
+                }
+                f
+            }
+            Err(e) => {
+                fail(tx, download_task, Failure::disk(format!("Erro ao abrir arquivo: {}", e))).await;
+                return;
+            }
+        };
+
+        let mirror_pool = rank_mirrors(&backend, url, total_size, download_task).await;
+        let already_downloaded: u64 = chunks.iter().map(|c| c.downloaded).sum();
+        let ctx = Arc::new(ChunkContext {
+            backend: backend.clone(),
+            url: url.to_string(),
+            mirrors: mirror_pool,
+            file,
+            chunks: AsyncMutex::new(chunks),
+            total_size,
+            map_path: map_path.clone(),
+            download_task: download_task.clone(),
+            tx: tx.clone(),
+            last_update: AsyncMutex::new(Instant::now()),
+            corrected_size: AsyncMutex::new(None),
+            last_downloaded: AsyncMutex::new(already_downloaded),
+            buffers: BufferPool::new(),
+        });
+
+        // Cada chunk tenta novamente de forma independente; o controlador decide quantos rodam ao mesmo tempo
+        let mut first_error = run_connections(&ctx).await;
+        // Garante que a última escrita pendente do tokio::fs::File foi concluída antes de renomear
+        if let Err(e) = ctx.file.flush().await {
+            eprintln!("Erro ao descarregar arquivo: {}", e);
+            first_error.get_or_insert(Failure::disk(format!("Erro ao descarregar arquivo: {}", e)));
+        }
+        let final_chunks = ctx.chunks.lock().await.clone();
+        let corrected_size = *ctx.corrected_size.lock().await;
+        drop(ctx);
+
+        // Verifica cancelamento antes de verificar sucesso
+        let (cancelled, shutdown) = download_task
+            .lock()
+            .map(|task| (task.is_cancelled(), task.shutdown))
+            .unwrap_or((false, false));
+        if cancelled {
+            let _ = std::fs::remove_file(&temp_path);
+            let _ = std::fs::remove_file(&map_path);
+            let _ = tx.send(DownloadMessage::Cancelled).await;
+            return;
+        }
+        if shutdown {
+            // Arquivo já descarregado acima: o mapa salvo agora corresponde ao disco
+            if let Err(e) = save_chunk_map(&map_path, total_size, &final_chunks).await {
+                eprintln!("Erro ao salvar mapa de chunks: {}", e);
+            }
+            if let Ok(mut task) = download_task.lock() {
+                task.downloaded = final_chunks.iter().map(|c| c.downloaded).sum();
+            }
+            return;
+        }
+
+        // Tamanho errado na consulta inicial: descarta o plano e recomeça com o real
+        if let Some(real) = corrected_size.filter(|_| corrections < MAX_SIZE_CORRECTIONS) {
+            corrections += 1;
+            log_event(download_task, size_correction_text(real, total_size));
+            let _ = std::fs::remove_file(&temp_path);
+            let _ = std::fs::remove_file(&map_path);
+            total_size = real;
+            if let Ok(mut records) = state_records.lock() {
+                if let Some(record) = records.iter_mut().find(|r| Some(r.id) == id) {
+                    record.total_bytes = total_size;
+                    save_downloads(&records);
+                }
+            }
+            send_progress(tx, download_task, 0, total_size, 0.0, true).await;
+            if total_size < min_parallel_size {
+                log_event(download_task, "Download sequencial");
+                download_sequential(&backend, url, &temp_path, &file_path, total_size, tx, download_task).await;
+                return;
+            }
+            continue;
+        }
+
+        if let Some(e) = first_error {
+            // Mantém .part e mapa de chunks para retomar só o que faltou
+            if let Err(e) = save_chunk_map(&map_path, total_size, &final_chunks).await {
+                eprintln!("Erro ao salvar mapa de chunks: {}", e);
+            }
+            fail(tx, download_task, e.within("Erro ao baixar chunks")).await;
+            return;
+        }
+
+        // Download completo - renomeia arquivo
+        let _ = std::fs::remove_file(&map_path);
+        finish_download(&backend, url, &temp_path, &file_path, total_size, tx, download_task).await;
+        return;
+    }
+}
+
+// Testa o endereço principal e os espelhos do download ao mesmo tempo e devolve
+// os que servem, do mais rápido ao mais lento. Sem espelhos, nada é testado.
+async fn rank_mirrors(backend: &SharedBackend, url: &str, total_size: u64, download_task: &Arc<Mutex<DownloadTask>>) -> MirrorPool {
+    let (extra, data_saver) = download_task
+        .lock()
+        .map(|task| (task.mirrors.clone(), task.data_saver))
+        .unwrap_or_default();
+    let mut urls = vec![url.to_string()];
+    urls.extend(extra.into_iter().filter(|mirror| mirror != url));
+    // Economia de dados: a sondagem baixa um pedaço de cada espelho
+    if urls.len() == 1 || data_saver {
+        return MirrorPool::single(url);
+    }
+
+    let results = futures_util::future::join_all(urls.iter().map(|mirror| mirrors::probe_mirror(backend, mirror, total_size))).await;
+    let mut probes = Vec::new();
+    for (mirror, result) in urls.iter().zip(results) {
+        match result {
+            Ok(probe) => probes.push(probe),
+            Err(e) => log_event(download_task, format!("Espelho ignorado: {} ({})", mirror, e)),
+        }
+    }
+    if probes.is_empty() {
+        return MirrorPool::single(url);
+    }
+    mirrors::rank_probes(&mut probes);
+    let ranking: Vec<String> = probes
+        .iter()
+        .map(|probe| format!("{} ({}, {} ms)", probe.url, format_speed(probe.speed), probe.latency.as_millis()))
+        .collect();
+    log_event(download_task, format!("Espelhos por velocidade: {}", ranking.join(", ")));
+    MirrorPool::from_probes(probes)
+}
+
+// Nome sugerido pelo servidor, na mesma subpasta do nome atual. Fica o atual se
+// o servidor não mandar nenhum ou se já existir outro arquivo com esse nome
+fn server_filename(info: &RemoteInfo, filename: &str, download_dir: &Path) -> Option<String> {
+    let name = info.filename.as_deref()?;
+    let renamed = match filename.rsplit_once('/') {
+        Some((folder, _)) => format!("{}/{}", folder, name),
+        None => name.to_string(),
+    };
+    (renamed != filename && !download_dir.join(&renamed).exists()).then_some(renamed)
+}
+
+// Segue páginas de redirecionamento (meta refresh, JavaScript ou a confirmação do
+// Google Drive) até o arquivo. Só vale quando o link não aponta para uma página
+// .html de propósito
+async fn follow_redirector_pages(
+    backend: &SharedBackend,
+    url: &str,
+    info: &RemoteInfo,
+    tx: &async_channel::Sender<DownloadMessage>,
+) -> Option<(String, RemoteInfo)> {
+    let is_page = |info: &RemoteInfo| info.content_type.as_deref().is_some_and(|t| t == "text/html");
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_ascii_lowercase();
+    if !is_page(info) || path.ends_with(".html") || path.ends_with(".htm") {
+        return None;
+    }
+
+    let mut current: Option<(String, RemoteInfo)> = None;
+    for _ in 0..MAX_REDIRECTOR_PAGES {
+        let page = current.as_ref().map(|(page, _)| page.as_str()).unwrap_or(url);
+        let html = fetch_html(backend, page).await.ok()?;
+        let target = resolve::page_target(&html, page)?;
+        if target == page {
+            return None;
+        }
+        let _ = tx.send(DownloadMessage::Status("Seguindo página de redirecionamento...".to_string())).await;
+        let target_info = probe_remote(backend, &target, None, tx, None).await.ok()?;
+        let done = !is_page(&target_info);
+        current = Some((target, target_info));
+        if done {
+            return current;
+        }
+    }
+    None
+}
+
+// Renomeia o .part para o nome final e avisa a interface
+async fn finish_download(
+    backend: &SharedBackend,
+    url: &str,
+    temp_path: &Path,
+    file_path: &Path,
+    total_size: u64,
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: &Arc<Mutex<DownloadTask>>,
+) {
+    // O .part tem o corpo como veio do servidor: descompacta antes de verificar e mover
+    let decode = download_task.lock().ok().and_then(|task| task.decode_body.clone());
+    let decoded_body = decode.is_some();
+    let mut total_size = total_size;
+    if let Some(encoding) = decode {
+        enter_state(download_task, tx, DownloadState::Completing);
+        let _ = tx.send(DownloadMessage::Status("Descompactando...".to_string())).await;
+        let received = std::fs::metadata(temp_path).map(|metadata| metadata.len()).unwrap_or(0);
+        let (src, decoded) = (temp_path.to_path_buf(), temp_path.with_extension("part.decoded"));
+        let dest = decoded.clone();
+        let result = tokio::task::spawn_blocking(move || decoding::decode_file(&encoding, &src, &dest))
+            .await
+            .map_err(|e| std::io::Error::other(format!("{:?}", e)))
+            .and_then(|result| result);
+        let size = match result.and_then(|size| std::fs::rename(&decoded, temp_path).map(|_| size)) {
+            Ok(size) => size,
+            Err(e) => {
+                // Corpo corrompido: descarta para recomeçar do zero na próxima vez
+                let _ = std::fs::remove_file(&decoded);
+                let _ = std::fs::remove_file(temp_path);
+                fail(tx, download_task, Failure::other(format!("Erro ao descompactar: {}", e))).await;
+                return;
+            }
+        };
+        log_event(download_task, format!("Descompactado: {} recebidos, {} gravados", format_bytes(received), format_bytes(size)));
+        // O arquivo final tem o tamanho descompactado, que passa a ser o total do download
+        total_size = size;
+        send_progress(tx, download_task, size, size, 0.0, false).await;
+    }
+
+    let verify = download_task.lock().map(|task| task.verify_checksum).unwrap_or(false);
+    if verify {
+        enter_state(download_task, tx, DownloadState::Verifying);
+        match verify_published_checksum(backend, url, temp_path, tx).await {
+            Ok(checksum) => {
+                if let Ok(mut task) = download_task.lock() {
+                    task.checksum = checksum;
+                }
+            }
+            Err(e) => {
+                // Arquivo corrompido: descarta para não ser retomado
+                let _ = std::fs::remove_file(temp_path);
+                fail(tx, download_task, Failure::other(e)).await;
+                return;
+            }
+        }
+    }
+
+    enter_state(download_task, tx, DownloadState::Completing);
+    // Versões de um download recorrente/sincronizado: substitui ou ganha nome próprio
+    let naming = download_task.lock().ok().and_then(|task| task.version_naming);
+    let versioned = naming
+        .filter(|naming| *naming != VersionNaming::Overwrite)
+        .map(|naming| version_path(file_path, naming, Local::now()));
+    if let Some(path) = &versioned {
+        log_event(download_task, format!("Versão salva como {}", path.file_name().unwrap_or_default().to_string_lossy()));
+    }
+    let file_path = versioned.as_deref().unwrap_or(file_path);
+    if let Err(e) = platform::move_file(temp_path, file_path) {
+        fail(tx, download_task, Failure::disk(format!("Erro ao finalizar: {}", e))).await;
+        return;
+    }
+
+    // O rename pode dar certo com o arquivo menor que o baixado (ex.: disco cheio
+    // ao descarregar): busca só o final que faltou; sem conseguir, o registro fica
+    // marcado como incompleto para reparar depois. O final de um corpo descompactado
+    // não corresponde a um intervalo do servidor
+    let size = std::fs::metadata(file_path).map(|metadata| metadata.len()).unwrap_or(0);
+    if total_size > 0 && size < total_size && !decoded_body {
+        let _ = tx.send(DownloadMessage::Status("Arquivo incompleto, buscando o final...".to_string())).await;
+        log_event(download_task, format!("Arquivo menor que o esperado ({} de {}), buscando o final", format_bytes(size), format_bytes(total_size)));
+        let validators = download_task.lock().map(|task| task.validators.clone()).unwrap_or_default();
+        if let Err(e) = repair_truncated(backend, url, file_path, total_size, &validators).await {
+            eprintln!("Erro ao reparar {}: {}", file_path.display(), e);
+            if let Ok(mut task) = download_task.lock() {
+                task.integrity = Some(FileIntegrity::Truncated);
+                task.log(format!("Reparo falhou: {}", e));
+            }
+        }
+    }
+
+    // Salva o caminho do arquivo no download task
+    let (settings, last_modified, referer) = match download_task.lock() {
+        Ok(mut task) => {
+            task.file_path = Some(file_path.to_path_buf());
+            (task.provenance, task.validators.last_modified.clone(), task.referer.clone())
+        }
+        Err(_) => (ProvenanceSettings::default(), None, None),
+    };
+
+    // Falhas aqui não invalidam o download (ex.: sistema de arquivos sem xattrs)
+    if settings.origin_xattrs {
+        if let Err(e) = provenance::write_origin(file_path, url, referer.as_deref()) {
+            eprintln!("Erro ao gravar origem do arquivo: {}", e);
+        }
+    }
+    if settings.remote_mtime {
+        if let Some(time) = last_modified.as_deref().and_then(provenance::parse_http_date) {
+            if let Err(e) = provenance::set_modified(file_path, time) {
+                eprintln!("Erro ao definir data de modificação: {}", e);
+            }
+        }
+    }
+
+    // Antivírus: o resultado fica no registro; arquivo infectado não passa pelos scripts
+    let scanner = download_task.lock().map(|task| task.scanner.clone()).unwrap_or_default();
+    let mut infected = false;
+    if !scanner.is_empty() {
+        enter_state(download_task, tx, DownloadState::Verifying);
+        let _ = tx.send(DownloadMessage::Status("Verificando com o antivírus...".to_string())).await;
+        match scan::scan_file(&scanner, file_path).await {
+            Ok(result) => {
+                infected = result == ScanResult::Infected;
+                if let Ok(mut task) = download_task.lock() {
+                    task.scan = Some(result);
+                    task.log(result.label());
+                }
+            }
+            Err(e) => log_event(download_task, format!("Antivírus falhou: {}", e)),
+        }
+    }
+
+    // Pós-processamento dos scripts (podem chamar programas externos)
+    let hooks = download_task.lock().map(|task| task.hooks.clone()).unwrap_or_default();
+    if !hooks.is_empty() && !infected {
+        let _ = tx.send(DownloadMessage::Status("Executando scripts...".to_string())).await;
+        let (path, url) = (file_path.to_path_buf(), url.to_string());
+        let _ = tokio::task::spawn_blocking(move || hooks.completed(&path, &url)).await;
+    }
+
+    enter_state(download_task, tx, DownloadState::Done);
+    publish(download_task, |id| events::DownloadEvent::Completed { id, path: file_path.to_path_buf() });
+    let _ = tx.send(DownloadMessage::Complete).await;
+}
+
+// Confere o arquivo com a soma SHA-256 publicada pelo servidor ou, em ipfs://,
+// com o próprio CID. Retorna a soma verificada (None quando não há com o que comparar)
+async fn verify_published_checksum(
+    backend: &SharedBackend,
+    url: &str,
+    path: &Path,
+    tx: &async_channel::Sender<DownloadMessage>,
+) -> Result<Option<String>, String> {
+    let (expected, mismatch) = if ipfs::is_ipfs_url(url) {
+        (ipfs::raw_sha256(url), "SHA-256 não confere com o CID do IPFS")
+    } else {
+        (published_checksum(backend, url).await, "SHA-256 não confere com o publicado pelo servidor")
+    };
+    let Some(expected) = expected else {
+        return Ok(None);
+    };
+
+    let _ = tx.send(DownloadMessage::Status("Verificando SHA-256...".to_string())).await;
+    let path = path.to_path_buf();
+    let actual = tokio::task::spawn_blocking(move || checksum::sha256_file(&path))
+        .await
+        .map_err(|e| format!("Erro ao calcular SHA-256: {:?}", e))?
+        .map_err(|e| format!("Erro ao calcular SHA-256: {}", e))?;
+    if actual != expected {
+        return Err(mismatch.to_string());
+    }
+    Ok(Some(actual))
+}
+
+// Procura a soma do arquivo nos SHA256SUMS publicados ao lado dele
+async fn published_checksum(backend: &SharedBackend, url: &str) -> Option<String> {
+    let filename = checksum::remote_filename(url)?;
+    for sums_url in checksum::checksum_urls(url) {
+        let Ok(response) = send_request(backend.as_ref(), HttpRequest::get(&sums_url)).await else {
+            continue;
+        };
+        if !response.status().is_success() {
+            continue;
+        }
+        let mut body = Vec::new();
+        let mut stream = response.bytes_stream();
+        while let Some(Ok(chunk)) = stream.next().await {
+            body.extend_from_slice(&chunk);
+            if body.len() > checksum::MAX_SUMS_SIZE {
+                break;
+            }
+        }
+        if let Some(hash) = checksum::find_checksum(&String::from_utf8_lossy(&body), filename) {
+            return Some(hash);
+        }
+    }
+    None
+}
+
+enum DeltaOutcome {
+    Done,
+    Unavailable, // Sem .zsync (ou de outra versão): segue o download normal
+    Cancelled,
+    Interrupted, // App fechando ou download abortado: retoma em chunks na próxima vez
+}
+
+// Monta o .part a partir dos blocos da cópia local e baixa só os intervalos que faltam
+async fn delta_download(
+    backend: &SharedBackend,
+    url: &str,
+    seed: &Path,
+    temp_path: &Path,
+    total_size: u64,
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: &Arc<Mutex<DownloadTask>>,
+) -> Result<DeltaOutcome, String> {
+    let response = match send_request(backend.as_ref(), HttpRequest::get(&zsync::control_file_url(url))).await {
+        Ok(response) if response.status().is_success() => response,
+        _ => return Ok(DeltaOutcome::Unavailable),
+    };
+    let mut control_data = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        control_data.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+        if control_data.len() > zsync::MAX_CONTROL_SIZE {
+            return Ok(DeltaOutcome::Unavailable);
+        }
+    }
+    let control = ControlFile::parse(&control_data)?;
+    if control.length != total_size {
+        return Ok(DeltaOutcome::Unavailable);
+    }
+
+    enter_state(download_task, tx, DownloadState::Verifying);
+    let _ = tx.send(DownloadMessage::Status("Comparando com a cópia local...".to_string())).await;
+    let (seed, temp) = (seed.to_path_buf(), temp_path.to_path_buf());
+    let (control, found, reused) = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+        let found = zsync::match_blocks(&control, std::io::BufReader::new(File::open(&seed)?))?;
+        let reused = zsync::copy_known_blocks(&control, &found, &seed, &temp)?;
+        Ok((control, found, reused))
+    })
+    .await
+    .map_err(|e| format!("Erro ao comparar cópia local: {:?}", e))?
+    .map_err(|e| format!("Erro ao comparar cópia local: {}", e))?;
+
+    let _ = tx
+        .send(DownloadMessage::Status(format!(
+            "Delta: {}% reaproveitado da cópia local",
+            reused * 100 / total_size
+        )))
+        .await;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(temp_path)
+        .map_err(|e| format!("Erro ao abrir arquivo: {}", e))?;
+    let mut downloaded = reused;
+    let mut last_update = Instant::now();
+    let mut last_downloaded = downloaded;
+
+    let ranges = zsync::missing_ranges(&control, &found);
+    for (current, &(start, end)) in ranges.iter().enumerate() {
+        let response = match retry_task_request(
+            || send_request(backend.as_ref(), HttpRequest::get(url).range(start, Some(end))),
+            tx,
+            Some(download_task),
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(e) if e.kind == HttpErrorKind::Interrupted => {
+                return Ok(stop_delta(download_task, temp_path, total_size, &ranges, current, start, downloaded).await);
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(format!("Status HTTP: {}", response.status()));
+        }
+
+        use std::io::{Seek, SeekFrom};
+        file.seek(SeekFrom::Start(start)).map_err(|e| format!("Erro ao posicionar arquivo: {}", e))?;
+        let mut position = start;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            if wait_while_paused(download_task).await {
+                return Ok(stop_delta(download_task, temp_path, total_size, &ranges, current, position, downloaded).await);
+            }
+            let chunk = chunk.map_err(|e| e.to_string())?;
+            let chunk = &chunk[..chunk.len().min((end + 1 - position) as usize)];
+            if chunk.is_empty() {
+                break;
+            }
+            file.write_all(chunk).map_err(|e| format!("Erro ao escrever: {}", e))?;
+            count_usage(download_task, chunk.len() as u64);
+            throttle(download_task, chunk.len() as u64).await;
+            position += chunk.len() as u64;
+            downloaded += chunk.len() as u64;
+
+            if last_update.elapsed().as_millis() >= 200 {
+                let speed_bytes = (downloaded - last_downloaded) as f64 / last_update.elapsed().as_secs_f64();
+                send_progress(tx, download_task, downloaded, total_size, speed_bytes, false).await;
+                last_update = Instant::now();
+                last_downloaded = downloaded;
+            }
+        }
+        if position <= end {
+            return Err("Resposta terminou antes do fim do intervalo".to_string());
+        }
+    }
+    file.flush().map_err(|e| format!("Erro ao escrever: {}", e))?;
+    drop(file);
+
+    // O SHA-1 do .zsync garante que o arquivo montado é idêntico ao remoto
+    if let Some(expected) = control.sha1 {
+        let temp = temp_path.to_path_buf();
+        let matches = tokio::task::spawn_blocking(move || zsync::sha1_matches(&temp, &expected))
+            .await
+            .map_err(|e| format!("Erro ao verificar SHA-1: {:?}", e))?
+            .map_err(|e| format!("Erro ao verificar SHA-1: {}", e))?;
+        if !matches {
+            return Err("SHA-1 do arquivo montado não confere".to_string());
+        }
+    }
+
+    Ok(DeltaOutcome::Done)
+}
+
+// Transferência delta parada no intervalo `current`, já gravado até `position`.
+// Cancelada, o .part vai embora; no fechamento do app (ou abortada) ele fica e o
+// mapa de chunks descreve o que já está nele: o que veio da cópia local e os
+// intervalos baixados contam como completos, e a próxima vez retoma em paralelo
+async fn stop_delta(
+    download_task: &Arc<Mutex<DownloadTask>>,
+    temp_path: &Path,
+    total_size: u64,
+    ranges: &[(u64, u64)],
+    current: usize,
+    position: u64,
+    downloaded: u64,
+) -> DeltaOutcome {
+    if download_task.lock().map(|task| task.is_cancelled()).unwrap_or(false) {
+        return DeltaOutcome::Cancelled;
+    }
+    let mut chunks = Vec::new();
+    let mut next = 0;
+    for (i, &(start, end)) in ranges.iter().enumerate() {
+        if start > next {
+            chunks.push(ChunkState { start: next, end: start - 1, downloaded: start - next });
+        }
+        let done = match i.cmp(&current) {
+            std::cmp::Ordering::Less => end + 1 - start,
+            std::cmp::Ordering::Equal => position - start,
+            std::cmp::Ordering::Greater => 0,
+        };
+        chunks.push(ChunkState { start, end, downloaded: done });
+        next = end + 1;
+    }
+    if next < total_size {
+        chunks.push(ChunkState { start: next, end: total_size - 1, downloaded: total_size - next });
+    }
+    if let Err(e) = save_chunk_map(&chunk_map_path(temp_path), total_size, &chunks).await {
+        // Sem o mapa, o .part (já com o tamanho total) pareceria completo
+        eprintln!("Erro ao salvar mapa de chunks: {}", e);
+        let _ = std::fs::remove_file(temp_path);
+    }
+    if let Ok(mut task) = download_task.lock() {
+        task.downloaded = downloaded;
+    }
+    DeltaOutcome::Interrupted
+}
+
+// Espera enquanto o download estiver pausado; retorna true se foi cancelado (ou o app está fechando)
+async fn wait_while_paused(download_task: &Arc<Mutex<DownloadTask>>) -> bool {
+    loop {
+        let (cancelled, paused) = download_task
+            .lock()
+            .map(|mut task| (task.is_cancelled() || task.shutdown, task.poll_paused()))
+            .unwrap_or((false, false));
+        if cancelled {
+            return true;
+        }
+        if !paused {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+// Download parado durante a espera de uma nova tentativa (`HttpErrorKind::Interrupted`):
+// cancelado apaga o .part (se houver) e avisa o card; no fechamento do app fica tudo como está
+async fn stop_interrupted(tx: &async_channel::Sender<DownloadMessage>, download_task: &Arc<Mutex<DownloadTask>>, temp_path: Option<&Path>) {
+    let cancelled = download_task.lock().map(|task| task.is_cancelled()).unwrap_or(false);
+    if cancelled {
+        if let Some(temp_path) = temp_path {
+            let _ = std::fs::remove_file(temp_path);
+        }
+        let _ = tx.send(DownloadMessage::Cancelled).await;
+    }
+}
+
+// Soma os bytes recebidos ao consumo do mês, quando o app acompanha a cota,
+// e marca a chegada de dados para a detecção de download parado
+fn count_usage(download_task: &Arc<Mutex<DownloadTask>>, bytes: u64) {
+    let usage = download_task.lock().ok().and_then(|mut task| {
+        task.last_data = Some(Instant::now());
+        task.usage.clone()
+    });
+    if let Some(usage) = usage {
+        if let Ok(mut usage) = usage.lock() {
+            usage.add(bytes, Local::now());
+        }
+    }
+}
+
+// Recebeu dados: volta (ou entra) em Downloading e manda o progresso com as
+// conexões abertas agora
+async fn send_progress(
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: &Arc<Mutex<DownloadTask>>,
+    bytes: u64,
+    total: u64,
+    speed_bps: f64,
+    parallel: bool,
+) {
+    publish_progress(tx, download_task, |connections, state| {
+        ProgressUpdate::new(bytes, total, speed_bps, connections, parallel, state)
+    })
+    .await;
+}
+
+async fn publish_progress(
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: &Arc<Mutex<DownloadTask>>,
+    update: impl FnOnce(usize, DownloadState) -> ProgressUpdate,
+) {
+    enter_state(download_task, tx, DownloadState::Downloading);
+    let (connections, state) = download_task
+        .lock()
+        .map(|task| (task.connections, task.state))
+        .unwrap_or((0, DownloadState::Downloading));
+    let update = update(connections, state);
+    publish(download_task, |id| events::DownloadEvent::Progress(id, update.clone()));
+    let _ = tx.send(DownloadMessage::Progress(update)).await;
+}
+
+// Baixando sem receber nada há STALLED_AFTER_SECS: passa para Stalled (o
+// próximo progresso volta para Downloading)
+fn check_stalled(download_task: &Arc<Mutex<DownloadTask>>, tx: &async_channel::Sender<DownloadMessage>) {
+    let stalled = download_task
+        .lock()
+        .map(|task| {
+            task.state == DownloadState::Downloading
+                && task.last_data.is_some_and(|at| at.elapsed() >= Duration::from_secs(STALLED_AFTER_SECS))
+        })
+        .unwrap_or(false);
+    if stalled {
+        enter_state(download_task, tx, DownloadState::Stalled);
+    }
+}
+
+// Segura a leitura enquanto o limite de velocidade global estiver excedido
+async fn throttle(download_task: &Arc<Mutex<DownloadTask>>, bytes: u64) {
+    let (limiter, download_limiter) = match download_task.lock() {
+        Ok(task) => (task.speed_limiter.clone(), task.download_limiter.clone()),
+        Err(_) => return,
+    };
+    if let Some(limiter) = limiter {
+        limiter.throttle(bytes).await;
+    }
+    if let Some(limiter) = download_limiter {
+        limiter.throttle(bytes).await;
+    }
+}
+
+// Intervalo de bytes de um chunk e quanto dele já foi gravado
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkState {
+    pub start: u64,
+    pub end: u64, // inclusivo
+    pub downloaded: u64,
+}
+
+impl ChunkState {
+    // Próximo byte a baixar
+    pub fn position(&self) -> u64 {
+        self.start + self.downloaded
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.position() > self.end
+    }
+}
+
+// Conteúdo do arquivo "<nome>.part.chunks"
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkMap {
+    total_size: u64,
+    chunks: Vec<ChunkState>,
+}
+
+pub fn chunk_map_path(temp_path: &Path) -> PathBuf {
+    let mut name = temp_path.as_os_str().to_owned();
+    name.push(".chunks");
+    PathBuf::from(name)
+}
+
+// Carrega o mapa de chunks se ele corresponder ao tamanho atual do arquivo remoto.
+// O mapa pode vir de fora (fila exportada, controle do aria2): só vale se os chunks
+// cobrirem o arquivo inteiro, sem buracos nem sobreposições
+pub fn load_chunk_map(path: &Path, total_size: u64) -> Option<Vec<ChunkState>> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let map: ChunkMap = serde_json::from_str(&contents).ok()?;
+    (map.total_size == total_size && valid_chunks(&map.chunks, total_size)).then_some(map.chunks)
+}
+
+fn valid_chunks(chunks: &[ChunkState], total_size: u64) -> bool {
+    if chunks.is_empty() || chunks.iter().any(|c| c.start > c.end || c.end >= total_size || c.downloaded > c.end - c.start + 1) {
+        return false;
+    }
+    let mut sorted = chunks.to_vec();
+    sorted.sort_by_key(|c| c.start);
+    let mut next = 0;
+    for chunk in &sorted {
+        if chunk.start != next {
+            return false;
+        }
+        next = chunk.end + 1;
+    }
+    next == total_size
+}
+
+// Grava em "<mapa>.tmp" e troca pelo mapa: um crash no meio da escrita deixa o
+// mapa anterior inteiro em vez de um arquivo truncado
+fn write_chunk_map(path: &Path, map: &ChunkMap) -> std::io::Result<()> {
+    let json = serde_json::to_string(map)?;
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+    std::fs::write(&temp_path, json)?;
+    platform::rename_file(&temp_path, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp_path);
+    })
+}
+
+// Salvo a cada 200ms durante o download: a escrita e o rename (que no Windows
+// espera o antivírus soltar o arquivo) rodam fora da task
+async fn save_chunk_map(path: &Path, total_size: u64, chunks: &[ChunkState]) -> std::io::Result<()> {
+    let path = path.to_path_buf();
+    let map = ChunkMap {
+        total_size,
+        chunks: chunks.to_vec(),
+    };
+    tokio::task::spawn_blocking(move || write_chunk_map(&path, &map))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+// A origem gravada no .part permite adotá-lo de volta se o registro se perder
+// (ver `recovery`). Só com os xattrs de origem ativos: o atributo segue o arquivo
+// ao ser renomeado no fim do download.
+fn mark_partial_origin(temp_path: &Path, url: &str, download_task: &Arc<Mutex<DownloadTask>>) {
+    let (settings, referer) = match download_task.lock() {
+        Ok(task) => (task.provenance, task.referer.clone()),
+        Err(_) => return,
+    };
+    if settings.origin_xattrs {
+        if let Err(e) = provenance::write_origin(temp_path, url, referer.as_deref()) {
+            eprintln!("Erro ao gravar origem do arquivo parcial: {}", e);
+        }
+    }
+}
+
+// Converte "<nome>" + "<nome>.aria2" em "<nome>.part" + mapa de chunks, para
+// o download paralelo retomar só as peças que faltam. Devolve os bytes já
+// baixados, ou None se não há controle do aria2 ao lado do arquivo
+pub fn adopt_aria2_download(file_path: &Path, temp_path: &Path, total_size: u64) -> Result<Option<u64>, String> {
+    let control_path = aria2::control_file_path(file_path);
+    if !control_path.exists() || !file_path.exists() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(&control_path).map_err(|e| e.to_string())?;
+    let control = aria2::parse_control_file(&bytes)?;
+    if control.total_length != total_size {
+        return Err(format!(
+            "tamanho {} não corresponde ao remoto ({})",
+            format_bytes(control.total_length),
+            format_bytes(total_size)
+        ));
+    }
+    // Mapa antes do .part, como no download paralelo
+    let map_path = chunk_map_path(temp_path);
+    let map = ChunkMap {
+        total_size,
+        chunks: control.chunks(),
+    };
+    write_chunk_map(&map_path, &map).map_err(|e| e.to_string())?;
+    if let Err(e) = platform::rename_file(file_path, temp_path) {
+        let _ = std::fs::remove_file(&map_path);
+        return Err(e.to_string());
+    }
+    let _ = std::fs::remove_file(&control_path);
+    Ok(Some(control.completed_bytes()))
+}
+
+// Divide o arquivo em `num_chunks` intervalos (o último absorve o resto)
+pub fn split_chunks(total_size: u64, num_chunks: u64) -> Vec<ChunkState> {
+    let chunk_size = total_size / num_chunks;
+    (0..num_chunks)
+        .map(|chunk_id| {
+            let start = chunk_id * chunk_size;
+            let end = if chunk_id == num_chunks - 1 {
+                total_size - 1
+            } else {
+                start + chunk_size - 1
+            };
+            ChunkState { start, end, downloaded: 0 }
+        })
+        .collect()
+}
+
+// Estado compartilhado entre os chunks de um download paralelo
+pub struct ChunkContext {
+    pub backend: SharedBackend,
+    pub url: String,
+    pub mirrors: MirrorPool, // Endereços usados nos chunks (só `url`, sem espelhos)
+    pub file: ChunkFile,
+    pub chunks: AsyncMutex<Vec<ChunkState>>,
+    pub total_size: u64,
+    pub corrected_size: AsyncMutex<Option<u64>>, // Tamanho real visto num chunk, quando difere de total_size
+    pub map_path: PathBuf,
+    pub download_task: Arc<Mutex<DownloadTask>>,
+    pub tx: async_channel::Sender<DownloadMessage>,
+    pub last_update: AsyncMutex<Instant>,
+    pub last_downloaded: AsyncMutex<u64>,
+    pub buffers: BufferPool,
+}
+
+// Buffers das conexões de chunk. Cada conexão pega um ao começar e devolve ao
+// terminar; o mesmo espaço é regravado a cada escrita, sem alocar por pedaço.
+#[derive(Debug, Default)]
+pub struct BufferPool {
+    free: Mutex<Vec<bytes::BytesMut>>,
+    allocated: std::sync::atomic::AtomicUsize,
+}
+
+impl BufferPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn take(&self) -> bytes::BytesMut {
+        if let Some(buffer) = self.free.lock().ok().and_then(|mut free| free.pop()) {
+            return buffer;
+        }
+        self.allocated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        bytes::BytesMut::with_capacity(CHUNK_BUFFER_SIZE)
+    }
+
+    pub fn give_back(&self, mut buffer: bytes::BytesMut) {
+        buffer.clear();
+        if let Ok(mut free) = self.free.lock() {
+            free.push(buffer);
+        }
+    }
+
+    // Buffers criados até agora (no máximo um por conexão aberta ao mesmo tempo)
+    pub fn allocated(&self) -> usize {
+        self.allocated.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+// Arquivo .part dos chunks: io_uring quando o kernel permite, senão tokio::fs::File
+pub enum ChunkFile {
+    Tokio(AsyncMutex<tokio::fs::File>),
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Uring(crate::uring::UringWriter),
+}
+
+impl ChunkFile {
+    pub async fn open(path: &Path) -> std::io::Result<Self> {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Ok(writer) = crate::uring::UringWriter::open(path) {
+            return Ok(ChunkFile::Uring(writer));
+        }
+        let file = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+        Ok(ChunkFile::Tokio(AsyncMutex::new(file)))
+    }
+
+    pub fn is_uring(&self) -> bool {
+        !matches!(self, ChunkFile::Tokio(_))
+    }
+
+    pub async fn write_at(&self, offset: u64, data: bytes::Bytes) -> std::io::Result<()> {
+        match self {
+            ChunkFile::Tokio(file) => {
+                use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+                let mut file = file.lock().await;
+                file.seek(std::io::SeekFrom::Start(offset)).await?;
+                file.write_all(&data).await
+            }
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            ChunkFile::Uring(writer) => writer.write_at(offset, data).await,
+        }
+    }
+
+    // Garante que as escritas pendentes terminaram (o io_uring responde só depois de gravar)
+    pub async fn flush(&self) -> std::io::Result<()> {
+        match self {
+            ChunkFile::Tokio(file) => {
+                use tokio::io::AsyncWriteExt;
+                file.lock().await.flush().await
+            }
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            ChunkFile::Uring(_) => Ok(()),
+        }
+    }
+}
+
+// Decide quando abrir mais uma conexão: só enquanto cada conexão extra
+// aumentar a velocidade total em pelo menos MIN_SPEEDUP
+#[derive(Debug)]
+pub struct ConnectionTuner {
+    last_speed: Option<f64>, // Velocidade medida com o número atual de conexões menos uma
+    settled: bool,           // Uma conexão extra não ajudou; não tenta mais
+}
+
+impl ConnectionTuner {
+    pub fn new() -> Self {
+        ConnectionTuner {
+            last_speed: None,
+            settled: false,
+        }
+    }
+
+    // Recebe a velocidade agregada (bytes/s) da última medição
+    pub fn should_add(&mut self, speed: f64, connections: usize, max_connections: usize) -> bool {
+        if self.settled || connections >= max_connections {
+            return false;
+        }
+        if let Some(previous) = self.last_speed {
+            if speed < previous * (1.0 + MIN_SPEEDUP) {
+                self.settled = true;
+                return false;
+            }
+        }
+        self.last_speed = Some(speed);
+        true
+    }
+}
+
+impl Default for ConnectionTuner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Escolhe o próximo chunk para uma conexão livre: um chunk ainda sem conexão
+// ou, se não houver, a metade final do maior intervalo restante
+pub fn next_chunk(chunks: &mut Vec<ChunkState>, assigned: &[bool]) -> Option<usize> {
+    if let Some(id) = (0..chunks.len()).find(|&id| !assigned.get(id).copied().unwrap_or(false) && !chunks[id].is_complete()) {
+        return Some(id);
+    }
+
+    let (id, remaining) = chunks
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.is_complete())
+        .map(|(id, c)| (id, c.end + 1 - c.position()))
+        .max_by_key(|&(_, remaining)| remaining)?;
+    if remaining < 2 * MIN_CHUNK_SIZE {
+        return None;
+    }
+
+    let split_at = chunks[id].position() + remaining / 2;
+    let end = chunks[id].end;
+    chunks[id].end = split_at - 1;
+    chunks.push(ChunkState { start: split_at, end, downloaded: 0 });
+    Some(chunks.len() - 1)
+}
+
+// Roda os chunks com um número de conexões ajustado pela velocidade medida.
+// Retorna o primeiro erro de chunk, se houver.
+async fn run_connections(ctx: &Arc<ChunkContext>) -> Option<Failure> {
+    let mut workers = tokio::task::JoinSet::new();
+    let mut assigned: Vec<bool> = Vec::new();
+    let mut tuner = ConnectionTuner::new();
+    let mut first_error = None;
+
+    let max_connections = || ctx.download_task.lock().map(|t| t.max_connections.max(1)).unwrap_or(DEFAULT_MAX_CONNECTIONS);
+    let initial = ctx.download_task.lock().ok().and_then(|t| t.initial_connections).unwrap_or(INITIAL_CONNECTIONS);
+    let mut target = initial.clamp(1, max_connections());
+
+    // Medição da velocidade agregada
+    let downloaded_now = |chunks: &[ChunkState]| chunks.iter().map(|c| c.downloaded).sum::<u64>();
+    let mut last_total = downloaded_now(&ctx.chunks.lock().await);
+    let mut last_measure = Instant::now();
+    let mut interval = tokio::time::interval(Duration::from_millis(TUNE_INTERVAL_MS));
+    interval.tick().await;
+
+    loop {
+        // Mantém `target` conexões ocupadas enquanto houver trabalho (e nenhum chunk tiver falhado)
+        if first_error.is_none() {
+            while workers.len() < target {
+                let chunk_id = {
+                    let mut chunks = ctx.chunks.lock().await;
+                    next_chunk(&mut chunks, &assigned)
+                };
+                let Some(chunk_id) = chunk_id else { break };
+                if assigned.len() <= chunk_id {
+                    assigned.resize(chunk_id + 1, false);
+                }
+                assigned[chunk_id] = true;
+                let ctx = ctx.clone();
+                workers.spawn(async move { download_chunk(&ctx, chunk_id).await });
+            }
+        }
+
+        set_open_connections(ctx, workers.len());
+        if workers.is_empty() {
+            break;
+        }
+
+        tokio::select! {
+            joined = workers.join_next() => {
+                let result = match joined {
+                    Some(Ok(result)) => result,
+                    Some(Err(e)) => Err(Failure::other(format!("Erro ao aguardar chunk: {:?}", e))),
+                    None => continue,
+                };
+                if let Err(e) = result {
+                    eprintln!("Erro no chunk: {}", e);
+                    first_error.get_or_insert(e);
+                }
+            }
+            _ = interval.tick() => {
+                check_stalled(&ctx.download_task, &ctx.tx);
+                let total = downloaded_now(&ctx.chunks.lock().await);
+                let elapsed = last_measure.elapsed().as_secs_f64();
+                let paused = ctx.download_task.lock().map(|t| t.is_paused()).unwrap_or(false);
+                // Pausado não conta como medição
+                if !paused && elapsed > 0.0 && workers.len() >= target {
+                    let speed = total.saturating_sub(last_total) as f64 / elapsed;
+                    if tuner.should_add(speed, target, max_connections()) {
+                        target += 1;
+                        log_event(&ctx.download_task, format!("Conexão extra: {} conexões ({})", target, format_speed(speed)));
+                    }
+                }
+                last_total = total;
+                last_measure = Instant::now();
+            }
+        }
+    }
+
+    first_error
+}
+
+fn log_event(download_task: &Arc<Mutex<DownloadTask>>, text: impl Into<String>) {
+    if let Ok(mut task) = download_task.lock() {
+        task.log(text);
+    }
+}
+
+fn note_mirror_ranking(ctx: &ChunkContext, changed: bool) {
+    if changed {
+        log_event(&ctx.download_task, format!("Espelhos reordenados: {}", ctx.mirrors.urls().join(", ")));
+    }
+}
+
+fn set_open_connections(ctx: &ChunkContext, count: usize) {
+    if let Ok(mut task) = ctx.download_task.lock() {
+        task.connections = count;
+        task.peak_connections = task.peak_connections.max(count);
+    }
+}
+
+// Motivo pelo qual uma tentativa de chunk parou
+enum ChunkFailure {
+    Cancelled,
+    Fatal(Failure),           // Não adianta tentar de novo (ex.: erro de disco, 404)
+    Retry(HttpError, bool),   // Erro de rede; bool = houve progresso nesta tentativa
+    Switch,                   // O espelho saiu do rodízio: continua logo em outro
+}
+
+// Baixa um chunk, tentando novamente a partir do ponto onde parou.
+// Só desiste depois de MAX_RETRIES falhas seguidas sem progresso.
+pub async fn download_chunk(ctx: &ChunkContext, chunk_id: usize) -> Result<(), Failure> {
+    let mut buffer = ctx.buffers.take();
+    let result = download_chunk_with(ctx, chunk_id, &mut buffer).await;
+    ctx.buffers.give_back(buffer);
+    result
+}
+
+async fn download_chunk_with(ctx: &ChunkContext, chunk_id: usize, buffer: &mut bytes::BytesMut) -> Result<(), Failure> {
+    let mut failures = 0;
+    let mut busy_waits = 0;
+
+    loop {
+        let chunk = ctx.chunks.lock().await[chunk_id];
+        if chunk.is_complete() {
+            return Ok(());
+        }
+
+        match fetch_chunk_range(ctx, chunk_id, chunk, buffer).await {
+            Ok(()) => return Ok(()),
+            Err(ChunkFailure::Cancelled) => return Err(Failure::other("Cancelado")),
+            Err(ChunkFailure::Fatal(e)) => return Err(e),
+            Err(ChunkFailure::Switch) => failures = 0,
+            // Servidor em manutenção: espera mais, sem contar como falha
+            Err(ChunkFailure::Retry(e, _)) if e.kind == HttpErrorKind::ServerBusy && busy_waits < MAX_BUSY_WAITS => {
+                let delay = busy_delay(busy_waits, e.retry_after);
+                busy_waits += 1;
+                retry_notice(&ctx.tx, Some(&ctx.download_task))(&e, delay);
+                log_event(&ctx.download_task, format!("Chunk {}: servidor ocupado, nova tentativa em {}s", chunk_id, delay.as_secs()));
+                if !retry_wait(ctx, delay).await {
+                    return Err(Failure::other("Cancelado"));
+                }
+            }
+            Err(ChunkFailure::Retry(e, progressed)) => {
+                failures = if progressed { 1 } else { failures + 1 };
+                if failures >= MAX_RETRIES {
+                    return Err(Failure::http(&format!("Chunk {} falhou após {} tentativas", chunk_id, MAX_RETRIES), &e));
+                }
+
+                // Respeita o Retry-After; senão, delay exponencial: 2s, 4s...
+                let delay = e
+                    .retry_after
+                    .map(|wait| wait.min(Duration::from_secs(MAX_RETRY_AFTER_SECS)))
+                    .unwrap_or_else(|| Duration::from_secs(RETRY_DELAY_SECS * (1 << (failures - 1))));
+                retry_notice(&ctx.tx, Some(&ctx.download_task))(&e, delay);
+                let reason = if e.kind == HttpErrorKind::RateLimited { "limite de requisições".to_string() } else { e.to_string() };
+                log_event(&ctx.download_task, format!("Chunk {}: nova tentativa em {}s ({})", chunk_id, delay.as_secs(), reason));
+                if !retry_wait(ctx, delay).await {
+                    return Err(Failure::other("Cancelado"));
+                }
+            }
+        }
+    }
+}
+
+// Espera antes de tentar o chunk de novo. Pausa, cancelamento e fechamento do app
+// cortam a espera; depois de uma pausa tenta logo. false se deve parar.
+async fn retry_wait(ctx: &ChunkContext, delay: Duration) -> bool {
+    if sleep_unless(delay, || task_stopped(&ctx.download_task)).await {
+        return !wait_while_paused(&ctx.download_task).await;
+    }
+    true
+}
+
+// Uma tentativa: pede o intervalo restante do chunk e grava até o fim ou até um erro
+// Os pedaços da rede se juntam em `buffer` e vão para o disco a cada
+// CHUNK_BUFFER_SIZE; pedaços maiores que isso são gravados direto, sem cópia.
+async fn fetch_chunk_range(
+    ctx: &ChunkContext,
+    chunk_id: usize,
+    chunk: ChunkState,
+    buffer: &mut bytes::BytesMut,
+) -> Result<(), ChunkFailure> {
+    let url = ctx.mirrors.pick().unwrap_or_else(|| ctx.url.clone());
+    let request = HttpRequest::get(&url).range(chunk.position(), Some(chunk.end));
+    let response = match send_request(ctx.backend.as_ref(), request).await {
+        Ok(response) => response,
+        Err(e) if e.is_recoverable() => {
+            note_mirror_ranking(ctx, ctx.mirrors.record_failure(&url));
+            return Err(ChunkFailure::Retry(e, false));
+        }
+        Err(e) => return Err(ChunkFailure::Fatal(Failure::http("Erro na requisição", &e))),
+    };
+
+    // Um 200 só serve se o intervalo pedido for o arquivo inteiro
+    let whole_file = chunk.position() == 0 && chunk.end + 1 == ctx.total_size;
+    let status = response.status();
+    if status != StatusCode::PARTIAL_CONTENT && !(status.is_success() && whole_file) {
+        // Com espelhos, o problema pode ser só deste endereço
+        if ctx.mirrors.remove(&url) {
+            log_event(&ctx.download_task, format!("Espelho removido: {} (status HTTP: {})", url, status));
+            return Err(ChunkFailure::Switch);
+        }
+        return Err(ChunkFailure::Fatal(Failure::new(FailureKind::from_status(status.as_u16()), format!("Status HTTP: {}", status))));
+    }
+    // O HEAD informou outro tamanho: o plano de chunks está errado e é refeito
+    if let Some(real) = response_total(&response).filter(|real| *real != ctx.total_size) {
+        *ctx.corrected_size.lock().await = Some(real);
+        return Err(ChunkFailure::Fatal(Failure::other(size_correction_text(real, ctx.total_size))));
+    }
+
+    let mut stream = response.bytes_stream();
+    // Posição já gravada no disco; o que está em `buffer` vem logo depois
+    let mut current_pos = chunk.position();
+    buffer.clear();
+    let mut progressed = false;
+    // Velocidade deste espelho, medida a cada TUNE_INTERVAL_MS
+    let mut sample_start = Instant::now();
+    let mut sample_bytes = 0u64;
+
+    while let Some(chunk_result) = stream.next().await {
+        // Verifica cancelamento/pausa
+        loop {
+            let (cancelled, paused) = {
+                if let Ok(mut task) = ctx.download_task.lock() {
+                    (task.is_cancelled() || task.shutdown, task.poll_paused())
+                } else {
+                    (false, false)
+                }
+            };
+
+            if cancelled {
+                // No encerramento do app o download continua depois deste ponto
+                flush_chunk_buffer(ctx, chunk_id, buffer, &mut current_pos).await?;
+                return Err(ChunkFailure::Cancelled);
+            }
+
+            if !paused {
+                break;
+            }
+
+            // Pausado: o mapa de chunks precisa refletir tudo o que já chegou
+            flush_chunk_buffer(ctx, chunk_id, buffer, &mut current_pos).await?;
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        let data = match chunk_result {
+            Ok(data) => data,
+            // Corpo interrompido: a próxima tentativa continua de current_pos
+            Err(e) => {
+                flush_chunk_buffer(ctx, chunk_id, buffer, &mut current_pos).await?;
+                return Err(ChunkFailure::Retry(e, progressed));
+            }
+        };
+
+        // Nunca grava além do fim do chunk (servidores que ignoram o fim do Range).
+        // O fim pode ter diminuído se o controlador dividiu o chunk para outra conexão.
+        let chunk_end = ctx.chunks.lock().await[chunk_id].end;
+        let received_pos = current_pos + buffer.len() as u64;
+        let remaining = (chunk_end + 1).saturating_sub(received_pos) as usize;
+        let data = data.slice(..data.len().min(remaining));
+        if data.is_empty() {
+            break;
+        }
+        let received = data.len() as u64;
+
+        if buffer.is_empty() && data.len() >= CHUNK_BUFFER_SIZE {
+            write_chunk_data(ctx, chunk_id, data, &mut current_pos).await?;
+        } else {
+            buffer.extend_from_slice(&data);
+            if buffer.len() >= CHUNK_BUFFER_SIZE {
+                flush_chunk_buffer(ctx, chunk_id, buffer, &mut current_pos).await?;
+            }
+        }
+
+        count_usage(&ctx.download_task, received);
+        throttle(&ctx.download_task, received).await;
+        progressed = true;
+        sample_bytes += received;
+        if sample_start.elapsed() >= Duration::from_millis(TUNE_INTERVAL_MS) {
+            let speed = sample_bytes as f64 / sample_start.elapsed().as_secs_f64();
+            note_mirror_ranking(ctx, ctx.mirrors.record_speed(&url, speed));
+            sample_start = Instant::now();
+            sample_bytes = 0;
+        }
+
+        report_parallel_progress(ctx).await;
+
+        // Espelho ficou lento demais para o rodízio: o resto do chunk vai para outro
+        if !ctx.mirrors.is_active(&url) && current_pos + (buffer.len() as u64) <= ctx.chunks.lock().await[chunk_id].end {
+            flush_chunk_buffer(ctx, chunk_id, buffer, &mut current_pos).await?;
+            log_event(&ctx.download_task, format!("Chunk {}: trocando de espelho ({} ficou lento)", chunk_id, url));
+            return Err(ChunkFailure::Switch);
+        }
+    }
+
+    flush_chunk_buffer(ctx, chunk_id, buffer, &mut current_pos).await?;
+    if current_pos <= ctx.chunks.lock().await[chunk_id].end {
+        // Conexão encerrada antes do fim do intervalo
+        return Err(ChunkFailure::Retry(
+            HttpError::new(HttpErrorKind::Body, "Resposta terminou antes do fim do chunk"),
+            progressed,
+        ));
+    }
+
+    Ok(())
+}
+
+// Grava o conteúdo do buffer da conexão e deixa o mesmo espaço pronto para reuso
+async fn flush_chunk_buffer(
+    ctx: &ChunkContext,
+    chunk_id: usize,
+    buffer: &mut bytes::BytesMut,
+    current_pos: &mut u64,
+) -> Result<(), ChunkFailure> {
+    if buffer.is_empty() {
+        return Ok(());
+    }
+    let data = buffer.split().freeze();
+    write_chunk_data(ctx, chunk_id, data, current_pos).await?;
+    // A escrita terminou e soltou `data`: o reserve recupera a mesma alocação
+    buffer.reserve(CHUNK_BUFFER_SIZE);
+    Ok(())
+}
+
+// Escreve no arquivo na posição correta e atualiza o progresso do chunk
+// (limitado ao fim atual, caso tenha sido dividido durante a escrita)
+async fn write_chunk_data(
+    ctx: &ChunkContext,
+    chunk_id: usize,
+    data: bytes::Bytes,
+    current_pos: &mut u64,
+) -> Result<(), ChunkFailure> {
+    let written = data.len() as u64;
+    ctx.file
+        .write_at(*current_pos, data)
+        .await
+        .map_err(|e| ChunkFailure::Fatal(Failure::disk(format!("Erro ao escrever arquivo: {}", e))))?;
+    *current_pos += written;
+
+    let mut chunks = ctx.chunks.lock().await;
+    let state = &mut chunks[chunk_id];
+    state.downloaded = (*current_pos - state.start).min(state.end + 1 - state.start);
+    Ok(())
+}
+
+// Envia o progresso total e salva o mapa de chunks a cada 200ms
+async fn report_parallel_progress(ctx: &ChunkContext) {
+    let mut last_update_guard = ctx.last_update.lock().await;
+    if last_update_guard.elapsed().as_millis() < 200 {
+        return;
+    }
+
+    let chunks = ctx.chunks.lock().await.clone();
+    let total_downloaded: u64 = chunks.iter().map(|c| c.downloaded).sum();
+    let total_size = ctx.total_size;
+
+    let mut last_downloaded_guard = ctx.last_downloaded.lock().await;
+    let elapsed_secs = last_update_guard.elapsed().as_secs_f64();
+    let speed_bytes = if elapsed_secs > 0.0 {
+        (total_downloaded as f64 - *last_downloaded_guard as f64) / elapsed_secs
+    } else {
+        0.0
+    };
+    send_progress(&ctx.tx, &ctx.download_task, total_downloaded, total_size, speed_bytes, true).await;
+
+    if let Err(e) = save_chunk_map(&ctx.map_path, total_size, &chunks).await {
+        eprintln!("Erro ao salvar mapa de chunks: {}", e);
+    }
+    if let Ok(mut task) = ctx.download_task.lock() {
+        task.downloaded = total_downloaded;
+    }
+
+    *last_update_guard = Instant::now();
+    *last_downloaded_guard = total_downloaded;
+}
+
+pub async fn download_sequential(
+    backend: &SharedBackend,
+    url: &str,
+    temp_path: &Path,
+    file_path: &Path,
+    mut total_size: u64,
+    tx: &async_channel::Sender<DownloadMessage>,
+    download_task: &Arc<Mutex<DownloadTask>>,
+) {
+    use tokio::io::AsyncWriteExt;
+
+    // Sequencial nunca usa chunks paralelos
+    let parallel_chunks = false;
+    // Verifica se existe arquivo parcial para resume
+    let mut downloaded = if temp_path.exists() {
+        std::fs::metadata(temp_path).map(|m| m.len()).unwrap_or(0)
+    } else {
+        0
+    };
+
+    // Abre ou cria arquivo para escrita (tokio: a escrita não trava o runtime)
+    let opened = if downloaded > 0 {
+        tokio::fs::OpenOptions::new().append(true).open(temp_path).await
+    } else {
+        tokio::fs::File::create(temp_path).await
+    };
+    let file = match opened {
+        Ok(f) => f,
+        Err(e) => {
+            fail(tx, download_task, Failure::disk(format!("Erro ao criar arquivo: {}", e))).await;
+            return;
+        }
+    };
+    if downloaded == 0 {
+        mark_partial_origin(temp_path, url, download_task);
+    }
+    // Pedaços da rede se acumulam no buffer e vão para o disco a cada `flush_interval`
+    let mut file = tokio::io::BufWriter::with_capacity(WRITE_BUFFER_SIZE, file);
+    let flush_interval = download_task
+        .lock()
+        .map(|task| task.flush_interval)
+        .unwrap_or(Duration::from_secs(DEFAULT_FLUSH_INTERVAL_SECS));
+    let mut last_flush = Instant::now();
+
+    // Faz requisição com Range header para resume (com retry)
+    let downloaded_bytes = downloaded;
+    let response = match retry_task_request(|| {
+        let mut req = HttpRequest::get(url);
+        if downloaded_bytes > 0 {
+            req = req.range(downloaded_bytes, None);
+        }
+        send_request(backend.as_ref(), req)
+    }, tx, Some(download_task)).await {
+        Ok(resp) => resp,
+        Err(e) if e.kind == HttpErrorKind::Interrupted => {
+            drop(file);
+            stop_interrupted(tx, download_task, Some(temp_path)).await;
+            return;
+        }
+        Err(e) => {
+            fail(tx, download_task, Failure::http(&format!("Erro na requisição após {} tentativas", MAX_RETRIES), &e)).await;
+            return;
+        }
+    };
+
+    if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        let status = response.status();
+        fail(tx, download_task, Failure::new(FailureKind::from_status(status.as_u16()), format!("Status HTTP: {}", status))).await;
+        return;
+    }
+
+    // Pediu a partir do meio e veio o arquivo inteiro: o .part recomeça do zero
+    // (acrescentar no fim corromperia o arquivo)
+    if downloaded > 0 && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        log_event(download_task, "Servidor ignorou o Range: recomeçando do início");
+        if let Err(e) = file.get_ref().set_len(0).await {
+            fail(tx, download_task, Failure::disk(format!("Erro ao escrever: {}", e))).await;
+            return;
+        }
+        downloaded = 0;
+    }
+
+    // O tamanho do GET vale mais que o da consulta inicial (HEAD errado por
+    // compressão ou link assinado) e preenche o que ela não informou; resposta
+    // chunked continua sem e o fim do corpo marca a conclusão
+    let length = content_length(response.headers());
+    let reported = content_range_total(response.headers()).or((length > 0).then_some(downloaded + length));
+    if let Some(real) = reported.filter(|real| *real != total_size) {
+        if total_size == 0 {
+            log_event(download_task, format!("Tamanho informado na resposta: {}", format_bytes(real)));
+        } else {
+            log_event(download_task, size_correction_text(real, total_size));
+        }
+        total_size = real;
+    }
+
+    // A consulta (HEAD) pode não ter mostrado o Content-Encoding que o GET trouxe
+    let probed_encoding = download_task
+        .lock()
+        .map(|task| task.response.as_ref().is_some_and(|probed| probed.header("content-encoding").is_some()))
+        .unwrap_or(true);
+    if !probed_encoding {
+        let filename = file_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let content_type = response.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+        let content_type = content_type.and_then(|v| v.split(';').next()).map(str::trim);
+        note_body_encoding(download_task, decoding::content_encoding(response.headers()).as_deref(), &filename, content_type);
+    }
+
+    // Stream de download
+    let mut stream = response.bytes_stream();
+    let mut last_update = Instant::now();
+    let mut last_downloaded = downloaded;
+
+    // Envia progresso inicial se estiver retomando
+    if downloaded > 0 && total_size > 0 {
+        send_progress(tx, download_task, downloaded, total_size, 0.0, parallel_chunks).await;
+    }
+
+    loop {
+        // Sem dados por um tempo: mostra como parado e continua esperando
+        let next = match tokio::time::timeout(Duration::from_secs(STALLED_AFTER_SECS), stream.next()).await {
+            Ok(next) => next,
+            Err(_) => {
+                check_stalled(download_task, tx);
+                continue;
+            }
+        };
+        let Some(chunk_result) = next else {
+            break;
+        };
+        // Verifica se foi cancelado ou está pausado
+        let mut flushed_for_pause = false;
+        loop {
+            let (cancelled, paused, shutdown) = {
+                if let Ok(mut task) = download_task.lock() {
+                    (task.is_cancelled(), task.poll_paused(), task.shutdown)
+                } else {
+                    (false, false, false)
+                }
+            };
+
+            if shutdown {
+                // Descarrega o buffer: o .part fica com tudo até aqui e retoma dele na próxima vez
+                if let Err(e) = flush_to_disk(&mut file).await {
+                    eprintln!("Erro ao descarregar arquivo: {}", e);
+                }
+                if let Ok(mut task) = download_task.lock() {
+                    task.downloaded = downloaded;
+                }
+                return;
+            }
+
+            if cancelled {
+                drop(file);
+                let _ = std::fs::remove_file(temp_path);
+                let _ = tx.send(DownloadMessage::Cancelled).await;
+                return;
+            }
+
+            if !paused {
+                break;
+            }
+
+            // Pausado: o que já chegou vai para o disco antes de esperar
+            if !flushed_for_pause {
+                if let Err(e) = flush_to_disk(&mut file).await {
+                    eprintln!("Erro ao descarregar arquivo: {}", e);
+                }
+                if let Ok(mut task) = download_task.lock() {
+                    task.downloaded = downloaded;
+                }
+                flushed_for_pause = true;
+            }
+
+            // Aguarda enquanto pausado
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        let chunk = match chunk_result {
+            Ok(c) => c,
+            Err(e) => {
+                // Erro durante stream - não tenta retry aqui (já foi feito na requisição inicial).
+                // O buffer vai para o disco para a retomada continuar do ponto certo.
+                if let Err(e) = flush_to_disk(&mut file).await {
+                    eprintln!("Erro ao descarregar arquivo: {}", e);
+                }
+                fail(tx, download_task, Failure::http("Erro ao baixar", &e)).await;
+                return;
+            }
+        };
+
+        if let Err(e) = file.write_all(&chunk).await {
+            fail(tx, download_task, Failure::disk(format!("Erro ao escrever: {}", e))).await;
+            return;
+        }
+        if last_flush.elapsed() >= flush_interval {
+            if let Err(e) = flush_to_disk(&mut file).await {
+                fail(tx, download_task, Failure::disk(format!("Erro ao escrever: {}", e))).await;
+                return;
+            }
+            last_flush = Instant::now();
+            // Tudo até aqui está no disco: é esse o ponto que o salvamento automático grava
+            if let Ok(mut task) = download_task.lock() {
+                task.downloaded = downloaded + chunk.len() as u64;
+            }
+        }
+
+        count_usage(download_task, chunk.len() as u64);
+        throttle(download_task, chunk.len() as u64).await;
+        downloaded += chunk.len() as u64;
+
+        // Atualiza progresso a cada 200ms
+        if last_update.elapsed().as_millis() >= 200 {
+            let speed_bytes = (downloaded - last_downloaded) as f64 / last_update.elapsed().as_secs_f64();
+            send_progress(tx, download_task, downloaded, total_size, speed_bytes, parallel_chunks).await;
+
+            last_update = Instant::now();
+            last_downloaded = downloaded;
+        }
+    }
+
+    // Download completo - descarrega o buffer e renomeia arquivo
+    if let Err(e) = flush_to_disk(&mut file).await {
+        fail(tx, download_task, Failure::disk(format!("Erro ao escrever: {}", e))).await;
+        return;
+    }
+    drop(file);
+    finish_download(backend, url, temp_path, file_path, total_size, tx, download_task).await;
+}
+
+// Esvazia o buffer e espera o sistema gravar os dados (fsync)
+async fn flush_to_disk(file: &mut tokio::io::BufWriter<tokio::fs::File>) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    file.flush().await?;
+    file.get_ref().sync_data().await
+}
+
+// Formatos que os players conseguem abrir ainda incompletos (lidos do início)
+pub fn is_streamable_media(filename: &str) -> bool {
+    const EXTENSIONS: [&str; 15] = [
+        "mp4", "m4v", "mkv", "webm", "mov", "avi", "ts", "mpg", "mpeg", "mp3", "m4a", "ogg", "opus", "flac", "wav",
+    ];
+    filename
+        .rsplit_once('.')
+        .map(|(_, ext)| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+pub fn format_speed(bytes_per_sec: f64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+
+    if bytes_per_sec >= MB {
+        format!("{:.2} MB/s", bytes_per_sec / MB)
+    } else if bytes_per_sec >= KB {
+        format!("{:.2} KB/s", bytes_per_sec / KB)
+    } else {
+        format!("{:.0} B/s", bytes_per_sec)
+    }
+}
+
+pub fn format_eta(seconds: f64) -> String {
+    if seconds.is_infinite() || seconds.is_nan() || seconds < 0.0 {
+        return String::new();
+    }
+
+    let total_seconds = seconds as u64;
+
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let secs = total_seconds % 60;
+
+    if hours > 0 {
+        format!("{}h {}min", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}min {}s", minutes, secs)
+    } else if secs > 0 {
+        format!("{}s", secs)
+    } else {
+        "< 1s".to_string()
+    }
+}
\ No newline at end of file
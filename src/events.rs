@@ -0,0 +1,64 @@
+// Barramento de eventos do ciclo de vida dos downloads
+//
+// O motor publica início, progresso, mudanças de estado, conclusão e falha; a
+// janela publica quando um download é adicionado ou removido. Cada módulo
+// interessado assina o barramento e recebe os eventos a partir dali, sem
+// precisar de clones passados de closure em closure. Quem fica para trás perde
+// os mais antigos (o canal guarda EVENT_BUS_CAPACITY) em vez de segurar os downloads.
+use std::path::PathBuf;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use crate::engine::{DownloadState, ProgressUpdate};
+
+pub const EVENT_BUS_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadEvent {
+    Added { id: Uuid, url: String, filename: String },
+    Started(Uuid),
+    Progress(Uuid, ProgressUpdate),
+    StateChanged(Uuid, DownloadState),
+    Completed { id: Uuid, path: PathBuf },
+    Failed { id: Uuid, error: String },
+    Removed(Uuid),
+}
+
+impl DownloadEvent {
+    pub fn id(&self) -> Uuid {
+        match self {
+            DownloadEvent::Added { id, .. }
+            | DownloadEvent::Completed { id, .. }
+            | DownloadEvent::Failed { id, .. } => *id,
+            DownloadEvent::Started(id)
+            | DownloadEvent::Progress(id, _)
+            | DownloadEvent::StateChanged(id, _)
+            | DownloadEvent::Removed(id) => *id,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DownloadEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus { sender: broadcast::channel(EVENT_BUS_CAPACITY).0 }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DownloadEvent> {
+        self.sender.subscribe()
+    }
+
+    // Sem assinantes o evento é descartado
+    pub fn publish(&self, event: DownloadEvent) {
+        let _ = self.sender.send(event);
+    }
+}
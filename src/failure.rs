@@ -0,0 +1,138 @@
+// Triagem das falhas: o que deu errado e o que fazer em seguida
+//
+// O motor decide a categoria onde o erro acontece (status HTTP, tipo do erro de
+// rede, operação no disco) e a manda junto com a mensagem. Falhas passageiras
+// (rede, servidor sobrecarregado) valem uma nova tentativa; as permanentes
+// (arquivo removido, acesso negado, problema no disco) precisam de uma ação do
+// usuário, sugerida no aviso da falha.
+use std::fmt;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use crate::http::{HttpError, HttpErrorKind};
+
+// Parâmetros de links assinados com prazo (S3, Google Cloud, Azure, CloudFront, CDNs)
+const SIGNATURE_PARAMS: [&str; 10] = [
+    "x-amz-signature",
+    "x-amz-expires",
+    "x-goog-signature",
+    "signature",
+    "expires",
+    "key-pair-id",
+    "sig",
+    "se",
+    "token",
+    "hdnts",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    Gone,         // 404/410: o arquivo não existe mais nesse endereço
+    AuthRequired, // 401/403/407: precisa de credenciais ou de um link novo
+    Network,      // Conexão, timeout ou corpo interrompido depois das tentativas
+    Server,       // 429/5xx: servidor sobrecarregado ou em manutenção
+    Disk,         // Pasta, espaço ou permissão no computador
+    Other,
+}
+
+impl FailureKind {
+    // Status HTTP, quando o servidor respondeu; senão o tipo do erro de rede
+    pub fn from_http(error: &HttpError) -> Self {
+        if let Some(status) = error.status {
+            return FailureKind::from_status(status);
+        }
+        match error.kind {
+            HttpErrorKind::Timeout | HttpErrorKind::Connect | HttpErrorKind::Request | HttpErrorKind::Body => FailureKind::Network,
+            HttpErrorKind::RateLimited | HttpErrorKind::ServerBusy => FailureKind::Server,
+            HttpErrorKind::Interrupted | HttpErrorKind::Other => FailureKind::Other,
+        }
+    }
+
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            404 | 410 => FailureKind::Gone,
+            401 | 403 | 407 => FailureKind::AuthRequired,
+            429 | 500..=599 => FailureKind::Server,
+            _ => FailureKind::Other,
+        }
+    }
+
+    // Texto do badge do card
+    pub fn label(self) -> &'static str {
+        match self {
+            FailureKind::Gone => "Arquivo não existe mais no servidor",
+            FailureKind::AuthRequired => "Acesso negado: requer credenciais",
+            FailureKind::Network => "Falha de rede",
+            FailureKind::Server => "Servidor indisponível",
+            FailureKind::Disk => "Erro no disco",
+            FailureKind::Other => "Falhou",
+        }
+    }
+
+    // Tentar de novo mais tarde pode dar certo sem mudar nada
+    pub fn is_transient(self) -> bool {
+        matches!(self, FailureKind::Network | FailureKind::Server)
+    }
+
+    // Próximo passo sugerido no aviso da falha
+    pub fn action_label(self) -> Option<&'static str> {
+        match self {
+            FailureKind::AuthRequired => Some("Adicionar credenciais"),
+            FailureKind::Network | FailureKind::Server => Some("Tentar novamente"),
+            FailureKind::Disk => Some("Escolher outra pasta"),
+            FailureKind::Gone | FailureKind::Other => None,
+        }
+    }
+}
+
+// Falha de um download: a categoria e a mensagem mostrada ao usuário
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(kind: FailureKind, message: impl Into<String>) -> Self {
+        Failure { kind, message: message.into() }
+    }
+
+    // Leitura/escrita local (pasta, espaço, permissão)
+    pub fn disk(message: impl Into<String>) -> Self {
+        Failure::new(FailureKind::Disk, message)
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Failure::new(FailureKind::Other, message)
+    }
+
+    // "Erro na requisição: <erro>", com a categoria do erro HTTP
+    pub fn http(context: &str, error: &HttpError) -> Self {
+        Failure::new(FailureKind::from_http(error), format!("{}: {}", context, error))
+    }
+
+    // Mesma categoria, com a mensagem precedida de `context`
+    pub fn within(self, context: &str) -> Self {
+        Failure::new(self.kind, format!("{}: {}", context, self.message))
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Link com assinatura ou prazo na query: vence sozinho depois de um tempo
+pub fn is_signed_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| {
+        url.query_pairs()
+            .any(|(key, _)| SIGNATURE_PARAMS.contains(&key.to_ascii_lowercase().as_str()))
+    })
+}
+
+// Acesso negado a um link assinado que já tinha entregado dados: o prazo venceu
+// e um link novo do mesmo arquivo pode continuar do ponto em que parou
+pub fn link_expired(kind: FailureKind, url: &str, downloaded: u64) -> bool {
+    kind == FailureKind::AuthRequired && downloaded > 0 && is_signed_url(url)
+}
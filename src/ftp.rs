@@ -0,0 +1,371 @@
+// URLs `ftp://` pelo protocolo FTP (RFC 959), com o mínimo que o motor precisa:
+// HEAD vira SIZE/MDTM, GET com Range vira REST/RETR e o GET de uma pasta (URL
+// terminada em "/") devolve a saída do LIST, lida por `listing::parse_ftp_list`.
+// Cada requisição abre a sua conexão de controle, então os chunks de um download
+// são conexões separadas. Sem FTPS e sem proxy: com proxy, PAC ou Tor configurado
+// o endereço é recusado (ver `FtpBackend::proxied`).
+use std::future::Future;
+use std::net::IpAddr;
+use std::time::Duration;
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::{stream, FutureExt, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, IF_MODIFIED_SINCE, LAST_MODIFIED, RANGE};
+use reqwest::{StatusCode, Url, Version};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use crate::http::{percent_decode, BodyStream, HttpBackend, HttpError, HttpErrorKind, HttpMethod, HttpRequest, HttpResponse, SharedBackend};
+use crate::listing::MAX_INDEX_SIZE;
+
+pub const DEFAULT_PORT: u16 = 21;
+// Espera máxima por uma resposta do servidor ou por dados da transferência
+const TIMEOUT: Duration = Duration::from_secs(30);
+const READ_BUFFER: usize = 64 * 1024;
+
+pub fn is_ftp_url(url: &str) -> bool {
+    url.starts_with("ftp://")
+}
+
+// "Entering Passive Mode (192,168,0,1,195,80)" -> 50000 (alguns servidores omitem os parênteses)
+pub fn pasv_port(text: &str) -> Option<u16> {
+    let numbers: Vec<u16> = text
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| part.parse().ok())
+        .collect();
+    let [high, low] = numbers.get(numbers.len().checked_sub(2)?..)? else {
+        return None;
+    };
+    (numbers.len() >= 6 && *high < 256 && *low < 256).then(|| high * 256 + low)
+}
+
+// "Entering Extended Passive Mode (|||6446|)" -> 6446
+pub fn epsv_port(text: &str) -> Option<u16> {
+    let start = text.find("|||")? + 3;
+    let end = text[start..].find('|')? + start;
+    text[start..end].parse().ok()
+}
+
+// Resposta do MDTM ("20240131120000" ou com fração, "20240131120000.123"), sempre em UTC
+pub fn mdtm_date(text: &str) -> Option<DateTime<Utc>> {
+    let digits = text.trim().get(..14)?;
+    NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M%S").ok().map(|date| date.and_utc())
+}
+
+// Valor que vai para a conexão de controle: um "\r\n" decodificado da URL
+// ("%0D%0A") acrescentaria comandos (ftp://h/x%0D%0ADELE%20y)
+fn control_safe(value: String, what: &str) -> Result<String, HttpError> {
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(HttpError::new(HttpErrorKind::Request, &format!("Endereço FTP inválido: {} com quebra de linha ou NUL", what)));
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reply {
+    code: u16,
+    text: String, // Primeira linha, sem o código
+}
+
+// Resposta negativa vira o status HTTP equivalente, para o motor (novas tentativas)
+// e a triagem das falhas tratarem do mesmo jeito
+fn rejected(command: &str, reply: &Reply) -> HttpError {
+    // Só o comando: "PASS senha" não vai para a mensagem
+    let verb = command.split(' ').next().unwrap_or(command);
+    let message = format!("FTP {}: {} {}", verb, reply.code, reply.text);
+    let (kind, status) = match reply.code {
+        421 | 425 | 426 | 450 | 451 | 452 => (HttpErrorKind::ServerBusy, Some(503)),
+        530 | 532 => (HttpErrorKind::Other, Some(401)),
+        550 | 553 => (HttpErrorKind::Other, Some(404)),
+        _ => (HttpErrorKind::Other, None),
+    };
+    let mut error = HttpError::new(kind, &message);
+    error.status = status;
+    error
+}
+
+async fn timed<T>(future: impl Future<Output = std::io::Result<T>>, kind: HttpErrorKind, what: &str) -> Result<T, HttpError> {
+    match tokio::time::timeout(TIMEOUT, future).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(HttpError::new(kind, &format!("{}: {}", what, e))),
+        Err(_) => Err(HttpError::new(HttpErrorKind::Timeout, &format!("{}: tempo esgotado", what))),
+    }
+}
+
+// Conexão de controle já autenticada e em modo binário
+struct Session {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    peer: IpAddr,
+}
+
+impl Session {
+    async fn connect(url: &Url) -> Result<Session, HttpError> {
+        let host = url
+            .host_str()
+            .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+            .ok_or_else(|| HttpError::new(HttpErrorKind::Request, "Endereço FTP sem servidor"))?;
+        let port = url.port().unwrap_or(DEFAULT_PORT);
+        // Sem usuário na URL: acesso anônimo
+        let user = Some(control_safe(percent_decode(url.username()), "usuário")?).filter(|user| !user.is_empty());
+        let password = match url.password() {
+            Some(password) => control_safe(percent_decode(password), "senha")?,
+            None => "anonymous@".to_string(),
+        };
+        let stream = timed(TcpStream::connect((host, port)), HttpErrorKind::Connect, "Erro ao conectar ao FTP").await?;
+        let peer = stream
+            .peer_addr()
+            .map_err(|e| HttpError::new(HttpErrorKind::Connect, &e.to_string()))?
+            .ip();
+        let (reader, writer) = stream.into_split();
+        let mut session = Session { reader: BufReader::new(reader), writer, peer };
+
+        let greeting = session.reply().await?;
+        if greeting.code != 220 {
+            return Err(rejected("conexão", &greeting));
+        }
+        let reply = session.command(&format!("USER {}", user.as_deref().unwrap_or("anonymous"))).await?;
+        match reply.code {
+            230 => {}
+            331 | 332 => {
+                session.expect(&format!("PASS {}", password), &[202, 230]).await?;
+            }
+            _ => return Err(rejected("USER", &reply)),
+        }
+        session.expect("TYPE I", &[200]).await?;
+        Ok(session)
+    }
+
+    // Resposta completa; nas de várias linhas ("150-..." até "150 ...") fica a primeira
+    async fn reply(&mut self) -> Result<Reply, HttpError> {
+        let mut line = String::new();
+        self.read_line(&mut line).await?;
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| HttpError::new(HttpErrorKind::Request, &format!("Resposta FTP inválida: {}", line.trim_end())))?;
+        let text = line.get(4..).unwrap_or_default().trim_end().to_string();
+        if line.as_bytes().get(3) == Some(&b'-') {
+            let last = format!("{} ", code);
+            loop {
+                line.clear();
+                self.read_line(&mut line).await?;
+                if line.starts_with(&last) {
+                    break;
+                }
+            }
+        }
+        Ok(Reply { code, text })
+    }
+
+    async fn read_line(&mut self, line: &mut String) -> Result<(), HttpError> {
+        let read = timed(self.reader.read_line(line), HttpErrorKind::Request, "Erro ao ler resposta do FTP").await?;
+        if read == 0 {
+            return Err(HttpError::new(HttpErrorKind::Request, "O servidor FTP fechou a conexão"));
+        }
+        Ok(())
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Reply, HttpError> {
+        let line = format!("{}\r\n", command);
+        timed(self.writer.write_all(line.as_bytes()), HttpErrorKind::Request, "Erro ao enviar comando FTP").await?;
+        self.reply().await
+    }
+
+    async fn expect(&mut self, command: &str, codes: &[u16]) -> Result<Reply, HttpError> {
+        let reply = self.command(command).await?;
+        if codes.contains(&reply.code) {
+            Ok(reply)
+        } else {
+            Err(rejected(command, &reply))
+        }
+    }
+
+    // Conexão de dados passiva: EPSV e, se o servidor não tiver, PASV. O endereço
+    // anunciado no PASV é ignorado; vale o do servidor (NAT mal configurado, FTP bounce)
+    async fn data_connection(&mut self) -> Result<TcpStream, HttpError> {
+        let reply = self.command("EPSV").await?;
+        let port = match (reply.code == 229).then(|| epsv_port(&reply.text)).flatten() {
+            Some(port) => port,
+            None => {
+                let reply = self.expect("PASV", &[227]).await?;
+                pasv_port(&reply.text).ok_or_else(|| rejected("PASV", &reply))?
+            }
+        };
+        timed(TcpStream::connect((self.peer, port)), HttpErrorKind::Connect, "Erro na conexão de dados do FTP").await
+    }
+
+    // Saída do LIST da pasta, inteira (até `MAX_INDEX_SIZE`, o que passar disso é da listagem)
+    async fn list(mut self, path: &str) -> Result<Bytes, HttpError> {
+        let mut data = self.data_connection().await?;
+        self.expect(&format!("LIST {}", path), &[125, 150]).await?;
+        let mut listing = Vec::new();
+        let mut limited = (&mut data).take(MAX_INDEX_SIZE as u64 + 1);
+        timed(limited.read_to_end(&mut listing), HttpErrorKind::Body, "Erro ao ler a listagem do FTP").await?;
+        drop(data);
+        let reply = self.reply().await?;
+        if !matches!(reply.code, 226 | 250) {
+            return Err(rejected("LIST", &reply));
+        }
+        Ok(Bytes::from(listing))
+    }
+}
+
+pub struct FtpBackend {
+    inner: SharedBackend,
+    proxied: bool, // Client com proxy, PAC ou Tor: o FTP conectaria direto, fora dele
+}
+
+impl FtpBackend {
+    pub fn new(inner: SharedBackend) -> Self {
+        FtpBackend { inner, proxied: false }
+    }
+
+    pub fn proxied(mut self, proxied: bool) -> Self {
+        self.proxied = proxied;
+        self
+    }
+}
+
+impl HttpBackend for FtpBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        if !is_ftp_url(&request.url) {
+            return self.inner.send(request);
+        }
+        // Recusa em vez de vazar o IP real de um download que pediu proxy ou Tor
+        if self.proxied {
+            let message = "Downloads ftp:// não passam por proxy, PAC nem Tor; desative-os para baixar este endereço";
+            return futures_util::future::ready(Err(HttpError::new(HttpErrorKind::Other, message))).boxed();
+        }
+        ftp_request(request).boxed()
+    }
+}
+
+fn response(status: StatusCode, headers: HeaderMap, body: BodyStream) -> HttpResponse {
+    HttpResponse { status, version: Version::HTTP_11, headers, body }
+}
+
+fn empty_body() -> BodyStream {
+    stream::empty().boxed()
+}
+
+fn header_value(value: impl ToString) -> HeaderValue {
+    HeaderValue::from_str(&value.to_string()).unwrap_or(HeaderValue::from_static(""))
+}
+
+// "bytes=100-199" -> (100, Some(199)); "bytes=100-" -> (100, None)
+fn parse_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (start, end) = value.strip_prefix("bytes=")?.split_once('-')?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()))
+}
+
+async fn ftp_request(request: HttpRequest) -> Result<HttpResponse, HttpError> {
+    let url = Url::parse(&request.url)
+        .map_err(|e| HttpError::new(HttpErrorKind::Request, &format!("Endereço FTP inválido: {}", e)))?;
+    let path = control_safe(percent_decode(url.path()), "caminho")?;
+    let mut session = Session::connect(&url).await?;
+
+    if path.ends_with('/') {
+        let listing = session.list(&path).await?;
+        let body = match request.method {
+            HttpMethod::Head => empty_body(),
+            HttpMethod::Get => stream::once(async { Ok(listing) }).boxed(),
+        };
+        return Ok(response(StatusCode::OK, HeaderMap::new(), body));
+    }
+
+    // SIZE ausente (502) não impede o download, só o divide em chunks
+    let size_reply = session.command(&format!("SIZE {}", path)).await?;
+    let size = match size_reply.code {
+        213 => size_reply.text.trim().parse::<u64>().ok(),
+        550 => return Err(rejected("SIZE", &size_reply)),
+        _ => None,
+    };
+    let modified_reply = session.command(&format!("MDTM {}", path)).await?;
+    let modified = (modified_reply.code == 213).then(|| mdtm_date(&modified_reply.text)).flatten();
+
+    let mut headers = HeaderMap::new();
+    if let Some(modified) = modified {
+        headers.insert(LAST_MODIFIED, header_value(modified.format("%a, %d %b %Y %H:%M:%S GMT")));
+        let since = request
+            .header_value(IF_MODIFIED_SINCE.as_str())
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok());
+        if since.is_some_and(|since| modified <= since) {
+            return Ok(response(StatusCode::NOT_MODIFIED, headers, empty_body()));
+        }
+    }
+    if let Some(size) = size {
+        headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        if request.method == HttpMethod::Head {
+            headers.insert(CONTENT_LENGTH, header_value(size));
+        }
+    }
+    if request.method == HttpMethod::Head {
+        return Ok(response(StatusCode::OK, headers, empty_body()));
+    }
+
+    let range = request.header_value(RANGE.as_str()).and_then(parse_range).filter(|_| size.is_some());
+    if let (Some((start, _)), Some(size)) = (range, size) {
+        if start >= size {
+            headers.insert(CONTENT_RANGE, header_value(format!("bytes */{}", size)));
+            return Ok(response(StatusCode::RANGE_NOT_SATISFIABLE, headers, empty_body()));
+        }
+    }
+
+    // REST logo antes do RETR (alguns servidores esquecem a posição depois do EPSV)
+    let data = session.data_connection().await?;
+    let start = range.map(|(start, _)| start).unwrap_or(0);
+    let resumed = start == 0 || session.command(&format!("REST {}", start)).await?.code == 350;
+    session.expect(&format!("RETR {}", path), &[125, 150]).await?;
+
+    // Sem REST, o arquivo vem inteiro: um 200 comum, como o de um servidor HTTP sem Range
+    let (status, limit) = match (range, size) {
+        (Some((start, end)), Some(size)) if resumed => {
+            let end = end.unwrap_or(size - 1).min(size - 1);
+            headers.insert(CONTENT_RANGE, header_value(format!("bytes {}-{}/{}", start, end, size)));
+            headers.insert(CONTENT_LENGTH, header_value(end - start + 1));
+            (StatusCode::PARTIAL_CONTENT, Some(end - start + 1))
+        }
+        _ => {
+            if let Some(size) = size {
+                headers.insert(CONTENT_LENGTH, header_value(size));
+            }
+            (StatusCode::OK, None)
+        }
+    };
+    Ok(response(status, headers, transfer_body(data, session, limit)))
+}
+
+// Corpo lido da conexão de dados. Com `limit`, para ao completar o Range (a conexão
+// é fechada no meio e o servidor aborta a transferência); sem ele, espera o 226
+// no controle para saber se o arquivo chegou inteiro
+fn transfer_body(data: TcpStream, session: Session, limit: Option<u64>) -> BodyStream {
+    stream::unfold(Some((data, session, limit)), |state| async move {
+        let (mut data, mut session, remaining) = state?;
+        if remaining == Some(0) {
+            return None;
+        }
+        let size = remaining.map_or(READ_BUFFER, |remaining| READ_BUFFER.min(remaining as usize));
+        let mut buffer = vec![0; size];
+        match timed(data.read(&mut buffer), HttpErrorKind::Body, "Erro ao receber dados do FTP").await {
+            Err(e) => Some((Err(e), None)),
+            Ok(0) => match session.reply().await {
+                Ok(reply) if matches!(reply.code, 226 | 250) => None,
+                Ok(reply) => {
+                    let mut error = rejected("RETR", &reply);
+                    error.kind = HttpErrorKind::Body;
+                    Some((Err(error), None))
+                }
+                Err(e) => Some((Err(e), None)),
+            },
+            Ok(read) => {
+                buffer.truncate(read);
+                let remaining = remaining.map(|remaining| remaining - read as u64);
+                Some((Ok(Bytes::from(buffer)), Some((data, session, remaining))))
+            }
+        }
+    })
+    .boxed()
+}
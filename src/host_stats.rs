@@ -0,0 +1,171 @@
+// Histórico de cada servidor (hosts.json): velocidade média, falhas e suporte a Range
+//
+// Cada download concluído ou com falha entra na conta do host da URL. O resultado
+// aparece nas estatísticas, avisa no diálogo de adicionar quando o servidor costuma
+// falhar e decide com quantas conexões um download novo começa.
+use std::collections::BTreeMap;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+// Downloads necessários antes de julgar um servidor
+pub const MIN_SAMPLES: u32 = 3;
+// A partir desta taxa de falhas o servidor é considerado instável
+pub const FLAKY_ERROR_RATE: f64 = 0.4;
+// Conexões iniciais em servidores instáveis (menos requisições simultâneas para falhar)
+pub const FLAKY_CONNECTIONS: usize = 1;
+// A partir destas conexões o servidor limita cada uma: os downloads dele só
+// ficaram mais rápidos abrindo várias, então até arquivos pequenos vão em chunks
+pub const THROTTLED_CONNECTIONS: usize = 4;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostStats {
+    #[serde(default)]
+    pub completed: u32,
+    #[serde(default)]
+    pub failed: u32,
+    #[serde(default)]
+    pub speed_total: f64, // Soma das velocidades médias dos downloads concluídos (bytes/s)
+    #[serde(default)]
+    pub range_supported: u32, // Downloads em que o servidor aceitou Range
+    #[serde(default)]
+    pub range_refused: u32,
+    #[serde(default)]
+    pub connections: Option<usize>, // Conexões que o último download em chunks chegou a usar
+}
+
+impl HostStats {
+    pub fn attempts(&self) -> u32 {
+        self.completed + self.failed
+    }
+
+    pub fn average_speed(&self) -> Option<f64> {
+        (self.completed > 0).then(|| self.speed_total / self.completed as f64)
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.attempts() == 0 {
+            0.0
+        } else {
+            self.failed as f64 / self.attempts() as f64
+        }
+    }
+
+    pub fn is_flaky(&self) -> bool {
+        self.attempts() >= MIN_SAMPLES && self.error_rate() >= FLAKY_ERROR_RATE
+    }
+
+    pub fn limits_each_connection(&self) -> bool {
+        !self.is_flaky() && self.connections.is_some_and(|connections| connections >= THROTTLED_CONNECTIONS)
+    }
+
+    // Some(false) quando o servidor nunca aceitou Range
+    pub fn supports_range(&self) -> Option<bool> {
+        match (self.range_supported, self.range_refused) {
+            (0, 0) => None,
+            (supported, _) => Some(supported > 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostBook {
+    #[serde(default)]
+    pub hosts: BTreeMap<String, HostStats>,
+}
+
+impl HostBook {
+    pub fn get(&self, url: &str) -> Option<&HostStats> {
+        self.hosts.get(&host_of(url)?)
+    }
+
+    // `speed` é a velocidade média do download (0 se não houve medição)
+    pub fn record_success(&mut self, url: &str, speed: f64, supports_range: Option<bool>, connections: usize) {
+        let Some(host) = host_of(url) else {
+            return;
+        };
+        let stats = self.hosts.entry(host).or_default();
+        stats.completed += 1;
+        stats.speed_total += speed.max(0.0);
+        match supports_range {
+            Some(true) => stats.range_supported += 1,
+            Some(false) => stats.range_refused += 1,
+            None => {}
+        }
+        if connections > 0 {
+            stats.connections = Some(connections);
+        }
+    }
+
+    pub fn record_failure(&mut self, url: &str) {
+        if let Some(host) = host_of(url) {
+            self.hosts.entry(host).or_default().failed += 1;
+        }
+    }
+
+    // Soma `other` (os downloads da sessão, ao abrir a lista cifrada depois de baixar)
+    pub fn merge(&mut self, other: &HostBook) {
+        for (host, stats) in &other.hosts {
+            let merged = self.hosts.entry(host.clone()).or_default();
+            merged.completed += stats.completed;
+            merged.failed += stats.failed;
+            merged.speed_total += stats.speed_total;
+            merged.range_supported += stats.range_supported;
+            merged.range_refused += stats.range_refused;
+            if stats.connections.is_some() {
+                merged.connections = stats.connections;
+            }
+        }
+    }
+
+    // Conexões para começar um download de `url`: a última quantidade que o
+    // servidor aguentou, ou uma só se ele costuma falhar. None = padrão do motor.
+    pub fn initial_connections(&self, url: &str) -> Option<usize> {
+        let stats = self.get(url)?;
+        if stats.is_flaky() {
+            return Some(FLAKY_CONNECTIONS);
+        }
+        stats.connections
+    }
+
+    // Chunks paralelos mesmo abaixo do tamanho mínimo: servidor da lista
+    // configurada ou que o histórico mostrou limitar cada conexão
+    pub fn forces_segmented(&self, url: &str, configured: &[String]) -> bool {
+        host_listed(configured, url) || self.get(url).is_some_and(HostStats::limits_each_connection)
+    }
+
+    // Servidores com mais downloads primeiro
+    pub fn ranked(&self) -> Vec<(&String, &HostStats)> {
+        let mut hosts: Vec<_> = self.hosts.iter().collect();
+        hosts.sort_by(|a, b| b.1.attempts().cmp(&a.1.attempts()).then(a.0.cmp(b.0)));
+        hosts
+    }
+}
+
+pub fn host_of(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(|host| host.to_ascii_lowercase())
+}
+
+// "example.com" na lista vale também para "cdn.example.com"
+pub fn host_listed(hosts: &[String], url: &str) -> bool {
+    let Some(host) = host_of(url) else {
+        return false;
+    };
+    hosts.iter().map(|listed| listed.trim().to_ascii_lowercase()).any(|listed| {
+        !listed.is_empty() && (host == listed || host.ends_with(&format!(".{}", listed)))
+    })
+}
+
+// Aviso do diálogo de adicionar para servidores instáveis ou sem retomada
+pub fn host_warning(stats: &HostStats) -> Option<String> {
+    if stats.is_flaky() {
+        return Some(format!(
+            "Servidor instável: {} de {} downloads falharam",
+            stats.failed,
+            stats.attempts()
+        ));
+    }
+    if stats.supports_range() == Some(false) {
+        return Some("Este servidor não permite retomar downloads interrompidos".to_string());
+    }
+    None
+}
@@ -0,0 +1,736 @@
+// Camada HTTP do motor de downloads
+//
+// O motor não fala diretamente com o reqwest: toda requisição passa pelo trait
+// `HttpBackend`, o que permite trocar a implementação real por um servidor
+// simulado nos testes (ranges, falhas de conexão, resume e cancelamento).
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+use futures_util::{FutureExt, StreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{StatusCode, Version};
+use serde::{Deserialize, Serialize};
+use crate::pac::{PacProxy, PacScript};
+use crate::platform::safe_file_name;
+
+// Stream com o corpo da resposta
+pub type BodyStream = BoxStream<'static, Result<Bytes, HttpError>>;
+
+// Backend compartilhado entre os chunks de um mesmo download
+pub type SharedBackend = Arc<dyn HttpBackend>;
+
+pub trait HttpBackend: Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>>;
+}
+
+// Envia a requisição tratando 429/503 como erro recuperável (com o Retry-After do servidor).
+// 503 é a resposta das páginas de manutenção: vira ServerBusy, com esperas mais longas.
+pub async fn send_request(backend: &dyn HttpBackend, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+    let response = backend.send(request).await?;
+    match response.status() {
+        status @ (StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE) => {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after);
+            let kind = if status == StatusCode::SERVICE_UNAVAILABLE {
+                HttpErrorKind::ServerBusy
+            } else {
+                HttpErrorKind::RateLimited
+            };
+            let mut error = HttpError::from_status(kind, status);
+            error.retry_after = retry_after;
+            Err(error)
+        }
+        _ => Ok(response),
+    }
+}
+
+// Retry-After aceita segundos ("120") ou uma data HTTP ("Wed, 21 Oct 2015 07:28:00 GMT")
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    // Datas no passado significam "pode tentar agora"
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+// Nome sugerido em Content-Disposition: `filename*=UTF-8''...` tem preferência
+// sobre `filename="..."`. Diretórios no nome são descartados e o resto passa por
+// `platform::safe_file_name` ("CON.txt" não pode ser criado no Windows).
+pub fn content_disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in value.split(';').skip(1) {
+        let Some((key, val)) = param.split_once('=') else {
+            continue;
+        };
+        let val = val.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                // charset'idioma'valor-com-percent-encoding
+                let encoded = val.splitn(3, '\'').nth(2).unwrap_or(val);
+                extended = Some(percent_decode(encoded));
+            }
+            "filename" => plain = Some(val.trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    let name = extended.or(plain)?;
+    let name = name.rsplit(['/', '\\']).next().unwrap_or("").trim().to_string();
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(safe_file_name(&name))
+    }
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Head,
+    Get,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    pub fn head(url: &str) -> Self {
+        HttpRequest {
+            method: HttpMethod::Head,
+            url: url.to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn get(url: &str) -> Self {
+        HttpRequest {
+            method: HttpMethod::Get,
+            url: url.to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Adiciona o header Range (end = None significa "até o fim do arquivo")
+    pub fn range(self, start: u64, end: Option<u64>) -> Self {
+        let value = match end {
+            Some(end) => format!("bytes={}-{}", start, end),
+            None => format!("bytes={}-", start),
+        };
+        self.header(reqwest::header::RANGE.as_str(), &value)
+    }
+
+    // Valor de um header da requisição (nome sem distinção de maiúsculas)
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub version: Version, // Protocolo negociado (HTTP/1.1, HTTP/2...)
+    pub headers: HeaderMap,
+    pub body: BodyStream,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn bytes_stream(self) -> BodyStream {
+        self.body
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpErrorKind {
+    Timeout,
+    Connect,
+    Request,
+    Body,
+    RateLimited, // 429
+    ServerBusy,  // 503: manutenção ou sobrecarga, espera mais antes de tentar
+    Interrupted, // Espera entre tentativas cortada (download pausado, cancelado ou app fechando)
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpError {
+    pub kind: HttpErrorKind,
+    pub message: String,
+    pub retry_after: Option<Duration>, // Espera pedida pelo servidor (Retry-After)
+    pub status: Option<u16>, // Status da resposta que virou erro (ver `failure::FailureKind::from_http`)
+}
+
+impl HttpError {
+    pub fn new(kind: HttpErrorKind, message: &str) -> Self {
+        HttpError {
+            kind,
+            message: message.to_string(),
+            retry_after: None,
+            status: None,
+        }
+    }
+
+    // Resposta com status de erro ("Status HTTP: 404 Not Found")
+    pub fn from_status(kind: HttpErrorKind, status: StatusCode) -> Self {
+        let mut error = HttpError::new(kind, &format!("Status HTTP: {}", status));
+        error.status = Some(status.as_u16());
+        error
+    }
+
+    // Erros recuperáveis (timeout, conexão) justificam uma nova tentativa
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self.kind,
+            HttpErrorKind::Timeout
+                | HttpErrorKind::Connect
+                | HttpErrorKind::Request
+                | HttpErrorKind::RateLimited
+                | HttpErrorKind::ServerBusy
+        )
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(err: reqwest::Error) -> Self {
+        let kind = if err.is_timeout() {
+            HttpErrorKind::Timeout
+        } else if err.is_connect() {
+            HttpErrorKind::Connect
+        } else if err.is_request() {
+            HttpErrorKind::Request
+        } else if err.is_body() || err.is_decode() {
+            HttpErrorKind::Body
+        } else {
+            HttpErrorKind::Other
+        };
+        HttpError {
+            kind,
+            message: err.to_string(),
+            retry_after: None,
+            status: err.status().map(|status| status.as_u16()),
+        }
+    }
+}
+
+// Família de endereços tentada primeiro ao conectar.
+// A outra continua como alternativa (Happy Eyeballs do hyper, após 300ms).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpPreference {
+    #[default]
+    Auto, // Ordem do sistema
+    PreferIpv4,
+    PreferIpv6,
+}
+
+// Coloca os endereços da família preferida na frente, mantendo a ordem dentro de cada família
+pub fn order_addresses(mut addrs: Vec<SocketAddr>, preference: IpPreference) -> Vec<SocketAddr> {
+    match preference {
+        IpPreference::Auto => {}
+        IpPreference::PreferIpv4 => addrs.sort_by_key(|addr| !addr.is_ipv4()),
+        IpPreference::PreferIpv6 => addrs.sort_by_key(|addr| !addr.is_ipv6()),
+    }
+    addrs
+}
+
+// Resolver do sistema com a ordem de endereços ajustada pela preferência
+struct PreferenceResolver(IpPreference);
+
+impl Resolve for PreferenceResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let preference = self.0;
+        async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            Ok(Box::new(order_addresses(addrs, preference).into_iter()) as Addrs)
+        }
+        .boxed()
+    }
+}
+
+// Tempo máximo para abrir uma conexão (evita esperar o timeout inteiro num IPv6 quebrado)
+pub const CONNECT_TIMEOUT_SECS: u64 = 10;
+
+// Configuração usada para montar o client de um download
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientOptions {
+    pub ip_preference: IpPreference,
+    pub ca_bundle: Option<PathBuf>, // Arquivo PEM com CAs adicionais
+    pub accept_invalid_certs: bool,
+    pub proxy: Option<String>, // URL do proxy (ex.: socks5h://...)
+    pub referer: Option<String>, // Header Referer enviado em todas as requisições
+    pub keep_alive: KeepAliveSettings,
+    pub pac: Option<Arc<PacScript>>, // Proxy decidido por URL; `proxy` vale se o script falhar
+}
+
+// Reaproveitamento de conexões. Em links com latência alta, abrir conexão (TCP +
+// TLS) custa mais que a transferência de arquivos pequenos. 0 desliga cada item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepAliveSettings {
+    pub pool_idle_timeout_secs: u64, // Conexão ociosa fica aberta por este tempo
+    pub max_idle_per_host: usize,    // Conexões ociosas guardadas por servidor
+    pub tcp_keepalive_secs: u64,     // Intervalo das sondas TCP keepalive
+}
+
+pub const MAX_POOL_IDLE_TIMEOUT_SECS: u64 = 3600;
+pub const MAX_IDLE_PER_HOST: usize = 64;
+pub const MAX_TCP_KEEPALIVE_SECS: u64 = 600;
+
+impl Default for KeepAliveSettings {
+    fn default() -> Self {
+        KeepAliveSettings { pool_idle_timeout_secs: 90, max_idle_per_host: 16, tcp_keepalive_secs: 60 }
+    }
+}
+
+// Porta SOCKS padrão do serviço Tor local
+pub const TOR_SOCKS_ADDR: &str = "127.0.0.1:9050";
+
+// Endereço aceito como proxy pelo reqwest (http://, https://, socks5://...)
+pub fn valid_proxy_url(url: &str) -> bool {
+    reqwest::Proxy::all(url).is_ok()
+}
+
+// Proxy do Tor com credenciais próprias: o Tor (IsolateSOCKSAuth) usa um
+// circuito separado para cada par usuário/senha. `socks5h` resolve o DNS
+// pelo Tor, necessário para endereços .onion.
+pub fn tor_proxy_url(isolation: &str) -> String {
+    format!("socks5h://{}:{}@{}", isolation, isolation, TOR_SOCKS_ADDR)
+}
+
+// Implementação real baseada no reqwest
+#[derive(Debug, Clone)]
+pub struct ReqwestBackend {
+    client: reqwest::Client,
+    pac: Option<Arc<PacScript>>,
+    // Com PAC: um client por item de resposta do script (proxy ou direto), montado
+    // com as mesmas opções. O proxy vai na requisição, não no servidor: em http o
+    // script pode decidir pelo caminho, e o callback de proxy do reqwest só vê o servidor
+    pac_clients: Arc<Mutex<HashMap<PacProxy, reqwest::Client>>>,
+    options: Arc<ClientOptions>,
+}
+
+impl ReqwestBackend {
+    pub fn new() -> Result<Self, HttpError> {
+        Self::with_options(&ClientOptions::default())
+    }
+
+    pub fn with_options(options: &ClientOptions) -> Result<Self, HttpError> {
+        let mut builder = client_builder(options)?;
+        if options.pac.is_some() {
+            // Cada destino de redirecionamento é decidido pelo script em `send_with_pac`;
+            // este client só é usado quando o script falha, com o proxy manual
+            builder = builder.redirect(reqwest::redirect::Policy::none());
+        }
+        if let Some(proxy) = &options.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        let client = builder.build()?;
+        Ok(ReqwestBackend {
+            client,
+            pac: options.pac.clone(),
+            pac_clients: Arc::new(Mutex::new(HashMap::new())),
+            options: Arc::new(options.clone()),
+        })
+    }
+
+    // Client que sai pelo item `proxy` da resposta do PAC
+    fn pac_client(&self, proxy: &PacProxy) -> Result<reqwest::Client, HttpError> {
+        if let Some(client) = self.pac_clients.lock().ok().and_then(|clients| clients.get(proxy).cloned()) {
+            return Ok(client);
+        }
+        let builder = client_builder(&self.options)?.redirect(reqwest::redirect::Policy::none());
+        let client = match proxy {
+            PacProxy::Direct => builder.no_proxy().build()?,
+            PacProxy::Proxy(url) => builder.proxy(reqwest::Proxy::all(url)?).build()?,
+        };
+        if let Ok(mut clients) = self.pac_clients.lock() {
+            clients.entry(proxy.clone()).or_insert(client.clone());
+        }
+        Ok(client)
+    }
+
+    fn request_builder(client: &reqwest::Client, request: &HttpRequest) -> reqwest::RequestBuilder {
+        let mut builder = match request.method {
+            HttpMethod::Head => client.head(&request.url),
+            HttpMethod::Get => client.get(&request.url),
+        };
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        builder
+    }
+
+    // Com PAC o client não segue redirecionamentos sozinho: cada destino é decidido
+    // pelo script antes da conexão (numa rede só com PAC, a conexão direta falha)
+    async fn send_with_pac(self, pac: Arc<PacScript>, mut request: HttpRequest) -> Result<reqwest::Response, HttpError> {
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self.send_pac_hop(&pac, &request).await?;
+            match redirect_target(&request.url, &response) {
+                Some(target) => request = redirected_request(request, target),
+                None => return Ok(response),
+            }
+        }
+        Err(HttpError::new(HttpErrorKind::Other, "Redirecionamentos demais"))
+    }
+
+    // Decide o servidor antes de conectar e, se o proxy escolhido não conecta,
+    // tenta o próximo item da resposta do script
+    async fn send_pac_hop(&self, pac: &Arc<PacScript>, request: &HttpRequest) -> Result<reqwest::Response, HttpError> {
+        let url = reqwest::Url::parse(&request.url).ok();
+        if let Some(url) = &url {
+            if let Err(e) = pac.prepare(url).await {
+                eprintln!("{}", e);
+            }
+        }
+        loop {
+            // Sem decisão (o script falhou): o client com o proxy manual
+            let tried = url.as_ref().and_then(|url| pac.proxy_for(url));
+            let client = match &tried {
+                Some(proxy) => self.pac_client(proxy)?,
+                None => self.client.clone(),
+            };
+            match Self::request_builder(&client, request).send().await {
+                Err(e) if e.is_connect() => match (&url, &tried) {
+                    (Some(url), Some(tried)) if pac.skip_failed(url, tried) => {
+                        eprintln!("Proxy do PAC não conectou ({:?}): tentando o próximo", tried);
+                    }
+                    _ => return Err(e.into()),
+                },
+                result => return Ok(result?),
+            }
+        }
+    }
+}
+
+// Builder com as opções de conexão, sem o proxy
+fn client_builder(options: &ClientOptions) -> Result<reqwest::ClientBuilder, HttpError> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS));
+    // Sem tempo ocioso, nenhuma conexão volta para o pool
+    let keep_alive = options.keep_alive;
+    let max_idle = if keep_alive.pool_idle_timeout_secs == 0 { 0 } else { keep_alive.max_idle_per_host };
+    builder = builder
+        .pool_idle_timeout(Duration::from_secs(keep_alive.pool_idle_timeout_secs))
+        .pool_max_idle_per_host(max_idle)
+        .tcp_keepalive((keep_alive.tcp_keepalive_secs > 0).then(|| Duration::from_secs(keep_alive.tcp_keepalive_secs)));
+    if options.ip_preference != IpPreference::Auto {
+        builder = builder.dns_resolver(Arc::new(PreferenceResolver(options.ip_preference)));
+    }
+    if let Some(path) = &options.ca_bundle {
+        for cert in load_ca_bundle(path)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if options.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(referer) = &options.referer {
+        let value = reqwest::header::HeaderValue::from_str(referer)
+            .map_err(|e| HttpError::new(HttpErrorKind::Other, &format!("Página de origem inválida: {}", e)))?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::REFERER, value);
+        builder = builder.default_headers(headers);
+    }
+    Ok(builder)
+}
+
+// Mesmo limite de redirecionamentos seguidos do reqwest
+const MAX_REDIRECTS: usize = 10;
+
+// Destino de um redirecionamento (Location relativo ao endereço pedido)
+fn redirect_target(url: &str, response: &reqwest::Response) -> Option<String> {
+    if !matches!(response.status().as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = response.headers().get(reqwest::header::LOCATION)?.to_str().ok()?;
+    reqwest::Url::parse(url).ok()?.join(location).ok().map(String::from)
+}
+
+// A mesma requisição para `target`. Em outro servidor, as credenciais ficam para
+// trás, como no redirecionamento do reqwest
+fn redirected_request(mut request: HttpRequest, target: String) -> HttpRequest {
+    let host = |url: &str| reqwest::Url::parse(url).ok().map(|url| (url.host_str().map(str::to_string), url.port_or_known_default()));
+    if host(&request.url) != host(&target) {
+        request.headers.retain(|(name, _)| {
+            !["authorization", "cookie", "proxy-authorization", "www-authenticate"].iter().any(|h| name.eq_ignore_ascii_case(h))
+        });
+    }
+    request.url = target;
+    request
+}
+
+// Client compartilhado pelos downloads: conexões abertas e sessões TLS são
+// reaproveitadas entre downloads e entre os chunks de um mesmo download.
+// É refeito quando as opções da configuração mudam.
+#[derive(Debug, Default)]
+pub struct ClientPool {
+    current: Mutex<Option<(ClientOptions, ReqwestBackend)>>,
+}
+
+impl ClientPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, options: &ClientOptions) -> Result<ReqwestBackend, HttpError> {
+        let Ok(mut current) = self.current.lock() else {
+            return ReqwestBackend::with_options(options);
+        };
+        if let Some((built_with, client)) = current.as_ref() {
+            if built_with == options {
+                return Ok(client.clone());
+            }
+        }
+        let client = ReqwestBackend::with_options(options)?;
+        *current = Some((options.clone(), client.clone()));
+        Ok(client)
+    }
+}
+
+// Lê os certificados de um arquivo PEM (pode conter vários)
+fn load_ca_bundle(path: &std::path::Path) -> Result<Vec<reqwest::Certificate>, HttpError> {
+    let pem = std::fs::read(path).map_err(|e| {
+        HttpError::new(HttpErrorKind::Other, &format!("Erro ao ler certificado CA {}: {}", path.display(), e))
+    })?;
+    let certs = reqwest::Certificate::from_pem_bundle(&pem)?;
+    if certs.is_empty() {
+        return Err(HttpError::new(
+            HttpErrorKind::Other,
+            &format!("Nenhum certificado encontrado em {}", path.display()),
+        ));
+    }
+    Ok(certs)
+}
+
+impl HttpBackend for ReqwestBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        let backend = self.clone();
+        async move {
+            let response = match backend.pac.clone() {
+                Some(pac) => backend.send_with_pac(pac, request).await?,
+                None => Self::request_builder(&backend.client, &request).send().await?,
+            };
+            let status = response.status();
+            let version = response.version();
+            let headers = response.headers().clone();
+            let body = response
+                .bytes_stream()
+                .map(|chunk| chunk.map_err(HttpError::from))
+                .boxed();
+            Ok(HttpResponse { status, version, headers, body })
+        }
+        .boxed()
+    }
+}
+
+// Acrescenta headers fixos a todas as requisições (os que a requisição já tem prevalecem)
+pub struct HeadersBackend {
+    inner: SharedBackend,
+    headers: Vec<(String, String)>,
+}
+
+impl HeadersBackend {
+    pub fn new(inner: SharedBackend, headers: Vec<(String, String)>) -> Self {
+        HeadersBackend { inner, headers }
+    }
+}
+
+impl HttpBackend for HeadersBackend {
+    fn send(&self, mut request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        for (name, value) in &self.headers {
+            if request.header_value(name).is_none() {
+                request.headers.push((name.clone(), value.clone()));
+            }
+        }
+        self.inner.send(request)
+    }
+}
+
+// Limite para esperas pedidas via Retry-After (evita travar o download por horas)
+pub const MAX_RETRY_AFTER_SECS: u64 = 300;
+
+// Servidor ocupado (503): até MAX_BUSY_WAITS esperas de 30s, 1min, 2min... (ou o
+// Retry-After), que não gastam as tentativas normais
+pub const MAX_BUSY_WAITS: u32 = 6;
+pub const BUSY_DELAY_SECS: u64 = 30;
+
+pub fn busy_delay(wait: u32, retry_after: Option<Duration>) -> Duration {
+    let delay = retry_after.unwrap_or_else(|| Duration::from_secs(BUSY_DELAY_SECS.saturating_mul(1 << wait.min(16))));
+    delay.min(Duration::from_secs(MAX_RETRY_AFTER_SECS))
+}
+
+// Função auxiliar para fazer retry automático em requisições
+pub async fn retry_request<F, Fut, T>(request_fn: F, max_retries: u32, delay_secs: u64) -> Result<T, HttpError>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, HttpError>>,
+{
+    retry_request_notify(request_fn, max_retries, delay_secs, |_, _| {}).await
+}
+
+// Igual a `retry_request`, mas avisa antes de cada espera (erro e tempo até a próxima tentativa)
+pub async fn retry_request_notify<F, Fut, T, N>(
+    request_fn: F,
+    max_retries: u32,
+    delay_secs: u64,
+    on_retry: N,
+) -> Result<T, HttpError>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, HttpError>>,
+    N: Fn(&HttpError, Duration),
+{
+    retry_request_until(request_fn, max_retries, delay_secs, on_retry, || false).await
+}
+
+// Intervalo em que `sleep_unless` confere se deve parar
+const STOP_POLL_MS: u64 = 200;
+
+// Dorme `delay` em fatias curtas; retorna true, sem esperar o resto, assim que `stopped` for verdadeiro
+pub async fn sleep_unless(delay: Duration, stopped: impl Fn() -> bool) -> bool {
+    let deadline = tokio::time::Instant::now() + delay;
+    loop {
+        if stopped() {
+            return true;
+        }
+        let now = tokio::time::Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        tokio::time::sleep((deadline - now).min(Duration::from_millis(STOP_POLL_MS))).await;
+    }
+}
+
+// Igual a `retry_request_notify`, mas as esperas terminam com um erro `Interrupted`
+// quando `stopped` fica verdadeiro (esperas de 503 chegam a vários minutos)
+pub async fn retry_request_until<F, Fut, T, N, S>(
+    request_fn: F,
+    max_retries: u32,
+    delay_secs: u64,
+    on_retry: N,
+    stopped: S,
+) -> Result<T, HttpError>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, HttpError>>,
+    N: Fn(&HttpError, Duration),
+    S: Fn() -> bool,
+{
+    let interrupted = || HttpError::new(HttpErrorKind::Interrupted, "Espera interrompida");
+    let mut last_error = None;
+    let mut busy_waits = 0;
+
+    let mut attempt = 0;
+    while attempt < max_retries {
+        match request_fn().await {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                // Verifica se é erro recuperável
+                if !e.is_recoverable() {
+                    // Erro não recuperável (404, 403, etc.) - não tenta novamente
+                    return Err(e);
+                }
+
+                // Servidor em manutenção: espera mais, sem gastar uma tentativa
+                if e.kind == HttpErrorKind::ServerBusy && busy_waits < MAX_BUSY_WAITS {
+                    let delay = busy_delay(busy_waits, e.retry_after);
+                    busy_waits += 1;
+                    on_retry(&e, delay);
+                    if sleep_unless(delay, &stopped).await {
+                        return Err(interrupted());
+                    }
+                    continue;
+                }
+
+                // Se não é a última tentativa, aguarda antes de tentar novamente
+                if attempt < max_retries - 1 {
+                    // Respeita o Retry-After; senão, delay exponencial: 2s, 4s, 8s...
+                    let delay = match e.retry_after {
+                        Some(wait) => wait.min(Duration::from_secs(MAX_RETRY_AFTER_SECS)),
+                        None => Duration::from_secs(delay_secs * (1 << attempt)),
+                    };
+                    on_retry(&e, delay);
+                    if sleep_unless(delay, &stopped).await {
+                        return Err(interrupted());
+                    }
+                }
+
+                last_error = Some(e);
+            }
+        }
+        attempt += 1;
+    }
+
+    // Retorna o último erro se todas as tentativas falharam
+    // Se não houver erro anterior (não deveria acontecer), tenta fazer uma última requisição
+    match last_error {
+        Some(e) => Err(e),
+        None => {
+            // Faz uma última tentativa
+            request_fn().await
+        }
+    }
+}
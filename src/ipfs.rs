@@ -0,0 +1,237 @@
+// URLs `ipfs://CID/caminho` baixadas por gateways HTTP. O primeiro pedido corre
+// em todos os gateways ao mesmo tempo; os seguintes usam o que respondeu primeiro
+// e só passam para os outros se ele falhar.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use futures_util::future::{self, BoxFuture};
+use futures_util::FutureExt;
+use reqwest::StatusCode;
+use crate::http::{HttpBackend, HttpError, HttpErrorKind, HttpRequest, HttpResponse, SharedBackend};
+
+pub const DEFAULT_GATEWAYS: [&str; 3] = ["https://ipfs.io", "https://dweb.link", "https://w3s.link"];
+
+// Códigos multiformats usados na verificação
+const CODEC_RAW: u64 = 0x55;
+const MULTIHASH_SHA2_256: u64 = 0x12;
+
+pub fn is_ipfs_url(url: &str) -> bool {
+    url.starts_with("ipfs://")
+}
+
+// "ipfs://CID/pasta/arq.bin" -> ("CID", "/pasta/arq.bin")
+pub fn parse_ipfs_url(url: &str) -> Option<(&str, &str)> {
+    let rest = url.strip_prefix("ipfs://")?;
+    let (cid, path) = match rest.find(['/', '?', '#']) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    if cid.is_empty() {
+        None
+    } else {
+        Some((cid, path))
+    }
+}
+
+pub fn gateway_url(gateway: &str, cid: &str, path: &str) -> String {
+    format!("{}/ipfs/{}{}", gateway.trim_end_matches('/'), cid, path)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cid {
+    pub version: u64,
+    pub codec: u64,
+    pub hash_code: u64,
+    pub digest: Vec<u8>,
+}
+
+impl Cid {
+    // CIDv0 ("Qm...", base58btc) ou CIDv1 em base32 ("b...", o formato dos gateways)
+    pub fn parse(text: &str) -> Option<Cid> {
+        if text.len() == 46 && text.starts_with("Qm") {
+            let bytes = base58_decode(text)?;
+            let (hash_code, digest) = parse_multihash(&bytes)?;
+            return Some(Cid { version: 0, codec: 0x70, hash_code, digest });
+        }
+        let bytes = base32_decode(text.strip_prefix('b')?)?;
+        let (version, rest) = read_varint(&bytes)?;
+        let (codec, rest) = read_varint(rest)?;
+        if version != 1 {
+            return None;
+        }
+        let (hash_code, digest) = parse_multihash(rest)?;
+        Some(Cid { version, codec, hash_code, digest })
+    }
+}
+
+// SHA-256 esperado do arquivo. Só CIDs de bloco "raw" guardam o hash do próprio
+// conteúdo; nos demais (dag-pb) o hash é da árvore de blocos e não dá para conferir
+// com o arquivo montado.
+pub fn raw_sha256(url: &str) -> Option<String> {
+    let (cid, path) = parse_ipfs_url(url)?;
+    let cid = Cid::parse(cid)?;
+    let verifiable = path.trim_start_matches('/').is_empty()
+        && cid.codec == CODEC_RAW
+        && cid.hash_code == MULTIHASH_SHA2_256
+        && cid.digest.len() == 32;
+    verifiable.then(|| cid.digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn parse_multihash(bytes: &[u8]) -> Option<(u64, Vec<u8>)> {
+    let (code, rest) = read_varint(bytes)?;
+    let (len, rest) = read_varint(rest)?;
+    (rest.len() as u64 == len).then(|| (code, rest.to_vec()))
+}
+
+fn read_varint(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(9) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &bytes[i + 1..]));
+        }
+    }
+    None
+}
+
+fn base58_decode(text: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    // Número em base 256, do byte menos significativo para o mais
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in text.bytes() {
+        let mut carry = ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    // Cada '1' no início é um byte zero
+    bytes.extend(text.bytes().take_while(|&c| c == b'1').map(|_| 0));
+    bytes.reverse();
+    Some(bytes)
+}
+
+fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = match c {
+            b'a'..=b'z' => c - b'a',
+            b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+// Respostas que indicam que vale tentar outro gateway
+fn gateway_failed(result: &Result<HttpResponse, HttpError>) -> bool {
+    match result {
+        Err(_) => true,
+        Ok(response) => {
+            let status = response.status();
+            status == StatusCode::NOT_FOUND
+                || status == StatusCode::GONE
+                || status == StatusCode::TOO_MANY_REQUESTS
+                || status.is_server_error()
+        }
+    }
+}
+
+pub struct IpfsBackend {
+    inner: SharedBackend,
+    gateways: Vec<String>,
+    preferred: Arc<AtomicUsize>, // Índice do gateway escolhido (usize::MAX = nenhum ainda)
+}
+
+impl IpfsBackend {
+    pub fn new(inner: SharedBackend, gateways: Vec<String>) -> Self {
+        IpfsBackend {
+            inner,
+            gateways,
+            preferred: Arc::new(AtomicUsize::new(usize::MAX)),
+        }
+    }
+
+    // Gateway em uso, depois de algum ter respondido
+    pub fn preferred_gateway(&self) -> Option<&str> {
+        self.gateways.get(self.preferred.load(Ordering::SeqCst)).map(String::as_str)
+    }
+}
+
+impl HttpBackend for IpfsBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        let Some((cid, path)) = parse_ipfs_url(&request.url) else {
+            return self.inner.send(request);
+        };
+        if self.gateways.is_empty() {
+            return async { Err(HttpError::new(HttpErrorKind::Other, "Nenhum gateway IPFS configurado")) }.boxed();
+        }
+
+        let requests: Vec<(usize, HttpRequest)> = self
+            .gateways
+            .iter()
+            .enumerate()
+            .map(|(index, gateway)| {
+                let mut request = request.clone();
+                request.url = gateway_url(gateway, cid, path);
+                (index, request)
+            })
+            .collect();
+        let inner = self.inner.clone();
+        let preferred = self.preferred.clone();
+        let current = preferred.load(Ordering::SeqCst);
+
+        async move {
+            if current == usize::MAX {
+                // Corrida: o primeiro gateway com resposta útil vence; os demais são descartados
+                let racing = requests.into_iter().map(|(index, request)| {
+                    let attempt = inner.send(request);
+                    async move {
+                        let result = attempt.await;
+                        if gateway_failed(&result) {
+                            Err(result)
+                        } else {
+                            result.map(|response| (index, response)).map_err(Err)
+                        }
+                    }
+                    .boxed()
+                });
+                // Se todos falharem, fica a última resposta (404, 5xx...) para o motor tratar
+                return match future::select_ok(racing).await {
+                    Ok(((index, response), _)) => {
+                        preferred.store(index, Ordering::SeqCst);
+                        Ok(response)
+                    }
+                    Err(last) => last,
+                };
+            }
+
+            // Gateway já escolhido primeiro; os outros, na ordem, se ele falhar
+            let mut ordered = requests;
+            ordered.sort_by_key(|(index, _)| *index != current);
+            let mut last = None;
+            for (index, request) in ordered {
+                let result = inner.send(request).await;
+                if !gateway_failed(&result) {
+                    preferred.store(index, Ordering::SeqCst);
+                    return result;
+                }
+                last = Some(result);
+            }
+            last.unwrap_or_else(|| Err(HttpError::new(HttpErrorKind::Other, "Nenhum gateway IPFS configurado")))
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,44 @@
+// Núcleo do Keepers, independente da interface GTK
+pub mod aria2;
+pub mod auto_retry;
+pub mod autosave;
+pub mod autostart;
+pub mod benchmark;
+pub mod checksum;
+pub mod decoding;
+pub mod engine;
+pub mod events;
+pub mod failure;
+pub mod ftp;
+pub mod host_stats;
+pub mod http;
+pub mod ipfs;
+pub mod link_import;
+pub mod listing;
+pub mod mirrors;
+pub mod pac;
+pub mod pipe;
+pub mod platform;
+pub mod power;
+pub mod provenance;
+pub mod queue;
+pub mod recovery;
+pub mod reproduce;
+pub mod resolve;
+pub mod s3;
+pub mod scan;
+pub mod schedule;
+pub mod scripting;
+pub mod slots;
+pub mod storage;
+pub mod suggestions;
+pub mod templates;
+pub mod throttle;
+pub mod updates;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+pub mod url_filter;
+pub mod usage;
+pub mod vault;
+pub mod watchdog;
+pub mod zsync;
@@ -0,0 +1,216 @@
+// Listas de links exportadas por outros gerenciadores de download
+//
+//   .crawljob  JDownloader (pasta "folderwatch"): blocos "chave=valor" ou JSON
+//   .dlc       JDownloader, só depois de descriptografado (XML com campos em base64)
+//   .json      categoria do uGet (category/NNNN.json), com os downloads dentro
+//   outros     uma URL por linha (ex.: "Exportar para arquivo de texto" do uGet)
+//
+// O pacote do JDownloader e a categoria do uGet viram a subpasta do download.
+use base64::Engine as _;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedLink {
+    pub url: String,
+    pub category: Option<String>, // Pacote (JDownloader) ou categoria (uGet)
+}
+
+// Escolhe o leitor pela extensão de `file_name`
+pub fn parse_link_list(file_name: &str, contents: &str) -> Result<Vec<ImportedLink>, String> {
+    let extension = file_name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    let links = match extension.as_str() {
+        "crawljob" => parse_crawljob(contents)?,
+        "dlc" => parse_decrypted_dlc(contents)?,
+        "json" => parse_uget_category(contents)?,
+        _ => parse_url_lines(contents),
+    };
+    if links.is_empty() {
+        return Err("Nenhum link encontrado no arquivo".to_string());
+    }
+    Ok(links)
+}
+
+// Uma URL por linha; linhas vazias e comentários (#) são ignorados
+pub fn parse_url_lines(contents: &str) -> Vec<ImportedLink> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.contains("://") && !line.starts_with('#'))
+        .map(|line| ImportedLink { url: line.to_string(), category: None })
+        .collect()
+}
+
+// Cada job tem "text" (um ou mais links) e, opcionalmente, "packageName". No formato
+// de propriedades, um "text=" novo ou uma linha vazia começa o próximo job.
+pub fn parse_crawljob(contents: &str) -> Result<Vec<ImportedLink>, String> {
+    let trimmed = contents.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        let value: Value = serde_json::from_str(trimmed).map_err(|e| format!("crawljob inválido: {}", e))?;
+        let jobs = match value {
+            Value::Array(jobs) => jobs,
+            job => vec![job],
+        };
+        let mut links = Vec::new();
+        for job in &jobs {
+            let text = job.get("text").and_then(Value::as_str).unwrap_or_default();
+            let package = job.get("packageName").and_then(Value::as_str);
+            push_job_links(&mut links, text, package);
+        }
+        return Ok(links);
+    }
+
+    let mut links = Vec::new();
+    let (mut text, mut package): (Option<String>, Option<String>) = (None, None);
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() {
+            if let Some(text) = text.take() {
+                push_job_links(&mut links, &text, package.take().as_deref());
+            }
+            continue;
+        }
+        if line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(['=', ':']) else {
+            continue;
+        };
+        let value = unescape_property(value.trim());
+        match key.trim() {
+            "text" => {
+                if let Some(text) = text.replace(value) {
+                    push_job_links(&mut links, &text, package.take().as_deref());
+                }
+            }
+            "packageName" => package = Some(value),
+            _ => {}
+        }
+    }
+    if let Some(text) = text {
+        push_job_links(&mut links, &text, package.as_deref());
+    }
+    Ok(links)
+}
+
+fn push_job_links(links: &mut Vec<ImportedLink>, text: &str, package: Option<&str>) {
+    let category = package.map(str::trim).filter(|name| !name.is_empty()).map(str::to_string);
+    for url in text.split_whitespace().filter(|word| word.contains("://")) {
+        links.push(ImportedLink { url: url.to_string(), category: category.clone() });
+    }
+}
+
+// "\:" -> ":", "\\" -> "\" (escapes de arquivos .properties do Java)
+fn unescape_property(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(next) => out.push(next),
+            None => {}
+        }
+    }
+    out
+}
+
+// DLC descriptografado: <package name="..."><file><url>...</url></file></package>,
+// com nome e URL em base64. O contêiner cifrado depende da chave do serviço do
+// JDownloader e não é aberto aqui.
+pub fn parse_decrypted_dlc(contents: &str) -> Result<Vec<ImportedLink>, String> {
+    let contents = contents.trim_start_matches('\u{feff}').trim();
+    if !contents.starts_with('<') {
+        return Err("DLC criptografado: descriptografe o arquivo antes de importar".to_string());
+    }
+    let mut links = Vec::new();
+    let mut rest = contents;
+    while let Some(start) = rest.find("<package") {
+        rest = &rest[start + "<package".len()..];
+        let tag_end = rest.find('>').unwrap_or(rest.len());
+        let category = xml_attribute(&rest[..tag_end], "name").map(|name| decode_dlc_field(&name));
+        let end = rest.find("</package>").unwrap_or(rest.len());
+        let package = &rest[..end];
+        let mut files = package;
+        while let Some(url_start) = files.find("<url>") {
+            files = &files[url_start + "<url>".len()..];
+            let Some(url_end) = files.find("</url>") else {
+                break;
+            };
+            let url = decode_dlc_field(&files[..url_end]);
+            if url.contains("://") {
+                links.push(ImportedLink { url, category: category.clone().filter(|name| !name.is_empty()) });
+            }
+            files = &files[url_end..];
+        }
+        rest = &rest[end..];
+    }
+    Ok(links)
+}
+
+fn xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = tag[start..].find('"')? + start;
+    Some(tag[start..end].to_string())
+}
+
+// Campos do DLC vêm em base64; alguns exportadores gravam o texto puro
+fn decode_dlc_field(value: &str) -> String {
+    let value = value.trim();
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .filter(|text| !text.chars().any(char::is_control))
+        .unwrap_or_else(|| value.to_string())
+        .trim()
+        .to_string()
+}
+
+// Categoria do uGet: o "name" do objeto principal é a categoria e cada "uri"
+// (ou lista "uris") dentro dele é um download. Um array é lido como várias categorias.
+pub fn parse_uget_category(contents: &str) -> Result<Vec<ImportedLink>, String> {
+    let value: Value = serde_json::from_str(contents).map_err(|e| format!("Categoria do uGet inválida: {}", e))?;
+    let categories = match value {
+        Value::Array(categories) => categories,
+        category => vec![category],
+    };
+    let mut links = Vec::new();
+    for category in &categories {
+        let name = category
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+        let mut urls = Vec::new();
+        collect_uris(category, &mut urls);
+        links.extend(urls.into_iter().map(|url| ImportedLink { url, category: name.clone() }));
+    }
+    Ok(links)
+}
+
+fn collect_uris(value: &Value, urls: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("uri", Value::String(uri)) => push_uri(urls, uri),
+                    ("uris", Value::Array(uris)) => uris.iter().filter_map(Value::as_str).for_each(|uri| push_uri(urls, uri)),
+                    _ => collect_uris(value, urls),
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_uris(value, urls)),
+        _ => {}
+    }
+}
+
+fn push_uri(urls: &mut Vec<String>, uri: &str) {
+    let uri = uri.trim();
+    if uri.contains("://") && !urls.iter().any(|url| url == uri) {
+        urls.push(uri.to_string());
+    }
+}
@@ -0,0 +1,304 @@
+// Links de páginas HTML: índices de diretório gerados pelos servidores (Apache,
+// nginx, lighttpd...) e o modo "baixar todos os links" de uma página qualquer.
+// Pastas FTP chegam como a saída do LIST (ver `ftp`) e viram as mesmas entradas
+use regex::Regex;
+use reqwest::Url;
+use crate::http::percent_decode;
+
+// Tamanho máximo aceito para a página do índice
+pub const MAX_INDEX_SIZE: usize = 4 * 1024 * 1024;
+// Profundidade máxima ao baixar uma pasta inteira
+pub const MAX_WALK_DEPTH: usize = 8;
+// Links de uma página mostrados (e consultados) de uma vez
+pub const MAX_PAGE_LINKS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageLink {
+    pub url: String,  // Absoluta, já resolvida contra a página
+    pub text: String, // Texto do link, sem tags
+    pub trailing: String, // Texto depois do link na mesma linha (data e tamanho nos índices)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub url: String,
+    pub name: String,
+    pub is_dir: bool,
+    pub size: Option<u64>, // Aproximado quando o índice mostra "1.2M"
+}
+
+// Todos os links <a href> de uma página HTML, na ordem em que aparecem
+pub fn extract_links(html: &str, base: &str) -> Vec<PageLink> {
+    let Ok(base) = Url::parse(base) else {
+        return Vec::new();
+    };
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<a").map(|i| pos + i) {
+        pos = start + 2;
+        if !lower[pos..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(tag_end) = lower[pos..].find('>').map(|i| pos + i) else {
+            break;
+        };
+        let href = attribute(&html[pos..tag_end], "href");
+        let close = lower[tag_end..].find("</a").map(|i| tag_end + i).unwrap_or(tag_end + 1);
+        let text = strip_tags(html.get(tag_end + 1..close).unwrap_or_default());
+        pos = tag_end + 1;
+
+        let Some(url) = href.and_then(|href| base.join(&decode_entities(href)).ok()) else {
+            continue;
+        };
+        // O resto da linha, até o próximo link
+        let after = lower[close..].find('>').map(|i| close + i + 1).unwrap_or(close);
+        let line_end = [lower[after..].find('\n'), lower[after..].find("<a ")]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|i| after + i)
+            .unwrap_or(html.len());
+        links.push(PageLink {
+            url: url.to_string(),
+            text,
+            trailing: strip_tags(&html[after..line_end]),
+        });
+    }
+    links
+}
+
+// Links baixáveis de uma página, sem repetidos: só http(s), sem âncoras da própria
+// página, com uma das extensões (vazio = qualquer) e casando com o padrão, se houver
+pub fn filter_links(links: &[PageLink], page: &str, extensions: &[String], pattern: Option<&Regex>) -> Vec<PageLink> {
+    let page = page.split('#').next().unwrap_or(page);
+    let mut filtered: Vec<PageLink> = Vec::new();
+    for link in links {
+        let url = link.url.split('#').next().unwrap_or(&link.url);
+        let path = url.split('?').next().unwrap_or(url).to_ascii_lowercase();
+        let wanted = (url.starts_with("http://") || url.starts_with("https://"))
+            && url != page
+            && (extensions.is_empty() || extensions.iter().any(|ext| path.ends_with(&format!(".{}", ext))))
+            && pattern.is_none_or(|pattern| pattern.is_match(url))
+            && !filtered.iter().any(|f| f.url == url);
+        if wanted {
+            filtered.push(PageLink { url: url.to_string(), ..link.clone() });
+        }
+    }
+    filtered
+}
+
+// "iso, .zip tar.gz" -> ["iso", "zip", "tar.gz"]
+pub fn parse_extensions(text: &str) -> Vec<String> {
+    text.split([',', ';', ' '])
+        .map(|ext| ext.trim().trim_start_matches("*.").trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
+
+// Entradas da pasta `base`: só links para filhos diretos dela, sem o link para a
+// pasta de cima nem os de ordenação (?C=N;O=D)
+pub fn parse_index(html: &str, base: &str) -> Vec<IndexEntry> {
+    let base = directory_url(base);
+    let mut entries: Vec<IndexEntry> = Vec::new();
+    for link in extract_links(html, &base) {
+        let Some(rest) = link.url.strip_prefix(&base) else {
+            continue;
+        };
+        let name = rest.trim_end_matches('/');
+        if name.is_empty() || name.contains(['/', '?', '#']) || entries.iter().any(|e| e.url == link.url) {
+            continue;
+        }
+        let is_dir = rest.ends_with('/');
+        entries.push(IndexEntry {
+            url: link.url.clone(),
+            name: percent_decode(name),
+            is_dir,
+            size: if is_dir { None } else { parse_size(&link.trailing) },
+        });
+    }
+    entries
+}
+
+// Saída do LIST de um servidor FTP, no formato Unix ("drwxr-xr-x 2 ftp ftp 4096
+// Jan 31 2024 nome") ou no do IIS ("01-31-24  10:00AM  <DIR>  nome"). Linhas em
+// outro formato ("total 12") são ignoradas; links simbólicos contam como arquivos
+pub fn parse_ftp_list(text: &str, base: &str) -> Vec<IndexEntry> {
+    let base = directory_url(base);
+    let Ok(base_url) = Url::parse(&base) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<IndexEntry> = Vec::new();
+    for line in text.lines() {
+        let Some((name, is_dir, size)) = unix_list_line(line).or_else(|| dos_list_line(line)) else {
+            continue;
+        };
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            continue;
+        }
+        // "#" e "?" no nome não podem virar fragmento e query da URL
+        let segment = name.replace('%', "%25").replace('#', "%23").replace('?', "%3F");
+        let Ok(url) = base_url.join(&if is_dir { format!("{}/", segment) } else { segment }) else {
+            continue;
+        };
+        entries.push(IndexEntry {
+            url: url.to_string(),
+            name: name.to_string(),
+            is_dir,
+            size: if is_dir { None } else { size },
+        });
+    }
+    entries
+}
+
+// Os `count` primeiros campos separados por espaços e o resto da linha (o nome,
+// que pode ter espaços)
+fn split_fields(line: &str, count: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(count);
+    let mut rest = line.trim_start();
+    while fields.len() < count {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Some((fields, rest))
+}
+
+fn unix_list_line(line: &str) -> Option<(&str, bool, Option<u64>)> {
+    let (fields, name) = split_fields(line, 8)?;
+    let kind = fields[0].chars().next()?;
+    if fields[0].len() < 10 || !matches!(kind, 'd' | '-' | 'l') {
+        return None;
+    }
+    let size = fields[4].parse::<u64>().ok()?;
+    let name = if kind == 'l' { name.split(" -> ").next().unwrap_or(name) } else { name };
+    Some((name, kind == 'd', Some(size)))
+}
+
+fn dos_list_line(line: &str) -> Option<(&str, bool, Option<u64>)> {
+    let (fields, name) = split_fields(line, 3)?;
+    let date = fields[0].split('-').collect::<Vec<_>>();
+    if date.len() != 3 || !date.iter().all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())) {
+        return None;
+    }
+    if fields[2].eq_ignore_ascii_case("<DIR>") {
+        return Some((name, true, None));
+    }
+    Some((name, false, Some(fields[2].parse().ok()?)))
+}
+
+// Destino de uma página intermediária de download: <meta http-equiv="refresh">
+// ou um redirecionamento em JavaScript (location.href = "...", location.replace(...))
+pub fn redirect_target(html: &str, base: &str) -> Option<String> {
+    let base = Url::parse(base).ok()?;
+    let lower = html.to_ascii_lowercase();
+    let mut target = None;
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<meta").map(|i| pos + i) {
+        let end = lower[start..].find('>').map(|i| start + i).unwrap_or(html.len());
+        pos = end;
+        let tag = &html[start..end];
+        let refresh = attribute(tag, "http-equiv").is_some_and(|value| value.eq_ignore_ascii_case("refresh"));
+        let content = attribute(tag, "content").unwrap_or_default();
+        if let Some(index) = refresh.then(|| content.to_ascii_lowercase().find("url=")).flatten() {
+            target = Some(content[index + 4..].trim().trim_matches(['\'', '"']).to_string());
+            break;
+        }
+    }
+
+    if target.is_none() {
+        let script = Regex::new(
+            r#"(?:location\.href|location)\s*=\s*["']([^"']+)["']|location\.(?:replace|assign)\(\s*["']([^"']+)["']"#,
+        )
+        .ok()?;
+        target = script
+            .captures(html)
+            .and_then(|captures| captures.get(1).or_else(|| captures.get(2)))
+            .map(|found| found.as_str().to_string());
+    }
+
+    let url = base.join(&decode_entities(&target?)).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+// Índices só resolvem os links certos com a barra final ("/pub" -> "/pub/")
+pub fn directory_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    if path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/", path)
+    }
+}
+
+// Pasta de `url` relativa a `base` ("https://x/pub/" + "https://x/pub/a/b/c.iso" -> "a/b")
+pub fn relative_folder(base: &str, url: &str) -> Option<String> {
+    let rest = url.strip_prefix(&directory_url(base))?;
+    let folder = rest.rsplit_once('/').map(|(folder, _)| folder).unwrap_or_default();
+    let parts: Vec<String> = folder.split('/').filter(|p| !p.is_empty()).map(percent_decode).collect();
+    Some(parts.join("/"))
+}
+
+// Último número da linha com sufixo opcional: "12345", "1.2K", "15M", "3.4G"
+pub fn parse_size(text: &str) -> Option<u64> {
+    let token = text.split_whitespace().last()?;
+    let (number, multiplier) = match token.char_indices().last()? {
+        (i, 'K' | 'k') => (&token[..i], 1u64 << 10),
+        (i, 'M') => (&token[..i], 1 << 20),
+        (i, 'G') => (&token[..i], 1 << 30),
+        (i, 'T') => (&token[..i], 1 << 40),
+        _ => (token, 1),
+    };
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return None;
+    }
+    let value: f64 = number.parse().ok()?;
+    Some((value * multiplier as f64).round() as u64)
+}
+
+// Valor de um atributo da tag (com aspas simples, duplas ou sem aspas)
+pub fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(name).map(|i| search + i) {
+        search = found + name.len();
+        let boundary = found == 0 || lower.as_bytes()[found - 1].is_ascii_whitespace();
+        let rest = lower[search..].trim_start();
+        if !boundary || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        return match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next(),
+            _ => value.split(|c: char| c.is_ascii_whitespace()).next(),
+        };
+    }
+    None
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    decode_entities(text.split_whitespace().collect::<Vec<_>>().join(" ").trim())
+}
+
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
@@ -1,23 +1,68 @@
-use gtk4::{prelude::*, Application, Box as GtkBox, Button, Entry, Label, ListBox, Orientation, ScrolledWindow, MenuButton, PopoverMenu, CssProvider, FileChooserDialog, FileChooserAction};
+use gtk4::{prelude::*, Application, Box as GtkBox, Button, Entry, Label, ListBox, Orientation, ScrolledWindow, MenuButton, PopoverMenu, CssProvider};
 use gtk4::glib;
 use gtk4::gio;
-use libadwaita::{prelude::*, ApplicationWindow as AdwApplicationWindow, HeaderBar, StatusPage, StyleManager, MessageDialog, ResponseAppearance};
+use libadwaita::{prelude::*, ApplicationWindow as AdwApplicationWindow, HeaderBar, StatusPage, StyleManager, ResponseAppearance};
 use std::sync::{Arc, Mutex};
-use std::path::PathBuf;
-use std::time::Instant;
-use futures_util::StreamExt;
-use std::fs::{File, OpenOptions};
-use std::io::Write;
-use tokio::sync::Mutex as AsyncMutex;
-use async_channel;
-use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, Utc};
+use keepers::engine::{
+    backend_for_config, config_client_options, format_speed, is_streamable_media, shutdown_downloads, start_directory_listing, start_download, start_link_check,
+    start_page_scan, start_repair, start_url_check, start_verify, DownloadMessage, DownloadState, DownloadTask, WaitReason, DEFAULT_FLUSH_INTERVAL_SECS, DEFAULT_MAX_CONNECTIONS, MAX_CONNECTIONS_LIMIT, MAX_FLUSH_INTERVAL_SECS,
+};
+use keepers::auto_retry::{next_retry, retry_text, MAX_AUTO_RETRIES};
+use keepers::autostart::AUTOSTART_ARG;
+use keepers::reproduce::{download_command, CommandTool};
+use keepers::http::{ClientPool, IpPreference, MAX_IDLE_PER_HOST, MAX_POOL_IDLE_TIMEOUT_SECS, MAX_TCP_KEEPALIVE_SECS};
+use keepers::power::{
+    battery_action, BatteryAction, BatteryStatus, UPOWER_BUS_NAME, UPOWER_DEVICE_INTERFACE, UPOWER_DISPLAY_DEVICE,
+};
+use keepers::ipfs::{parse_ipfs_url, DEFAULT_GATEWAYS};
+use keepers::listing::{
+    directory_url, filter_links, parse_extensions, relative_folder, IndexEntry, PageLink, MAX_PAGE_LINKS,
+};
+use keepers::benchmark::{parse_connection_counts, run_benchmark};
+use keepers::host_stats::{host_warning, HostBook};
+use keepers::link_import::parse_link_list;
+use keepers::pipe::STDOUT_TARGET;
+use keepers::platform;
+use keepers::queue::{export_queue, import_queue};
+use keepers::recovery::{adopt_orphan, find_orphan_parts, OrphanPart};
+use keepers::resolve::installed_resolvers;
+use keepers::s3::parse_s3_url;
+use keepers::scripting::installed_hooks;
+use keepers::schedule::{
+    daily_cron, format_countdown, next_run, next_time_of_day, push_version, weekly_cron,
+    CronSchedule, DEFAULT_KEEP_VERSIONS,
+};
+use keepers::storage::{
+    disable_encryption, domain_folder, downloads_encrypted, downloads_locked, enable_encryption, get_backups_dir, get_download_directory, get_flush_interval, get_max_connections, get_resolvers_dir, get_scripts_dir, PORTABLE_ARG,
+    last_save_error, list_backups, load_config, load_downloads, load_host_stats, save_host_stats, missing_from_backup, read_backup, unlock_downloads, unlock_secrets, load_usage, new_download_id, record_directory, save_config, save_downloads, save_usage, unique_filename, unique_name, AppConfig, AppearanceSettings, DownloadEvent, DownloadOptions,
+    DownloadRecord, DownloadStatus, DownloadTemplate, FileIntegrity, NetworkProfile, parse_header_lines, portable_data_dir, push_event, MinSpeed, Recurrence, ResponseDetails, ScanResult, UrlFilterSettings, VersionNaming, ViewState,
+};
+use keepers::slots::{DownloadSlots, MAX_ACTIVE_DOWNLOADS};
+use keepers::events::{self, EventBus};
+use keepers::failure::{link_expired, FailureKind};
+use keepers::ftp::is_ftp_url;
+use keepers::autosave::{snapshot_tasks, start_autosave, AUTOSAVE_INTERVAL_SECS};
+use keepers::suggestions::{apply_suggestion, suggest_for, Suggestion};
+use keepers::templates::{apply_matching_template, matching_template};
+use keepers::throttle::SpeedLimiter;
+use keepers::updates::{start_update_check, CURRENT_VERSION, PROJECT_URL};
+use keepers::url_filter::{check_url, UrlFilter};
+use keepers::usage::{month_key, DataUsage, QuotaState};
+use uuid::Uuid;
+
+mod download_object;
+mod download_row;
+
+use download_object::{next_run_text, DownloadObject, INFECTED_TEXT};
+use download_row::DownloadRow;
 
 const APP_ID: &str = "com.downstream.app";
-const DEFAULT_NUM_CHUNKS: u64 = 4; // Número padrão de chunks paralelos
-const MIN_CHUNK_SIZE: u64 = 1024 * 1024; // 1MB - tamanho mínimo por chunk
-const MAX_RETRIES: u32 = 3; // Número máximo de tentativas em caso de erro de conexão
-const RETRY_DELAY_SECS: u64 = 2; // Delay entre tentativas em segundos
+const PROFILE_ARG: &str = "--profile";
+const SHUTDOWN_TIMEOUT_SECS: u64 = 5; // Espera máxima pelos downloads ao sair
+const USAGE_CHECK_SECS: u32 = 15; // Intervalo para salvar o consumo e conferir a cota
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
 
 // ===== DESIGN TOKENS =====
 // Sistema de espaçamento padronizado (ultra minimalista)
@@ -26,6 +71,11 @@ const SPACING_MEDIUM: i32 = 6;  // Espaçamento entre grupos relacionados
 const SPACING_SMALL: i32 = 4;   // Espaçamento entre elementos próximos
 const SPACING_TINY: i32 = 2;    // Espaçamento mínimo dentro de componentes
 
+// Largura (em sp) abaixo da qual a janela usa o layout compacto (celulares, meia tela)
+const NARROW_WIDTH: f64 = 500.0;
+// Largura máxima da lista; em telas largas os cards ficam centralizados
+const LIST_MAX_WIDTH: i32 = 1000;
+
 // Sistema de border radius (ultra minimalista)
 const RADIUS_LARGE: &str = "6px";   // Cards, badges grandes
 const RADIUS_MEDIUM: &str = "4px";  // Componentes médios
@@ -37,72 +87,39 @@ const COLOR_WARNING: &str = "#f59e0b";  // Âmbar - Pausado
 const COLOR_ERROR: &str = "#ef4444";    // Vermelho - Falhas
 const COLOR_NEUTRAL: &str = "#6b7280";  // Cinza - Cancelado
 
+// Paleta acessível (Okabe-Ito): distinguível com daltonismo; combinada com padrões nas barras
+const COLOR_SAFE_SUCCESS: &str = "#009e73"; // Verde-azulado - Concluídos
+const COLOR_SAFE_INFO: &str = "#0072b2";    // Azul - Em progresso
+const COLOR_SAFE_WARNING: &str = "#e69f00"; // Laranja - Pausado
+const COLOR_SAFE_ERROR: &str = "#d55e00";   // Vermelhão - Falhas
+
 // Sistema de opacidade
 const OPACITY_DIM_TEXT: f32 = 0.75;     // Texto secundário
 const OPACITY_CANCELLED: f32 = 0.65;    // Items cancelados
 
-#[derive(Clone, Debug)]
-enum DownloadMessage {
-    Progress(f64, String, String, String, bool, u64), // (progress, status_text, speed, eta, parallel_chunks, speed_bytes)
-    Complete,
-    Error(String),
-}
-
-#[derive(Debug)]
-struct DownloadTask {
-    paused: bool,
-    cancelled: bool,
-    file_path: Option<PathBuf>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct DownloadRecord {
-    url: String,
-    filename: String,
-    file_path: Option<String>,
-    status: DownloadStatus,
-    date_added: DateTime<Utc>,
-    date_completed: Option<DateTime<Utc>>,
-    downloaded_bytes: u64, // Quantidade já baixada (para resume)
-    total_bytes: u64,      // Tamanho total do arquivo
-    #[serde(default)]      // Para compatibilidade com arquivos antigos
-    was_paused: bool,      // Se estava pausado quando o app foi fechado
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum DownloadStatus {
-    InProgress,
-    Completed,
-    Failed,
-    Cancelled,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct AppConfig {
-    download_directory: Option<String>, // Caminho da pasta de downloads padrão
-    window_width: Option<i32>, // Largura da janela
-    window_height: Option<i32>, // Altura da janela
-}
-
 struct AppState {
     downloads: Vec<Arc<Mutex<DownloadTask>>>,
     records: Arc<Mutex<Vec<DownloadRecord>>>,
     config: Arc<Mutex<AppConfig>>,
-    download_speeds: Arc<Mutex<std::collections::HashMap<String, u64>>>, // URL -> velocidade em bytes/s
+    download_speeds: Arc<Mutex<std::collections::HashMap<Uuid, u64>>>, // id -> velocidade em bytes/s
+    usage: Arc<Mutex<DataUsage>>, // Bytes recebidos por mês (cota de dados)
+    speed_limiter: Arc<SpeedLimiter>, // Limite de velocidade dividido entre os downloads
+    hosts: Arc<Mutex<HostBook>>, // Histórico de velocidade e falhas por servidor
+    clients: Arc<ClientPool>, // Client HTTP reaproveitado por todos os downloads
+    slots: Arc<DownloadSlots>, // Fila do limite de downloads simultâneos
+    bus: EventBus, // Eventos do ciclo de vida dos downloads (ver subscribe_download_speeds)
 }
 
 // Função para sanitizar e limitar o tamanho do nome do arquivo
 fn sanitize_filename(url: &str) -> String {
     // Extrai o nome do arquivo da URL
-    let filename = url.split('/').last().unwrap_or("download").to_string();
+    let filename = url.split('/').next_back().unwrap_or("download").to_string();
 
     // Remove query parameters se houver
     let filename_clean = filename.split('?').next().unwrap_or(&filename);
 
-    // Remove caracteres inválidos no sistema de arquivos
-    let filename_safe = filename_clean
-        .replace(['<', '>', ':', '"', '|', '?', '*'], "_")
-        .replace(['\\', '/'], "_");
+    // Remove caracteres inválidos no sistema de arquivos e nomes reservados do Windows
+    let filename_safe = platform::safe_file_name(filename_clean);
 
     // Limita o tamanho do nome (considerando extensão)
     const MAX_FILENAME_LENGTH: usize = 200; // Limite seguro para a maioria dos sistemas
@@ -133,9 +150,188 @@ fn sanitize_filename(url: &str) -> String {
     }
 }
 
+// Caminho relativo "sub/pasta/arquivo" dentro da pasta de downloads; componentes
+// vazios, "." e ".." são descartados para não sair dela
+fn subfolder_filename(subfolder: &str, filename: &str) -> String {
+    let mut parts: Vec<String> = subfolder
+        .split(['/', '\\'])
+        .filter(|part| !matches!(*part, "" | "." | ".."))
+        .map(platform::safe_file_name)
+        .collect();
+    parts.push(filename.to_string());
+    parts.join("/")
+}
+
+// Valor de `--profile NOME` / `--profile=NOME`, lido antes do GApplication porque
+// o perfil decide o id da aplicação (uma instância por perfil)
+fn profile_from_args(args: &[String]) -> Option<String> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == PROFILE_ARG {
+            return iter.next().cloned();
+        }
+        if let Some(name) = arg.strip_prefix(PROFILE_ARG).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+// Argumentos de um subcomando sem `--profile NOME` e `--portable`, já aplicados em `main`
+fn subcommand_args(args: &[String]) -> Vec<String> {
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == PROFILE_ARG {
+            iter.next();
+        } else if arg != PORTABLE_ARG && !arg.starts_with(&format!("{}=", PROFILE_ARG)) {
+            rest.push(arg.clone());
+        }
+    }
+    rest
+}
+
+// Dentro de uma AppImage o executável fica montado em /tmp: o que conta é o
+// arquivo .AppImage, apontado por $APPIMAGE
+fn executable_path() -> Option<PathBuf> {
+    std::env::var_os("APPIMAGE")
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_exe().ok())
+}
+
+// `keepers fetch <url> [-o ARQUIVO|-] [--profile NOME]`: baixa sem abrir a janela;
+// "-" (ou sem -o) escreve no stdout. Devolve o código de saída do processo.
+fn run_fetch(args: &[String]) -> i32 {
+    let mut url = None;
+    let mut target = STDOUT_TARGET.to_string();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match iter.next() {
+                Some(value) => target = value.clone(),
+                None => url = None,
+            },
+            _ if url.is_none() && !arg.starts_with('-') => url = Some(arg.clone()),
+            _ => {
+                url = None;
+                break;
+            }
+        }
+    }
+    let Some(url) = url else {
+        eprintln!("Uso: keepers fetch <url> [-o ARQUIVO|-] [--profile NOME]");
+        return 2;
+    };
+
+    let config = match keepers::pipe::fetch_config(&url) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let result = if target == STDOUT_TARGET {
+        keepers::pipe::fetch(&config, &url, &mut std::io::stdout().lock())
+    } else {
+        std::fs::File::create(&target)
+            .map_err(|e| format!("Erro ao criar arquivo: {}", e))
+            .and_then(|file| keepers::pipe::fetch(&config, &url, &mut std::io::BufWriter::new(file)))
+    };
+    match result {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// `keepers benchmark <url> [conexões...]` (oculto): velocidade com 1/2/4/8/16 conexões
+fn run_benchmark_command(args: &[String]) -> i32 {
+    let Some((url, counts)) = args.split_first() else {
+        eprintln!("Uso: keepers benchmark <url> [conexões...]");
+        return 2;
+    };
+    let counts = match parse_connection_counts(counts) {
+        Ok(counts) => counts,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let backend = match backend_for_config(&load_config()) {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("Erro ao criar cliente HTTP: {}", e);
+            return 1;
+        }
+    };
+    let work_dir = std::env::temp_dir().join(format!("keepers-benchmark-{}", std::process::id()));
+    if let Err(e) = std::fs::create_dir_all(&work_dir) {
+        eprintln!("Erro ao criar pasta temporária: {}", e);
+        return 1;
+    }
+    let result = tokio::runtime::Runtime::new()
+        .map_err(|e| e.to_string())
+        .and_then(|rt| rt.block_on(run_benchmark(&backend, url, &counts, &work_dir)));
+    let _ = std::fs::remove_dir_all(&work_dir);
+    match result {
+        Ok(results) => {
+            println!("{:>9}  {:>10}  {:>8}  {:>12}", "Conexões", "Tamanho", "Tempo", "Velocidade");
+            for result in &results {
+                println!(
+                    "{:>9}  {:>10}  {:>7.1}s  {:>12}",
+                    result.connections,
+                    format_file_size(result.bytes),
+                    result.elapsed.as_secs_f64(),
+                    format_speed(result.speed())
+                );
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // Antes dos subcomandos: `keepers fetch` também usa o filtro, o proxy e as
+    // credenciais da pasta portátil e do perfil escolhido
+    let portable = executable_path().and_then(|exe| portable_data_dir(&exe, args.iter().any(|arg| arg == PORTABLE_ARG)));
+    keepers::storage::set_portable_dir(portable.clone());
+
+    let profile = profile_from_args(&args).filter(|name| {
+        let valid = keepers::storage::valid_profile_name(name);
+        if !valid {
+            eprintln!("Perfil inválido \"{}\": usando o perfil padrão", name);
+        }
+        valid
+    });
+    keepers::storage::set_profile(profile.as_deref());
+
+    match args.get(1).map(String::as_str) {
+        Some("fetch") => std::process::exit(run_fetch(&subcommand_args(&args[2..]))),
+        Some("benchmark") => std::process::exit(run_benchmark_command(&subcommand_args(&args[2..]))),
+        _ => {}
+    }
+
+    let mut application_id = match &profile {
+        Some(name) => format!("{}.Profile_{}", APP_ID, name.replace('-', "_")),
+        None => APP_ID.to_string(),
+    };
+    // Instância própria: não entrega URLs a um Keepers instalado na máquina
+    if portable.is_some() {
+        application_id.push_str(".Portable");
+    }
+
+    // HANDLES_OPEN: `keepers <url>` com o app aberto entrega a URL à instância primária
     let app = Application::builder()
-        .application_id(APP_ID)
+        .application_id(application_id.as_str())
+        .flags(gio::ApplicationFlags::HANDLES_OPEN)
         .build();
 
     // Cria ações globais para o menu
@@ -158,96 +354,68 @@ fn main() {
     app.add_action(&show_action);
     app.add_action(&quit_action);
 
-    app.connect_activate(build_ui);
-    app.run();
-}
-
-fn get_data_file_path() -> PathBuf {
-    // Obtém diretório de dados do app (funciona em Linux, Windows, macOS)
-    let data_dir = dirs::data_local_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("keeper");
-
-    // Cria o diretório se não existir
-    let _ = std::fs::create_dir_all(&data_dir);
-
-    data_dir.join("downloads.json")
-}
-
-fn get_config_file_path() -> PathBuf {
-    let data_dir = dirs::data_local_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("keeper");
-    let _ = std::fs::create_dir_all(&data_dir);
-    data_dir.join("config.json")
-}
+    // --autostart vem da entrada de início automático (ver keepers::autostart)
+    app.add_main_option(
+        &AUTOSTART_ARG[2..],
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Aberto no login (respeita \"iniciar minimizado\")",
+        None,
+    );
+    // Já lido em profile_from_args; registrado para o GLib aceitar a opção
+    app.add_main_option(
+        &PROFILE_ARG[2..],
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Abre o perfil com configuração e histórico próprios",
+        Some("NOME"),
+    );
+    // Já lido antes de criar o app; registrado para o GLib aceitar a opção
+    app.add_main_option(
+        &PORTABLE_ARG[2..],
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Guarda configuração e histórico ao lado do executável",
+        None,
+    );
+    let launched_at_login = std::rc::Rc::new(std::cell::Cell::new(false));
+    app.connect_handle_local_options(glib::clone!(#[strong] launched_at_login, move |_, options| {
+        launched_at_login.set(options.contains(&AUTOSTART_ARG[2..]));
+        -1
+    }));
 
-fn load_config() -> AppConfig {
-    let file_path = get_config_file_path();
-    if !file_path.exists() {
-        return AppConfig {
-            download_directory: None,
-            window_width: None,
-            window_height: None,
-        };
-    }
-    match std::fs::read_to_string(&file_path) {
-        Ok(contents) => {
-            serde_json::from_str(&contents).unwrap_or_else(|_| AppConfig {
-                download_directory: None,
-                window_width: None,
-                window_height: None,
-            })
+    app.connect_activate(move |app| {
+        // Já em execução (inclusive oculta no login): só traz a janela para frente
+        if let Some(window) = main_window(app) {
+            window.present();
+            return;
         }
-        Err(_) => AppConfig {
-            download_directory: None,
-            window_width: None,
-            window_height: None,
-        },
-    }
-}
+        build_ui(app, launched_at_login.get());
+    });
 
-fn save_config(config: &AppConfig) {
-    let file_path = get_config_file_path();
-    match serde_json::to_string_pretty(config) {
-        Ok(json) => {
-            let temp_path = file_path.with_extension("json.tmp");
-            if let Err(e) = std::fs::write(&temp_path, json) {
-                eprintln!("Erro ao escrever arquivo de configuração temporário: {}", e);
-                return;
-            }
-            if let Err(e) = std::fs::rename(&temp_path, &file_path) {
-                eprintln!("Erro ao renomear arquivo de configuração: {}", e);
-                let _ = std::fs::remove_file(&temp_path);
-            }
+    // URLs vindas de "Abrir com" do navegador ou da linha de comando
+    app.connect_open(|app, files, _| {
+        if main_window(app).is_none() {
+            build_ui(app, false);
         }
-        Err(e) => {
-            eprintln!("Erro ao serializar configuração: {}", e);
+        let Some(window) = main_window(app) else {
+            return;
+        };
+        window.present();
+        for url in files.iter().filter_map(|file| url_from_text(&file.uri())) {
+            let _ = WidgetExt::activate_action(&window, "win.add-url", Some(&url.to_variant()));
         }
-    }
-}
-
-fn get_download_directory(config: &AppConfig) -> PathBuf {
-    if let Some(ref dir) = config.download_directory {
-        PathBuf::from(dir)
-    } else {
-        dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
-    }
+    });
+    app.run();
 }
 
-fn load_downloads() -> Vec<DownloadRecord> {
-    let file_path = get_data_file_path();
-
-    if !file_path.exists() {
-        return Vec::new();
-    }
-
-    match std::fs::read_to_string(&file_path) {
-        Ok(contents) => {
-            serde_json::from_str(&contents).unwrap_or_else(|_| Vec::new())
-        }
-        Err(_) => Vec::new(),
-    }
+fn main_window(app: &Application) -> Option<AdwApplicationWindow> {
+    app.windows()
+        .into_iter()
+        .find_map(|window| window.downcast::<AdwApplicationWindow>().ok())
 }
 
 fn format_file_size(bytes: u64) -> String {
@@ -270,58 +438,69 @@ fn format_file_size(bytes: u64) -> String {
     }
 }
 
-fn save_downloads(records: &[DownloadRecord]) {
-    let file_path = get_data_file_path();
-
-    match serde_json::to_string_pretty(records) {
-        Ok(json) => {
-            // Tenta escrever o arquivo, criando um arquivo temporário primeiro para garantir atomicidade
-            let temp_path = file_path.with_extension("json.tmp");
-            if let Err(e) = std::fs::write(&temp_path, json) {
-                eprintln!("Erro ao escrever arquivo temporário: {}", e);
-                return;
-            }
-            // Renomeia o arquivo temporário para o arquivo final (operação atômica)
-            if let Err(e) = std::fs::rename(&temp_path, &file_path) {
-                eprintln!("Erro ao renomear arquivo: {}", e);
-                let _ = std::fs::remove_file(&temp_path);
-            }
-        }
-        Err(e) => {
-            eprintln!("Erro ao serializar downloads: {}", e);
-        }
-    }
-}
-
-fn build_ui(app: &Application) {
+fn build_ui(app: &Application, launched_at_login: bool) {
     let style_manager = StyleManager::default();
     style_manager.set_color_scheme(libadwaita::ColorScheme::ForceDark);
 
     // Carrega downloads salvos e configurações
-    let saved_records = load_downloads();
+    let (saved_records, load_error) = match load_downloads() {
+        Ok(records) => (records, None),
+        Err(e) => {
+            eprintln!("{}", e);
+            (Vec::new(), Some(e))
+        }
+    };
     let config = load_config();
     let config_clone = config.clone();
+    // Já monta o client; o primeiro download não espera por isso
+    let clients = Arc::new(ClientPool::new());
+    if let Err(e) = clients.get(&config_client_options(&config)) {
+        eprintln!("Erro ao criar client: {}", e);
+    }
 
     let state = Arc::new(Mutex::new(AppState {
         downloads: Vec::new(),
         records: Arc::new(Mutex::new(saved_records.clone())),
         config: Arc::new(Mutex::new(config)),
         download_speeds: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        usage: Arc::new(Mutex::new(load_usage())),
+        hosts: Arc::new(Mutex::new(load_host_stats())),
+        speed_limiter: Arc::new(SpeedLimiter::new(config_clone.speed_limit.active_limit())),
+        clients,
+        slots: Arc::new(DownloadSlots::new(config_clone.max_active_downloads)),
+        bus: EventBus::new(),
     }));
+    subscribe_download_speeds(&state);
+    // Progresso no registro mesmo com a janela oculta (os cards param de receber avisos)
+    if let Ok(app_state) = state.lock() {
+        let state_tasks = state.clone();
+        let tasks = move || state_tasks.lock().map(|app_state| app_state.downloads.clone()).unwrap_or_default();
+        start_autosave(tasks, app_state.records.clone(), std::time::Duration::from_secs(AUTOSAVE_INTERVAL_SECS));
+    }
 
+    let mut window_title = match keepers::storage::active_profile() {
+        Some(profile) => format!("Keepers — {}", profile),
+        None => "Keepers".to_string(),
+    };
+    if keepers::storage::portable_dir().is_some() {
+        window_title.push_str(" (portátil)");
+    }
     let window = AdwApplicationWindow::builder()
         .application(app)
-        .title("Keepers")
+        .title(window_title.as_str())
         .default_width(700)
         .default_height(500)
         .build();
 
-    // Aplica tamanho salvo se existir
+    // Aplica tamanho salvo se existir; maximizada volta maximizada (o tamanho salvo
+    // continua valendo ao sair da maximização). GTK4 não posiciona janelas: a
+    // posição e o monitor ficam a cargo do compositor.
     if let Some(width) = config_clone.window_width {
         if let Some(height) = config_clone.window_height {
             window.set_default_size(width, height);
         }
     }
+    window.set_maximized(config_clone.window_maximized);
 
 
     // ToastOverlay para notificações in-app
@@ -492,10 +671,51 @@ fn build_ui(app: &Application) {
 
     let menu = gio::Menu::new();
     menu.append(Some("Mostrar Janela"), Some("app.show"));
+    menu.append(Some("Verificar Links"), Some("win.check-links"));
+    menu.append(Some("Navegar em Diretório"), Some("win.browse-directory"));
+    menu.append(Some("Baixar Links de Página"), Some("win.grab-page"));
+    menu.append(Some("Exportar Fila"), Some("win.export-queue"));
+    menu.append(Some("Importar Fila"), Some("win.import-queue"));
+    menu.append(Some("Importar Links (uGet/JDownloader)"), Some("win.import-links"));
+    menu.append(Some("Restaurar Backup"), Some("win.restore-backup"));
+    menu.append(Some("Estatísticas de Uso"), Some("win.statistics"));
+    menu.append(Some("Perfis"), Some("win.profiles"));
+    menu.append(Some("Economia de Dados"), Some("win.data-saver"));
+    menu.append(Some("Preferências"), Some("win.preferences"));
+    menu.append(Some("Atalhos de Teclado"), Some("win.show-help-overlay"));
 
     // Submenu de configurações
     let config_menu = gio::Menu::new();
     config_menu.append(Some("Pasta de Downloads"), Some("app.config-downloads"));
+    config_menu.append(Some("Pasta de Concluídos"), Some("app.config-finished-folder"));
+    config_menu.append(Some("Antivírus"), Some("app.config-scanner"));
+    config_menu.append(Some("Organização"), Some("app.config-organize"));
+    config_menu.append(Some("Nome das Versões"), Some("app.config-version-naming"));
+    config_menu.append(Some("Modelos de Download"), Some("app.config-templates"));
+    config_menu.append(Some("Aparência"), Some("app.config-appearance"));
+    config_menu.append(Some("Criptografia"), Some("app.config-encryption"));
+    config_menu.append(Some("Filtro de URLs"), Some("app.config-url-filter"));
+    config_menu.append(Some("Conexões Simultâneas"), Some("app.config-connections"));
+    config_menu.append(Some("Servidores Limitados"), Some("app.config-segmented-hosts"));
+    config_menu.append(Some("Downloads Simultâneos"), Some("app.config-active-downloads"));
+    config_menu.append(Some("Novas Tentativas"), Some("app.config-auto-retry"));
+    config_menu.append(Some("Gravação em Disco"), Some("app.config-disk-writes"));
+    config_menu.append(Some("Conteúdo Compactado"), Some("app.config-content-encoding"));
+    config_menu.append(Some("Limite de Velocidade"), Some("app.config-speed-limit"));
+    config_menu.append(Some("Rede (IPv4/IPv6)"), Some("app.config-network"));
+    config_menu.append(Some("Reuso de Conexões"), Some("app.config-keep-alive"));
+    config_menu.append(Some("Proxy"), Some("app.config-proxy"));
+    config_menu.append(Some("Perfis de Rede"), Some("app.config-network-profiles"));
+    config_menu.append(Some("Certificado CA"), Some("app.config-ca"));
+    config_menu.append(Some("Metadados do Arquivo"), Some("app.config-provenance"));
+    config_menu.append(Some("Inicialização"), Some("app.config-startup"));
+    config_menu.append(Some("Atualizações"), Some("app.config-updates"));
+    config_menu.append(Some("Cota de Dados"), Some("app.config-quota"));
+    config_menu.append(Some("Bateria"), Some("app.config-battery"));
+    config_menu.append(Some("Armazenamento S3"), Some("app.config-s3"));
+    config_menu.append(Some("Gateways IPFS"), Some("app.config-ipfs"));
+    config_menu.append(Some("Resolvedores de Links"), Some("app.config-resolvers"));
+    config_menu.append(Some("Scripts"), Some("app.config-scripts"));
 
     let config_section = gio::Menu::new();
     config_section.append_submenu(Some("Configurações"), &config_menu);
@@ -509,6 +729,12 @@ fn build_ui(app: &Application) {
 
     header.pack_end(&menu_button);
 
+    // Alterna entre ilimitado (turbo) e o limite de velocidade configurado
+    let speed_toggle = gtk4::ToggleButton::builder()
+        .action_name("win.limit-speed")
+        .build();
+    header.pack_end(&speed_toggle);
+
     // Ação para configurações de pasta de downloads
     let config_action = gio::SimpleAction::new("config-downloads", None);
     let window_clone_config = window.clone();
@@ -519,72 +745,435 @@ fn build_ui(app: &Application) {
         let config_state = state_clone_config.clone();
         let toast_overlay_response = toast_overlay_config.clone();
 
-        // Cria diálogo de seleção de pasta
-        let dialog = FileChooserDialog::new(
-            Some("Selecionar Pasta de Downloads"),
-            Some(&config_window),
-            FileChooserAction::SelectFolder,
-            &[("Cancelar", gtk4::ResponseType::Cancel), ("Selecionar", gtk4::ResponseType::Accept)],
+        // Seleção de pasta
+        choose_folder(&config_window, "Selecionar Pasta de Downloads", "Selecionar", move |path| {
+            let path_str = path.to_string_lossy().to_string();
+
+            // Atualiza configuração
+            if let Ok(app_state) = config_state.lock() {
+                if let Ok(mut config) = app_state.config.lock() {
+                    config.download_directory = Some(path_str.clone());
+                    save_config(&config);
+                }
+            }
+
+            // Mostra toast com confirmação
+            let toast = libadwaita::Toast::new(&format!("Pasta de downloads alterada para:\n{}", path_str));
+            toast.set_timeout(5);
+            toast.set_priority(libadwaita::ToastPriority::High);
+
+            // Adiciona botão de ação para abrir a pasta
+            toast.set_button_label(Some("Abrir Pasta"));
+            toast.connect_button_clicked(move |_| {
+                let _ = platform::open_path(&path);
+            });
+
+            toast_overlay_response.add_toast(toast);
+        });
+    });
+    app.add_action(&config_action);
+
+    // Ação para a pasta de concluídos
+    let finished_folder_action = gio::SimpleAction::new("config-finished-folder", None);
+    let window_clone_finished = window.clone();
+    let state_clone_finished = state.clone();
+    let toast_overlay_finished = toast_overlay.clone();
+    finished_folder_action.connect_activate(move |_, _| {
+        show_finished_folder_dialog(&window_clone_finished, &state_clone_finished, &toast_overlay_finished);
+    });
+    app.add_action(&finished_folder_action);
+
+    // Ação para o antivírus rodado ao concluir
+    let scanner_action = gio::SimpleAction::new("config-scanner", None);
+    let window_clone_scanner = window.clone();
+    let state_clone_scanner = state.clone();
+    let toast_overlay_scanner = toast_overlay.clone();
+    scanner_action.connect_activate(move |_, _| {
+        show_scanner_dialog(&window_clone_scanner, &state_clone_scanner, &toast_overlay_scanner);
+    });
+    app.add_action(&scanner_action);
+
+    // Ação para as novas tentativas automáticas de downloads com falha
+    let auto_retry_action = gio::SimpleAction::new("config-auto-retry", None);
+    let window_clone_retry = window.clone();
+    let state_clone_retry = state.clone();
+    let toast_overlay_retry = toast_overlay.clone();
+    auto_retry_action.connect_activate(move |_, _| {
+        show_auto_retry_dialog(&window_clone_retry, &state_clone_retry, &toast_overlay_retry);
+    });
+    app.add_action(&auto_retry_action);
+
+    // Ação para configurar o máximo de conexões por download
+    let connections_action = gio::SimpleAction::new("config-connections", None);
+    let window_clone_connections = window.clone();
+    let state_clone_connections = state.clone();
+    let toast_overlay_connections = toast_overlay.clone();
+    connections_action.connect_activate(move |_, _| {
+        show_connections_dialog(&window_clone_connections, &state_clone_connections, &toast_overlay_connections);
+    });
+    app.add_action(&connections_action);
+
+    // Ação para os servidores que limitam a velocidade de cada conexão
+    let segmented_hosts_action = gio::SimpleAction::new("config-segmented-hosts", None);
+    let window_clone_segmented = window.clone();
+    let state_clone_segmented = state.clone();
+    let toast_overlay_segmented = toast_overlay.clone();
+    segmented_hosts_action.connect_activate(move |_, _| {
+        show_segmented_hosts_dialog(&window_clone_segmented, &state_clone_segmented, &toast_overlay_segmented);
+    });
+    app.add_action(&segmented_hosts_action);
+
+    // Ação para o nome das versões de downloads repetidos
+    let version_naming_action = gio::SimpleAction::new("config-version-naming", None);
+    let window_clone_version_naming = window.clone();
+    let state_clone_version_naming = state.clone();
+    let toast_overlay_version_naming = toast_overlay.clone();
+    version_naming_action.connect_activate(move |_, _| {
+        show_version_naming_dialog(
+            &window_clone_version_naming,
+            &state_clone_version_naming,
+            &toast_overlay_version_naming,
+        );
+    });
+    app.add_action(&version_naming_action);
+
+    // Ação para o limite de downloads simultâneos (fila)
+    let active_downloads_action = gio::SimpleAction::new("config-active-downloads", None);
+    let window_clone_active_downloads = window.clone();
+    let state_clone_active_downloads = state.clone();
+    let toast_overlay_active_downloads = toast_overlay.clone();
+    active_downloads_action.connect_activate(move |_, _| {
+        show_active_downloads_dialog(
+            &window_clone_active_downloads,
+            &state_clone_active_downloads,
+            &toast_overlay_active_downloads,
+        );
+    });
+    app.add_action(&active_downloads_action);
+
+    // Ação para configurar o intervalo de gravação do buffer no disco
+    let disk_writes_action = gio::SimpleAction::new("config-disk-writes", None);
+    let window_clone_disk_writes = window.clone();
+    let state_clone_disk_writes = state.clone();
+    let toast_overlay_disk_writes = toast_overlay.clone();
+    disk_writes_action.connect_activate(move |_, _| {
+        show_disk_writes_dialog(&window_clone_disk_writes, &state_clone_disk_writes, &toast_overlay_disk_writes);
+    });
+    app.add_action(&disk_writes_action);
+
+    // Ação para descompactar (ou não) corpos com Content-Encoding
+    let content_encoding_action = gio::SimpleAction::new("config-content-encoding", None);
+    let window_clone_encoding = window.clone();
+    let state_clone_encoding = state.clone();
+    let toast_overlay_encoding = toast_overlay.clone();
+    content_encoding_action.connect_activate(move |_, _| {
+        show_content_encoding_dialog(&window_clone_encoding, &state_clone_encoding, &toast_overlay_encoding);
+    });
+    app.add_action(&content_encoding_action);
+
+    let keep_alive_action = gio::SimpleAction::new("config-keep-alive", None);
+    let window_clone_keep_alive = window.clone();
+    let state_clone_keep_alive = state.clone();
+    let toast_overlay_keep_alive = toast_overlay.clone();
+    keep_alive_action.connect_activate(move |_, _| {
+        show_keep_alive_dialog(&window_clone_keep_alive, &state_clone_keep_alive, &toast_overlay_keep_alive);
+    });
+    app.add_action(&keep_alive_action);
+
+    let proxy_action = gio::SimpleAction::new("config-proxy", None);
+    let window_clone_proxy = window.clone();
+    let state_clone_proxy = state.clone();
+    let toast_overlay_proxy = toast_overlay.clone();
+    proxy_action.connect_activate(move |_, _| {
+        show_proxy_dialog(&window_clone_proxy, &state_clone_proxy, &toast_overlay_proxy);
+    });
+    app.add_action(&proxy_action);
+
+    let network_profiles_action = gio::SimpleAction::new("config-network-profiles", None);
+    let window_clone_network_profiles = window.clone();
+    let state_clone_network_profiles = state.clone();
+    let toast_overlay_network_profiles = toast_overlay.clone();
+    network_profiles_action.connect_activate(move |_, _| {
+        show_network_profiles_dialog(
+            &window_clone_network_profiles,
+            &state_clone_network_profiles,
+            &toast_overlay_network_profiles,
         );
+    });
+    app.add_action(&network_profiles_action);
+
+    // Ação para escolher a família de endereços preferida
+    let network_action = gio::SimpleAction::new("config-network", None);
+    let window_clone_network = window.clone();
+    let state_clone_network = state.clone();
+    let toast_overlay_network = toast_overlay.clone();
+    network_action.connect_activate(move |_, _| {
+        show_network_dialog(&window_clone_network, &state_clone_network, &toast_overlay_network);
+    });
+    app.add_action(&network_action);
+
+    // Ação para escolher um arquivo de CA adicional (servidores internos)
+    let ca_action = gio::SimpleAction::new("config-ca", None);
+    let window_clone_ca = window.clone();
+    let state_clone_ca = state.clone();
+    let toast_overlay_ca = toast_overlay.clone();
+    ca_action.connect_activate(move |_, _| {
+        show_ca_dialog(&window_clone_ca, &state_clone_ca, &toast_overlay_ca);
+    });
+    app.add_action(&ca_action);
+
+    // Ação para cifrar a lista de downloads com uma senha
+    let encryption_action = gio::SimpleAction::new("config-encryption", None);
+    let window_clone_encryption = window.clone();
+    let state_clone_encryption = state.clone();
+    let toast_overlay_encryption = toast_overlay.clone();
+    encryption_action.connect_activate(move |_, _| {
+        show_encryption_dialog(&window_clone_encryption, &state_clone_encryption, &toast_overlay_encryption);
+    });
+    app.add_action(&encryption_action);
+
+    // Ação para as regras de URLs permitidas e bloqueadas
+    let url_filter_action = gio::SimpleAction::new("config-url-filter", None);
+    let window_clone_url_filter = window.clone();
+    let state_clone_url_filter = state.clone();
+    let toast_overlay_url_filter = toast_overlay.clone();
+    url_filter_action.connect_activate(move |_, _| {
+        show_url_filter_dialog(&window_clone_url_filter, &state_clone_url_filter, &toast_overlay_url_filter);
+    });
+    app.add_action(&url_filter_action);
+
+    // Ação para escolher os metadados gravados nos arquivos concluídos
+    let provenance_action = gio::SimpleAction::new("config-provenance", None);
+    let window_clone_provenance = window.clone();
+    let state_clone_provenance = state.clone();
+    let toast_overlay_provenance = toast_overlay.clone();
+    provenance_action.connect_activate(move |_, _| {
+        show_provenance_dialog(&window_clone_provenance, &state_clone_provenance, &toast_overlay_provenance);
+    });
+    app.add_action(&provenance_action);
+
+    // Ação para início automático no login
+    let startup_action = gio::SimpleAction::new("config-startup", None);
+    let window_clone_startup = window.clone();
+    let state_clone_startup = state.clone();
+    let toast_overlay_startup = toast_overlay.clone();
+    startup_action.connect_activate(move |_, _| {
+        show_startup_dialog(&window_clone_startup, &state_clone_startup, &toast_overlay_startup);
+    });
+    app.add_action(&startup_action);
+
+    // Ação para o aviso de versão nova
+    let updates_action = gio::SimpleAction::new("config-updates", None);
+    let window_clone_updates = window.clone();
+    let state_clone_updates = state.clone();
+    let toast_overlay_updates = toast_overlay.clone();
+    updates_action.connect_activate(move |_, _| {
+        show_updates_dialog(&window_clone_updates, &state_clone_updates, &toast_overlay_updates);
+    });
+    app.add_action(&updates_action);
 
-        dialog.set_modal(true);
-
-        // Conecta a resposta
-        let config_state_response = config_state.clone();
-        dialog.connect_response(move |dialog, response| {
-            if response == gtk4::ResponseType::Accept {
-                if let Some(file) = dialog.file() {
-                    if let Some(path) = file.path() {
-                        let path_str = path.to_string_lossy().to_string();
-                        let path_display = path.clone();
-
-                        // Atualiza configuração
-                        if let Ok(app_state) = config_state_response.lock() {
-                            if let Ok(mut config) = app_state.config.lock() {
-                                config.download_directory = Some(path_str.clone());
-                                save_config(&config);
-
-                                // Mostra toast com confirmação
-                                let toast = libadwaita::Toast::new(&format!(
-                                    "Pasta de downloads alterada para:\n{}",
-                                    path_str
-                                ));
-                                toast.set_timeout(5);
-                                toast.set_priority(libadwaita::ToastPriority::High);
-
-                                // Adiciona botão de ação para abrir a pasta
-                                toast.set_button_label(Some("Abrir Pasta"));
-                                let path_for_action = path_display.clone();
-                                toast.connect_button_clicked(move |_| {
-                                    let _ = open::that(&path_for_action);
-                                });
-
-                                toast_overlay_response.add_toast(toast);
-                            }
-                        }
-                    }
+    // Estado true = velocidade limitada; sem limite configurado, abre a configuração
+    let limit_speed_action = gio::SimpleAction::new_stateful(
+        "limit-speed",
+        None,
+        &config_clone.speed_limit.enabled.to_variant(),
+    );
+    limit_speed_action.connect_change_state(glib::clone!(
+        #[weak] window,
+        #[strong] state,
+        #[strong] toast_overlay, move |action, value| {
+            let Some(enabled) = value.and_then(|value| value.get::<bool>()) else {
+                return;
+            };
+            let Ok(app_state) = state.lock() else {
+                return;
+            };
+            let Ok(mut config) = app_state.config.lock() else {
+                return;
+            };
+            if enabled && config.speed_limit.bytes_per_sec.is_none() {
+                drop(config);
+                drop(app_state);
+                let _ = WidgetExt::activate_action(&window, "app.config-speed-limit", None);
+                return;
+            }
+            config.speed_limit.enabled = enabled;
+            save_config(&config);
+            app_state.speed_limiter.set_limit(config.speed_limit.active_limit());
+            action.set_state(&enabled.to_variant());
+
+            let message = match config.speed_limit.active_limit() {
+                Some(limit) => format!("Velocidade limitada a {}", format_speed(limit as f64)),
+                None => "Velocidade ilimitada".to_string(),
+            };
+            toast_overlay.add_toast(libadwaita::Toast::new(&message));
+        }
+    ));
+    window.add_action(&limit_speed_action);
+
+    // Estado true = economia de dados (hotspot medido): downloads seguintes em uma
+    // conexão, sem consultas antecipadas nem sondagem de espelhos
+    let data_saver_action = gio::SimpleAction::new_stateful("data-saver", None, &config_clone.data_saver.to_variant());
+    data_saver_action.connect_change_state(glib::clone!(
+        #[strong] state,
+        #[strong] toast_overlay, move |action, value| {
+            let Some(enabled) = value.and_then(|value| value.get::<bool>()) else {
+                return;
+            };
+            if let Ok(app_state) = state.lock() {
+                if let Ok(mut config) = app_state.config.lock() {
+                    config.data_saver = enabled;
+                    save_config(&config);
                 }
             }
-            dialog.close();
+            action.set_state(&enabled.to_variant());
+            toast_overlay.add_toast(libadwaita::Toast::new(if enabled {
+                "Economia de dados: uma conexão por download, sem consultas antecipadas"
+            } else {
+                "Economia de dados desligada"
+            }));
+        }
+    ));
+    window.add_action(&data_saver_action);
+
+    // Ícone acompanha o estado (inclusive quando mudado pela configuração); a dica
+    // é montada ao exibir, para mostrar o limite atual
+    let update_speed_icon = glib::clone!(#[weak] speed_toggle, move || {
+        speed_toggle.set_icon_name(if speed_toggle.is_active() {
+            "power-profile-power-saver-symbolic"
+        } else {
+            "power-profile-performance-symbolic"
         });
+    });
+    update_speed_icon();
+    speed_toggle.connect_active_notify(move |_| update_speed_icon());
+    speed_toggle.set_has_tooltip(true);
+    speed_toggle.connect_query_tooltip(glib::clone!(#[strong] state, move |toggle, _, _, _, tooltip| {
+        let limit = state
+            .lock()
+            .ok()
+            .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.speed_limit.bytes_per_sec));
+        let text = match (toggle.is_active(), limit) {
+            (true, Some(limit)) => format!("Limitado a {}: clique para velocidade máxima", format_speed(limit as f64)),
+            (false, Some(limit)) => format!("Velocidade máxima: clique para limitar a {}", format_speed(limit as f64)),
+            _ => "Velocidade máxima: clique para definir um limite".to_string(),
+        };
+        tooltip.set_text(Some(&text));
+        true
+    }));
 
-        dialog.show();
+    // Ação para o limite de velocidade
+    let speed_limit_action = gio::SimpleAction::new("config-speed-limit", None);
+    let window_clone_speed = window.clone();
+    let state_clone_speed = state.clone();
+    speed_limit_action.connect_activate(move |_, _| {
+        show_speed_limit_dialog(&window_clone_speed, &state_clone_speed);
     });
-    app.add_action(&config_action);
+    app.add_action(&speed_limit_action);
+
+    // Ação para a cota mensal de dados
+    let quota_action = gio::SimpleAction::new("config-quota", None);
+    let window_clone_quota = window.clone();
+    let state_clone_quota = state.clone();
+    let toast_overlay_quota = toast_overlay.clone();
+    quota_action.connect_activate(move |_, _| {
+        show_quota_dialog(&window_clone_quota, &state_clone_quota, &toast_overlay_quota);
+    });
+    app.add_action(&quota_action);
+
+    // Ignora a cota até o fim do mês (botão do aviso de cota esgotada)
+    let quota_override_action = gio::SimpleAction::new("quota-override", None);
+    let state_clone_override = state.clone();
+    quota_override_action.connect_activate(move |_, _| {
+        if let Ok(app_state) = state_clone_override.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.quota.override_month = Some(month_key(Local::now()));
+                save_config(&config);
+            }
+        }
+    });
+    app.add_action(&quota_override_action);
+
+    // Ação para as subpastas automáticas
+    let organize_action = gio::SimpleAction::new("config-organize", None);
+    let window_clone_organize = window.clone();
+    let state_clone_organize = state.clone();
+    let toast_overlay_organize = toast_overlay.clone();
+    organize_action.connect_activate(move |_, _| {
+        show_organize_dialog(&window_clone_organize, &state_clone_organize, &toast_overlay_organize);
+    });
+    app.add_action(&organize_action);
+
+    // Ação para os modelos de download por padrão de URL
+    let templates_action = gio::SimpleAction::new("config-templates", None);
+    let window_clone_templates = window.clone();
+    let state_clone_templates = state.clone();
+    let toast_overlay_templates = toast_overlay.clone();
+    templates_action.connect_activate(move |_, _| {
+        show_templates_dialog(&window_clone_templates, &state_clone_templates, &toast_overlay_templates);
+    });
+    app.add_action(&templates_action);
+
+    // Ação para a pausa com bateria fraca
+    let battery_action = gio::SimpleAction::new("config-battery", None);
+    let window_clone_battery = window.clone();
+    let state_clone_battery = state.clone();
+    let toast_overlay_battery = toast_overlay.clone();
+    battery_action.connect_activate(move |_, _| {
+        show_battery_dialog(&window_clone_battery, &state_clone_battery, &toast_overlay_battery);
+    });
+    app.add_action(&battery_action);
+
+    // Ação para as credenciais de URLs s3://
+    let s3_action = gio::SimpleAction::new("config-s3", None);
+    let window_clone_s3 = window.clone();
+    let state_clone_s3 = state.clone();
+    let toast_overlay_s3 = toast_overlay.clone();
+    s3_action.connect_activate(move |_, _| {
+        show_s3_dialog(&window_clone_s3, &state_clone_s3, &toast_overlay_s3);
+    });
+    app.add_action(&s3_action);
+
+    // Ação para os gateways usados nas URLs ipfs://
+    let ipfs_action = gio::SimpleAction::new("config-ipfs", None);
+    let window_clone_ipfs = window.clone();
+    let state_clone_ipfs = state.clone();
+    let toast_overlay_ipfs = toast_overlay.clone();
+    ipfs_action.connect_activate(move |_, _| {
+        show_ipfs_dialog(&window_clone_ipfs, &state_clone_ipfs, &toast_overlay_ipfs);
+    });
+    app.add_action(&ipfs_action);
+
+    // Ação para os resolvedores de links (embutidos e plugins)
+    let resolvers_action = gio::SimpleAction::new("config-resolvers", None);
+    let window_clone_resolvers = window.clone();
+    let toast_overlay_resolvers = toast_overlay.clone();
+    resolvers_action.connect_activate(move |_, _| {
+        show_resolvers_dialog(&window_clone_resolvers, &toast_overlay_resolvers);
+    });
+    app.add_action(&resolvers_action);
+
+    // Ação para os scripts com ganchos de eventos
+    let scripts_action = gio::SimpleAction::new("config-scripts", None);
+    let window_clone_scripts = window.clone();
+    let toast_overlay_scripts = toast_overlay.clone();
+    scripts_action.connect_activate(move |_, _| {
+        show_scripts_dialog(&window_clone_scripts, &toast_overlay_scripts);
+    });
+    app.add_action(&scripts_action);
 
     // Ação para mostrar diálogo "Sobre"
     let about_action = gio::SimpleAction::new("about", None);
     let window_clone_about = window.clone();
     about_action.connect_activate(move |_, _| {
-        let about_window = libadwaita::AboutWindow::builder()
-            .transient_for(&window_clone_about)
+        let about_window = libadwaita::AboutDialog::builder()
             .application_name("Keeper")
             .application_icon("folder-download")
             .developer_name("Karan Luciano")
             .version("1.0.0")
             .comments("Gerenciador minimalista de downloads com suporte a downloads paralelos")
-            .website("https://github.com/KaranLuciano/Keeper")
-            .issue_url("https://github.com/KaranLuciano/Keeper/issues")
+            .website(PROJECT_URL)
+            .issue_url(format!("{}/issues", PROJECT_URL))
             .copyright("© 2025 Karan Luciano")
             .license_type(gtk4::License::MitX11)
             .build();
@@ -604,12 +1193,67 @@ fn build_ui(app: &Application) {
             ],
         );
 
-        about_window.present();
+        about_window.present(Some(&window_clone_about));
     });
     app.add_action(&about_action);
 
     main_box.append(&header);
 
+    // Aviso de falha ao ler ou gravar a lista de downloads
+    let storage_banner = libadwaita::Banner::builder().use_markup(false).build();
+    storage_banner.connect_button_clicked(|banner| {
+        banner.set_revealed(false);
+        let _ = banner.activate_action("win.restore-backup", None);
+    });
+    main_box.append(&storage_banner);
+    if let Some(error) = &load_error {
+        storage_banner.set_title(error);
+        storage_banner.set_button_label(Some("Restaurar Backup"));
+        storage_banner.set_revealed(true);
+    }
+    // Versão nova publicada (só com a opção ligada e fora da economia de dados)
+    let update_banner = libadwaita::Banner::builder().use_markup(false).button_label("Ver Novidades").build();
+    main_box.append(&update_banner);
+    if config_clone.check_updates && !config_clone.data_saver {
+        let (tx, rx) = async_channel::bounded(1);
+        if let Ok(app_state) = state.lock() {
+            start_update_check(app_state.config.clone(), tx);
+        }
+        glib::spawn_future_local(glib::clone!(#[weak] update_banner, async move {
+            match rx.recv().await {
+                Ok(Ok(Some(release))) => {
+                    update_banner.set_title(&format!("Keepers {} disponível (esta é a {})", release.version, CURRENT_VERSION));
+                    update_banner.connect_button_clicked(move |banner| {
+                        banner.set_revealed(false);
+                        if let Err(e) = open::that(&release.url) {
+                            eprintln!("Erro ao abrir {}: {}", release.url, e);
+                        }
+                    });
+                    update_banner.set_revealed(true);
+                }
+                Ok(Err(e)) => eprintln!("Erro ao procurar atualizações: {}", e),
+                _ => {}
+            }
+        }));
+    }
+    let showing_save_error = std::cell::Cell::new(false);
+    glib::timeout_add_seconds_local(2, glib::clone!(#[weak] storage_banner, #[upgrade_or] glib::ControlFlow::Break, move || {
+        match last_save_error() {
+            Some(error) => {
+                storage_banner.set_title(&error);
+                storage_banner.set_button_label(None);
+                storage_banner.set_revealed(true);
+                showing_save_error.set(true);
+            }
+            None if showing_save_error.get() => {
+                storage_banner.set_revealed(false);
+                showing_save_error.set(false);
+            }
+            None => {}
+        }
+        glib::ControlFlow::Continue
+    }));
+
     let scrolled = ScrolledWindow::builder()
         .hexpand(true)
         .vexpand(true)
@@ -618,11 +1262,39 @@ fn build_ui(app: &Application) {
         .margin_bottom(SPACING_LARGE)
         .build();
 
+    // Seleção simples: o card selecionado é o alvo da tecla Delete
     let list_box = ListBox::builder()
-        .selection_mode(gtk4::SelectionMode::None)
+        .selection_mode(gtk4::SelectionMode::Single)
         .css_classes(vec!["boxed-list"])
         .build();
 
+    // Busca por nome ou URL (Ctrl+F)
+    let search_entry = gtk4::SearchEntry::builder()
+        .placeholder_text("Buscar por nome ou URL")
+        .hexpand(true)
+        .build();
+    let search_bar = gtk4::SearchBar::builder()
+        .child(&search_entry)
+        .show_close_button(true)
+        .build();
+    search_bar.connect_entry(&search_entry);
+    list_box.set_filter_func(glib::clone!(#[weak] search_entry, #[upgrade_or] true, move |list_row| {
+        let query = search_entry.text().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+        list_row
+            .child()
+            .and_then(|child| child.downcast::<DownloadRow>().ok())
+            .and_then(|row| row.object())
+            .is_some_and(|object| {
+                object.filename().to_lowercase().contains(&query) || object.url().to_lowercase().contains(&query)
+            })
+    }));
+    search_entry.connect_search_changed(glib::clone!(#[weak] list_box, move |_| {
+        list_box.invalidate_filter();
+    }));
+
     // Container principal para incluir painel de métricas + lista
     let list_container = GtkBox::builder()
         .orientation(Orientation::Vertical)
@@ -742,10 +1414,41 @@ fn build_ui(app: &Application) {
     space_metrics_box.append(&space_metrics_value);
     space_metrics_box.append(&space_metrics_details);
 
+    // Métrica: Consumo do Mês (cota de dados)
+    let usage_metrics_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(4)
+        .css_classes(vec!["metric-card"])
+        .build();
+
+    let usage_metrics_title = Label::builder()
+        .label("Dados no Mês")
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["caption-heading", "dim-label"])
+        .build();
+
+    let usage_metrics_value = Label::builder()
+        .label("0 B")
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["title-2", "metric-value"])
+        .build();
+
+    let usage_metrics_details = Label::builder()
+        .label("Sem cota mensal")
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["caption", "dim-label"])
+        .wrap(true)
+        .build();
+
+    usage_metrics_box.append(&usage_metrics_title);
+    usage_metrics_box.append(&usage_metrics_value);
+    usage_metrics_box.append(&usage_metrics_details);
+
     // Adiciona as métricas ao grid
     metrics_grid.append(&status_metrics_box);
     metrics_grid.append(&speed_metrics_box);
     metrics_grid.append(&space_metrics_box);
+    metrics_grid.append(&usage_metrics_box);
 
     metrics_panel.append(&metrics_title);
     metrics_panel.append(&metrics_grid);
@@ -754,7 +1457,11 @@ fn build_ui(app: &Application) {
     list_container.append(&metrics_panel);
     list_container.append(&list_box);
 
-    scrolled.set_child(Some(&list_container));
+    let list_clamp = libadwaita::Clamp::builder()
+        .maximum_size(LIST_MAX_WIDTH)
+        .child(&list_container)
+        .build();
+    scrolled.set_child(Some(&list_clamp));
 
     // Função para atualizar métricas do painel
     let update_metrics = {
@@ -765,6 +1472,8 @@ fn build_ui(app: &Application) {
         let speed_details_update = speed_metrics_details.clone();
         let space_value_update = space_metrics_value.clone();
         let space_details_update = space_metrics_details.clone();
+        let usage_value_update = usage_metrics_value.clone();
+        let usage_details_update = usage_metrics_details.clone();
 
         move || {
             if let Ok(app_state) = state_metrics.lock() {
@@ -850,6 +1559,19 @@ fn build_ui(app: &Application) {
                         completed_size_str, completed_count
                     ));
                 }
+
+                // Consumo do mês e, se houver, a cota
+                let used = app_state.usage.lock().map(|usage| usage.month_total(Local::now())).unwrap_or(0);
+                let limit = app_state.config.lock().ok().and_then(|config| config.quota.monthly_limit);
+                usage_value_update.set_text(&if used > 0 { format_file_size(used) } else { "0 B".to_string() });
+                match limit {
+                    Some(limit) if limit > 0 => usage_details_update.set_text(&format!(
+                        "de {} ({}%)",
+                        format_file_size(limit),
+                        used.saturating_mul(100) / limit
+                    )),
+                    _ => usage_details_update.set_text("Sem cota mensal"),
+                }
             }
         }
     };
@@ -866,6 +1588,53 @@ fn build_ui(app: &Application) {
         }
     });
 
+    // Salva o consumo do mês e avisa ao passar de 80% e ao esgotar a cota
+    let mut last_saved_total = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.usage.lock().ok().map(|usage| usage.month_total(Local::now())))
+        .unwrap_or(0);
+    let mut last_quota_state = QuotaState::Normal;
+    glib::timeout_add_seconds_local(USAGE_CHECK_SECS, glib::clone!(#[strong] state, #[strong] toast_overlay, move || {
+        let now = Local::now();
+        let Some((usage, quota)) = state.lock().ok().map(|app_state| {
+            let quota = app_state.config.lock().map(|config| config.quota.clone()).unwrap_or_default();
+            (app_state.usage.clone(), quota)
+        }) else {
+            return glib::ControlFlow::Continue;
+        };
+        let Ok(mut usage) = usage.lock() else {
+            return glib::ControlFlow::Continue;
+        };
+
+        let total = usage.month_total(now);
+        if total != last_saved_total {
+            usage.prune_hours(now);
+            save_usage(&usage);
+            last_saved_total = total;
+        }
+
+        let quota_state = quota.state(&usage, now);
+        let overridden = quota.override_month == Some(month_key(now));
+        if quota_state != last_quota_state && !overridden {
+            match quota_state {
+                QuotaState::Warning => {
+                    toast_overlay.add_toast(libadwaita::Toast::new("80% da cota mensal de dados já foi usada"));
+                }
+                QuotaState::Exhausted => {
+                    let toast = libadwaita::Toast::new("Cota mensal esgotada: novos downloads começam pausados");
+                    toast.set_button_label(Some("Ignorar neste mês"));
+                    toast.set_action_name(Some("app.quota-override"));
+                    toast.set_timeout(0);
+                    toast_overlay.add_toast(toast);
+                }
+                QuotaState::Normal => {}
+            }
+        }
+        last_quota_state = quota_state;
+        glib::ControlFlow::Continue
+    }));
+
     // Estado vazio com botão de ação proeminente
     let empty_state_box = GtkBox::builder()
         .orientation(Orientation::Vertical)
@@ -902,42 +1671,49 @@ fn build_ui(app: &Application) {
     content_stack.add_named(&scrolled, Some("list"));
     content_stack.set_visible_child_name("empty");
 
+    main_box.append(&search_bar);
     main_box.append(&content_stack);
 
     // Carrega downloads salvos e adiciona à lista
-    if !saved_records.is_empty() {
-        content_stack.set_visible_child_name("list");
+    add_saved_records(&list_box, saved_records, &state, &content_stack);
+
+    restore_view_state(&search_entry, &search_bar, &scrolled, &config_clone.view);
+    // Busca e rolagem são salvas pouco depois de pararem de mudar
+    let view_generation = std::rc::Rc::new(std::cell::Cell::new(0u64));
+    let schedule_view_save = std::rc::Rc::new(glib::clone!(
+        #[weak] search_entry,
+        #[weak] scrolled,
+        #[strong] state,
+        move || {
+            const DEBOUNCE_MS: u64 = 500;
+
+            let current = view_generation.get() + 1;
+            view_generation.set(current);
+            glib::timeout_add_local_once(
+                std::time::Duration::from_millis(DEBOUNCE_MS),
+                glib::clone!(#[weak] search_entry, #[weak] scrolled, #[strong] state, #[strong] view_generation, move || {
+                    if view_generation.get() == current {
+                        save_view_state(&search_entry, &scrolled, &state);
+                    }
+                }),
+            );
+        }
+    ));
+    search_entry.connect_search_changed(glib::clone!(#[strong] schedule_view_save, move |_| schedule_view_save()));
+    scrolled.vadjustment().connect_value_changed(move |_| schedule_view_save());
+
+    // Verifica downloads recorrentes a cada 30 segundos (e logo ao abrir)
+    run_due_downloads(&list_box, &state, &content_stack);
+    glib::timeout_add_seconds_local(30, glib::clone!(
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[strong] state, #[upgrade_or] glib::ControlFlow::Break, move || {
+            run_due_downloads(&list_box, &state, &content_stack);
+            glib::ControlFlow::Continue
+        }
+    ));
 
-        // Separa downloads que devem retomar automaticamente
-        let mut to_resume = Vec::new();
-
-        for record in saved_records {
-            // Se estava em progresso e NÃO estava pausado, marca para retomar
-            if record.status == DownloadStatus::InProgress && !record.was_paused {
-                to_resume.push(record.url.clone());
-            } else {
-                // Caso contrário, mostra como download completo/pausado/falhado/cancelado
-                add_completed_download(&list_box, &record, &state, &content_stack);
-            }
-        }
-
-        // Remove downloads que vão retomar do JSON (evita duplicação)
-        if !to_resume.is_empty() {
-            if let Ok(app_state) = state.lock() {
-                if let Ok(mut records) = app_state.records.lock() {
-                    for url in &to_resume {
-                        records.retain(|r| &r.url != url);
-                    }
-                    save_downloads(&records);
-                }
-            }
-        }
-
-        // Retoma downloads ativos
-        for url in to_resume {
-            add_download(&list_box, &url, &state, &content_stack);
-        }
-    }
+    watch_battery(&list_box, &state, &toast_overlay);
 
     // Cria função para mostrar o diálogo de adicionar download
     let show_add_dialog = {
@@ -946,10 +1722,10 @@ fn build_ui(app: &Application) {
         let state_clone = state.clone();
         let window_clone = window.clone();
 
-        move || {
+        // `prefill`: URL já preenchida (ex.: vinda da área de transferência)
+        move |prefill: Option<String>| {
             // Cria a modal
-            let dialog = MessageDialog::builder()
-                .transient_for(&window_clone)
+            let dialog = libadwaita::AlertDialog::builder()
                 .heading("Adicionar Download")
                 .body("Insira a URL completa do arquivo que deseja baixar")
                 .build();
@@ -995,7 +1771,7 @@ fn build_ui(app: &Application) {
                     if let Ok(Some(text)) = result {
                         let text = text.to_string().trim().to_string();
                         // Verifica se é uma URL válida
-                        if (text.starts_with("http://") || text.starts_with("https://")) && !text.contains('\n') {
+                        if is_download_url(&text) && !text.contains('\n') {
                             url_entry_clone.set_text(&text);
                         }
                     }
@@ -1024,6 +1800,14 @@ fn build_ui(app: &Application) {
             preview_box.append(&preview_icon);
             preview_box.append(&preview_label);
 
+            // Dados do servidor (nome, tamanho, tipo, estratégia), consultados ao digitar
+            let metadata_label = Label::builder()
+                .halign(gtk4::Align::Start)
+                .css_classes(vec!["dim-label", "caption"])
+                .wrap(true)
+                .visible(false)
+                .build();
+
             // Histórico recente de URLs (últimos 5 downloads)
             let history_expander = libadwaita::ExpanderRow::builder()
                 .title("Histórico Recente")
@@ -1065,10 +1849,163 @@ fn build_ui(app: &Application) {
                 .css_classes(vec!["dim-label", "caption"])
                 .build();
 
+            // Página onde o link foi encontrado: vira o Referer das requisições
+            let referer_entry = Entry::builder()
+                .placeholder_text("Página de origem (opcional, enviada como Referer)")
+                .tooltip_text("Alguns servidores só liberam o arquivo para quem vem da página do link")
+                .build();
+
+            // Outros endereços do mesmo arquivo: os mais rápidos dividem os chunks
+            let mirrors_entry = Entry::builder()
+                .placeholder_text("Espelhos (opcional, URLs separadas por espaço)")
+                .tooltip_text("Cada espelho é testado antes do download; os mais rápidos baixam partes do arquivo ao mesmo tempo")
+                .build();
+
+            // Sincronização: downloads repetidos só baixam se o arquivo remoto mudou
+            let sync_check = gtk4::CheckButton::builder()
+                .label("Modo sincronização")
+                .tooltip_text("Ao sincronizar, usa ETag/Last-Modified e só baixa de novo se houver uma versão nova")
+                .build();
+
+            // Repetição automática (mantém as últimas versões com data no nome)
+            let repeat_dropdown = gtk4::DropDown::from_strings(&[
+                "Não repetir",
+                "Diariamente",
+                "Semanalmente",
+                "Personalizado (cron)",
+            ]);
+            repeat_dropdown.set_tooltip_text(Some("Baixa de novo automaticamente no mesmo horário"));
+            let cron_entry = Entry::builder()
+                .placeholder_text("min hora dia mês semana (ex.: 0 */6 * * *)")
+                .hexpand(true)
+                .visible(false)
+                .build();
+            let keep_spin = gtk4::SpinButton::with_range(1.0, 100.0, 1.0);
+            keep_spin.set_value(DEFAULT_KEEP_VERSIONS as f64);
+            keep_spin.set_tooltip_text(Some("Quantas versões manter"));
+            keep_spin.set_visible(false);
+            // Nome de cada versão baixada de novo (repetição ou sincronização)
+            let naming_dropdown = version_naming_dropdown("Nome das versões: padrão");
+            naming_dropdown.set_tooltip_text(Some("Substituir o arquivo anterior ou manter cada versão com data e hora ou contador no nome"));
+            naming_dropdown.set_halign(gtk4::Align::Start);
+            naming_dropdown.set_visible(false);
+            let naming_visible = {
+                let naming_dropdown = naming_dropdown.clone();
+                let repeat_dropdown = repeat_dropdown.clone();
+                let sync_check = sync_check.clone();
+                move || naming_dropdown.set_visible(repeat_dropdown.selected() != 0 || sync_check.is_active())
+            };
+            let naming_visible_sync = naming_visible.clone();
+            sync_check.connect_toggled(move |_| naming_visible_sync());
+            let repeat_box = GtkBox::builder()
+                .orientation(Orientation::Horizontal)
+                .spacing(8)
+                .build();
+            repeat_box.append(&Label::new(Some("Repetir")));
+            repeat_box.append(&repeat_dropdown);
+            repeat_box.append(&cron_entry);
+            repeat_box.append(&keep_spin);
+            let cron_entry_toggle = cron_entry.clone();
+            let keep_spin_toggle = keep_spin.clone();
+            repeat_dropdown.connect_selected_notify(move |dropdown| {
+                cron_entry_toggle.set_visible(dropdown.selected() == 3);
+                keep_spin_toggle.set_visible(dropdown.selected() != 0);
+                naming_visible();
+            });
+
+            // Confere com SHA256SUMS/.sha256 quando o servidor publica (comum em espelhos de ISOs)
+            let checksum_check = gtk4::CheckButton::builder()
+                .label("Verificar SHA-256 publicado pelo servidor")
+                .tooltip_text("Ao concluir, procura arquivo.sha256 ou SHA256SUMS na mesma pasta e confere o arquivo")
+                .active(true)
+                .build();
+
+            // Chunks em ordem para poder assistir/ouvir enquanto baixa
+            let sequential_check = gtk4::CheckButton::builder()
+                .label("Prioridade sequencial (pré-visualizar mídia)")
+                .tooltip_text("Baixa o início do arquivo primeiro para abrir o vídeo ou áudio antes de terminar")
+                .build();
+
+            // Desiste de transferências mortas para não segurar a vaga na fila (0 = sem limite)
+            let max_time_spin = gtk4::SpinButton::with_range(0.0, 10_000.0, 10.0);
+            max_time_spin.set_tooltip_text(Some("Minutos baixando até desistir (0 = sem prazo)"));
+            let min_speed_spin = gtk4::SpinButton::with_range(0.0, 1_000_000.0, 10.0);
+            min_speed_spin.set_tooltip_text(Some("KB/s abaixo dos quais o download desiste (0 = sem mínimo)"));
+            let min_speed_minutes_spin = gtk4::SpinButton::with_range(1.0, 600.0, 1.0);
+            min_speed_minutes_spin.set_value(10.0);
+            min_speed_minutes_spin.set_tooltip_text(Some("Minutos seguidos abaixo do mínimo"));
+            let limits_box = GtkBox::builder()
+                .orientation(Orientation::Horizontal)
+                .spacing(8)
+                .build();
+            limits_box.append(&Label::new(Some("Desistir após (min)")));
+            limits_box.append(&max_time_spin);
+            limits_box.append(&Label::new(Some("ou abaixo de (KB/s)")));
+            limits_box.append(&min_speed_spin);
+            limits_box.append(&Label::new(Some("por (min)")));
+            limits_box.append(&min_speed_minutes_spin);
+
+            // Perfil de rede (headers, credenciais, proxy e limite); só aparece se houver algum
+            let network_profiles: Vec<String> = state_clone
+                .lock()
+                .ok()
+                .and_then(|app_state| {
+                    app_state.config.lock().ok().map(|config| config.network_profiles.iter().map(|p| p.name.clone()).collect())
+                })
+                .unwrap_or_default();
+            let mut profile_choices = vec!["Sem perfil de rede"];
+            profile_choices.extend(network_profiles.iter().map(String::as_str));
+            let profile_dropdown = gtk4::DropDown::from_strings(&profile_choices);
+            profile_dropdown.set_tooltip_text(Some("Headers, credenciais, proxy e limite de velocidade do perfil"));
+            profile_dropdown.set_halign(gtk4::Align::Start);
+            profile_dropdown.set_visible(!network_profiles.is_empty());
+
+            // Opção para baixar pelo Tor (precisa do serviço tor rodando na porta 9050)
+            let tor_check = gtk4::CheckButton::builder()
+                .label("Usar Tor")
+                .tooltip_text("Conecta pelo Tor local (127.0.0.1:9050), com um circuito separado para este download")
+                .build();
+
+            // Privado: aparece enquanto baixa, mas não entra no histórico
+            let private_check = gtk4::CheckButton::builder()
+                .label("Download privado")
+                .tooltip_text("Não fica no histórico: o registro some ao concluir ou ao fechar o Keepers")
+                .build();
+
+            // Opção para servidores com certificado autoassinado ou expirado
+            let insecure_check = gtk4::CheckButton::builder()
+                .label("Aceitar certificado inválido")
+                .build();
+            let insecure_warning = Label::builder()
+                .label("⚠ Inseguro: a conexão não será verificada e pode ser interceptada. Use apenas em servidores confiáveis.")
+                .halign(gtk4::Align::Start)
+                .css_classes(vec!["warning", "caption"])
+                .wrap(true)
+                .visible(false)
+                .build();
+            let insecure_warning_toggle = insecure_warning.clone();
+            insecure_check.connect_toggled(move |check| {
+                insecure_warning_toggle.set_visible(check.is_active());
+            });
+
             main_box.append(&label);
             main_box.append(&url_entry);
             main_box.append(&preview_box);
+            main_box.append(&metadata_label);
             main_box.append(&help_label);
+            main_box.append(&referer_entry);
+            main_box.append(&mirrors_entry);
+            main_box.append(&repeat_box);
+            main_box.append(&limits_box);
+            main_box.append(&profile_dropdown);
+            main_box.append(&sync_check);
+            main_box.append(&naming_dropdown);
+            main_box.append(&checksum_check);
+            main_box.append(&sequential_check);
+            main_box.append(&tor_check);
+            main_box.append(&private_check);
+            main_box.append(&insecure_check);
+            main_box.append(&insecure_warning);
 
             // Só mostra histórico se houver registros
             if history_expander.first_child().is_some() {
@@ -1093,20 +2030,72 @@ fn build_ui(app: &Application) {
 
             main_box.append(&error_label);
 
+            // Histórico do servidor: avisa se ele costuma falhar ou não permite retomar
+            let host_warning_label = Label::builder()
+                .halign(gtk4::Align::Start)
+                .css_classes(vec!["warning", "caption"])
+                .wrap(true)
+                .visible(false)
+                .build();
+            main_box.append(&host_warning_label);
+
+            // Servidor já usado: oferece as opções do download mais recente dele
+            let suggestion: std::rc::Rc<std::cell::RefCell<Option<Suggestion>>> = Default::default();
+            let suggestion_check = gtk4::CheckButton::builder()
+                .active(true)
+                .visible(false)
+                .tooltip_text("Pasta, subpasta, perfil de rede, conexões e limite do último download deste servidor")
+                .build();
+            main_box.append(&suggestion_check);
+
             // Conecta validação em tempo real
             let dialog_clone = dialog.clone();
             let error_label_changed = error_label.clone();
             let preview_box_changed = preview_box.clone();
             let preview_label_changed = preview_label.clone();
+            let tor_check_changed = tor_check.clone();
+            let sequential_check_changed = sequential_check.clone();
+            let metadata_label_changed = metadata_label.clone();
+            let state_changed = state_clone.clone();
+            let suggestion_check_changed = suggestion_check.clone();
+            let suggestion_changed = suggestion.clone();
+            // Só a consulta da URL mais recente atualiza o rótulo
+            let metadata_generation = std::rc::Rc::new(std::cell::Cell::new(0u64));
             url_entry.connect_changed(move |entry| {
                 let url = entry.text().to_string().trim().to_string();
+                // Endereços .onion só funcionam pelo Tor
+                let host = url.split("://").nth(1).unwrap_or("").split(['/', ':', '?']).next().unwrap_or("");
+                if host.ends_with(".onion") {
+                    tor_check_changed.set_active(true);
+                }
+                // Vídeo/áudio: sugere baixar em ordem para poder pré-visualizar
+                if is_streamable_media(&sanitize_filename(&url)) {
+                    sequential_check_changed.set_active(true);
+                }
                 // Remove classe de erro quando usuário começar a digitar
                 entry.remove_css_class("error");
                 // Esconde mensagem de erro
                 error_label_changed.set_visible(false);
-                // Valida se tem conteúdo e começa com http:// ou https://
-                let is_valid = !url.is_empty() && (url.starts_with("http://") || url.starts_with("https://"));
+                // Valida se tem conteúdo e começa com http://, https://, s3://, ipfs:// ou ftp://
+                let mut is_valid = is_download_url(&url);
+                // Bloqueada pelo filtro de URLs: nem consulta o servidor
+                if is_valid {
+                    if let Err(e) = url_filter_check(&state_changed, &url) {
+                        error_label_changed.set_text(&e);
+                        error_label_changed.set_visible(true);
+                        is_valid = false;
+                    }
+                }
                 dialog_clone.set_response_enabled("download", is_valid);
+                let warning = if is_valid { host_warning_for(&state_changed, &url) } else { None };
+                host_warning_label.set_visible(warning.is_some());
+                host_warning_label.set_text(&warning.unwrap_or_default());
+                let found = if is_valid { suggestion_for(&state_changed, &url) } else { None };
+                if let Some(found) = &found {
+                    suggestion_check_changed.set_label(Some(&format!("Como no último download de {}: {}", found.host, found.summary())));
+                }
+                suggestion_check_changed.set_visible(found.is_some());
+                suggestion_changed.replace(found);
 
                 // Mostra preview do nome do arquivo se a URL for válida
                 if is_valid {
@@ -1120,11 +2109,21 @@ fn build_ui(app: &Application) {
                         preview_box_changed.set_visible(false);
                     }
 
+                    schedule_metadata_preview(
+                        &url,
+                        &filename_clean,
+                        &metadata_label_changed,
+                        &metadata_generation,
+                        &state_changed,
+                    );
+
                     dialog_clone.set_default_response(Some("download"));
                     // Reativa o activates_default quando válido
                     entry.set_activates_default(true);
                 } else {
                     preview_box_changed.set_visible(false);
+                    metadata_generation.set(metadata_generation.get() + 1);
+                    metadata_label_changed.set_visible(false);
                     dialog_clone.set_default_response(None);
                     entry.set_activates_default(false);
                 }
@@ -1135,6 +2134,61 @@ fn build_ui(app: &Application) {
             let content_stack_dialog = content_stack_clone.clone();
             let state_dialog = state_clone.clone();
             let url_entry_response = url_entry.clone();
+            let options_from_dialog = move || -> Result<DownloadOptions, String> {
+                let suggestion = suggestion_check.is_active().then(|| suggestion.borrow().clone()).flatten();
+                let now = Local::now();
+                let cron = match repeat_dropdown.selected() {
+                    1 => Some(daily_cron(now)),
+                    2 => Some(weekly_cron(now)),
+                    3 => {
+                        let cron = cron_entry.text().trim().to_string();
+                        CronSchedule::parse(&cron).map_err(|e| format!("Agendamento inválido: {}", e))?;
+                        Some(cron)
+                    }
+                    _ => None,
+                };
+                let referer = referer_entry.text().trim().to_string();
+                if !referer.is_empty() && !referer.starts_with("http://") && !referer.starts_with("https://") {
+                    return Err("Página de origem inválida. Use http:// ou https://".to_string());
+                }
+                let mirrors: Vec<String> = mirrors_entry.text().split_whitespace().map(str::to_string).collect();
+                if let Some(invalid) = mirrors.iter().find(|mirror| !is_download_url(mirror)) {
+                    return Err(format!("Espelho inválido: {}", invalid));
+                }
+                Ok(DownloadOptions {
+                    accept_invalid_certs: insecure_check.is_active(),
+                    use_tor: tor_check.is_active(),
+                    sync: sync_check.is_active(),
+                    verify_checksum: checksum_check.is_active(),
+                    sequential_priority: sequential_check.is_active(),
+                    subfolder: None,
+                    referer: (!referer.is_empty()).then_some(referer),
+                    private: private_check.is_active(),
+                    mirrors,
+                    network_profile: (profile_dropdown.selected() as usize)
+                        .checked_sub(1)
+                        .and_then(|index| network_profiles.get(index).cloned()),
+                    recurrence: cron.map(|cron| Recurrence {
+                        cron,
+                        keep_versions: keep_spin.value_as_int().max(1) as usize,
+                    }),
+                    version_naming: selected_version_naming(&naming_dropdown),
+                    max_minutes: Some(max_time_spin.value_as_int().max(0) as u64).filter(|minutes| *minutes > 0),
+                    min_speed: Some(min_speed_spin.value_as_int().max(0) as u64 * 1024)
+                        .filter(|speed| *speed > 0)
+                        .map(|bytes_per_sec| MinSpeed {
+                            bytes_per_sec,
+                            minutes: min_speed_minutes_spin.value_as_int().max(1) as u64,
+                        }),
+                    ..DownloadOptions::default()
+                })
+                .map(|mut options| {
+                    if let Some(suggestion) = &suggestion {
+                        apply_suggestion(suggestion, &mut options);
+                    }
+                    options
+                })
+            };
 
             // Conecta resposta da modal
             let error_label_response = error_label.clone();
@@ -1142,15 +2196,30 @@ fn build_ui(app: &Application) {
                 if response == "download" {
                     let url = url_entry_response.text().to_string().trim().to_string();
 
-                    // Valida se tem conteúdo e começa com http:// ou https://
-                    if url.is_empty() || (!url.starts_with("http://") && !url.starts_with("https://")) {
+                    // Valida se tem conteúdo e começa com http://, https://, s3://, ipfs:// ou ftp://
+                    if !is_download_url(&url) {
                         // URL inválida
                         url_entry_response.add_css_class("error");
-                        error_label_response.set_text("URL inválida. Use http:// ou https://");
+                        error_label_response.set_text("URL inválida. Use http://, https://, s3://, ipfs:// ou ftp://");
+                        error_label_response.set_visible(true);
+                        return;
+                    }
+                    if let Err(e) = url_filter_check(&state_dialog, &url) {
+                        url_entry_response.add_css_class("error");
+                        error_label_response.set_text(&e);
                         error_label_response.set_visible(true);
                         return;
                     }
 
+                    let options = match options_from_dialog() {
+                        Ok(options) => options,
+                        Err(e) => {
+                            error_label_response.set_text(&e);
+                            error_label_response.set_visible(true);
+                            return;
+                        }
+                    };
+
                     // Verifica se já existe um download com esta URL
                     let mut existing_record: Option<DownloadRecord> = None;
                     if let Ok(app_state) = state_dialog.lock() {
@@ -1161,8 +2230,7 @@ fn build_ui(app: &Application) {
 
                     if let Some(record) = existing_record {
                         // URL duplicada - mostra diálogo de aviso
-                        let warning_dialog = libadwaita::MessageDialog::new(
-                            Some(dialog),
+                        let warning_dialog = libadwaita::AlertDialog::new(
                             Some("Download Duplicado"),
                             Some("Este arquivo já existe na lista de downloads."),
                         );
@@ -1183,15 +2251,31 @@ fn build_ui(app: &Application) {
 
                         warning_dialog.set_body(&body_text);
                         warning_dialog.add_response("ok", "Entendi");
+                        warning_dialog.add_response("download-again", "Baixar Mesmo Assim");
                         warning_dialog.set_response_appearance("ok", libadwaita::ResponseAppearance::Suggested);
                         warning_dialog.set_default_response(Some("ok"));
                         warning_dialog.set_close_response("ok");
 
-                        warning_dialog.present();
+                        // Cada download tem seu próprio id, então a mesma URL pode ser baixada de novo
+                        let list_box_again = list_box_dialog.clone();
+                        let state_again = state_dialog.clone();
+                        let content_stack_again = content_stack_dialog.clone();
+                        let add_dialog = dialog.clone();
+                        let options_again = options.clone();
+                        warning_dialog.connect_response(Some("download-again"), move |_, _| {
+                            add_download(&list_box_again, new_download_id(), &url, options_again.clone(), &state_again, &content_stack_again);
+                            content_stack_again.set_visible_child_name("list");
+                            show_toast(&list_box_again, "Download adicionado");
+                            add_dialog.close();
+                        });
+
+                        // O diálogo de adicionar já fechou: o aviso fica sobre a janela principal
+                        warning_dialog.present(Some(&list_box_dialog));
                     } else {
                         // URL válida e não duplicada, pode adicionar
-                        add_download(&list_box_dialog, &url, &state_dialog, &content_stack_dialog);
+                        add_download(&list_box_dialog, new_download_id(), &url, options, &state_dialog, &content_stack_dialog);
                         content_stack_dialog.set_visible_child_name("list");
+                        show_toast(&list_box_dialog, "Download adicionado");
                         dialog.close();
                     }
                 } else {
@@ -1199,10 +2283,16 @@ fn build_ui(app: &Application) {
                 }
             });
 
+            // Preenche depois de conectar a validação, para já mostrar o preview
+            if let Some(url) = prefill {
+                url_entry.set_text(&url);
+                url_entry.set_position(-1);
+            }
+
             // Foca automaticamente no campo de entrada quando a modal abre
             url_entry.grab_focus();
 
-            dialog.present();
+            dialog.present(Some(&window_clone));
         }
     };
 
@@ -1210,26 +2300,243 @@ fn build_ui(app: &Application) {
     let add_action = gio::SimpleAction::new("add-download", None);
     let show_add_dialog_action = show_add_dialog.clone();
     add_action.connect_activate(move |_, _| {
-        show_add_dialog_action();
+        show_add_dialog_action(None);
     });
     window.add_action(&add_action);
 
+    // Mesmo diálogo já preenchido, para URLs recebidas de outra instância
+    let add_url_action = gio::SimpleAction::new("add-url", Some(glib::VariantTy::STRING));
+    let show_add_dialog_url = show_add_dialog.clone();
+    add_url_action.connect_activate(move |_, parameter| {
+        show_add_dialog_url(parameter.and_then(|url| url.get::<String>()));
+    });
+    window.add_action(&add_url_action);
+
     // Adiciona atalho de teclado Ctrl+N
     app.set_accels_for_action("win.add-download", &["<Ctrl>N"]);
 
+    // Verificação de vários links antes de adicionar
+    let check_links_action = gio::SimpleAction::new("check-links", None);
+    check_links_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[strong] state, move |_, _| {
+            show_link_checker(&window, &list_box, &state, &content_stack);
+        }
+    ));
+    window.add_action(&check_links_action);
+    app.set_accels_for_action("win.check-links", &["<Ctrl>L"]);
+
+    // Listagens de diretório remotas: escolher arquivos e pastas para baixar
+    let browse_action = gio::SimpleAction::new("browse-directory", None);
+    browse_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[strong] state, move |_, _| {
+            show_directory_browser(&window, &list_box, &state, &content_stack);
+        }
+    ));
+    window.add_action(&browse_action);
+    app.set_accels_for_action("win.browse-directory", &["<Ctrl>D"]);
+
+    // Todos os links de uma página, filtrados por extensão/expressão regular
+    let grab_page_action = gio::SimpleAction::new("grab-page", None);
+    grab_page_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[strong] state, move |_, _| {
+            show_page_grabber(&window, &list_box, &state, &content_stack);
+        }
+    ));
+    window.add_action(&grab_page_action);
+
+    // Fila em andamento: levar para outra máquina (com os .part) e trazer de volta
+    let export_queue_action = gio::SimpleAction::new("export-queue", None);
+    export_queue_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] toast_overlay,
+        #[strong] state, move |_, _| {
+            show_export_queue(&window, &state, &toast_overlay);
+        }
+    ));
+    window.add_action(&export_queue_action);
+
+    let import_queue_action = gio::SimpleAction::new("import-queue", None);
+    import_queue_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[weak] toast_overlay,
+        #[strong] state, move |_, _| {
+            show_import_queue(&window, &list_box, &state, &content_stack, &toast_overlay);
+        }
+    ));
+    window.add_action(&import_queue_action);
+
+    let import_links_action = gio::SimpleAction::new("import-links", None);
+    import_links_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[weak] toast_overlay,
+        #[strong] state, move |_, _| {
+            show_import_links(&window, &list_box, &state, &content_stack, &toast_overlay);
+        }
+    ));
+    window.add_action(&import_links_action);
+
+    let restore_backup_action = gio::SimpleAction::new("restore-backup", None);
+    restore_backup_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[weak] toast_overlay,
+        #[strong] state, move |_, _| {
+            show_restore_backup_dialog(&window, &list_box, &state, &content_stack, &toast_overlay);
+        }
+    ));
+    window.add_action(&restore_backup_action);
+
+    let profiles_action = gio::SimpleAction::new("profiles", None);
+    profiles_action.connect_activate(glib::clone!(#[weak] window, move |_, _| {
+        show_profiles_dialog(&window);
+    }));
+    window.add_action(&profiles_action);
+
+    // Gráficos do consumo de banda por hora/dia
+    let statistics_action = gio::SimpleAction::new("statistics", None);
+    statistics_action.connect_activate(glib::clone!(#[weak] window, #[strong] state, move |_, _| {
+        show_usage_statistics(&window, &state);
+    }));
+    window.add_action(&statistics_action);
+
     // Conecta botão do header
     let show_add_dialog_header = show_add_dialog.clone();
     add_download_btn.connect_clicked(move |_| {
-        show_add_dialog_header();
+        show_add_dialog_header(None);
     });
 
     // Conecta botão do empty state
+    let show_add_dialog_empty = show_add_dialog.clone();
     empty_add_btn.connect_clicked(move |_| {
-        show_add_dialog();
+        show_add_dialog_empty(None);
     });
 
+    // Ctrl+V na janela principal abre o diálogo com a URL copiada. Fica na fase
+    // de bubble: campos de texto com foco tratam o próprio Ctrl+V antes.
+    let paste_controller = gtk4::EventControllerKey::new();
+    let show_add_dialog_paste = show_add_dialog.clone();
+    paste_controller.connect_key_pressed(glib::clone!(
+        #[weak] window, #[upgrade_or] glib::Propagation::Proceed, move |_, key, _, modifiers| {
+            let is_paste = modifiers.contains(gtk4::gdk::ModifierType::CONTROL_MASK)
+                && matches!(key, gtk4::gdk::Key::v | gtk4::gdk::Key::V);
+            if !is_paste {
+                return glib::Propagation::Proceed;
+            }
+            add_from_clipboard(window.clipboard(), show_add_dialog_paste.clone());
+            glib::Propagation::Stop
+        }
+    ));
+    window.add_controller(paste_controller);
+
+    // Clique do meio cola a seleção primária (como em terminais e navegadores no Linux)
+    let middle_click = gtk4::GestureClick::builder().button(gtk4::gdk::BUTTON_MIDDLE).build();
+    middle_click.connect_pressed(glib::clone!(#[weak] window, move |_, _, _, _| {
+        add_from_clipboard(window.primary_clipboard(), show_add_dialog.clone());
+    }));
+    window.add_controller(middle_click);
+
+    // Demais atalhos da janela (lista completa em `show_shortcuts_window`)
+    let pause_all_action = gio::SimpleAction::new("pause-all", None);
+    pause_all_action.connect_activate(glib::clone!(#[weak] list_box, move |_, _| {
+        // Reaproveita o botão de pausa de cada card em andamento
+        for row in download_rows(&list_box) {
+            if row.object().is_some_and(|object| object.active() && object.download_state().is_running()) {
+                row.pause_button().emit_clicked();
+            }
+        }
+    }));
+    window.add_action(&pause_all_action);
+
+    let search_action = gio::SimpleAction::new("search", None);
+    search_action.connect_activate(glib::clone!(#[weak] search_bar, move |_, _| {
+        search_bar.set_search_mode(!search_bar.is_search_mode());
+    }));
+    window.add_action(&search_action);
+
+    let preferences_action = gio::SimpleAction::new("preferences", None);
+    preferences_action.connect_activate(glib::clone!(#[weak] window, #[strong] state, move |_, _| {
+        show_preferences(&window, &state);
+    }));
+    window.add_action(&preferences_action);
+
+    let shortcuts_action = gio::SimpleAction::new("show-help-overlay", None);
+    shortcuts_action.connect_activate(glib::clone!(#[weak] window, move |_, _| {
+        show_shortcuts_window(&window);
+    }));
+    window.add_action(&shortcuts_action);
+
+    app.set_accels_for_action("win.pause-all", &["<Ctrl>P"]);
+    app.set_accels_for_action("win.search", &["<Ctrl>F"]);
+    app.set_accels_for_action("win.preferences", &["<Ctrl>comma"]);
+    app.set_accels_for_action("win.show-help-overlay", &["<Ctrl>question"]);
+    app.set_accels_for_action("app.quit", &["<Ctrl>Q"]);
+
+    // Delete remove o card selecionado. Como o Ctrl+V, fica na fase de bubble
+    // para não roubar a tecla do campo de busca.
+    let delete_controller = gtk4::EventControllerKey::new();
+    delete_controller.connect_key_pressed(glib::clone!(
+        #[weak] list_box, #[upgrade_or] glib::Propagation::Proceed, move |_, key, _, _| {
+            if key != gtk4::gdk::Key::Delete {
+                return glib::Propagation::Proceed;
+            }
+            let selected = list_box
+                .selected_row()
+                .and_then(|list_row| list_row.child())
+                .and_then(|child| child.downcast::<DownloadRow>().ok());
+            match selected {
+                // O botão só aparece quando remover é permitido (não ativo)
+                Some(row) if row.delete_button().is_visible() => {
+                    row.delete_button().emit_clicked();
+                    glib::Propagation::Stop
+                }
+                _ => glib::Propagation::Proceed,
+            }
+        }
+    ));
+    window.add_controller(delete_controller);
+
     toast_overlay.set_child(Some(&main_box));
     window.set_content(Some(&toast_overlay));
+
+    // Layout adaptável: abaixo de NARROW_WIDTH os cards ficam compactos
+    window.set_size_request(360, 294);
+    let narrow = libadwaita::Breakpoint::new(libadwaita::BreakpointCondition::new_length(
+        libadwaita::BreakpointConditionLengthType::MaxWidth,
+        NARROW_WIDTH,
+        libadwaita::LengthUnit::Sp,
+    ));
+    narrow.add_setter(&badges_box, "visible", Some(&false.to_value()));
+    narrow.add_setter(&scrolled, "margin-start", Some(&SPACING_SMALL.to_value()));
+    narrow.add_setter(&scrolled, "margin-end", Some(&SPACING_SMALL.to_value()));
+    narrow.add_setter(&metrics_grid, "orientation", Some(&Orientation::Vertical.to_value()));
+    narrow.connect_apply(glib::clone!(#[weak] list_box, move |_| set_rows_compact(&list_box, true)));
+    narrow.connect_unapply(glib::clone!(#[weak] list_box, move |_| set_rows_compact(&list_box, false)));
+    window.add_breakpoint(narrow);
+
+    // Ação para as opções de aparência (precisa da lista para aplicar a densidade)
+    let appearance_action = gio::SimpleAction::new("config-appearance", None);
+    appearance_action.connect_activate(glib::clone!(
+        #[weak] window,
+        #[weak] list_box,
+        #[strong] state,
+        #[strong] toast_overlay,
+        move |_, _| show_appearance_dialog(&window, &list_box, &state, &toast_overlay)
+    ));
+    app.add_action(&appearance_action);
     
     // Adiciona CSS customizado usando design tokens
     let provider = CssProvider::new();
@@ -1285,59 +2592,18 @@ fn build_ui(app: &Application) {
             text-shadow: 0 0 3px rgba(0, 0, 0, 0.5);
         }}
 
-        /* Barra de progresso - Em Progresso (Azul) */
-        .download-progress.in-progress trough progress {{
-            background: {};
-            min-height: 20px;
-            border-radius: 6px;
-        }}
-
-        .download-progress.in-progress text {{
-            color: white;
-        }}
-
-        /* Barra de progresso - Pausado (Amarelo/Âmbar) */
-        .download-progress.paused trough progress {{
-            background: {};
-            min-height: 20px;
-            border-radius: 6px;
-        }}
-
-        .download-progress.paused text {{
-            color: rgba(0, 0, 0, 0.9);
-        }}
-
-        /* Barra de progresso - Completo (Verde) */
-        .download-progress.completed trough progress {{
-            background: {};
-            min-height: 20px;
-            border-radius: 6px;
-        }}
-
-        .download-progress.completed text {{
-            color: white;
-        }}
-
-        /* Barra de progresso - Cancelado (Cinza) */
-        .download-progress.cancelled trough progress {{
-            background: {};
-            min-height: 20px;
-            border-radius: 6px;
-        }}
-
-        .download-progress.cancelled text {{
-            color: white;
-        }}
+        /* Cores da barra e do badge por estado: ver status_css */
 
-        /* Barra de progresso - Falhou (Vermelho) */
-        .download-progress.failed trough progress {{
-            background: {};
-            min-height: 20px;
-            border-radius: 6px;
+        /* Lista compacta: cards e barras mais baixos */
+        .download-card.dense {{
+            padding: 4px 10px;
         }}
 
-        .download-progress.failed text {{
-            color: white;
+        .download-card.dense .download-progress,
+        .download-card.dense .download-progress trough,
+        .download-card.dense .download-progress trough progress {{
+            min-height: 12px;
+            font-size: 9px;
         }}
 
         /* Badges minimalistas - sem background, apenas cor de texto */
@@ -1348,26 +2614,6 @@ fn build_ui(app: &Application) {
             background-color: transparent;
         }}
 
-        .status-badge.completed {{
-            color: {};
-        }}
-
-        .status-badge.in-progress {{
-            color: {};
-        }}
-
-        .status-badge.paused {{
-            color: {};
-        }}
-
-        .status-badge.failed {{
-            color: {};
-        }}
-
-        .status-badge.cancelled {{
-            color: {};
-        }}
-
         /* Metadados minimalistas - sem background */
         .metadata-group {{
             padding: 0;
@@ -1471,18 +2717,6 @@ fn build_ui(app: &Application) {
         }}
     ",
         RADIUS_LARGE,
-        // Cores da barra de progresso por status
-        COLOR_INFO,           // in-progress (azul)
-        COLOR_WARNING,        // paused (amarelo/âmbar)
-        COLOR_SUCCESS,        // completed (verde)
-        COLOR_NEUTRAL,        // cancelled (cinza)
-        COLOR_ERROR,          // failed (vermelho)
-        // Cores dos badges de status
-        COLOR_SUCCESS,        // completed badge
-        COLOR_INFO,           // in-progress badge
-        COLOR_WARNING,        // paused badge
-        COLOR_ERROR,          // failed badge
-        COLOR_NEUTRAL,        // cancelled badge
         // Opacidades
         OPACITY_DIM_TEXT,
         OPACITY_CANCELLED,
@@ -1504,89 +2738,86 @@ fn build_ui(app: &Application) {
         "12px"                // padding dos cards
     );
     
-    provider.load_from_data(&css);
+    provider.load_from_data(&format!("{}{}{}", css, status_css(), accessible_status_css()));
+    apply_appearance(&window, &list_box, &config_clone.appearance);
     
     // Adiciona o provider CSS ao display
     if let Some(display) = gtk4::gdk::Display::default() {
         gtk4::style_context_add_provider_for_display(&display, &provider, gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION);
     }
     
-    // Salva tamanho da janela periodicamente durante redimensionamento
-    let state_save_size = state.clone();
-    let window_save_size = window.clone();
-    let save_timer_running = Arc::new(Mutex::new(false));
-    
-    {
-        let window_timer = window_save_size.clone();
-        let state_timer = state_save_size.clone();
-        let timer_running = save_timer_running.clone();
-        
-        glib::timeout_add_local(std::time::Duration::from_millis(500), move || {
-            if let Ok(mut running) = timer_running.lock() {
-                if *running {
-                    let (w, h) = window_timer.default_size();
-                    if let Ok(app_state) = state_timer.lock() {
-                        if let Ok(mut config) = app_state.config.lock() {
-                            config.window_width = Some(w);
-                            config.window_height = Some(h);
-                            save_config(&config);
+    // Salva tamanho e maximização pouco depois de a janela parar de mudar
+    let resize_generation = std::rc::Rc::new(std::cell::Cell::new(0u64));
+    for property in ["default-width", "default-height", "maximized"] {
+        window.connect_notify_local(
+            Some(property),
+            glib::clone!(#[strong] state, #[strong] resize_generation, move |window, _| {
+                const DEBOUNCE_MS: u64 = 500;
+
+                let current = resize_generation.get() + 1;
+                resize_generation.set(current);
+                glib::timeout_add_local_once(
+                    std::time::Duration::from_millis(DEBOUNCE_MS),
+                    glib::clone!(#[weak] window, #[strong] state, #[strong] resize_generation, move || {
+                        if resize_generation.get() == current {
+                            save_window_state(&window, &state);
                         }
-                    }
-                    *running = false;
-                }
-            }
-            glib::ControlFlow::Continue
-        });
-    }
-    
-    // Marca que precisa salvar quando a janela for redimensionada
-    // Usa um timer periódico que verifica o tamanho da janela
-    let window_check = window_save_size.clone();
-    let timer_check = save_timer_running.clone();
-    let last_size = Arc::new(Mutex::new((0, 0)));
-    
-    {
-        let window_size_check = window_check.clone();
-        let timer_size_check = timer_check.clone();
-        let last_size_check = last_size.clone();
-        
-        glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
-            let (w, h) = window_size_check.default_size();
-            let mut changed = false;
-            {
-                if let Ok(mut last) = last_size_check.lock() {
-                    if w != last.0 || h != last.1 {
-                        *last = (w, h);
-                        changed = true;
-                    }
-                }
-            }
-            if changed {
-                if let Ok(mut running) = timer_size_check.lock() {
-                    *running = true;
-                }
-            }
-            glib::ControlFlow::Continue
-        });
+                    }),
+                );
+            }),
+        );
     }
 
+    // Ao sair, para os downloads sem perder o que já foi gravado e salva o progresso exato
+    app.connect_shutdown(glib::clone!(#[strong] state, move |_| {
+        let Ok((downloads, records, usage)) = state
+            .lock()
+            .map(|app_state| (app_state.downloads.clone(), app_state.records.clone(), app_state.usage.clone()))
+        else {
+            return;
+        };
+        if !shutdown_downloads(&downloads, std::time::Duration::from_secs(SHUTDOWN_TIMEOUT_SECS)) {
+            eprintln!("Alguns downloads não pararam a tempo; serão retomados do último ponto salvo");
+        }
+        if let Ok(usage) = usage.lock() {
+            save_usage(&usage);
+        }
+
+        let Ok(mut records) = records.lock() else {
+            return;
+        };
+        snapshot_tasks(&downloads, &mut records);
+        save_downloads(&records);
+    }));
+
     // Salva tamanho quando a janela for fechada/minimizada
     let state_close = state.clone();
     let window_close = window.clone();
     window.connect_close_request(move |_| {
-        let (w, h) = window_close.default_size();
-        if let Ok(app_state) = state_close.lock() {
-            if let Ok(mut config) = app_state.config.lock() {
-                config.window_width = Some(w);
-                config.window_height = Some(h);
-                save_config(&config);
-            }
-        }
+        save_window_state(&window_close, &state_close);
         window_close.set_visible(false);
         glib::Propagation::Stop
     });
     
-    window.present();
+    // No login com "iniciar minimizado" a janela fica oculta; fila e agendamentos rodam mesmo assim
+    // Lista cifrada: a janela abre mesmo no login minimizado, para pedir a senha
+    if downloads_locked() {
+        window.present();
+        show_unlock_dialog(&window, &list_box, &state, &content_stack, &toast_overlay);
+    } else if !(launched_at_login && config_clone.startup.start_minimized) {
+        window.present();
+
+        // .part sem registro (lista corrompida ou apagada): oferece retomar
+        let download_dir = get_download_directory(&config_clone);
+        let records = state_records(&state).lock().map(|records| records.clone()).unwrap_or_default();
+        let orphans: Vec<OrphanPart> = find_orphan_parts(&download_dir, &records)
+            .into_iter()
+            .filter(|orphan| !config_clone.ignored_parts.contains(&orphan.part_path.to_string_lossy().to_string()))
+            .collect();
+        if !orphans.is_empty() {
+            show_orphan_parts_dialog(&window, &list_box, &state, &content_stack, &toast_overlay, orphans);
+        }
+    }
     
     // Nota: Esta implementação adiciona um menu no header
     // Para um verdadeiro system tray icon no Linux, você precisaria:
@@ -1595,2291 +2826,5415 @@ fn build_ui(app: &Application) {
     // Por enquanto, o menu no header funciona como alternativa
 }
 
-fn add_completed_download(list_box: &ListBox, record: &DownloadRecord, state: &Arc<Mutex<AppState>>, content_stack: &gtk4::Stack) {
-    let row_box = GtkBox::builder()
-        .orientation(Orientation::Vertical)
-        .spacing(SPACING_MEDIUM)
-        .margin_top(SPACING_MEDIUM)
-        .margin_bottom(SPACING_MEDIUM)
-        .margin_start(SPACING_MEDIUM)
-        .margin_end(SPACING_MEDIUM)
-        .css_classes(vec!["download-card"])
-        .build();
+// Volta a busca e, assim que a lista tiver altura suficiente, a rolagem
+fn restore_view_state(
+    search_entry: &gtk4::SearchEntry,
+    search_bar: &gtk4::SearchBar,
+    scrolled: &ScrolledWindow,
+    view: &ViewState,
+) {
+    if !view.search_text.is_empty() {
+        search_entry.set_text(&view.search_text);
+        search_bar.set_search_mode(true);
+    }
+    if view.scroll_position <= 0.0 {
+        return;
+    }
+    let position = view.scroll_position;
+    let restored = std::rc::Rc::new(std::cell::Cell::new(false));
+    scrolled.vadjustment().connect_changed(move |adjustment| {
+        if restored.get() || adjustment.upper() - adjustment.page_size() < position {
+            return;
+        }
+        restored.set(true);
+        adjustment.set_value(position);
+    });
+}
+
+fn save_view_state(search_entry: &gtk4::SearchEntry, scrolled: &ScrolledWindow, state: &Arc<Mutex<AppState>>) {
+    let view = ViewState {
+        search_text: search_entry.text().to_string(),
+        scroll_position: scrolled.vadjustment().value(),
+    };
+    if let Ok(app_state) = state.lock() {
+        if let Ok(mut config) = app_state.config.lock() {
+            if config.view != view {
+                config.view = view;
+                save_config(&config);
+            }
+        }
+    }
+}
 
-    // Se estiver cancelado, aplica estilo especial (opaco)
-    let is_cancelled = record.status == DownloadStatus::Cancelled;
-    if is_cancelled {
-        row_box.add_css_class("cancelled-download");
+// Tamanho fora da maximização (default-size) e se a janela está maximizada
+fn save_window_state(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>) {
+    let (width, height) = window.default_size();
+    if let Ok(app_state) = state.lock() {
+        if let Ok(mut config) = app_state.config.lock() {
+            config.window_width = Some(width);
+            config.window_height = Some(height);
+            config.window_maximized = window.is_maximized();
+            save_config(&config);
+        }
     }
+}
 
-    // Header com título - tipografia melhorada
-    let title_label = Label::builder()
-        .halign(gtk4::Align::Start)
-        .hexpand(true)
-        .css_classes(vec!["title-2"])
-        .ellipsize(gtk4::pango::EllipsizeMode::End)
-        .build();
+// Registra um evento na linha do tempo e devolve a lista para gravar no registro
+fn log_task_event(download_task: &Arc<Mutex<DownloadTask>>, text: impl Into<String>) -> Vec<DownloadEvent> {
+    download_task
+        .lock()
+        .map(|mut task| {
+            task.log(text);
+            task.events.clone()
+        })
+        .unwrap_or_default()
+}
 
-    // Se cancelado, adiciona risco no meio do texto usando Pango markup
-    if is_cancelled {
-        title_label.set_markup(&markup_title_strikethrough(&record.filename));
+// Registros compartilhados com as threads de download
+fn state_records(state: &Arc<Mutex<AppState>>) -> Arc<Mutex<Vec<DownloadRecord>>> {
+    if let Ok(app_state) = state.lock() {
+        app_state.records.clone()
     } else {
-        title_label.set_markup(&markup_title(&record.filename));
+        Arc::new(Mutex::new(Vec::new()))
     }
+}
 
-    // Barra de progresso
-    let (fraction, text) = if record.status == DownloadStatus::InProgress && record.total_bytes > 0 {
-        let progress = record.downloaded_bytes as f64 / record.total_bytes as f64;
-        (progress, format!("{:.0}%", progress * 100.0))
-    } else if record.status == DownloadStatus::Completed {
-        (1.0, "100%".to_string())
-    } else {
-        (0.0, "0%".to_string())
+fn publish_event(state: &Arc<Mutex<AppState>>, event: events::DownloadEvent) {
+    if let Ok(app_state) = state.lock() {
+        app_state.bus.publish(event);
+    }
+}
+
+// Velocidade atual de cada download (painel de métricas), mantida pelo barramento:
+// entra com o progresso e sai quando o download para, termina ou é removido
+fn subscribe_download_speeds(state: &Arc<Mutex<AppState>>) {
+    let Ok((mut receiver, speeds)) = state.lock().map(|app_state| (app_state.bus.subscribe(), app_state.download_speeds.clone())) else {
+        return;
     };
+    glib::spawn_future_local(async move {
+        loop {
+            let (id, speed) = match receiver.recv().await {
+                Ok(events::DownloadEvent::Progress(id, update)) => (id, Some(update.speed_bps)),
+                Ok(events::DownloadEvent::StateChanged(id, download_state)) if !download_state.is_running() => (id, None),
+                Ok(event @ (events::DownloadEvent::Completed { .. } | events::DownloadEvent::Failed { .. } | events::DownloadEvent::Removed(_))) => (event.id(), None),
+                Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            if let Ok(mut speeds) = speeds.lock() {
+                match speed {
+                    Some(speed) => speeds.insert(id, speed),
+                    None => speeds.remove(&id),
+                };
+            }
+        }
+    });
+}
 
-    let progress_bar = gtk4::ProgressBar::builder()
-        .hexpand(true)
-        .show_text(true)
-        .fraction(fraction)
-        .text(&text)
-        .css_classes(vec!["download-progress"])
-        .build();
-
-    // Aplica classe CSS baseada no status
-    let progress_status_class = match record.status {
-        DownloadStatus::Completed => "completed",
-        DownloadStatus::InProgress => {
-            if record.was_paused {
-                "paused"
-            } else {
-                "in-progress"
+// Remove o card da lista (o ListBox envolve cada card em um ListBoxRow)
+fn remove_row(row: &DownloadRow) -> bool {
+    if let Some(parent) = row.parent() {
+        if let Some(grandparent) = parent.parent() {
+            if let Some(list_box) = grandparent.downcast_ref::<ListBox>() {
+                list_box.remove(&parent);
+                return true;
             }
         }
-        DownloadStatus::Failed => "failed",
-        DownloadStatus::Cancelled => "cancelled",
-    };
-    progress_bar.add_css_class(progress_status_class);
+    }
+    false
+}
 
-    // Box de status e metadados
-    let info_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_MEDIUM)
-        .build();
+// Remove o card e inicia o download de novo. Retomar mantém o registro (e o id);
+// com `from_scratch`, o registro é descartado e o .part apagado para não retomar.
+fn restart_download(
+    row: &DownloadRow,
+    object: &DownloadObject,
+    from_scratch: bool,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+) {
+    let id = object.download_id();
+    remove_row(row);
 
-    // Box para status com badge colorido
-    let status_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .halign(gtk4::Align::Start)
-        .hexpand(true)
-        .build();
+    if !from_scratch {
+        add_download(list_box, id, &object.url(), DownloadOptions::default(), state, content_stack);
+        return;
+    }
 
-    let (status_text, status_icon_name) = match record.status {
-        DownloadStatus::InProgress => {
-            if record.was_paused {
-                ("Pausado", Some("media-playback-pause-symbolic"))
-            } else {
-                ("Em progresso", Some("folder-download-symbolic"))
-            }
+    // Recomeçar do zero mantém as opções escolhidas para o download
+    let mut options = DownloadOptions::default();
+    let mut removed = None;
+    if let Ok(mut records) = state_records(state).lock() {
+        if let Some(record) = records.iter().find(|r| r.id == id) {
+            options = record.options.clone();
+            removed = Some(record.clone());
         }
-        DownloadStatus::Completed => ("Concluído", Some("emblem-ok-symbolic")),
-        DownloadStatus::Failed => ("Falhou", Some("dialog-error-symbolic")),
-        DownloadStatus::Cancelled => ("Cancelado", Some("process-stop-symbolic")),
-    };
+        records.retain(|r| r.id != id);
+        save_downloads(&records);
+    }
 
-    // Badge colorido para status
-    let status_badge = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .halign(gtk4::Align::Start)
-        .css_classes(vec!["status-badge"])
-        .build();
-
-    // Determina a classe CSS baseada no status
-    let badge_class = match record.status {
-        DownloadStatus::Completed => "completed",
-        DownloadStatus::InProgress => {
-            if record.was_paused {
-                "paused"
+    {
+        let download_dir = if let Ok(app_state) = state.lock() {
+            if let Ok(config_guard) = app_state.config.lock() {
+                get_download_directory(&config_guard)
             } else {
-                "in-progress"
+                dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
             }
+        } else {
+            dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
+        };
+        // O .part fica na pasta do modelo, quando o download tem uma
+        let download_dir = match &removed {
+            Some(record) => record_directory(record, &download_dir),
+            None => download_dir,
+        };
+        let temp_path = download_dir.join(format!("{}.part", object.filename()));
+        if temp_path.exists() {
+            let _ = std::fs::remove_file(&temp_path);
         }
-        DownloadStatus::Failed => "failed",
-        DownloadStatus::Cancelled => "cancelled",
-    };
-    status_badge.add_css_class(badge_class);
-
-    // Ícone de status (GTK symbolic)
-    if let Some(icon_name) = status_icon_name {
-        let status_icon = gtk4::Image::builder()
-            .icon_name(icon_name)
-            .pixel_size(16)
-            .build();
-        status_badge.append(&status_icon);
     }
 
-    // Texto de status
-    let status_label = Label::builder()
-        .halign(gtk4::Align::Start)
-        .build();
+    add_download(list_box, new_download_id(), &object.url(), options, state, content_stack);
+}
+
+// Cards de download na lista (cada card fica dentro de um ListBoxRow)
+// Acompanha a bateria pelo UPower: com carga baixa fora da tomada pausa os downloads
+// em andamento e, ao ligar na tomada, retoma só os que ela pausou
+fn watch_battery(list_box: &ListBox, state: &Arc<Mutex<AppState>>, toast_overlay: &libadwaita::ToastOverlay) {
+    let paused_ids = std::rc::Rc::new(std::cell::RefCell::new(Vec::<Uuid>::new()));
+    let paused_by_battery = std::rc::Rc::new(std::cell::Cell::new(false));
+
+    let check = glib::clone!(#[weak] list_box, #[strong] state, #[weak] toast_overlay, move |proxy: &gio::DBusProxy| {
+        let property = |name| proxy.cached_property(name);
+        let present = property("IsPresent").and_then(|value| value.get::<bool>()).unwrap_or(false);
+        let percentage = property("Percentage").and_then(|value| value.get::<f64>()).unwrap_or(100.0);
+        let upower_state = property("State").and_then(|value| value.get::<u32>()).unwrap_or(0);
+        let Some(status) = BatteryStatus::from_upower(present, percentage, upower_state) else {
+            return;
+        };
+        let settings = state
+            .lock()
+            .ok()
+            .and_then(|app_state| app_state.config.lock().ok().map(|config| config.battery))
+            .unwrap_or_default();
+        if !settings.pause_on_low_battery && !paused_by_battery.get() {
+            return;
+        }
 
-    status_label.set_markup(&markup_status(status_text));
+        match battery_action(status, settings.threshold_percent, paused_by_battery.get()) {
+            BatteryAction::Pause => {
+                paused_by_battery.set(true);
+                for row in download_rows(&list_box) {
+                    let Some(object) = row.object() else {
+                        continue;
+                    };
+                    if object.active() && object.download_state().is_running() {
+                        paused_ids.borrow_mut().push(object.download_id());
+                        row.pause_button().emit_clicked();
+                    }
+                }
+                if !paused_ids.borrow().is_empty() {
+                    toast_overlay.add_toast(libadwaita::Toast::new(&format!(
+                        "Bateria abaixo de {}%: downloads pausados até ligar na tomada",
+                        settings.threshold_percent
+                    )));
+                }
+            }
+            BatteryAction::Resume => {
+                paused_by_battery.set(false);
+                let ids = paused_ids.take();
+                for row in download_rows(&list_box) {
+                    let Some(object) = row.object() else {
+                        continue;
+                    };
+                    if ids.contains(&object.download_id()) && object.active() && object.download_state() == DownloadState::Paused {
+                        row.pause_button().emit_clicked();
+                    }
+                }
+                if !ids.is_empty() {
+                    toast_overlay.add_toast(libadwaita::Toast::new("Na tomada: downloads retomados"));
+                }
+            }
+            BatteryAction::Keep => {}
+        }
+    });
 
-    status_badge.append(&status_label);
-    status_box.append(&status_badge);
+    gio::DBusProxy::for_bus(
+        gio::BusType::System,
+        gio::DBusProxyFlags::NONE,
+        None,
+        UPOWER_BUS_NAME,
+        UPOWER_DISPLAY_DEVICE,
+        UPOWER_DEVICE_INTERFACE,
+        None::<&gio::Cancellable>,
+        move |result| {
+            let proxy = match result {
+                Ok(proxy) => proxy,
+                Err(e) => {
+                    eprintln!("UPower indisponível, pausa por bateria desativada: {}", e);
+                    return;
+                }
+            };
+            check(&proxy);
+            // O handler guarda o proxy: ele vive enquanto o app estiver aberto
+            let watched = proxy.clone();
+            proxy.connect_local("g-properties-changed", false, move |_| {
+                check(&watched);
+                None
+            });
+        },
+    );
+}
 
-    // Box para metadados (tamanho e data) - layout horizontal minimalista
-    let metadata_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .halign(gtk4::Align::End)
-        .css_classes(vec!["metadata-group"])
-        .build();
+fn download_rows(list_box: &ListBox) -> Vec<DownloadRow> {
+    let mut rows = Vec::new();
+    let mut child = list_box.first_child();
+    while let Some(widget) = child {
+        if let Some(row) = widget
+            .downcast_ref::<gtk4::ListBoxRow>()
+            .and_then(|list_row| list_row.child())
+            .and_then(|c| c.downcast::<DownloadRow>().ok())
+        {
+            rows.push(row);
+        }
+        child = widget.next_sibling();
+    }
+    rows
+}
 
-    // Label para tamanho do arquivo
-    let size_label = Label::builder()
-        .halign(gtk4::Align::End)
-        .build();
+fn find_row(list_box: &ListBox, id: Uuid) -> Option<DownloadRow> {
+    download_rows(list_box)
+        .into_iter()
+        .find(|row| row.object().is_some_and(|object| object.download_id() == id))
+}
 
-    let size_text = if record.total_bytes > 0 {
-        format_file_size(record.total_bytes)
-    } else {
-        "Desconhecido".to_string()
+// Dispara os downloads recorrentes cuja próxima execução já passou.
+// Cada execução grava um novo arquivo com a data no nome.
+fn run_due_downloads(list_box: &ListBox, state: &Arc<Mutex<AppState>>, content_stack: &gtk4::Stack) {
+    let now = Utc::now();
+    let state_records = state_records(state);
+    let due: Vec<Uuid> = match state_records.lock() {
+        Ok(records) => records
+            .iter()
+            .filter(|r| r.options.recurrence.is_some() && r.next_run.is_some_and(|next| next <= now))
+            .filter(|r| matches!(r.status, DownloadStatus::Completed | DownloadStatus::Failed))
+            .map(|r| r.id)
+            .collect(),
+        Err(_) => return,
     };
-    size_label.set_markup(&markup_metadata_primary(&size_text));
-
-    let date_label = Label::builder()
-        .halign(gtk4::Align::End)
-        .css_classes(vec!["dim-label"])
-        .build();
 
-    // Data em tamanho menor e peso normal
-    let date_text = format!("{}", record.date_added.format("%d/%m/%Y %H:%M"));
-    date_label.set_markup(&markup_metadata_secondary(&date_text));
+    // Pausas com prazo que venceram com o app fechado
+    let resumable: Vec<Uuid> = match state_records.lock() {
+        Ok(records) => records
+            .iter()
+            .filter(|r| r.status == DownloadStatus::InProgress && r.resume_at.is_some_and(|at| at <= now))
+            .map(|r| r.id)
+            .collect(),
+        Err(_) => return,
+    };
+    for id in resumable {
+        if let Some(row) = find_row(list_box, id) {
+            if let Some(object) = row.object().filter(|object| !object.active()) {
+                restart_download(&row, &object, false, list_box, state, content_stack);
+            }
+        }
+    }
 
-    metadata_box.append(&size_label);
-    metadata_box.append(&date_label);
+    // Falhas passageiras com nova tentativa automática: as vencidas recomeçam,
+    // as outras só atualizam a contagem no card
+    let retries: Vec<(Uuid, DateTime<Utc>, Option<FailureKind>)> = match state_records.lock() {
+        Ok(records) => records
+            .iter()
+            .filter(|r| r.status == DownloadStatus::Failed)
+            .filter_map(|r| r.retry_at.map(|at| (r.id, at, r.failure)))
+            .collect(),
+        Err(_) => return,
+    };
+    for (id, at, failure) in retries {
+        let Some(row) = find_row(list_box, id) else {
+            continue;
+        };
+        let Some(object) = row.object().filter(|object| !object.active()) else {
+            continue;
+        };
+        if at > now {
+            let label = failure.map_or("Falhou", FailureKind::label);
+            object.set_status_text(format!("{} · {}", label, retry_text(at, now)));
+            continue;
+        }
+        if let Ok(mut records) = state_records.lock() {
+            if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                record.retry_attempts += 1;
+                record.retry_at = None;
+                push_event(&mut record.events, format!("Nova tentativa automática ({})", record.retry_attempts));
+            }
+            save_downloads(&records);
+        }
+        restart_download(&row, &object, false, list_box, state, content_stack);
+    }
 
-    info_box.append(&status_box);
-    info_box.append(&metadata_box);
+    for id in due {
+        let Some(row) = find_row(list_box, id) else {
+            continue;
+        };
+        let Some(object) = row.object() else {
+            continue;
+        };
+        if object.active() {
+            continue;
+        }
 
-    // Box de botões - mantém estrutura consistente em todos os estados
-    let buttons_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_MEDIUM)
-        .halign(gtk4::Align::End)
-        .build();
+        if let Ok(mut records) = state_records.lock() {
+            let Some(index) = records.iter().position(|r| r.id == id) else {
+                continue;
+            };
+            // O nome de cada versão é escolhido ao concluir; aqui volta o nome base
+            let filename = sanitize_filename(&records[index].url);
+            let filename = unique_name(&filename, |name| records.iter().any(|r| r.id != id && r.filename == name));
+            let record = &mut records[index];
+            record.filename = filename;
+            record.downloaded_bytes = 0;
+            record.next_run = record
+                .options
+                .recurrence
+                .as_ref()
+                .and_then(|recurrence| next_run(&recurrence.cron, now));
+            save_downloads(&records);
+        }
 
-    // Container para botões de ação primária (à esquerda)
-    let primary_actions_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .hexpand(true)
-        .halign(gtk4::Align::Start)
-        .build();
+        restart_download(&row, &object, false, list_box, state, content_stack);
+    }
+}
 
-    // Container para botões destrutivos (à direita)
-    let destructive_actions_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .halign(gtk4::Align::End)
-        .build();
+// Onde está o arquivo de um download concluído (registros antigos não guardam o caminho)
+fn completed_file_path(record: &DownloadRecord, config: &Arc<Mutex<AppConfig>>) -> PathBuf {
+    match record.file_path.as_deref() {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => {
+            let download_dir = config.lock().map(|config| get_download_directory(&config)).unwrap_or_default();
+            record_directory(record, &download_dir).join(&record.filename)
+        }
+    }
+}
 
-    // Botão de retomar (apenas para downloads em progresso)
-    if record.status == DownloadStatus::InProgress {
-        let resume_btn = Button::builder()
-            .icon_name("media-playback-start-symbolic")
-            .tooltip_text("Retomar download")
-            .css_classes(vec!["suggested-action"])
-            .build();
+// Aviso passageiro na janela de `widget` (o conteúdo da janela principal é um
+// ToastOverlay); fora dela, só vai para o log
+fn show_toast(widget: &impl IsA<gtk4::Widget>, message: &str) {
+    show_toast_with(widget, libadwaita::Toast::new(message));
+}
 
-        let record_url = record.url.clone();
-        let row_box_clone = row_box.clone();
-        let list_box_clone = list_box.clone();
-        let state_clone = state.clone();
-        let content_stack_clone = content_stack.clone();
-        let state_records = if let Ok(st) = state.lock() {
-            st.records.clone()
-        } else {
-            Arc::new(Mutex::new(Vec::new()))
-        };
+fn show_toast_with(widget: &impl IsA<gtk4::Widget>, toast: libadwaita::Toast) {
+    let overlay = widget
+        .root()
+        .and_downcast::<AdwApplicationWindow>()
+        .and_then(|window| window.content())
+        .and_downcast::<libadwaita::ToastOverlay>();
+    match overlay {
+        Some(overlay) => overlay.add_toast(toast),
+        None => eprintln!("{}", toast.title().unwrap_or_default()),
+    }
+}
 
-        resume_btn.connect_clicked(move |_| {
-            // Remove da UI
-            if let Some(parent) = row_box_clone.parent() {
-                if let Some(grandparent) = parent.parent() {
-                    if let Some(lb) = grandparent.downcast_ref::<ListBox>() {
-                        lb.remove(&parent);
-                    }
-                }
+// Aviso de falha com o próximo passo: credenciais para acesso negado, outra
+// pasta para erro no disco, nova tentativa para falhas passageiras
+fn show_failure_toast(row: &DownloadRow, filename: &str, kind: FailureKind) {
+    let toast = libadwaita::Toast::new(&format!("{}: {}", filename, kind.label()));
+    if let Some(label) = kind.action_label() {
+        toast.set_button_label(Some(label));
+        match kind {
+            FailureKind::AuthRequired => toast.set_action_name(Some("app.config-network-profiles")),
+            FailureKind::Disk => toast.set_action_name(Some("app.config-downloads")),
+            _ => {
+                let resume = row.resume_button();
+                toast.connect_button_clicked(move |_| resume.emit_clicked());
             }
+        }
+    }
+    show_toast_with(row, toast);
+}
 
-            // Remove do state.records e do JSON
-            if let Ok(mut records) = state_records.lock() {
-                records.retain(|r| r.url != record_url);
-                save_downloads(&records);
-            }
+// Link assinado que venceu no meio do download: com um link novo do mesmo
+// arquivo a retomada continua do .part, com o registro e os metadados mantidos
+fn show_expired_link_dialog(row: &DownloadRow, object: &DownloadObject, state: &Arc<Mutex<AppState>>) {
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Link Expirado"),
+        Some(&format!(
+            "O servidor recusou o link de {}, que já estava funcionando. Cole um link novo para o mesmo arquivo: o que já foi baixado é aproveitado.",
+            object.filename()
+        )),
+    );
 
-            // Reinicia o download (vai usar o arquivo .part existente)
-            add_download(&list_box_clone, &record_url, &state_clone, &content_stack_clone);
-        });
+    dialog.add_response("cancel", "Agora Não");
+    dialog.add_response("refresh", "Continuar Download");
+    dialog.set_response_appearance("refresh", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_response_enabled("refresh", false);
+    dialog.set_default_response(Some("refresh"));
+    dialog.set_close_response("cancel");
+
+    let entry = gtk4::Entry::new();
+    entry.set_placeholder_text(Some("https://..."));
+    entry.set_activates_default(true);
+    dialog.set_extra_child(Some(&entry));
+    entry.connect_changed(glib::clone!(#[weak] dialog, move |entry| {
+        dialog.set_response_enabled("refresh", is_download_url(entry.text().trim()));
+    }));
 
-        primary_actions_box.append(&resume_btn);
-    }
+    let state = state.clone();
+    dialog.connect_response(None, glib::clone!(#[weak] row, #[weak] object, move |_, response| {
+        if response != "refresh" {
+            return;
+        }
+        switch_download_url(&row, &object, &state, entry.text().trim(), "Link renovado");
+    }));
 
-    // Botão de reiniciar (apenas para downloads cancelados)
-    if record.status == DownloadStatus::Cancelled {
-        let restart_btn = Button::builder()
-            .icon_name("view-refresh-symbolic")
-            .tooltip_text("Reiniciar download do zero")
-            .css_classes(vec!["suggested-action"])
-            .build();
+    dialog.present(Some(row));
+}
 
-        let record_url = record.url.clone();
-        let record_filename = record.filename.clone();
-        let row_box_clone = row_box.clone();
-        let list_box_clone = list_box.clone();
-        let state_clone = state.clone();
-        let content_stack_clone = content_stack.clone();
-        let state_records = if let Ok(st) = state.lock() {
-            st.records.clone()
-        } else {
-            Arc::new(Mutex::new(Vec::new()))
+// Troca a origem de um download parado mantendo o registro (nome, .part,
+// metadados) e retoma. Antes, o novo endereço precisa entregar o mesmo arquivo:
+// tamanho, ETag e Last-Modified da resposta guardada (ver `check_same_content`).
+fn switch_download_url(row: &DownloadRow, object: &DownloadObject, state: &Arc<Mutex<AppState>>, url: &str, event: &str) {
+    let id = object.download_id();
+    let state_records = state_records(state);
+    let Some(record) = state_records.lock().ok().and_then(|records| records.iter().find(|r| r.id == id).cloned()) else {
+        return;
+    };
+    let Ok(config) = state.lock().map(|app_state| app_state.config.clone()) else {
+        return;
+    };
+
+    let previous_text = object.status_text();
+    object.set_status_text("Conferindo o novo link...");
+    let (tx, rx) = async_channel::bounded(1);
+    start_url_check(record, url.to_string(), config, tx);
+    let url = url.to_string();
+    let event = event.to_string();
+    glib::spawn_future_local(glib::clone!(#[weak] row, #[weak] object, async move {
+        let Ok(result) = rx.recv().await else {
+            return;
         };
+        if let Err(e) = result {
+            eprintln!("Novo link recusado ({}): {}", url, e);
+            object.set_status_text(previous_text);
+            show_toast(&row, &format!("Link não usado: {}", e));
+            return;
+        }
+        if let Ok(mut records) = state_records.lock() {
+            if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                record.url = url.clone();
+                record.failure = None;
+                push_event(&mut record.events, event);
+            }
+            save_downloads(&records);
+        }
+        object.set_url(url);
+        row.resume_button().emit_clicked();
+    }));
+}
+
+// Conecta os botões comuns a downloads ativos e do histórico
+fn connect_row_actions(row: &DownloadRow, object: &DownloadObject, list_box: &ListBox, state: &Arc<Mutex<AppState>>, content_stack: &gtk4::Stack) {
+    row.open_button().connect_clicked(glib::clone!(#[weak] object, move |button| {
+        // Abre o arquivo com o app padrão do sistema
+        if let Err(e) = platform::open_path(object.file_path()) {
+            eprintln!("Erro ao abrir arquivo: {}", e);
+            show_toast(button, "Não foi possível abrir o arquivo");
+        }
+    }));
 
-        restart_btn.connect_clicked(move |_| {
-            // Remove da UI
-            if let Some(parent) = row_box_clone.parent() {
-                if let Some(grandparent) = parent.parent() {
-                    if let Some(lb) = grandparent.downcast_ref::<ListBox>() {
-                        lb.remove(&parent);
+    row.open_folder_button().connect_clicked(glib::clone!(#[weak] object, move |button| {
+        // Abre a pasta que contém o arquivo no explorador, com ele selecionado quando o sistema permite
+        if let Err(e) = platform::reveal_file(Path::new(&object.file_path())) {
+            eprintln!("Erro ao abrir pasta: {}", e);
+            show_toast(button, "Não foi possível abrir a pasta");
+        }
+    }));
+
+    // Retomar usa o arquivo .part existente
+    row.resume_button().connect_clicked(glib::clone!(
+        #[weak] row,
+        #[weak] object,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[strong] state, move |_| {
+            restart_download(&row, &object, false, &list_box, &state, &content_stack);
+        }
+    ));
+
+    // Sincronizar reaproveita o registro: o motor envia If-None-Match/If-Modified-Since
+    row.sync_button().connect_clicked(glib::clone!(
+        #[weak] row,
+        #[weak] object,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[strong] state, move |_| {
+            restart_download(&row, &object, false, &list_box, &state, &content_stack);
+        }
+    ));
+
+    // Confere o arquivo concluído (hash guardado ou tamanho) e marca o registro
+    row.verify_button().connect_clicked(glib::clone!(#[weak] object, #[strong] state, move |button| {
+        let id = object.download_id();
+        let state_records = state_records(&state);
+        let Some(record) = state_records.lock().ok().and_then(|records| records.iter().find(|r| r.id == id).cloned()) else {
+            return;
+        };
+        let config = match state.lock() {
+            Ok(app_state) => app_state.config.clone(),
+            Err(_) => return,
+        };
+        let path = completed_file_path(&record, &config);
+
+        button.set_sensitive(false);
+        object.set_status_text("Verificando...");
+        let (tx, rx) = async_channel::bounded(1);
+        start_verify(record, path, config, tx);
+        glib::spawn_future_local(glib::clone!(#[weak] object, #[weak] button, async move {
+            let result = rx.recv().await;
+            button.set_sensitive(true);
+            let Ok(result) = result else {
+                return;
+            };
+            match result {
+                Ok(integrity) => {
+                    object.set_status_text(integrity.label());
+                    object.set_truncated(integrity == FileIntegrity::Truncated);
+                    if let Ok(mut records) = state_records.lock() {
+                        if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                            record.integrity = Some(integrity);
+                        }
+                        save_downloads(&records);
                     }
                 }
+                Err(e) => {
+                    eprintln!("Erro ao verificar arquivo: {}", e);
+                    object.set_status_text(format!("Não verificado: {}", e));
+                }
             }
+        }));
+    }));
 
-            // Remove do state.records e do JSON
-            if let Ok(mut records) = state_records.lock() {
-                records.retain(|r| r.url != record_url);
-                save_downloads(&records);
-            }
-
-            // Remove arquivo parcial se existir (para começar do zero)
-            let download_dir = if let Ok(app_state) = state_clone.lock() {
-                if let Ok(config_guard) = app_state.config.lock() {
-                    get_download_directory(&config_guard)
-                } else {
-                    dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
-                }
-            } else {
-                dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
+    // Busca só o final que falta de um arquivo concluído menor que o baixado
+    row.repair_button().connect_clicked(glib::clone!(#[weak] object, #[strong] state, move |button| {
+        let id = object.download_id();
+        let state_records = state_records(&state);
+        let Some(record) = state_records.lock().ok().and_then(|records| records.iter().find(|r| r.id == id).cloned()) else {
+            return;
+        };
+        let config = match state.lock() {
+            Ok(app_state) => app_state.config.clone(),
+            Err(_) => return,
+        };
+        let path = completed_file_path(&record, &config);
+
+        button.set_sensitive(false);
+        object.set_status_text("Reparando...");
+        let (tx, rx) = async_channel::bounded(1);
+        start_repair(record, path, config, tx);
+        glib::spawn_future_local(glib::clone!(#[weak] object, #[weak] button, async move {
+            let result = rx.recv().await;
+            button.set_sensitive(true);
+            let Ok(result) = result else {
+                return;
             };
-            let temp_path = download_dir.join(format!("{}.part", record_filename));
-            if temp_path.exists() {
-                let _ = std::fs::remove_file(&temp_path);
+            match result {
+                Ok(written) => {
+                    object.set_status_text(format!("Reparado ({} recuperados)", format_file_size(written)));
+                    object.set_truncated(false);
+                    if let Ok(mut records) = state_records.lock() {
+                        if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                            record.integrity = Some(FileIntegrity::Intact);
+                        }
+                        save_downloads(&records);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Erro ao reparar arquivo: {}", e);
+                    object.set_status_text(format!("Não reparado: {}", e));
+                }
             }
+        }));
+    }));
 
-            // Inicia novo download do zero
-            add_download(&list_box_clone, &record_url, &state_clone, &content_stack_clone);
+    row.restart_button().connect_clicked(glib::clone!(
+        #[weak] row,
+        #[weak] object,
+        #[weak] list_box,
+        #[weak] content_stack,
+        #[strong] state, move |_| {
+            restart_download(&row, &object, true, &list_box, &state, &content_stack);
+        }
+    ));
+
+    row.info_button().connect_clicked(glib::clone!(#[weak] row, #[weak] object, #[strong] state, move |button| {
+        let Some(record) = state_records(&state)
+            .lock()
+            .ok()
+            .and_then(|records| records.iter().find(|r| r.id == object.download_id()).cloned())
+        else {
+            return;
+        };
+        // A origem só muda com o download parado e ainda por terminar
+        let editable = !object.active() && record.status != DownloadStatus::Completed;
+        let edit_url = editable.then(|| -> Box<dyn Fn(String)> {
+            Box::new(glib::clone!(#[weak] row, #[weak] object, #[strong] state, move |url| {
+                switch_download_url(&row, &object, &state, &url, "URL alterada");
+            }))
         });
+        show_download_info(button, &record, edit_url);
+    }));
 
-        primary_actions_box.append(&restart_btn);
-    }
+    // Comando equivalente para reproduzir o download no terminal; o toast oferece a versão wget
+    row.copy_command_button().connect_clicked(glib::clone!(#[weak] object, #[strong] state, move |button| {
+        let Ok(config) = state.lock().map(|app_state| app_state.config.clone()) else {
+            return;
+        };
+        let Some(record) = state_records(&state)
+            .lock()
+            .ok()
+            .and_then(|records| records.iter().find(|r| r.id == object.download_id()).cloned())
+        else {
+            return;
+        };
+        let output = completed_file_path(&record, &config);
+        let ca_bundle = config.lock().ok().and_then(|config| config.ca_bundle_path.clone());
+        let command = |tool| download_command(tool, &record, &output, ca_bundle.as_deref());
+        let (curl, wget) = (command(CommandTool::Curl), command(CommandTool::Wget));
+
+        let clipboard = button.clipboard();
+        clipboard.set_text(&curl);
+        let toast = libadwaita::Toast::new("Comando curl copiado");
+        toast.set_button_label(Some("Copiar wget"));
+        toast.connect_button_clicked(move |_| clipboard.set_text(&wget));
+        show_toast_with(button, toast);
+    }));
 
-    // Botão de abrir (apenas para completados)
-    if record.status == DownloadStatus::Completed {
-        let open_btn = Button::builder()
-            .icon_name("document-open-symbolic")
-            .tooltip_text("Abrir arquivo")
-            .build();
+    // Arquivo na área de transferência: colar no Nautilus copia o próprio arquivo
+    row.copy_file_button().connect_clicked(glib::clone!(#[weak] row, move |button| {
+        match row.file_content() {
+            Some(content) => match button.clipboard().set_content(Some(&content)) {
+                Ok(()) => show_toast(button, "Arquivo copiado"),
+                Err(e) => {
+                    eprintln!("Erro ao copiar arquivo: {}", e);
+                    show_toast(button, "Não foi possível copiar o arquivo");
+                }
+            },
+            None => show_toast(button, "Arquivo não encontrado"),
+        }
+    }));
+
+    row.share_button().connect_clicked(glib::clone!(#[weak] object, move |button| {
+        let path = PathBuf::from(object.file_path());
+        if !path.exists() {
+            show_toast(button, "Arquivo não encontrado");
+            return;
+        }
+        show_share_dialog(button, path);
+    }));
+
+    row.delete_button().connect_clicked(glib::clone!(
+        #[weak] row,
+        #[weak] object,
+        #[weak] content_stack,
+        #[strong] state, move |_| {
+            // Remove do state.records e do arquivo de dados PRIMEIRO
+            let id = object.download_id();
+            let mut is_empty = false;
+            if let Ok(mut records) = state_records(&state).lock() {
+                let before_count = records.len();
+                records.retain(|r| r.id != id);
+                let after_count = records.len();
 
-        let file_path = record.file_path.clone();
-        open_btn.connect_clicked(move |_| {
-            if let Some(ref path) = file_path {
-                let _ = open::that(path);
+                if before_count == after_count {
+                    // Não encontrou o registro, pode já ter sido removido
+                    return;
+                }
+                save_downloads(&records);
+                is_empty = after_count == 0;
             }
-        });
+            publish_event(&state, events::DownloadEvent::Removed(id));
 
-        primary_actions_box.append(&open_btn);
+            // Remove da UI e, se a lista ficou vazia, mostra o estado vazio
+            if remove_row(&row) && is_empty {
+                content_stack.set_visible_child_name("empty");
+            }
+        }
+    ));
+}
 
-        // Botão de abrir explorador de arquivos
-        let open_folder_btn = Button::builder()
-            .icon_name("folder-open-symbolic")
-            .tooltip_text("Abrir pasta no explorador")
-            .build();
+// Alterna o layout compacto em todos os cards da lista
+fn set_rows_compact(list_box: &ListBox, compact: bool) {
+    for row in download_rows(list_box) {
+        row.set_compact(compact);
+    }
+}
 
-        let file_path_folder = record.file_path.clone();
-        open_folder_btn.connect_clicked(move |_| {
-            if let Some(ref path) = file_path_folder {
-                // Abre a pasta que contém o arquivo
-                if let Some(parent) = PathBuf::from(path).parent() {
-                    let _ = open::that(parent);
-                }
+// Cards novos seguem o layout atual da janela (largura e densidade)
+fn apply_row_layout(row: &DownloadRow, list_box: &ListBox) {
+    let Some(window) = list_box.root().and_downcast::<AdwApplicationWindow>() else {
+        return;
+    };
+    row.set_compact(window.current_breakpoint().is_some());
+    row.set_dense(window.has_css_class("dense-list"));
+}
+
+// Tira da lista um download privado concluído e avisa com um botão para abrir o arquivo
+fn forget_private_download(
+    row: &DownloadRow,
+    id: Uuid,
+    filename: &str,
+    file_path: String,
+    state_records: &Arc<Mutex<Vec<DownloadRecord>>>,
+    content_stack: &gtk4::Stack,
+) {
+    let toast = libadwaita::Toast::new(&format!("Download privado concluído: {}", filename));
+    if !file_path.is_empty() {
+        toast.set_button_label(Some("Abrir"));
+        toast.connect_button_clicked(move |_| {
+            if let Err(e) = platform::open_path(&file_path) {
+                eprintln!("Erro ao abrir arquivo: {}", e);
             }
         });
+    }
+    show_toast_with(row, toast);
 
-        primary_actions_box.append(&open_folder_btn);
+    let is_empty = match state_records.lock() {
+        Ok(mut records) => {
+            records.retain(|r| r.id != id);
+            records.is_empty()
+        }
+        Err(_) => false,
+    };
+    if remove_row(row) && is_empty {
+        content_stack.set_visible_child_name("empty");
     }
+}
 
-    // Botão de informações (sempre visível)
-    let info_btn = Button::builder()
-        .icon_name("info-symbolic")
-        .tooltip_text("Ver estatísticas e detalhes")
-        .build();
+// Registros lidos do disco (ao abrir ou ao desbloquear a lista cifrada)
+fn add_saved_records(list_box: &ListBox, records: Vec<DownloadRecord>, state: &Arc<Mutex<AppState>>, content_stack: &gtk4::Stack) {
+    if records.is_empty() {
+        return;
+    }
+    content_stack.set_visible_child_name("list");
 
-    let record_clone = record.clone();
-    info_btn.connect_clicked(move |_| {
-        // Cria diálogo de informações
-        let dialog = libadwaita::MessageDialog::new(
-            None::<&AdwApplicationWindow>,
-            Some("Informações do Download"),
-            None,
-        );
+    for record in records {
+        // Se estava em progresso e NÃO estava pausado, retoma com o mesmo id
+        if record.status == DownloadStatus::InProgress && !record.was_paused {
+            add_download(list_box, record.id, &record.url, record.options.clone(), state, content_stack);
+        } else {
+            // Caso contrário, mostra como download completo/pausado/falhado/cancelado
+            add_completed_download(list_box, &record, state, content_stack);
+        }
+    }
+}
 
-        dialog.add_response("close", "Fechar");
-        dialog.set_response_appearance("close", libadwaita::ResponseAppearance::Default);
-        dialog.set_default_response(Some("close"));
-        dialog.set_close_response("close");
+fn add_completed_download(list_box: &ListBox, record: &DownloadRecord, state: &Arc<Mutex<AppState>>, content_stack: &gtk4::Stack) {
+    let object = DownloadObject::from_record(record);
+    let row = DownloadRow::new();
+    row.bind(&object);
+    apply_row_layout(&row, list_box);
+    connect_row_actions(&row, &object, list_box, state, content_stack);
 
-        // Container principal
-        let main_box = GtkBox::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(16)
-            .margin_top(12)
-            .margin_bottom(12)
-            .margin_start(16)
-            .margin_end(16)
-            .build();
+    // Design minimalista - sem separadores entre cards
+    list_box.append(&row);
+}
 
-        // Nome do arquivo
-        let filename_group = GtkBox::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(4)
-            .build();
+fn add_download(
+    list_box: &ListBox,
+    id: Uuid,
+    url: &str,
+    mut options: DownloadOptions,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+) {
+    let state_records = state_records(state);
+    // Scripts do usuário podem reescrever a URL de downloads novos (não das retomadas)
+    let is_new = state_records.lock().map(|records| !records.iter().any(|r| r.id == id)).unwrap_or(false);
+    let url = if is_new { installed_hooks().url_added(url) } else { url.to_string() };
+    let url = url.as_str();
+    // Vale para todas as entradas (diálogo, linha de comando, área de transferência,
+    // pastas e páginas), inclusive a URL reescrita pelos scripts
+    if is_new {
+        if let Err(e) = url_filter_check(state, url) {
+            eprintln!("Download recusado ({}): {}", url, e);
+            show_toast_with(list_box, libadwaita::Toast::new(&format!("Download recusado: {}", e)));
+            return;
+        }
+    }
+    let mut filename = sanitize_filename(url);
+    let mut sync = options.sync;
+    let mut sequential_priority = options.sequential_priority;
+    let mut next_run_label = String::new();
+
+    // Cota mensal esgotada: o download entra na lista pausado (retomar manualmente ignora a cota)
+    let quota_blocked = state
+        .lock()
+        .ok()
+        .and_then(|app_state| {
+            let usage = app_state.usage.lock().ok()?;
+            let config = app_state.config.lock().ok()?;
+            Some(config.quota.blocks_new_downloads(&usage, Local::now()))
+        })
+        .unwrap_or(false);
+
+    let by_domain = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.organize.by_domain))
+        .unwrap_or(false);
+    // Modelo do primeiro padrão que casa: pasta, conexões e limite que o diálogo não definiu
+    if is_new {
+        let templates = state
+            .lock()
+            .ok()
+            .and_then(|app_state| app_state.config.lock().ok().map(|config| config.templates.clone()))
+            .unwrap_or_default();
+        apply_matching_template(&templates, url, &mut options);
+    }
 
-        let filename_label = Label::builder()
-            .label("Nome do Arquivo")
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["title-4"])
-            .build();
+    // Salva registro inicial como InProgress (ou atualiza existente)
+    if let Ok(mut records) = state_records.lock() {
+        // Verifica se já existe um registro com esse id (retomada)
+        if let Some(existing) = records.iter_mut().find(|r| r.id == id) {
+            // Atualiza o registro existente
+            existing.status = DownloadStatus::InProgress;
+            existing.date_completed = None;
+            existing.was_paused = quota_blocked;  // Retomando: só fica pausado pela cota
+            existing.resume_at = None;
+            existing.retry_at = None;
+            filename = existing.filename.clone();
+            sync = existing.options.sync;
+            sequential_priority = existing.options.sequential_priority;
+        } else {
+            // Adiciona novo registro (em progresso e não pausado)
+            // Subpasta escolhida (ex.: estrutura de um índice) ou, se configurado, o site de origem
+            let subfolder = options.subfolder.clone().or_else(|| by_domain.then(|| domain_folder(url)).flatten());
+            if let Some(subfolder) = &subfolder {
+                filename = subfolder_filename(subfolder, &filename);
+            }
+            filename = unique_filename(&records, &filename);
+            let next_run = options
+                .recurrence
+                .as_ref()
+                .and_then(|recurrence| next_run(&recurrence.cron, Utc::now()));
+            records.push(DownloadRecord {
+                id,
+                was_paused: quota_blocked,  // Ativo, a menos que a cota tenha esgotado
+                options,
+                next_run,
+                ..DownloadRecord::new(url, &filename)
+            });
+        }
+        next_run_label = records.iter().find(|r| r.id == id).map(next_run_text).unwrap_or_default();
+        save_downloads(&records);
+    }
+    if is_new {
+        publish_event(state, events::DownloadEvent::Added { id, url: url.to_string(), filename: filename.clone() });
+    }
 
-        let filename_value = Label::builder()
-            .label(&record_clone.filename)
-            .halign(gtk4::Align::Start)
-            .wrap(true)
-            .selectable(true)
-            .css_classes(vec!["caption"])
-            .build();
+    let object = DownloadObject::new(id, url, &filename);
+    object.set_sync(sync);
+    object.set_sequential_priority(sequential_priority);
+    object.set_next_run_text(next_run_label);
+    let row = DownloadRow::new();
+    row.bind(&object);
+    apply_row_layout(&row, list_box);
+    connect_row_actions(&row, &object, list_box, state, content_stack);
 
-        filename_group.append(&filename_label);
-        filename_group.append(&filename_value);
+    // Design minimalista - sem separadores entre cards
+    list_box.append(&row);
 
-        // URL de origem com botão de copiar
-        let url_group = GtkBox::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(4)
-            .build();
-
-        let url_label = Label::builder()
-            .label("URL de Origem")
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["title-4"])
-            .build();
-
-        let url_box = GtkBox::builder()
-            .orientation(Orientation::Horizontal)
-            .spacing(8)
-            .build();
-
-        let url_value = Label::builder()
-            .label(&record_clone.url)
-            .halign(gtk4::Align::Start)
-            .hexpand(true)
-            .wrap(true)
-            .ellipsize(gtk4::pango::EllipsizeMode::End)
-            .selectable(true)
-            .css_classes(vec!["caption"])
-            .build();
-
-        let copy_btn = Button::builder()
-            .icon_name("edit-copy-symbolic")
-            .tooltip_text("Copiar URL")
-            .valign(gtk4::Align::Start)
-            .build();
-
-        let record_url_copy = record_clone.url.clone();
-        let dialog_clone = dialog.clone();
-        copy_btn.connect_clicked(move |_| {
-            if let Some(display) = gtk4::gdk::Display::default() {
-                let clipboard = display.clipboard();
-                clipboard.set_text(&record_url_copy);
+    // Cria o download task
+    let download_task = Arc::new(Mutex::new(DownloadTask::new(id)));
+    if quota_blocked {
+        object.set_download_state(DownloadState::Paused, "Pausado: cota mensal esgotada");
+    }
 
-                // Feedback visual temporário
-                dialog_clone.set_body("URL copiada para a área de transferência");
+    if let Ok(mut state) = state.lock() {
+        if let Ok(mut task) = download_task.lock() {
+            if quota_blocked {
+                task.pause();
             }
-        });
-
-        url_box.append(&url_value);
-        url_box.append(&copy_btn);
-        url_group.append(&url_label);
-        url_group.append(&url_box);
+            task.usage = Some(state.usage.clone());
+            task.speed_limiter = Some(state.speed_limiter.clone());
+            task.clients = Some(state.clients.clone());
+            task.slots = Some(state.slots.clone());
+            task.bus = Some(state.bus.clone());
+            task.initial_connections = state.hosts.lock().ok().and_then(|hosts| hosts.initial_connections(url));
+            let segmented_hosts = state.config.lock().map(|config| config.segmented_hosts.clone()).unwrap_or_default();
+            task.force_segmented = state.hosts.lock().is_ok_and(|hosts| hosts.forces_segmented(url, &segmented_hosts));
+        }
+        state.downloads.push(download_task.clone());
+    }
 
-        // Tamanho do arquivo
-        let size_group = GtkBox::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(4)
-            .build();
+    // Cria channel para comunicação entre threads usando async-channel
+    let (msg_tx, msg_rx) = async_channel::unbounded();
 
-        let size_label = Label::builder()
-            .label("Tamanho")
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["title-4"])
-            .build();
+    // Inicia o download em thread separada
+    let config_clone = if let Ok(app_state) = state.lock() {
+        app_state.config.clone()
+    } else {
+        Arc::new(Mutex::new(AppConfig::default()))
+    };
+    start_download(url, &filename, msg_tx, download_task.clone(), state_records.clone(), config_clone);
 
-        let size_value = Label::builder()
-            .label(&format_file_size(record_clone.total_bytes))
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["caption"])
-            .build();
+    // Monitora mensagens na thread principal do GTK; o card acompanha o objeto
+    glib::spawn_future_local(glib::clone!(
+        #[strong] object,
+        #[weak] row,
+        #[weak] content_stack,
+        #[strong] download_task,
+        #[strong] state_records,
+        #[strong] state, async move {
+        // Média das velocidades medidas (histórico do servidor)
+        let (mut speed_sum, mut speed_samples) = (0.0, 0u32);
 
-        size_group.append(&size_label);
-        size_group.append(&size_value);
+        while let Ok(msg) = msg_rx.recv().await {
+            match msg {
+                DownloadMessage::Progress(update) => {
+                    // Tamanho desconhecido (resposta chunked): a barra pulsa
+                    object.set_indeterminate(update.total == 0);
+                    object.set_progress(update.fraction());
+                    if update.speed_bps > 0 {
+                        speed_sum += update.speed_bps as f64;
+                        speed_samples += 1;
+                    }
 
-        // Status
-        let status_group = GtkBox::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(4)
-            .build();
+                    // Se é um resume (tem bytes já baixados). O tamanho pode ter sido
+                    // corrigido pela resposta do GET: o registro acompanha
+                    let is_resuming = state_records
+                        .lock()
+                        .ok()
+                        .and_then(|mut records| {
+                            records.iter_mut().find(|r| r.id == id).map(|record| {
+                                if update.total > 0 {
+                                    record.total_bytes = update.total;
+                                }
+                                record.downloaded_bytes > 0
+                            })
+                        })
+                        .unwrap_or(false);
+                    if update.total > 0 {
+                        object.set_size_text(format_file_size(update.total));
+                    }
 
-        let status_label = Label::builder()
-            .label("Status")
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["title-4"])
-            .build();
+                    // O badge segue as mensagens State do motor
+                    object.set_status_text(update.status_text());
+                    object.set_speed_text(update.speed_text());
+                    object.set_eta_text(update.eta_text());
 
-        let status_text = match record_clone.status {
-            DownloadStatus::InProgress => if record_clone.was_paused { "Pausado" } else { "Em Progresso" },
-            DownloadStatus::Completed => "Concluído",
-            DownloadStatus::Failed => "Falhou",
-            DownloadStatus::Cancelled => "Cancelado",
-        };
+                    // Mostra tag apropriada baseado no modo de download
+                    object.set_parallel(update.parallel);
+                    object.set_connections(update.connections as u32);
+                    object.set_resuming(!update.parallel && is_resuming);
 
-        let status_value = Label::builder()
-            .label(status_text)
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["caption"])
-            .build();
+                    // O registro é atualizado pelo salvamento automático (ver start_autosave)
+                }
+                DownloadMessage::Renamed(filename) => {
+                    // Link de compartilhamento: o servidor informou o nome real do arquivo
+                    object.set_filename(filename.clone());
+                    if let Ok(mut records) = state_records.lock() {
+                        if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                            record.filename = filename;
+                        }
+                        save_downloads(&records);
+                    }
+                }
+                DownloadMessage::State(download_state) => {
+                    object.set_state(download_state.css_class());
+                    // Parado não vem com outro aviso: o texto explica o badge
+                    if download_state == DownloadState::Stalled {
+                        object.set_status_text(download_state.label());
+                        object.set_speed_text("");
+                        object.set_eta_text("");
+                    }
+                }
+                DownloadMessage::Waiting(reason) => {
+                    // Fila, horário ou rede: o próximo Progress volta a mostrar os bytes
+                    object.set_status_text(reason.text());
+                    object.set_speed_text("");
+                    object.set_eta_text("");
+                    if let WaitReason::ServerBusy(until) = reason {
+                        start_busy_countdown(&object, &download_task, until);
+                    }
+                }
+                DownloadMessage::Status(text) => {
+                    // Aviso temporário; o próximo Progress volta a mostrar os bytes
+                    object.set_status_text(text);
+                    object.set_speed_text("");
+                    object.set_eta_text("");
+                }
+                DownloadMessage::Complete => {
+                    // Obtém o caminho do arquivo final
+                    let file_path_str = if let Ok(task) = download_task.lock() {
+                        task.file_path.as_ref().map(|p| p.to_string_lossy().to_string())
+                    } else {
+                        None
+                    };
 
-        status_group.append(&status_label);
-        status_group.append(&status_value);
+                    object.set_indeterminate(false);
+                    object.set_progress(1.0);
+                    object.set_speed_text("");
+                    object.set_eta_text("");
+                    object.set_file_path(file_path_str.clone().unwrap_or_default());
+                    object.set_active(false);
 
-        // Data de início
-        let date_group = GtkBox::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(4)
-            .build();
+                    // Atualiza registro no arquivo
+                    let (validators, checksum, integrity, scan, response) = download_task
+                        .lock()
+                        .map(|task| (task.validators.clone(), task.checksum.clone(), task.integrity, task.scan, task.response.clone()))
+                        .unwrap_or_default();
+                    let infected = scan == Some(ScanResult::Infected);
+                    let status_text = match (integrity, &checksum) {
+                        _ if infected => INFECTED_TEXT,
+                        (Some(integrity), _) => integrity.label(),
+                        (None, Some(_)) => "Concluído · SHA-256 verificado",
+                        (None, None) => "Concluído",
+                    };
+                    let events = log_task_event(&download_task, status_text);
+                    object.set_download_state(DownloadState::Done, status_text);
+                    object.set_truncated(integrity == Some(FileIntegrity::Truncated));
+                    object.set_infected(infected);
+                    if let Ok(mut records) = state_records.lock() {
+                        if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                            // Recorrente: guarda a versão e apaga as que passaram do limite
+                            // (sincronizado não tem limite: só guarda as que ficaram com nome próprio)
+                            let keep = match (&record.options.recurrence, record.options.sync) {
+                                (Some(recurrence), _) => Some(recurrence.keep_versions),
+                                (None, true) => Some(usize::MAX),
+                                (None, false) => None,
+                            };
+                            if let (Some(keep), Some(path)) = (keep, &file_path_str) {
+                                for old in push_version(&mut record.versions, path.clone(), keep) {
+                                    if let Err(e) = std::fs::remove_file(&old) {
+                                        eprintln!("Erro ao remover versão antiga {}: {}", old, e);
+                                    }
+                                }
+                            }
+                            record.status = DownloadStatus::Completed;
+                            record.file_path = file_path_str;
+                            record.validators = validators;
+                            record.sha256 = checksum;
+                            record.integrity = integrity; // Arquivo novo: a verificação anterior não vale mais
+                            record.scan = scan;
+                            record.failure = None;
+                            record.retry_attempts = 0;
+                            record.response = response;
+                            record.events = events;
+                            record.date_completed = Some(Utc::now());
+                            // Sem tamanho do servidor (chunked): vale o que ficou no disco
+                            if record.total_bytes == 0 {
+                                record.total_bytes = record
+                                    .file_path
+                                    .as_ref()
+                                    .and_then(|path| std::fs::metadata(path).ok())
+                                    .map(|metadata| metadata.len())
+                                    .unwrap_or(0);
+                                if record.total_bytes > 0 {
+                                    object.set_size_text(format_file_size(record.total_bytes));
+                                }
+                            }
+                            record.downloaded_bytes = record.total_bytes; // Marca como 100% completo
+                        }
+                        save_downloads(&records);
+                    }
 
-        let date_label = Label::builder()
-            .label("Data de Início")
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["title-4"])
-            .build();
+                    // Privado: o registro e o card somem; o arquivo fica acessível pelo aviso
+                    let is_private = state_records
+                        .lock()
+                        .ok()
+                        .and_then(|records| records.iter().find(|r| r.id == id).map(|r| r.options.private))
+                        .unwrap_or(false);
+                    if is_private {
+                        // Infectado: o aviso não oferece abrir
+                        let file_path = if infected { String::new() } else { object.file_path() };
+                        forget_private_download(&row, id, &object.filename(), file_path, &state_records, &content_stack);
+                        publish_event(&state, events::DownloadEvent::Removed(id));
+                    } else {
+                        let average = if speed_samples > 0 { speed_sum / speed_samples as f64 } else { 0.0 };
+                        let (supports_range, connections) = download_task
+                            .lock()
+                            .map(|task| (task.supports_range, task.peak_connections))
+                            .unwrap_or_default();
+                        update_host_stats(&state, |hosts| hosts.record_success(&object.url(), average, supports_range, connections));
+                    }
 
-        let date_value = Label::builder()
-            .label(&format!("{}", record_clone.date_added.format("%d/%m/%Y às %H:%M:%S")))
-            .halign(gtk4::Align::Start)
-            .css_classes(vec!["caption"])
-            .build();
+                    break;
+                }
+                DownloadMessage::NotModified => {
+                    // Cópia local já é a mais recente: volta a concluído sem tocar no arquivo
+                    let events = log_task_event(&download_task, "Sem alterações no servidor");
+                    let file_path = if let Ok(mut records) = state_records.lock() {
+                        let file_path = records.iter_mut().find(|r| r.id == id).map(|record| {
+                            record.status = DownloadStatus::Completed;
+                            record.events = events;
+                            record.downloaded_bytes = record.total_bytes;
+                            record.file_path.clone().unwrap_or_default()
+                        });
+                        save_downloads(&records);
+                        file_path.unwrap_or_default()
+                    } else {
+                        String::new()
+                    };
 
-        date_group.append(&date_label);
-        date_group.append(&date_value);
+                    object.set_progress(1.0);
+                    object.set_file_path(file_path);
+                    object.set_active(false);
+                    object.set_download_state(DownloadState::Done, "Sem alterações");
+                    break;
+                }
+                DownloadMessage::Error(_) | DownloadMessage::Cancelled => {
+                    // A categoria da falha decide o badge, o próximo passo sugerido e as estatísticas do servidor
+                    let failure = match &msg {
+                        DownloadMessage::Error(err) => Some(err.kind),
+                        _ => None,
+                    };
+                    let (badge_state, status, mut status_text, event) = match (&msg, failure) {
+                        (DownloadMessage::Error(err), Some(kind)) => {
+                            let text = if kind == FailureKind::Other { format!("Erro: {}", err.message) } else { kind.label().to_string() };
+                            (DownloadState::Failed, DownloadStatus::Failed, text, format!("Falhou: {}", err.message))
+                        }
+                        _ => (DownloadState::Cancelled, DownloadStatus::Cancelled, "Cancelado".to_string(), "Cancelado".to_string()),
+                    };
 
-        // Data de conclusão (se completado)
-        if let Some(completed_date) = record_clone.date_completed {
-            let completed_group = GtkBox::builder()
-                .orientation(Orientation::Vertical)
-                .spacing(4)
-                .build();
+                    // Falha passageira: agenda a próxima tentativa automática, se ligadas
+                    let now = Utc::now();
+                    let budget = state
+                        .lock()
+                        .ok()
+                        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.auto_retries))
+                        .unwrap_or(0);
+                    let attempts = state_records
+                        .lock()
+                        .ok()
+                        .and_then(|records| records.iter().find(|r| r.id == id).map(|r| r.retry_attempts))
+                        .unwrap_or(0);
+                    let retry_at = failure.and_then(|kind| next_retry(kind, attempts, budget, now));
+                    if let Some(at) = retry_at {
+                        status_text = format!("{} · {}", status_text, retry_text(at, now));
+                    }
 
-            let completed_label = Label::builder()
-                .label("Data de Conclusão")
-                .halign(gtk4::Align::Start)
-                .css_classes(vec!["title-4"])
-                .build();
+                    object.set_speed_text("");
+                    object.set_eta_text("");
+                    object.set_active(false);
+                    object.set_download_state(badge_state, &status_text);
+
+                    // Atualiza registro de erro (a resposta do servidor ajuda a entender a falha)
+                    let response = download_task.lock().ok().and_then(|task| task.response.clone());
+                    let failed = status == DownloadStatus::Failed;
+                    let events = log_task_event(&download_task, event);
+                    if let Ok(mut records) = state_records.lock() {
+                        if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                            record.status = status;
+                            record.events = events;
+                            record.date_completed = Some(Utc::now());
+                            record.failure = failure;
+                            record.retry_at = retry_at;
+                            if response.is_some() {
+                                record.response = response;
+                            }
+                        }
+                        save_downloads(&records);
+                    }
+                    let is_private = state_records
+                        .lock()
+                        .ok()
+                        .and_then(|records| records.iter().find(|r| r.id == id).map(|r| r.options.private))
+                        .unwrap_or(false);
+                    // Problema no disco é daqui, não do servidor
+                    if failed && !is_private && failure != Some(FailureKind::Disk) {
+                        update_host_stats(&state, |hosts| hosts.record_failure(&object.url()));
+                    }
+                    // Quem vai tentar de novo sozinho não precisa de aviso
+                    let downloaded = download_task.lock().map(|task| task.downloaded).unwrap_or(0);
+                    match failure.filter(|_| retry_at.is_none()) {
+                        Some(kind) if link_expired(kind, &object.url(), downloaded) => {
+                            show_expired_link_dialog(&row, &object, &state);
+                        }
+                        Some(kind) => show_failure_toast(&row, &object.filename(), kind),
+                        None => {}
+                    }
 
-            let completed_value = Label::builder()
-                .label(&format!("{}", completed_date.format("%d/%m/%Y às %H:%M:%S")))
-                .halign(gtk4::Align::Start)
-                .css_classes(vec!["caption"])
-                .build();
+                    break;
+                }
+            }
+        }
+    }));
 
-            completed_group.append(&completed_label);
-            completed_group.append(&completed_value);
-            main_box.append(&completed_group);
+    // Abre o .part no player padrão (o motor informa o caminho ao começar)
+    row.preview_button().connect_clicked(glib::clone!(#[strong] download_task, move |button| {
+        let temp_path = download_task.lock().ok().and_then(|task| task.temp_path.clone());
+        match temp_path {
+            Some(path) if path.exists() => {
+                if let Err(e) = platform::open_path(&path) {
+                    eprintln!("Erro ao abrir pré-visualização: {}", e);
+                    show_toast(button, "Não foi possível abrir a pré-visualização");
+                }
+            }
+            _ => show_toast(button, "Arquivo parcial ainda não disponível"),
         }
+    }));
 
-        // Caminho do arquivo (se completado)
-        if let Some(ref file_path) = record_clone.file_path {
-            let path_group = GtkBox::builder()
-                .orientation(Orientation::Vertical)
-                .spacing(4)
-                .build();
+    // Handler para botão de pausa/retomar
+    row.pause_button().connect_clicked(glib::clone!(
+        #[weak] object,
+        #[strong] download_task,
+        #[strong] state_records, move |_| {
+        if let Ok(mut task) = download_task.lock() {
+            // Pausar ou retomar à mão descarta o prazo
+            if task.is_paused() {
+                task.resume();
+            } else {
+                task.pause();
+                task.resume_at = None;
+            }
+            let is_paused = task.is_paused();
+            task.log(if is_paused { "Pausado" } else { "Retomado" });
+            object.set_download_state(task.state, task.state.label());
+            if let Some(bus) = &task.bus {
+                bus.publish(events::DownloadEvent::StateChanged(id, task.state));
+            }
 
-            let path_label = Label::builder()
-                .label("Caminho do Arquivo")
-                .halign(gtk4::Align::Start)
-                .css_classes(vec!["title-4"])
-                .build();
+            // Atualiza was_paused no registro
+            if let Ok(mut records) = state_records.lock() {
+                if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                    record.was_paused = is_paused;
+                    record.resume_at = None;
+                    record.events = task.events.clone();
+                }
+                save_downloads(&records);
+            }
+        }
+    }));
 
-            let path_value = Label::builder()
-                .label(file_path)
-                .halign(gtk4::Align::Start)
-                .wrap(true)
-                .selectable(true)
-                .css_classes(vec!["caption"])
-                .build();
+    // Pausa com prazo ("por 1 hora", "até 18:00"): o motor retoma sozinho
+    row.pause_timer_button().connect_clicked(glib::clone!(
+        #[weak] object,
+        #[strong] download_task,
+        #[strong] state_records, move |button| {
+        show_pause_timer_dialog(button, glib::clone!(
+            #[weak] object,
+            #[strong] download_task,
+            #[strong] state_records, move |resume_at| {
+            let events = download_task
+                .lock()
+                .map(|mut task| {
+                    task.pause();
+                    task.resume_at = Some(resume_at);
+                    if let Some(bus) = &task.bus {
+                        bus.publish(events::DownloadEvent::StateChanged(id, task.state));
+                    }
+                    task.log(format!("Pausado até {}", resume_at.with_timezone(&Local).format("%d/%m %H:%M")));
+                    task.events.clone()
+                })
+                .unwrap_or_default();
+            if let Ok(mut records) = state_records.lock() {
+                if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                    record.was_paused = true;
+                    record.resume_at = Some(resume_at);
+                    record.events = events;
+                }
+                save_downloads(&records);
+            }
+            start_pause_countdown(&object, &download_task, &state_records);
+        }));
+    }));
+
+    // Handler para botão de cancelar
+    row.cancel_button().connect_clicked(glib::clone!(
+        #[weak] object,
+        #[strong] download_task,
+        #[strong] state_records, move |_| {
+        // Cancela o download
+        if let Ok(mut task) = download_task.lock() {
+            task.cancel();
+            if let Some(bus) = &task.bus {
+                bus.publish(events::DownloadEvent::StateChanged(id, task.state));
+            }
+        }
 
-            path_group.append(&path_label);
-            path_group.append(&path_value);
-            main_box.append(&path_group);
+        // Marca como cancelado no registro (mantém os metadados)
+        if let Ok(mut records) = state_records.lock() {
+            if let Some(record) = records.iter_mut().find(|r| r.id == id) {
+                record.status = DownloadStatus::Cancelled;
+                record.date_completed = Some(Utc::now());
+            }
+            save_downloads(&records);
         }
 
-        main_box.append(&filename_group);
-        main_box.append(&url_group);
-        main_box.append(&size_group);
-        main_box.append(&status_group);
-        main_box.append(&date_group);
+        // Mostra como cancelado (não remove da tela); reiniciar e excluir aparecem
+        object.set_speed_text("");
+        object.set_eta_text("");
+        object.set_active(false);
+        object.set_download_state(DownloadState::Cancelled, "Cancelado");
+    }));
+}
 
-        dialog.set_extra_child(Some(&main_box));
-        dialog.present();
-    });
+// Prazos oferecidos para a pausa (id da resposta, rótulo, minutos)
+const PAUSE_DURATIONS: [(&str, &str, i64); 3] = [("15m", "15 min", 15), ("1h", "1 hora", 60), ("3h", "3 horas", 180)];
 
-    primary_actions_box.append(&info_btn);
+// Escolha do prazo da pausa: durações prontas ou um horário ("18:00")
+fn show_pause_timer_dialog(parent: &impl IsA<gtk4::Widget>, on_chosen: impl Fn(chrono::DateTime<Utc>) + 'static) {
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Pausar por um tempo"),
+        Some("O download continua sozinho quando o prazo acabar."),
+    );
 
-    // Botão de excluir
-    let delete_btn = Button::builder()
-        .icon_name("user-trash-symbolic")
-        .tooltip_text("Remover da lista")
-        .css_classes(vec!["destructive-action"])
+    let time_entry = Entry::builder()
+        .placeholder_text("Até o horário (ex.: 18:00)")
+        .activates_default(true)
         .build();
+    dialog.set_extra_child(Some(&time_entry));
 
-    let row_box_clone = row_box.clone();
-    let record_url = record.url.clone();
-    let state_clone = state.clone();
-    let content_stack_clone = content_stack.clone();
-
-    delete_btn.connect_clicked(move |_| {
-        // Remove do state.records e do arquivo de dados PRIMEIRO
-        let mut should_remove_ui = true;
-        let mut is_empty = false;
-        if let Ok(app_state) = state_clone.lock() {
-            if let Ok(mut records) = app_state.records.lock() {
-                let before_count = records.len();
-                records.retain(|r| r.url != record_url);
-                let after_count = records.len();
-
-                if before_count != after_count {
-                    // Salvou com sucesso, agora remove da UI
-                    save_downloads(&records);
-                    // Verifica se ficou vazio
-                    is_empty = after_count == 0;
-                } else {
-                    // Não encontrou o registro, pode já ter sido removido
-                    should_remove_ui = false;
+    dialog.add_response("cancel", "Cancelar");
+    for (response, label, _) in PAUSE_DURATIONS {
+        dialog.add_response(response, label);
+    }
+    dialog.add_response("until", "Até o horário");
+    dialog.set_response_appearance("until", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("until"));
+    dialog.set_close_response("cancel");
+
+    let toast_parent = parent.clone().upcast::<gtk4::Widget>();
+    dialog.connect_response(None, move |_, response| {
+        let resume_at = match response {
+            "until" => match next_time_of_day(&time_entry.text(), Local::now()) {
+                Some(at) => at.with_timezone(&Utc),
+                None => {
+                    show_toast(&toast_parent, &format!("Horário inválido: {}", time_entry.text()));
+                    return;
                 }
-            }
-        }
+            },
+            _ => match PAUSE_DURATIONS.iter().find(|(id, _, _)| *id == response) {
+                Some((_, _, minutes)) => Utc::now() + chrono::Duration::minutes(*minutes),
+                None => return,
+            },
+        };
+        on_chosen(resume_at);
+    });
 
-        // Remove da UI
-        if should_remove_ui {
-            if let Some(parent) = row_box_clone.parent() {
-                if let Some(grandparent) = parent.parent() {
-                    if let Some(list_box) = grandparent.downcast_ref::<ListBox>() {
-                        list_box.remove(&parent);
+    dialog.present(Some(parent));
+}
 
-                        // Se a lista ficou vazia, mostra o estado vazio
-                        if is_empty {
-                            content_stack_clone.set_visible_child_name("empty");
-                        }
+// Contagem regressiva no badge enquanto a pausa com prazo durar
+fn start_pause_countdown(
+    object: &DownloadObject,
+    download_task: &Arc<Mutex<DownloadTask>>,
+    state_records: &Arc<Mutex<Vec<DownloadRecord>>>,
+) {
+    let id = object.download_id();
+    let update = glib::clone!(#[weak] object, #[strong] download_task, #[strong] state_records, #[upgrade_or] glib::ControlFlow::Break, move || {
+        let (download_state, resume_at) = download_task
+            .lock()
+            .map(|task| (task.state, task.resume_at))
+            .unwrap_or((DownloadState::Paused, None));
+        let paused = download_state == DownloadState::Paused;
+        match resume_at {
+            Some(at) if paused => {
+                let text = format!("{} · em {}", WaitReason::Scheduled(at).text(), format_countdown(at - Utc::now()));
+                object.set_download_state(DownloadState::Paused, &text);
+                glib::ControlFlow::Continue
+            }
+            _ => {
+                // O motor retomou (ou a pausa virou manual): o prazo sai do registro
+                if !paused {
+                    object.set_download_state(download_state, download_state.label());
+                }
+                if let Ok(mut records) = state_records.lock() {
+                    if let Some(record) = records.iter_mut().find(|r| r.id == id && r.resume_at.is_some()) {
+                        record.resume_at = None;
+                        record.was_paused = paused;
+                        save_downloads(&records);
                     }
                 }
+                glib::ControlFlow::Break
             }
         }
     });
-
-    destructive_actions_box.append(&delete_btn);
-
-    // Monta a estrutura de botões de forma consistente
-    buttons_box.append(&primary_actions_box);
-    buttons_box.append(&destructive_actions_box);
-
-    row_box.append(&title_label);
-    row_box.append(&progress_bar);
-    row_box.append(&info_box);
-    row_box.append(&buttons_box);
-
-    // Design minimalista - sem separadores entre cards
-    list_box.append(&row_box);
+    if update() == glib::ControlFlow::Continue {
+        glib::timeout_add_seconds_local(1, update);
+    }
 }
 
-fn add_download(list_box: &ListBox, url: &str, state: &Arc<Mutex<AppState>>, content_stack: &gtk4::Stack) {
-    let row_box = GtkBox::builder()
-        .orientation(Orientation::Vertical)
-        .spacing(SPACING_MEDIUM)
-        .margin_top(SPACING_MEDIUM)
-        .margin_bottom(SPACING_MEDIUM)
-        .margin_start(SPACING_MEDIUM)
-        .margin_end(SPACING_MEDIUM)
-        .css_classes(vec!["download-card"])
-        .build();
+// Contagem regressiva no badge enquanto o motor espera um servidor em manutenção
+fn start_busy_countdown(object: &DownloadObject, download_task: &Arc<Mutex<DownloadTask>>, until: DateTime<Utc>) {
+    let update = glib::clone!(#[weak] object, #[strong] download_task, #[upgrade_or] glib::ControlFlow::Break, move || {
+        let waiting = download_task.lock().map(|task| task.state == DownloadState::Retrying).unwrap_or(false);
+        let remaining = until - Utc::now();
+        if !waiting || remaining <= chrono::Duration::zero() {
+            return glib::ControlFlow::Break;
+        }
+        let text = format!("{} · nova tentativa em {}", WaitReason::ServerBusy(until).text(), format_countdown(remaining));
+        object.set_download_state(DownloadState::Retrying, &text);
+        glib::ControlFlow::Continue
+    });
+    if update() == glib::ControlFlow::Continue {
+        glib::timeout_add_seconds_local(1, update);
+    }
+}
 
-    let filename = sanitize_filename(url);
+// Janela de atalhos (Ctrl+?)
+fn show_shortcuts_window(window: &AdwApplicationWindow) {
+    const SHORTCUTS: [(&str, &[(&str, &str)]); 2] = [
+        (
+            "Geral",
+            &[
+                ("<Ctrl>N", "Adicionar download"),
+                ("<Ctrl>V", "Adicionar URL da área de transferência"),
+                ("<Ctrl>L", "Verificar links"),
+                ("<Ctrl>D", "Navegar em diretório"),
+                ("<Ctrl>F", "Buscar downloads"),
+                ("<Ctrl>comma", "Preferências"),
+                ("<Ctrl>question", "Atalhos de teclado"),
+                ("<Ctrl>Q", "Sair"),
+            ],
+        ),
+        (
+            "Downloads",
+            &[
+                ("<Ctrl>P", "Pausar todos"),
+                ("Delete", "Remover download selecionado"),
+            ],
+        ),
+    ];
+
+    let mut groups = String::new();
+    for (title, shortcuts) in SHORTCUTS {
+        groups.push_str(&format!(
+            "<child><object class=\"GtkShortcutsGroup\"><property name=\"title\">{}</property>",
+            glib::markup_escape_text(title)
+        ));
+        for (accelerator, description) in shortcuts {
+            groups.push_str(&format!(
+                "<child><object class=\"GtkShortcutsShortcut\">\
+                 <property name=\"accelerator\">{}</property>\
+                 <property name=\"title\">{}</property></object></child>",
+                glib::markup_escape_text(accelerator),
+                glib::markup_escape_text(description)
+            ));
+        }
+        groups.push_str("</object></child>");
+    }
+    let ui = format!(
+        "<interface><object class=\"GtkShortcutsWindow\" id=\"shortcuts\"><property name=\"modal\">true</property>\
+         <child><object class=\"GtkShortcutsSection\"><property name=\"section-name\">main</property>{}</object></child>\
+         </object></interface>",
+        groups
+    );
 
-    // Header com título e tag de chunks paralelos
-    let title_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_MEDIUM)
-        .halign(gtk4::Align::Start)
-        .build();
+    let builder = gtk4::Builder::from_string(&ui);
+    if let Some(shortcuts) = builder.object::<gtk4::ShortcutsWindow>("shortcuts") {
+        shortcuts.set_transient_for(Some(window));
+        shortcuts.present();
+    }
+}
 
-    let title_label = Label::builder()
-        .halign(gtk4::Align::Start)
-        .hexpand(true)
-        .css_classes(vec!["title-2"])
-        .ellipsize(gtk4::pango::EllipsizeMode::End)
+// Preferências reúnem as configurações do menu; cada linha abre o diálogo correspondente
+fn show_preferences(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>) {
+    let download_dir = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| get_download_directory(&config)))
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let rows = [
+        ("Pasta de Downloads", download_dir.as_str(), "app.config-downloads"),
+        ("Pasta de Concluídos", "Para onde vão os arquivos prontos", "app.config-finished-folder"),
+        ("Antivírus", "Verifica cada arquivo concluído", "app.config-scanner"),
+        ("Organização", "Subpastas por site de origem", "app.config-organize"),
+        ("Nome das Versões", "Downloads repetidos e sincronizados", "app.config-version-naming"),
+        ("Modelos de Download", "Pasta, conexões e limite por padrão de URL", "app.config-templates"),
+        ("Aparência", "Cores acessíveis e lista compacta", "app.config-appearance"),
+        ("Criptografia", "Senha para a lista de downloads", "app.config-encryption"),
+        ("Filtro de URLs", "Endereços permitidos e bloqueados", "app.config-url-filter"),
+        ("Conexões Simultâneas", "Máximo de conexões por download", "app.config-connections"),
+        ("Servidores Limitados", "Sempre em várias conexões, mesmo arquivos pequenos", "app.config-segmented-hosts"),
+        ("Downloads Simultâneos", "Os que passam do limite esperam na fila", "app.config-active-downloads"),
+        ("Novas Tentativas", "Repete sozinho os downloads com falha passageira", "app.config-auto-retry"),
+        ("Gravação em Disco", "Intervalo para gravar o que já foi recebido", "app.config-disk-writes"),
+        ("Conteúdo Compactado", "Descompactar gzip/br enviados pelo servidor", "app.config-content-encoding"),
+        ("Limite de Velocidade", "Usado quando o modo turbo está desligado", "app.config-speed-limit"),
+        ("Rede (IPv4/IPv6)", "Família de endereços preferida", "app.config-network"),
+        ("Reuso de Conexões", "Conexões ociosas e keepalive TCP", "app.config-keep-alive"),
+        ("Proxy", "Proxy manual ou configuração automática (PAC)", "app.config-proxy"),
+        ("Perfis de Rede", "Headers, credenciais e proxy escolhidos por download", "app.config-network-profiles"),
+        ("Certificado CA", "Certificados extras para servidores internos", "app.config-ca"),
+        ("Metadados do Arquivo", "Origem e data gravadas nos arquivos", "app.config-provenance"),
+        ("Inicialização", "Abrir no login e iniciar minimizado", "app.config-startup"),
+        ("Atualizações", "Avisar quando houver uma versão nova", "app.config-updates"),
+        ("Cota de Dados", "Limite mensal de transferência", "app.config-quota"),
+        ("Bateria", "Pausar downloads com bateria fraca", "app.config-battery"),
+        ("Armazenamento S3", "Credenciais para URLs s3://", "app.config-s3"),
+        ("Gateways IPFS", "Gateways HTTP para URLs ipfs://", "app.config-ipfs"),
+        ("Resolvedores de Links", "Serviços de arquivos e plugins instalados", "app.config-resolvers"),
+        ("Scripts", "Ganchos ao adicionar, antes de baixar e ao concluir", "app.config-scripts"),
+    ];
+
+    let preferences = libadwaita::PreferencesDialog::builder()
+        .search_enabled(false)
         .build();
+    let page = libadwaita::PreferencesPage::new();
+    let group = libadwaita::PreferencesGroup::new();
+    for (title, subtitle, action) in rows {
+        let row = libadwaita::ActionRow::builder()
+            .title(title)
+            .subtitle(subtitle)
+            .activatable(true)
+            .build();
+        row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        row.connect_activated(glib::clone!(#[weak] preferences, #[weak] window, move |_| {
+            // Os diálogos são filhos da janela principal: fecha este antes
+            preferences.close();
+            let _ = WidgetExt::activate_action(&window, action, None);
+        }));
+        group.add(&row);
+    }
+    page.add(&group);
+    preferences.add(&page);
+    preferences.present(Some(window));
+}
 
-    // Título com peso bold e tamanho large
-    title_label.set_markup(&markup_title(&filename));
-
-    // Tag de chunks paralelos (inicialmente escondida)
-    let parallel_tag_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_TINY)
-        .halign(gtk4::Align::Start)
-        .visible(false)
-        .tooltip_text("Download otimizado: arquivo baixado em múltiplas partes simultâneas")
-        .build();
+// Filtro de URLs configurado (ver `url_filter`)
+// Atualiza e grava o histórico de servidores
+fn update_host_stats(state: &Arc<Mutex<AppState>>, update: impl FnOnce(&mut HostBook)) {
+    let Some(hosts) = state.lock().ok().map(|app_state| app_state.hosts.clone()) else {
+        return;
+    };
+    let Ok(mut hosts) = hosts.lock() else {
+        return;
+    };
+    update(&mut hosts);
+    save_host_stats(&hosts);
+}
 
-    let parallel_icon = gtk4::Image::builder()
-        .icon_name("network-transmit-receive-symbolic")
-        .pixel_size(12)
-        .build();
+// Aviso do histórico do servidor de `url` (instável ou sem retomada)
+// Um modelo que casa com a URL tem precedência sobre o histórico
+fn suggestion_for(state: &Arc<Mutex<AppState>>, url: &str) -> Option<Suggestion> {
+    let app_state = state.lock().ok()?;
+    let has_template = app_state
+        .config
+        .lock()
+        .map(|config| matching_template(&config.templates, url).is_some())
+        .unwrap_or(false);
+    if has_template {
+        return None;
+    }
+    let records = app_state.records.lock().ok()?;
+    suggest_for(&records, url)
+}
 
-    let parallel_label = Label::builder()
-        .label("Chunks Paralelos")
-        .css_classes(vec!["caption", "dim-label"])
-        .build();
+fn host_warning_for(state: &Arc<Mutex<AppState>>, url: &str) -> Option<String> {
+    let hosts = state.lock().ok()?.hosts.clone();
+    let hosts = hosts.lock().ok()?;
+    host_warning(hosts.get(url)?)
+}
 
-    parallel_tag_box.append(&parallel_icon);
-    parallel_tag_box.append(&parallel_label);
+fn url_filter_check(state: &Arc<Mutex<AppState>>, url: &str) -> Result<(), String> {
+    let settings = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.url_filter.clone()))
+        .unwrap_or_default();
+    check_url(&settings, url)
+}
 
-    // Tag de retomando download (inicialmente escondida)
-    let resume_tag_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_TINY)
-        .halign(gtk4::Align::Start)
-        .visible(false)
-        .tooltip_text("Continuando download de onde parou")
-        .build();
+// Endereços que o motor sabe baixar (s3:// usa as credenciais configuradas)
+fn is_download_url(url: &str) -> bool {
+    url.starts_with("http://")
+        || url.starts_with("https://")
+        || is_ftp_url(url)
+        || parse_s3_url(url).is_some()
+        || parse_ipfs_url(url).is_some()
+}
 
-    let resume_icon = gtk4::Image::builder()
-        .icon_name("media-skip-forward-symbolic")
-        .pixel_size(12)
-        .build();
+// Primeira URL baixável de um texto colado
+fn url_from_text(text: &str) -> Option<String> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c| matches!(c, '<' | '>' | '"' | '\'')))
+        .find(|word| is_download_url(word))
+        .map(|url| url.to_string())
+}
 
-    let resume_label = Label::builder()
-        .label("Retomando")
-        .css_classes(vec!["caption", "dim-label"])
-        .build();
+// Lê a área de transferência e abre o diálogo de adicionar já com a URL
+fn add_from_clipboard(clipboard: gtk4::gdk::Clipboard, show_add_dialog: impl Fn(Option<String>) + 'static) {
+    glib::spawn_future_local(async move {
+        let text = clipboard.read_text_future().await.ok().flatten();
+        if let Some(url) = text.as_deref().and_then(url_from_text) {
+            show_add_dialog(Some(url));
+        }
+    });
+}
 
-    resume_tag_box.append(&resume_icon);
-    resume_tag_box.append(&resume_label);
+// Consulta a URL (HEAD) pouco depois de o usuário parar de digitar e mostra o que
+// o servidor informa antes de o download começar
+fn schedule_metadata_preview(
+    url: &str,
+    url_filename: &str,
+    label: &Label,
+    generation: &std::rc::Rc<std::cell::Cell<u64>>,
+    state: &Arc<Mutex<AppState>>,
+) {
+    const DEBOUNCE_MS: u64 = 500;
 
-    title_box.append(&title_label);
-    title_box.append(&parallel_tag_box);
-    title_box.append(&resume_tag_box);
+    let current = generation.get() + 1;
+    generation.set(current);
+    label.remove_css_class("error");
+    label.set_visible(true);
 
-    // Barra de progresso
-    let progress_bar = gtk4::ProgressBar::builder()
-        .hexpand(true)
-        .show_text(true)
-        .css_classes(vec!["download-progress", "in-progress"])
-        .build();
-
-    // Box de status e velocidade
-    let info_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_MEDIUM)
-        .build();
-
-    // Box para status com badge colorido
-    let status_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .halign(gtk4::Align::Start)
-        .hexpand(true)
-        .build();
+    let Ok(config) = state.lock().map(|app_state| app_state.config.clone()) else {
+        return;
+    };
+    // Economia de dados: nada de HEAD antes de o download começar
+    if config.lock().is_ok_and(|config| config.data_saver) {
+        label.set_text("Economia de dados: servidor não consultado");
+        return;
+    }
+    label.set_text("Consultando servidor...");
+    let url = url.to_string();
+    let url_filename = url_filename.to_string();
+    glib::timeout_add_local_once(
+        std::time::Duration::from_millis(DEBOUNCE_MS),
+        glib::clone!(#[weak] label, #[strong] generation, move || {
+            if generation.get() != current {
+                return;
+            }
+            let (tx, rx) = async_channel::bounded(1);
+            start_link_check(vec![url], config, tx);
+            glib::spawn_future_local(async move {
+                let Ok((_, report)) = rx.recv().await else {
+                    return;
+                };
+                if generation.get() != current {
+                    return;
+                }
+                match report.result {
+                    Ok(info) => {
+                        let mut parts = Vec::new();
+                        if let Some(name) = info.filename.as_ref().filter(|name| **name != url_filename) {
+                            parts.push(format!("Nome sugerido: {}", name));
+                        }
+                        parts.push(format_file_size(info.size));
+                        if let Some(content_type) = &info.content_type {
+                            parts.push(content_type.clone());
+                        }
+                        parts.push(if info.uses_parallel_chunks() {
+                            "download paralelo em partes".to_string()
+                        } else {
+                            "download sequencial".to_string()
+                        });
+                        label.set_text(&parts.join(" · "));
+                    }
+                    Err(e) => {
+                        label.set_text(&format!("Não foi possível consultar: {}", e));
+                        label.add_css_class("error");
+                    }
+                }
+            });
+        }),
+    );
+}
 
-    // Badge colorido para status (inicialmente azul para "em progresso")
-    let status_badge = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .halign(gtk4::Align::Start)
-        .css_classes(vec!["status-badge", "in-progress"])
+// Ferramenta "Verificar Links": consulta uma lista de URLs (sem baixar) e deixa
+// escolher quais adicionar à fila
+fn show_link_checker(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+) {
+    let dialog = libadwaita::AlertDialog::builder()
+        .heading("Verificar Links")
+        .body("Cole as URLs (uma por linha) para conferir status, tamanho e suporte a download em partes")
         .build();
-
-    // Ícone de status (GTK symbolic)
-    let status_icon = gtk4::Image::builder()
-        .icon_name("folder-download-symbolic")
-        .pixel_size(16)
+    dialog.add_response("cancel", "Fechar");
+    dialog.add_response("add", "Adicionar Selecionados");
+    dialog.set_response_appearance("add", ResponseAppearance::Suggested);
+    dialog.set_response_enabled("add", false);
+    dialog.set_close_response("cancel");
+
+    let urls_view = gtk4::TextView::builder()
+        .wrap_mode(gtk4::WrapMode::Char)
+        .top_margin(SPACING_SMALL)
+        .bottom_margin(SPACING_SMALL)
+        .left_margin(SPACING_SMALL)
+        .right_margin(SPACING_SMALL)
         .build();
-
-    // Texto de status
-    let status_label = Label::builder()
-        .halign(gtk4::Align::Start)
+    let urls_scroll = ScrolledWindow::builder()
+        .child(&urls_view)
+        .min_content_height(100)
+        .min_content_width(480)
         .build();
+    urls_scroll.add_css_class("card");
 
-    status_label.set_markup(&markup_status("Iniciando..."));
-
-    status_badge.append(&status_icon);
-    status_badge.append(&status_label);
-    status_box.append(&status_badge);
-
-    // Box para metadados (tamanho, velocidade e ETA) - layout horizontal minimalista
-    let metadata_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
+    let check_button = Button::builder()
+        .label("Verificar")
         .halign(gtk4::Align::End)
-        .css_classes(vec!["metadata-group"])
         .build();
 
-    // Label para tamanho do arquivo (inicialmente vazio, será atualizado quando disponível)
-    let size_label = Label::builder()
-        .halign(gtk4::Align::End)
+    let results_box = ListBox::builder()
+        .selection_mode(gtk4::SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
         .build();
-
-    size_label.set_markup(&markup_metadata_primary(""));
-
-    let speed_label = Label::builder()
-        .halign(gtk4::Align::End)
+    let results_scroll = ScrolledWindow::builder()
+        .child(&results_box)
+        .min_content_height(180)
+        .visible(false)
         .build();
 
-    // Velocidade com peso semibold para destaque (inicialmente vazio)
-    speed_label.set_markup(&markup_metadata_primary(""));
+    let main_box = GtkBox::new(Orientation::Vertical, SPACING_LARGE);
+    main_box.append(&urls_scroll);
+    main_box.append(&check_button);
+    main_box.append(&results_scroll);
+    dialog.set_extra_child(Some(&main_box));
+
+    // Um CheckButton por link verificado (na ordem em que foram colados)
+    let checks: std::rc::Rc<std::cell::RefCell<Vec<(String, gtk4::CheckButton)>>> = Default::default();
+
+    check_button.connect_clicked(glib::clone!(
+        #[weak] dialog,
+        #[weak] urls_view,
+        #[weak] results_box,
+        #[weak] results_scroll,
+        #[strong] checks,
+        #[strong] state, move |button| {
+            let buffer = urls_view.buffer();
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            let mut urls: Vec<String> = Vec::new();
+            for url in text.split_whitespace() {
+                if is_download_url(url) && !urls.iter().any(|u| u == url) {
+                    urls.push(url.to_string());
+                }
+            }
+            if urls.is_empty() {
+                return;
+            }
 
-    let eta_label = Label::builder()
-        .halign(gtk4::Align::End)
-        .css_classes(vec!["dim-label"])
-        .build();
+            while let Some(child) = results_box.first_child() {
+                results_box.remove(&child);
+            }
+            let mut rows = Vec::new();
+            for url in &urls {
+                let check = gtk4::CheckButton::builder().sensitive(false).build();
+                let title = Label::builder()
+                    .label(url.as_str())
+                    .halign(gtk4::Align::Start)
+                    .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+                    .build();
+                let details = Label::builder()
+                    .label("Verificando...")
+                    .halign(gtk4::Align::Start)
+                    .css_classes(vec!["dim-label", "caption"])
+                    .build();
+                let text_box = GtkBox::new(Orientation::Vertical, SPACING_TINY);
+                text_box.append(&title);
+                text_box.append(&details);
+                let row_box = GtkBox::new(Orientation::Horizontal, SPACING_MEDIUM);
+                row_box.set_margin_top(SPACING_SMALL);
+                row_box.set_margin_bottom(SPACING_SMALL);
+                row_box.set_margin_start(SPACING_MEDIUM);
+                row_box.set_margin_end(SPACING_MEDIUM);
+                row_box.append(&check);
+                row_box.append(&text_box);
+                results_box.append(&row_box);
+                rows.push((check, title, details));
+            }
+            *checks.borrow_mut() = urls.iter().cloned().zip(rows.iter().map(|(check, _, _)| check.clone())).collect();
+            results_scroll.set_visible(true);
+            button.set_sensitive(false);
+            dialog.set_response_enabled("add", false);
+
+            let config = state.lock().map(|app_state| app_state.config.clone());
+            let Ok(config) = config else {
+                return;
+            };
+            let total = urls.len();
+            let (tx, rx) = async_channel::unbounded();
+            start_link_check(urls, config, tx);
+
+            glib::spawn_future_local(glib::clone!(#[weak] dialog, #[weak] button, async move {
+                let mut received = 0;
+                while let Ok((index, report)) = rx.recv().await {
+                    let Some((check, title, details)) = rows.get(index) else {
+                        continue;
+                    };
+                    title.set_text(&report.filename);
+                    title.set_tooltip_text(Some(&report.url));
+                    match &report.result {
+                        Ok(info) => {
+                            let range = if info.supports_range { "download em partes" } else { "sem suporte a partes" };
+                            details.set_text(&format!("OK · {} · {}", format_file_size(info.size), range));
+                            check.set_sensitive(true);
+                            check.set_active(true);
+                        }
+                        Err(e) => {
+                            details.set_text(&format!("Erro: {}", e));
+                            details.add_css_class("error");
+                        }
+                    }
+                    received += 1;
+                    if received == total {
+                        break;
+                    }
+                }
+                button.set_sensitive(true);
+                dialog.set_response_enabled("add", true);
+            }));
+        }
+    ));
+
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    dialog.connect_response(Some("add"), move |_, _| {
+        let mut added = 0;
+        for (url, check) in checks.borrow().iter() {
+            if check.is_active() {
+                add_download(&list_box, new_download_id(), url, DownloadOptions::default(), &state, &content_stack);
+                added += 1;
+            }
+        }
+        if added > 0 {
+            content_stack.set_visible_child_name("list");
+            show_toast(&list_box, &format!("{} download(s) adicionado(s)", added));
+        }
+    });
 
-    // ETA em tamanho small e peso normal (inicialmente vazio)
-    eta_label.set_markup(&markup_metadata_secondary(""));
+    urls_view.grab_focus();
+    dialog.present(Some(window));
+}
 
-    metadata_box.append(&size_label);
-    metadata_box.append(&speed_label);
-    metadata_box.append(&eta_label);
+// Endereço e links da página lida no modo "baixar todos os links"
+type ScannedPage = std::rc::Rc<std::cell::RefCell<Option<(String, Vec<PageLink>)>>>;
 
-    info_box.append(&status_box);
-    info_box.append(&metadata_box);
+// Modo "baixar todos os links": lista os links de uma página, filtrados por
+// extensão e/ou expressão regular, com o tamanho de cada um
+fn show_page_grabber(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+) {
+    let Ok(config) = state.lock().map(|app_state| app_state.config.clone()) else {
+        return;
+    };
 
-    // Box de botões de ação - mantém estrutura consistente
-    let buttons_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_MEDIUM)
-        .halign(gtk4::Align::End)
+    let dialog = libadwaita::AlertDialog::builder()
+        .heading("Baixar Links de Página")
+        .body("Lista os links de uma página para escolher quais baixar")
         .build();
-
-    // Container para botões de ação primária (à esquerda)
-    let primary_actions_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
+    dialog.add_response("cancel", "Fechar");
+    dialog.add_response("add", "Adicionar Selecionados");
+    dialog.set_response_appearance("add", ResponseAppearance::Suggested);
+    dialog.set_response_enabled("add", false);
+    dialog.set_close_response("cancel");
+
+    let url_entry = Entry::builder()
+        .placeholder_text("https://exemplo.com/downloads.html")
         .hexpand(true)
-        .halign(gtk4::Align::Start)
         .build();
+    let list_button = Button::with_label("Listar");
+    let url_box = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+    url_box.append(&url_entry);
+    url_box.append(&list_button);
 
-    // Container para botões destrutivos (à direita)
-    let destructive_actions_box = GtkBox::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(SPACING_SMALL)
-        .halign(gtk4::Align::End)
+    let extensions_entry = Entry::builder()
+        .placeholder_text("Extensões (ex.: iso, zip)")
+        .hexpand(true)
         .build();
-
-    // Botão de abrir arquivo (inicialmente escondido)
-    let open_btn = Button::builder()
-        .icon_name("document-open-symbolic")
-        .tooltip_text("Abrir arquivo")
-        .visible(false)
+    let pattern_entry = Entry::builder()
+        .placeholder_text("Expressão regular (opcional)")
+        .hexpand(true)
         .build();
+    let filter_box = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+    filter_box.append(&extensions_entry);
+    filter_box.append(&pattern_entry);
 
-    // Botão de abrir explorador de arquivos (inicialmente escondido)
-    let open_folder_btn = Button::builder()
-        .icon_name("folder-open-symbolic")
-        .tooltip_text("Abrir pasta no explorador")
+    let status_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["dim-label", "caption"])
         .visible(false)
         .build();
-
-    // Botão de pausa/retomar
-    let pause_btn = Button::builder()
-        .icon_name("media-playback-pause-symbolic")
-        .tooltip_text("Pausar")
-        .build();
-
-    // Botão de cancelar
-    let cancel_btn = Button::builder()
-        .icon_name("process-stop-symbolic")
-        .tooltip_text("Cancelar")
-        .css_classes(vec!["destructive-action"])
+    let results_box = ListBox::builder()
+        .selection_mode(gtk4::SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
         .build();
-
-    // Botão de excluir (inicialmente escondido)
-    let delete_btn = Button::builder()
-        .icon_name("user-trash-symbolic")
-        .tooltip_text("Remover da lista")
+    let results_scroll = ScrolledWindow::builder()
+        .child(&results_box)
+        .min_content_height(260)
+        .min_content_width(480)
         .visible(false)
-        .css_classes(vec!["destructive-action"])
-        .build();
-
-    // Botão de informações (sempre visível)
-    let info_btn = Button::builder()
-        .icon_name("info-symbolic")
-        .tooltip_text("Ver estatísticas e detalhes")
         .build();
 
-    // Organiza botões de forma consistente
-    primary_actions_box.append(&open_btn);
-    primary_actions_box.append(&open_folder_btn);
-    primary_actions_box.append(&pause_btn);
-    primary_actions_box.append(&info_btn);
-
-    destructive_actions_box.append(&cancel_btn);
-    destructive_actions_box.append(&delete_btn);
-
-    buttons_box.append(&primary_actions_box);
-    buttons_box.append(&destructive_actions_box);
-
-    row_box.append(&title_box);
-    row_box.append(&progress_bar);
-    row_box.append(&info_box);
-    row_box.append(&buttons_box);
-
-    // Design minimalista - sem separadores entre cards
-    list_box.append(&row_box);
-
-    // Cria o download task
-    let download_task = Arc::new(Mutex::new(DownloadTask {
-        paused: false,
-        cancelled: false,
-        file_path: None,
-    }));
-
-    // Cria registro de download inicial (em progresso e não pausado)
-    let initial_record = DownloadRecord {
-        url: url.to_string(),
-        filename: filename.clone(),
-        file_path: None,
-        status: DownloadStatus::InProgress,
-        date_added: Utc::now(),
-        date_completed: None,
-        downloaded_bytes: 0,
-        total_bytes: 0,
-        was_paused: false,  // Iniciando download ativo
-    };
-
-    let record_url = url.to_string();
-    let state_records = if let Ok(state) = state.lock() {
-        state.records.clone()
-    } else {
-        Arc::new(Mutex::new(Vec::new()))
-    };
-
-    // Salva registro inicial como InProgress (ou atualiza existente)
-    if let Ok(mut records) = state_records.lock() {
-        // Verifica se já existe um registro com essa URL
-        if let Some(existing) = records.iter_mut().find(|r| r.url == initial_record.url) {
-            // Atualiza o registro existente
-            existing.status = DownloadStatus::InProgress;
-            existing.date_completed = None;
-            existing.was_paused = false;  // Retomando, então não está pausado
-        } else {
-            // Adiciona novo registro
-            records.push(initial_record);
-        }
-        save_downloads(&records);
-    }
-
-    if let Ok(mut state) = state.lock() {
-        state.downloads.push(download_task.clone());
-    }
-
-    // Cria channel para comunicação entre threads usando async-channel
-    let (msg_tx, msg_rx) = async_channel::unbounded();
-
-    // Inicia o download em thread separada
-    let config_clone = if let Ok(app_state) = state.lock() {
-        app_state.config.clone()
-    } else {
-        Arc::new(Mutex::new(AppConfig {
-            download_directory: None,
-            window_width: None,
-            window_height: None,
-        }))
-    };
-    start_download(url, &filename, msg_tx, download_task.clone(), state_records.clone(), config_clone);
-
-    // Monitora mensagens na thread principal do GTK usando spawn_future_local
-    let progress_bar_clone = progress_bar.clone();
-    let status_badge_clone = status_badge.clone();
-    let status_icon_clone = status_icon.clone();
-    let status_label_clone = status_label.clone();
-    let size_label_clone = size_label.clone();
-    let speed_label_clone = speed_label.clone();
-    let eta_label_clone = eta_label.clone();
-    let parallel_tag_box_clone = parallel_tag_box.clone();
-    let resume_tag_box_clone = resume_tag_box.clone();
-    let pause_btn_clone = pause_btn.clone();
-    let cancel_btn_clone = cancel_btn.clone();
-    let open_btn_clone = open_btn.clone();
-    let open_folder_btn_clone = open_folder_btn.clone();
-    let delete_btn_clone = delete_btn.clone();
-    let download_task_clone_msg = download_task.clone();
-    let record_url_clone = record_url.clone();
-    let state_records_clone = state_records.clone();
-    let state_clone = state.clone();
-
-    glib::spawn_future_local(async move {
-        let mut last_save = std::time::Instant::now();
-
-        while let Ok(msg) = msg_rx.recv().await {
-            match msg {
-                DownloadMessage::Progress(progress, status_text, speed, eta, parallel_chunks, speed_bytes) => {
-                    progress_bar_clone.set_fraction(progress);
-                    progress_bar_clone.set_text(Some(&format!("{:.0}%", progress * 100.0)));
-
-                    // Armazena velocidade atual no HashMap
-                    if let Ok(app_state) = state_clone.lock() {
-                        if let Ok(mut speeds) = app_state.download_speeds.lock() {
-                            speeds.insert(record_url_clone.clone(), speed_bytes);
-                        }
-                    }
-
-                    // Atualiza tamanho do arquivo se disponível no registro
-                    if let Ok(records) = state_records_clone.lock() {
-                        if let Some(record) = records.iter().find(|r| r.url == record_url_clone) {
-                            if record.total_bytes > 0 {
-                                let size_text = format_file_size(record.total_bytes);
-                                size_label_clone.set_markup(&markup_metadata_primary(&size_text));
-                            }
-                        }
+    let main_box = GtkBox::new(Orientation::Vertical, SPACING_MEDIUM);
+    main_box.append(&url_box);
+    main_box.append(&filter_box);
+    main_box.append(&status_label);
+    main_box.append(&results_scroll);
+    dialog.set_extra_child(Some(&main_box));
+
+    // Links da última página lida (a página só é baixada de novo se o endereço mudar)
+    let page: ScannedPage = Default::default();
+    let checks: std::rc::Rc<std::cell::RefCell<Vec<(String, gtk4::CheckButton)>>> = Default::default();
+    // Resultados de uma listagem anterior não devem chegar às linhas novas
+    let generation = std::rc::Rc::new(std::cell::Cell::new(0u32));
+
+    let show_links = glib::clone!(
+        #[weak] dialog,
+        #[weak] extensions_entry,
+        #[weak] pattern_entry,
+        #[weak] status_label,
+        #[weak] results_box,
+        #[weak] results_scroll,
+        #[strong] page,
+        #[strong] checks,
+        #[strong] generation,
+        #[strong] config, move || {
+            let Some((page_url, links)) = page.borrow().clone() else {
+                return;
+            };
+            let pattern_text = pattern_entry.text().trim().to_string();
+            let pattern = if pattern_text.is_empty() {
+                None
+            } else {
+                match regex::Regex::new(&pattern_text) {
+                    Ok(pattern) => Some(pattern),
+                    Err(e) => {
+                        status_label.set_text(&format!("Expressão regular inválida: {}", e));
+                        status_label.set_visible(true);
+                        return;
                     }
-                    
-                    // Atualiza ícone de status e badge baseado no status_text
-                    let (icon_name, badge_class) = if status_text.contains("Pausado") || status_text.contains("Pausar") {
-                        ("media-playback-pause-symbolic", "paused")
-                    } else if status_text.contains("Erro") || status_text.contains("Falha") {
-                        ("dialog-error-symbolic", "failed")
-                    } else {
-                        ("folder-download-symbolic", "in-progress")
-                    };
-
-                    // Atualiza classe CSS do badge
-                    status_badge_clone.remove_css_class("completed");
-                    status_badge_clone.remove_css_class("in-progress");
-                    status_badge_clone.remove_css_class("paused");
-                    status_badge_clone.remove_css_class("failed");
-                    status_badge_clone.remove_css_class("cancelled");
-                    status_badge_clone.add_css_class(badge_class);
-
-                    // Atualiza classe CSS da barra de progresso
-                    progress_bar_clone.remove_css_class("completed");
-                    progress_bar_clone.remove_css_class("in-progress");
-                    progress_bar_clone.remove_css_class("paused");
-                    progress_bar_clone.remove_css_class("failed");
-                    progress_bar_clone.remove_css_class("cancelled");
-                    progress_bar_clone.add_css_class(badge_class);
-
-                    status_icon_clone.set_icon_name(Some(icon_name));
-                    status_label_clone.set_markup(&markup_status(&status_text));
-                    speed_label_clone.set_markup(&markup_metadata_primary(&speed));
-                    eta_label_clone.set_markup(&markup_metadata_secondary(&eta));
+                }
+            };
+            let extensions = parse_extensions(&extensions_entry.text());
+            let mut links = filter_links(&links, &page_url, &extensions, pattern.as_ref());
+            let total = links.len();
+            links.truncate(MAX_PAGE_LINKS);
 
-                    // Mostra tag apropriada baseado no modo de download
-                    if parallel_chunks {
-                        // Download em chunks paralelos
-                        parallel_tag_box_clone.set_visible(true);
-                        resume_tag_box_clone.set_visible(false);
-                    } else {
-                        // Verifica se é um resume (tem bytes já baixados)
-                        let is_resuming = if let Ok(records) = state_records_clone.lock() {
-                            if let Some(record) = records.iter().find(|r| r.url == record_url_clone) {
-                                record.downloaded_bytes > 0
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        };
+            while let Some(child) = results_box.first_child() {
+                results_box.remove(&child);
+            }
+            generation.set(generation.get() + 1);
+            status_label.set_text(&match total {
+                0 => "Nenhum link corresponde aos filtros".to_string(),
+                n if n > MAX_PAGE_LINKS => format!("{} links (mostrando os primeiros {})", n, MAX_PAGE_LINKS),
+                n => format!("{} links", n),
+            });
+            status_label.set_visible(true);
+            results_scroll.set_visible(total > 0);
+            dialog.set_response_enabled("add", total > 0);
+
+            // Economia de dados: os links não são consultados um a um
+            let data_saver = config.lock().is_ok_and(|config| config.data_saver);
+            let mut rows = Vec::new();
+            for link in &links {
+                let check = gtk4::CheckButton::builder().active(true).build();
+                let title = Label::builder()
+                    .label(if link.text.is_empty() { link.url.as_str() } else { link.text.as_str() })
+                    .tooltip_text(link.url.as_str())
+                    .halign(gtk4::Align::Start)
+                    .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+                    .build();
+                let details = Label::builder()
+                    .label(if data_saver { "Tamanho não consultado (economia de dados)" } else { "Consultando tamanho..." })
+                    .halign(gtk4::Align::Start)
+                    .css_classes(vec!["dim-label", "caption"])
+                    .build();
+                let text_box = GtkBox::new(Orientation::Vertical, SPACING_TINY);
+                text_box.append(&title);
+                text_box.append(&details);
+                let row_box = GtkBox::new(Orientation::Horizontal, SPACING_MEDIUM);
+                row_box.set_margin_top(SPACING_SMALL);
+                row_box.set_margin_bottom(SPACING_SMALL);
+                row_box.set_margin_start(SPACING_MEDIUM);
+                row_box.set_margin_end(SPACING_MEDIUM);
+                row_box.append(&check);
+                row_box.append(&text_box);
+                results_box.append(&row_box);
+                rows.push((check, details));
+            }
+            *checks.borrow_mut() = links.iter().map(|link| link.url.clone()).zip(rows.iter().map(|(check, _)| check.clone())).collect();
+            if links.is_empty() || data_saver {
+                return;
+            }
 
-                        parallel_tag_box_clone.set_visible(false);
-                        resume_tag_box_clone.set_visible(is_resuming);
+            let (tx, rx) = async_channel::unbounded();
+            start_link_check(links.into_iter().map(|link| link.url).collect(), config.clone(), tx);
+            let current = generation.get();
+            glib::spawn_future_local(glib::clone!(#[strong] generation, async move {
+                while let Ok((index, report)) = rx.recv().await {
+                    if generation.get() != current {
+                        break;
                     }
-
-                    // Atualiza registro a cada 5 segundos
-                    if last_save.elapsed().as_secs() >= 5 {
-                        // Verifica se está pausado neste momento
-                        let is_currently_paused = if let Ok(task) = download_task_clone_msg.lock() {
-                            task.paused
-                        } else {
-                            false
-                        };
-
-                        if let Ok(mut records) = state_records_clone.lock() {
-                            if let Some(record) = records.iter_mut().find(|r| r.url == record_url_clone) {
-                                record.was_paused = is_currently_paused;
-                                // Atualiza downloaded_bytes baseado no progresso
-                                if record.total_bytes > 0 {
-                                    record.downloaded_bytes = (progress * record.total_bytes as f64) as u64;
-                                }
-                            }
-                            save_downloads(&records);
+                    let Some((check, details)) = rows.get(index) else {
+                        continue;
+                    };
+                    match &report.result {
+                        Ok(info) => details.set_text(&format!("{} · {}", report.filename, format_file_size(info.size))),
+                        Err(e) => {
+                            details.set_text(&format!("Erro: {}", e));
+                            details.add_css_class("error");
+                            check.set_active(false);
                         }
-                        last_save = std::time::Instant::now();
                     }
                 }
-                DownloadMessage::Complete => {
-                    progress_bar_clone.set_fraction(1.0);
-                    progress_bar_clone.set_text(Some("100%"));
-
-                    // Remove velocidade do HashMap quando completa
-                    if let Ok(app_state) = state_clone.lock() {
-                        if let Ok(mut speeds) = app_state.download_speeds.lock() {
-                            speeds.remove(&record_url_clone);
-                        }
+            }));
+        }
+    );
+    let show_links = std::rc::Rc::new(show_links);
+
+    let list = glib::clone!(
+        #[weak] url_entry,
+        #[weak] status_label,
+        #[strong] page,
+        #[strong] show_links,
+        #[strong] config, move || {
+            let url = url_entry.text().trim().to_string();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                status_label.set_text("Informe um endereço http(s)");
+                status_label.set_visible(true);
+                return;
+            }
+            if page.borrow().as_ref().is_some_and(|(page_url, _)| *page_url == url) {
+                show_links();
+                return;
+            }
+            status_label.set_text("Carregando página...");
+            status_label.set_visible(true);
+            let (tx, rx) = async_channel::bounded(1);
+            start_page_scan(url.clone(), config.clone(), tx);
+            glib::spawn_future_local(glib::clone!(#[weak] status_label, #[strong] page, #[strong] show_links, async move {
+                match rx.recv().await {
+                    Ok(Ok(links)) => {
+                        *page.borrow_mut() = Some((url, links));
+                        show_links();
                     }
+                    Ok(Err(e)) => status_label.set_text(&format!("Erro: {}", e)),
+                    Err(_) => status_label.set_visible(false),
+                }
+            }));
+        }
+    );
+    let list = std::rc::Rc::new(list);
+    list_button.connect_clicked(glib::clone!(#[strong] list, move |_| list()));
+    for entry in [&url_entry, &extensions_entry, &pattern_entry] {
+        entry.connect_activate(glib::clone!(#[strong] list, move |_| list()));
+    }
 
-                    // Atualiza badge para completo (verde)
-                    status_badge_clone.remove_css_class("in-progress");
-                    status_badge_clone.remove_css_class("paused");
-                    status_badge_clone.remove_css_class("failed");
-                    status_badge_clone.remove_css_class("cancelled");
-                    status_badge_clone.add_css_class("completed");
-
-                    // Atualiza barra de progresso para completo (verde)
-                    progress_bar_clone.remove_css_class("in-progress");
-                    progress_bar_clone.remove_css_class("paused");
-                    progress_bar_clone.remove_css_class("failed");
-                    progress_bar_clone.remove_css_class("cancelled");
-                    progress_bar_clone.add_css_class("completed");
-
-                    // Ícone verde para completo
-                    status_icon_clone.set_icon_name(Some("emblem-ok-symbolic"));
-                    status_label_clone.set_markup(&markup_status("Concluído"));
-                    speed_label_clone.set_markup(&markup_metadata_primary(""));
-                    eta_label_clone.set_markup(&markup_metadata_secondary(""));
-
-                    // Esconde botões de controle e mostra botões de arquivo completo
-                    pause_btn_clone.set_visible(false);
-                    cancel_btn_clone.set_visible(false);
-                    open_btn_clone.set_visible(true);
-                    open_folder_btn_clone.set_visible(true);
-                    delete_btn_clone.set_visible(true);
-
-                    // Marca como completo e obtém o caminho do arquivo
-                    let file_path_str = if let Ok(task) = download_task_clone_msg.lock() {
-                        task.file_path.as_ref().map(|p| p.to_string_lossy().to_string())
-                    } else {
-                        None
-                    };
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    dialog.connect_response(Some("add"), move |_, _| {
+        let mut added = 0;
+        for (url, check) in checks.borrow().iter() {
+            if check.is_active() {
+                add_download(&list_box, new_download_id(), url, DownloadOptions::default(), &state, &content_stack);
+                added += 1;
+            }
+        }
+        if added > 0 {
+            content_stack.set_visible_child_name("list");
+            show_toast(&list_box, &format!("{} download(s) adicionado(s)", added));
+        }
+    });
 
-                    // Atualiza registro no arquivo
-                    if let Ok(mut records) = state_records_clone.lock() {
-                        if let Some(record) = records.iter_mut().find(|r| r.url == record_url_clone) {
-                            record.status = DownloadStatus::Completed;
-                            record.file_path = file_path_str;
-                            record.date_completed = Some(Utc::now());
-                            record.downloaded_bytes = record.total_bytes; // Marca como 100% completo
-                        }
-                        save_downloads(&records);
-                    }
+    url_entry.grab_focus();
+    dialog.present(Some(window));
+}
 
-                    break;
-                }
-                DownloadMessage::Error(err) => {
-                    // Remove velocidade do HashMap quando há erro
-                    if let Ok(app_state) = state_clone.lock() {
-                        if let Ok(mut speeds) = app_state.download_speeds.lock() {
-                            speeds.remove(&record_url_clone);
-                        }
-                    }
+// Arquivos e pastas marcados no navegador de diretórios
+type IndexSelection = std::rc::Rc<std::cell::RefCell<Vec<(IndexEntry, gtk4::CheckButton)>>>;
+
+// Navegador de listagens de diretório (índices do Apache, nginx...): mostra a pasta
+// em árvore e baixa os arquivos marcados mantendo as subpastas. Pastas marcadas
+// são baixadas inteiras.
+fn show_directory_browser(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+) {
+    let Ok(config) = state.lock().map(|app_state| app_state.config.clone()) else {
+        return;
+    };
 
-                    // Atualiza ícone de status e badge baseado no tipo de erro
-                    let (icon_name, badge_class, status) = if err.contains("Cancelado") {
-                        ("process-stop-symbolic", "cancelled", DownloadStatus::Cancelled) // cinza
-                    } else {
-                        ("dialog-error-symbolic", "failed", DownloadStatus::Failed) // vermelho
-                    };
+    let dialog = libadwaita::AlertDialog::builder()
+        .heading("Navegar em Diretório")
+        .body("Endereço de uma listagem de diretório (HTTP ou FTP). Os arquivos marcados são baixados mantendo as subpastas.")
+        .build();
+    dialog.add_response("cancel", "Fechar");
+    dialog.add_response("add", "Baixar Selecionados");
+    dialog.set_response_appearance("add", ResponseAppearance::Suggested);
+    dialog.set_response_enabled("add", false);
+    dialog.set_close_response("cancel");
+
+    let url_entry = Entry::builder()
+        .placeholder_text("https://exemplo.com/pub/")
+        .hexpand(true)
+        .build();
+    let open_button = Button::with_label("Abrir");
+    let url_box = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+    url_box.append(&url_entry);
+    url_box.append(&open_button);
 
-                    // Atualiza classe CSS do badge
-                    status_badge_clone.remove_css_class("completed");
-                    status_badge_clone.remove_css_class("in-progress");
-                    status_badge_clone.remove_css_class("paused");
-                    status_badge_clone.remove_css_class("failed");
-                    status_badge_clone.remove_css_class("cancelled");
-                    status_badge_clone.add_css_class(badge_class);
-
-                    // Atualiza classe CSS da barra de progresso
-                    progress_bar_clone.remove_css_class("completed");
-                    progress_bar_clone.remove_css_class("in-progress");
-                    progress_bar_clone.remove_css_class("paused");
-                    progress_bar_clone.remove_css_class("failed");
-                    progress_bar_clone.remove_css_class("cancelled");
-                    progress_bar_clone.add_css_class(badge_class);
-
-                    status_icon_clone.set_icon_name(Some(icon_name));
-                    status_label_clone.set_markup(&markup_status(&format!("Erro: {}", err)));
-                    speed_label_clone.set_markup(&markup_metadata_primary(""));
-                    eta_label_clone.set_markup(&markup_metadata_secondary(""));
-                    pause_btn_clone.set_visible(false);
-                    cancel_btn_clone.set_visible(false);
-                    delete_btn_clone.set_visible(true);
-
-                    // Atualiza registro de erro
-
-                    if let Ok(mut records) = state_records_clone.lock() {
-                        if let Some(record) = records.iter_mut().find(|r| r.url == record_url_clone) {
-                            record.status = status;
-                            record.date_completed = Some(Utc::now());
+    let status_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["dim-label", "caption"])
+        .visible(false)
+        .build();
+    let tree_box = GtkBox::new(Orientation::Vertical, SPACING_TINY);
+    tree_box.set_margin_top(SPACING_SMALL);
+    tree_box.set_margin_bottom(SPACING_SMALL);
+    tree_box.set_margin_start(SPACING_SMALL);
+    tree_box.set_margin_end(SPACING_SMALL);
+    let tree_scroll = ScrolledWindow::builder()
+        .child(&tree_box)
+        .min_content_height(280)
+        .min_content_width(480)
+        .visible(false)
+        .build();
+    tree_scroll.add_css_class("card");
+
+    let main_box = GtkBox::new(Orientation::Vertical, SPACING_MEDIUM);
+    main_box.append(&url_box);
+    main_box.append(&status_label);
+    main_box.append(&tree_scroll);
+    dialog.set_extra_child(Some(&main_box));
+
+    let selection: IndexSelection = Default::default();
+    let base_url: std::rc::Rc<std::cell::RefCell<String>> = Default::default();
+
+    let open = glib::clone!(
+        #[weak] dialog,
+        #[weak] url_entry,
+        #[weak] status_label,
+        #[weak] tree_box,
+        #[weak] tree_scroll,
+        #[strong] selection,
+        #[strong] base_url,
+        #[strong] config, move || {
+            let url = url_entry.text().trim().to_string();
+            if !url.starts_with("http://") && !url.starts_with("https://") && !is_ftp_url(&url) {
+                status_label.set_text("Informe um endereço http(s) ou ftp");
+                status_label.set_visible(true);
+                return;
+            }
+            while let Some(child) = tree_box.first_child() {
+                tree_box.remove(&child);
+            }
+            selection.borrow_mut().clear();
+            *base_url.borrow_mut() = directory_url(&url);
+            status_label.set_text("Carregando...");
+            status_label.set_visible(true);
+            dialog.set_response_enabled("add", false);
+
+            let (tx, rx) = async_channel::bounded(1);
+            start_directory_listing(url, false, config.clone(), tx);
+            glib::spawn_future_local(glib::clone!(
+                #[weak] dialog,
+                #[weak] status_label,
+                #[weak] tree_box,
+                #[weak] tree_scroll,
+                #[strong] selection,
+                #[strong] config, async move {
+                    match rx.recv().await {
+                        Ok(Ok(entries)) if !entries.is_empty() => {
+                            status_label.set_visible(false);
+                            fill_directory_tree(&tree_box, entries, &selection, &config);
+                            tree_scroll.set_visible(true);
+                            dialog.set_response_enabled("add", true);
                         }
-                        save_downloads(&records);
+                        Ok(Ok(_)) => status_label.set_text("Nenhum arquivo encontrado na listagem"),
+                        Ok(Err(e)) => status_label.set_text(&format!("Erro: {}", e)),
+                        Err(_) => status_label.set_visible(false),
                     }
-
-                    break;
                 }
-            }
+            ));
         }
-    });
-
-    // Handler para botão de abrir arquivo
-    let download_task_clone = download_task.clone();
-    open_btn.connect_clicked(move |_| {
-        if let Ok(task) = download_task_clone.lock() {
-            if let Some(ref path) = task.file_path {
-                // Abre o arquivo com o app padrão do sistema
-                if let Err(e) = open::that(path) {
-                    eprintln!("Erro ao abrir arquivo: {}", e);
-                }
+    );
+    let open = std::rc::Rc::new(open);
+    open_button.connect_clicked(glib::clone!(#[strong] open, move |_| open()));
+    url_entry.connect_activate(glib::clone!(#[strong] open, move |_| open()));
+
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    dialog.connect_response(Some("add"), move |_, _| {
+        let base = base_url.borrow().clone();
+        let checked: Vec<IndexEntry> = selection
+            .borrow()
+            .iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(entry, _)| entry.clone())
+            .collect();
+        // Itens dentro de uma pasta marcada já vêm com ela
+        let folders: Vec<String> = checked.iter().filter(|e| e.is_dir).map(|e| e.url.clone()).collect();
+        let inside_folder = |url: &str| folders.iter().any(|folder| url != folder && url.starts_with(folder.as_str()));
+
+        let enqueue = {
+            let (list_box, state, content_stack, base) = (list_box.clone(), state.clone(), content_stack.clone(), base.clone());
+            move |entry: &IndexEntry| {
+                let options = DownloadOptions {
+                    subfolder: relative_folder(&base, &entry.url).filter(|folder| !folder.is_empty()),
+                    ..DownloadOptions::default()
+                };
+                add_download(&list_box, new_download_id(), &entry.url, options, &state, &content_stack);
             }
+        };
+        let mut added = 0;
+        for entry in checked.iter().filter(|e| !e.is_dir && !inside_folder(&e.url)) {
+            enqueue(entry);
+            added += 1;
         }
-    });
-
-    // Handler para botão de abrir pasta no explorador
-    let download_task_clone_folder = download_task.clone();
-    open_folder_btn.connect_clicked(move |_| {
-        if let Ok(task) = download_task_clone_folder.lock() {
-            if let Some(ref path) = task.file_path {
-                // Abre a pasta que contém o arquivo no explorador
-                if let Some(parent) = PathBuf::from(path).parent() {
-                    if let Err(e) = open::that(parent) {
-                        eprintln!("Erro ao abrir pasta: {}", e);
-                    }
+        for folder in checked.iter().filter(|e| e.is_dir && !inside_folder(&e.url)) {
+            let (tx, rx) = async_channel::bounded(1);
+            start_directory_listing(folder.url.clone(), true, config.clone(), tx);
+            let enqueue = enqueue.clone();
+            glib::spawn_future_local(async move {
+                match rx.recv().await {
+                    Ok(Ok(files)) => files.iter().for_each(&enqueue),
+                    Ok(Err(e)) => eprintln!("Erro ao listar pasta: {}", e),
+                    Err(_) => {}
                 }
-            }
+            });
+            added += 1;
+        }
+        if added > 0 {
+            content_stack.set_visible_child_name("list");
         }
     });
 
-    // Handler para botão de informações
-    let state_records_clone_info = state_records.clone();
-    let record_url_clone_info = record_url.clone();
-    info_btn.connect_clicked(move |_| {
-        // Pega as informações do registro
-        if let Ok(records) = state_records_clone_info.lock() {
-            if let Some(record) = records.iter().find(|r| r.url == record_url_clone_info) {
-                // Cria diálogo de informações
-                let dialog = libadwaita::MessageDialog::new(
-                    None::<&AdwApplicationWindow>,
-                    Some("Informações do Download"),
-                    None,
-                );
+    url_entry.grab_focus();
+    dialog.present(Some(window));
+}
 
-                dialog.add_response("close", "Fechar");
-                dialog.set_response_appearance("close", libadwaita::ResponseAppearance::Default);
-                dialog.set_default_response(Some("close"));
-                dialog.set_close_response("close");
+// Acrescenta as entradas de uma pasta à árvore. Subpastas viram expanders que
+// carregam o conteúdo na primeira vez que são abertos
+fn fill_directory_tree(
+    container: &GtkBox,
+    entries: Vec<IndexEntry>,
+    selection: &IndexSelection,
+    config: &Arc<Mutex<AppConfig>>,
+) {
+    for entry in entries {
+        let check = gtk4::CheckButton::new();
+        let icon = gtk4::Image::from_icon_name(if entry.is_dir { "folder-symbolic" } else { "text-x-generic-symbolic" });
+        let name = Label::builder()
+            .label(entry.name.as_str())
+            .halign(gtk4::Align::Start)
+            .hexpand(true)
+            .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+            .build();
+        let header = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+        header.append(&check);
+        header.append(&icon);
+        header.append(&name);
+        if let Some(size) = entry.size {
+            let size_label = Label::builder()
+                .label(format_file_size(size))
+                .css_classes(vec!["dim-label", "caption"])
+                .build();
+            header.append(&size_label);
+        }
 
-                // Container principal
-                let main_box = GtkBox::builder()
-                    .orientation(Orientation::Vertical)
-                    .spacing(16)
-                    .margin_top(12)
-                    .margin_bottom(12)
-                    .margin_start(16)
-                    .margin_end(16)
-                    .build();
+        if entry.is_dir {
+            let children = GtkBox::new(Orientation::Vertical, SPACING_TINY);
+            children.set_margin_start(SPACING_LARGE);
+            let expander = gtk4::Expander::builder().label_widget(&header).child(&children).build();
+            let loaded = std::cell::Cell::new(false);
+            let url = entry.url.clone();
+            expander.connect_expanded_notify(glib::clone!(
+                #[weak] children,
+                #[strong] selection,
+                #[strong] config, move |expander| {
+                    if !expander.is_expanded() || loaded.replace(true) {
+                        return;
+                    }
+                    let loading = Label::builder()
+                        .label("Carregando...")
+                        .halign(gtk4::Align::Start)
+                        .css_classes(vec!["dim-label", "caption"])
+                        .build();
+                    children.append(&loading);
+                    let (tx, rx) = async_channel::bounded(1);
+                    start_directory_listing(url.clone(), false, config.clone(), tx);
+                    glib::spawn_future_local(glib::clone!(
+                        #[weak] children,
+                        #[weak] loading,
+                        #[strong] selection,
+                        #[strong] config, async move {
+                            match rx.recv().await {
+                                Ok(Ok(entries)) => {
+                                    children.remove(&loading);
+                                    fill_directory_tree(&children, entries, &selection, &config);
+                                }
+                                Ok(Err(e)) => loading.set_text(&format!("Erro: {}", e)),
+                                Err(_) => {}
+                            }
+                        }
+                    ));
+                }
+            ));
+            container.append(&expander);
+        } else {
+            container.append(&header);
+        }
+        selection.borrow_mut().push((entry, check));
+    }
+}
 
-                // Nome do arquivo
-                let filename_group = GtkBox::builder()
-                    .orientation(Orientation::Vertical)
-                    .spacing(4)
-                    .build();
+// Seletor de pasta do sistema (portal, quando disponível); cancelar não chama `on_chosen`
+fn choose_folder(window: &AdwApplicationWindow, title: &str, accept_label: &str, on_chosen: impl FnOnce(PathBuf) + 'static) {
+    let dialog = gtk4::FileDialog::builder().title(title).accept_label(accept_label).modal(true).build();
+    dialog.select_folder(Some(window), gio::Cancellable::NONE, move |result| {
+        if let Some(path) = result.ok().and_then(|file| file.path()) {
+            on_chosen(path);
+        }
+    });
+}
 
-                let filename_label = Label::builder()
-                    .label("Nome do Arquivo")
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["title-4"])
-                    .build();
+// Pasta para onde os arquivos vão ao concluir (ex.: a pasta observada por um
+// servidor de mídia). O .part continua na pasta de downloads enquanto baixa.
+fn show_finished_folder_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.finished_directory.clone()));
+
+    let body = match &current {
+        Some(dir) => format!("Os downloads em andamento ficam na pasta de downloads; ao concluir, o arquivo é movido para:\n{}", dir),
+        None => "Os downloads em andamento ficam na pasta de downloads; ao concluir, o arquivo pode ser movido para outra pasta.".to_string(),
+    };
+    let dialog = libadwaita::AlertDialog::new(Some("Pasta de Concluídos"), Some(&body));
+    dialog.add_response("cancel", "Cancelar");
+    if current.is_some() {
+        dialog.add_response("off", "Não Mover");
+    }
+    dialog.add_response("choose", "Escolher Pasta...");
+    dialog.set_response_appearance("choose", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("choose"));
+    dialog.set_close_response("cancel");
+
+    let window_response = window.clone();
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        let save = {
+            let state = state.clone();
+            move |dir: Option<String>| {
+                if let Ok(app_state) = state.lock() {
+                    if let Ok(mut config) = app_state.config.lock() {
+                        config.finished_directory = dir;
+                        save_config(&config);
+                    }
+                }
+            }
+        };
+        match response {
+            "off" => {
+                save(None);
+                toast_overlay.add_toast(libadwaita::Toast::new("Concluídos ficam na pasta de downloads"));
+            }
+            "choose" => {
+                let toast_overlay = toast_overlay.clone();
+                choose_folder(&window_response, "Selecionar Pasta de Concluídos", "Selecionar", move |path| {
+                    let path_str = path.to_string_lossy().to_string();
+                    save(Some(path_str.clone()));
+                    toast_overlay.add_toast(libadwaita::Toast::new(&format!("Concluídos vão para:\n{}", path_str)));
+                });
+            }
+            _ => {}
+        }
+    });
 
-                let filename_value = Label::builder()
-                    .label(&record.filename)
-                    .halign(gtk4::Align::Start)
-                    .wrap(true)
-                    .selectable(true)
-                    .css_classes(vec!["caption"])
-                    .build();
+    dialog.present(Some(window));
+}
 
-                filename_group.append(&filename_label);
-                filename_group.append(&filename_value);
+// Antivírus externo rodado em cada arquivo concluído (ver `keepers::scan`)
+fn show_scanner_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.scanner_command.clone()))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Antivírus"),
+        Some("Comando rodado em cada arquivo concluído, com o caminho do arquivo no final. \
+              Saída 1 marca o arquivo como infectado e bloqueia o botão de abrir. Vazio = não verificar."),
+    );
 
-                // URL de origem com botão de copiar
-                let url_group = GtkBox::builder()
-                    .orientation(Orientation::Vertical)
-                    .spacing(4)
-                    .build();
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-                let url_label = Label::builder()
-                    .label("URL de Origem")
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["title-4"])
-                    .build();
+    let entry = Entry::builder()
+        .text(current.as_str())
+        .placeholder_text("clamscan --no-summary")
+        .activates_default(true)
+        .build();
+    dialog.set_extra_child(Some(&entry));
 
-                let url_box = GtkBox::builder()
-                    .orientation(Orientation::Horizontal)
-                    .spacing(8)
-                    .build();
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let command = entry.text().trim().to_string();
+        let command = (!command.is_empty()).then_some(command);
+        let message = match &command {
+            Some(command) => format!("Antivírus: {}", command),
+            None => "Arquivos concluídos não são verificados".to_string(),
+        };
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.scanner_command = command;
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&message));
+    });
 
-                let url_value = Label::builder()
-                    .label(&record.url)
-                    .halign(gtk4::Align::Start)
-                    .hexpand(true)
-                    .wrap(true)
-                    .ellipsize(gtk4::pango::EllipsizeMode::End)
-                    .selectable(true)
-                    .css_classes(vec!["caption"])
-                    .build();
+    dialog.present(Some(window));
+}
 
-                let copy_btn = Button::builder()
-                    .icon_name("edit-copy-symbolic")
-                    .tooltip_text("Copiar URL")
-                    .valign(gtk4::Align::Start)
-                    .build();
+// Servidores que limitam cada conexão: downloads deles sempre em chunks
+// paralelos, mesmo abaixo do tamanho mínimo (ver `HostBook::forces_segmented`)
+fn show_segmented_hosts_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.segmented_hosts.join(" ")))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Servidores Limitados"),
+        Some("Servidores que limitam a velocidade de cada conexão, separados por espaço. \
+              Os downloads deles abrem várias conexões mesmo em arquivos pequenos. \
+              Servidores em que o histórico mostrou o mesmo entram sozinhos."),
+    );
 
-                let record_url_copy = record.url.clone();
-                let dialog_clone = dialog.clone();
-                copy_btn.connect_clicked(move |_| {
-                    if let Some(display) = gtk4::gdk::Display::default() {
-                        let clipboard = display.clipboard();
-                        clipboard.set_text(&record_url_copy);
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-                        // Feedback visual temporário
-                        dialog_clone.set_body("URL copiada para a área de transferência");
+    let entry = Entry::builder()
+        .text(current.as_str())
+        .placeholder_text("arquivos.exemplo.com cdn.exemplo.org")
+        .activates_default(true)
+        .build();
+    dialog.set_extra_child(Some(&entry));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let hosts: Vec<String> = entry.text().split_whitespace().map(str::to_ascii_lowercase).collect();
+        let message = match hosts.len() {
+            0 => "Só o histórico decide os servidores limitados".to_string(),
+            1 => "1 servidor sempre em várias conexões".to_string(),
+            count => format!("{} servidores sempre em várias conexões", count),
+        };
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.segmented_hosts = hosts;
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&message));
+    });
+
+    dialog.present(Some(window));
+}
+
+fn show_export_queue(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>, toast_overlay: &libadwaita::ToastOverlay) {
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Exportar Fila"),
+        Some("Os downloads em andamento vão para uma pasta nova dentro da pasta escolhida."),
+    );
+    let partial_check = gtk4::CheckButton::builder()
+        .label("Incluir arquivos parciais (.part)")
+        .active(true)
+        .build();
+    dialog.set_extra_child(Some(&partial_check));
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("choose", "Escolher Pasta...");
+    dialog.set_response_appearance("choose", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("choose"));
+    dialog.set_close_response("cancel");
+
+    let window_response = window.clone();
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("choose"), move |_, _| {
+        let include_partial = partial_check.is_active();
+        let (state, toast_overlay) = (state.clone(), toast_overlay.clone());
+        choose_folder(&window_response, "Exportar Fila para a Pasta", "Exportar", move |folder| {
+            export_queue_to(&folder, include_partial, &state, &toast_overlay);
+        });
+    });
+
+    dialog.present(Some(window));
+}
+
+// Certificado CA extra: escolher um arquivo PEM ou remover o atual
+fn show_ca_dialog(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>, toast_overlay: &libadwaita::ToastOverlay) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.ca_bundle_path.clone()));
+    let body = match &current {
+        Some(path) => format!("Certificados extras confiáveis para servidores internos.\n\nAtual: {}", path),
+        None => "Certificados extras confiáveis para servidores internos.".to_string(),
+    };
+    let dialog = libadwaita::AlertDialog::new(Some("Certificado CA"), Some(&body));
+    dialog.add_response("cancel", "Cancelar");
+    if current.is_some() {
+        dialog.add_response("remove", "Remover");
+        dialog.set_response_appearance("remove", ResponseAppearance::Destructive);
+    }
+    dialog.add_response("choose", "Escolher Arquivo...");
+    dialog.set_response_appearance("choose", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("choose"));
+    dialog.set_close_response("cancel");
+
+    let window_response = window.clone();
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        let save = {
+            let (state, toast_overlay) = (state.clone(), toast_overlay.clone());
+            move |new_path: Option<String>| {
+                if let Ok(app_state) = state.lock() {
+                    if let Ok(mut config) = app_state.config.lock() {
+                        config.ca_bundle_path = new_path.clone();
+                        save_config(&config);
+                    }
+                }
+                let message = match new_path {
+                    Some(path) => format!("Certificado CA adicional:\n{}", path),
+                    None => "Certificado CA adicional removido".to_string(),
+                };
+                toast_overlay.add_toast(libadwaita::Toast::new(&message));
+            }
+        };
+        match response {
+            "remove" => save(None),
+            "choose" => {
+                let filter = gtk4::FileFilter::new();
+                filter.set_name(Some("Certificados (PEM)"));
+                for pattern in ["*.pem", "*.crt", "*.cer"] {
+                    filter.add_pattern(pattern);
+                }
+                let file_dialog = gtk4::FileDialog::builder()
+                    .title("Selecionar Certificado CA (PEM)")
+                    .default_filter(&filter)
+                    .modal(true)
+                    .build();
+                file_dialog.open(Some(&window_response), gio::Cancellable::NONE, move |result| {
+                    if let Some(path) = result.ok().and_then(|file| file.path()) {
+                        save(Some(path.to_string_lossy().to_string()));
                     }
                 });
+            }
+            _ => {}
+        }
+    });
 
-                url_box.append(&url_value);
-                url_box.append(&copy_btn);
-                url_group.append(&url_label);
-                url_group.append(&url_box);
+    dialog.present(Some(window));
+}
 
-                // Tamanho do arquivo
-                let size_group = GtkBox::builder()
-                    .orientation(Orientation::Vertical)
-                    .spacing(4)
-                    .build();
+// Grava a fila numa pasta nova (keepers-fila-<data>) dentro de `folder`
+fn export_queue_to(folder: &Path, include_partial: bool, state: &Arc<Mutex<AppState>>, toast_overlay: &libadwaita::ToastOverlay) {
+    let dest = folder.join(format!("keepers-fila-{}", Local::now().format("%Y%m%d-%H%M")));
+    let download_dir = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| get_download_directory(&config)))
+        .unwrap_or_else(|| PathBuf::from("."));
+    let records = state_records(state).lock().map(|records| records.clone()).unwrap_or_default();
+    let message = match export_queue(&records, &download_dir, &dest, include_partial) {
+        Ok(0) => "Nenhum download em andamento para exportar".to_string(),
+        Ok(count) => format!("{} download(s) exportado(s) para {}", count, dest.display()),
+        Err(e) => {
+            eprintln!("Erro ao exportar fila: {}", e);
+            format!("Erro ao exportar fila: {}", e)
+        }
+    };
+    toast_overlay.add_toast(libadwaita::Toast::new(&message));
+}
 
-                let size_label = Label::builder()
-                    .label("Tamanho")
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["title-4"])
-                    .build();
+fn show_import_queue(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    let toast_overlay = toast_overlay.clone();
+    choose_folder(window, "Importar Fila (pasta com fila.json)", "Importar", move |folder| {
+        let download_dir = state
+            .lock()
+            .ok()
+            .and_then(|app_state| app_state.config.lock().ok().map(|config| get_download_directory(&config)))
+            .unwrap_or_else(|| PathBuf::from("."));
+        let state_records = state_records(&state);
+        let existing = state_records.lock().map(|records| records.clone()).unwrap_or_default();
+        let imported = match import_queue(&folder, &download_dir, &existing) {
+            Ok(imported) => imported,
+            Err(e) => {
+                eprintln!("Erro ao importar fila: {}", e);
+                toast_overlay.add_toast(libadwaita::Toast::new(&format!("Erro ao importar fila: {}", e)));
+                return;
+            }
+        };
+        if let Ok(mut records) = state_records.lock() {
+            records.extend(imported.iter().cloned());
+            save_downloads(&records);
+        }
+        // Entram pausados: cada um continua de onde parou ao ser retomado
+        for record in &imported {
+            add_completed_download(&list_box, record, &state, &content_stack);
+        }
+        if !imported.is_empty() {
+            content_stack.set_visible_child_name("list");
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&format!("{} download(s) importado(s), pausados", imported.len())));
+    });
+}
 
-                let size_value = Label::builder()
-                    .label(&format_file_size(record.total_bytes))
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["caption"])
-                    .build();
+// Links exportados pelo uGet ou JDownloader; o pacote/categoria vira a subpasta
+fn show_import_links(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let filter = gtk4::FileFilter::new();
+    filter.set_name(Some("Listas de links (crawljob, DLC, uGet, texto)"));
+    for pattern in ["*.crawljob", "*.dlc", "*.json", "*.txt"] {
+        filter.add_pattern(pattern);
+    }
+    let file_dialog = gtk4::FileDialog::builder()
+        .title("Importar Links")
+        .accept_label("Importar")
+        .default_filter(&filter)
+        .modal(true)
+        .build();
 
-                size_group.append(&size_label);
-                size_group.append(&size_value);
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    let toast_overlay = toast_overlay.clone();
+    file_dialog.open(Some(window), gio::Cancellable::NONE, move |result| {
+        let Some(path) = result.ok().and_then(|file| file.path()) else {
+            return;
+        };
+        let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let links = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| parse_link_list(&file_name, &String::from_utf8_lossy(&bytes)));
+        let links = match links {
+            Ok(links) => links,
+            Err(e) => {
+                eprintln!("Erro ao importar links: {}", e);
+                toast_overlay.add_toast(libadwaita::Toast::new(&format!("Erro ao importar links: {}", e)));
+                return;
+            }
+        };
+        let (supported, skipped): (Vec<_>, Vec<_>) = links.into_iter().partition(|link| is_download_url(&link.url));
+        for link in &supported {
+            let options = DownloadOptions { subfolder: link.category.clone(), ..DownloadOptions::default() };
+            add_download(&list_box, new_download_id(), &link.url, options, &state, &content_stack);
+        }
+        if !supported.is_empty() {
+            content_stack.set_visible_child_name("list");
+        }
+        let mut message = format!("{} download(s) importado(s)", supported.len());
+        if !skipped.is_empty() {
+            message.push_str(&format!(", {} link(s) não suportado(s)", skipped.len()));
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&message));
+    });
+}
 
-                // Status
-                let status_group = GtkBox::builder()
-                    .orientation(Orientation::Vertical)
-                    .spacing(4)
-                    .build();
+// Regras de URLs permitidas e bloqueadas, uma regex por linha
+fn show_url_filter_dialog(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>, toast_overlay: &libadwaita::ToastOverlay) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.url_filter.clone()))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Filtro de URLs"),
+        Some("Uma expressão regular por linha, sem diferenciar maiúsculas. O bloqueio vence; com \
+              alguma regra de permissão, só as URLs que casam com ela podem ser baixadas."),
+    );
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let rules_view = |title: &str, rules: &[String]| {
+        let view = gtk4::TextView::builder()
+            .top_margin(SPACING_SMALL)
+            .bottom_margin(SPACING_SMALL)
+            .left_margin(SPACING_SMALL)
+            .right_margin(SPACING_SMALL)
+            .monospace(true)
+            .build();
+        view.buffer().set_text(&rules.join("\n"));
+        let scroll = ScrolledWindow::builder()
+            .child(&view)
+            .min_content_height(80)
+            .min_content_width(360)
+            .build();
+        scroll.add_css_class("card");
+        let section = GtkBox::new(Orientation::Vertical, SPACING_SMALL);
+        section.append(&Label::builder().label(title).halign(gtk4::Align::Start).build());
+        section.append(&scroll);
+        (view, section)
+    };
+    let (allow_view, allow_section) = rules_view("Permitir (vazio = qualquer uma)", &current.allow);
+    let (deny_view, deny_section) = rules_view("Bloquear", &current.deny);
+    let rules_box = GtkBox::new(Orientation::Vertical, SPACING_LARGE);
+    rules_box.append(&allow_section);
+    rules_box.append(&deny_section);
+    dialog.set_extra_child(Some(&rules_box));
+
+    let lines = |view: &gtk4::TextView| -> Vec<String> {
+        let buffer = view.buffer();
+        buffer
+            .text(&buffer.start_iter(), &buffer.end_iter(), false)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("save"), move |_, _| {
+        let settings = UrlFilterSettings { allow: lines(&allow_view), deny: lines(&deny_view) };
+        if let Err(e) = UrlFilter::new(&settings) {
+            toast_overlay.add_toast(libadwaita::Toast::new(&e));
+            return;
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.url_filter = settings;
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new("Filtro de URLs atualizado"));
+    });
 
-                let status_label = Label::builder()
-                    .label("Status")
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["title-4"])
-                    .build();
+    dialog.present(Some(window));
+}
 
-                let status_text = match record.status {
-                    DownloadStatus::InProgress => if record.was_paused { "Pausado" } else { "Em Progresso" },
-                    DownloadStatus::Completed => "Concluído",
-                    DownloadStatus::Failed => "Falhou",
-                    DownloadStatus::Cancelled => "Cancelado",
-                };
+// Senha da lista de downloads cifrada. Sem ela, a sessão funciona mas nada é salvo.
+fn show_unlock_dialog(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Lista de Downloads Protegida"),
+        Some("Digite a senha para abrir o histórico e retomar os downloads. Sem ela, nada desta sessão é salvo."),
+    );
+    let password_entry = gtk4::PasswordEntry::builder()
+        .placeholder_text("Senha")
+        .show_peek_icon(true)
+        .activates_default(true)
+        .build();
+    dialog.set_extra_child(Some(&password_entry));
+    dialog.add_response("skip", "Continuar Sem Histórico");
+    dialog.add_response("unlock", "Abrir");
+    dialog.set_response_appearance("unlock", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("unlock"));
+    dialog.set_close_response("skip");
+
+    let window_response = window.clone();
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("unlock"), move |_, _| {
+        let loaded = match unlock_downloads(&password_entry.text()) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                toast_overlay.add_toast(libadwaita::Toast::new(&e));
+                show_unlock_dialog(&window_response, &list_box, &state, &content_stack, &toast_overlay);
+                return;
+            }
+        };
+        // Senhas da configuração cifradas com a mesma chave (o save muda de lugar
+        // as que ainda estavam no config.json)
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                if let Err(e) = unlock_secrets(&mut config) {
+                    toast_overlay.add_toast(libadwaita::Toast::new(&format!("Não foi possível abrir as senhas salvas ({})", e)));
+                }
+                save_config(&config);
+            }
+            // Consumo e histórico de servidores cifrados: o que esta sessão já
+            // baixou soma com o que estava salvo
+            if let Ok(mut usage) = app_state.usage.lock() {
+                let mut saved = load_usage();
+                saved.merge(&usage);
+                *usage = saved;
+                save_usage(&usage);
+            }
+            if let Ok(mut hosts) = app_state.hosts.lock() {
+                let mut saved = load_host_stats();
+                saved.merge(&hosts);
+                *hosts = saved;
+                save_host_stats(&hosts);
+            }
+        }
+        // Downloads adicionados antes de desbloquear ficam depois do histórico
+        let state_records = state_records(&state);
+        if let Ok(mut records) = state_records.lock() {
+            let added: Vec<DownloadRecord> = std::mem::take(&mut *records);
+            records.extend(loaded.iter().cloned());
+            records.extend(added);
+            save_downloads(&records);
+        }
+        add_saved_records(&list_box, loaded, &state, &content_stack);
+    });
 
-                let status_value = Label::builder()
-                    .label(status_text)
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["caption"])
-                    .build();
+    dialog.present(Some(window));
+}
 
-                status_group.append(&status_label);
-                status_group.append(&status_value);
+// Ativa ou desativa a criptografia da lista de downloads e dos backups
+fn show_encryption_dialog(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>, toast_overlay: &libadwaita::ToastOverlay) {
+    if downloads_locked() {
+        toast_overlay.add_toast(libadwaita::Toast::new("Abra a lista cifrada com a senha antes de mudar a criptografia"));
+        return;
+    }
+    let encrypted = downloads_encrypted();
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Criptografia"),
+        Some(if encrypted {
+            "A lista de downloads, os backups, o consumo, o histórico de servidores e as senhas da configuração estão cifrados. \
+             Para voltar a gravá-los sem criptografia, digite a senha atual."
+        } else {
+            "Cifra a lista de downloads, os backups, o consumo, o histórico de servidores e as senhas da configuração (S3, perfis de rede e proxy) \
+             com uma senha, pedida ao abrir o Keepers. Os arquivos baixados não são cifrados. \
+             Sem a senha, o histórico não pode ser recuperado."
+        }),
+    );
+    let password_entry = gtk4::PasswordEntry::builder()
+        .placeholder_text(if encrypted { "Senha atual" } else { "Senha" })
+        .show_peek_icon(true)
+        .build();
+    let confirm_entry = gtk4::PasswordEntry::builder()
+        .placeholder_text("Confirmar senha")
+        .show_peek_icon(true)
+        .visible(!encrypted)
+        .build();
+    let entries_box = GtkBox::new(Orientation::Vertical, SPACING_SMALL);
+    entries_box.append(&password_entry);
+    entries_box.append(&confirm_entry);
+    dialog.set_extra_child(Some(&entries_box));
+    dialog.add_response("cancel", "Cancelar");
+    if encrypted {
+        dialog.add_response("save", "Desativar");
+        dialog.set_response_appearance("save", ResponseAppearance::Destructive);
+    } else {
+        dialog.add_response("save", "Ativar");
+        dialog.set_response_appearance("save", ResponseAppearance::Suggested);
+    }
+    dialog.set_close_response("cancel");
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("save"), move |_, _| {
+        let passphrase = password_entry.text().to_string();
+        let result = if encrypted {
+            disable_encryption(&passphrase).map(|_| "Criptografia desativada")
+        } else if passphrase.is_empty() {
+            Err("Informe uma senha".to_string())
+        } else if passphrase != confirm_entry.text() {
+            Err("As senhas não conferem".to_string())
+        } else {
+            enable_encryption(&passphrase).map(|_| "Lista de downloads cifrada")
+        };
+        match result {
+            Ok(message) => {
+                // Regrava a lista, a configuração (com ou sem as senhas), o consumo e o
+                // histórico de servidores no novo formato
+                if let Ok(records) = state_records(&state).lock() {
+                    save_downloads(&records);
+                }
+                if let Ok(app_state) = state.lock() {
+                    if let Ok(config) = app_state.config.lock() {
+                        save_config(&config);
+                    }
+                    if let Ok(usage) = app_state.usage.lock() {
+                        save_usage(&usage);
+                    }
+                    if let Ok(hosts) = app_state.hosts.lock() {
+                        save_host_stats(&hosts);
+                    }
+                }
+                toast_overlay.add_toast(libadwaita::Toast::new(message));
+            }
+            Err(e) => toast_overlay.add_toast(libadwaita::Toast::new(&e)),
+        }
+    });
 
-                // Data de início
-                let date_group = GtkBox::builder()
-                    .orientation(Orientation::Vertical)
-                    .spacing(4)
-                    .build();
+    dialog.present(Some(window));
+}
 
-                let date_label = Label::builder()
-                    .label("Data de Início")
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["title-4"])
-                    .build();
+// Restaura do backup escolhido os downloads que não estão na lista atual
+// (nada da lista é apagado ou substituído)
+// Lista os perfis e abre o escolhido em outra janela (outro processo, com o
+// próprio id de aplicação); também cria perfis novos
+fn show_profiles_dialog(window: &AdwApplicationWindow) {
+    let dialog = libadwaita::AlertDialog::builder()
+        .heading("Perfis")
+        .body("Cada perfil tem configurações, histórico e pasta de downloads próprios. Na linha de comando: keepers --profile NOME")
+        .build();
 
-                let date_value = Label::builder()
-                    .label(&format!("{}", record.date_added.format("%d/%m/%Y às %H:%M:%S")))
-                    .halign(gtk4::Align::Start)
-                    .css_classes(vec!["caption"])
-                    .build();
+    let content = GtkBox::new(Orientation::Vertical, 12);
+    let profiles_list = ListBox::new();
+    profiles_list.set_selection_mode(gtk4::SelectionMode::Single);
+    profiles_list.add_css_class("boxed-list");
+
+    let active = keepers::storage::active_profile();
+    let mut names: Vec<Option<String>> = vec![None];
+    names.extend(keepers::storage::list_profiles().into_iter().map(Some));
+    for name in &names {
+        let title = name.clone().unwrap_or_else(|| "Padrão".to_string());
+        let row = libadwaita::ActionRow::builder().title(title.as_str()).build();
+        if *name == active {
+            row.set_subtitle("Em uso nesta janela");
+        }
+        profiles_list.append(&row);
+    }
+    if let Some(index) = names.iter().position(|name| *name == active) {
+        profiles_list.select_row(profiles_list.row_at_index(index as i32).as_ref());
+    }
+    content.append(&profiles_list);
+
+    let new_entry = Entry::builder()
+        .placeholder_text("Novo perfil (ex.: trabalho)")
+        .build();
+    content.append(&new_entry);
+    dialog.set_extra_child(Some(&content));
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("open", "Abrir Perfil");
+    dialog.set_response_appearance("open", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("open"));
+
+    dialog.connect_response(None, glib::clone!(#[weak] window, move |_, response| {
+        if response != "open" {
+            return;
+        }
+        let new_name = new_entry.text().trim().to_string();
+        let target = if !new_name.is_empty() {
+            if let Err(e) = keepers::storage::create_profile(&new_name) {
+                show_toast_with(&window, libadwaita::Toast::new(&e));
+                return;
+            }
+            Some(new_name)
+        } else {
+            profiles_list
+                .selected_row()
+                .and_then(|row| names.get(row.index() as usize).cloned())
+                .flatten()
+        };
+        if target == keepers::storage::active_profile() {
+            return;
+        }
+        if let Err(e) = launch_profile(target.as_deref()) {
+            eprintln!("Erro ao abrir perfil: {}", e);
+            show_toast_with(&window, libadwaita::Toast::new(&format!("Erro ao abrir perfil: {}", e)));
+        }
+    }));
+
+    dialog.present(Some(window));
+}
+
+// Abre outra instância do Keepers no perfil (None = padrão)
+fn launch_profile(profile: Option<&str>) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut command = std::process::Command::new(exe);
+    if let Some(name) = profile {
+        command.arg(PROFILE_ARG).arg(name);
+    }
+    command.spawn().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn show_restore_backup_dialog(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let backups = list_backups(&get_backups_dir());
+    if backups.is_empty() {
+        toast_overlay.add_toast(libadwaita::Toast::new("Nenhum backup da lista de downloads ainda"));
+        return;
+    }
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Restaurar Backup"),
+        Some("Os downloads do backup que não estão na lista são adicionados de volta; os em andamento voltam pausados."),
+    );
+    let backups_box = ListBox::builder()
+        .selection_mode(gtk4::SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
+        .build();
+    let mut choices: Vec<(PathBuf, gtk4::CheckButton)> = Vec::new();
+    for backup in backups {
+        let count = match read_backup(&backup.path) {
+            Ok(records) => format!("{} download(s)", records.len()),
+            Err(_) => "ilegível".to_string(),
+        };
+        let check = gtk4::CheckButton::builder().valign(gtk4::Align::Center).build();
+        if let Some((_, first)) = choices.first() {
+            check.set_group(Some(first));
+        } else {
+            check.set_active(true);
+        }
+        let row = libadwaita::ActionRow::builder()
+            .title(backup.created.with_timezone(&Local).format("%d/%m/%Y %H:%M").to_string())
+            .subtitle(count)
+            .activatable_widget(&check)
+            .build();
+        row.add_prefix(&check);
+        backups_box.append(&row);
+        choices.push((backup.path, check));
+    }
+    let backups_scroll = ScrolledWindow::builder()
+        .child(&backups_box)
+        .min_content_width(360)
+        .propagate_natural_height(true)
+        .max_content_height(360)
+        .build();
+    dialog.set_extra_child(Some(&backups_scroll));
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("restore", "Restaurar");
+    dialog.set_response_appearance("restore", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("restore"));
+    dialog.set_close_response("cancel");
+
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("restore"), move |_, _| {
+        let Some((path, _)) = choices.iter().find(|(_, check)| check.is_active()) else {
+            return;
+        };
+        let backup = match read_backup(path) {
+            Ok(backup) => backup,
+            Err(e) => {
+                eprintln!("Erro ao ler backup: {}", e);
+                toast_overlay.add_toast(libadwaita::Toast::new(&format!("Erro ao ler backup: {}", e)));
+                return;
+            }
+        };
+        let state_records = state_records(&state);
+        let restored = match state_records.lock() {
+            Ok(mut records) => {
+                let restored = missing_from_backup(backup, &records);
+                records.extend(restored.iter().cloned());
+                save_downloads(&records);
+                restored
+            }
+            Err(_) => return,
+        };
+        for record in &restored {
+            add_completed_download(&list_box, record, &state, &content_stack);
+        }
+        if !restored.is_empty() {
+            content_stack.set_visible_child_name("list");
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&format!("{} download(s) restaurado(s)", restored.len())));
+    });
+
+    dialog.present(Some(window));
+}
+
+// Adota .part encontrados na pasta de downloads sem registro correspondente.
+// Cada um precisa de uma URL; a gravada no próprio .part vem preenchida.
+fn show_orphan_parts_dialog(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    content_stack: &gtk4::Stack,
+    toast_overlay: &libadwaita::ToastOverlay,
+    orphans: Vec<OrphanPart>,
+) {
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Downloads Parciais Encontrados"),
+        Some(&format!(
+            "{} arquivo(s) .part na pasta de downloads não estão na lista. Marque os que deseja \
+             retomar e confira a URL de cada um.",
+            orphans.len()
+        )),
+    );
+
+    let orphans_box = ListBox::builder()
+        .selection_mode(gtk4::SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
+        .build();
+    let mut rows: Vec<(OrphanPart, gtk4::CheckButton, libadwaita::EntryRow)> = Vec::new();
+    for orphan in orphans {
+        let entry_row = libadwaita::EntryRow::builder()
+            .title(format!("{} ({})", orphan.filename, format_file_size(orphan.size)))
+            .text(orphan.url.clone().unwrap_or_default())
+            .build();
+        let check = gtk4::CheckButton::builder()
+            .active(orphan.url.is_some())
+            .valign(gtk4::Align::Center)
+            .build();
+        entry_row.add_prefix(&check);
+        orphans_box.append(&entry_row);
+        rows.push((orphan, check, entry_row));
+    }
+    let orphans_scroll = ScrolledWindow::builder()
+        .child(&orphans_box)
+        .min_content_height(160)
+        .min_content_width(420)
+        .propagate_natural_height(true)
+        .max_content_height(360)
+        .build();
+    dialog.set_extra_child(Some(&orphans_scroll));
+    dialog.add_response("ignore", "Não Perguntar de Novo");
+    dialog.add_response("later", "Agora Não");
+    dialog.add_response("adopt", "Adicionar Pausados");
+    dialog.set_response_appearance("adopt", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("adopt"));
+    dialog.set_close_response("later");
+
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let content_stack = content_stack.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| match response {
+        "ignore" => {
+            if let Ok(app_state) = state.lock() {
+                if let Ok(mut config) = app_state.config.lock() {
+                    for (orphan, _, _) in &rows {
+                        config.ignored_parts.push(orphan.part_path.to_string_lossy().to_string());
+                    }
+                    save_config(&config);
+                }
+            }
+        }
+        "adopt" => {
+            let mut adopted = Vec::new();
+            let mut missing_url = 0;
+            for (orphan, _, entry_row) in rows.iter().filter(|(_, check, _)| check.is_active()) {
+                let url = entry_row.text().trim().to_string();
+                if !is_download_url(&url) {
+                    missing_url += 1;
+                    continue;
+                }
+                adopted.push(adopt_orphan(orphan, &url));
+            }
+            let state_records = state_records(&state);
+            if let Ok(mut records) = state_records.lock() {
+                records.extend(adopted.iter().cloned());
+                save_downloads(&records);
+            }
+            for record in &adopted {
+                add_completed_download(&list_box, record, &state, &content_stack);
+            }
+            if !adopted.is_empty() {
+                content_stack.set_visible_child_name("list");
+            }
+            let mut message = format!("{} download(s) parcial(is) adicionado(s), pausados", adopted.len());
+            if missing_url > 0 {
+                message.push_str(&format!("; {} sem URL válida", missing_url));
+            }
+            toast_overlay.add_toast(libadwaita::Toast::new(&message));
+        }
+        _ => {}
+    });
+
+    dialog.present(Some(window));
+}
+
+// Quantas vezes um download com falha passageira é tentado de novo sozinho.
+// A espera dobra a cada tentativa (ver `keepers::auto_retry`).
+fn show_auto_retry_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.auto_retries))
+        .unwrap_or(0);
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Novas Tentativas"),
+        Some("Downloads que falharam por rede ou servidor instável são tentados de novo sozinhos, esperando 3min, 6min, 12min... entre as tentativas. Zero desliga."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let spin = gtk4::SpinButton::with_range(0.0, MAX_AUTO_RETRIES as f64, 1.0);
+    spin.set_value(current as f64);
+    spin.set_halign(gtk4::Align::Center);
+    dialog.set_extra_child(Some(&spin));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let value = spin.value_as_int().max(0) as u32;
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.auto_retries = (value > 0).then_some(value);
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&if value > 0 {
+            format!("Até {} novas tentativas automáticas", value)
+        } else {
+            "Novas tentativas automáticas desligadas".to_string()
+        }));
+    });
+
+    dialog.present(Some(window));
+}
+
+// Diálogo para escolher o máximo de conexões por download.
+// O motor começa com poucas e só abre mais enquanto a velocidade aumentar.
+fn show_connections_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| get_max_connections(&config)))
+        .unwrap_or(DEFAULT_MAX_CONNECTIONS);
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Conexões Simultâneas"),
+        Some("Número máximo de conexões por download. Novas conexões só são abertas enquanto aumentarem a velocidade."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let spin = gtk4::SpinButton::with_range(1.0, MAX_CONNECTIONS_LIMIT as f64, 1.0);
+    spin.set_value(current as f64);
+    spin.set_halign(gtk4::Align::Center);
+    dialog.set_extra_child(Some(&spin));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let value = spin.value_as_int().max(1) as usize;
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.max_connections = Some(value);
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&format!(
+            "Até {} conexões por download",
+            value
+        )));
+    });
+
+    dialog.present(Some(window));
+}
+
+// Como ficam as versões quando um download repetido ou sincronizado baixa o
+// mesmo arquivo de novo. Cada download pode escolher outra no diálogo de adicionar.
+fn show_version_naming_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.version_naming));
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Nome das Versões"),
+        Some("Ao baixar de novo o mesmo arquivo. No padrão, downloads repetidos ganham a data e hora no nome e os sincronizados substituem a cópia anterior."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let dropdown = version_naming_dropdown("Padrão");
+    let selected = current
+        .and_then(|naming| VersionNaming::ALL.iter().position(|option| *option == naming))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    dropdown.set_selected(selected as u32);
+    dropdown.set_halign(gtk4::Align::Center);
+    dialog.set_extra_child(Some(&dropdown));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let naming = selected_version_naming(&dropdown);
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.version_naming = naming;
+                save_config(&config);
+            }
+        }
+        let label = naming.map(VersionNaming::label).unwrap_or("Padrão");
+        toast_overlay.add_toast(libadwaita::Toast::new(&format!("Versões: {}", label)));
+    });
+
+    dialog.present(Some(window));
+}
+
+// "padrão" (None) seguido das estratégias de `VersionNaming::ALL`
+fn version_naming_dropdown(default_label: &str) -> gtk4::DropDown {
+    let mut labels = vec![default_label];
+    labels.extend(VersionNaming::ALL.iter().map(|naming| naming.label()));
+    gtk4::DropDown::from_strings(&labels)
+}
+
+fn selected_version_naming(dropdown: &gtk4::DropDown) -> Option<VersionNaming> {
+    (dropdown.selected() as usize)
+        .checked_sub(1)
+        .and_then(|index| VersionNaming::ALL.get(index).copied())
+}
+
+// Quantos downloads transferem ao mesmo tempo; os outros esperam na fila e o
+// card mostra a posição. Vale para a fila atual, sem interromper os ativos.
+fn show_active_downloads_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.max_active_downloads))
+        .unwrap_or(0);
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Downloads Simultâneos"),
+        Some("Máximo de downloads transferindo ao mesmo tempo (0 = sem limite). Os demais esperam na fila, na ordem em que foram adicionados."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let spin = gtk4::SpinButton::with_range(0.0, MAX_ACTIVE_DOWNLOADS as f64, 1.0);
+    spin.set_value(current as f64);
+    spin.set_halign(gtk4::Align::Center);
+    dialog.set_extra_child(Some(&spin));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let value = spin.value_as_int().max(0) as usize;
+        let limit = (value > 0).then_some(value);
+        if let Ok(app_state) = state.lock() {
+            app_state.slots.set_limit(limit);
+            if let Ok(mut config) = app_state.config.lock() {
+                config.max_active_downloads = limit;
+                save_config(&config);
+            }
+        }
+        let message = match limit {
+            Some(limit) => format!("Até {} downloads ao mesmo tempo", limit),
+            None => "Sem limite de downloads simultâneos".to_string(),
+        };
+        toast_overlay.add_toast(libadwaita::Toast::new(&message));
+    });
+
+    dialog.present(Some(window));
+}
+
+// Intervalo em que o download sequencial grava o buffer no disco (com fsync)
+fn show_disk_writes_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| get_flush_interval(&config)))
+        .unwrap_or(DEFAULT_FLUSH_INTERVAL_SECS);
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Gravação em Disco"),
+        Some("Segundos entre cada gravação dos dados recebidos. Intervalos maiores poupam o disco; numa queda de energia, no máximo esse tempo de download é baixado de novo."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let spin = gtk4::SpinButton::with_range(1.0, MAX_FLUSH_INTERVAL_SECS as f64, 1.0);
+    spin.set_value(current as f64);
+    spin.set_halign(gtk4::Align::Center);
+    dialog.set_extra_child(Some(&spin));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let value = spin.value_as_int().max(1) as u64;
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.flush_interval_secs = Some(value);
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(&format!(
+            "Dados gravados no disco a cada {}s",
+            value
+        )));
+    });
+
+    dialog.present(Some(window));
+}
+
+// Corpos com Content-Encoding (ver `keepers::decoding`)
+fn show_content_encoding_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.keep_content_encoding))
+        .unwrap_or(false);
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Conteúdo Compactado"),
+        Some("Servidores podem mandar o arquivo compactado (gzip, br). Por padrão ele é baixado como veio e \
+              descompactado ao concluir; arquivos que já são compactados (.gz, .br ou o tipo informado) ficam como vieram."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let keep_check = gtk4::CheckButton::builder()
+        .label("Manter como veio do servidor")
+        .tooltip_text("Nunca descompacta, mesmo quando o nome do arquivo não indica a compactação")
+        .active(current)
+        .build();
+    dialog.set_extra_child(Some(&keep_check));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let keep = keep_check.is_active();
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.keep_content_encoding = keep;
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(if keep {
+            "Conteúdo compactado gravado como veio"
+        } else {
+            "Conteúdo compactado descompactado ao baixar"
+        }));
+    });
+
+    dialog.present(Some(window));
+}
 
-                date_group.append(&date_label);
-                date_group.append(&date_value);
+// Diálogo do reuso de conexões (vale para os próximos downloads; o client é refeito)
+fn show_keep_alive_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.keep_alive))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Reuso de Conexões"),
+        Some("Em links com latência alta, manter conexões abertas evita refazer TCP e TLS a cada download. 0 desliga cada opção."),
+    );
 
-                // Data de conclusão (se completado)
-                if let Some(completed_date) = record.date_completed {
-                    let completed_group = GtkBox::builder()
-                        .orientation(Orientation::Vertical)
-                        .spacing(4)
-                        .build();
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let idle_spin = gtk4::SpinButton::with_range(0.0, MAX_POOL_IDLE_TIMEOUT_SECS as f64, 5.0);
+    idle_spin.set_value(current.pool_idle_timeout_secs as f64);
+    let per_host_spin = gtk4::SpinButton::with_range(0.0, MAX_IDLE_PER_HOST as f64, 1.0);
+    per_host_spin.set_value(current.max_idle_per_host as f64);
+    let keepalive_spin = gtk4::SpinButton::with_range(0.0, MAX_TCP_KEEPALIVE_SECS as f64, 5.0);
+    keepalive_spin.set_value(current.tcp_keepalive_secs as f64);
+
+    let grid = gtk4::Grid::builder().row_spacing(6).column_spacing(12).halign(gtk4::Align::Center).build();
+    let fields = [
+        ("Conexão ociosa aberta por (s)", &idle_spin),
+        ("Conexões ociosas por servidor", &per_host_spin),
+        ("Intervalo do keepalive TCP (s)", &keepalive_spin),
+    ];
+    for (row, (text, spin)) in fields.into_iter().enumerate() {
+        let label = Label::builder().label(text).halign(gtk4::Align::Start).build();
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(spin, 1, row as i32, 1, 1);
+    }
+    dialog.set_extra_child(Some(&grid));
 
-                    let completed_label = Label::builder()
-                        .label("Data de Conclusão")
-                        .halign(gtk4::Align::Start)
-                        .css_classes(vec!["title-4"])
-                        .build();
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.keep_alive.pool_idle_timeout_secs = idle_spin.value_as_int().max(0) as u64;
+                config.keep_alive.max_idle_per_host = per_host_spin.value_as_int().max(0) as usize;
+                config.keep_alive.tcp_keepalive_secs = keepalive_spin.value_as_int().max(0) as u64;
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new("Reuso de conexões atualizado"));
+    });
 
-                    let completed_value = Label::builder()
-                        .label(&format!("{}", completed_date.format("%d/%m/%Y às %H:%M:%S")))
-                        .halign(gtk4::Align::Start)
-                        .css_classes(vec!["caption"])
-                        .build();
+    dialog.present(Some(window));
+}
 
-                    completed_group.append(&completed_label);
-                    completed_group.append(&completed_value);
-                    main_box.append(&completed_group);
-                }
+// Proxy manual e endereço do PAC (valem para os próximos downloads)
+fn show_proxy_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.proxy.clone()))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Proxy"),
+        Some("Com um arquivo PAC, o proxy de cada endereço é decidido pelo script da rede. \
+              O proxy manual vale quando não há PAC ou quando o script falha."),
+    );
 
-                // Caminho do arquivo (se completado)
-                if let Some(ref file_path) = record.file_path {
-                    let path_group = GtkBox::builder()
-                        .orientation(Orientation::Vertical)
-                        .spacing(4)
-                        .build();
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-                    let path_label = Label::builder()
-                        .label("Caminho do Arquivo")
-                        .halign(gtk4::Align::Start)
-                        .css_classes(vec!["title-4"])
-                        .build();
+    let manual_entry = Entry::builder()
+        .placeholder_text("Proxy manual (ex.: http://proxy:3128 ou socks5://proxy:1080)")
+        .text(current.manual.as_str())
+        .build();
+    let pac_entry = Entry::builder()
+        .placeholder_text("Endereço do PAC (ex.: http://wpad/wpad.dat)")
+        .text(current.pac_url.as_str())
+        .build();
+    let fields_box = GtkBox::new(Orientation::Vertical, 6);
+    fields_box.append(&manual_entry);
+    fields_box.append(&pac_entry);
+    dialog.set_extra_child(Some(&fields_box));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let manual = manual_entry.text().trim().to_string();
+        if !manual.is_empty() && !keepers::http::valid_proxy_url(&manual) {
+            toast_overlay.add_toast(libadwaita::Toast::new("Endereço de proxy inválido"));
+            return;
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.proxy.manual = manual;
+                config.proxy.pac_url = pac_entry.text().trim().to_string();
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new("Proxy atualizado"));
+    });
 
-                    let path_value = Label::builder()
-                        .label(file_path)
-                        .halign(gtk4::Align::Start)
-                        .wrap(true)
-                        .selectable(true)
-                        .css_classes(vec!["caption"])
-                        .build();
+    dialog.present(Some(window));
+}
 
-                    path_group.append(&path_label);
-                    path_group.append(&path_value);
-                    main_box.append(&path_group);
+// Cria, edita e remove perfis de rede. Downloads guardam só o nome do perfil,
+// então a mudança vale também para as próximas tentativas deles.
+fn show_network_profiles_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let profiles = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.network_profiles.clone()))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Perfis de Rede"),
+        Some("Escolha um perfil ao adicionar um download para usar estes headers, credenciais, proxy e limite de velocidade."),
+    );
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("remove", "Remover");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("remove", ResponseAppearance::Destructive);
+    dialog.set_response_appearance("save", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let mut choices = vec!["Novo perfil"];
+    choices.extend(profiles.iter().map(|profile| profile.name.as_str()));
+    let profile_dropdown = gtk4::DropDown::from_strings(&choices);
+
+    let name_entry = Entry::builder().placeholder_text("Nome (ex.: VPN do trabalho)").build();
+    let proxy_entry = Entry::builder()
+        .placeholder_text("Proxy (opcional, ex.: http://proxy.empresa:3128)")
+        .build();
+    let username_entry = Entry::builder().placeholder_text("Usuário (opcional, autenticação HTTP)").build();
+    let password_entry = gtk4::PasswordEntry::builder()
+        .placeholder_text("Senha")
+        .show_peek_icon(true)
+        .build();
+    let limit_spin = gtk4::SpinButton::with_range(0.0, 1_000_000.0, 100.0);
+    limit_spin.set_tooltip_text(Some("KB/s de cada download do perfil (0 = sem limite)"));
+    let limit_box = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+    limit_box.append(&Label::new(Some("Limite (KB/s, 0 = sem)")));
+    limit_box.append(&limit_spin);
+    let headers_view = gtk4::TextView::builder()
+        .top_margin(SPACING_SMALL)
+        .bottom_margin(SPACING_SMALL)
+        .left_margin(SPACING_SMALL)
+        .right_margin(SPACING_SMALL)
+        .monospace(true)
+        .build();
+    let headers_scroll = ScrolledWindow::builder()
+        .child(&headers_view)
+        .min_content_height(80)
+        .min_content_width(360)
+        .build();
+    headers_scroll.add_css_class("card");
+
+    let fields_box = GtkBox::new(Orientation::Vertical, SPACING_SMALL);
+    fields_box.append(&profile_dropdown);
+    fields_box.append(&name_entry);
+    fields_box.append(&proxy_entry);
+    fields_box.append(&username_entry);
+    fields_box.append(&password_entry);
+    fields_box.append(&limit_box);
+    fields_box.append(&Label::builder().label("Headers (um \"Nome: valor\" por linha)").halign(gtk4::Align::Start).build());
+    fields_box.append(&headers_scroll);
+    dialog.set_extra_child(Some(&fields_box));
+
+    // Escolher um perfil preenche os campos; "Novo perfil" limpa
+    let fill = {
+        let profiles = profiles.clone();
+        let name_entry = name_entry.clone();
+        let proxy_entry = proxy_entry.clone();
+        let username_entry = username_entry.clone();
+        let password_entry = password_entry.clone();
+        let limit_spin = limit_spin.clone();
+        let headers_view = headers_view.clone();
+        move |selected: u32| {
+            let profile = (selected as usize)
+                .checked_sub(1)
+                .and_then(|index| profiles.get(index).cloned())
+                .unwrap_or_default();
+            name_entry.set_text(&profile.name);
+            proxy_entry.set_text(&profile.proxy);
+            username_entry.set_text(&profile.username);
+            password_entry.set_text(&profile.password);
+            limit_spin.set_value(profile.speed_limit.map(|limit| limit / 1024).unwrap_or(0) as f64);
+            let headers: Vec<String> = profile.headers.iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+            headers_view.buffer().set_text(&headers.join("\n"));
+        }
+    };
+    profile_dropdown.connect_selected_notify(move |dropdown| fill(dropdown.selected()));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        let editing = (profile_dropdown.selected() as usize)
+            .checked_sub(1)
+            .and_then(|index| profiles.get(index))
+            .map(|profile| profile.name.clone());
+        let profile = match response {
+            "save" => {
+                let buffer = headers_view.buffer();
+                let headers = match parse_header_lines(&buffer.text(&buffer.start_iter(), &buffer.end_iter(), false)) {
+                    Ok(headers) => headers,
+                    Err(e) => {
+                        toast_overlay.add_toast(libadwaita::Toast::new(&e));
+                        return;
+                    }
+                };
+                let name = name_entry.text().trim().to_string();
+                if name.is_empty() {
+                    toast_overlay.add_toast(libadwaita::Toast::new("Dê um nome ao perfil"));
+                    return;
                 }
+                let proxy = proxy_entry.text().trim().to_string();
+                if !proxy.is_empty() && !keepers::http::valid_proxy_url(&proxy) {
+                    toast_overlay.add_toast(libadwaita::Toast::new("Endereço de proxy inválido"));
+                    return;
+                }
+                let limit = limit_spin.value_as_int().max(0) as u64;
+                Some(NetworkProfile {
+                    name,
+                    headers,
+                    username: username_entry.text().trim().to_string(),
+                    password: password_entry.text().to_string(),
+                    proxy,
+                    speed_limit: (limit > 0).then_some(limit * 1024),
+                })
+            }
+            "remove" if editing.is_some() => None,
+            _ => return,
+        };
 
-                main_box.append(&filename_group);
-                main_box.append(&url_group);
-                main_box.append(&size_group);
-                main_box.append(&status_group);
-                main_box.append(&date_group);
-
-                dialog.set_extra_child(Some(&main_box));
-                dialog.present();
+        let Ok(app_state) = state.lock() else {
+            return;
+        };
+        let Ok(mut config) = app_state.config.lock() else {
+            return;
+        };
+        if let Some(profile) = &profile {
+            let duplicate = config
+                .network_profiles
+                .iter()
+                .any(|existing| existing.name == profile.name && Some(&existing.name) != editing.as_ref());
+            if duplicate {
+                toast_overlay.add_toast(libadwaita::Toast::new("Já existe um perfil com esse nome"));
+                return;
             }
         }
+        config.network_profiles.retain(|existing| Some(&existing.name) != editing.as_ref());
+        let message = match profile {
+            Some(profile) => {
+                let message = format!("Perfil \"{}\" salvo", profile.name);
+                config.network_profiles.push(profile);
+                message
+            }
+            None => "Perfil removido".to_string(),
+        };
+        save_config(&config);
+        toast_overlay.add_toast(libadwaita::Toast::new(&message));
     });
 
-    // Handler para botão de pausa/retomar
-    let download_task_clone = download_task.clone();
-    let state_records_clone4 = state_records.clone();
-    let record_url_clone4 = record_url.clone();
-    let status_badge_clone_pause = status_badge.clone();
-    let status_icon_clone_pause = status_icon.clone();
-    let status_label_clone_pause = status_label.clone();
-    let progress_bar_clone_pause = progress_bar.clone();
-
-    pause_btn.connect_clicked(move |btn| {
-        if let Ok(mut task) = download_task_clone.lock() {
-            task.paused = !task.paused;
-            let is_paused = task.paused;
-
-            if is_paused {
-                btn.set_icon_name("media-playback-start-symbolic");
-                btn.set_tooltip_text(Some("Retomar"));
-
-                // Atualiza UI para pausado
-                status_badge_clone_pause.remove_css_class("in-progress");
-                status_badge_clone_pause.remove_css_class("paused");
-                status_badge_clone_pause.add_css_class("paused");
-                status_icon_clone_pause.set_icon_name(Some("media-playback-pause-symbolic"));
-                status_label_clone_pause.set_markup(&markup_status("Pausado"));
-
-                // Atualiza barra de progresso para pausado
-                progress_bar_clone_pause.remove_css_class("in-progress");
-                progress_bar_clone_pause.remove_css_class("paused");
-                progress_bar_clone_pause.add_css_class("paused");
-            } else {
-                btn.set_icon_name("media-playback-pause-symbolic");
-                btn.set_tooltip_text(Some("Pausar"));
+    dialog.present(Some(window));
+}
 
-                // Atualiza UI para em progresso
-                status_badge_clone_pause.remove_css_class("paused");
-                status_badge_clone_pause.remove_css_class("in-progress");
-                status_badge_clone_pause.add_css_class("in-progress");
-                status_icon_clone_pause.set_icon_name(Some("folder-download-symbolic"));
-                status_label_clone_pause.set_markup(&markup_status("Em progresso"));
+// Cria, edita e remove modelos de download. A ordem da lista é a de avaliação:
+// um modelo editado fica no lugar, um novo vai para o fim.
+fn show_templates_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let templates = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.templates.clone()))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Modelos de Download"),
+        Some("Downloads novos cujo endereço casa com um padrão recebem a pasta, as conexões e o limite do primeiro modelo da lista. Use \"*.example.com\" para um site ou uma URL com * para endereços específicos."),
+    );
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("remove", "Remover");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("remove", ResponseAppearance::Destructive);
+    dialog.set_response_appearance("save", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let mut choices = vec!["Novo modelo"];
+    choices.extend(templates.iter().map(|template| template.pattern.as_str()));
+    let template_dropdown = gtk4::DropDown::from_strings(&choices);
+
+    let pattern_entry = Entry::builder().placeholder_text("Padrão (ex.: *.example.com)").build();
+    let directory_entry = Entry::builder()
+        .placeholder_text("Pasta (vazio = pasta de downloads)")
+        .hexpand(true)
+        .build();
+    let directory_button = Button::with_label("Escolher...");
+    let directory_box = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+    directory_box.append(&directory_entry);
+    directory_box.append(&directory_button);
+    let connections_spin = gtk4::SpinButton::with_range(0.0, MAX_CONNECTIONS_LIMIT as f64, 1.0);
+    let connections_box = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+    connections_box.append(&Label::new(Some("Conexões (0 = configuração)")));
+    connections_box.append(&connections_spin);
+    let limit_spin = gtk4::SpinButton::with_range(0.0, 1_000_000.0, 100.0);
+    let limit_box = GtkBox::new(Orientation::Horizontal, SPACING_SMALL);
+    limit_box.append(&Label::new(Some("Limite (KB/s, 0 = sem)")));
+    limit_box.append(&limit_spin);
+
+    let fields_box = GtkBox::new(Orientation::Vertical, SPACING_SMALL);
+    fields_box.append(&template_dropdown);
+    fields_box.append(&pattern_entry);
+    fields_box.append(&directory_box);
+    fields_box.append(&connections_box);
+    fields_box.append(&limit_box);
+    dialog.set_extra_child(Some(&fields_box));
+
+    let window_folder = window.clone();
+    let directory_entry_folder = directory_entry.clone();
+    directory_button.connect_clicked(move |_| {
+        let directory_entry = directory_entry_folder.clone();
+        choose_folder(&window_folder, "Pasta do Modelo", "Selecionar", move |path| {
+            directory_entry.set_text(&path.to_string_lossy());
+        });
+    });
 
-                // Atualiza barra de progresso para em progresso
-                progress_bar_clone_pause.remove_css_class("paused");
-                progress_bar_clone_pause.remove_css_class("in-progress");
-                progress_bar_clone_pause.add_css_class("in-progress");
+    // Escolher um modelo preenche os campos; "Novo modelo" limpa
+    let fill = {
+        let templates = templates.clone();
+        let pattern_entry = pattern_entry.clone();
+        let directory_entry = directory_entry.clone();
+        let connections_spin = connections_spin.clone();
+        let limit_spin = limit_spin.clone();
+        move |selected: u32| {
+            let template = (selected as usize)
+                .checked_sub(1)
+                .and_then(|index| templates.get(index).cloned())
+                .unwrap_or_default();
+            pattern_entry.set_text(&template.pattern);
+            directory_entry.set_text(&template.directory);
+            connections_spin.set_value(template.max_connections.unwrap_or(0) as f64);
+            limit_spin.set_value(template.speed_limit.map(|limit| limit / 1024).unwrap_or(0) as f64);
+        }
+    };
+    template_dropdown.connect_selected_notify(move |dropdown| fill(dropdown.selected()));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        let editing = (template_dropdown.selected() as usize)
+            .checked_sub(1)
+            .filter(|index| *index < templates.len());
+        let template = match response {
+            "save" => {
+                let pattern = pattern_entry.text().trim().to_string();
+                if pattern.is_empty() {
+                    toast_overlay.add_toast(libadwaita::Toast::new("Informe o padrão de URL do modelo"));
+                    return;
+                }
+                let directory = directory_entry.text().trim().to_string();
+                if !directory.is_empty() && !Path::new(&directory).is_absolute() {
+                    toast_overlay.add_toast(libadwaita::Toast::new("Use o caminho completo da pasta"));
+                    return;
+                }
+                let connections = connections_spin.value_as_int().max(0) as usize;
+                let limit = limit_spin.value_as_int().max(0) as u64;
+                Some(DownloadTemplate {
+                    pattern,
+                    directory,
+                    max_connections: (connections > 0).then_some(connections),
+                    speed_limit: (limit > 0).then_some(limit * 1024),
+                })
             }
+            "remove" if editing.is_some() => None,
+            _ => return,
+        };
 
-            // Atualiza was_paused no registro
-            if let Ok(mut records) = state_records_clone4.lock() {
-                if let Some(record) = records.iter_mut().find(|r| r.url == record_url_clone4) {
-                    record.was_paused = is_paused;
-                }
-                save_downloads(&records);
+        let Ok(app_state) = state.lock() else {
+            return;
+        };
+        let Ok(mut config) = app_state.config.lock() else {
+            return;
+        };
+        let message = match (template, editing) {
+            (Some(template), Some(index)) if index < config.templates.len() => {
+                let message = format!("Modelo \"{}\" salvo", template.pattern);
+                config.templates[index] = template;
+                message
             }
-        }
+            (Some(template), _) => {
+                let message = format!("Modelo \"{}\" salvo", template.pattern);
+                config.templates.push(template);
+                message
+            }
+            (None, Some(index)) if index < config.templates.len() => {
+                config.templates.remove(index);
+                "Modelo removido".to_string()
+            }
+            (None, _) => return,
+        };
+        save_config(&config);
+        toast_overlay.add_toast(libadwaita::Toast::new(&message));
     });
 
-    // Handler para botão de cancelar
-    let download_task_clone = download_task.clone();
-    let row_box_clone_cancel = row_box.clone();
-    let state_clone_cancel = state.clone();
-    let record_url_clone2 = record_url.clone();
-    let title_label_clone_cancel = title_label.clone();
-    let progress_bar_clone_cancel = progress_bar.clone();
-    let status_badge_clone_cancel = status_badge.clone();
-    let status_label_clone_cancel = status_label.clone();
-    let speed_label_clone_cancel = speed_label.clone();
-    let eta_label_clone_cancel = eta_label.clone();
-    let pause_btn_clone_cancel = pause_btn.clone();
-    let cancel_btn_clone_cancel = cancel_btn.clone();
-    let delete_btn_clone_cancel = delete_btn.clone();
-    let buttons_box_clone_cancel = buttons_box.clone();
-    let list_box_clone_cancel = list_box.clone();
-    let filename_clone_cancel = filename.clone();
-    let content_stack_clone_cancel = content_stack.clone();
-
-    cancel_btn.connect_clicked(move |_| {
-        // Cancela o download
-        if let Ok(mut task) = download_task_clone.lock() {
-            task.cancelled = true;
-        }
+    dialog.present(Some(window));
+}
 
-        // Marca como cancelado no registro (mantém os metadados)
-        if let Ok(app_state) = state_clone_cancel.lock() {
-            if let Ok(mut records) = app_state.records.lock() {
-                if let Some(record) = records.iter_mut().find(|r| r.url == record_url_clone2) {
-                    record.status = DownloadStatus::Cancelled;
-                    record.date_completed = Some(Utc::now());
-                }
-                save_downloads(&records);
+// Diálogo para escolher entre IPv4 e IPv6 (vale para os próximos downloads)
+fn show_network_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    const OPTIONS: [(IpPreference, &str); 3] = [
+        (IpPreference::Auto, "Automático"),
+        (IpPreference::PreferIpv4, "Preferir IPv4"),
+        (IpPreference::PreferIpv6, "Preferir IPv6"),
+    ];
+
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.ip_preference))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Preferência de Rede"),
+        Some("Alguns espelhos têm IPv6 quebrado. Preferir IPv4 evita esperas longas ao conectar."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let labels: Vec<&str> = OPTIONS.iter().map(|(_, label)| *label).collect();
+    let dropdown = gtk4::DropDown::from_strings(&labels);
+    let selected = OPTIONS.iter().position(|(preference, _)| *preference == current).unwrap_or(0);
+    dropdown.set_selected(selected as u32);
+    dropdown.set_halign(gtk4::Align::Center);
+    dialog.set_extra_child(Some(&dropdown));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let (preference, label) = OPTIONS[(dropdown.selected() as usize).min(OPTIONS.len() - 1)];
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.ip_preference = preference;
+                save_config(&config);
             }
         }
+        toast_overlay.add_toast(libadwaita::Toast::new(&format!("Rede: {}", label)));
+    });
 
-        // Atualiza a UI para mostrar como cancelado (não remove da tela)
-        // Aplica opacidade no container (melhor legibilidade)
-        row_box_clone_cancel.add_css_class("cancelled-download");
+    dialog.present(Some(window));
+}
 
-        // Mantém título normal, sem strikethrough (melhor legibilidade)
-        title_label_clone_cancel.set_markup(&markup_title(&filename_clone_cancel));
+fn show_provenance_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.provenance))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Metadados do Arquivo"),
+        Some("Informações gravadas nos arquivos ao concluir o download."),
+    );
 
-        // Atualiza barra de progresso para cancelado
-        progress_bar_clone_cancel.remove_css_class("in-progress");
-        progress_bar_clone_cancel.remove_css_class("paused");
-        progress_bar_clone_cancel.remove_css_class("failed");
-        progress_bar_clone_cancel.remove_css_class("completed");
-        progress_bar_clone_cancel.add_css_class("cancelled");
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-        // Atualiza badge para cancelado (cinza)
-        status_badge_clone_cancel.remove_css_class("in-progress");
-        status_badge_clone_cancel.remove_css_class("paused");
-        status_badge_clone_cancel.remove_css_class("failed");
-        status_badge_clone_cancel.remove_css_class("completed");
-        status_badge_clone_cancel.add_css_class("cancelled");
+    let origin_check = gtk4::CheckButton::builder()
+        .label("Gravar URL de origem (atributos estendidos)")
+        .tooltip_text("Define user.xdg.origin.url e user.xdg.referrer.url, como fazem os navegadores")
+        .active(current.origin_xattrs)
+        .build();
+    let mtime_check = gtk4::CheckButton::builder()
+        .label("Usar data de modificação do servidor")
+        .tooltip_text("Aplica o cabeçalho Last-Modified como data de modificação do arquivo")
+        .active(current.remote_mtime)
+        .build();
+    let options_box = GtkBox::new(Orientation::Vertical, 6);
+    options_box.append(&origin_check);
+    options_box.append(&mtime_check);
+    dialog.set_extra_child(Some(&options_box));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.provenance.origin_xattrs = origin_check.is_active();
+                config.provenance.remote_mtime = mtime_check.is_active();
+                save_config(&config);
+            }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new("Metadados do arquivo atualizados"));
+    });
 
-        // Atualiza status
-        status_label_clone_cancel.set_markup(&markup_status("Cancelado"));
-        speed_label_clone_cancel.set_markup(&markup_metadata_primary(""));
-        eta_label_clone_cancel.set_markup(&markup_metadata_secondary(""));
+    dialog.present(Some(window));
+}
 
-        // Adiciona botão de reiniciar
-        let restart_btn = Button::builder()
-            .icon_name("view-refresh-symbolic")
-            .tooltip_text("Reiniciar download do zero")
-            .css_classes(vec!["suggested-action"])
-            .build();
+// Aviso de versão nova (ver `keepers::updates`)
+fn show_updates_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.check_updates))
+        .unwrap_or(false);
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Atualizações"),
+        Some(&format!(
+            "Versão instalada: {}. Ao abrir, o Keepers pode consultar a última versão publicada no GitHub \
+             e mostrar um aviso com o link para as novidades. Nada é baixado sozinho.",
+            CURRENT_VERSION
+        )),
+    );
 
-        let record_url_clone_restart = record_url_clone2.clone();
-        let row_box_clone_restart = row_box_clone_cancel.clone();
-        let list_box_clone_restart = list_box_clone_cancel.clone();
-        let state_clone_restart = state_clone_cancel.clone();
-        let filename_clone_restart = filename_clone_cancel.clone();
-        let content_stack_clone_restart = content_stack_clone_cancel.clone();
-
-        restart_btn.connect_clicked(move |_| {
-            // Remove da UI
-            if let Some(parent) = row_box_clone_restart.parent() {
-                if let Some(grandparent) = parent.parent() {
-                    if let Some(lb) = grandparent.downcast_ref::<ListBox>() {
-                        lb.remove(&parent);
-                    }
-                }
-            }
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-            // Remove do state.records e do JSON
-            if let Ok(app_state) = state_clone_restart.lock() {
-                if let Ok(mut records) = app_state.records.lock() {
-                    records.retain(|r| r.url != record_url_clone_restart);
-                    save_downloads(&records);
-                }
-            }
+    let check = gtk4::CheckButton::builder()
+        .label("Procurar versões novas ao abrir")
+        .active(current)
+        .build();
+    dialog.set_extra_child(Some(&check));
 
-            // Remove arquivo parcial se existir (para começar do zero)
-            let download_dir = if let Ok(app_state) = state_clone_restart.lock() {
-                if let Ok(config_guard) = app_state.config.lock() {
-                    get_download_directory(&config_guard)
-                } else {
-                    dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
-                }
-            } else {
-                dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
-            };
-            let temp_path = download_dir.join(format!("{}.part", filename_clone_restart));
-            if temp_path.exists() {
-                let _ = std::fs::remove_file(&temp_path);
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let enabled = check.is_active();
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.check_updates = enabled;
+                save_config(&config);
             }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new(if enabled {
+            "Versões novas são procuradas ao abrir"
+        } else {
+            "Versões novas não são procuradas"
+        }));
+    });
 
-            // Inicia novo download do zero
-            add_download(&list_box_clone_restart, &record_url_clone_restart, &state_clone_restart, &content_stack_clone_restart);
-        });
+    dialog.present(Some(window));
+}
+
+fn show_startup_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.startup))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Inicialização"),
+        Some("Abre o Keepers no login para que a fila e os agendamentos rodem sem abri-lo manualmente."),
+    );
 
-        // Esconde botões de controle e mostra botão de reiniciar e excluir
-        pause_btn_clone_cancel.set_visible(false);
-        cancel_btn_clone_cancel.set_visible(false);
-        delete_btn_clone_cancel.set_visible(true);
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-        // Adiciona restart_btn no container de primary actions
-        if let Some(first_child) = buttons_box_clone_cancel.first_child() {
-            if let Some(primary_box) = first_child.downcast_ref::<GtkBox>() {
-                primary_box.prepend(&restart_btn);
+    let autostart_check = gtk4::CheckButton::builder()
+        .label("Abrir ao iniciar a sessão")
+        .active(current.autostart)
+        .build();
+    let minimized_check = gtk4::CheckButton::builder()
+        .label("Iniciar minimizado")
+        .tooltip_text("Ao abrir no login, mantém a janela oculta (use \"Mostrar Janela\" para exibi-la)")
+        .active(current.start_minimized)
+        .sensitive(current.autostart)
+        .build();
+    autostart_check.bind_property("active", &minimized_check, "sensitive").build();
+    let options_box = GtkBox::new(Orientation::Vertical, 6);
+    options_box.append(&autostart_check);
+    options_box.append(&minimized_check);
+    dialog.set_extra_child(Some(&options_box));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    let app = window.application();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let autostart = autostart_check.is_active();
+        if autostart != current.autostart {
+            if let Err(e) = apply_autostart(app.as_ref(), autostart) {
+                eprintln!("Erro ao configurar início automático: {}", e);
+                toast_overlay.add_toast(libadwaita::Toast::new("Não foi possível configurar o início automático"));
+                return;
+            }
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.startup.autostart = autostart;
+                config.startup.start_minimized = minimized_check.is_active();
+                save_config(&config);
             }
         }
+        toast_overlay.add_toast(libadwaita::Toast::new("Inicialização atualizada"));
     });
 
-    // Handler para botão de excluir
-    let row_box_clone_delete = row_box.clone();
-    let state_clone_delete = state.clone();
-    let record_url_clone3 = record_url.clone();
-    let content_stack_clone_delete = content_stack.clone();
+    dialog.present(Some(window));
+}
 
-    delete_btn.connect_clicked(move |_| {
-        // Remove do state.records e salva no arquivo PRIMEIRO
-        let mut should_remove_ui = true;
-        let mut is_empty = false;
-        if let Ok(app_state) = state_clone_delete.lock() {
-            if let Ok(mut records) = app_state.records.lock() {
-                let before_count = records.len();
-                records.retain(|r| r.url != record_url_clone3);
-                let after_count = records.len();
+// O limite salvo é ligado na hora; o botão do cabeçalho volta a alternar com o ilimitado
+fn show_speed_limit_dialog(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().and_then(|config| config.speed_limit.bytes_per_sec))
+        .unwrap_or(0);
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Limite de Velocidade"),
+        Some("Velocidade máxima somando todos os downloads, em KB/s (0 = sem limite)."),
+    );
 
-                if before_count != after_count {
-                    // Salvou com sucesso, agora remove da UI
-                    save_downloads(&records);
-                    // Verifica se ficou vazio
-                    is_empty = after_count == 0;
-                } else {
-                    // Não encontrou o registro, pode já ter sido removido
-                    should_remove_ui = false;
-                }
-            }
-        }
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-        // Remove da UI
-        if should_remove_ui {
-            if let Some(parent) = row_box_clone_delete.parent() {
-                if let Some(grandparent) = parent.parent() {
-                    if let Some(list_box) = grandparent.downcast_ref::<ListBox>() {
-                        list_box.remove(&parent);
+    let spin = gtk4::SpinButton::with_range(0.0, 10_000_000.0, 64.0);
+    spin.set_value((current / 1024) as f64);
+    spin.set_halign(gtk4::Align::Center);
+    dialog.set_extra_child(Some(&spin));
 
-                        // Se a lista ficou vazia, mostra o estado vazio
-                        if is_empty {
-                            content_stack_clone_delete.set_visible_child_name("empty");
-                        }
-                    }
-                }
+    let state = state.clone();
+    dialog.connect_response(None, glib::clone!(#[weak] window, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let limit = spin.value_as_int().max(0) as u64 * 1024;
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.speed_limit.bytes_per_sec = (limit > 0).then_some(limit);
+                config.speed_limit.enabled = false;
+                save_config(&config);
             }
         }
-    });
+        // Passa pela ação para o botão, o limitador e o aviso ficarem em sincronia
+        window.change_action_state("limit-speed", &(limit > 0).to_variant());
+    }));
+
+    dialog.present(Some(window));
 }
 
-fn start_download(
-    url: &str,
-    filename: &str,
-    tx: async_channel::Sender<DownloadMessage>,
-    download_task: Arc<Mutex<DownloadTask>>,
-    state_records: Arc<Mutex<Vec<DownloadRecord>>>,
-    config: Arc<Mutex<AppConfig>>,
-) {
-    let url = url.to_string();
-    let filename = filename.to_string();
+// Consumo de banda em barras: hoje por hora, últimos 7 e 30 dias por dia
+fn show_usage_statistics(window: &AdwApplicationWindow, state: &Arc<Mutex<AppState>>) {
+    let usage = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.usage.lock().ok().map(|usage| usage.clone()))
+        .unwrap_or_default();
+    let today = Local::now().date_naive();
+    let daily_bars = |days| -> Vec<(String, u64)> {
+        usage
+            .daily_series(today, days)
+            .into_iter()
+            .map(|(day, bytes)| (day.format("%d/%m").to_string(), bytes))
+            .collect()
+    };
+    let hourly_bars: Vec<(String, u64)> = usage
+        .hourly_series(today)
+        .into_iter()
+        .enumerate()
+        .map(|(hour, bytes)| (format!("{}h", hour), bytes))
+        .collect();
+
+    // (nome, título, descrição, barras, rótulo a cada N barras)
+    let views = [
+        ("day", "Dia", "Hoje, por hora", hourly_bars, 3),
+        ("week", "Semana", "Últimos 7 dias", daily_bars(7), 1),
+        ("month", "Mês", "Últimos 30 dias", daily_bars(30), 5),
+    ];
+
+    let stack = libadwaita::ViewStack::new();
+    for (name, title, description, bars, labels_every) in views {
+        let total: u64 = bars.iter().map(|(_, bytes)| bytes).sum();
+        let summary = Label::builder()
+            .label(format!(
+                "{}: {}",
+                description,
+                if total > 0 { format_file_size(total) } else { "nada baixado".to_string() }
+            ))
+            .halign(gtk4::Align::Start)
+            .css_classes(vec!["dim-label"])
+            .build();
+        let chart = gtk4::DrawingArea::builder()
+            .content_height(220)
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        chart.set_draw_func(move |_, cr, width, height| {
+            draw_usage_chart(cr, width, height, &bars, labels_every);
+        });
 
-    std::thread::spawn(move || {
-        // Cria runtime tokio para operações assíncronas
-        let rt = tokio::runtime::Runtime::new().unwrap();
+        let page = GtkBox::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(SPACING_MEDIUM)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        page.append(&summary);
+        page.append(&chart);
+        stack.add_titled(&page, Some(name), title);
+    }
+    stack.add_titled(&host_statistics_page(state), Some("hosts"), "Servidores");
 
-        rt.block_on(async {
-            // Diretório de download usando configuração
-            let download_dir = if let Ok(config_guard) = config.lock() {
-                get_download_directory(&config_guard)
-            } else {
-                dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
-            };
+    let switcher = libadwaita::ViewSwitcher::builder()
+        .stack(&stack)
+        .policy(libadwaita::ViewSwitcherPolicy::Wide)
+        .build();
+    let header = HeaderBar::new();
+    header.set_title_widget(Some(&switcher));
+    let content = GtkBox::new(Orientation::Vertical, 0);
+    content.append(&header);
+    content.append(&stack);
+
+    let statistics = libadwaita::Dialog::builder()
+        .title("Estatísticas de Uso")
+        .content_width(600)
+        .content_height(360)
+        .child(&content)
+        .build();
+    statistics.present(Some(window));
+}
 
-            let file_path = download_dir.join(&filename);
-            let temp_path = download_dir.join(format!("{}.part", filename));
+// Velocidade média, falhas e retomada de cada servidor já usado
+fn host_statistics_page(state: &Arc<Mutex<AppState>>) -> gtk4::Widget {
+    let hosts = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.hosts.lock().ok().map(|hosts| hosts.clone()))
+        .unwrap_or_default();
+    if hosts.hosts.is_empty() {
+        return libadwaita::StatusPage::builder()
+            .icon_name("network-server-symbolic")
+            .title("Nenhum servidor ainda")
+            .description("O histórico aparece depois dos primeiros downloads")
+            .build()
+            .upcast();
+    }
 
-            // Cria client reqwest
-            let client = match reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build() {
-                    Ok(c) => c,
-                    Err(e) => {
-                        let _ = tx.send(DownloadMessage::Error(format!("Erro ao criar client: {}", e))).await;
-                        return;
-                    }
-                };
+    let list = ListBox::builder()
+        .selection_mode(gtk4::SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    for (host, stats) in hosts.ranked() {
+        let speed = stats
+            .average_speed()
+            .filter(|speed| *speed > 0.0)
+            .map(|speed| format!("{}/s", format_file_size(speed as u64)))
+            .unwrap_or_else(|| "—".to_string());
+        let range = match stats.supports_range() {
+            Some(true) => "retomada: sim",
+            Some(false) => "retomada: não",
+            None => "retomada: ?",
+        };
+        let row = libadwaita::ActionRow::builder()
+            .title(host.as_str())
+            .subtitle(format!(
+                "{} downloads · média {} · {:.0}% falhas · {}",
+                stats.attempts(),
+                speed,
+                stats.error_rate() * 100.0,
+                range
+            ))
+            .build();
+        if stats.is_flaky() {
+            let icon = gtk4::Image::from_icon_name("dialog-warning-symbolic");
+            icon.set_tooltip_text(Some("Servidor instável"));
+            icon.add_css_class("warning");
+            row.add_suffix(&icon);
+        }
+        list.append(&row);
+    }
+    ScrolledWindow::builder()
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .vexpand(true)
+        .child(&list)
+        .build()
+        .upcast()
+}
 
-            // Faz requisição HEAD para obter tamanho total e verificar suporte a Range (com retry)
-            let (total_size, supports_range) = match retry_request(|| client.head(&url).send(), MAX_RETRIES, RETRY_DELAY_SECS).await {
-                Ok(resp) => {
-                    let size = resp.headers()
-                        .get(reqwest::header::CONTENT_LENGTH)
-                        .and_then(|v| v.to_str().ok())
-                        .and_then(|v| v.parse::<u64>().ok())
-                        .unwrap_or(0);
-                    
-                    let supports = resp.headers()
-                        .get(reqwest::header::ACCEPT_RANGES)
-                        .and_then(|v| v.to_str().ok())
-                        .map(|v| v == "bytes")
-                        .unwrap_or(false);
-                    
-                    (size, supports)
-                }
-                Err(e) => {
-                    let _ = tx.send(DownloadMessage::Error(format!("Erro ao obter info após {} tentativas: {}", MAX_RETRIES, e))).await;
-                    return;
-                }
-            };
+// Barras proporcionais ao maior valor, com o máximo no topo como escala
+fn draw_usage_chart(cr: &gtk4::cairo::Context, width: i32, height: i32, bars: &[(String, u64)], labels_every: usize) {
+    const TOP: f64 = 20.0;
+    const BOTTOM: f64 = 18.0;
+    let (width, height) = (width as f64, height as f64);
+    let chart_height = (height - TOP - BOTTOM).max(1.0);
+    let max = bars.iter().map(|(_, bytes)| *bytes).max().unwrap_or(0).max(1);
+    let slot = width / bars.len().max(1) as f64;
+    let (red, green, blue) = hex_rgb(COLOR_INFO);
+
+    cr.set_font_size(10.0);
+    for (i, (label, bytes)) in bars.iter().enumerate() {
+        let x = i as f64 * slot;
+        let bar_height = chart_height * *bytes as f64 / max as f64;
+        cr.set_source_rgb(red, green, blue);
+        cr.rectangle(x + slot * 0.15, TOP + chart_height - bar_height, slot * 0.7, bar_height);
+        let _ = cr.fill();
+
+        if i % labels_every.max(1) == 0 {
+            cr.set_source_rgba(1.0, 1.0, 1.0, OPACITY_DIM_TEXT as f64);
+            cr.move_to(x + slot * 0.15, height - 4.0);
+            let _ = cr.show_text(label);
+        }
+    }
 
-            // Atualiza total_bytes no registro quando disponível
-            if total_size > 0 {
-                if let Ok(mut records) = state_records.lock() {
-                    if let Some(record) = records.iter_mut().find(|r| r.url == url) {
-                        record.total_bytes = total_size;
-                        save_downloads(&records);
-                    }
-                }
+    if bars.iter().any(|(_, bytes)| *bytes > 0) {
+        cr.set_source_rgba(1.0, 1.0, 1.0, OPACITY_DIM_TEXT as f64);
+        cr.move_to(0.0, 12.0);
+        let _ = cr.show_text(&format!("máx. {}", format_file_size(max)));
+    }
+}
+
+// "#3b82f6" -> componentes de 0 a 1 para o cairo
+fn hex_rgb(color: &str) -> (f64, f64, f64) {
+    let channel = |i: usize| {
+        color
+            .get(i..i + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .unwrap_or(0) as f64
+            / 255.0
+    };
+    (channel(1), channel(3), channel(5))
+}
+
+fn show_s3_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.s3.clone()))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Armazenamento S3"),
+        Some("Credenciais usadas nas URLs s3://bucket/chave. Campos vazios usam AWS_ACCESS_KEY_ID, \
+              AWS_SECRET_ACCESS_KEY e AWS_REGION do ambiente."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let access_key_entry = Entry::builder()
+        .placeholder_text("Access key ID")
+        .text(current.access_key_id.as_str())
+        .build();
+    let secret_entry = gtk4::PasswordEntry::builder()
+        .placeholder_text("Secret access key")
+        .show_peek_icon(true)
+        .text(current.secret_access_key.as_str())
+        .build();
+    let region_entry = Entry::builder()
+        .placeholder_text(format!("Região (padrão {})", keepers::s3::DEFAULT_REGION))
+        .text(current.region.as_str())
+        .build();
+    let endpoint_entry = Entry::builder()
+        .placeholder_text("Endpoint compatível (opcional, ex.: http://localhost:9000)")
+        .text(current.endpoint.clone().unwrap_or_default())
+        .build();
+    let fields_box = GtkBox::new(Orientation::Vertical, 6);
+    fields_box.append(&access_key_entry);
+    fields_box.append(&secret_entry);
+    fields_box.append(&region_entry);
+    fields_box.append(&endpoint_entry);
+    dialog.set_extra_child(Some(&fields_box));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let endpoint = endpoint_entry.text().trim().to_string();
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.s3.access_key_id = access_key_entry.text().trim().to_string();
+                config.s3.secret_access_key = secret_entry.text().trim().to_string();
+                config.s3.region = region_entry.text().trim().to_string();
+                config.s3.endpoint = (!endpoint.is_empty()).then_some(endpoint);
+                save_config(&config);
             }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new("Credenciais S3 atualizadas"));
+    });
 
-            // Verifica se já existe arquivo .part (download pausado/interrompido)
-            let is_resume = temp_path.exists();
+    dialog.present(Some(window));
+}
 
-            // Se não suporta Range, tamanho desconhecido, arquivo pequeno ou é resume, usa download sequencial
-            // Motivo: download sequencial tem suporte completo a resume, download paralelo não
-            if !supports_range || total_size == 0 || total_size < 1024 * 1024 || is_resume {
-                // Download sequencial (código original)
-                download_sequential(&client, &url, &temp_path, &file_path, total_size, &tx, &download_task, false).await;
-                return;
+fn show_ipfs_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.ipfs.clone()))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Gateways IPFS"),
+        Some("Um gateway por linha. O primeiro download de cada CID testa todos ao mesmo tempo \
+              e segue com o que responder primeiro; os demais ficam de reserva."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("defaults", "Restaurar Padrão");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let gateways_view = gtk4::TextView::builder()
+        .top_margin(SPACING_SMALL)
+        .bottom_margin(SPACING_SMALL)
+        .left_margin(SPACING_SMALL)
+        .right_margin(SPACING_SMALL)
+        .build();
+    gateways_view.buffer().set_text(&current.gateways.join("\n"));
+    let gateways_scroll = ScrolledWindow::builder()
+        .child(&gateways_view)
+        .min_content_height(100)
+        .min_content_width(360)
+        .build();
+    gateways_scroll.add_css_class("card");
+    dialog.set_extra_child(Some(&gateways_scroll));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        let gateways: Vec<String> = match response {
+            "save" => {
+                let buffer = gateways_view.buffer();
+                buffer
+                    .text(&buffer.start_iter(), &buffer.end_iter(), false)
+                    .lines()
+                    .map(|line| line.trim().trim_end_matches('/').to_string())
+                    .filter(|line| line.starts_with("http://") || line.starts_with("https://"))
+                    .collect()
+            }
+            "defaults" => DEFAULT_GATEWAYS.iter().map(|gateway| gateway.to_string()).collect(),
+            _ => return,
+        };
+        if gateways.is_empty() {
+            toast_overlay.add_toast(libadwaita::Toast::new("Informe ao menos um gateway http(s)"));
+            return;
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.ipfs.gateways = gateways;
+                save_config(&config);
             }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new("Gateways IPFS atualizados"));
+    });
 
-            // Download paralelo em chunks
-            // Calcula número ótimo de chunks baseado no tamanho do arquivo
-            // Arquivos grandes podem se beneficiar de mais chunks
-            let num_chunks = calculate_optimal_chunks(total_size);
-            let chunk_size = total_size / num_chunks;
-            let last_chunk_size = total_size - (chunk_size * (num_chunks - 1));
+    dialog.present(Some(window));
+}
 
-            // Cria arquivo vazio
-            let file_handle = match tokio::fs::File::create(&temp_path).await {
-                Ok(f) => f,
-                Err(e) => {
-                    let _ = tx.send(DownloadMessage::Error(format!("Erro ao criar arquivo: {}", e))).await;
-                    return;
-                }
-            };
+fn show_resolvers_dialog(window: &AdwApplicationWindow, toast_overlay: &libadwaita::ToastOverlay) {
+    let names: Vec<String> = installed_resolvers().iter().map(|resolver| resolver.name().to_string()).collect();
+    show_plugin_folder_dialog(
+        window,
+        toast_overlay,
+        "Resolvedores de Links",
+        "Transformam links de serviços de arquivos em endereços diretos. Para instalar um \
+         plugin, coloque na pasta de resolvedores um .json com nome, padrão (regex) e o \
+         comando, que recebe a URL e responde {\"urls\": [...], \"headers\": {...}}.",
+        &names,
+        get_resolvers_dir(),
+    );
+}
 
-            // Pre-aloca espaço no arquivo
-            if let Err(e) = file_handle.set_len(total_size).await {
-                let _ = tx.send(DownloadMessage::Error(format!("Erro ao pre-alocar arquivo: {}", e))).await;
-                return;
-            }
-            drop(file_handle);
+fn show_scripts_dialog(window: &AdwApplicationWindow, toast_overlay: &libadwaita::ToastOverlay) {
+    let names: Vec<String> = installed_hooks().names().into_iter().map(String::from).collect();
+    show_plugin_folder_dialog(
+        window,
+        toast_overlay,
+        "Scripts",
+        "Arquivos .rhai na pasta de scripts podem definir url_added(url), before_request(url) \
+         e completed(path, url) para reescrever URLs, enviar headers ou tratar o arquivo baixado. \
+         As mudanças valem para os próximos downloads.",
+        &names,
+        get_scripts_dir(),
+    );
+}
 
-            // Abre arquivo para escrita paralela
-            let file = match tokio::fs::OpenOptions::new()
-                .write(true)
-                .open(&temp_path)
-                .await
-            {
-                Ok(f) => Arc::new(AsyncMutex::new(f)),
-                Err(e) => {
-                    let _ = tx.send(DownloadMessage::Error(format!("Erro ao abrir arquivo: {}", e))).await;
-                    return;
-                }
-            };
+// Lista do que está instalado numa pasta de extensões, com atalho para abri-la
+fn show_plugin_folder_dialog(
+    window: &AdwApplicationWindow,
+    toast_overlay: &libadwaita::ToastOverlay,
+    title: &str,
+    body: &str,
+    names: &[String],
+    dir: PathBuf,
+) {
+    let dialog = libadwaita::AlertDialog::new(Some(title), Some(body));
+
+    dialog.add_response("folder", "Abrir Pasta");
+    dialog.add_response("close", "Fechar");
+    dialog.set_default_response(Some("close"));
+    dialog.set_close_response("close");
+
+    if !names.is_empty() {
+        let list = ListBox::new();
+        list.add_css_class("boxed-list");
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        for name in names {
+            let row = libadwaita::ActionRow::builder().title(name.as_str()).build();
+            list.append(&row);
+        }
+        dialog.set_extra_child(Some(&list));
+    }
 
-            // Progresso compartilhado entre chunks
-            let progress = Arc::new(AsyncMutex::new(vec![0u64; num_chunks as usize]));
-            let last_update = Arc::new(AsyncMutex::new(Instant::now()));
-            let last_downloaded = Arc::new(AsyncMutex::new(0u64));
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("folder"), move |_, _| {
+        let opened = std::fs::create_dir_all(&dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| platform::open_path(&dir).map_err(|e| e.to_string()));
+        if let Err(e) = opened {
+            eprintln!("Erro ao abrir pasta {}: {}", dir.display(), e);
+            toast_overlay.add_toast(libadwaita::Toast::new("Não foi possível abrir a pasta"));
+        }
+    });
 
-            // Baixa cada chunk em paralelo
-            let mut handles = Vec::new();
+    dialog.present(Some(window));
+}
 
-            for chunk_id in 0..num_chunks {
-                let start = chunk_id * chunk_size;
-                let end = if chunk_id == num_chunks - 1 {
-                    start + last_chunk_size - 1
-                } else {
-                    start + chunk_size - 1
-                };
+// Cor de cada estado (paleta normal, paleta acessível): azul trabalhando,
+// âmbar esperando, verde concluído, vermelho falhou e cinza cancelado
+fn state_colors(state: DownloadState) -> (&'static str, &'static str) {
+    match state {
+        DownloadState::Connecting | DownloadState::Downloading | DownloadState::Verifying | DownloadState::Completing => {
+            (COLOR_INFO, COLOR_SAFE_INFO)
+        }
+        DownloadState::Queued | DownloadState::Paused | DownloadState::Stalled | DownloadState::Retrying => {
+            (COLOR_WARNING, COLOR_SAFE_WARNING)
+        }
+        DownloadState::Done => (COLOR_SUCCESS, COLOR_SAFE_SUCCESS),
+        DownloadState::Failed => (COLOR_ERROR, COLOR_SAFE_ERROR),
+        DownloadState::Cancelled => (COLOR_NEUTRAL, COLOR_NEUTRAL),
+    }
+}
 
-                let url_clone = url.clone();
-                let client_clone = client.clone();
-                let file_clone = file.clone();
-                let progress_clone = progress.clone();
-                let download_task_clone = download_task.clone();
-                let tx_clone = tx.clone();
-                let last_update_clone = last_update.clone();
-                let last_downloaded_clone = last_downloaded.clone();
-
-                let handle = tokio::spawn(async move {
-                    download_chunk(
-                        &client_clone,
-                        &url_clone,
-                        start,
-                        end,
-                        chunk_id as usize,
-                        file_clone,
-                        progress_clone,
-                        total_size,
-                        &download_task_clone,
-                        &tx_clone,
-                        last_update_clone,
-                        last_downloaded_clone,
-                    ).await
-                });
+// Barra de progresso e badge de cada estado (uma classe CSS por `DownloadState`)
+fn status_css() -> String {
+    let mut css = String::new();
+    for state in DownloadState::ALL {
+        let (class, (color, _)) = (state.css_class(), state_colors(state));
+        // Texto escuro sobre o âmbar
+        let text = if color == COLOR_WARNING { "rgba(0, 0, 0, 0.9)" } else { "white" };
+        css.push_str(&format!(
+            ".download-progress.{class} trough progress {{ background: {color}; min-height: 20px; border-radius: 6px; }}\n\
+             .download-progress.{class} text {{ color: {text}; }}\n\
+             .status-badge.{class} {{ color: {color}; }}\n"
+        ));
+    }
+    css
+}
 
-                handles.push(handle);
+// Status que não dependem só da cor: paleta Okabe-Ito e um padrão por estado na
+// barra (listras nas esperas, hachura na falha, pontilhado no cancelado). Só vale
+// com a classe `accessible-colors` na janela.
+fn accessible_status_css() -> String {
+    let mut css = String::new();
+    for state in DownloadState::ALL {
+        let (class, (_, color)) = (state.css_class(), state_colors(state));
+        let pattern = match state {
+            DownloadState::Queued | DownloadState::Paused | DownloadState::Stalled | DownloadState::Retrying => {
+                "repeating-linear-gradient(45deg, alpha(black, 0.25) 0px, alpha(black, 0.25) 6px, transparent 6px, transparent 12px)"
             }
-
-            // Aguarda todos os chunks terminarem
-            let mut all_success = true;
-            for handle in handles {
-                match handle.await {
-                    Ok(Ok(_)) => {}
-                    Ok(Err(e)) => {
-                        eprintln!("Erro no chunk: {}", e);
-                        all_success = false;
-                    }
-                    Err(e) => {
-                        eprintln!("Erro ao aguardar chunk: {:?}", e);
-                        all_success = false;
-                    }
-                }
+            DownloadState::Failed => {
+                "repeating-linear-gradient(45deg, alpha(white, 0.35) 0px, alpha(white, 0.35) 2px, transparent 2px, transparent 6px), \
+                 repeating-linear-gradient(-45deg, alpha(white, 0.35) 0px, alpha(white, 0.35) 2px, transparent 2px, transparent 6px)"
             }
-
-            drop(file);
-
-            // Verifica cancelamento antes de verificar sucesso
-            if let Ok(task) = download_task.lock() {
-                if task.cancelled {
-                    let _ = std::fs::remove_file(&temp_path);
-                    let _ = tx.send(DownloadMessage::Error("Cancelado".to_string())).await;
-                    return;
-                }
+            DownloadState::Cancelled => {
+                "repeating-linear-gradient(90deg, alpha(white, 0.35) 0px, alpha(white, 0.35) 3px, transparent 3px, transparent 9px)"
             }
+            _ => "none",
+        };
+        css.push_str(&format!(
+            "window.accessible-colors .download-progress.{class} trough progress {{ background-color: {color}; background-image: {pattern}; }}\n\
+             window.accessible-colors .status-badge.{class} {{ color: {color}; font-weight: 700; }}\n"
+        ));
+    }
+    for (badge, color) in [("active", COLOR_SAFE_INFO), ("paused", COLOR_SAFE_WARNING), ("error", COLOR_SAFE_ERROR)] {
+        css.push_str(&format!(
+            "window.accessible-colors .badge-container.{badge} {{ background-color: alpha({color}, 0.15); }}\n\
+             window.accessible-colors .badge-container.{badge} .badge-label {{ color: {color}; }}\n"
+        ));
+    }
+    css
+}
 
-            if !all_success {
-                let _ = tx.send(DownloadMessage::Error("Erro ao baixar chunks".to_string())).await;
-                return;
-            }
+fn apply_appearance(window: &AdwApplicationWindow, list_box: &ListBox, appearance: &AppearanceSettings) {
+    for (class, enabled) in [("accessible-colors", appearance.accessible_colors), ("dense-list", appearance.dense_list)] {
+        if enabled {
+            window.add_css_class(class);
+        } else {
+            window.remove_css_class(class);
+        }
+    }
+    for row in download_rows(list_box) {
+        row.set_dense(appearance.dense_list);
+    }
+}
 
-            // Download completo - renomeia arquivo
-            if let Err(e) = std::fs::rename(&temp_path, &file_path) {
-                let _ = tx.send(DownloadMessage::Error(format!("Erro ao finalizar: {}", e))).await;
-                return;
-            }
+fn show_appearance_dialog(
+    window: &AdwApplicationWindow,
+    list_box: &ListBox,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.appearance))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Aparência"),
+        Some("A paleta acessível troca as cores dos status por tons distinguíveis com daltonismo \
+              e marca as barras com padrões (listras, hachura), sem depender só da cor. \
+              A lista compacta encolhe os cards para caber mais downloads na tela."),
+    );
 
-            // Salva o caminho do arquivo no download task
-            if let Ok(mut task) = download_task.lock() {
-                task.file_path = Some(file_path.clone());
-            }
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-            let _ = tx.send(DownloadMessage::Complete).await;
-        });
+    let accessible_check = gtk4::CheckButton::builder()
+        .label("Cores acessíveis (daltonismo e alto contraste)")
+        .active(current.accessible_colors)
+        .build();
+    let dense_check = gtk4::CheckButton::builder()
+        .label("Lista compacta (sem tamanho, velocidade e data)")
+        .active(current.dense_list)
+        .build();
+    let options_box = GtkBox::new(Orientation::Vertical, SPACING_SMALL);
+    options_box.append(&accessible_check);
+    options_box.append(&dense_check);
+    dialog.set_extra_child(Some(&options_box));
+
+    let window_response = window.clone();
+    let list_box = list_box.clone();
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let appearance = AppearanceSettings {
+            accessible_colors: accessible_check.is_active(),
+            dense_list: dense_check.is_active(),
+        };
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.appearance = appearance;
+                save_config(&config);
+            }
+        }
+        apply_appearance(&window_response, &list_box, &appearance);
+        toast_overlay.add_toast(libadwaita::Toast::new("Aparência atualizada"));
     });
-}
 
-async fn download_chunk(
-    client: &reqwest::Client,
-    url: &str,
-    start: u64,
-    end: u64,
-    chunk_id: usize,
-    file: Arc<AsyncMutex<tokio::fs::File>>,
-    progress: Arc<AsyncMutex<Vec<u64>>>,
-    total_size: u64,
-    download_task: &Arc<Mutex<DownloadTask>>,
-    tx: &async_channel::Sender<DownloadMessage>,
-    last_update: Arc<AsyncMutex<Instant>>,
-    last_downloaded: Arc<AsyncMutex<u64>>,
-) -> Result<(), String> {
-    let range_header = format!("bytes={}-{}", start, end);
-    
-    // Tenta fazer requisição com retry automático
-    let response = retry_request(|| {
-        client
-            .get(url)
-            .header(reqwest::header::RANGE, &range_header)
-            .send()
-    }, MAX_RETRIES, RETRY_DELAY_SECS)
-    .await
-    .map_err(|e| format!("Erro na requisição após {} tentativas: {}", MAX_RETRIES, e))?;
+    dialog.present(Some(window));
+}
 
-    if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
-        return Err(format!("Status HTTP: {}", response.status()));
-    }
+fn show_organize_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.organize))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Organização"),
+        Some("Novos downloads vão para uma subpasta com o nome do site (ex.: github.com). \
+              Downloads de uma pasta remota mantêm a estrutura dela."),
+    );
 
-    let mut stream = response.bytes_stream();
-    let mut current_pos = start;
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-    while let Some(chunk_result) = stream.next().await {
-        // Verifica cancelamento/pausa
-        loop {
-            let (cancelled, paused) = {
-                if let Ok(task) = download_task.lock() {
-                    (task.cancelled, task.paused)
-                } else {
-                    (false, false)
-                }
-            };
+    let domain_check = gtk4::CheckButton::builder()
+        .label("Separar por site de origem")
+        .active(current.by_domain)
+        .build();
+    dialog.set_extra_child(Some(&domain_check));
 
-            if cancelled {
-                return Err("Cancelado".to_string());
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.organize.by_domain = domain_check.is_active();
+                save_config(&config);
             }
+        }
+        toast_overlay.add_toast(libadwaita::Toast::new("Organização atualizada"));
+    });
 
-            if !paused {
-                break;
-            }
+    dialog.present(Some(window));
+}
 
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-        }
+fn show_battery_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.battery))
+        .unwrap_or_default();
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Bateria"),
+        Some("Fora da tomada, pausa os downloads abaixo desta carga (%) e retoma ao ligar na tomada."),
+    );
 
-        let chunk = chunk_result.map_err(|e| format!("Erro ao baixar chunk: {}", e))?;
-        let chunk_len = chunk.len() as u64;
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
 
-        // Escreve no arquivo na posição correta
-        {
-            let mut file_guard = file.lock().await;
-            use tokio::io::AsyncSeekExt;
-            use tokio::io::AsyncWriteExt;
-            file_guard.seek(std::io::SeekFrom::Start(current_pos)).await
-                .map_err(|e| format!("Erro ao posicionar arquivo: {}", e))?;
-            file_guard.write_all(&chunk).await
-                .map_err(|e| format!("Erro ao escrever arquivo: {}", e))?;
+    let enabled_check = gtk4::CheckButton::builder()
+        .label("Pausar com bateria fraca")
+        .active(current.pause_on_low_battery)
+        .build();
+    let spin = gtk4::SpinButton::with_range(5.0, 95.0, 5.0);
+    spin.set_value(current.threshold_percent as f64);
+    spin.set_halign(gtk4::Align::Center);
+    enabled_check.bind_property("active", &spin, "sensitive").sync_create().build();
+    let options_box = GtkBox::new(Orientation::Vertical, 6);
+    options_box.append(&enabled_check);
+    options_box.append(&spin);
+    dialog.set_extra_child(Some(&options_box));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.battery.pause_on_low_battery = enabled_check.is_active();
+                config.battery.threshold_percent = spin.value_as_int().clamp(5, 95) as u8;
+                save_config(&config);
+            }
         }
+        toast_overlay.add_toast(libadwaita::Toast::new("Configuração de bateria atualizada"));
+    });
 
-        current_pos += chunk_len;
+    dialog.present(Some(window));
+}
 
-        // Atualiza progresso deste chunk
-        {
-            let mut progress_guard = progress.lock().await;
-            progress_guard[chunk_id] = current_pos - start;
+fn show_quota_dialog(
+    window: &AdwApplicationWindow,
+    state: &Arc<Mutex<AppState>>,
+    toast_overlay: &libadwaita::ToastOverlay,
+) {
+    let current = state
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.config.lock().ok().map(|config| config.quota.clone()))
+        .unwrap_or_default();
+    let this_month = month_key(Local::now());
+
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Cota de Dados"),
+        Some("Limite de transferência por mês, em GB (0 = sem cota). Ao esgotar, novos downloads começam pausados."),
+    );
+
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let spin = gtk4::SpinButton::with_range(0.0, 100_000.0, 1.0);
+    spin.set_digits(1);
+    spin.set_value(current.monthly_limit.map(|limit| limit as f64 / GIB).unwrap_or(0.0));
+    spin.set_halign(gtk4::Align::Center);
+    let override_check = gtk4::CheckButton::builder()
+        .label("Ignorar a cota até o fim deste mês")
+        .active(current.override_month.as_deref() == Some(this_month.as_str()))
+        .build();
+    let options_box = GtkBox::new(Orientation::Vertical, 6);
+    options_box.append(&spin);
+    options_box.append(&override_check);
+    dialog.set_extra_child(Some(&options_box));
+
+    let state = state.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response != "save" {
+            return;
+        }
+        let limit = (spin.value() * GIB) as u64;
+        if let Ok(app_state) = state.lock() {
+            if let Ok(mut config) = app_state.config.lock() {
+                config.quota.monthly_limit = (limit > 0).then_some(limit);
+                config.quota.override_month = override_check.is_active().then(|| this_month.clone());
+                save_config(&config);
+            }
         }
+        let message = if limit > 0 {
+            format!("Cota mensal: {}", format_file_size(limit))
+        } else {
+            "Cota mensal desativada".to_string()
+        };
+        toast_overlay.add_toast(libadwaita::Toast::new(&message));
+    });
 
-        // Atualiza progresso total a cada 200ms
-        {
-            let mut last_update_guard = last_update.lock().await;
-            if last_update_guard.elapsed().as_millis() >= 200 {
-                let progress_guard = progress.lock().await;
-                let total_downloaded: u64 = progress_guard.iter().sum();
-                let progress_ratio = if total_size > 0 {
-                    total_downloaded as f64 / total_size as f64
-                } else {
-                    0.0
-                };
+    dialog.present(Some(window));
+}
 
-                let mut last_downloaded_guard = last_downloaded.lock().await;
-                let elapsed_secs = last_update_guard.elapsed().as_secs_f64();
-                let speed_bytes = if elapsed_secs > 0.0 {
-                    (total_downloaded as f64 - *last_downloaded_guard as f64) / elapsed_secs
-                } else {
-                    0.0
-                };
-                let speed_text = format_speed(speed_bytes);
+// Fora do Flatpak a entrada depende do sistema (ver `platform::set_autostart`); dentro
+// dele ~/.config/autostart não é visível para a sessão: pede ao portal Background
+fn apply_autostart(app: Option<&gtk4::Application>, enabled: bool) -> Result<(), String> {
+    // O modo portátil não deixa nada na máquina (e o pendrive pode não estar lá no próximo login)
+    if enabled && keepers::storage::portable_dir().is_some() {
+        return Err("indisponível no modo portátil".to_string());
+    }
+    if !platform::in_flatpak() {
+        let exec = executable_path().ok_or("executável não encontrado")?;
+        return platform::set_autostart(APP_ID, &exec, enabled);
+    }
 
-                let eta_text = if total_size > 0 && speed_bytes > 0.0 && total_downloaded < total_size {
-                    let remaining_bytes = total_size - total_downloaded;
-                    let eta_seconds = remaining_bytes as f64 / speed_bytes;
-                    format_eta(eta_seconds)
-                } else {
-                    String::new()
-                };
+    let connection = app
+        .and_then(|app| app.dbus_connection())
+        .ok_or("sessão D-Bus indisponível")?;
+    let options = glib::VariantDict::new(None);
+    options.insert("reason", "Baixar a fila e os agendamentos ao iniciar a sessão");
+    options.insert("autostart", enabled);
+    options.insert("commandline", vec!["keepers".to_string(), AUTOSTART_ARG.to_string()]);
+    let parameters = glib::Variant::tuple_from_iter(["".to_variant(), options.end()]);
+    connection.call(
+        Some("org.freedesktop.portal.Desktop"),
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Background",
+        "RequestBackground",
+        Some(&parameters),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+        |result| {
+            if let Err(e) = result {
+                eprintln!("Erro ao pedir execução em segundo plano ao portal: {}", e);
+            }
+        },
+    );
+    Ok(())
+}
 
-                let status = format!("{}/{}", format_bytes(total_downloaded), format_bytes(total_size));
-                let _ = tx.send(DownloadMessage::Progress(progress_ratio, status, speed_text, eta_text, true, speed_bytes as u64)).await;
+// Para onde mandar um arquivo concluído (ver `share_file`)
+#[derive(Debug, Clone, Copy)]
+enum ShareTarget {
+    Email,    // Portal Email: abre o cliente de e-mail com o arquivo anexado
+    OtherApp, // Portal OpenURI com "ask": o sistema lista os apps que aceitam o arquivo
+}
 
-                *last_update_guard = Instant::now();
-                *last_downloaded_guard = total_downloaded;
+fn show_share_dialog(parent: &impl IsA<gtk4::Widget>, path: PathBuf) {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let dialog = libadwaita::AlertDialog::new(Some("Compartilhar"), Some(&name));
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("email", "Enviar por E-mail");
+    dialog.add_response("app", "Outro Aplicativo...");
+    dialog.set_response_appearance("app", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("app"));
+    dialog.set_close_response("cancel");
+
+    let parent_response = parent.as_ref().clone();
+    dialog.connect_response(None, move |_, response| {
+        let target = match response {
+            "email" => ShareTarget::Email,
+            "app" => ShareTarget::OtherApp,
+            _ => return,
+        };
+        let parent = parent_response.clone();
+        share_file(&path, target, move |result| {
+            if let Err(e) = result {
+                eprintln!("Erro ao compartilhar arquivo: {}", e);
+                show_toast(&parent, "Não foi possível compartilhar o arquivo");
             }
-        }
-    }
+        });
+    });
 
-    Ok(())
+    dialog.present(Some(parent));
 }
 
-async fn download_sequential(
-    client: &reqwest::Client,
-    url: &str,
-    temp_path: &PathBuf,
-    file_path: &PathBuf,
-    total_size: u64,
-    tx: &async_channel::Sender<DownloadMessage>,
-    download_task: &Arc<Mutex<DownloadTask>>,
-    parallel_chunks: bool,
-) {
-    // Verifica se existe arquivo parcial para resume
-    let mut downloaded = if temp_path.exists() {
-        std::fs::metadata(temp_path).map(|m| m.len()).unwrap_or(0)
-    } else {
-        0
+// O arquivo vai para o portal como descritor aberto, então funciona também
+// dentro do Flatpak, sem acesso do outro app à pasta de downloads
+fn share_file(path: &Path, target: ShareTarget, done: impl FnOnce(Result<(), String>) + 'static) {
+    let Some(connection) = gio::Application::default().and_then(|app| app.dbus_connection()) else {
+        done(Err("sessão D-Bus indisponível".to_string()));
+        return;
     };
-
-    // Abre ou cria arquivo para escrita
-    let mut file = match if downloaded > 0 {
-        OpenOptions::new().append(true).open(temp_path)
-    } else {
-        File::create(temp_path)
-    } {
-        Ok(f) => f,
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
         Err(e) => {
-            let _ = tx.send(DownloadMessage::Error(format!("Erro ao criar arquivo: {}", e))).await;
+            done(Err(e.to_string()));
             return;
         }
     };
-
-    // Faz requisição com Range header para resume (com retry)
-    let downloaded_bytes = downloaded;
-    let response = match retry_request(|| {
-        let mut req = client.get(url);
-        if downloaded_bytes > 0 {
-            req = req.header(reqwest::header::RANGE, format!("bytes={}-", downloaded_bytes));
+    let fd_list = gio::UnixFDList::from_array([file]);
+    let fd = glib::variant::Handle(0); // Índice na lista de descritores
+    let options = glib::VariantDict::new(None);
+    let (interface, method, parameters) = match target {
+        ShareTarget::Email => {
+            options.insert("attachment_fds", vec![fd]);
+            ("org.freedesktop.portal.Email", "ComposeEmail", vec!["".to_variant(), options.end()])
         }
-        req.send()
-    }, MAX_RETRIES, RETRY_DELAY_SECS).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            let _ = tx.send(DownloadMessage::Error(format!("Erro na requisição após {} tentativas: {}", MAX_RETRIES, e))).await;
-            return;
+        ShareTarget::OtherApp => {
+            options.insert("ask", true);
+            ("org.freedesktop.portal.OpenURI", "OpenFile", vec!["".to_variant(), fd.to_variant(), options.end()])
         }
     };
+    connection.call_with_unix_fd_list(
+        Some("org.freedesktop.portal.Desktop"),
+        "/org/freedesktop/portal/desktop",
+        interface,
+        method,
+        Some(&glib::Variant::tuple_from_iter(parameters)),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        Some(&fd_list),
+        None::<&gio::Cancellable>,
+        move |result| done(result.map(|_| ()).map_err(|e| e.to_string())),
+    );
+}
 
-    if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
-        let _ = tx.send(DownloadMessage::Error(format!("Status HTTP: {}", response.status()))).await;
-        return;
-    }
+// Diálogo com os detalhes de um download
+// `edit_url` recebe o novo endereço escolhido em "Editar URL" (None = download
+// ativo ou concluído, sem o botão)
+fn show_download_info(parent: &impl IsA<gtk4::Widget>, record: &DownloadRecord, edit_url: Option<Box<dyn Fn(String)>>) {
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Informações do Download"),
+        None,
+    );
+
+    dialog.add_response("close", "Fechar");
+    dialog.set_response_appearance("close", libadwaita::ResponseAppearance::Default);
+    dialog.set_default_response(Some("close"));
+    dialog.set_close_response("close");
 
-    // Stream de download
-    let mut stream = response.bytes_stream();
-    let mut last_update = Instant::now();
-    let mut last_downloaded = downloaded;
+    // Container principal
+    let main_box = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(16)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(16)
+        .margin_end(16)
+        .build();
 
-    // Envia progresso inicial se estiver retomando
-    if downloaded > 0 && total_size > 0 {
-        let progress = downloaded as f64 / total_size as f64;
-        let status = format!("{}/{}", format_bytes(downloaded), format_bytes(total_size));
-        let _ = tx.send(DownloadMessage::Progress(progress, status, String::new(), String::new(), parallel_chunks, 0)).await;
+    // Data de conclusão (se completado)
+    if let Some(completed_date) = record.date_completed {
+        let value = format!("{}", completed_date.format("%d/%m/%Y às %H:%M:%S"));
+        main_box.append(&info_group("Data de Conclusão", &info_value(&value)));
     }
 
-    while let Some(chunk_result) = stream.next().await {
-        // Verifica se foi cancelado ou está pausado
-        loop {
-            let (cancelled, paused) = {
-                if let Ok(task) = download_task.lock() {
-                    (task.cancelled, task.paused)
-                } else {
-                    (false, false)
-                }
-            };
+    // Caminho do arquivo (se completado)
+    if let Some(ref file_path) = record.file_path {
+        let path_value = info_value(file_path);
+        path_value.set_wrap(true);
+        path_value.set_selectable(true);
+        main_box.append(&info_group("Caminho do Arquivo", &path_value));
+    }
 
-            if cancelled {
-                let _ = std::fs::remove_file(temp_path);
-                let _ = tx.send(DownloadMessage::Error("Cancelado".to_string())).await;
-                return;
-            }
+    // Nome do arquivo
+    let filename_value = info_value(&record.filename);
+    filename_value.set_wrap(true);
+    filename_value.set_selectable(true);
+    main_box.append(&info_group("Nome do Arquivo", &filename_value));
 
-            if !paused {
-                break;
-            }
+    // URL de origem com botão de copiar
+    let url_box = GtkBox::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(8)
+        .build();
 
-            // Aguarda enquanto pausado
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-        }
+    let url_value = info_value(&record.url);
+    url_value.set_hexpand(true);
+    url_value.set_wrap(true);
+    url_value.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    url_value.set_selectable(true);
 
-        let chunk = match chunk_result {
-            Ok(c) => c,
-            Err(e) => {
-                // Erro durante stream - não tenta retry aqui (já foi feito na requisição inicial)
-                let _ = tx.send(DownloadMessage::Error(format!("Erro ao baixar: {}", e))).await;
-                return;
-            }
-        };
+    let copy_btn = Button::builder()
+        .icon_name("edit-copy-symbolic")
+        .tooltip_text("Copiar URL")
+        .valign(gtk4::Align::Start)
+        .build();
 
-        if let Err(e) = file.write_all(&chunk) {
-            let _ = tx.send(DownloadMessage::Error(format!("Erro ao escrever: {}", e))).await;
-            return;
+    // Os avisos aparecem sobre o próprio conteúdo (a janela principal fica atrás do diálogo)
+    let toast_overlay = libadwaita::ToastOverlay::new();
+    let record_url = record.url.clone();
+    copy_btn.connect_clicked(glib::clone!(#[weak] toast_overlay, move |_| {
+        if let Some(display) = gtk4::gdk::Display::default() {
+            display.clipboard().set_text(&record_url);
+            toast_overlay.add_toast(libadwaita::Toast::new("URL copiada"));
         }
+    }));
 
-        downloaded += chunk.len() as u64;
-
-        // Atualiza progresso a cada 200ms
-        if last_update.elapsed().as_millis() >= 200 {
-            let progress = if total_size > 0 {
-                downloaded as f64 / total_size as f64
-            } else {
-                0.0
-            };
-
-            let speed_bytes = (downloaded - last_downloaded) as f64 / last_update.elapsed().as_secs_f64();
-            let speed_text = format_speed(speed_bytes);
+    url_box.append(&url_value);
+    url_box.append(&copy_btn);
+    if let Some(edit_url) = edit_url {
+        let edit_btn = Button::builder()
+            .icon_name("document-edit-symbolic")
+            .tooltip_text("Editar URL")
+            .valign(gtk4::Align::Start)
+            .build();
+        let record_url = record.url.clone();
+        let edit_url = std::rc::Rc::new(edit_url);
+        edit_btn.connect_clicked(glib::clone!(#[weak] dialog, move |button| {
+            show_edit_url_dialog(button, &record_url, glib::clone!(#[weak] dialog, #[strong] edit_url, move |url| {
+                dialog.close();
+                edit_url(url);
+            }));
+        }));
+        url_box.append(&edit_btn);
+    }
+    main_box.append(&info_group("URL de Origem", &url_box));
+    if let Some(referer) = &record.options.referer {
+        let referer_value = info_value(referer);
+        referer_value.set_selectable(true);
+        main_box.append(&info_group("Página de Origem", &referer_value));
+    }
+    if let Some(profile) = &record.options.network_profile {
+        main_box.append(&info_group("Perfil de Rede", &info_value(profile)));
+    }
+    if let Some(template) = &record.options.template {
+        main_box.append(&info_group("Modelo", &info_value(template)));
+    }
+    if !record.options.mirrors.is_empty() {
+        let mirrors_value = info_value(&record.options.mirrors.join("\n"));
+        mirrors_value.set_selectable(true);
+        main_box.append(&info_group("Espelhos", &mirrors_value));
+    }
 
-            // Calcula ETA (tempo restante estimado)
-            let eta_text = if total_size > 0 && speed_bytes > 0.0 && downloaded < total_size {
-                let remaining_bytes = total_size - downloaded;
-                let eta_seconds = remaining_bytes as f64 / speed_bytes;
-                format_eta(eta_seconds)
-            } else {
-                String::new()
-            };
+    // Tamanho do arquivo
+    main_box.append(&info_group("Tamanho", &info_value(&format_file_size(record.total_bytes))));
 
-            let status = format!("{}/{}", format_bytes(downloaded), format_bytes(total_size));
+    // Status
+    let status_text = match record.status {
+        DownloadStatus::InProgress => if record.was_paused { "Pausado" } else { "Em Progresso" },
+        DownloadStatus::Completed => "Concluído",
+        DownloadStatus::Failed => record.failure.map_or("Falhou", FailureKind::label),
+        DownloadStatus::Cancelled => "Cancelado",
+    };
+    main_box.append(&info_group("Status", &info_value(status_text)));
 
-            let _ = tx.send(DownloadMessage::Progress(progress, status, speed_text, eta_text, parallel_chunks, speed_bytes as u64)).await;
+    // Data de início
+    let date_value = format!("{}", record.date_added.format("%d/%m/%Y às %H:%M:%S"));
+    main_box.append(&info_group("Data de Início", &info_value(&date_value)));
 
-            last_update = Instant::now();
-            last_downloaded = downloaded;
-        }
+    if record.options.use_tor {
+        main_box.append(&info_group("Rede", &info_value("Tor (circuito isolado)")));
     }
 
-    // Download completo - renomeia arquivo
-    drop(file);
-    if let Err(e) = std::fs::rename(temp_path, file_path) {
-        let _ = tx.send(DownloadMessage::Error(format!("Erro ao finalizar: {}", e))).await;
-        return;
+    let mut limits = Vec::new();
+    if let Some(minutes) = record.options.max_minutes {
+        limits.push(format!("Desiste após {}min baixando", minutes));
     }
-
-    // Salva o caminho do arquivo no download task
-    if let Ok(mut task) = download_task.lock() {
-        task.file_path = Some(file_path.clone());
+    if let Some(min) = record.options.min_speed {
+        limits.push(format!("Desiste abaixo de {} por {}min", format_speed(min.bytes_per_sec as f64), min.minutes));
+    }
+    if !limits.is_empty() {
+        main_box.append(&info_group("Limites", &info_value(&limits.join("\n"))));
     }
 
-    let _ = tx.send(DownloadMessage::Complete).await;
-}
-
-fn calculate_optimal_chunks(file_size: u64) -> u64 {
-    // Calcula número ótimo de chunks baseado no tamanho do arquivo
-    // - Arquivos pequenos (< 10MB): 2 chunks
-    // - Arquivos médios (10MB - 100MB): 4 chunks (padrão)
-    // - Arquivos grandes (100MB - 1GB): 6 chunks
-    // - Arquivos muito grandes (> 1GB): 8 chunks
-    // Garante que cada chunk tenha pelo menos MIN_CHUNK_SIZE
-    
-    let max_chunks_by_size = file_size / MIN_CHUNK_SIZE;
-    let suggested_chunks = if file_size < 10 * 1024 * 1024 {
-        2
-    } else if file_size < 100 * 1024 * 1024 {
-        DEFAULT_NUM_CHUNKS
-    } else if file_size < 1024 * 1024 * 1024 {
-        6
-    } else {
-        8
-    };
-    
-    // Usa o menor valor entre o sugerido e o máximo possível
-    suggested_chunks.min(max_chunks_by_size.max(1))
-}
+    if let Some(recurrence) = &record.options.recurrence {
+        let next = record
+            .next_run
+            .map(|next| next.with_timezone(&Local).format("%d/%m/%Y às %H:%M").to_string())
+            .unwrap_or_else(|| "Nunca".to_string());
+        let value = format!(
+            "{}\nPróxima execução: {}\nVersões mantidas: {} de {}",
+            recurrence.cron,
+            next,
+            record.versions.len(),
+            recurrence.keep_versions
+        );
+        main_box.append(&info_group("Recorrência", &info_value(&value)));
+    }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
+    if record.options.sync {
+        let version = record
+            .validators
+            .etag
+            .as_deref()
+            .or(record.validators.last_modified.as_deref())
+            .unwrap_or("Nenhuma versão registrada");
+        main_box.append(&info_group("Sincronização", &info_value(version)));
+    }
 
-    if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} B", bytes)
+    if let Some(sha256) = &record.sha256 {
+        main_box.append(&info_group("SHA-256 (verificado)", &info_value(sha256)));
     }
-}
 
-fn format_speed(bytes_per_sec: f64) -> String {
-    const KB: f64 = 1024.0;
-    const MB: f64 = KB * 1024.0;
+    if record.options.accept_invalid_certs {
+        let value = info_value("Não verificado (certificados inválidos aceitos)");
+        value.add_css_class("warning");
+        main_box.append(&info_group("Certificado TLS", &value));
+    }
 
-    if bytes_per_sec >= MB {
-        format!("{:.2} MB/s", bytes_per_sec / MB)
-    } else if bytes_per_sec >= KB {
-        format!("{:.2} KB/s", bytes_per_sec / KB)
-    } else {
-        format!("{:.0} B/s", bytes_per_sec)
+    if !record.events.is_empty() {
+        main_box.append(&timeline_expander(&record.events));
     }
-}
 
-fn format_eta(seconds: f64) -> String {
-    if seconds.is_infinite() || seconds.is_nan() || seconds < 0.0 {
-        return String::new();
+    if let Some(response) = &record.response {
+        main_box.append(&response_expander(response));
     }
 
-    let total_seconds = seconds as u64;
+    toast_overlay.set_child(Some(&main_box));
+    dialog.set_extra_child(Some(&toast_overlay));
+    dialog.present(Some(parent));
+}
 
-    let hours = total_seconds / 3600;
-    let minutes = (total_seconds % 3600) / 60;
-    let secs = total_seconds % 60;
+// Novo endereço para o mesmo arquivo (link vencido, espelho que mudou de lugar)
+fn show_edit_url_dialog(parent: &impl IsA<gtk4::Widget>, current: &str, on_chosen: impl Fn(String) + 'static) {
+    let dialog = libadwaita::AlertDialog::new(
+        Some("Editar URL"),
+        Some("O que já foi baixado é mantido. Antes de continuar, o novo endereço é conferido pelo tamanho e pelo ETag do arquivo."),
+    );
 
-    if hours > 0 {
-        format!("{}h {}min", hours, minutes)
-    } else if minutes > 0 {
-        format!("{}min {}s", minutes, secs)
-    } else if secs > 0 {
-        format!("{}s", secs)
-    } else {
-        "< 1s".to_string()
-    }
-}
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Conferir e Continuar");
+    dialog.set_response_appearance("save", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    let entry = gtk4::Entry::new();
+    entry.set_text(current);
+    entry.set_activates_default(true);
+    dialog.set_extra_child(Some(&entry));
+    let current = current.to_string();
+    entry.connect_changed(glib::clone!(#[weak] dialog, move |entry| {
+        let url = entry.text();
+        dialog.set_response_enabled("save", is_download_url(url.trim()) && url.trim() != current);
+    }));
+    dialog.set_response_enabled("save", false);
 
-// Funções auxiliares para markup Pango padronizado
-fn markup_title(text: &str) -> String {
-    format!(
-        "<span weight='bold' size='large'>{}</span>",
-        glib::markup_escape_text(text)
-    )
-}
+    dialog.connect_response(None, move |_, response| {
+        if response == "save" {
+            on_chosen(entry.text().trim().to_string());
+        }
+    });
 
-fn markup_title_strikethrough(text: &str) -> String {
-    format!(
-        "<s><span weight='bold' size='large'>{}</span></s>",
-        glib::markup_escape_text(text)
-    )
+    dialog.present(Some(parent));
 }
 
-fn markup_status(text: &str) -> String {
-    format!(
-        "<span weight='600'>{}</span>",
-        glib::markup_escape_text(text)
-    )
-}
+// Linha do tempo do download; a data só aparece quando muda de um evento para o outro
+fn timeline_expander(events: &[DownloadEvent]) -> gtk4::Expander {
+    let mut lines = Vec::new();
+    let mut last_day = None;
+    for event in events {
+        let at = event.at.with_timezone(&Local);
+        let day = at.date_naive();
+        if last_day != Some(day) {
+            lines.push(at.format("%d/%m/%Y").to_string());
+            last_day = Some(day);
+        }
+        lines.push(format!("  {}  {}", at.format("%H:%M:%S"), event.text));
+    }
 
-// Removida: markup_status_icon - agora usa gtk4::Image com ícones simbólicos
+    let value = info_value(&lines.join("\n"));
+    value.set_wrap(true);
+    value.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+    value.set_selectable(true);
+    value.add_css_class("monospace");
 
-fn markup_metadata_primary(text: &str) -> String {
-    format!(
-        "<span weight='600'>{}</span>",
-        glib::markup_escape_text(text)
-    )
+    gtk4::Expander::builder()
+        .label("Linha do Tempo")
+        .child(&value)
+        .build()
 }
 
-fn markup_metadata_secondary(text: &str) -> String {
-    format!(
-        "<span size='small' weight='normal'>{}</span>",
-        glib::markup_escape_text(text)
-    )
+// Seção recolhida com protocolo e headers da resposta (para depurar espelhos)
+fn response_expander(response: &ResponseDetails) -> gtk4::Expander {
+    let secure = if response.url.starts_with("https://") { " sobre TLS" } else { "" };
+    let mut lines = vec![
+        format!("Endereço: {}", response.url),
+        format!("Protocolo: {}{}", response.protocol, secure),
+        String::new(),
+    ];
+    lines.extend(response.headers.iter().map(|(name, value)| format!("{}: {}", name, value)));
+
+    let value = info_value(&lines.join("\n"));
+    value.set_wrap(true);
+    value.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+    value.set_selectable(true);
+    value.add_css_class("monospace");
+
+    gtk4::Expander::builder()
+        .label("Resposta do Servidor")
+        .child(&value)
+        .build()
 }
 
-// Função auxiliar para verificar se um erro é recuperável (timeout, conexão)
-fn is_recoverable_error(err: &reqwest::Error) -> bool {
-    err.is_timeout() || err.is_connect() || err.is_request()
-}
+// Título + valor de uma seção do diálogo de informações
+fn info_group(title: &str, value: &impl IsA<gtk4::Widget>) -> GtkBox {
+    let group = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(4)
+        .build();
 
-// Função auxiliar para fazer retry automático em requisições
-async fn retry_request<F, Fut, T>(request_fn: F, max_retries: u32, delay_secs: u64) -> Result<T, reqwest::Error>
-where
-    F: Fn() -> Fut,
-    Fut: std::future::Future<Output = Result<T, reqwest::Error>>,
-{
-    let mut last_error = None;
-    
-    for attempt in 0..max_retries {
-        match request_fn().await {
-            Ok(result) => return Ok(result),
-            Err(e) => {
-                // Verifica se é erro recuperável
-                if !is_recoverable_error(&e) {
-                    // Erro não recuperável (404, 403, etc.) - não tenta novamente
-                    return Err(e);
-                }
-                
-                last_error = Some(e);
-                
-                // Se não é a última tentativa, aguarda antes de tentar novamente
-                if attempt < max_retries - 1 {
-                    // Delay exponencial: 2s, 4s, 8s...
-                    let delay = delay_secs * (1 << attempt);
-                    tokio::time::sleep(std::time::Duration::from_secs(delay)).await;
-                }
-            }
-        }
-    }
-    
-    // Retorna o último erro se todas as tentativas falharam
-    // Se não houver erro anterior (não deveria acontecer), tenta fazer uma última requisição
-    match last_error {
-        Some(e) => Err(e),
-        None => {
-            // Faz uma última tentativa
-            request_fn().await
-        }
-    }
+    let title_label = Label::builder()
+        .label(title)
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["title-4"])
+        .build();
+
+    group.append(&title_label);
+    group.append(value);
+    group
 }
 
+fn info_value(text: &str) -> Label {
+    Label::builder()
+        .label(text)
+        .halign(gtk4::Align::Start)
+        .css_classes(vec!["caption"])
+        .build()
+}
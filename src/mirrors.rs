@@ -0,0 +1,151 @@
+// Espelhos de um download: outros endereços com o mesmo arquivo
+//
+// Antes do download em chunks, cada espelho recebe um pedido pequeno com Range
+// (latência até a resposta e velocidade do trecho). Os mais rápidos entram no
+// rodízio de chunks; a velocidade medida em cada chunk atualiza a classificação,
+// e um espelho que piorar sai do rodízio no meio do chunk.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use futures_util::StreamExt;
+use reqwest::header::CONTENT_RANGE;
+use reqwest::StatusCode;
+use crate::http::{send_request, HttpRequest, SharedBackend};
+
+pub const PROBE_BYTES: u64 = 256 * 1024; // Trecho baixado de cada espelho no teste
+pub const PROBE_TIMEOUT_SECS: u64 = 10;
+pub const MAX_ACTIVE_MIRRORS: usize = 3; // Espelhos mais rápidos usados ao mesmo tempo
+const SPEED_WEIGHT: f64 = 0.3; // Peso de cada nova medição na média da velocidade
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorProbe {
+    pub url: String,
+    pub latency: Duration, // Até a resposta (headers)
+    pub speed: f64,        // Bytes/s do trecho de teste
+}
+
+// Baixa os primeiros PROBE_BYTES de `url`. Um espelho que não aceita Range ou tem
+// outro tamanho de arquivo não serve para chunks.
+pub async fn probe_mirror(backend: &SharedBackend, url: &str, total_size: u64) -> Result<MirrorProbe, String> {
+    let probe = async {
+        let started = Instant::now();
+        let end = PROBE_BYTES.min(total_size).saturating_sub(1);
+        let response = send_request(backend.as_ref(), HttpRequest::get(url).range(0, Some(end)))
+            .await
+            .map_err(|e| e.to_string())?;
+        let latency = started.elapsed();
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(format!("Status HTTP: {} (sem suporte a Range)", response.status()));
+        }
+        let remote_total = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse::<u64>().ok());
+        if remote_total != Some(total_size) {
+            return Err("Tamanho diferente do arquivo principal".to_string());
+        }
+
+        let body_started = Instant::now();
+        let mut received = 0u64;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            received += chunk.map_err(|e| e.to_string())?.len() as u64;
+        }
+        let elapsed = body_started.elapsed().as_secs_f64().max(0.001);
+        Ok(MirrorProbe { url: url.to_string(), latency, speed: received as f64 / elapsed })
+    };
+    tokio::time::timeout(Duration::from_secs(PROBE_TIMEOUT_SECS), probe)
+        .await
+        .unwrap_or_else(|_| Err("Tempo esgotado".to_string()))
+}
+
+// Mais rápido primeiro; na mesma velocidade, menor latência
+pub fn rank_probes(probes: &mut [MirrorProbe]) {
+    probes.sort_by(|a, b| b.speed.total_cmp(&a.speed).then(a.latency.cmp(&b.latency)));
+}
+
+#[derive(Debug, Clone)]
+struct MirrorState {
+    url: String,
+    speed: f64, // Média das medições (bytes/s)
+}
+
+// Espelhos em ordem de velocidade, compartilhados pelas conexões de um download
+#[derive(Debug)]
+pub struct MirrorPool {
+    mirrors: Mutex<Vec<MirrorState>>,
+    next: AtomicUsize,
+}
+
+impl MirrorPool {
+    // Só o endereço principal (download sem espelhos)
+    pub fn single(url: &str) -> Self {
+        Self::from_probes(vec![MirrorProbe { url: url.to_string(), latency: Duration::ZERO, speed: 0.0 }])
+    }
+
+    pub fn from_probes(mut probes: Vec<MirrorProbe>) -> Self {
+        rank_probes(&mut probes);
+        let mirrors = probes.into_iter().map(|probe| MirrorState { url: probe.url, speed: probe.speed }).collect();
+        MirrorPool { mirrors: Mutex::new(mirrors), next: AtomicUsize::new(0) }
+    }
+
+    // Classificação atual, do mais rápido ao mais lento
+    pub fn urls(&self) -> Vec<String> {
+        self.mirrors
+            .lock()
+            .map(|mirrors| mirrors.iter().map(|m| m.url.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    // Próximo espelho do rodízio entre os MAX_ACTIVE_MIRRORS mais rápidos
+    pub fn pick(&self) -> Option<String> {
+        let mirrors = self.mirrors.lock().ok()?;
+        let active = mirrors.len().min(MAX_ACTIVE_MIRRORS);
+        if active == 0 {
+            return None;
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % active;
+        Some(mirrors[index].url.clone())
+    }
+
+    pub fn is_active(&self, url: &str) -> bool {
+        self.mirrors
+            .lock()
+            .map(|mirrors| mirrors.iter().take(MAX_ACTIVE_MIRRORS).any(|m| m.url == url))
+            .unwrap_or(true)
+    }
+
+    // Nova medição de `url`; true se a ordem dos espelhos mudou
+    pub fn record_speed(&self, url: &str, speed: f64) -> bool {
+        let Ok(mut mirrors) = self.mirrors.lock() else {
+            return false;
+        };
+        let Some(mirror) = mirrors.iter_mut().find(|m| m.url == url) else {
+            return false;
+        };
+        mirror.speed = mirror.speed * (1.0 - SPEED_WEIGHT) + speed * SPEED_WEIGHT;
+        let before: Vec<String> = mirrors.iter().map(|m| m.url.clone()).collect();
+        mirrors.sort_by(|a, b| b.speed.total_cmp(&a.speed));
+        mirrors.iter().map(|m| &m.url).ne(before.iter())
+    }
+
+    // Espelho que respondeu com erro sai da lista (o último nunca sai)
+    pub fn remove(&self, url: &str) -> bool {
+        let Ok(mut mirrors) = self.mirrors.lock() else {
+            return false;
+        };
+        if mirrors.len() <= 1 {
+            return false;
+        }
+        let before = mirrors.len();
+        mirrors.retain(|m| m.url != url);
+        mirrors.len() != before
+    }
+
+    // Falha de conexão conta como velocidade zero
+    pub fn record_failure(&self, url: &str) -> bool {
+        self.record_speed(url, 0.0)
+    }
+}
@@ -0,0 +1,308 @@
+// Configuração automática de proxy (arquivo PAC)
+//
+// Redes corporativas publicam um script PAC: JavaScript com FindProxyForURL(url, host),
+// que devolve algo como "PROXY proxy.empresa:3128; DIRECT". O script roda no boa
+// (interpretador JavaScript em Rust) com as funções auxiliares do padrão, e a decisão
+// de cada endereço (de cada servidor, em https) fica guardada. Se o script falhar,
+// vale o proxy manual.
+//
+// O script e o dnsResolve são bloqueantes: rodam em spawn_blocking antes da requisição
+// e de cada redirecionamento (`prepare`), e a requisição sai pelo client do item
+// escolhido. Quando um item da resposta não conecta, o próximo é tentado (`skip_failed`).
+use std::collections::HashMap;
+use std::net::{IpAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use boa_engine::{js_string, Context, JsResult, JsString, JsValue, NativeFunction, Source};
+use futures_util::StreamExt;
+use reqwest::Url;
+use crate::http::{send_request, HttpRequest, ReqwestBackend};
+
+pub const PAC_REFRESH_SECS: u64 = 3600; // O arquivo é baixado de novo depois deste tempo
+pub const MAX_PAC_SIZE: usize = 1024 * 1024;
+const LOOP_LIMIT: u64 = 1_000_000; // Um script com laço infinito não trava a conexão
+
+// Funções auxiliares do padrão PAC escritas em JavaScript; dnsResolve e
+// myIpAddress vêm do Rust. dateRange não é suportado (o script cai no proxy manual).
+const PAC_UTILS: &str = r#"
+function isPlainHostName(host) { return host.indexOf('.') < 0; }
+function dnsDomainIs(host, domain) {
+    return host.length >= domain.length && host.substring(host.length - domain.length) === domain;
+}
+function localHostOrDomainIs(host, hostdom) { return host === hostdom || hostdom.lastIndexOf(host + '.', 0) === 0; }
+function isResolvable(host) { return dnsResolve(host) !== null; }
+function dnsDomainLevels(host) { return host.split('.').length - 1; }
+function convertAddr(ip) {
+    var parts = ip.split('.');
+    return ((parts[0] & 0xff) << 24) | ((parts[1] & 0xff) << 16) | ((parts[2] & 0xff) << 8) | (parts[3] & 0xff);
+}
+function isInNet(host, pattern, mask) {
+    var ip = /^\d+\.\d+\.\d+\.\d+$/.test(host) ? host : dnsResolve(host);
+    if (ip === null) return false;
+    return (convertAddr(ip) & convertAddr(mask)) === (convertAddr(pattern) & convertAddr(mask));
+}
+function shExpMatch(str, shexp) {
+    var source = shexp.replace(/[.+^${}()|[\]\\]/g, '\\$&').replace(/\*/g, '.*').replace(/\?/g, '.');
+    return new RegExp('^' + source + '$').test(str);
+}
+function weekdayRange(wd1, wd2, gmt) {
+    var days = ['SUN', 'MON', 'TUE', 'WED', 'THU', 'FRI', 'SAT'];
+    if (wd2 === 'GMT') { gmt = wd2; wd2 = undefined; }
+    var now = new Date();
+    var today = gmt === 'GMT' ? now.getUTCDay() : now.getDay();
+    var start = days.indexOf(wd1);
+    var end = wd2 === undefined ? start : days.indexOf(wd2);
+    return start <= end ? today >= start && today <= end : today >= start || today <= end;
+}
+function timeRange(h1, h2, gmt) {
+    if (h2 === 'GMT') { gmt = h2; h2 = undefined; }
+    var now = new Date();
+    var hour = gmt === 'GMT' ? now.getUTCHours() : now.getHours();
+    if (h2 === undefined) return hour === h1;
+    return h1 <= h2 ? hour >= h1 && hour < h2 : hour >= h1 || hour < h2;
+}
+"#;
+
+// Um item da resposta do script, na ordem de preferência
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PacProxy {
+    Direct,
+    Proxy(String), // URL do proxy (http://, https:// ou socks5://)
+}
+
+// "PROXY a:3128; SOCKS b:1080; DIRECT" -> itens reconhecidos, na ordem
+pub fn parse_pac_result(result: &str) -> Vec<PacProxy> {
+    result
+        .split(';')
+        .filter_map(|item| {
+            let mut parts = item.split_whitespace();
+            let kind = parts.next()?.to_ascii_uppercase();
+            let address = parts.next();
+            match (kind.as_str(), address) {
+                ("DIRECT", _) => Some(PacProxy::Direct),
+                ("PROXY" | "HTTP", Some(address)) => Some(PacProxy::Proxy(format!("http://{}", address))),
+                ("HTTPS", Some(address)) => Some(PacProxy::Proxy(format!("https://{}", address))),
+                ("SOCKS" | "SOCKS5", Some(address)) => Some(PacProxy::Proxy(format!("socks5://{}", address))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+pub struct PacScript {
+    source: String,
+    decisions: Mutex<HashMap<String, Vec<PacProxy>>>, // Por URL passada ao script (ver `script_url`)
+}
+
+impl std::fmt::Debug for PacScript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PacScript({} bytes)", self.source.len())
+    }
+}
+
+// Mesmo conteúdo = mesmo script (o client compartilhado só é refeito se o PAC mudar)
+impl PartialEq for PacScript {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl PacScript {
+    // Recusa scripts que não carregam ou não definem FindProxyForURL
+    pub fn new(source: &str) -> Result<Self, String> {
+        let mut context = pac_context(source)?;
+        find_proxy_function(&mut context)?;
+        Ok(PacScript { source: source.to_string(), decisions: Mutex::new(HashMap::new()) })
+    }
+
+    // Decide (ou reaproveita a decisão de) `url`. Bloqueante: roda o script e pode
+    // consultar o DNS.
+    pub fn find_proxy(&self, url: &Url) -> Result<Vec<PacProxy>, String> {
+        let key = script_url(url);
+        if let Some(decision) = self.decisions.lock().ok().and_then(|decisions| decisions.get(&key).cloned()) {
+            return Ok(decision);
+        }
+        let result = evaluate(&self.source, &key, url.host_str().unwrap_or_default())?;
+        let decision = parse_pac_result(&result);
+        if decision.is_empty() {
+            return Err(format!("Resposta do PAC não reconhecida: {}", result));
+        }
+        if let Ok(mut decisions) = self.decisions.lock() {
+            decisions.entry(key).or_insert(decision.clone());
+        }
+        Ok(decision)
+    }
+
+    // Decide `url` fora do runtime, antes de a requisição chegar ao conector
+    pub async fn prepare(self: &Arc<Self>, url: &Url) -> Result<(), String> {
+        if self.proxy_for(url).is_some() {
+            return Ok(());
+        }
+        let (script, url) = (self.clone(), url.clone());
+        tokio::task::spawn_blocking(move || script.find_proxy(&url).map(|_| ()))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    // Item em uso para `url`, se já decidido (não roda o script)
+    pub fn proxy_for(&self, url: &Url) -> Option<PacProxy> {
+        let decisions = self.decisions.lock().ok()?;
+        decisions.get(&script_url(url)).and_then(|decision| decision.first().cloned())
+    }
+
+    // `failed` não conectou: passa para o próximo item da resposta. Devolve false se
+    // não sobrou alternativa. O item descartado só volta quando o PAC é baixado de novo.
+    pub fn skip_failed(&self, url: &Url, failed: &PacProxy) -> bool {
+        let Ok(mut decisions) = self.decisions.lock() else {
+            return false;
+        };
+        let Some(decision) = decisions.get_mut(&script_url(url)) else {
+            return false;
+        };
+        if decision.first() != Some(failed) {
+            // Outra conexão já passou adiante
+            return !decision.is_empty();
+        }
+        if decision.len() < 2 {
+            return false;
+        }
+        decision.remove(0);
+        true
+    }
+}
+
+// URL entregue ao FindProxyForURL, que também identifica a decisão guardada. Em
+// http o script vê o caminho (e pode decidir por ele); em https só esquema e
+// servidor, como nos navegadores, e a decisão vale para o servidor inteiro
+pub fn script_url(url: &Url) -> String {
+    if url.scheme() == "http" {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        return url.to_string();
+    }
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}://{}:{}/", url.scheme(), host, port),
+        None => format!("{}://{}/", url.scheme(), host),
+    }
+}
+
+fn pac_context(source: &str) -> Result<Context, String> {
+    let mut context = Context::default();
+    context.runtime_limits_mut().set_loop_iteration_limit(LOOP_LIMIT);
+    context
+        .register_global_callable(js_string!("dnsResolve"), 1, NativeFunction::from_fn_ptr(dns_resolve))
+        .map_err(|e| e.to_string())?;
+    context
+        .register_global_callable(js_string!("myIpAddress"), 0, NativeFunction::from_fn_ptr(my_ip_address))
+        .map_err(|e| e.to_string())?;
+    context.eval(Source::from_bytes(PAC_UTILS)).map_err(|e| e.to_string())?;
+    context
+        .eval(Source::from_bytes(source))
+        .map_err(|e| format!("Erro no script PAC: {}", e))?;
+    Ok(context)
+}
+
+fn find_proxy_function(context: &mut Context) -> Result<boa_engine::JsObject, String> {
+    let function = context
+        .global_object()
+        .get(js_string!("FindProxyForURL"), context)
+        .map_err(|e| e.to_string())?;
+    function
+        .as_callable()
+        .cloned()
+        .ok_or_else(|| "O script PAC não define FindProxyForURL".to_string())
+}
+
+fn evaluate(source: &str, url: &str, host: &str) -> Result<String, String> {
+    let mut context = pac_context(source)?;
+    let function = find_proxy_function(&mut context)?;
+    let args = [JsValue::from(JsString::from(url)), JsValue::from(JsString::from(host))];
+    let result = function
+        .call(&JsValue::undefined(), &args, &mut context)
+        .map_err(|e| format!("Erro no script PAC: {}", e))?;
+    result
+        .to_string(&mut context)
+        .map(|text| text.to_std_string_escaped())
+        .map_err(|e| e.to_string())
+}
+
+// dnsResolve(host): primeiro endereço IPv4, ou null
+fn dns_resolve(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let host = match args.first() {
+        Some(value) => value.to_string(context)?.to_std_string_escaped(),
+        None => return Ok(JsValue::null()),
+    };
+    let address = (host.as_str(), 0)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.find(|addr| addr.is_ipv4()));
+    Ok(match address {
+        Some(address) => JsValue::from(JsString::from(address.ip().to_string().as_str())),
+        None => JsValue::null(),
+    })
+}
+
+// myIpAddress(): endereço local da rota padrão (o UDP "conectado" não envia nada)
+fn my_ip_address(_: &JsValue, _: &[JsValue], _: &mut Context) -> JsResult<JsValue> {
+    let address = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| socket.connect("192.0.2.1:80").map(|_| socket))
+        .and_then(|socket| socket.local_addr())
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+    Ok(JsValue::from(JsString::from(address.to_string().as_str())))
+}
+
+// Endereço do PAC: http(s)://, file:// ou caminho local
+pub async fn fetch_pac(location: &str) -> Result<String, String> {
+    if let Some(path) = location.strip_prefix("file://").or_else(|| location.starts_with('/').then_some(location)) {
+        return std::fs::read_to_string(path).map_err(|e| format!("Erro ao ler {}: {}", path, e));
+    }
+    // O próprio PAC é sempre baixado sem proxy
+    let backend = ReqwestBackend::new().map_err(|e| e.to_string())?;
+    let response = send_request(&backend, HttpRequest::get(location)).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Status HTTP: {}", response.status()));
+    }
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+        if body.len() > MAX_PAC_SIZE {
+            return Err("Arquivo PAC grande demais".to_string());
+        }
+    }
+    String::from_utf8(body).map_err(|_| "Arquivo PAC não é texto".to_string())
+}
+
+struct LoadedPac {
+    location: String,
+    script: Arc<PacScript>,
+    loaded_at: Instant,
+}
+
+static LOADED: Mutex<Option<LoadedPac>> = Mutex::new(None);
+
+// Script já carregado de `location` (mesmo vencido: serve até o próximo `load`)
+pub fn cached(location: &str) -> Option<Arc<PacScript>> {
+    let loaded = LOADED.lock().ok()?;
+    loaded.as_ref().filter(|pac| pac.location == location).map(|pac| pac.script.clone())
+}
+
+// Baixa o PAC se ainda não foi baixado ou se passou de PAC_REFRESH_SECS
+pub async fn load(location: &str) -> Result<Arc<PacScript>, String> {
+    let fresh = LOADED.lock().ok().and_then(|loaded| {
+        loaded
+            .as_ref()
+            .filter(|pac| pac.location == location && pac.loaded_at.elapsed() < Duration::from_secs(PAC_REFRESH_SECS))
+            .map(|pac| pac.script.clone())
+    });
+    if let Some(script) = fresh {
+        return Ok(script);
+    }
+    let script = Arc::new(PacScript::new(&fetch_pac(location).await?)?);
+    if let Ok(mut loaded) = LOADED.lock() {
+        *loaded = Some(LoadedPac { location: location.to_string(), script: script.clone(), loaded_at: Instant::now() });
+    }
+    Ok(script)
+}
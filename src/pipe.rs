@@ -0,0 +1,131 @@
+// Download direto para um `Write`, sem .part nem registro na lista
+//
+// Usado por `keepers fetch <url> -o -` para mandar o corpo ao stdout (pipelines
+// de shell). Erros de conexão seguem as mesmas tentativas e esperas do motor; se o
+// corpo for interrompido, o restante é pedido com Range a partir do que já saiu.
+// Servidores que ignoram o Range mandam o arquivo inteiro de novo, e o começo
+// já escrito é descartado.
+use std::io::{ErrorKind, Write};
+use std::time::Duration;
+use futures_util::StreamExt;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE};
+use reqwest::StatusCode;
+use crate::engine::{backend_for_config, MAX_RETRIES, RETRY_DELAY_SECS};
+use crate::http::{retry_request_notify, send_request, HttpError, HttpErrorKind, HttpRequest, HttpResponse, SharedBackend};
+use crate::storage::{load_config, AppConfig};
+use crate::url_filter;
+
+// Argumento de `-o` que significa "saída padrão"
+pub const STDOUT_TARGET: &str = "-";
+
+// Configuração de `keepers fetch`: a do perfil ativo (modo portátil e `--profile`
+// já aplicados), com a URL conferida pelo filtro dela
+pub fn fetch_config(url: &str) -> Result<AppConfig, String> {
+    let config = load_config();
+    url_filter::check_url(&config.url_filter, url)?;
+    Ok(config)
+}
+
+// Baixa `url` com o client da configuração (proxy, CA, S3, IPFS) e escreve em `out`
+pub fn fetch(config: &AppConfig, url: &str, out: &mut impl Write) -> Result<u64, String> {
+    let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    // Com PAC, o script precisa estar carregado antes de montar o client
+    let pac_url = config.proxy.pac_url.trim();
+    if !pac_url.is_empty() {
+        if let Err(e) = rt.block_on(crate::pac::load(pac_url)) {
+            eprintln!("PAC indisponível ({}), usando o proxy manual", e);
+        }
+    }
+    let backend = backend_for_config(config).map_err(|e| format!("Erro ao criar cliente HTTP: {}", e))?;
+    rt.block_on(stream_to(&backend, url, out, |error, wait| {
+        eprintln!("{}; nova tentativa em {}s", error, wait.as_secs());
+    }))
+}
+
+// Escreve o corpo em `out` e devolve quantos bytes saíram. Leitor fechado do outro
+// lado do pipe (ex.: `| head`) encerra sem erro.
+pub async fn stream_to(
+    backend: &SharedBackend,
+    url: &str,
+    out: &mut impl Write,
+    on_retry: impl Fn(&HttpError, Duration),
+) -> Result<u64, String> {
+    let mut written: u64 = 0;
+    let mut interruptions = 0;
+    loop {
+        let offset = written;
+        let response = retry_request_notify(|| {
+            let mut req = HttpRequest::get(url);
+            if offset > 0 {
+                req = req.range(offset, None);
+            }
+            send_request(backend.as_ref(), req)
+        }, MAX_RETRIES, RETRY_DELAY_SECS, &on_retry)
+        .await
+        .map_err(|e| format!("Erro na requisição após {} tentativas: {}", MAX_RETRIES, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Status HTTP: {}", status));
+        }
+        // Sem 206 o corpo recomeça do zero
+        let mut skip = if status == StatusCode::PARTIAL_CONTENT { 0 } else { written };
+        let expected_total = body_total(&response, status);
+
+        let mut stream = response.bytes_stream();
+        let mut failure = None;
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    failure = Some(e.to_string());
+                    break;
+                }
+            };
+            let dropped = skip.min(chunk.len() as u64);
+            skip -= dropped;
+            match out.write_all(&chunk[dropped as usize..]) {
+                Ok(()) => written += chunk.len() as u64 - dropped,
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(written),
+                Err(e) => return Err(format!("Erro ao escrever: {}", e)),
+            }
+        }
+        if failure.is_none() && expected_total.is_some_and(|total| written < total) {
+            failure = Some("Conexão encerrada antes do fim do arquivo".to_string());
+        }
+
+        match failure {
+            None => {
+                return match out.flush() {
+                    Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(format!("Erro ao escrever: {}", e)),
+                    _ => Ok(written),
+                };
+            }
+            Some(e) => {
+                interruptions += 1;
+                if interruptions >= MAX_RETRIES {
+                    return Err(format!("Erro ao baixar: {}", e));
+                }
+                let wait = Duration::from_secs(RETRY_DELAY_SECS * (1 << (interruptions - 1)));
+                on_retry(&HttpError::new(HttpErrorKind::Body, &e), wait);
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+// Tamanho total anunciado (Content-Range em 206, Content-Length em 200)
+fn body_total(response: &HttpResponse, status: StatusCode) -> Option<u64> {
+    let headers = response.headers();
+    if status == StatusCode::PARTIAL_CONTENT {
+        return headers
+            .get(CONTENT_RANGE)?
+            .to_str()
+            .ok()?
+            .rsplit_once('/')?
+            .1
+            .parse()
+            .ok();
+    }
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
@@ -0,0 +1,216 @@
+// Diferenças entre Linux, Windows e macOS em um lugar só
+//
+// Abrir arquivos e pastas, início automático no login, caminhos longos e a troca
+// de um arquivo por outro (rename) mudam de sistema para sistema; o resto do app
+// chama estas funções sem `cfg`. Notificações e ícone na bandeja não entram aqui:
+// o app não usa nenhum dos dois (o menu do cabeçalho faz o papel da bandeja).
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::autostart;
+
+// Antivírus e indexador do Windows seguram por instantes o arquivo recém-fechado:
+// o rename é tentado de novo antes de desistir
+#[cfg(windows)]
+const RENAME_ATTEMPTS: u32 = 10;
+#[cfg(windows)]
+const RENAME_RETRY_MS: u64 = 100;
+// Acima disso o Windows só aceita o caminho com o prefixo \\?\ (MAX_PATH é 260,
+// mas pastas precisam de 12 caracteres livres para os nomes 8.3)
+pub const WINDOWS_MAX_DIR_PATH: usize = 248;
+
+// Nomes de dispositivo do Windows: não podem ser usados nem com extensão ("CON.txt")
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Sandbox do Flatpak (só existe no Linux)
+pub fn in_flatpak() -> bool {
+    cfg!(target_os = "linux") && Path::new("/.flatpak-info").exists()
+}
+
+// Abre o arquivo ou a pasta com o app padrão do sistema
+pub fn open_path(path: impl AsRef<Path>) -> io::Result<()> {
+    open::that(long_path(path.as_ref()))
+}
+
+// Abre o gerenciador de arquivos com o arquivo selecionado
+#[cfg(windows)]
+pub fn reveal_file(path: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    // O explorer sai com código 1 mesmo quando abre: só a falha ao executar conta
+    std::process::Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reveal_file(path: &Path) -> io::Result<()> {
+    let status = std::process::Command::new("open").arg("-R").arg(path).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("open -R saiu com {}", status)));
+    }
+    Ok(())
+}
+
+// Sem um jeito comum a todos os gerenciadores de arquivos: abre a pasta
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn reveal_file(path: &Path) -> io::Result<()> {
+    let parent = path.parent().ok_or_else(|| io::Error::other("arquivo sem pasta"))?;
+    open::that(parent)
+}
+
+pub fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved))
+}
+
+// Nome de arquivo que vale em qualquer sistema (o pendrive e a pasta de rede podem
+// ir parar no Windows): sem os caracteres proibidos lá, sem ponto ou espaço no
+// final (que o Windows descarta) e sem nome de dispositivo ("CON.txt" -> "_CON.txt")
+pub fn safe_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || "<>:\"|?*\\/".contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim_end_matches(['.', ' ']);
+    if cleaned.is_empty() {
+        "download".to_string()
+    } else if is_reserved_name(cleaned) {
+        format!("_{}", cleaned)
+    } else {
+        cleaned.to_string()
+    }
+}
+
+// "C:\pasta\arquivo" -> "\\?\C:\pasta\arquivo" e "\\servidor\pasta" ->
+// "\\?\UNC\servidor\pasta"; None para caminhos relativos ou já com o prefixo.
+// Com o prefixo o Windows não normaliza mais nada: as barras viram "\"
+pub fn extended_length_path(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", unc));
+    }
+    let bytes = path.as_bytes();
+    let absolute = bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    absolute.then(|| format!(r"\\?\{}", path))
+}
+
+// Caminho que o sistema aceita mesmo passando do limite do Windows
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if text.chars().count() < WINDOWS_MAX_DIR_PATH {
+        return path.to_path_buf();
+    }
+    // Relativo: absolutiza antes (o prefixo não aceita "." nem "..")
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match extended_length_path(&absolute.to_string_lossy()) {
+        Some(extended) => PathBuf::from(extended),
+        None => absolute,
+    }
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+// Troca `to` por `from`, substituindo o destino se existir
+#[cfg(windows)]
+pub fn rename_file(from: &Path, to: &Path) -> io::Result<()> {
+    let (from, to) = (long_path(from), long_path(to));
+    let mut attempt = 1;
+    loop {
+        match std::fs::rename(&from, &to) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied && attempt < RENAME_ATTEMPTS => {
+                attempt += 1;
+                std::thread::sleep(std::time::Duration::from_millis(RENAME_RETRY_MS));
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn rename_file(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::rename(from, to)
+}
+
+// Rename; entre sistemas de arquivos diferentes (ex.: pasta de concluídos em
+// outro disco), copia e apaga o original
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if rename_file(from, to).is_ok() {
+        return Ok(());
+    }
+    let (from, to) = (long_path(from), long_path(to));
+    std::fs::copy(&from, &to)?;
+    std::fs::remove_file(&from)
+}
+
+// Início automático no login (no Flatpak quem cuida é o portal Background)
+#[cfg(windows)]
+pub fn set_autostart(app_id: &str, exec: &Path, enabled: bool) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    // Sem abrir uma janela de console a cada chamada ao reg.exe
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut command = std::process::Command::new("reg");
+    if enabled {
+        command.args(["add", autostart::RUN_KEY, "/v", app_id, "/t", "REG_SZ", "/d", autostart::run_key_command(exec).as_str(), "/f"]);
+    } else {
+        if !autostart_enabled(app_id) {
+            return Ok(());
+        }
+        command.args(["delete", autostart::RUN_KEY, "/v", app_id, "/f"]);
+    }
+    let output = command.creation_flags(CREATE_NO_WINDOW).output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn autostart_enabled(app_id: &str) -> bool {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    std::process::Command::new("reg")
+        .args(["query", autostart::RUN_KEY, "/v", app_id])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(target_os = "macos")]
+pub fn set_autostart(app_id: &str, exec: &Path, enabled: bool) -> Result<(), String> {
+    let dir = autostart::launch_agents_dir().ok_or("pasta pessoal não encontrada")?;
+    let path = autostart::launch_agent_path(&dir, app_id);
+    if enabled {
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        return std::fs::write(&path, autostart::launch_agent_plist(app_id, exec)).map_err(|e| e.to_string());
+    }
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn autostart_enabled(app_id: &str) -> bool {
+    autostart::launch_agents_dir().is_some_and(|dir| autostart::launch_agent_path(&dir, app_id).exists())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn set_autostart(app_id: &str, exec: &Path, enabled: bool) -> Result<(), String> {
+    let dir = autostart::autostart_dir().ok_or("pasta de configuração não encontrada")?;
+    autostart::set_enabled(&dir, app_id, exec, enabled).map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn autostart_enabled(app_id: &str) -> bool {
+    autostart::autostart_dir().is_some_and(|dir| autostart::is_enabled(&dir, app_id))
+}
@@ -0,0 +1,41 @@
+// Pausa por bateria fraca: decide a partir do estado informado pelo UPower
+// (dispositivo de exibição, que agrega as baterias do notebook)
+pub const UPOWER_BUS_NAME: &str = "org.freedesktop.UPower";
+pub const UPOWER_DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+pub const UPOWER_DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+// Valor da propriedade State do UPower quando a bateria está descarregando
+pub const UPOWER_STATE_DISCHARGING: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryStatus {
+    pub percentage: f64,
+    pub discharging: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryAction {
+    Pause,  // Abaixo do limite e fora da tomada
+    Resume, // Voltou à tomada depois de uma pausa por bateria
+    Keep,
+}
+
+impl BatteryStatus {
+    pub fn from_upower(present: bool, percentage: f64, state: u32) -> Option<Self> {
+        present.then_some(BatteryStatus {
+            percentage,
+            discharging: state == UPOWER_STATE_DISCHARGING,
+        })
+    }
+}
+
+// Só retoma ao carregar: subir acima do limite descarregando não basta
+pub fn battery_action(status: BatteryStatus, threshold_percent: u8, paused_by_battery: bool) -> BatteryAction {
+    if !paused_by_battery && status.discharging && status.percentage < threshold_percent as f64 {
+        BatteryAction::Pause
+    } else if paused_by_battery && !status.discharging {
+        BatteryAction::Resume
+    } else {
+        BatteryAction::Keep
+    }
+}
@@ -0,0 +1,56 @@
+// Procedência do arquivo baixado: xattrs de origem (como os navegadores) e data do servidor
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+use chrono::DateTime;
+
+// Atributos lidos por gerenciadores de arquivos (ex.: "Origem" nas propriedades)
+pub const ORIGIN_XATTR: &str = "user.xdg.origin.url";
+pub const REFERRER_XATTR: &str = "user.xdg.referrer.url";
+
+// URL sem usuário/senha, que não devem ficar gravados no arquivo
+pub fn origin_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+#[cfg(unix)]
+pub fn write_origin(path: &Path, url: &str, referrer: Option<&str>) -> io::Result<()> {
+    xattr::set(path, ORIGIN_XATTR, origin_url(url).as_bytes())?;
+    if let Some(referrer) = referrer {
+        xattr::set(path, REFERRER_XATTR, origin_url(referrer).as_bytes())?;
+    }
+    Ok(())
+}
+
+// Sem xattrs fora do Unix
+#[cfg(not(unix))]
+pub fn write_origin(_path: &Path, _url: &str, _referrer: Option<&str>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn read_origin(path: &Path) -> Option<String> {
+    let value = xattr::get(path, ORIGIN_XATTR).ok()??;
+    String::from_utf8(value).ok().filter(|url| !url.is_empty())
+}
+
+#[cfg(not(unix))]
+pub fn read_origin(_path: &Path) -> Option<String> {
+    None
+}
+
+// Cabeçalho Last-Modified ("Wed, 21 Oct 2015 07:28:00 GMT")
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    DateTime::parse_from_rfc2822(value.trim()).ok().map(SystemTime::from)
+}
+
+pub fn set_modified(path: &Path, time: SystemTime) -> io::Result<()> {
+    std::fs::File::options().write(true).open(path)?.set_modified(time)
+}
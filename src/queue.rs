@@ -0,0 +1,145 @@
+// Exportação da fila de downloads em andamento, para continuar em outra máquina
+// ou depois de reinstalar. A exportação é uma pasta:
+//
+//   fila.json                 registros (URL, opções, bytes já baixados...)
+//   partes/<id>.part          arquivo parcial
+//   partes/<id>.part.chunks   mapa de chunks do download paralelo
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::engine::chunk_map_path;
+use crate::platform;
+use crate::storage::{new_download_id, record_directory, unique_name, DownloadRecord, DownloadStatus};
+
+pub const QUEUE_FILE: &str = "fila.json";
+pub const PARTS_DIR: &str = "partes";
+// Versão do formato de fila.json
+pub const QUEUE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueueExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub downloads: Vec<DownloadRecord>,
+}
+
+// Downloads que fazem parte da fila (em andamento ou pausados)
+pub fn is_queued(record: &DownloadRecord) -> bool {
+    record.status == DownloadStatus::InProgress
+}
+
+// Nome vindo de fila.json (que pode ter sido editado) como caminho relativo dentro
+// da pasta de downloads: "/", ".", ".." e componentes vazios são descartados
+// e cada parte passa por `safe_file_name` ("C:" vira "C_")
+fn contained_filename(filename: &str) -> String {
+    let parts: Vec<String> = filename
+        .split(['/', '\\'])
+        .filter(|part| !matches!(*part, "" | "." | ".."))
+        .map(platform::safe_file_name)
+        .collect();
+    if parts.is_empty() {
+        "download".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+fn part_path(download_dir: &Path, filename: &str) -> PathBuf {
+    download_dir.join(format!("{}.part", filename))
+}
+
+// Grava a fila em `dest`. Com `include_partial`, os .part vão junto; sem eles os
+// downloads recomeçam do zero. O mapa de chunks é copiado antes do .part: se o
+// download estiver rodando, o mapa fica para trás do arquivo (nunca à frente) e a
+// retomada só baixa de novo um pouco a mais. Retorna quantos downloads foram exportados.
+pub fn export_queue(
+    records: &[DownloadRecord],
+    download_dir: &Path,
+    dest: &Path,
+    include_partial: bool,
+) -> Result<usize, String> {
+    let parts_dir = dest.join(PARTS_DIR);
+    std::fs::create_dir_all(&parts_dir).map_err(|e| format!("Erro ao criar pasta: {}", e))?;
+
+    let mut downloads = Vec::new();
+    for record in records.iter().filter(|record| is_queued(record)) {
+        let mut record = record.clone();
+        record.was_paused = true; // Na outra máquina, só continua quando o usuário pedir
+        let part = part_path(&record_directory(&record, download_dir), &record.filename);
+        let copied = include_partial && part.exists() && {
+            let name = record.id.to_string();
+            let map = chunk_map_path(&part);
+            if map.exists() {
+                let _ = std::fs::copy(&map, parts_dir.join(format!("{}.part.chunks", name)));
+            }
+            std::fs::copy(&part, parts_dir.join(format!("{}.part", name)))
+                .map_err(|e| format!("Erro ao copiar {}: {}", part.display(), e))?;
+            true
+        };
+        if !copied {
+            record.downloaded_bytes = 0;
+        }
+        downloads.push(record);
+    }
+
+    let export = QueueExport { version: QUEUE_VERSION, exported_at: Utc::now(), downloads };
+    let json = serde_json::to_string_pretty(&export).map_err(|e| format!("Erro ao serializar fila: {}", e))?;
+    std::fs::write(dest.join(QUEUE_FILE), json).map_err(|e| format!("Erro ao gravar {}: {}", QUEUE_FILE, e))?;
+    Ok(export.downloads.len())
+}
+
+// Lê a fila exportada em `src` e coloca os .part na pasta de downloads. Os
+// registros voltam pausados, com id e nome novos quando colidem com os existentes.
+pub fn import_queue(src: &Path, download_dir: &Path, existing: &[DownloadRecord]) -> Result<Vec<DownloadRecord>, String> {
+    let contents = std::fs::read_to_string(src.join(QUEUE_FILE))
+        .map_err(|e| format!("Erro ao ler {}: {}", QUEUE_FILE, e))?;
+    let export: QueueExport = serde_json::from_str(&contents).map_err(|e| format!("Fila inválida: {}", e))?;
+    if export.version > QUEUE_VERSION {
+        return Err("Fila exportada por uma versão mais nova do Keepers".to_string());
+    }
+
+    let parts_dir = src.join(PARTS_DIR);
+    let mut imported: Vec<DownloadRecord> = Vec::new();
+    for mut record in export.downloads {
+        let old_id = record.id;
+        let taken_ids = existing.iter().chain(&imported).any(|r| r.id == record.id);
+        if taken_ids {
+            record.id = new_download_id();
+        }
+        let filename = contained_filename(&record.filename);
+        record.filename = unique_name(&filename, |name| {
+            existing.iter().chain(&imported).any(|r| r.filename == name)
+                || download_dir.join(name).exists()
+                || part_path(download_dir, name).exists()
+        });
+        record.status = DownloadStatus::InProgress;
+        record.was_paused = true;
+        record.file_path = None;
+        // A pasta de um modelo é da outra máquina: o download vem para a pasta de downloads
+        record.options.directory = None;
+
+        let source = parts_dir.join(format!("{}.part", old_id));
+        let dest = part_path(download_dir, &record.filename);
+        let restored = source.exists()
+            && dest.parent().is_some_and(|parent| std::fs::create_dir_all(parent).is_ok())
+            && match std::fs::copy(&source, &dest) {
+                Ok(_) => true,
+                Err(e) => {
+                    eprintln!("Erro ao restaurar {}: {}", source.display(), e);
+                    false
+                }
+            };
+        if restored {
+            let map = parts_dir.join(format!("{}.part.chunks", old_id));
+            if map.exists() {
+                if let Err(e) = std::fs::copy(&map, chunk_map_path(&dest)) {
+                    eprintln!("Erro ao restaurar mapa de chunks: {}", e);
+                }
+            }
+        } else {
+            record.downloaded_bytes = 0;
+        }
+        imported.push(record);
+    }
+    Ok(imported)
+}
@@ -0,0 +1,71 @@
+// Arquivos .part sem registro na lista (ex.: downloads.json corrompido ou apagado)
+//
+// A URL vem do atributo de origem gravado no .part quando ele foi criado (se os
+// metadados de procedência estiverem ativos); sem ele, o usuário informa a URL.
+// O registro adotado entra pausado e, ao retomar, continua do tamanho do .part
+// (ou do mapa de chunks, se existir).
+use std::path::{Path, PathBuf};
+use crate::provenance;
+use crate::storage::DownloadRecord;
+
+// Subpastas visitadas abaixo da pasta de downloads
+pub const MAX_SCAN_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanPart {
+    pub part_path: PathBuf,
+    pub filename: String, // Relativo à pasta de downloads, sem ".part"
+    pub size: u64,
+    pub url: Option<String>, // Origem gravada no .part
+}
+
+// `.part` da pasta (e subpastas) que nenhum registro usa, em ordem de nome
+pub fn find_orphan_parts(download_dir: &Path, records: &[DownloadRecord]) -> Vec<OrphanPart> {
+    let mut orphans = Vec::new();
+    scan_dir(download_dir, download_dir, 0, records, &mut orphans);
+    orphans.sort_by(|a, b| a.filename.cmp(&b.filename));
+    orphans
+}
+
+fn scan_dir(root: &Path, dir: &Path, depth: usize, records: &[DownloadRecord], orphans: &mut Vec<OrphanPart>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            // Pastas ocultas (.git, .cache...) não são de downloads
+            if depth < MAX_SCAN_DEPTH && !name.starts_with('.') {
+                scan_dir(root, &path, depth + 1, records, orphans);
+            }
+            continue;
+        }
+        if !file_type.is_file() || name.len() <= ".part".len() || !name.ends_with(".part") {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let filename = relative[..relative.len() - ".part".len()].to_string();
+        if records.iter().any(|record| record.filename == filename) {
+            continue;
+        }
+        let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        let url = provenance::read_origin(&path);
+        orphans.push(OrphanPart { part_path: path, filename, size, url });
+    }
+}
+
+// Registro pausado que retoma o .part a partir de `url`
+pub fn adopt_orphan(orphan: &OrphanPart, url: &str) -> DownloadRecord {
+    DownloadRecord {
+        downloaded_bytes: orphan.size,
+        was_paused: true,
+        ..DownloadRecord::new(url, &orphan.filename)
+    }
+}
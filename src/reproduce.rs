@@ -0,0 +1,86 @@
+// Comandos curl/wget equivalentes a um download
+//
+// Servem para reproduzir fora do app um download problemático: mesma URL,
+// página de origem, opções de TLS, proxy do Tor e arquivo de saída. Headers
+// vindos de scripts e resolvedores não são guardados e ficam de fora.
+use std::path::Path;
+
+use crate::http::TOR_SOCKS_ADDR;
+use crate::storage::DownloadRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandTool {
+    Curl,
+    Wget,
+}
+
+// `ca_bundle` é o certificado CA extra da configuração, se houver
+pub fn download_command(tool: CommandTool, record: &DownloadRecord, output: &Path, ca_bundle: Option<&str>) -> String {
+    let mut args: Vec<String> = Vec::new();
+    let options = &record.options;
+    match tool {
+        CommandTool::Curl => {
+            args.push("curl".into());
+            args.push("--location".into());
+            args.push("--fail".into());
+            if let Some(referer) = &options.referer {
+                args.push("--referer".into());
+                args.push(shell_quote(referer));
+            }
+            if options.accept_invalid_certs {
+                args.push("--insecure".into());
+            }
+            if let Some(path) = ca_bundle {
+                args.push("--cacert".into());
+                args.push(shell_quote(path));
+            }
+            if options.use_tor {
+                args.push("--proxy".into());
+                args.push(format!("socks5h://{}", TOR_SOCKS_ADDR));
+            }
+            args.push("--output".into());
+            args.push(shell_quote(&output.to_string_lossy()));
+        }
+        CommandTool::Wget => {
+            // wget não fala SOCKS: passa pelo torsocks
+            if options.use_tor {
+                args.push("torsocks".into());
+            }
+            args.push("wget".into());
+            if let Some(referer) = &options.referer {
+                args.push(format!("--referer={}", shell_quote(referer)));
+            }
+            if options.accept_invalid_certs {
+                args.push("--no-check-certificate".into());
+            }
+            if let Some(path) = ca_bundle {
+                args.push(format!("--ca-certificate={}", shell_quote(path)));
+            }
+            args.push(format!("--output-document={}", shell_quote(&output.to_string_lossy())));
+        }
+    }
+    args.push(shell_quote(&command_url(record)));
+    args.join(" ")
+}
+
+// s3:// e ipfs:// não existem fora do app: usa o endereço HTTP que respondeu por último
+fn command_url(record: &DownloadRecord) -> String {
+    let is_http = record.url.starts_with("http://") || record.url.starts_with("https://");
+    match &record.response {
+        Some(response) if !is_http && !response.url.is_empty() => response.url.clone(),
+        _ => record.url.clone(),
+    }
+}
+
+// Aspas simples do shell; só deixa sem aspas o que não tem nada especial
+pub fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | ',' | '@' | '=' | '+'));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
@@ -0,0 +1,302 @@
+// Links de compartilhamento de serviços de arquivos: a página que o usuário copia
+// vira o endereço direto do arquivo, e páginas de confirmação (aviso de antivírus
+// do Google Drive) são atravessadas até o download.
+//
+// Cada serviço é um `Resolver`: recebe a URL colada e devolve os endereços diretos
+// e os headers que o servidor exige. Além dos embutidos, resolvedores externos são
+// carregados da pasta de plugins (ver `CommandResolver`).
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use regex::Regex;
+use reqwest::Url;
+use serde::Deserialize;
+use crate::listing::{self, attribute, decode_entities};
+use crate::storage::get_resolvers_dir;
+
+// Endereço de download do Google Drive para arquivos públicos
+pub const DRIVE_DOWNLOAD_URL: &str = "https://drive.usercontent.google.com/download";
+
+// Tempo máximo de um resolvedor externo
+pub const PLUGIN_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolved {
+    pub urls: Vec<String>, // Endereços diretos, em ordem: o primeiro que responder é usado
+    pub headers: Vec<(String, String)>, // Enviados em todas as requisições do download
+}
+
+pub trait Resolver: Send + Sync {
+    fn name(&self) -> &str;
+    // None = a URL não é deste resolvedor
+    fn resolve(&self, url: &str) -> BoxFuture<'static, Option<Result<Resolved, String>>>;
+}
+
+pub type SharedResolver = Arc<dyn Resolver>;
+
+impl std::fmt::Debug for dyn Resolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Resolvedores embutidos
+pub fn builtin_resolvers() -> Vec<SharedResolver> {
+    vec![Arc::new(ShareLinkResolver)]
+}
+
+// Plugins instalados primeiro (podem substituir os embutidos), depois os embutidos
+pub fn installed_resolvers() -> Vec<SharedResolver> {
+    let mut resolvers = load_plugins(&get_resolvers_dir());
+    resolvers.extend(builtin_resolvers());
+    resolvers
+}
+
+// Primeiro resolvedor que reconhece a URL: (nome, resultado)
+pub async fn resolve_url(resolvers: &[SharedResolver], url: &str) -> Option<(String, Result<Resolved, String>)> {
+    for resolver in resolvers {
+        if let Some(result) = resolver.resolve(url).await {
+            let result = result.and_then(|resolved| {
+                if resolved.urls.is_empty() {
+                    Err("nenhum endereço retornado".to_string())
+                } else {
+                    Ok(resolved)
+                }
+            });
+            return Some((resolver.name().to_string(), result));
+        }
+    }
+    None
+}
+
+// Google Drive, Dropbox e GitHub
+pub struct ShareLinkResolver;
+
+impl Resolver for ShareLinkResolver {
+    fn name(&self) -> &str {
+        "Links de compartilhamento"
+    }
+
+    fn resolve(&self, url: &str) -> BoxFuture<'static, Option<Result<Resolved, String>>> {
+        let direct = direct_url(url).map(|url| Ok(Resolved { urls: vec![url], headers: Vec::new() }));
+        async move { direct }.boxed()
+    }
+}
+
+// Manifesto de um plugin (um .json na pasta de resolvedores):
+// {"name": "Meu host", "pattern": "^https://meuhost\\.com/f/", "command": ["python3", "meuhost.py"]}
+#[derive(Debug, Deserialize)]
+struct PluginManifest {
+    name: String,
+    pattern: String,
+    command: Vec<String>,
+}
+
+// Saída esperada do comando: {"urls": ["https://..."], "headers": {"Cookie": "..."}}
+#[derive(Debug, Deserialize)]
+struct PluginOutput {
+    urls: Vec<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+// Resolvedor externo: o comando recebe a URL como último argumento e escreve o
+// resultado em JSON na saída padrão. Qualquer linguagem serve.
+pub struct CommandResolver {
+    name: String,
+    pattern: Regex,
+    command: Vec<String>,
+    dir: PathBuf, // Pasta do manifesto: caminhos relativos do comando partem dela
+}
+
+impl CommandResolver {
+    pub fn from_manifest(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let manifest: PluginManifest = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        if manifest.command.is_empty() {
+            return Err("comando vazio".to_string());
+        }
+        Ok(CommandResolver {
+            name: manifest.name,
+            pattern: Regex::new(&manifest.pattern).map_err(|e| e.to_string())?,
+            command: manifest.command,
+            dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        self.pattern.as_str()
+    }
+}
+
+impl Resolver for CommandResolver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn resolve(&self, url: &str) -> BoxFuture<'static, Option<Result<Resolved, String>>> {
+        if !self.pattern.is_match(url) {
+            return async { None }.boxed();
+        }
+        let program = if self.command[0].contains('/') {
+            self.dir.join(&self.command[0])
+        } else {
+            PathBuf::from(&self.command[0])
+        };
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(&self.command[1..])
+            .arg(url)
+            .current_dir(&self.dir)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        async move {
+            let output = match tokio::time::timeout(Duration::from_secs(PLUGIN_TIMEOUT_SECS), command.output()).await {
+                Ok(Ok(output)) => output,
+                Ok(Err(e)) => return Some(Err(format!("não foi possível executar: {}", e))),
+                Err(_) => return Some(Err(format!("sem resposta em {}s", PLUGIN_TIMEOUT_SECS))),
+            };
+            if !output.status.success() {
+                let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                return Some(Err(if message.is_empty() { output.status.to_string() } else { message }));
+            }
+            let result = serde_json::from_slice::<PluginOutput>(&output.stdout)
+                .map(|output| Resolved { urls: output.urls, headers: output.headers.into_iter().collect() })
+                .map_err(|e| format!("resposta inválida: {}", e));
+            Some(result)
+        }
+        .boxed()
+    }
+}
+
+// Manifestos *.json da pasta, em ordem alfabética; os inválidos são ignorados
+pub fn load_plugins(dir: &Path) -> Vec<SharedResolver> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| match CommandResolver::from_manifest(&path) {
+            Ok(resolver) => Some(Arc::new(resolver) as SharedResolver),
+            Err(e) => {
+                eprintln!("Resolvedor ignorado ({}): {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Endereço direto de um link de compartilhamento conhecido (None = já é direto)
+pub fn direct_url(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.trim_start_matches("www.");
+    match host {
+        "drive.google.com" | "docs.google.com" => drive_file_id(&parsed).map(|id| drive_download_url(&id)),
+        "dropbox.com" => dropbox_direct(parsed),
+        "github.com" => github_raw(&parsed),
+        _ => None,
+    }
+}
+
+// /file/d/<id>/view, /open?id=<id> e /uc?id=<id>
+fn drive_file_id(url: &Url) -> Option<String> {
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    if let Some(index) = segments.windows(2).position(|pair| pair == ["file", "d"]) {
+        return segments.get(index + 2).filter(|id| !id.is_empty()).map(|id| id.to_string());
+    }
+    if matches!(segments.last(), Some(&"open") | Some(&"uc")) {
+        return url.query_pairs().find(|(name, _)| name == "id").map(|(_, id)| id.into_owned());
+    }
+    None
+}
+
+pub fn drive_download_url(id: &str) -> String {
+    format!("{}?id={}&export=download", DRIVE_DOWNLOAD_URL, id)
+}
+
+// ?dl=0 abre a pré-visualização; dl=1 entrega o arquivo
+fn dropbox_direct(mut url: Url) -> Option<String> {
+    if url.query_pairs().any(|(name, value)| name == "dl" && value == "1") {
+        return None;
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| name != "dl")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs).append_pair("dl", "1");
+    Some(url.to_string())
+}
+
+// github.com/<dono>/<repo>/blob/<ref>/<caminho> -> raw.githubusercontent.com/<dono>/<repo>/<ref>/<caminho>
+fn github_raw(url: &Url) -> Option<String> {
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    match segments.as_slice() {
+        [owner, repo, "blob", rest @ ..] if rest.len() >= 2 => Some(format!(
+            "https://raw.githubusercontent.com/{}/{}/{}",
+            owner,
+            repo,
+            rest.join("/")
+        )),
+        _ => None,
+    }
+}
+
+// Próximo endereço a partir de uma página recebida no lugar do arquivo: o
+// formulário de confirmação do Google Drive ou um redirecionamento comum
+pub fn page_target(html: &str, page: &str) -> Option<String> {
+    drive_confirm_url(html, page).or_else(|| listing::redirect_target(html, page))
+}
+
+// "Não foi possível verificar se há vírus": o formulário download-form leva os
+// campos ocultos (id, export, confirm, uuid) que liberam o download. Páginas
+// antigas usam um link com confirm=<token>.
+pub fn drive_confirm_url(html: &str, page: &str) -> Option<String> {
+    let base = Url::parse(page).ok()?;
+    let lower = html.to_ascii_lowercase();
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<form").map(|i| pos + i) {
+        let tag_end = lower[start..].find('>').map(|i| start + i)?;
+        let form_end = lower[tag_end..].find("</form").map(|i| tag_end + i).unwrap_or(html.len());
+        pos = form_end;
+        let tag = &html[start..tag_end];
+        let action = attribute(tag, "action").map(decode_entities).unwrap_or_default();
+        let is_confirm = attribute(tag, "id") == Some("download-form") || action.contains("/download");
+        if !is_confirm {
+            continue;
+        }
+
+        let mut url = base.join(&action).ok()?;
+        let mut fields = Vec::new();
+        let mut input = tag_end;
+        while let Some(found) = lower[input..form_end].find("<input").map(|i| input + i) {
+            let end = lower[found..].find('>').map(|i| found + i).unwrap_or(form_end);
+            input = end;
+            let tag = &html[found..end];
+            if let (Some(name), Some(value)) = (attribute(tag, "name"), attribute(tag, "value")) {
+                fields.push((decode_entities(name), decode_entities(value)));
+            }
+        }
+        if fields.iter().any(|(name, _)| name == "id") {
+            url.query_pairs_mut().clear().extend_pairs(fields);
+            return Some(url.to_string());
+        }
+    }
+
+    listing::extract_links(html, page)
+        .into_iter()
+        .map(|link| link.url)
+        .find(|url| url.contains("confirm=") && url.contains("id="))
+}
@@ -0,0 +1,242 @@
+// Objetos S3 (`s3://bucket/chave`): a URL vira o endpoint HTTPS do bucket e cada
+// requisição é assinada com AWS Signature V4. URLs pré-assinadas são HTTPS comuns
+// e não passam por aqui.
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use sha2::{Digest, Sha256};
+use crate::http::{HttpBackend, HttpError, HttpErrorKind, HttpMethod, HttpRequest, HttpResponse, SharedBackend};
+use crate::storage::S3Settings;
+
+pub const DEFAULT_REGION: &str = "us-east-1";
+// O corpo das requisições (GET/HEAD) é vazio; não é preciso assinar o hash do conteúdo
+pub const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct S3Config {
+    pub credentials: Option<S3Credentials>,
+    pub region: String,
+    pub endpoint: Option<String>, // Serviços compatíveis (MinIO, R2...): endereço com o bucket no caminho
+}
+
+impl S3Config {
+    // Configuração do app; campos vazios caem nas variáveis AWS_* do ambiente
+    pub fn from_settings(settings: &S3Settings) -> Self {
+        let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let or_env = |value: &str, name: &str| {
+            if value.is_empty() {
+                env(name)
+            } else {
+                Some(value.to_string())
+            }
+        };
+
+        let credentials = match (
+            or_env(&settings.access_key_id, "AWS_ACCESS_KEY_ID"),
+            or_env(&settings.secret_access_key, "AWS_SECRET_ACCESS_KEY"),
+        ) {
+            (Some(access_key_id), Some(secret_access_key)) => Some(S3Credentials {
+                access_key_id,
+                secret_access_key,
+                session_token: env("AWS_SESSION_TOKEN").filter(|_| settings.access_key_id.is_empty()),
+            }),
+            _ => None,
+        };
+        S3Config {
+            credentials,
+            region: or_env(&settings.region, "AWS_REGION").unwrap_or_else(|| DEFAULT_REGION.to_string()),
+            endpoint: settings.endpoint.clone().filter(|endpoint| !endpoint.is_empty()),
+        }
+    }
+}
+
+pub fn is_s3_url(url: &str) -> bool {
+    url.starts_with("s3://")
+}
+
+// "s3://bucket/pasta/arquivo.bin" -> ("bucket", "pasta/arquivo.bin")
+pub fn parse_s3_url(url: &str) -> Option<(&str, &str)> {
+    let (bucket, key) = url.strip_prefix("s3://")?.split_once('/')?;
+    if bucket.is_empty() || key.is_empty() {
+        None
+    } else {
+        Some((bucket, key))
+    }
+}
+
+// Endereço HTTPS do objeto. Buckets com ponto não combinam com o certificado
+// *.s3.amazonaws.com, então usam o estilo com o bucket no caminho.
+pub fn object_url(bucket: &str, key: &str, config: &S3Config) -> String {
+    let key = uri_encode(key, false);
+    match &config.endpoint {
+        Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+        None if bucket.contains('.') => format!("https://s3.{}.amazonaws.com/{}/{}", config.region, bucket, key),
+        None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, config.region, key),
+    }
+}
+
+// Codificação da especificação SigV4: tudo menos A-Z a-z 0-9 - _ . ~ (e "/" nos caminhos)
+pub fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+// Headers a acrescentar à requisição (x-amz-* e Authorization). Todos os headers
+// já presentes (Range, If-None-Match...) entram na assinatura.
+pub fn sign_request(
+    method: &str,
+    url: &reqwest::Url,
+    headers: &[(String, String)],
+    credentials: &S3Credentials,
+    region: &str,
+    payload_hash: &str,
+    time: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = time.format("%Y%m%d").to_string();
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut added = vec![
+        ("x-amz-content-sha256".to_string(), payload_hash.to_string()),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        added.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+
+    let mut signed: Vec<(String, String)> = headers
+        .iter()
+        .chain(added.iter())
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string()))
+        .chain(std::iter::once(("host".to_string(), host)))
+        .collect();
+    signed.sort();
+    let canonical_headers: String = signed.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = signed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name, true), uri_encode(&value, true)))
+        .collect();
+    query.sort();
+    let canonical_query = query.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        url.path(),
+        canonical_query,
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let secret = format!("AWS4{}", credentials.secret_access_key);
+    let key = hmac_sha256(secret.as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, b"s3");
+    let key = hmac_sha256(&key, b"aws4_request");
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    added.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    added
+}
+
+// Traduz e assina requisições s3://; as demais seguem direto para o backend interno
+pub struct S3Backend {
+    inner: SharedBackend,
+    config: S3Config,
+}
+
+impl S3Backend {
+    pub fn new(inner: SharedBackend, config: S3Config) -> Self {
+        S3Backend { inner, config }
+    }
+}
+
+impl HttpBackend for S3Backend {
+    fn send(&self, mut request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        let Some((bucket, key)) = parse_s3_url(&request.url) else {
+            return self.inner.send(request);
+        };
+        let Some(credentials) = &self.config.credentials else {
+            return async { Err(HttpError::new(HttpErrorKind::Other, "Credenciais S3 não configuradas")) }.boxed();
+        };
+
+        let url = object_url(bucket, key, &self.config);
+        let parsed = match reqwest::Url::parse(&url) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let error = HttpError::new(HttpErrorKind::Other, &format!("Endereço S3 inválido: {}", e));
+                return async move { Err(error) }.boxed();
+            }
+        };
+        let method = match request.method {
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Get => "GET",
+        };
+        let signed = sign_request(
+            method,
+            &parsed,
+            &request.headers,
+            credentials,
+            &self.config.region,
+            UNSIGNED_PAYLOAD,
+            Utc::now(),
+        );
+        request.url = url;
+        request.headers.extend(signed);
+        self.inner.send(request)
+    }
+}
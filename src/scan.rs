@@ -0,0 +1,39 @@
+// Verificação dos arquivos concluídos por um antivírus externo (ex.: clamscan)
+//
+// O comando configurado ("clamscan --no-summary") recebe o caminho do arquivo
+// como último argumento. Vale a convenção do clamscan: saída 0 = limpo,
+// 1 = infectado; qualquer outra é erro e o arquivo fica sem marca.
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use crate::storage::ScanResult;
+
+// Arquivos grandes demoram; passado o prazo o antivírus é encerrado
+pub const SCAN_TIMEOUT_SECS: u64 = 600;
+
+// "clamscan --no-summary" -> ["clamscan", "--no-summary"]; vazio = sem verificação
+pub fn scanner_command(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_string).collect()
+}
+
+pub async fn scan_file(command: &[String], path: &Path) -> Result<ScanResult, String> {
+    let Some((program, args)) = command.split_first() else {
+        return Err("nenhum antivírus configurado".to_string());
+    };
+    let mut child = tokio::process::Command::new(program);
+    child.args(args).arg(path).stdin(Stdio::null()).kill_on_drop(true);
+
+    let output = match tokio::time::timeout(Duration::from_secs(SCAN_TIMEOUT_SECS), child.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("não foi possível executar {}: {}", program, e)),
+        Err(_) => return Err(format!("sem resposta em {}s", SCAN_TIMEOUT_SECS)),
+    };
+    match output.status.code() {
+        Some(0) => Ok(ScanResult::Clean),
+        Some(1) => Ok(ScanResult::Infected),
+        _ => {
+            let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(if message.is_empty() { output.status.to_string() } else { message })
+        }
+    }
+}
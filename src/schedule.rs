@@ -0,0 +1,208 @@
+// Downloads recorrentes: expressões no estilo cron, próxima execução e versões mantidas
+//
+// Formato: "minuto hora dia-do-mês mês dia-da-semana" (horário local), com `*`,
+// listas (`1,15`), intervalos (`1-5`) e passos (`*/6`). Domingo é 0 (ou 7).
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone, Timelike, Utc};
+use crate::storage::{unique_name, VersionNaming};
+
+// Versões mantidas por padrão em um download recorrente
+pub const DEFAULT_KEEP_VERSIONS: usize = 5;
+
+// Limite de busca da próxima execução (expressões impossíveis, ex.: 31 de fevereiro)
+const MAX_SEARCH_DAYS: i64 = 366 * 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,  // bit n = minuto n
+    hours: u64,
+    days: u64,     // 1..=31
+    months: u64,   // 1..=12
+    weekdays: u64, // 0..=6 (domingo = 0)
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Esperados 5 campos (min hora dia mês semana), encontrados {}", fields.len()));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        // 7 também é domingo
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+
+        Ok(CronSchedule {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    // Próximo horário (estritamente depois de `after`) que satisfaz a expressão
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(MAX_SEARCH_DAYS);
+        let mut candidate = start;
+
+        while candidate < limit {
+            if !self.day_matches(&candidate) {
+                // Pula direto para o início do dia seguinte
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if has(self.hours, candidate.hour()) && has(self.minutes, candidate.minute()) {
+                // Horários inexistentes (início do horário de verão) são ignorados
+                if let Some(local) = Local.from_local_datetime(&candidate).earliest() {
+                    return Some(local);
+                }
+            }
+            candidate += Duration::minutes(1);
+        }
+        None
+    }
+
+    fn day_matches(&self, date: &NaiveDateTime) -> bool {
+        if !has(self.months, date.month()) {
+            return false;
+        }
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        // Como no cron: com os dois campos restritos, basta um deles casar
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("Passo inválido: {}", part))?;
+                if step == 0 {
+                    return Err(format!("Passo inválido: {}", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (parse_value(a, min, max)?, parse_value(b, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // "5/10" significa de 5 até o máximo, de 10 em 10
+            (value, if step > 1 { max } else { value })
+        };
+        if start > end {
+            return Err(format!("Intervalo inválido: {}", part));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(n) if (min..=max).contains(&n) => Ok(n),
+        _ => Err(format!("Valor fora do intervalo {}-{}: {}", min, max, value)),
+    }
+}
+
+// Próxima execução em UTC (None se a expressão for inválida ou nunca ocorrer)
+pub fn next_run(cron: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let schedule = CronSchedule::parse(cron).ok()?;
+    schedule
+        .next_after(after.with_timezone(&Local))
+        .map(|next| next.with_timezone(&Utc))
+}
+
+// Todo dia no horário informado
+pub fn daily_cron(at: DateTime<Local>) -> String {
+    format!("{} {} * * *", at.minute(), at.hour())
+}
+
+// Toda semana no mesmo dia e horário
+pub fn weekly_cron(at: DateTime<Local>) -> String {
+    format!("{} {} * * {}", at.minute(), at.hour(), at.weekday().num_days_from_sunday())
+}
+
+// "dados.csv" -> "dados-20261018-030000.csv"
+pub fn versioned_filename(filename: &str, at: DateTime<Local>) -> String {
+    suffixed_filename(filename, &at.format("%Y%m%d-%H%M%S").to_string())
+}
+
+// "dados.csv" -> "dados-3.csv"
+pub fn numbered_filename(filename: &str, number: usize) -> String {
+    suffixed_filename(filename, &number.to_string())
+}
+
+fn suffixed_filename(filename: &str, suffix: &str) -> String {
+    match filename.rfind('.') {
+        Some(dot_pos) if dot_pos > 0 => format!("{}-{}{}", &filename[..dot_pos], suffix, &filename[dot_pos..]),
+        _ => format!("{}-{}", filename, suffix),
+    }
+}
+
+// Onde gravar a versão nova de `path`: o próprio caminho (substituir), com a
+// data e hora ou com o primeiro contador que ainda não existe na pasta
+pub fn version_path(path: &Path, naming: VersionNaming, at: DateTime<Local>) -> PathBuf {
+    let Some(name) = path.file_name().map(|name| name.to_string_lossy().to_string()) else {
+        return path.to_path_buf();
+    };
+    let exists = |candidate: &str| path.with_file_name(candidate).exists();
+    let name = match naming {
+        VersionNaming::Overwrite => name,
+        // Duas versões no mesmo segundo ganham "(2)"
+        VersionNaming::Timestamp => unique_name(&versioned_filename(&name, at), exists),
+        VersionNaming::Counter => (1..).map(|number| numbered_filename(&name, number)).find(|candidate| !exists(candidate)).unwrap_or(name),
+    };
+    path.with_file_name(name)
+}
+
+// Registra uma nova versão e devolve as que passaram do limite (mais antigas primeiro)
+pub fn push_version(versions: &mut Vec<String>, path: String, keep: usize) -> Vec<String> {
+    versions.retain(|v| *v != path);
+    versions.push(path);
+    let excess = versions.len().saturating_sub(keep.max(1));
+    versions.drain(..excess).collect()
+}
+
+// Próxima ocorrência de "18:00" (hoje, ou amanhã se o horário já passou)
+pub fn next_time_of_day(text: &str, after: DateTime<Local>) -> Option<DateTime<Local>> {
+    let (hour, minute) = text.trim().split_once([':', 'h'])?;
+    let hour: u32 = hour.trim().parse().ok().filter(|h| *h < 24)?;
+    let minute: u32 = if minute.trim().is_empty() { 0 } else { minute.trim().parse().ok().filter(|m| *m < 60)? };
+    CronSchedule::parse(&format!("{} {} * * *", minute, hour)).ok()?.next_after(after)
+}
+
+// Tempo restante de uma pausa: "1:02:03" ou "59:30"
+pub fn format_countdown(remaining: Duration) -> String {
+    let secs = remaining.num_seconds().max(0);
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
@@ -0,0 +1,228 @@
+// Scripts Rhai do usuário (*.rhai na pasta de scripts) chamados em três momentos:
+//
+//   fn url_added(url)            -> nova URL (ou nada para manter)
+//   fn before_request(url)       -> #{ url: "...", headers: #{ "Cookie": "..." } } (campos opcionais)
+//   fn completed(path, url)      -> pós-processamento do arquivo baixado
+//
+// Os scripts rodam em ordem alfabética e cada um recebe o resultado do anterior.
+// Erros são só registrados: um script quebrado não impede o download.
+//
+// url_added roda na thread da interface: ali (e em before_request, que segura o
+// início do download) os programas chamados com run() dividem RUN_TIMEOUT_SECS
+// por evento, e o que passar disso é encerrado. Em completed eles rodam até o fim
+// (descompactar pode demorar).
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, NativeCallContext, Scope, AST};
+use crate::storage::get_scripts_dir;
+
+// Limite de operações por chamada (evita travar com laços infinitos)
+pub const MAX_SCRIPT_OPERATIONS: u64 = 1_000_000;
+
+// Tempo total de run() num evento url_added ou before_request (todos os scripts juntos)
+pub const RUN_TIMEOUT_SECS: u64 = 5;
+
+pub struct Hooks {
+    engine: Engine,
+    scripts: Vec<(String, AST)>, // (nome do arquivo, script compilado)
+}
+
+pub type SharedHooks = Arc<Hooks>;
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.scripts.iter().map(|(name, _)| name)).finish()
+    }
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Hooks { engine: new_engine(), scripts: Vec::new() }
+    }
+}
+
+// Funções disponíveis para os scripts além da biblioteca padrão do Rhai
+fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+    engine.on_print(|text| eprintln!("[script] {}", text));
+    // run("unzip", ["-o", path]) -> código de saída (-1 se não executou ou passou do limite)
+    engine.register_fn("run", |context: NativeCallContext, program: &str, args: Array| -> i64 {
+        let args: Vec<String> = args.into_iter().map(|arg| arg.to_string()).collect();
+        // O prazo do evento vem na tag da chamada (ver `Hooks::call`)
+        let deadline = context.tag().and_then(|tag| tag.clone().try_cast::<Instant>());
+        run_program(program, &args, deadline)
+    });
+    engine.register_fn("file_exists", |path: &str| Path::new(path).exists());
+    engine.register_fn("rename_file", |from: &str, to: &str| std::fs::rename(from, to).is_ok());
+    engine
+}
+
+fn run_program(program: &str, args: &[String], deadline: Option<Instant>) -> i64 {
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        eprintln!("[script] {} não executado: o evento já passou de {} s", program, RUN_TIMEOUT_SECS);
+        return -1;
+    }
+    let mut child = match Command::new(program).args(args).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("[script] Erro ao executar {}: {}", program, e);
+            return -1;
+        }
+    };
+    loop {
+        let status = match deadline {
+            None => child.wait().map(Some),
+            Some(_) => child.try_wait(),
+        };
+        match status {
+            Ok(Some(status)) => return status.code().unwrap_or(-1) as i64,
+            Ok(None) if deadline.is_some_and(|deadline| Instant::now() < deadline) => std::thread::sleep(Duration::from_millis(50)),
+            Ok(None) => {
+                eprintln!("[script] {} passou do prazo de {} s do evento e foi encerrado", program, RUN_TIMEOUT_SECS);
+                let _ = child.kill();
+                let _ = child.wait();
+                return -1;
+            }
+            Err(e) => {
+                eprintln!("[script] Erro ao esperar {}: {}", program, e);
+                return -1;
+            }
+        }
+    }
+}
+
+impl Hooks {
+    // Compila os scripts (nome, código); os que não compilam ficam de fora
+    pub fn from_sources(sources: Vec<(String, String)>) -> Self {
+        let engine = new_engine();
+        let scripts = sources
+            .into_iter()
+            .filter_map(|(name, source)| match engine.compile(&source) {
+                Ok(ast) => Some((name, ast)),
+                Err(e) => {
+                    eprintln!("Script ignorado ({}): {}", name, e);
+                    None
+                }
+            })
+            .collect();
+        Hooks { engine, scripts }
+    }
+
+    // *.rhai da pasta, em ordem alfabética
+    pub fn load(dir: &Path) -> Self {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Hooks::default();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        paths.sort();
+        let sources = paths
+            .into_iter()
+            .filter_map(|path| {
+                let name = path.file_name()?.to_string_lossy().to_string();
+                match std::fs::read_to_string(&path) {
+                    Ok(source) => Some((name, source)),
+                    Err(e) => {
+                        eprintln!("Erro ao ler script {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Hooks::from_sources(sources)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.scripts.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    // Chama `function` no script, se ele a define com esse número de argumentos.
+    // Com `deadline`, os programas chamados com run() são encerrados nesse instante
+    fn call(&self, name: &str, ast: &AST, function: &str, args: Vec<Dynamic>, deadline: Option<Instant>) -> Option<Dynamic> {
+        if !ast.iter_functions().any(|def| def.name == function && def.params.len() == args.len()) {
+            return None;
+        }
+        let mut options = CallFnOptions::new().eval_ast(false);
+        if let Some(deadline) = deadline {
+            options = options.with_tag(deadline);
+        }
+        match self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, function, args) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                eprintln!("Erro no script {} ({}): {}", name, function, e);
+                None
+            }
+        }
+    }
+
+    // Evento "url-added": URL digitada/colada antes de virar um download
+    pub fn url_added(&self, url: &str) -> String {
+        let deadline = Instant::now() + Duration::from_secs(RUN_TIMEOUT_SECS);
+        let mut current = url.to_string();
+        for (name, ast) in &self.scripts {
+            let Some(result) = self.call(name, ast, "url_added", vec![Dynamic::from(current.clone())], Some(deadline)) else {
+                continue;
+            };
+            if result.is_unit() {
+                continue;
+            }
+            match result.into_string() {
+                Ok(url) if !url.trim().is_empty() => current = url.trim().to_string(),
+                _ => eprintln!("Script {}: url_added deve retornar uma string", name),
+            }
+        }
+        current
+    }
+
+    // Evento "before-request": URL final e headers extras antes da primeira requisição
+    pub fn before_request(&self, url: &str) -> (String, Vec<(String, String)>) {
+        let mut current = url.to_string();
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(RUN_TIMEOUT_SECS);
+        for (name, ast) in &self.scripts {
+            let Some(result) = self.call(name, ast, "before_request", vec![Dynamic::from(current.clone())], Some(deadline)) else {
+                continue;
+            };
+            if result.is_unit() {
+                continue;
+            }
+            let Some(map) = result.try_cast::<Map>() else {
+                eprintln!("Script {}: before_request deve retornar um mapa #{{ url, headers }}", name);
+                continue;
+            };
+            if let Some(url) = map.get("url").and_then(|url| url.clone().into_string().ok()) {
+                current = url;
+            }
+            if let Some(extra) = map.get("headers").and_then(|extra| extra.clone().try_cast::<Map>()) {
+                for (header, value) in extra {
+                    headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&header));
+                    headers.push((header.to_string(), value.to_string()));
+                }
+            }
+        }
+        (current, headers)
+    }
+
+    // Evento "completed": arquivo já no lugar final
+    pub fn completed(&self, path: &Path, url: &str) {
+        let path = path.to_string_lossy().to_string();
+        for (name, ast) in &self.scripts {
+            self.call(name, ast, "completed", vec![Dynamic::from(path.clone()), Dynamic::from(url.to_string())], None);
+        }
+    }
+}
+
+// Scripts instalados na pasta do usuário (relidos a cada uso: editar não exige reiniciar)
+pub fn installed_hooks() -> Hooks {
+    Hooks::load(&get_scripts_dir())
+}
@@ -0,0 +1,78 @@
+// Limite de downloads simultâneos: os que passam do limite esperam numa fila
+//
+// Cada download entra na fila antes de conectar e só começa quando há vaga e ele
+// é o primeiro da fila. A vaga é devolvida quando o download termina (concluído,
+// com erro ou cancelado); um download pausado continua com a sua vaga.
+use std::sync::Mutex;
+use uuid::Uuid;
+
+pub const MAX_ACTIVE_DOWNLOADS: usize = 20; // Maior limite aceito na configuração
+
+#[derive(Debug, Default)]
+pub struct DownloadSlots {
+    state: Mutex<SlotsState>,
+}
+
+#[derive(Debug, Default)]
+struct SlotsState {
+    limit: Option<usize>, // None = sem limite
+    active: Vec<Uuid>,
+    waiting: Vec<Uuid>, // Ordem de chegada
+}
+
+impl DownloadSlots {
+    pub fn new(limit: Option<usize>) -> Self {
+        DownloadSlots {
+            state: Mutex::new(SlotsState { limit: limit.filter(|limit| *limit > 0), ..SlotsState::default() }),
+        }
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.state.lock().ok().and_then(|state| state.limit)
+    }
+
+    // Vale na próxima consulta da fila; downloads já ativos não são interrompidos
+    pub fn set_limit(&self, limit: Option<usize>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.limit = limit.filter(|limit| *limit > 0);
+        }
+    }
+
+    // Pede uma vaga: true se o download pode começar agora; senão ele fica na fila
+    pub fn try_acquire(&self, id: Uuid) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return true;
+        };
+        if state.active.contains(&id) {
+            return true;
+        }
+        if !state.waiting.contains(&id) {
+            state.waiting.push(id);
+        }
+        let free = state.limit.is_none_or(|limit| state.active.len() < limit);
+        if free && state.waiting.first() == Some(&id) {
+            state.waiting.remove(0);
+            state.active.push(id);
+            return true;
+        }
+        false
+    }
+
+    // Posição na fila (1 = o próximo a começar); None fora da fila
+    pub fn position(&self, id: Uuid) -> Option<usize> {
+        let state = self.state.lock().ok()?;
+        state.waiting.iter().position(|waiting| *waiting == id).map(|index| index + 1)
+    }
+
+    // Devolve a vaga (ou sai da fila)
+    pub fn release(&self, id: Uuid) {
+        if let Ok(mut state) = self.state.lock() {
+            state.active.retain(|active| *active != id);
+            state.waiting.retain(|waiting| *waiting != id);
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.state.lock().map(|state| state.active.len()).unwrap_or(0)
+    }
+}
@@ -0,0 +1,90 @@
+// Sugestões do diálogo de adicionar a partir do histórico
+//
+// Ao colar a URL de um servidor já usado, o download mais recente dele empresta
+// a pasta, a subpasta (categoria de uma importação ou estrutura de um índice),
+// o perfil de rede (headers e credenciais), as conexões e o limite. Como nos
+// modelos, só é preenchido o que ainda não foi escolhido para o download.
+use crate::engine::format_speed;
+use crate::host_stats::host_of;
+use crate::storage::{DownloadOptions, DownloadRecord};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Suggestion {
+    pub host: String,
+    pub directory: Option<String>,
+    pub subfolder: Option<String>,
+    pub network_profile: Option<String>,
+    pub max_connections: Option<usize>,
+    pub speed_limit: Option<u64>,
+}
+
+impl Suggestion {
+    fn from_options(host: String, options: &DownloadOptions) -> Self {
+        let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+        Suggestion {
+            host,
+            directory: text(&options.directory),
+            subfolder: text(&options.subfolder),
+            network_profile: text(&options.network_profile),
+            max_connections: options.max_connections.filter(|connections| *connections > 0),
+            speed_limit: options.speed_limit.filter(|limit| *limit > 0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directory.is_none()
+            && self.subfolder.is_none()
+            && self.network_profile.is_none()
+            && self.max_connections.is_none()
+            && self.speed_limit.is_none()
+    }
+
+    // "pasta /dados/isos · subpasta linux · perfil Empresa · 8 conexões"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(directory) = &self.directory {
+            parts.push(format!("pasta {}", directory));
+        }
+        if let Some(subfolder) = &self.subfolder {
+            parts.push(format!("subpasta {}", subfolder));
+        }
+        if let Some(profile) = &self.network_profile {
+            parts.push(format!("perfil {}", profile));
+        }
+        if let Some(connections) = self.max_connections {
+            parts.push(format!("{} conexões", connections));
+        }
+        if let Some(limit) = self.speed_limit {
+            parts.push(format!("limite de {}", format_speed(limit as f64)));
+        }
+        parts.join(" · ")
+    }
+}
+
+// Opções do download mais recente do mesmo servidor; None se não houver nada a sugerir
+pub fn suggest_for(records: &[DownloadRecord], url: &str) -> Option<Suggestion> {
+    let host = host_of(url)?;
+    let record = records
+        .iter()
+        .filter(|record| !record.options.private && host_of(&record.url).as_deref() == Some(host.as_str()))
+        .max_by_key(|record| record.date_added)?;
+    Some(Suggestion::from_options(host, &record.options)).filter(|suggestion| !suggestion.is_empty())
+}
+
+pub fn apply_suggestion(suggestion: &Suggestion, options: &mut DownloadOptions) {
+    if options.directory.is_none() {
+        options.directory = suggestion.directory.clone();
+    }
+    if options.subfolder.is_none() {
+        options.subfolder = suggestion.subfolder.clone();
+    }
+    if options.network_profile.is_none() {
+        options.network_profile = suggestion.network_profile.clone();
+    }
+    if options.max_connections.is_none() {
+        options.max_connections = suggestion.max_connections;
+    }
+    if options.speed_limit.is_none() {
+        options.speed_limit = suggestion.speed_limit;
+    }
+}
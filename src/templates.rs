@@ -0,0 +1,57 @@
+// Modelos de download: opções padrão para URLs que casam com um padrão
+//
+// "*.example.com → /data/example, 8 conexões, 5 MB/s". Padrões sem "://" valem
+// para o servidor da URL; com "://", para a URL inteira. `*` casa qualquer
+// sequência e `?` um caractere, sem diferenciar maiúsculas. O primeiro modelo
+// da lista que casar é aplicado ao adicionar; opções já escolhidas no diálogo ficam.
+use regex::RegexBuilder;
+use crate::host_stats::host_of;
+use crate::storage::{DownloadOptions, DownloadTemplate};
+
+// Padrão vazio ou que não vira expressão válida não casa com nada
+pub fn pattern_matches(pattern: &str, url: &str) -> bool {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return false;
+    }
+    let subject = if pattern.contains("://") {
+        url.to_string()
+    } else {
+        match host_of(url) {
+            Some(host) => host,
+            None => return false,
+        }
+    };
+    let source = regex::escape(pattern).replace(r"\*", ".*").replace(r"\?", ".");
+    RegexBuilder::new(&format!("^{}$", source))
+        .case_insensitive(true)
+        .build()
+        .map(|regex| regex.is_match(&subject))
+        .unwrap_or(false)
+}
+
+pub fn matching_template<'a>(templates: &'a [DownloadTemplate], url: &str) -> Option<&'a DownloadTemplate> {
+    templates.iter().find(|template| pattern_matches(&template.pattern, url))
+}
+
+// Preenche só o que ainda não foi escolhido para o download
+pub fn apply_template(template: &DownloadTemplate, options: &mut DownloadOptions) {
+    let directory = template.directory.trim();
+    if options.directory.is_none() && !directory.is_empty() {
+        options.directory = Some(directory.to_string());
+    }
+    if options.max_connections.is_none() {
+        options.max_connections = template.max_connections.filter(|connections| *connections > 0);
+    }
+    if options.speed_limit.is_none() {
+        options.speed_limit = template.speed_limit.filter(|limit| *limit > 0);
+    }
+    options.template = Some(template.pattern.clone());
+}
+
+// Aplica o primeiro modelo que casa com `url`; devolve o padrão dele
+pub fn apply_matching_template(templates: &[DownloadTemplate], url: &str, options: &mut DownloadOptions) -> Option<String> {
+    let template = matching_template(templates, url)?;
+    apply_template(template, options);
+    Some(template.pattern.clone())
+}
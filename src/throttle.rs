@@ -0,0 +1,68 @@
+// Limite de velocidade global, dividido entre todos os downloads (balde de fichas)
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct SpeedLimiter {
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    limit: Option<u64>, // Bytes/s (None = ilimitado)
+    available: f64,     // Fichas disponíveis; negativo = bytes já recebidos além do limite
+    last_refill: Instant,
+}
+
+impl SpeedLimiter {
+    pub fn new(limit: Option<u64>) -> Self {
+        SpeedLimiter {
+            state: Mutex::new(LimiterState {
+                limit: limit.filter(|limit| *limit > 0),
+                available: 0.0,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.state.lock().ok().and_then(|state| state.limit)
+    }
+
+    // Vale para os próximos bytes de todos os downloads em andamento
+    pub fn set_limit(&self, limit: Option<u64>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.limit = limit.filter(|limit| *limit > 0);
+            state.available = 0.0;
+            state.last_refill = Instant::now();
+        }
+    }
+
+    // Desconta `bytes` recebidos e diz quanto esperar para manter a média no limite.
+    // Acumula no máximo um segundo de folga, para não liberar rajadas após uma pausa.
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        let Ok(mut state) = self.state.lock() else {
+            return Duration::ZERO;
+        };
+        let Some(limit) = state.limit else {
+            return Duration::ZERO;
+        };
+        let limit = limit as f64;
+        let now = Instant::now();
+        let refill = now.duration_since(state.last_refill).as_secs_f64() * limit;
+        state.available = (state.available + refill).min(limit) - bytes as f64;
+        state.last_refill = now;
+        if state.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.available / limit)
+        }
+    }
+
+    pub async fn throttle(&self, bytes: u64) {
+        let delay = self.reserve(bytes);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
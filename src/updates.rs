@@ -0,0 +1,106 @@
+// Aviso de versão nova (opcional)
+//
+// A AppImage não tem loja que a atualize: com a opção ligada, ao abrir o app a
+// última release publicada no GitHub é consultada e, se for mais nova que esta,
+// um banner leva às novidades. Nada é baixado nem instalado sozinho.
+use std::sync::{Arc, Mutex};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use crate::engine::backend_for_config;
+use crate::http::{send_request, HttpRequest, SharedBackend};
+use crate::storage::AppConfig;
+
+// Página do projeto (a mesma do appdata e do "Sobre"), vinda do Cargo.toml
+pub const PROJECT_URL: &str = env!("CARGO_PKG_REPOSITORY");
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+// A resposta da API traz as notas da versão; mais que isso não é uma release
+const MAX_RELEASE_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub version: String, // Sem o "v" da tag
+    pub url: String,     // Página da release, com o changelog
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+// "https://github.com/dono/repo" -> API da última release desse repositório
+pub fn releases_api(project_url: &str) -> Option<String> {
+    let repo = project_url.trim_end_matches('/').strip_prefix("https://github.com/")?;
+    (repo.split('/').count() == 2).then(|| format!("https://api.github.com/repos/{}/releases/latest", repo))
+}
+
+// "v1.10.0" -> [1, 10, 0]; sufixos como "-beta" são ignorados
+pub fn parse_version(text: &str) -> Option<Vec<u64>> {
+    let text = text.trim().trim_start_matches(['v', 'V']);
+    let core = text.split(['-', '+']).next()?;
+    core.split('.').map(|part| part.parse().ok()).collect()
+}
+
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    match (parse_version(candidate), parse_version(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+// Resposta de /releases/latest; rascunhos e pré-releases não contam
+pub fn parse_release(json: &str) -> Result<Option<Release>, String> {
+    let release: GithubRelease = serde_json::from_str(json).map_err(|e| format!("Resposta inválida: {}", e))?;
+    if release.draft || release.prerelease || parse_version(&release.tag_name).is_none() {
+        return Ok(None);
+    }
+    Ok(Some(Release {
+        version: release.tag_name.trim().trim_start_matches(['v', 'V']).to_string(),
+        url: release.html_url,
+    }))
+}
+
+// Some(release) só quando há uma versão mais nova que `current`
+pub async fn check_for_update(backend: &SharedBackend, url: &str, current: &str) -> Result<Option<Release>, String> {
+    // A API do GitHub recusa requisições sem User-Agent
+    let request = HttpRequest::get(url)
+        .header("User-Agent", &format!("Keepers/{}", current))
+        .header("Accept", "application/vnd.github+json");
+    let response = send_request(backend.as_ref(), request).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Status HTTP: {}", response.status()));
+    }
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+        if body.len() > MAX_RELEASE_SIZE {
+            return Err("Resposta grande demais".to_string());
+        }
+    }
+    let release = parse_release(&String::from_utf8_lossy(&body))?;
+    Ok(release.filter(|release| is_newer(&release.version, current)))
+}
+
+// Roda `check_for_update` em uma thread própria, com o client da configuração atual
+pub fn start_update_check(config: Arc<Mutex<AppConfig>>, tx: async_channel::Sender<Result<Option<Release>, String>>) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let backend = match config.lock() {
+                Ok(config) => backend_for_config(&config),
+                Err(_) => backend_for_config(&AppConfig::default()),
+            };
+            let result = match (backend, releases_api(PROJECT_URL)) {
+                (Ok(backend), Some(api)) => check_for_update(&backend, &api, CURRENT_VERSION).await,
+                (Ok(_), None) => Err(format!("Endereço do projeto fora do GitHub: {}", PROJECT_URL)),
+                (Err(e), _) => Err(format!("Erro ao criar client: {}", e)),
+            };
+            let _ = tx.send(result).await;
+        });
+    });
+}
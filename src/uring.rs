@@ -0,0 +1,239 @@
+// Escrita dos chunks pelo io_uring (Linux)
+//
+// Em conexões muito rápidas, cada pedaço recebido vira um seek + write no arquivo.
+// Aqui uma thread dona do anel junta os pedidos que chegaram ao mesmo tempo (de
+// todas as conexões), envia tudo numa única chamada ao kernel e responde a cada
+// conexão quando o seu pedaço foi gravado. Se o kernel não permitir io_uring
+// (versão antiga, seccomp de contêiner), `UringWriter::open` falha e o motor usa
+// o tokio::fs::File de sempre.
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::sync::mpsc;
+use bytes::Bytes;
+use io_uring::{opcode, types, IoUring};
+use tokio::sync::oneshot;
+
+// Entradas do anel (pedidos em andamento ao mesmo tempo)
+pub const QUEUE_DEPTH: u32 = 64;
+
+enum Request {
+    Write { offset: u64, data: Bytes, done: oneshot::Sender<io::Result<()>> },
+    Sync { done: oneshot::Sender<io::Result<()>> },
+}
+
+pub struct UringWriter {
+    requests: mpsc::Sender<Request>,
+}
+
+impl UringWriter {
+    // Abre `path` para escrita e cria o anel; erro se o io_uring não estiver disponível
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = std::fs::OpenOptions::new().write(true).open(path)?;
+        let ring = IoUring::new(QUEUE_DEPTH)?;
+        let (requests, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("keepers-uring".to_string())
+            .spawn(move || run_ring(ring, file, receiver))?;
+        Ok(UringWriter { requests })
+    }
+
+    pub async fn write_at(&self, offset: u64, data: Bytes) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let (done, result) = oneshot::channel();
+        self.send(Request::Write { offset, data, done })?;
+        result.await.unwrap_or_else(|_| Err(closed()))
+    }
+
+    // Espera os dados chegarem ao disco (fdatasync)
+    pub async fn sync(&self) -> io::Result<()> {
+        let (done, result) = oneshot::channel();
+        self.send(Request::Sync { done })?;
+        result.await.unwrap_or_else(|_| Err(closed()))
+    }
+
+    fn send(&self, request: Request) -> io::Result<()> {
+        self.requests.send(request).map_err(|_| closed())
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "Escritor io_uring encerrado")
+}
+
+// Pedido em andamento: quanto do pedaço já foi gravado (o kernel pode gravar menos)
+struct Pending {
+    request: Request,
+    written: usize,
+}
+
+// Anel que falhou no meio de um envio: o kernel pode ter recebido parte das
+// escritas e ainda ler dos buffers delas. Esses buffers ficam guardados e o anel
+// não é mais usado (as entradas que sobraram na fila iriam no próximo envio)
+#[derive(Default)]
+struct Broken {
+    error: Option<(io::ErrorKind, String)>,
+    buffers: Vec<Bytes>,
+}
+
+impl Broken {
+    fn error(&self) -> Option<io::Error> {
+        self.error.as_ref().map(|(kind, message)| io::Error::new(*kind, message.clone()))
+    }
+}
+
+// Roda até todos os UringWriter serem descartados
+fn run_ring(mut ring: IoUring, file: File, receiver: mpsc::Receiver<Request>) {
+    let fd = types::Fd(file.as_raw_fd());
+    let mut broken = Broken::default();
+    while let Ok(first) = receiver.recv() {
+        if let Some(error) = broken.error() {
+            answer(first, Err(error));
+            continue;
+        }
+        // Junta o que já estiver na fila, até encher o anel
+        let mut batch = vec![Pending { request: first, written: 0 }];
+        while batch.len() < QUEUE_DEPTH as usize {
+            match receiver.try_recv() {
+                Ok(request) => batch.push(Pending { request, written: 0 }),
+                Err(_) => break,
+            }
+        }
+
+        // Escritas primeiro; um sync só vai depois das escritas anteriores a ele
+        let sync_at = batch.iter().position(|p| matches!(p.request, Request::Sync { .. }));
+        let (writes, rest): (Vec<Pending>, Vec<Pending>) = match sync_at {
+            Some(at) => {
+                let rest = batch.split_off(at);
+                (batch, rest)
+            }
+            None => (batch, Vec::new()),
+        };
+        let mut results: Vec<(Pending, io::Result<()>)> = submit_writes(&mut ring, fd, writes, &mut broken);
+        for pending in rest {
+            let result = match (&pending.request, broken.error()) {
+                (_, Some(error)) => Err(error),
+                (Request::Sync { .. }, None) => submit_sync(&mut ring, fd),
+                (Request::Write { .. }, None) => {
+                    results.extend(submit_writes(&mut ring, fd, vec![pending], &mut broken));
+                    continue;
+                }
+            };
+            results.push((pending, result));
+        }
+
+        for (pending, result) in results {
+            answer(pending.request, result);
+        }
+    }
+    // Fechar o anel não espera as escritas em andamento: os buffers que o kernel
+    // ainda pode usar são esquecidos em vez de liberados
+    drop(ring);
+    std::mem::forget(broken.buffers);
+}
+
+fn answer(request: Request, result: io::Result<()>) {
+    match request {
+        // Solta os dados antes de responder: a conexão reaproveita o mesmo buffer
+        Request::Write { done, data, .. } => {
+            drop(data);
+            let _ = done.send(result);
+        }
+        Request::Sync { done } => {
+            let _ = done.send(result);
+        }
+    }
+}
+
+// submit_and_wait, de novo se um sinal interromper a espera (as entradas já
+// foram com o kernel e as respostas ainda vão chegar)
+fn submit_and_wait(ring: &mut IoUring, want: usize) -> io::Result<()> {
+    loop {
+        match ring.submit_and_wait(want) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            result => return result.map(|_| ()),
+        }
+    }
+}
+
+// Envia as escritas juntas e reenvia o que o kernel gravou pela metade
+fn submit_writes(
+    ring: &mut IoUring,
+    fd: types::Fd,
+    mut pending: Vec<Pending>,
+    broken: &mut Broken,
+) -> Vec<(Pending, io::Result<()>)> {
+    let mut finished = Vec::new();
+    while !pending.is_empty() {
+        let mut pushed = 0;
+        for (index, item) in pending.iter().enumerate() {
+            let Request::Write { offset, data, .. } = &item.request else {
+                continue;
+            };
+            let remaining = &data[item.written..];
+            let entry = opcode::Write::new(fd, remaining.as_ptr(), remaining.len() as u32)
+                .offset(offset + item.written as u64)
+                .build()
+                .user_data(index as u64);
+            // O anel tem QUEUE_DEPTH entradas e o lote nunca passa disso
+            if unsafe { ring.submission().push(&entry) }.is_err() {
+                break;
+            }
+            pushed = index + 1;
+        }
+        if let Err(e) = submit_and_wait(ring, pushed) {
+            for item in &pending[..pushed] {
+                if let Request::Write { data, .. } = &item.request {
+                    broken.buffers.push(data.clone());
+                }
+            }
+            broken.error = Some((e.kind(), e.to_string()));
+            finished.extend(pending.drain(..).map(|item| (item, Err(io::Error::new(e.kind(), e.to_string())))));
+            break;
+        }
+
+        let mut outcomes: Vec<Option<i32>> = vec![None; pending.len()];
+        for completion in ring.completion() {
+            if let Some(slot) = outcomes.get_mut(completion.user_data() as usize) {
+                *slot = Some(completion.result());
+            }
+        }
+        let mut still_pending = Vec::new();
+        for (item, outcome) in pending.into_iter().zip(outcomes) {
+            let mut item = item;
+            let len = match &item.request {
+                Request::Write { data, .. } => data.len(),
+                Request::Sync { .. } => 0,
+            };
+            match outcome {
+                Some(result) if result < 0 => finished.push((item, Err(io::Error::from_raw_os_error(-result)))),
+                Some(0) => finished.push((item, Err(io::ErrorKind::WriteZero.into()))),
+                Some(written) => {
+                    item.written += written as usize;
+                    if item.written >= len {
+                        finished.push((item, Ok(())));
+                    } else {
+                        still_pending.push(item);
+                    }
+                }
+                None => still_pending.push(item),
+            }
+        }
+        pending = still_pending;
+    }
+    finished
+}
+
+fn submit_sync(ring: &mut IoUring, fd: types::Fd) -> io::Result<()> {
+    let entry = opcode::Fsync::new(fd).flags(types::FsyncFlags::DATASYNC).build().user_data(u64::MAX);
+    unsafe { ring.submission().push(&entry) }.map_err(|_| io::Error::other("Fila do io_uring cheia"))?;
+    submit_and_wait(ring, 1)?;
+    match ring.completion().next() {
+        Some(completion) if completion.result() < 0 => Err(io::Error::from_raw_os_error(-completion.result())),
+        Some(_) => Ok(()),
+        None => Err(io::Error::other("fsync sem resposta do io_uring")),
+    }
+}
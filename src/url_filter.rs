@@ -0,0 +1,44 @@
+// Regras de permissão e bloqueio de URLs, para máquinas compartilhadas ou quiosques
+//
+// Uma expressão regular por regra, sem diferenciar maiúsculas. O bloqueio vence;
+// com alguma regra de permissão, só passam as URLs que casam com uma delas.
+use regex::{Regex, RegexBuilder};
+use crate::storage::UrlFilterSettings;
+
+pub struct UrlFilter {
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+}
+
+impl UrlFilter {
+    pub fn new(settings: &UrlFilterSettings) -> Result<Self, String> {
+        Ok(UrlFilter { allow: compile(&settings.allow)?, deny: compile(&settings.deny)? })
+    }
+
+    pub fn check(&self, url: &str) -> Result<(), String> {
+        if let Some(rule) = self.deny.iter().find(|rule| rule.is_match(url)) {
+            return Err(format!("Bloqueado pela regra \"{}\"", rule.as_str()));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|rule| rule.is_match(url)) {
+            return Err("Fora dos endereços permitidos".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Regra inválida \"{}\": {}", pattern, e))
+        })
+        .collect()
+}
+
+// Regras que não compilam bloqueiam tudo: um filtro quebrado não deve liberar downloads
+pub fn check_url(settings: &UrlFilterSettings, url: &str) -> Result<(), String> {
+    UrlFilter::new(settings)?.check(url)
+}
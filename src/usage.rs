@@ -0,0 +1,88 @@
+// Consumo de dados por mês do calendário (cota mensal) e por hora (gráficos de uso)
+use std::collections::BTreeMap;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+
+// A partir desta porcentagem da cota o app avisa
+pub const QUOTA_WARNING_PERCENT: u64 = 80;
+// Totais por hora guardados para os gráficos (o mês mais longo e mais alguns dias)
+pub const HOURLY_RETENTION_DAYS: i64 = 35;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataUsage {
+    #[serde(default)]
+    pub months: BTreeMap<String, u64>, // "2026-10" -> bytes recebidos no mês
+    #[serde(default)]
+    pub hours: BTreeMap<String, u64>, // "2026-10-18T14" -> bytes recebidos na hora
+}
+
+impl DataUsage {
+    pub fn add(&mut self, bytes: u64, time: DateTime<Local>) {
+        *self.months.entry(month_key(time)).or_default() += bytes;
+        *self.hours.entry(hour_key(time)).or_default() += bytes;
+    }
+
+    // Soma `other` (o consumo da sessão, ao abrir a lista cifrada depois de baixar)
+    pub fn merge(&mut self, other: &DataUsage) {
+        for (month, bytes) in &other.months {
+            *self.months.entry(month.clone()).or_default() += bytes;
+        }
+        for (hour, bytes) in &other.hours {
+            *self.hours.entry(hour.clone()).or_default() += bytes;
+        }
+    }
+
+    // Descarta horas mais antigas que HOURLY_RETENTION_DAYS (os totais mensais ficam)
+    pub fn prune_hours(&mut self, now: DateTime<Local>) {
+        let oldest = hour_key(now - Duration::days(HOURLY_RETENTION_DAYS));
+        self.hours.retain(|key, _| *key >= oldest);
+    }
+
+    // Bytes de cada hora de `day`, de 0h a 23h
+    pub fn hourly_series(&self, day: NaiveDate) -> Vec<u64> {
+        let prefix = day.format("%Y-%m-%d").to_string();
+        (0..24)
+            .map(|hour| self.hours.get(&format!("{}T{:02}", prefix, hour)).copied().unwrap_or(0))
+            .collect()
+    }
+
+    // Bytes de cada um dos `days` dias terminando em `last_day` (o mais antigo primeiro)
+    pub fn daily_series(&self, last_day: NaiveDate, days: i64) -> Vec<(NaiveDate, u64)> {
+        (0..days)
+            .rev()
+            .map(|back| {
+                let day = last_day - Duration::days(back);
+                (day, self.hourly_series(day).iter().sum())
+            })
+            .collect()
+    }
+
+    pub fn month_total(&self, time: DateTime<Local>) -> u64 {
+        self.months.get(&month_key(time)).copied().unwrap_or(0)
+    }
+}
+
+pub fn month_key(time: DateTime<Local>) -> String {
+    format!("{:04}-{:02}", time.year(), time.month())
+}
+
+pub fn hour_key(time: DateTime<Local>) -> String {
+    format!("{}T{:02}", time.format("%Y-%m-%d"), time.hour())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaState {
+    Normal,
+    Warning,   // Passou de QUOTA_WARNING_PERCENT
+    Exhausted, // Novos downloads começam pausados
+}
+
+pub fn quota_state(used: u64, limit: Option<u64>) -> QuotaState {
+    match limit {
+        Some(limit) if used >= limit => QuotaState::Exhausted,
+        Some(limit) if used.saturating_mul(100) >= limit.saturating_mul(QUOTA_WARNING_PERCENT) => {
+            QuotaState::Warning
+        }
+        _ => QuotaState::Normal,
+    }
+}
@@ -0,0 +1,92 @@
+// Criptografia opcional da lista de downloads (downloads.json e seus backups), do
+// consumo e do histórico de servidores (usage.json e hosts.json, que também listam
+// os sites visitados) e das senhas da configuração (secrets.enc, ver
+// `storage::ConfigSecrets`)
+//
+// Formato: "KEEPERS-ENC1" + sal (16 bytes) + nonce (12 bytes) + JSON cifrado com
+// ChaCha20-Poly1305. A chave vem da senha pelo Argon2id com o sal do arquivo; a
+// senha nunca é gravada e, sem ela, o histórico não pode ser recuperado.
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+pub const MAGIC: &[u8] = b"KEEPERS-ENC1";
+pub const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+// Chave derivada da senha, junto com o sal usado (vai no cabeçalho de cada arquivo)
+#[derive(Clone, PartialEq, Eq)]
+pub struct VaultKey {
+    salt: [u8; SALT_LEN],
+    key: [u8; KEY_LEN],
+}
+
+impl VaultKey {
+    // Chave nova, com sal aleatório (ao ativar a criptografia)
+    pub fn new(passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, salt)
+    }
+
+    pub fn derive(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, String> {
+        let mut key = [0u8; KEY_LEN];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| format!("Erro ao derivar chave: {}", e))?;
+        Ok(VaultKey { salt, key })
+    }
+
+    pub fn salt(&self) -> [u8; SALT_LEN] {
+        self.salt
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher.encrypt(&nonce, plaintext).map_err(|_| "Erro ao cifrar".to_string())?;
+        let mut out = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.salt);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    // Falha com senha errada, arquivo de outra chave ou conteúdo alterado
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if file_salt(data) != Some(self.salt) {
+            return Err("Arquivo cifrado com outra senha".to_string());
+        }
+        let nonce_start = MAGIC.len() + SALT_LEN;
+        let ciphertext_start = nonce_start + NONCE_LEN;
+        if data.len() < ciphertext_start {
+            return Err("Arquivo cifrado truncado".to_string());
+        }
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
+        cipher
+            .decrypt(Nonce::from_slice(&data[nonce_start..ciphertext_start]), &data[ciphertext_start..])
+            .map_err(|_| "Senha incorreta ou arquivo danificado".to_string())
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn file_salt(data: &[u8]) -> Option<[u8; SALT_LEN]> {
+    if !is_encrypted(data) {
+        return None;
+    }
+    data.get(MAGIC.len()..MAGIC.len() + SALT_LEN)?.try_into().ok()
+}
+
+// Deriva a chave com o sal do próprio arquivo e decifra
+pub fn unlock(passphrase: &str, data: &[u8]) -> Result<(VaultKey, Vec<u8>), String> {
+    let salt = file_salt(data).ok_or_else(|| "Arquivo não está cifrado".to_string())?;
+    let key = VaultKey::derive(passphrase, salt)?;
+    let plaintext = key.decrypt(data)?;
+    Ok((key, plaintext))
+}
@@ -0,0 +1,66 @@
+// Tempo máximo e velocidade mínima de um download
+//
+// Transferências mortas (servidor que manda poucos bytes por minuto, conexão
+// que nunca termina) não podem segurar a vaga na fila a noite toda. O motor
+// mede só o tempo ativo: pausa e fila não contam. Quando a política é violada,
+// o download para mantendo o .part e termina como falha, com o motivo.
+use std::collections::VecDeque;
+use std::time::Duration;
+use crate::engine::{format_eta, format_speed};
+use crate::storage::{DownloadOptions, MinSpeed};
+
+pub const WATCH_INTERVAL_SECS: u64 = 5;
+
+#[derive(Debug, Clone)]
+pub struct TransferWatch {
+    max_time: Option<Duration>,
+    min_speed: Option<MinSpeed>,
+    active: Duration,
+    // (tempo ativo, bytes gravados); a primeira é a base da janela de velocidade
+    samples: VecDeque<(Duration, u64)>,
+}
+
+impl TransferWatch {
+    // None se o download não tem política
+    pub fn for_options(options: &DownloadOptions, downloaded: u64) -> Option<Self> {
+        let max_time = options.max_minutes.filter(|minutes| *minutes > 0).map(|minutes| Duration::from_secs(minutes * 60));
+        let min_speed = options.min_speed.filter(|min| min.bytes_per_sec > 0 && min.minutes > 0);
+        if max_time.is_none() && min_speed.is_none() {
+            return None;
+        }
+        Some(TransferWatch {
+            max_time,
+            min_speed,
+            active: Duration::ZERO,
+            samples: VecDeque::from([(Duration::ZERO, downloaded)]),
+        })
+    }
+
+    // Soma `elapsed` de tempo ativo com `downloaded` bytes gravados até agora.
+    // Retorna o motivo para encerrar, se a política foi violada.
+    pub fn tick(&mut self, elapsed: Duration, downloaded: u64) -> Option<String> {
+        self.active += elapsed;
+        if let Some(max_time) = self.max_time {
+            if self.active >= max_time {
+                return Some(format!("Tempo máximo de {} atingido", format_eta(max_time.as_secs_f64())));
+            }
+        }
+
+        let min = self.min_speed?;
+        let window = Duration::from_secs(min.minutes * 60);
+        self.samples.push_back((self.active, downloaded));
+        // Base = a amostra mais recente que ainda cobre a janela inteira
+        while self.samples.len() > 1 && self.active - self.samples[1].0 >= window {
+            self.samples.pop_front();
+        }
+        let (since, bytes) = self.samples[0];
+        let span = self.active - since;
+        if span < window {
+            return None;
+        }
+        let speed = downloaded.saturating_sub(bytes) as f64 / span.as_secs_f64();
+        (speed < min.bytes_per_sec as f64).then(|| {
+            format!("Abaixo de {} por {}min", format_speed(min.bytes_per_sec as f64), min.minutes)
+        })
+    }
+}
@@ -0,0 +1,369 @@
+// Transferência delta no estilo zsync
+//
+// O arquivo de controle `.zsync` traz, para cada bloco do arquivo remoto, uma
+// soma móvel (rsum) e o início do MD4 do bloco. Procuramos esses blocos numa
+// cópia local antiga (em qualquer posição) e baixamos só o que não foi achado.
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use sha1::{Digest, Sha1};
+
+// Tamanho máximo aceito para um arquivo .zsync (controle, não o arquivo em si)
+pub const MAX_CONTROL_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockSum {
+    pub rsum: u32,         // Só os últimos `rsum_bytes` bytes são significativos
+    pub checksum: Vec<u8>, // Início do MD4 do bloco
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFile {
+    pub filename: Option<String>,
+    pub blocksize: usize,
+    pub length: u64,
+    pub seq_matches: usize,
+    pub rsum_bytes: usize,
+    pub checksum_bytes: usize,
+    pub url: Option<String>, // Relativa ao .zsync quando não for absoluta
+    pub sha1: Option<String>,
+    pub blocks: Vec<BlockSum>,
+}
+
+impl ControlFile {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut filename = None;
+        let mut blocksize = None;
+        let mut length = None;
+        let mut hash_lengths = (1, 4, 16);
+        let mut url = None;
+        let mut sha1 = None;
+
+        // Cabeçalho "Chave: valor" até a primeira linha vazia
+        let mut pos = 0;
+        loop {
+            let end = data[pos..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .ok_or("Cabeçalho .zsync incompleto")?;
+            let line = String::from_utf8_lossy(&data[pos..pos + end]).trim_end_matches('\r').to_string();
+            pos += end + 1;
+            if line.is_empty() {
+                break;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(format!("Linha inválida no .zsync: {}", line));
+            };
+            let value = value.trim();
+            match key {
+                "Filename" => filename = Some(value.to_string()),
+                "Blocksize" => blocksize = value.parse::<usize>().ok(),
+                "Length" => length = value.parse::<u64>().ok(),
+                "Hash-Lengths" => {
+                    let parts: Vec<usize> = value.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+                    if parts.len() != 3 {
+                        return Err(format!("Hash-Lengths inválido: {}", value));
+                    }
+                    hash_lengths = (parts[0], parts[1], parts[2]);
+                }
+                "URL" if url.is_none() => url = Some(value.to_string()),
+                "SHA-1" => sha1 = Some(value.to_lowercase()),
+                _ => {}
+            }
+        }
+
+        let blocksize = blocksize.ok_or("Blocksize ausente no .zsync")?;
+        if blocksize == 0 {
+            return Err("Blocksize inválido no .zsync".to_string());
+        }
+        let length = length.ok_or("Length ausente no .zsync")?;
+        let (seq_matches, rsum_bytes, checksum_bytes) = hash_lengths;
+        // seq_matches 2: somas curtas, que só valem conferidas em dois blocos seguidos
+        if !(1..=2).contains(&seq_matches) || !(1..=4).contains(&rsum_bytes) || !(1..=16).contains(&checksum_bytes) {
+            return Err("Hash-Lengths fora do intervalo".to_string());
+        }
+
+        // Length e Blocksize vêm do servidor: contas verificadas para um .zsync
+        // corrompido não estourar nem ler fora das somas
+        let num_blocks = usize::try_from(length.div_ceil(blocksize as u64)).map_err(|_| "Length grande demais no .zsync")?;
+        let entry = rsum_bytes + checksum_bytes;
+        let sums_len = num_blocks
+            .checked_mul(entry)
+            .filter(|&len| len <= data.len() - pos)
+            .ok_or("Somas de blocos incompletas no .zsync")?;
+
+        let blocks = data[pos..pos + sums_len]
+            .chunks(entry)
+            .map(|chunk| BlockSum {
+                rsum: chunk[..rsum_bytes].iter().fold(0u32, |acc, &b| (acc << 8) | b as u32),
+                checksum: chunk[rsum_bytes..].to_vec(),
+            })
+            .collect();
+
+        Ok(ControlFile {
+            filename,
+            blocksize,
+            length,
+            seq_matches,
+            rsum_bytes,
+            checksum_bytes,
+            url,
+            sha1,
+            blocks,
+        })
+    }
+
+    // Tamanho real de um bloco (o último pode ser menor)
+    pub fn block_len(&self, index: usize) -> u64 {
+        let start = index as u64 * self.blocksize as u64;
+        (self.length - start).min(self.blocksize as u64)
+    }
+
+    // Confere o bloco `index` contra `data` (completado com zeros se for o último)
+    fn block_matches(&self, index: usize, data: &[u8]) -> bool {
+        let block = &self.blocks[index];
+        rsum(data) & self.rsum_mask() == block.rsum & self.rsum_mask() && md4(data)[..self.checksum_bytes] == block.checksum[..]
+    }
+
+    fn rsum_mask(&self) -> u32 {
+        if self.rsum_bytes == 4 {
+            u32::MAX
+        } else {
+            (1u32 << (8 * self.rsum_bytes)) - 1
+        }
+    }
+}
+
+// Endereço do .zsync publicado ao lado do arquivo: o sufixo vai no caminho, e a
+// query (token de links assinados) fica como estava
+pub fn control_file_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) => {
+            let path = format!("{}.zsync", parsed.path());
+            parsed.set_path(&path);
+            parsed.set_fragment(None);
+            parsed.to_string()
+        }
+        Err(_) => format!("{}.zsync", url),
+    }
+}
+
+// Soma móvel do zsync: a = Σ byte, b = Σ (len - i) · byte (ambos módulo 2^16)
+pub fn rsum(block: &[u8]) -> u32 {
+    let (a, b) = rsum_parts(block);
+    ((a as u32) << 16) | b as u32
+}
+
+fn rsum_parts(block: &[u8]) -> (u16, u16) {
+    let len = block.len();
+    block.iter().enumerate().fold((0u16, 0u16), |(a, b), (i, &c)| {
+        (
+            a.wrapping_add(c as u16),
+            b.wrapping_add(((len - i) as u16).wrapping_mul(c as u16)),
+        )
+    })
+}
+
+// Procura cada bloco do arquivo remoto na cópia local.
+// Retorna, por bloco, o offset local onde ele foi encontrado.
+// Com seq_matches 2 um bloco só conta se o seguinte também casar logo depois dele
+// (ou se continuar uma sequência já encontrada), como no zsync original.
+pub fn match_blocks<R: Read>(control: &ControlFile, mut seed: R) -> io::Result<Vec<Option<u64>>> {
+    let bs = control.blocksize;
+    let mask = control.rsum_mask();
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, block) in control.blocks.iter().enumerate() {
+        index.entry(block.rsum & mask).or_default().push(i);
+    }
+
+    let mut found = vec![None; control.blocks.len()];
+    let mut buf: Vec<u8> = Vec::new();
+    let mut buf_offset = 0u64; // Offset local de buf[0]
+    let mut pos = 0usize; // Início da janela dentro de buf
+    let mut eof = false;
+    let mut seed_len = u64::MAX; // Conhecido só ao chegar no fim
+    let mut rolling: Option<(u16, u16)> = None;
+    let mut chunk = vec![0u8; 1024 * 1024];
+    // Janela e, com seq_matches 2, o bloco seguinte a ela
+    let lookahead = bs * control.seq_matches;
+
+    loop {
+        // Garante a janela inteira (mais o bloco seguinte) e o próximo byte (para rolar)
+        while buf.len() - pos <= lookahead && !eof {
+            buf.drain(..pos);
+            buf_offset += pos as u64;
+            pos = 0;
+            let n = seed.read(&mut chunk)?;
+            if n == 0 {
+                // Completa com zeros, como o último bloco do arquivo remoto
+                eof = true;
+                seed_len = buf_offset + buf.len() as u64;
+                buf.resize(buf.len() + bs, 0);
+            } else {
+                buf.extend_from_slice(&chunk[..n]);
+            }
+        }
+        if buf.len() - pos < bs {
+            break;
+        }
+
+        let window = &buf[pos..pos + bs];
+        let (a, b) = rolling.unwrap_or_else(|| rsum_parts(window));
+        let key = (((a as u32) << 16) | b as u32) & mask;
+
+        let mut matched = false;
+        if let Some(candidates) = index.get(&key) {
+            let mut digest = None;
+            for &i in candidates {
+                let offset = buf_offset + pos as u64;
+                if found[i].is_some() || offset + control.block_len(i) > seed_len {
+                    continue;
+                }
+                let digest = digest.get_or_insert_with(|| md4(window));
+                if digest[..control.checksum_bytes] != control.blocks[i].checksum[..] {
+                    continue;
+                }
+                let in_sequence = control.seq_matches == 1
+                    || i + 1 == control.blocks.len()
+                    || (i > 0 && found[i - 1] == Some(offset.wrapping_sub(bs as u64)));
+                let next_matches = || {
+                    let next = offset + bs as u64;
+                    buf.len() - pos >= 2 * bs
+                        && next + control.block_len(i + 1) <= seed_len
+                        && control.block_matches(i + 1, &buf[pos + bs..pos + 2 * bs])
+                };
+                if in_sequence || next_matches() {
+                    found[i] = Some(offset);
+                    matched = true;
+                }
+            }
+        }
+
+        if matched {
+            // Blocos consecutivos costumam casar em sequência
+            pos += bs;
+            rolling = None;
+            continue;
+        }
+
+        if buf.len() - pos <= bs {
+            break;
+        }
+        let old = buf[pos] as u16;
+        let new = buf[pos + bs] as u16;
+        let a = a.wrapping_sub(old).wrapping_add(new);
+        let b = b.wrapping_add(a).wrapping_sub((bs as u16).wrapping_mul(old));
+        rolling = Some((a, b));
+        pos += 1;
+    }
+
+    Ok(found)
+}
+
+// Intervalos (início, fim inclusivo) do arquivo remoto que precisam ser baixados
+pub fn missing_ranges(control: &ControlFile, found: &[Option<u64>]) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for (i, location) in found.iter().enumerate() {
+        if location.is_some() {
+            continue;
+        }
+        let start = i as u64 * control.blocksize as u64;
+        let end = start + control.block_len(i) - 1;
+        match ranges.last_mut() {
+            Some(last) if last.1 + 1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+// Monta o arquivo de saída com os blocos reaproveitados da cópia local.
+// Os buracos (blocos não encontrados) ficam para serem baixados depois.
+pub fn copy_known_blocks(control: &ControlFile, found: &[Option<u64>], seed: &Path, output: &Path) -> io::Result<u64> {
+    let mut seed = File::open(seed)?;
+    let mut out = OpenOptions::new().create(true).write(true).truncate(true).open(output)?;
+    out.set_len(control.length)?;
+
+    let mut block = vec![0u8; control.blocksize];
+    let mut reused = 0;
+    for (i, location) in found.iter().enumerate() {
+        let Some(offset) = location else {
+            continue;
+        };
+        let len = control.block_len(i) as usize;
+        seed.seek(SeekFrom::Start(*offset))?;
+        seed.read_exact(&mut block[..len])?;
+        out.seek(SeekFrom::Start(i as u64 * control.blocksize as u64))?;
+        out.write_all(&block[..len])?;
+        reused += len as u64;
+    }
+    out.flush()?;
+    Ok(reused)
+}
+
+// Confere o SHA-1 do arquivo montado com o informado no .zsync
+pub fn sha1_matches(path: &Path, expected: &str) -> io::Result<bool> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha1::new();
+    io::copy(&mut file, &mut hasher)?;
+    let digest: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(digest.eq_ignore_ascii_case(expected))
+}
+
+// MD4 (RFC 1320), usado pelo formato zsync para as somas fortes dos blocos.
+// O último bloco é completado com zeros até o tamanho do bloco.
+pub fn md4(data: &[u8]) -> [u8; 16] {
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&bit_len.to_le_bytes());
+
+    for chunk in message.chunks(64) {
+        let x: Vec<u32> = chunk
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+
+        let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+        let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+        let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+        for &i in &[0, 4, 8, 12] {
+            a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+            d = d.wrapping_add(f(a, b, c)).wrapping_add(x[i + 1]).rotate_left(7);
+            c = c.wrapping_add(f(d, a, b)).wrapping_add(x[i + 2]).rotate_left(11);
+            b = b.wrapping_add(f(c, d, a)).wrapping_add(x[i + 3]).rotate_left(19);
+        }
+        for &i in &[0, 1, 2, 3] {
+            a = a.wrapping_add(g(b, c, d)).wrapping_add(x[i]).wrapping_add(0x5a827999).rotate_left(3);
+            d = d.wrapping_add(g(a, b, c)).wrapping_add(x[i + 4]).wrapping_add(0x5a827999).rotate_left(5);
+            c = c.wrapping_add(g(d, a, b)).wrapping_add(x[i + 8]).wrapping_add(0x5a827999).rotate_left(9);
+            b = b.wrapping_add(g(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x5a827999).rotate_left(13);
+        }
+        for &i in &[0, 2, 1, 3] {
+            a = a.wrapping_add(h(b, c, d)).wrapping_add(x[i]).wrapping_add(0x6ed9eba1).rotate_left(3);
+            d = d.wrapping_add(h(a, b, c)).wrapping_add(x[i + 8]).wrapping_add(0x6ed9eba1).rotate_left(9);
+            c = c.wrapping_add(h(d, a, b)).wrapping_add(x[i + 4]).wrapping_add(0x6ed9eba1).rotate_left(11);
+            b = b.wrapping_add(h(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x6ed9eba1).rotate_left(15);
+        }
+
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    let mut digest = [0u8; 16];
+    for (i, word) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
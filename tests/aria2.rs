@@ -0,0 +1,71 @@
+// Testes da leitura dos arquivos de controle do aria2
+use keepers::aria2::{control_file_path, parse_control_file};
+use keepers::engine::ChunkState;
+use std::path::Path;
+
+// Controle no formato do aria2 (versão 1, big-endian), sem hash de torrent
+fn aria2_control(piece_length: u32, total_length: u64, bitfield: &[u8]) -> Vec<u8> {
+    let mut out = vec![0, 1];
+    out.extend_from_slice(&0u32.to_be_bytes()); // Extensão
+    out.extend_from_slice(&0u32.to_be_bytes()); // Sem info hash
+    out.extend_from_slice(&piece_length.to_be_bytes());
+    out.extend_from_slice(&total_length.to_be_bytes());
+    out.extend_from_slice(&0u64.to_be_bytes()); // Enviado
+    out.extend_from_slice(&(bitfield.len() as u32).to_be_bytes());
+    out.extend_from_slice(bitfield);
+    out.extend_from_slice(&0u32.to_be_bytes()); // Peças em andamento
+    out
+}
+
+#[test]
+fn bitfield_becomes_complete_and_missing_chunks() {
+    // 10 peças de 100 bytes, a última com 50: baixadas 0, 1, 4 e 9
+    let control = parse_control_file(&aria2_control(100, 950, &[0b1100_1000, 0b0100_0000])).unwrap();
+
+    assert_eq!(
+        control.chunks(),
+        vec![
+            ChunkState { start: 0, end: 199, downloaded: 200 },
+            ChunkState { start: 200, end: 399, downloaded: 0 },
+            ChunkState { start: 400, end: 499, downloaded: 100 },
+            ChunkState { start: 500, end: 899, downloaded: 0 },
+            ChunkState { start: 900, end: 949, downloaded: 50 },
+        ]
+    );
+    assert_eq!(control.completed_bytes(), 350);
+}
+
+#[test]
+fn version_zero_files_are_little_endian() {
+    let mut data = vec![0, 0];
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&3u32.to_le_bytes());
+    data.extend_from_slice(b"abc");
+    data.extend_from_slice(&64u32.to_le_bytes());
+    data.extend_from_slice(&128u64.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.push(0b1000_0000);
+
+    let control = parse_control_file(&data).unwrap();
+
+    assert_eq!((control.piece_length, control.total_length), (64, 128));
+    assert_eq!(control.completed_bytes(), 64);
+}
+
+#[test]
+fn truncated_or_unknown_control_files_are_rejected() {
+    let data = aria2_control(100, 950, &[0xff, 0xc0]);
+    assert!(parse_control_file(&data[..20]).is_err());
+    assert!(parse_control_file(&[0, 7, 0, 0]).is_err());
+    // Bitfield curto demais para o número de peças
+    assert!(parse_control_file(&aria2_control(100, 950, &[0xff])).is_err());
+}
+
+#[test]
+fn control_file_sits_next_to_the_download() {
+    assert_eq!(
+        control_file_path(Path::new("/tmp/imagem.iso")),
+        Path::new("/tmp/imagem.iso.aria2")
+    );
+}
@@ -0,0 +1,34 @@
+// Testes das novas tentativas automáticas de downloads com falha
+use chrono::{Duration, TimeZone, Utc};
+use keepers::auto_retry::{next_retry, retry_delay, retry_text, RETRY_MAX_DELAY_SECS};
+use keepers::failure::FailureKind;
+
+#[test]
+fn delay_doubles_up_to_the_limit() {
+    assert_eq!(retry_delay(0), Duration::minutes(3));
+    assert_eq!(retry_delay(1), Duration::minutes(6));
+    assert_eq!(retry_delay(2), Duration::minutes(12));
+    assert_eq!(retry_delay(10), Duration::seconds(RETRY_MAX_DELAY_SECS));
+    assert_eq!(retry_delay(u32::MAX), Duration::seconds(RETRY_MAX_DELAY_SECS));
+}
+
+#[test]
+fn only_transient_failures_within_the_budget_are_retried() {
+    let now = Utc.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
+    assert_eq!(next_retry(FailureKind::Network, 2, 5, now), Some(now + Duration::minutes(12)));
+    assert_eq!(next_retry(FailureKind::Server, 0, 1, now), Some(now + Duration::minutes(3)));
+    assert_eq!(next_retry(FailureKind::Network, 5, 5, now), None);
+    assert_eq!(next_retry(FailureKind::Network, 0, 0, now), None);
+    assert_eq!(next_retry(FailureKind::Gone, 0, 5, now), None);
+    assert_eq!(next_retry(FailureKind::AuthRequired, 0, 5, now), None);
+}
+
+#[test]
+fn status_shows_the_time_left() {
+    let now = Utc.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
+    assert_eq!(retry_text(now + Duration::minutes(12), now), "próxima tentativa em 12min");
+    assert_eq!(retry_text(now + Duration::seconds(30), now), "próxima tentativa em 1min");
+    assert_eq!(retry_text(now + Duration::minutes(120), now), "próxima tentativa em 2h");
+    assert_eq!(retry_text(now + Duration::minutes(96), now), "próxima tentativa em 1h 36min");
+    assert_eq!(retry_text(now - Duration::minutes(1), now), "próxima tentativa em instantes");
+}
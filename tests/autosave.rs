@@ -0,0 +1,49 @@
+// Testes do salvamento automático do progresso
+use std::sync::{Arc, Mutex};
+use keepers::autosave::snapshot_tasks;
+use keepers::engine::DownloadTask;
+use keepers::storage::{new_download_id, DownloadRecord, DownloadStatus};
+use uuid::Uuid;
+
+fn record(id: Uuid, status: DownloadStatus, downloaded_bytes: u64) -> DownloadRecord {
+    DownloadRecord {
+        id,
+        status,
+        downloaded_bytes,
+        total_bytes: 1000,
+        ..DownloadRecord::new("https://exemplo.com/arquivo.iso", "arquivo.iso")
+    }
+}
+
+fn task(id: Uuid, downloaded: u64) -> Arc<Mutex<DownloadTask>> {
+    let mut task = DownloadTask::new(id);
+    task.downloaded = downloaded;
+    Arc::new(Mutex::new(task))
+}
+
+#[test]
+fn snapshot_copies_the_offset_of_downloads_in_progress() {
+    let (running, finished) = (new_download_id(), new_download_id());
+    let tasks = vec![task(running, 600), task(finished, 300)];
+    tasks[0].lock().unwrap().log("Iniciado");
+    let mut records = vec![record(running, DownloadStatus::InProgress, 100), record(finished, DownloadStatus::Completed, 1000)];
+
+    assert!(snapshot_tasks(&tasks, &mut records));
+    assert_eq!(records[0].downloaded_bytes, 600);
+    assert_eq!(records[0].events.len(), 1);
+    // Concluído: o registro final não é sobrescrito
+    assert_eq!(records[1].downloaded_bytes, 1000);
+
+    // Nada novo: não precisa gravar de novo
+    assert!(!snapshot_tasks(&tasks, &mut records));
+}
+
+#[test]
+fn snapshot_keeps_the_saved_offset_before_anything_reaches_the_disk() {
+    let id = new_download_id();
+    let tasks = vec![task(id, 0)];
+    let mut records = vec![record(id, DownloadStatus::InProgress, 400)];
+
+    assert!(!snapshot_tasks(&tasks, &mut records));
+    assert_eq!(records[0].downloaded_bytes, 400);
+}
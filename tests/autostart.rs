@@ -0,0 +1,45 @@
+// Testes da entrada de início automático
+use std::path::Path;
+use keepers::autostart::{desktop_entry, entry_path, is_enabled, launch_agent_plist, run_key_command, set_enabled, AUTOSTART_ARG};
+
+#[test]
+fn entry_quotes_paths_with_spaces() {
+    let entry = desktop_entry(Path::new("/opt/Meus Apps/keepers"));
+    assert!(entry.starts_with("[Desktop Entry]\n"));
+    assert!(entry.contains(&format!("Exec=\"/opt/Meus Apps/keepers\" {}\n", AUTOSTART_ARG)));
+
+    let entry = desktop_entry(Path::new("/usr/bin/keepers"));
+    assert!(entry.contains(&format!("Exec=/usr/bin/keepers {}\n", AUTOSTART_ARG)));
+}
+
+#[test]
+fn enabling_and_disabling_manages_the_entry_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let autostart_dir = dir.path().join("autostart");
+    let exec = Path::new("/usr/bin/keepers");
+
+    assert!(!is_enabled(&autostart_dir, "com.exemplo.app"));
+    set_enabled(&autostart_dir, "com.exemplo.app", exec, true).unwrap();
+    assert!(is_enabled(&autostart_dir, "com.exemplo.app"));
+    let contents = std::fs::read_to_string(entry_path(&autostart_dir, "com.exemplo.app")).unwrap();
+    assert_eq!(contents, desktop_entry(exec));
+
+    set_enabled(&autostart_dir, "com.exemplo.app", exec, false).unwrap();
+    assert!(!is_enabled(&autostart_dir, "com.exemplo.app"));
+    // Desativar de novo não falha
+    set_enabled(&autostart_dir, "com.exemplo.app", exec, false).unwrap();
+}
+
+#[test]
+fn windows_and_macos_entries_pass_the_autostart_argument() {
+    assert_eq!(
+        run_key_command(Path::new(r"C:\Program Files\Keepers\keepers.exe")),
+        format!(r#""C:\Program Files\Keepers\keepers.exe" {}"#, AUTOSTART_ARG)
+    );
+
+    let plist = launch_agent_plist("com.downstream.app", Path::new("/Applications/Keepers & Cia.app/Contents/MacOS/keepers"));
+    assert!(plist.contains("<string>com.downstream.app</string>"));
+    assert!(plist.contains("<string>/Applications/Keepers &amp; Cia.app/Contents/MacOS/keepers</string>"));
+    assert!(plist.contains(&format!("<string>{}</string>", AUTOSTART_ARG)));
+    assert!(plist.contains("<key>RunAtLoad</key>"));
+}
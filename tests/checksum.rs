@@ -0,0 +1,33 @@
+// Testes da leitura de listas de somas SHA-256 publicadas pelos servidores
+use keepers::checksum::{checksum_urls, find_checksum, remote_filename};
+
+const HASH_A: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+const HASH_B: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+#[test]
+fn sidecar_urls_ignore_query_strings() {
+    assert_eq!(remote_filename("https://espelho.org/iso/debian.iso?x=1"), Some("debian.iso"));
+    assert_eq!(remote_filename("https://espelho.org/iso/"), None);
+    assert_eq!(
+        checksum_urls("https://espelho.org/iso/debian.iso?x=1"),
+        vec![
+            "https://espelho.org/iso/debian.iso.sha256".to_string(),
+            "https://espelho.org/iso/SHA256SUMS".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn finds_the_entry_for_the_file_in_each_format() {
+    let gnu = format!("{}  debian.iso\n{} *./ubuntu.iso\n", HASH_A, HASH_B.to_uppercase());
+    assert_eq!(find_checksum(&gnu, "debian.iso").as_deref(), Some(HASH_A));
+    assert_eq!(find_checksum(&gnu, "ubuntu.iso").as_deref(), Some(HASH_B));
+    assert_eq!(find_checksum(&gnu, "fedora.iso"), None);
+
+    let bsd = format!("SHA256 (debian.iso) = {}\n", HASH_A);
+    assert_eq!(find_checksum(&bsd, "debian.iso").as_deref(), Some(HASH_A));
+
+    // Arquivo .sha256 só com o hash
+    assert_eq!(find_checksum(&format!("{}\n", HASH_B), "qualquer.iso").as_deref(), Some(HASH_B));
+    assert_eq!(find_checksum("<html>404</html>", "debian.iso"), None);
+}
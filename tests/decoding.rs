@@ -0,0 +1,65 @@
+// Testes da descompactação de corpos com Content-Encoding
+use std::io::Write;
+use keepers::decoding::{content_encoding, decode_file, names_encoding, ContentDecoder};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_ENCODING};
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn identity_and_missing_encodings_are_ignored() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(content_encoding(&headers), None);
+    headers.insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+    assert_eq!(content_encoding(&headers), None);
+    headers.insert(CONTENT_ENCODING, HeaderValue::from_static(" GZIP "));
+    assert_eq!(content_encoding(&headers).as_deref(), Some("gzip"));
+
+    assert!(ContentDecoder::for_encoding("br").is_some());
+    assert!(ContentDecoder::for_encoding("zstd").is_none(), "sem suporte: fica como veio");
+}
+
+#[test]
+fn gzip_body_is_decoded_piece_by_piece() {
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 97) as u8).collect();
+    let compressed = gzip(&data);
+    let mut decoder = ContentDecoder::for_encoding("gzip").unwrap();
+
+    let mut decoded = Vec::new();
+    for piece in compressed.chunks(1000) {
+        decoded.extend(decoder.decode(piece).unwrap());
+    }
+    decoded.extend(decoder.finish().unwrap());
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn corrupted_body_is_an_error() {
+    let mut decoder = ContentDecoder::for_encoding("gzip").unwrap();
+    assert!(decoder.decode(b"isto nao e gzip").is_err());
+}
+
+#[test]
+fn files_already_named_as_compressed_are_not_decoded() {
+    assert!(names_encoding("gzip", "linux-6.8.tar.gz", None));
+    assert!(names_encoding("gzip", "PACOTE.TGZ", None));
+    assert!(names_encoding("gzip", "dados", Some("application/x-gzip")));
+    assert!(names_encoding("br", "app.js.br", None));
+    assert!(!names_encoding("gzip", "pagina.html", Some("text/html")));
+    assert!(!names_encoding("br", "linux.tar.gz", None));
+}
+
+#[test]
+fn whole_file_is_decoded_at_the_end() {
+    let dir = tempfile::tempdir().unwrap();
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 89) as u8).collect();
+    let (src, dest) = (dir.path().join("a.part"), dir.path().join("a.part.decoded"));
+    std::fs::write(&src, gzip(&data)).unwrap();
+
+    assert_eq!(decode_file("gzip", &src, &dest).unwrap(), data.len() as u64);
+    assert_eq!(std::fs::read(&dest).unwrap(), data);
+    assert!(decode_file("zstd", &src, &dest).is_err());
+}
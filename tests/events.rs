@@ -0,0 +1,39 @@
+// Testes do barramento de eventos (entrega aos assinantes e perda dos eventos mais antigos)
+use keepers::engine::DownloadState;
+use keepers::events::{DownloadEvent, EventBus, EVENT_BUS_CAPACITY};
+use keepers::storage::new_download_id;
+use tokio::sync::broadcast::error::TryRecvError;
+
+#[test]
+fn every_subscriber_receives_the_events_published_after_it_joined() {
+    let bus = EventBus::new();
+    let id = new_download_id();
+    // Sem assinantes o evento some sem erro
+    bus.publish(DownloadEvent::Started(id));
+
+    let mut first = bus.subscribe();
+    let mut second = bus.subscribe();
+    bus.publish(DownloadEvent::StateChanged(id, DownloadState::Paused));
+    bus.publish(DownloadEvent::Removed(id));
+
+    for receiver in [&mut first, &mut second] {
+        assert_eq!(receiver.try_recv().unwrap(), DownloadEvent::StateChanged(id, DownloadState::Paused));
+        assert_eq!(receiver.try_recv().unwrap(), DownloadEvent::Removed(id));
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    }
+}
+
+#[test]
+fn slow_subscribers_lose_the_oldest_events() {
+    let bus = EventBus::new();
+    let mut receiver = bus.subscribe();
+    let id = new_download_id();
+    for _ in 0..EVENT_BUS_CAPACITY + 10 {
+        bus.publish(DownloadEvent::Started(id));
+    }
+    bus.publish(DownloadEvent::Failed { id, error: "fim".to_string() });
+
+    assert!(matches!(receiver.try_recv(), Err(TryRecvError::Lagged(_))));
+    let last = std::iter::from_fn(|| receiver.try_recv().ok()).last();
+    assert_eq!(last, Some(DownloadEvent::Failed { id, error: "fim".to_string() }));
+}
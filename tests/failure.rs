@@ -0,0 +1,61 @@
+// Testes da triagem das falhas (categoria, badge e próximo passo)
+use keepers::failure::{is_signed_url, link_expired, Failure, FailureKind};
+use keepers::http::{HttpError, HttpErrorKind};
+use reqwest::StatusCode;
+
+#[test]
+fn http_status_picks_the_category() {
+    assert_eq!(FailureKind::from_status(404), FailureKind::Gone);
+    assert_eq!(FailureKind::from_status(410), FailureKind::Gone);
+    assert_eq!(FailureKind::from_status(403), FailureKind::AuthRequired);
+    assert_eq!(FailureKind::from_status(401), FailureKind::AuthRequired);
+    assert_eq!(FailureKind::from_status(503), FailureKind::Server);
+    assert_eq!(FailureKind::from_status(400), FailureKind::Other);
+    let gone = HttpError::from_status(HttpErrorKind::Other, StatusCode::NOT_FOUND);
+    assert_eq!(FailureKind::from_http(&gone), FailureKind::Gone);
+}
+
+#[test]
+fn local_and_network_errors_are_told_apart() {
+    assert_eq!(FailureKind::from_http(&HttpError::new(HttpErrorKind::Timeout, "operation timed out")), FailureKind::Network);
+    assert_eq!(FailureKind::from_http(&HttpError::new(HttpErrorKind::Connect, "error sending request")), FailureKind::Network);
+    assert_eq!(FailureKind::from_http(&HttpError::new(HttpErrorKind::ServerBusy, "Status HTTP: 503")), FailureKind::Server);
+    assert_eq!(FailureKind::from_http(&HttpError::new(HttpErrorKind::Other, "Checksum não confere")), FailureKind::Other);
+
+    // A categoria não depende do texto: vale a do ponto onde o erro aconteceu
+    let disk = Failure::disk("No space left on device (os error 28)").within("Erro ao baixar chunks");
+    assert_eq!(disk.kind, FailureKind::Disk);
+    assert_eq!(disk.message, "Erro ao baixar chunks: No space left on device (os error 28)");
+    let timeout = Failure::http("Chunk 2 falhou após 5 tentativas", &HttpError::new(HttpErrorKind::Timeout, "operation timed out"));
+    assert_eq!(timeout.kind, FailureKind::Network);
+    assert!(timeout.message.starts_with("Chunk 2 falhou após 5 tentativas: "));
+}
+
+#[test]
+fn only_transient_failures_suggest_a_retry() {
+    assert!(FailureKind::Network.is_transient());
+    assert!(FailureKind::Server.is_transient());
+    assert!(!FailureKind::Gone.is_transient());
+    assert!(!FailureKind::AuthRequired.is_transient());
+    assert!(!FailureKind::Disk.is_transient());
+    assert_eq!(FailureKind::AuthRequired.action_label(), Some("Adicionar credenciais"));
+    assert_eq!(FailureKind::Gone.action_label(), None);
+}
+
+#[test]
+fn signed_links_are_recognized() {
+    assert!(is_signed_url("https://bucket.s3.amazonaws.com/a.iso?X-Amz-Expires=3600&X-Amz-Signature=abc"));
+    assert!(is_signed_url("https://cdn.example.com/a.iso?Expires=1700000000&Signature=abc&Key-Pair-Id=K1"));
+    assert!(is_signed_url("https://conta.blob.core.windows.net/c/a.iso?se=2026-01-01&sig=abc"));
+    assert!(!is_signed_url("https://example.com/a.iso?version=2"));
+    assert!(!is_signed_url("https://example.com/a.iso"));
+}
+
+#[test]
+fn expired_link_needs_a_refused_signed_url_that_already_worked() {
+    let url = "https://bucket.s3.amazonaws.com/a.iso?X-Amz-Signature=abc";
+    assert!(link_expired(FailureKind::AuthRequired, url, 1024));
+    assert!(!link_expired(FailureKind::AuthRequired, url, 0));
+    assert!(!link_expired(FailureKind::Gone, url, 1024));
+    assert!(!link_expired(FailureKind::AuthRequired, "https://example.com/a.iso", 1024));
+}
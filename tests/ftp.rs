@@ -0,0 +1,196 @@
+// Testes do FTP: respostas do servidor e downloads/listagens contra um servidor FTP simulado
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use keepers::engine::{list_directory, run_download, DownloadMessage, DownloadTask};
+use keepers::failure::FailureKind;
+use keepers::ftp::{epsv_port, mdtm_date, pasv_port, FtpBackend};
+use keepers::http::{HttpBackend, HttpErrorKind, HttpRequest, ReqwestBackend, SharedBackend};
+use keepers::storage::new_download_id;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+const LISTING: &str = "drwxr-xr-x    2 ftp      ftp          4096 Jan 31  2024 isos\r\n\
+-rw-r--r--    1 ftp      ftp       1048576 Jan 31 12:00 dados.bin\r\n";
+
+#[derive(Default)]
+struct ServerState {
+    rests: Vec<u64>, // Posições pedidas com REST antes de cada RETR
+    users: Vec<String>,
+}
+
+fn sample_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+// Servidor FTP mínimo: login, EPSV, SIZE, MDTM, REST, RETR e LIST de "/pub/"
+async fn spawn_ftp(data: Vec<u8>) -> (SocketAddr, Arc<Mutex<ServerState>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let state = Arc::new(Mutex::new(ServerState::default()));
+    let data = Arc::new(data);
+    let server_state = state.clone();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(serve(socket, data.clone(), server_state.clone()));
+        }
+    });
+    (addr, state)
+}
+
+async fn serve(socket: TcpStream, data: Arc<Vec<u8>>, state: Arc<Mutex<ServerState>>) {
+    let (reader, mut writer) = socket.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let _ = writer.write_all(b"220-Bem-vindo\r\n220 pronto\r\n").await;
+    let mut passive = None;
+    let mut offset = 0u64;
+    while let Ok(Some(line)) = lines.next_line().await {
+        let (command, arg) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+        let reply = match command {
+            "USER" => {
+                state.lock().unwrap().users.push(arg.to_string());
+                "331 senha".to_string()
+            }
+            "PASS" => "230 ok".to_string(),
+            "TYPE" => "200 binario".to_string(),
+            "EPSV" => {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let port = listener.local_addr().unwrap().port();
+                passive = Some(listener);
+                format!("229 Entering Extended Passive Mode (|||{}|)", port)
+            }
+            "SIZE" if arg == "/pub/dados.bin" => format!("213 {}", data.len()),
+            "MDTM" if arg == "/pub/dados.bin" => "213 20240131120000".to_string(),
+            "REST" => {
+                offset = arg.parse().unwrap();
+                "350 ok".to_string()
+            }
+            "RETR" if arg == "/pub/dados.bin" => {
+                state.lock().unwrap().rests.push(offset);
+                let body = data[offset as usize..].to_vec();
+                offset = 0;
+                send_data(&mut writer, passive.take(), &body).await
+            }
+            "LIST" if arg == "/pub/" => send_data(&mut writer, passive.take(), LISTING.as_bytes()).await,
+            "SIZE" | "MDTM" | "RETR" | "LIST" => "550 Arquivo não encontrado".to_string(),
+            _ => "502 comando não implementado".to_string(),
+        };
+        if writer.write_all(format!("{}\r\n", reply).as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+// "150" no controle, os dados na conexão passiva e a resposta final (426 se o cliente fechou antes)
+async fn send_data(writer: &mut tokio::net::tcp::OwnedWriteHalf, passive: Option<TcpListener>, body: &[u8]) -> String {
+    let Some(listener) = passive else {
+        return "425 sem conexão de dados".to_string();
+    };
+    let _ = writer.write_all(b"150 abrindo conexao de dados\r\n").await;
+    let (mut data, _) = listener.accept().await.unwrap();
+    match data.write_all(body).await {
+        Ok(()) => "226 transferencia completa".to_string(),
+        Err(_) => "426 conexao fechada".to_string(),
+    }
+}
+
+fn ftp_backend() -> SharedBackend {
+    Arc::new(FtpBackend::new(Arc::new(ReqwestBackend::new().unwrap())))
+}
+
+async fn run(url: &str, filename: &str, dir: &std::path::Path) -> Vec<DownloadMessage> {
+    let (tx, rx) = async_channel::unbounded();
+    let task = Arc::new(Mutex::new(DownloadTask::new(new_download_id())));
+    run_download(ftp_backend(), url, filename, dir, &tx, &task, &Arc::new(Mutex::new(Vec::new()))).await;
+    let mut messages = Vec::new();
+    while let Ok(message) = rx.try_recv() {
+        messages.push(message);
+    }
+    messages
+}
+
+#[test]
+fn passive_replies_and_dates_are_parsed() {
+    assert_eq!(pasv_port("Entering Passive Mode (192,168,0,1,195,80)."), Some(50000));
+    assert_eq!(pasv_port("Entering Passive Mode 10,0,0,1,4,1"), Some(1025));
+    assert_eq!(pasv_port("Entering Passive Mode"), None);
+    assert_eq!(epsv_port("Entering Extended Passive Mode (|||6446|)"), Some(6446));
+    assert_eq!(epsv_port("EPSV desativado"), None);
+
+    let date = mdtm_date("20240131120000.123").unwrap();
+    assert_eq!(date.to_rfc3339(), "2024-01-31T12:00:00+00:00");
+    assert_eq!(mdtm_date("ontem"), None);
+}
+
+#[tokio::test]
+async fn ftp_folder_is_listed_with_sizes() {
+    let (addr, state) = spawn_ftp(Vec::new()).await;
+
+    let entries = list_directory(&ftp_backend(), &format!("ftp://{}/pub", addr)).await.unwrap();
+
+    let summary: Vec<(&str, bool, Option<u64>)> = entries.iter().map(|e| (e.name.as_str(), e.is_dir, e.size)).collect();
+    assert_eq!(summary, vec![("isos", true, None), ("dados.bin", false, Some(1048576))]);
+    assert_eq!(entries[1].url, format!("ftp://{}/pub/dados.bin", addr));
+    // Sem usuário na URL: login anônimo
+    assert_eq!(state.lock().unwrap().users, vec!["anonymous"]);
+}
+
+#[tokio::test]
+async fn ftp_file_is_downloaded_in_ranges() {
+    let data = sample_data(3 * 1024 * 1024 + 123);
+    let (addr, state) = spawn_ftp(data.clone()).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let messages = run(&format!("ftp://usuario:senha@{}/pub/dados.bin", addr), "dados.bin", dir.path()).await;
+
+    assert!(matches!(messages.last(), Some(DownloadMessage::Complete)), "{:?}", messages.last());
+    assert_eq!(std::fs::read(dir.path().join("dados.bin")).unwrap(), data);
+    let state = state.lock().unwrap();
+    assert!(state.users.iter().all(|user| user == "usuario"));
+    // Cada chunk é uma conexão própria que começa com REST na sua posição
+    assert!(state.rests.len() > 1, "{:?}", state.rests);
+    assert!(state.rests.contains(&0) && state.rests.iter().any(|rest| *rest > 0));
+}
+
+#[tokio::test]
+async fn missing_ftp_file_fails_as_gone() {
+    let (addr, _state) = spawn_ftp(sample_data(1024)).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let messages = run(&format!("ftp://{}/pub/sumiu.bin", addr), "sumiu.bin", dir.path()).await;
+
+    match messages.last() {
+        Some(DownloadMessage::Error(failure)) => {
+            assert_eq!(failure.kind, FailureKind::Gone);
+            assert!(failure.message.contains("550"), "{}", failure.message);
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn line_breaks_in_the_url_never_reach_the_control_channel() {
+    let (addr, state) = spawn_ftp(sample_data(1024)).await;
+
+    for url in [
+        format!("ftp://{}/pub/x%0D%0ADELE%20y", addr),
+        format!("ftp://eu%0D%0ADELE%20y@{}/pub/dados.bin", addr),
+        format!("ftp://eu:senha%0AQUIT@{}/pub/dados.bin", addr),
+    ] {
+        let error = ftp_backend().send(HttpRequest::head(&url)).await.err().unwrap();
+        assert_eq!(error.kind, HttpErrorKind::Request, "{}", url);
+    }
+    assert!(state.lock().unwrap().users.is_empty());
+}
+
+#[tokio::test]
+async fn ftp_is_refused_when_the_client_uses_a_proxy() {
+    let (addr, state) = spawn_ftp(sample_data(1024)).await;
+    let backend = FtpBackend::new(Arc::new(ReqwestBackend::new().unwrap())).proxied(true);
+
+    let error = backend.send(HttpRequest::head(&format!("ftp://{}/pub/dados.bin", addr))).await.err().unwrap();
+
+    assert!(error.message.contains("proxy"), "{}", error.message);
+    assert!(!error.is_recoverable());
+    // Nenhuma conexão direta ao servidor
+    assert!(state.lock().unwrap().users.is_empty());
+}
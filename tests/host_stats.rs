@@ -0,0 +1,90 @@
+// Testes do histórico por servidor (velocidade, falhas, Range e conexões iniciais)
+use keepers::host_stats::{host_listed, host_of, host_warning, HostBook, FLAKY_CONNECTIONS, THROTTLED_CONNECTIONS};
+
+#[test]
+fn hosts_are_keyed_by_lowercase_host_name() {
+    assert_eq!(host_of("https://Espelho.ORG:8080/pub/a.iso").as_deref(), Some("espelho.org"));
+    assert_eq!(host_of("não é url"), None);
+}
+
+#[test]
+fn speed_errors_and_range_support_are_accumulated() {
+    let mut book = HostBook::default();
+    book.record_success("https://a.com/1.zip", 1000.0, Some(true), 4);
+    book.record_success("https://a.com/2.zip", 3000.0, Some(true), 6);
+    book.record_failure("https://a.com/3.zip");
+
+    let stats = book.get("https://a.com/outro").unwrap();
+    assert_eq!(stats.attempts(), 3);
+    assert_eq!(stats.average_speed(), Some(2000.0));
+    assert!((stats.error_rate() - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(stats.supports_range(), Some(true));
+    assert!(!stats.is_flaky());
+    // Começa com as conexões que o último download usou
+    assert_eq!(book.initial_connections("https://a.com/novo.zip"), Some(6));
+    assert_eq!(book.initial_connections("https://b.com/novo.zip"), None);
+}
+
+#[test]
+fn flaky_hosts_start_with_one_connection_and_warn() {
+    let mut book = HostBook::default();
+    book.record_success("http://instavel.net/a", 500.0, Some(false), 0);
+    book.record_failure("http://instavel.net/b");
+    assert!(!book.get("http://instavel.net/").unwrap().is_flaky(), "poucos downloads para julgar");
+    book.record_failure("http://instavel.net/c");
+
+    let stats = book.get("http://instavel.net/").unwrap();
+    assert!(stats.is_flaky());
+    assert_eq!(book.initial_connections("http://instavel.net/d"), Some(FLAKY_CONNECTIONS));
+    assert_eq!(host_warning(stats).as_deref(), Some("Servidor instável: 2 de 3 downloads falharam"));
+
+    let mut no_resume = HostBook::default();
+    no_resume.record_success("http://sem-range.org/a", 1.0, Some(false), 0);
+    assert!(host_warning(no_resume.get("http://sem-range.org/").unwrap()).unwrap().contains("retomar"));
+}
+
+#[test]
+fn busiest_hosts_come_first() {
+    let mut book = HostBook::default();
+    book.record_failure("http://pouco.com/a");
+    for _ in 0..3 {
+        book.record_success("http://muito.com/a", 1.0, None, 0);
+    }
+    let hosts: Vec<&str> = book.ranked().into_iter().map(|(host, _)| host.as_str()).collect();
+    assert_eq!(hosts, vec!["muito.com", "pouco.com"]);
+}
+
+#[test]
+fn hosts_that_limit_each_connection_force_chunks() {
+    let configured = vec!["Exemplo.com".to_string()];
+    assert!(host_listed(&configured, "https://exemplo.com/a.zip"));
+    assert!(host_listed(&configured, "https://cdn.exemplo.com/a.zip"));
+    assert!(!host_listed(&configured, "https://outroexemplo.com/a.zip"));
+
+    let mut book = HostBook::default();
+    assert!(book.forces_segmented("https://cdn.exemplo.com/a.zip", &configured));
+    assert!(!book.forces_segmented("https://lento.net/a.zip", &configured));
+    // Só ficou rápido abrindo várias conexões: o servidor limita cada uma
+    book.record_success("https://lento.net/a.zip", 1000.0, Some(true), THROTTLED_CONNECTIONS);
+    assert!(book.forces_segmented("https://lento.net/b.zip", &[]));
+    book.record_success("https://rapido.org/a.zip", 1000.0, Some(true), 2);
+    assert!(!book.forces_segmented("https://rapido.org/b.zip", &[]));
+}
+
+#[test]
+fn session_history_adds_to_the_saved_one() {
+    let mut saved = HostBook::default();
+    saved.record_success("https://espelho.org/a.iso", 1000.0, Some(true), 2);
+    saved.record_failure("https://espelho.org/b.iso");
+    let mut session = HostBook::default();
+    session.record_success("https://espelho.org/c.iso", 3000.0, Some(true), 6);
+    session.record_failure("https://outro.net/d.iso");
+
+    saved.merge(&session);
+
+    let stats = saved.get("https://espelho.org/").unwrap();
+    assert_eq!((stats.completed, stats.failed, stats.range_supported), (2, 1, 2));
+    assert_eq!(stats.average_speed(), Some(2000.0));
+    assert_eq!(stats.connections, Some(6));
+    assert_eq!(saved.get("https://outro.net/").unwrap().failed, 1);
+}
@@ -0,0 +1,125 @@
+// Testes dos CIDs e da escolha de gateways para URLs ipfs://
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use keepers::http::{HttpBackend, HttpError, HttpRequest, HttpResponse};
+use keepers::ipfs::{gateway_url, parse_ipfs_url, raw_sha256, Cid, IpfsBackend};
+use reqwest::header::HeaderMap;
+use reqwest::{StatusCode, Version};
+
+// CIDv1 "raw" do texto "conteudo do keepers"
+const RAW_CID: &str = "bafkreiczahq5fthi3sl4dthvbeejsobkv46wohs2cx22anvjxmo5se7ksa";
+const RAW_SHA256: &str = "5901e1d2cce8dc97c1ccf5090899382aaf3d671e5a15f5a036a9bb1dd913ea90";
+
+#[test]
+fn ipfs_urls_map_to_gateway_paths() {
+    assert_eq!(parse_ipfs_url("ipfs://bafyabc/pasta/a.bin"), Some(("bafyabc", "/pasta/a.bin")));
+    assert_eq!(parse_ipfs_url("ipfs://bafyabc"), Some(("bafyabc", "")));
+    assert_eq!(parse_ipfs_url("ipfs://"), None);
+    assert_eq!(parse_ipfs_url("https://ipfs.io/ipfs/bafyabc"), None);
+
+    assert_eq!(gateway_url("https://ipfs.io/", "bafyabc", "/a.bin"), "https://ipfs.io/ipfs/bafyabc/a.bin");
+}
+
+#[test]
+fn cids_decode_to_their_multihash() {
+    let v0 = Cid::parse("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG").unwrap();
+    assert_eq!((v0.version, v0.codec, v0.hash_code), (0, 0x70, 0x12));
+    assert_eq!(
+        v0.digest,
+        [
+            0x9d, 0x6c, 0x2b, 0xe5, 0x0f, 0x70, 0x69, 0x53, 0x47, 0x9a, 0xb9, 0xdf, 0x2c, 0xe3, 0xed, 0xca,
+            0x90, 0xb6, 0x80, 0x53, 0xc0, 0x0b, 0x30, 0x04, 0xb7, 0xf0, 0xac, 0xcb, 0xe1, 0xe8, 0xee, 0xdf,
+        ]
+    );
+
+    let raw = Cid::parse(RAW_CID).unwrap();
+    assert_eq!((raw.version, raw.codec, raw.hash_code), (1, 0x55, 0x12));
+    assert_eq!(raw_sha256(&format!("ipfs://{}", RAW_CID)).as_deref(), Some(RAW_SHA256));
+
+    // dag-pb (arquivos fatiados) e caminhos dentro de diretórios não dão para conferir
+    let dag_pb = "bafybeiczahq5fthi3sl4dthvbeejsobkv46wohs2cx22anvjxmo5se7ksa";
+    assert_eq!(Cid::parse(dag_pb).unwrap().codec, 0x70);
+    assert_eq!(raw_sha256(&format!("ipfs://{}", dag_pb)), None);
+    assert_eq!(raw_sha256(&format!("ipfs://{}/a.bin", RAW_CID)), None);
+    assert_eq!(Cid::parse("bafk!"), None);
+}
+
+// Cada gateway responde com o status e o atraso configurados
+struct GatewayBackend {
+    gateways: Mutex<Vec<(&'static str, StatusCode, u64)>>,
+    requests: Mutex<Vec<String>>,
+}
+
+impl HttpBackend for GatewayBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        self.requests.lock().unwrap().push(request.url.clone());
+        let gateways = self.gateways.lock().unwrap();
+        let (_, status, delay) = *gateways.iter().find(|(host, _, _)| request.url.starts_with(host)).unwrap();
+        async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(HttpResponse { status, version: Version::HTTP_11, headers: HeaderMap::new(), body: futures_util::stream::empty().boxed() })
+        }
+        .boxed()
+    }
+}
+
+fn gateway_backend(gateways: Vec<(&'static str, StatusCode, u64)>) -> (Arc<GatewayBackend>, IpfsBackend) {
+    let inner = Arc::new(GatewayBackend { gateways: Mutex::new(gateways.clone()), requests: Mutex::new(Vec::new()) });
+    let backend = IpfsBackend::new(inner.clone(), gateways.iter().map(|(host, _, _)| host.to_string()).collect());
+    (inner, backend)
+}
+
+#[tokio::test]
+async fn fastest_working_gateway_wins_the_race() {
+    let (_, backend) = gateway_backend(vec![
+        ("https://lento", StatusCode::OK, 200),
+        ("https://sem-conteudo", StatusCode::NOT_FOUND, 0),
+        ("https://rapido", StatusCode::OK, 10),
+    ]);
+
+    let response = backend.send(HttpRequest::head(&format!("ipfs://{}", RAW_CID))).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(backend.preferred_gateway(), Some("https://rapido"));
+}
+
+#[tokio::test]
+async fn later_requests_fail_over_from_the_preferred_gateway() {
+    let (inner, backend) = gateway_backend(vec![
+        ("https://a", StatusCode::OK, 0),
+        ("https://b", StatusCode::SERVICE_UNAVAILABLE, 0),
+    ]);
+    backend.send(HttpRequest::head("ipfs://bafyabc/a.bin")).await.unwrap();
+    assert_eq!(backend.preferred_gateway(), Some("https://a"));
+    inner.requests.lock().unwrap().clear();
+
+    // Depois da corrida, só o gateway escolhido é consultado
+    backend.send(HttpRequest::get("ipfs://bafyabc/a.bin").range(0, Some(9))).await.unwrap();
+    assert_eq!(*inner.requests.lock().unwrap(), vec!["https://a/ipfs/bafyabc/a.bin".to_string()]);
+
+    // O escolhido caiu: o próximo que responder assume
+    inner.gateways.lock().unwrap()[0].1 = StatusCode::BAD_GATEWAY;
+    inner.gateways.lock().unwrap()[1].1 = StatusCode::PARTIAL_CONTENT;
+    let response = backend.send(HttpRequest::get("ipfs://bafyabc/a.bin").range(10, Some(19))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(backend.preferred_gateway(), Some("https://b"));
+
+    // URLs comuns seguem direto
+    let (inner, backend) = gateway_backend(vec![("https://exemplo.com", StatusCode::OK, 0)]);
+    backend.send(HttpRequest::get("https://exemplo.com/b.bin")).await.unwrap();
+    assert_eq!(*inner.requests.lock().unwrap(), vec!["https://exemplo.com/b.bin".to_string()]);
+}
+
+#[tokio::test]
+async fn every_gateway_failing_returns_the_last_answer() {
+    let (_, backend) = gateway_backend(vec![
+        ("https://a", StatusCode::NOT_FOUND, 0),
+        ("https://b", StatusCode::BAD_GATEWAY, 5),
+    ]);
+
+    let response = backend.send(HttpRequest::head("ipfs://bafyabc")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(backend.preferred_gateway(), None);
+}
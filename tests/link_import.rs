@@ -0,0 +1,79 @@
+// Testes da importação de listas de links do uGet e do JDownloader
+use keepers::link_import::{parse_crawljob, parse_decrypted_dlc, parse_link_list, parse_uget_category, ImportedLink};
+
+fn link(url: &str, category: Option<&str>) -> ImportedLink {
+    ImportedLink { url: url.to_string(), category: category.map(str::to_string) }
+}
+
+#[test]
+fn crawljob_properties_keep_the_package_of_each_job() {
+    let contents = "\
+# job do folderwatch
+text=https\\://exemplo.com/a.iso https://exemplo.com/b.iso
+packageName=Distros
+autoStart=TRUE
+
+text=https://outro.org/c.zip
+";
+
+    assert_eq!(
+        parse_crawljob(contents).unwrap(),
+        vec![
+            link("https://exemplo.com/a.iso", Some("Distros")),
+            link("https://exemplo.com/b.iso", Some("Distros")),
+            link("https://outro.org/c.zip", None),
+        ]
+    );
+}
+
+#[test]
+fn crawljob_json_lists_are_read() {
+    let contents = r#"[{"text": "https://exemplo.com/a.iso", "packageName": "ISOs"}, {"text": "https://exemplo.com/b.iso"}]"#;
+
+    assert_eq!(
+        parse_crawljob(contents).unwrap(),
+        vec![link("https://exemplo.com/a.iso", Some("ISOs")), link("https://exemplo.com/b.iso", None)]
+    );
+}
+
+#[test]
+fn decrypted_dlc_fields_are_base64() {
+    // "Filmes" e "https://exemplo.com/f.mkv"; o segundo arquivo vem em texto puro
+    let contents = r#"<dlc><header></header><content>
+        <package name="RmlsbWVz"><file><url>aHR0cHM6Ly9leGVtcGxvLmNvbS9mLm1rdg==</url><filename>Zi5ta3Y=</filename></file>
+        <file><url>https://exemplo.com/g.mkv</url></file></package>
+    </content></dlc>"#;
+
+    assert_eq!(
+        parse_decrypted_dlc(contents).unwrap(),
+        vec![link("https://exemplo.com/f.mkv", Some("Filmes")), link("https://exemplo.com/g.mkv", Some("Filmes"))]
+    );
+    assert!(parse_decrypted_dlc("QmluYXJpbyBjaWZyYWRv").is_err());
+}
+
+#[test]
+fn uget_category_links_take_the_category_name() {
+    let contents = r#"{
+        "name": "Vídeos",
+        "children": [
+            {"name": "a.mp4", "info": {"common": {"uri": "https://exemplo.com/a.mp4", "folder": "/home/eu"}}},
+            {"name": "b.mp4", "info": {"common": {"uris": ["http://espelho.net/b.mp4"]}}}
+        ]
+    }"#;
+
+    assert_eq!(
+        parse_uget_category(contents).unwrap(),
+        vec![link("https://exemplo.com/a.mp4", Some("Vídeos")), link("http://espelho.net/b.mp4", Some("Vídeos"))]
+    );
+}
+
+#[test]
+fn format_follows_the_file_extension() {
+    let text = "https://exemplo.com/a.iso\n# comentário\n\nftp://exemplo.com/b.iso\n";
+    assert_eq!(
+        parse_link_list("uget-export.txt", text).unwrap(),
+        vec![link("https://exemplo.com/a.iso", None), link("ftp://exemplo.com/b.iso", None)]
+    );
+    assert!(parse_link_list("vazio.crawljob", "autoStart=TRUE\n").is_err());
+    assert!(parse_link_list("quebrado.json", "{").is_err());
+}
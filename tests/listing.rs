@@ -0,0 +1,180 @@
+// Testes da leitura de listagens de diretório, dos links de páginas e da navegação recursiva
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use keepers::engine::walk_directory;
+use keepers::http::{HttpBackend, HttpError, HttpRequest, HttpResponse, SharedBackend};
+use keepers::listing::{
+    extract_links, filter_links, parse_extensions, parse_ftp_list, parse_index, parse_size, redirect_target,
+    relative_folder,
+};
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::{StatusCode, Version};
+
+const APACHE_INDEX: &str = r#"<html><head><title>Index of /pub</title></head><body>
+<table>
+<tr><th><a href="?C=N;O=D">Name</a></th><th><a href="?C=S;O=A">Size</a></th></tr>
+<tr><td valign="top"><img src="/icons/back.gif"></td><td><a href="/">Parent Directory</a></td><td>&nbsp;</td><td align="right">  - </td></tr>
+<tr><td valign="top"><img src="/icons/folder.gif"></td><td><a href="isos/">isos/</a></td><td align="right">2026-10-01 10:00  </td><td align="right">  - </td></tr>
+<tr><td valign="top"><img src="/icons/text.gif"></td><td><a href="LEIA%20ME.txt">LEIA ME.txt</a></td><td align="right">2026-10-01 10:00  </td><td align="right">1.5K</td></tr>
+</table></body></html>"#;
+
+const NGINX_INDEX: &str = "<html><head><title>Index of /pub/isos/</title></head><body>\n\
+<h1>Index of /pub/isos/</h1><hr><pre><a href=\"../\">../</a>\n\
+<a href=\"antigas/\">antigas/</a>                                            01-Oct-2026 10:00                   -\n\
+<a href=\"distro.iso\">distro.iso</a>                                         01-Oct-2026 10:00          734003200\n\
+<a href=\"https://outro.site/x.iso\">externo</a>\n\
+</pre><hr></body></html>";
+
+#[test]
+fn apache_index_lists_children_with_sizes() {
+    let entries = parse_index(APACHE_INDEX, "https://espelho.org/pub");
+
+    let summary: Vec<(&str, bool, Option<u64>)> =
+        entries.iter().map(|e| (e.name.as_str(), e.is_dir, e.size)).collect();
+    assert_eq!(summary, vec![("isos", true, None), ("LEIA ME.txt", false, Some(1536))]);
+    assert_eq!(entries[1].url, "https://espelho.org/pub/LEIA%20ME.txt");
+}
+
+#[test]
+fn nginx_index_skips_parent_and_external_links() {
+    let entries = parse_index(NGINX_INDEX, "https://espelho.org/pub/isos/");
+
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["antigas", "distro.iso"]);
+    assert_eq!(entries[1].size, Some(734003200));
+
+    // Os links externos continuam disponíveis para quem quiser a página inteira
+    assert!(extract_links(NGINX_INDEX, "https://espelho.org/pub/isos/")
+        .iter()
+        .any(|link| link.url == "https://outro.site/x.iso" && link.text == "externo"));
+}
+
+#[test]
+fn ftp_list_output_in_unix_and_iis_formats() {
+    let unix = "total 12\r\n\
+drwxr-xr-x    2 ftp      ftp          4096 Jan 31  2024 .\r\n\
+drwxr-xr-x    5 ftp      ftp          4096 Jan 31  2024 antigas\r\n\
+-rw-r--r--    1 ftp      ftp     734003200 Jan 31 12:00 distro 1.0.iso\r\n\
+lrwxrwxrwx    1 ftp      ftp            14 Jan 31 12:00 atual.iso -> distro 1.0.iso\r\n";
+    let entries = parse_ftp_list(unix, "ftp://espelho.org/pub");
+    let summary: Vec<(&str, bool, Option<u64>)> = entries.iter().map(|e| (e.name.as_str(), e.is_dir, e.size)).collect();
+    assert_eq!(
+        summary,
+        vec![("antigas", true, None), ("distro 1.0.iso", false, Some(734003200)), ("atual.iso", false, Some(14))]
+    );
+    assert_eq!(entries[0].url, "ftp://espelho.org/pub/antigas/");
+    assert_eq!(entries[1].url, "ftp://espelho.org/pub/distro%201.0.iso");
+
+    let iis = "01-31-24  10:00AM       <DIR>          drivers\r\n01-31-24  10:00AM              2048 leia#me.txt\r\n";
+    let entries = parse_ftp_list(iis, "ftp://espelho.org/pub/");
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].is_dir);
+    assert_eq!(entries[1].size, Some(2048));
+    assert_eq!(entries[1].url, "ftp://espelho.org/pub/leia%23me.txt");
+}
+
+#[test]
+fn sizes_and_relative_folders() {
+    assert_eq!(parse_size("01-Oct-2026 10:00 12345"), Some(12345));
+    assert_eq!(parse_size("2026-10-01 10:00 3M"), Some(3 * 1024 * 1024));
+    assert_eq!(parse_size("2026-10-01 10:00 -"), None);
+
+    let base = "https://espelho.org/pub";
+    assert_eq!(relative_folder(base, "https://espelho.org/pub/a.iso").as_deref(), Some(""));
+    assert_eq!(relative_folder(base, "https://espelho.org/pub/isos/velhas%20isos/a.iso").as_deref(), Some("isos/velhas isos"));
+    assert_eq!(relative_folder(base, "https://outro.site/a.iso"), None);
+}
+
+// Serve páginas de índice fixas, por URL
+struct IndexBackend {
+    pages: HashMap<String, String>,
+}
+
+impl HttpBackend for IndexBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        let page = self.pages.get(&request.url).cloned();
+        async move {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html; charset=utf-8"));
+            let (status, body) = match page {
+                Some(page) => (StatusCode::OK, page),
+                None => (StatusCode::NOT_FOUND, String::new()),
+            };
+            let body = futures_util::stream::once(async move { Ok(bytes::Bytes::from(body)) }).boxed();
+            Ok(HttpResponse { status, version: Version::HTTP_11, headers, body })
+        }
+        .boxed()
+    }
+}
+
+#[tokio::test]
+async fn walking_a_folder_collects_files_from_subfolders() {
+    let pages = HashMap::from([
+        ("https://espelho.org/pub/".to_string(), APACHE_INDEX.to_string()),
+        ("https://espelho.org/pub/isos/".to_string(), NGINX_INDEX.to_string()),
+        // "antigas/" não responde: é ignorada sem perder o resto
+    ]);
+    let backend: SharedBackend = Arc::new(IndexBackend { pages });
+
+    let mut files: Vec<String> = walk_directory(&backend, "https://espelho.org/pub")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.url)
+        .collect();
+    files.sort();
+
+    assert_eq!(
+        files,
+        vec!["https://espelho.org/pub/LEIA%20ME.txt", "https://espelho.org/pub/isos/distro.iso"]
+    );
+    assert!(walk_directory(&backend, "https://espelho.org/nada/").await.is_err());
+}
+
+#[test]
+fn page_links_filter_by_extension_and_pattern() {
+    let page = "https://projeto.org/downloads.html";
+    let html = r##"<p><a href="#topo">Topo</a> <a href="mailto:a@b.c">Contato</a></p>
+<a href="/rel/app-1.0.zip">Versão 1.0</a>
+<a href="/rel/app-2.0.ZIP?x=1">Versão 2.0</a>
+<a href="/rel/app-2.0.zip?x=1#notas">Versão 2.0 (de novo)</a>
+<a href="https://cdn.projeto.org/app-2.0.tar.gz">Tarball</a>
+<a href="docs.html">Documentação</a>"##;
+    let links = extract_links(html, page);
+
+    let urls = |links: Vec<keepers::listing::PageLink>| links.into_iter().map(|l| l.url).collect::<Vec<_>>();
+    assert_eq!(filter_links(&links, page, &[], None).len(), 5); // Sem âncoras nem mailto
+    assert_eq!(
+        urls(filter_links(&links, page, &parse_extensions(".zip, tar.gz"), None)),
+        vec![
+            "https://projeto.org/rel/app-1.0.zip",
+            "https://projeto.org/rel/app-2.0.ZIP?x=1",
+            "https://projeto.org/rel/app-2.0.zip?x=1",
+            "https://cdn.projeto.org/app-2.0.tar.gz",
+        ]
+    );
+    let pattern = Regex::new(r"app-2\.\d").unwrap();
+    assert_eq!(
+        urls(filter_links(&links, page, &parse_extensions("tar.gz"), Some(&pattern))),
+        vec!["https://cdn.projeto.org/app-2.0.tar.gz"]
+    );
+}
+
+#[test]
+fn redirector_pages_point_to_the_real_file() {
+    let page = "https://arquivos.example/get.php?id=42";
+    let meta = r#"<head><META HTTP-EQUIV="Refresh" CONTENT="5; URL='/files/app.iso'"></head>"#;
+    assert_eq!(redirect_target(meta, page).as_deref(), Some("https://arquivos.example/files/app.iso"));
+
+    let script = r#"<script>setTimeout(function() { window.location.href = "https://cdn.example/app.iso?t=1"; }, 3000);</script>"#;
+    assert_eq!(redirect_target(script, page).as_deref(), Some("https://cdn.example/app.iso?t=1"));
+    let replace = r#"<script>location.replace('mirror/app.iso')</script>"#;
+    assert_eq!(redirect_target(replace, page).as_deref(), Some("https://arquivos.example/mirror/app.iso"));
+
+    // Meta sem refresh ou destino que não é http(s)
+    assert_eq!(redirect_target(r#"<meta name="description" content="url=/x">"#, page), None);
+    assert_eq!(redirect_target(r#"<script>location.href = "javascript:void(0)"</script>"#, page), None);
+}
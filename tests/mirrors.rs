@@ -0,0 +1,168 @@
+// Testes da classificação de espelhos e do download em chunks dividido entre eles
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use keepers::engine::{run_download, DownloadMessage, DownloadTask};
+use keepers::http::{HttpBackend, HttpError, HttpRequest, HttpResponse, SharedBackend};
+use keepers::mirrors::{probe_mirror, rank_probes, MirrorPool, MirrorProbe, MAX_ACTIVE_MIRRORS};
+use keepers::storage::new_download_id;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::{StatusCode, Version};
+
+const PRIMARY: &str = "http://principal/arquivo.iso";
+const MIRROR: &str = "http://espelho/arquivo.iso";
+
+// Cada URL serve um arquivo (com Range); URLs desconhecidas dão 404
+struct MirrorBackend {
+    files: HashMap<String, Vec<u8>>,
+    requests: Mutex<Vec<(String, Option<String>)>>,
+}
+
+impl MirrorBackend {
+    fn new(files: &[(&str, Vec<u8>)]) -> Arc<Self> {
+        Arc::new(MirrorBackend {
+            files: files.iter().map(|(url, data)| (url.to_string(), data.clone())).collect(),
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn ranged_requests_to(&self, url: &str) -> usize {
+        self.requests.lock().unwrap().iter().filter(|(to, range)| to == url && range.is_some()).count()
+    }
+}
+
+impl HttpBackend for MirrorBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        let range = request.header_value(RANGE.as_str()).map(str::to_string);
+        self.requests.lock().unwrap().push((request.url.clone(), range.clone()));
+
+        let mut headers = HeaderMap::new();
+        let Some(data) = self.files.get(&request.url) else {
+            return async move {
+                Ok(HttpResponse {
+                    status: StatusCode::NOT_FOUND,
+                    version: Version::HTTP_11,
+                    headers,
+                    body: futures_util::stream::empty().boxed(),
+                })
+            }
+            .boxed();
+        };
+        headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        let len = data.len();
+        let (status, body) = match range {
+            Some(range) => {
+                let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+                let start: usize = start.parse().unwrap();
+                let end = if end.is_empty() { len - 1 } else { end.parse::<usize>().unwrap().min(len - 1) };
+                headers.insert(
+                    CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)).unwrap(),
+                );
+                (StatusCode::PARTIAL_CONTENT, data[start..=end].to_vec())
+            }
+            None => (StatusCode::OK, data.clone()),
+        };
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len() as u64));
+        let body = if request.method == keepers::http::HttpMethod::Head { Vec::new() } else { body };
+        async move {
+            Ok(HttpResponse {
+                status,
+                version: Version::HTTP_11,
+                headers,
+                body: futures_util::stream::iter(vec![Ok(Bytes::from(body))]).boxed(),
+            })
+        }
+        .boxed()
+    }
+}
+
+fn sample_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn probe(url: &str, speed: f64, latency_ms: u64) -> MirrorProbe {
+    MirrorProbe { url: url.to_string(), latency: Duration::from_millis(latency_ms), speed }
+}
+
+#[test]
+fn probes_rank_by_speed_then_latency() {
+    let mut probes = vec![probe("a", 100.0, 50), probe("b", 300.0, 90), probe("c", 100.0, 10)];
+    rank_probes(&mut probes);
+    let order: Vec<&str> = probes.iter().map(|p| p.url.as_str()).collect();
+    assert_eq!(order, vec!["b", "c", "a"]);
+}
+
+#[test]
+fn pool_rotates_among_the_fastest_and_drops_degraded_mirrors() {
+    let pool = MirrorPool::from_probes(vec![
+        probe("lento", 10.0, 5),
+        probe("rapido", 1000.0, 5),
+        probe("medio", 500.0, 5),
+        probe("reserva", 300.0, 5),
+    ]);
+    assert_eq!(MAX_ACTIVE_MIRRORS, 3);
+    let picked: Vec<String> = (0..6).filter_map(|_| pool.pick()).collect();
+    assert_eq!(picked, vec!["rapido", "medio", "reserva", "rapido", "medio", "reserva"]);
+    assert!(!pool.is_active("lento"));
+
+    // O mais rápido piora: cai na classificação e a reserva continua no rodízio
+    let mut changed = false;
+    for _ in 0..15 {
+        changed |= pool.record_speed("rapido", 1.0);
+    }
+    assert!(changed);
+    assert!(!pool.is_active("rapido"));
+    assert_eq!(pool.urls()[..3], ["medio".to_string(), "reserva".to_string(), "lento".to_string()]);
+}
+
+#[test]
+fn the_last_mirror_is_never_removed() {
+    let pool = MirrorPool::from_probes(vec![probe("a", 1.0, 1), probe("b", 2.0, 1)]);
+    assert!(pool.remove("a"));
+    assert!(!pool.remove("b"));
+    assert_eq!(pool.urls(), vec!["b".to_string()]);
+    assert_eq!(MirrorPool::single("c").pick().as_deref(), Some("c"));
+}
+
+#[tokio::test]
+async fn probe_rejects_mirrors_with_another_file() {
+    let backend: SharedBackend = MirrorBackend::new(&[(PRIMARY, sample_data(1000)), (MIRROR, sample_data(999))]);
+
+    assert!(probe_mirror(&backend, PRIMARY, 1000).await.is_ok());
+    assert!(probe_mirror(&backend, MIRROR, 1000).await.is_err());
+    assert!(probe_mirror(&backend, "http://fora/arquivo.iso", 1000).await.is_err());
+}
+
+#[tokio::test]
+async fn chunks_are_split_between_the_working_mirrors() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = sample_data(6 * 1024 * 1024 + 77);
+    let backend = MirrorBackend::new(&[
+        (PRIMARY, data.clone()),
+        (MIRROR, data.clone()),
+        ("http://antigo/arquivo.iso", sample_data(1024)),
+    ]);
+    let task = Arc::new(Mutex::new(DownloadTask::new(new_download_id())));
+    task.lock().unwrap().mirrors = vec![MIRROR.to_string(), "http://antigo/arquivo.iso".to_string()];
+
+    let (tx, rx) = async_channel::unbounded();
+    let records = Arc::new(Mutex::new(Vec::new()));
+    run_download(backend.clone(), PRIMARY, "arquivo.iso", dir.path(), &tx, &task, &records).await;
+    let mut messages = Vec::new();
+    while let Ok(msg) = rx.try_recv() {
+        messages.push(msg);
+    }
+
+    assert!(matches!(messages.last(), Some(DownloadMessage::Complete)));
+    assert_eq!(std::fs::read(dir.path().join("arquivo.iso")).unwrap(), data);
+    // Teste de velocidade + pelo menos um chunk em cada espelho que serve
+    assert!(backend.ranged_requests_to(PRIMARY) >= 2);
+    assert!(backend.ranged_requests_to(MIRROR) >= 2);
+    let events: Vec<String> = task.lock().unwrap().events.iter().map(|event| event.text.clone()).collect();
+    assert!(events.iter().any(|text| text.starts_with("Espelho ignorado: http://antigo/")), "{:?}", events);
+    assert!(events.iter().any(|text| text.starts_with("Espelhos por velocidade: ")), "{:?}", events);
+}
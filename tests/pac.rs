@@ -0,0 +1,122 @@
+// Testes da configuração automática de proxy (PAC)
+use keepers::pac::{parse_pac_result, script_url, PacProxy, PacScript};
+use reqwest::Url;
+use std::sync::Arc;
+
+const CORPORATE_PAC: &str = r#"
+function FindProxyForURL(url, host) {
+    if (isPlainHostName(host) || dnsDomainIs(host, ".interno.example")) return "DIRECT";
+    if (isInNet(host, "10.0.0.0", "255.0.0.0")) return "DIRECT";
+    if (shExpMatch(url, "https://*.cdn.example/*")) return "SOCKS5 socks.example:1080";
+    return "PROXY proxy.example:3128; DIRECT";
+}
+"#;
+
+fn url(text: &str) -> Url {
+    Url::parse(text).unwrap()
+}
+
+#[test]
+fn pac_results_are_parsed_in_order() {
+    assert_eq!(
+        parse_pac_result("PROXY a:3128; socks b:1080;HTTPS c:443 ; DIRECT; BOGUS x"),
+        vec![
+            PacProxy::Proxy("http://a:3128".to_string()),
+            PacProxy::Proxy("socks5://b:1080".to_string()),
+            PacProxy::Proxy("https://c:443".to_string()),
+            PacProxy::Direct,
+        ]
+    );
+    assert!(parse_pac_result("").is_empty());
+}
+
+#[test]
+fn script_decides_the_proxy_of_each_url() {
+    let pac = PacScript::new(CORPORATE_PAC).unwrap();
+
+    assert_eq!(pac.find_proxy(&url("http://intranet/arquivo.zip")).unwrap(), vec![PacProxy::Direct]);
+    assert_eq!(pac.find_proxy(&url("http://wiki.interno.example/a.pdf")).unwrap(), vec![PacProxy::Direct]);
+    assert_eq!(pac.find_proxy(&url("http://10.1.2.3/a.iso")).unwrap(), vec![PacProxy::Direct]);
+    assert_eq!(
+        pac.find_proxy(&url("https://video.cdn.example/filme.mp4")).unwrap(),
+        vec![PacProxy::Proxy("socks5://socks.example:1080".to_string())]
+    );
+    assert_eq!(
+        pac.find_proxy(&url("https://github.com/release.tar.gz")).unwrap(),
+        vec![PacProxy::Proxy("http://proxy.example:3128".to_string()), PacProxy::Direct]
+    );
+}
+
+#[test]
+fn proxy_for_only_reads_decisions_already_taken() {
+    let pac = PacScript::new(CORPORATE_PAC).unwrap();
+    let release = url("https://github.com/release.tar.gz");
+
+    // Consultar não roda o script
+    assert_eq!(pac.proxy_for(&release), None);
+    pac.find_proxy(&release).unwrap();
+    assert_eq!(pac.proxy_for(&release), Some(PacProxy::Proxy("http://proxy.example:3128".to_string())));
+    // Em https, mesmo servidor reaproveita a decisão
+    assert!(pac.proxy_for(&url("https://github.com/outro.zip")).is_some());
+    assert_eq!(pac.proxy_for(&url("http://github.com/release.tar.gz")), None);
+}
+
+#[test]
+fn http_decisions_follow_the_path() {
+    let pac = PacScript::new(
+        "function FindProxyForURL(url, host) { return shExpMatch(url, '*/interno/*') ? 'DIRECT' : 'PROXY proxy.example:3128'; }",
+    )
+    .unwrap();
+    let internal = url("http://arquivos.example/interno/a.iso");
+    let public = url("http://arquivos.example/publico/a.iso");
+
+    assert_eq!(pac.find_proxy(&internal).unwrap(), vec![PacProxy::Direct]);
+    assert_eq!(pac.proxy_for(&public), None);
+    assert_eq!(pac.find_proxy(&public).unwrap(), vec![PacProxy::Proxy("http://proxy.example:3128".to_string())]);
+    // Em https o script só vê o servidor, como nos navegadores
+    assert_eq!(script_url(&url("https://arquivos.example/interno/a.iso?x=1")), "https://arquivos.example/");
+    assert_eq!(script_url(&url("http://arquivos.example:8080/a.iso#fim")), "http://arquivos.example:8080/a.iso");
+}
+
+#[tokio::test]
+async fn prepare_decides_off_the_runtime() {
+    let pac = Arc::new(PacScript::new(CORPORATE_PAC).unwrap());
+    let video = url("https://video.cdn.example/filme.mp4");
+
+    pac.prepare(&video).await.unwrap();
+    assert_eq!(pac.proxy_for(&video), Some(PacProxy::Proxy("socks5://socks.example:1080".to_string())));
+}
+
+#[test]
+fn failed_entries_fall_through_to_the_next() {
+    let pac = PacScript::new("function FindProxyForURL(url, host) { return 'PROXY a:1; PROXY b:2; DIRECT'; }").unwrap();
+    let file = url("http://arquivos.example/a.iso");
+    let first = PacProxy::Proxy("http://a:1".to_string());
+    let second = PacProxy::Proxy("http://b:2".to_string());
+    pac.find_proxy(&file).unwrap();
+
+    assert!(pac.skip_failed(&file, &first));
+    assert_eq!(pac.proxy_for(&file), Some(second.clone()));
+    // Outra conexão que também falhou em `a` não pula `b`
+    assert!(pac.skip_failed(&file, &first));
+    assert_eq!(pac.proxy_for(&file), Some(second.clone()));
+
+    assert!(pac.skip_failed(&file, &second));
+    assert_eq!(pac.proxy_for(&file), Some(PacProxy::Direct));
+    // O último item não tem para onde ir
+    assert!(!pac.skip_failed(&file, &PacProxy::Direct));
+    assert_eq!(pac.proxy_for(&file), Some(PacProxy::Direct));
+}
+
+#[test]
+fn broken_scripts_are_rejected() {
+    assert!(PacScript::new("function FindProxyForURL(url, host) {").is_err());
+    assert!(PacScript::new("var x = 1;").is_err());
+
+    // Erros ao decidir (laço infinito, função inexistente) também voltam como Err
+    let looping = PacScript::new("function FindProxyForURL(url, host) { while (true) {} }").unwrap();
+    assert!(looping.find_proxy(&url("http://a.example/")).is_err());
+    let missing = PacScript::new("function FindProxyForURL(url, host) { return dateRange(1) ? 'DIRECT' : 'DIRECT'; }").unwrap();
+    assert!(missing.find_proxy(&url("http://a.example/")).is_err());
+    assert_eq!(missing.proxy_for(&url("http://a.example/")), None);
+}
@@ -0,0 +1,136 @@
+// Testes do download direto para um Write (`keepers fetch <url> -o -`) e da configuração que ele usa
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use keepers::http::{HttpBackend, HttpError, HttpErrorKind, HttpRequest, HttpResponse, SharedBackend};
+use keepers::pipe::{fetch_config, stream_to};
+use keepers::storage::{save_config, set_portable_dir, set_profile, AppConfig, UrlFilterSettings};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::{StatusCode, Version};
+
+// Servidor simulado cujos primeiros corpos são cortados depois de `cut_after` bytes
+struct FlakyBackend {
+    data: Vec<u8>,
+    supports_range: bool,
+    broken_bodies: AtomicU32,
+    cut_after: usize,
+    ranges: Mutex<Vec<Option<String>>>,
+}
+
+impl FlakyBackend {
+    fn new(data: Vec<u8>, supports_range: bool, broken_bodies: u32, cut_after: usize) -> Arc<Self> {
+        Arc::new(FlakyBackend {
+            data,
+            supports_range,
+            broken_bodies: AtomicU32::new(broken_bodies),
+            cut_after,
+            ranges: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+impl HttpBackend for FlakyBackend {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'static, Result<HttpResponse, HttpError>> {
+        let range = request.header_value(RANGE.as_str()).map(str::to_string);
+        self.ranges.lock().unwrap().push(range.clone());
+
+        let len = self.data.len();
+        let mut headers = HeaderMap::new();
+        let (status, body) = match range.filter(|_| self.supports_range) {
+            Some(range) => {
+                let start: usize = range.trim_start_matches("bytes=").trim_end_matches('-').parse().unwrap();
+                headers.insert(
+                    CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes {}-{}/{}", start, len - 1, len)).unwrap(),
+                );
+                (StatusCode::PARTIAL_CONTENT, self.data[start..].to_vec())
+            }
+            None => (StatusCode::OK, self.data.clone()),
+        };
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len() as u64));
+
+        let broken = self
+            .broken_bodies
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        let mut chunks: Vec<Result<Bytes, HttpError>> = Vec::new();
+        if broken {
+            chunks.push(Ok(Bytes::copy_from_slice(&body[..self.cut_after.min(body.len())])));
+            chunks.push(Err(HttpError::new(HttpErrorKind::Body, "conexão interrompida")));
+        } else {
+            chunks.push(Ok(Bytes::from(body)));
+        }
+        async move {
+            Ok(HttpResponse {
+                status,
+                version: Version::HTTP_11,
+                headers,
+                body: futures_util::stream::iter(chunks).boxed(),
+            })
+        }
+        .boxed()
+    }
+}
+
+fn sample_data() -> Vec<u8> {
+    (0..50_000u32).map(|i| (i % 251) as u8).collect()
+}
+
+#[tokio::test]
+async fn interrupted_stream_resumes_with_a_range_request() {
+    let backend = FlakyBackend::new(sample_data(), true, 1, 12_345);
+    let shared: SharedBackend = backend.clone();
+    let mut out = Vec::new();
+
+    let written = stream_to(&shared, "http://mock/arquivo.bin", &mut out, |_, _| {}).await.unwrap();
+
+    assert_eq!(written, 50_000);
+    assert_eq!(out, sample_data());
+    assert_eq!(*backend.ranges.lock().unwrap(), vec![None, Some("bytes=12345-".to_string())]);
+}
+
+#[tokio::test]
+async fn server_without_ranges_skips_what_was_already_written() {
+    let backend = FlakyBackend::new(sample_data(), false, 1, 7_000);
+    let shared: SharedBackend = backend.clone();
+    let mut out = Vec::new();
+
+    stream_to(&shared, "http://mock/arquivo.bin", &mut out, |_, _| {}).await.unwrap();
+
+    // O corpo recomeça do zero, mas a saída não repete os primeiros bytes
+    assert_eq!(out, sample_data());
+}
+
+#[tokio::test]
+async fn closed_reader_ends_the_stream_quietly() {
+    struct ClosedPipe;
+    impl std::io::Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let shared: SharedBackend = FlakyBackend::new(sample_data(), true, 0, 0);
+
+    assert_eq!(stream_to(&shared, "http://mock/arquivo.bin", &mut ClosedPipe, |_, _| {}).await, Ok(0));
+}
+
+#[test]
+fn fetch_uses_the_url_filter_of_the_selected_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    set_portable_dir(Some(dir.path().to_path_buf()));
+    set_profile(Some("escola"));
+    let url_filter = UrlFilterSettings { allow: Vec::new(), deny: vec![r"\.exe$".to_string()] };
+    save_config(&AppConfig { url_filter, ..Default::default() });
+
+    assert!(fetch_config("https://exemplo.com/jogo.exe").unwrap_err().contains(r"\.exe$"));
+    assert!(fetch_config("https://exemplo.com/apostila.pdf").is_ok());
+    // O perfil padrão não tem a regra
+    set_profile(None);
+    assert!(fetch_config("https://exemplo.com/jogo.exe").is_ok());
+    set_portable_dir(None);
+}
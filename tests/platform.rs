@@ -0,0 +1,54 @@
+// Testes da camada de sistema (nomes e caminhos longos do Windows, troca de arquivos)
+use keepers::platform::{extended_length_path, is_reserved_name, move_file, rename_file, safe_file_name};
+
+#[test]
+fn long_windows_paths_get_the_extended_prefix() {
+    assert_eq!(extended_length_path(r"C:\Downloads\a.iso").as_deref(), Some(r"\\?\C:\Downloads\a.iso"));
+    assert_eq!(extended_length_path("D:/Downloads/sub/a.iso").as_deref(), Some(r"\\?\D:\Downloads\sub\a.iso"));
+    assert_eq!(extended_length_path(r"\\nas\publico\a.iso").as_deref(), Some(r"\\?\UNC\nas\publico\a.iso"));
+    // Já prefixados ou relativos ficam como estão
+    assert_eq!(extended_length_path(r"\\?\C:\a.iso"), None);
+    assert_eq!(extended_length_path(r"Downloads\a.iso"), None);
+    assert_eq!(extended_length_path("/home/ana/a.iso"), None);
+}
+
+#[test]
+fn windows_device_names_are_not_used_as_is() {
+    assert!(is_reserved_name("CON"));
+    assert!(is_reserved_name("con.txt"));
+    assert!(is_reserved_name("Lpt1.tar.gz"));
+    assert!(!is_reserved_name("console.txt"));
+    assert!(!is_reserved_name("COM10"));
+
+    assert_eq!(safe_file_name("CON.txt"), "_CON.txt");
+    assert_eq!(safe_file_name("nul"), "_nul");
+    assert_eq!(safe_file_name("relatório: final?.pdf"), "relatório_ final_.pdf");
+    // O Windows descarta ponto e espaço no final do nome
+    assert_eq!(safe_file_name("notas. "), "notas");
+    assert_eq!(safe_file_name("..."), "download");
+    assert_eq!(safe_file_name("a.iso"), "a.iso");
+}
+
+#[test]
+fn rename_replaces_the_destination() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("config.json.tmp"), dir.path().join("config.json"));
+    std::fs::write(&from, b"novo").unwrap();
+    std::fs::write(&to, b"antigo").unwrap();
+
+    rename_file(&from, &to).unwrap();
+    assert!(!from.exists());
+    assert_eq!(std::fs::read(&to).unwrap(), b"novo");
+}
+
+#[test]
+fn move_creates_the_file_and_removes_the_part() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("a.iso.part"), dir.path().join("a.iso"));
+    std::fs::write(&from, b"dados").unwrap();
+
+    move_file(&from, &to).unwrap();
+    assert!(!from.exists());
+    assert_eq!(std::fs::read(&to).unwrap(), b"dados");
+    assert!(move_file(&from, &to).is_err(), "origem que não existe é erro");
+}
@@ -0,0 +1,30 @@
+// Testes da pausa automática com bateria fraca
+use keepers::power::{battery_action, BatteryAction, BatteryStatus, UPOWER_STATE_DISCHARGING};
+
+fn status(percentage: f64, discharging: bool) -> BatteryStatus {
+    BatteryStatus { percentage, discharging }
+}
+
+#[test]
+fn pauses_below_threshold_only_on_discharge() {
+    assert_eq!(battery_action(status(15.0, true), 20, false), BatteryAction::Pause);
+    assert_eq!(battery_action(status(25.0, true), 20, false), BatteryAction::Keep);
+    assert_eq!(battery_action(status(15.0, false), 20, false), BatteryAction::Keep);
+}
+
+#[test]
+fn resumes_when_plugged_in_after_a_battery_pause() {
+    assert_eq!(battery_action(status(15.0, true), 20, true), BatteryAction::Keep);
+    // Subir acima do limite ainda descarregando não retoma
+    assert_eq!(battery_action(status(30.0, true), 20, true), BatteryAction::Keep);
+    assert_eq!(battery_action(status(16.0, false), 20, true), BatteryAction::Resume);
+}
+
+#[test]
+fn desktops_without_battery_are_ignored() {
+    assert_eq!(BatteryStatus::from_upower(false, 0.0, 0), None);
+    assert_eq!(
+        BatteryStatus::from_upower(true, 42.0, UPOWER_STATE_DISCHARGING),
+        Some(status(42.0, true))
+    );
+}
@@ -0,0 +1,87 @@
+// Testes da exportação/importação da fila em andamento
+use keepers::engine::chunk_map_path;
+use keepers::queue::{export_queue, import_queue, PARTS_DIR, QUEUE_FILE};
+use keepers::storage::{DownloadOptions, DownloadRecord, DownloadStatus};
+
+fn record(filename: &str, status: DownloadStatus, downloaded_bytes: u64) -> DownloadRecord {
+    DownloadRecord {
+        status,
+        downloaded_bytes,
+        total_bytes: 1000,
+        options: DownloadOptions { referer: Some("https://exemplo.com/".to_string()), ..Default::default() },
+        ..DownloadRecord::new(&format!("https://exemplo.com/{}", filename), filename)
+    }
+}
+
+#[test]
+fn queue_moves_with_partial_files_and_chunk_maps() {
+    let origin = tempfile::tempdir().unwrap();
+    let export = tempfile::tempdir().unwrap();
+    let target = tempfile::tempdir().unwrap();
+
+    let chunked = record("isos/distro.iso", DownloadStatus::InProgress, 600);
+    let fresh = record("leia.txt", DownloadStatus::InProgress, 40);
+    let done = record("pronto.zip", DownloadStatus::Completed, 1000);
+    std::fs::create_dir_all(origin.path().join("isos")).unwrap();
+    let part = origin.path().join("isos/distro.iso.part");
+    std::fs::write(&part, vec![7u8; 600]).unwrap();
+    std::fs::write(chunk_map_path(&part), r#"{"total_size":1000,"chunks":[]}"#).unwrap();
+
+    let records = vec![chunked.clone(), fresh.clone(), done];
+    assert_eq!(export_queue(&records, origin.path(), export.path(), true).unwrap(), 2);
+    assert!(export.path().join(QUEUE_FILE).exists());
+
+    // Na outra máquina já existe um download com o mesmo id e o mesmo nome
+    let mut existing = record("leia.txt", DownloadStatus::Completed, 1000);
+    existing.id = fresh.id;
+    let imported = import_queue(export.path(), target.path(), &[existing.clone()]).unwrap();
+
+    assert_eq!(imported.len(), 2);
+    assert!(imported.iter().all(|r| r.status == DownloadStatus::InProgress && r.was_paused));
+    let iso = &imported[0];
+    assert_eq!((iso.id, iso.filename.as_str(), iso.downloaded_bytes), (chunked.id, "isos/distro.iso", 600));
+    assert_eq!(iso.options.referer.as_deref(), Some("https://exemplo.com/"));
+    let restored = target.path().join("isos/distro.iso.part");
+    assert_eq!(std::fs::read(&restored).unwrap().len(), 600);
+    assert!(chunk_map_path(&restored).exists());
+
+    // Sem .part para levar: recomeça do zero, com id e nome novos
+    let txt = &imported[1];
+    assert_ne!(txt.id, existing.id);
+    assert_eq!((txt.filename.as_str(), txt.downloaded_bytes), ("leia (2).txt", 0));
+}
+
+#[test]
+fn export_without_partial_files_restarts_downloads() {
+    let origin = tempfile::tempdir().unwrap();
+    let export = tempfile::tempdir().unwrap();
+    let active = record("a.bin", DownloadStatus::InProgress, 500);
+    std::fs::write(origin.path().join("a.bin.part"), vec![1u8; 500]).unwrap();
+
+    export_queue(&[active], origin.path(), export.path(), false).unwrap();
+    let imported = import_queue(export.path(), origin.path(), &[]).unwrap();
+
+    // O .part antigo continua no lugar; o importado ganha outro nome
+    assert_eq!((imported[0].filename.as_str(), imported[0].downloaded_bytes), ("a (2).bin", 0));
+    assert!(import_queue(origin.path(), origin.path(), &[]).is_err());
+}
+
+#[test]
+fn imported_names_stay_inside_the_download_folder() {
+    let origin = tempfile::tempdir().unwrap();
+    let export = tempfile::tempdir().unwrap();
+    let root = tempfile::tempdir().unwrap();
+    let target = root.path().join("downloads");
+    let escaping = record("../../.config/autostart/x.desktop", DownloadStatus::InProgress, 10);
+    let absolute = record("/etc/cron.d/x", DownloadStatus::InProgress, 0);
+
+    export_queue(&[escaping.clone(), absolute], origin.path(), export.path(), false).unwrap();
+    std::fs::create_dir_all(export.path().join(PARTS_DIR)).unwrap();
+    std::fs::write(export.path().join(PARTS_DIR).join(format!("{}.part", escaping.id)), vec![3u8; 10]).unwrap();
+    let imported = import_queue(export.path(), &target, &[]).unwrap();
+
+    let names: Vec<&str> = imported.iter().map(|r| r.filename.as_str()).collect();
+    assert_eq!(names, vec![".config/autostart/x.desktop", "etc/cron.d/x"]);
+    assert!(target.join(".config/autostart/x.desktop.part").exists());
+    assert!(!root.path().join(".config").exists());
+}
@@ -0,0 +1,46 @@
+// Testes da descoberta de .part sem registro na lista
+use keepers::provenance;
+use keepers::recovery::{adopt_orphan, find_orphan_parts};
+use keepers::storage::DownloadStatus;
+
+#[test]
+fn only_parts_without_a_record_are_orphans() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("conhecido.iso.part"), b"123").unwrap();
+    std::fs::write(dir.path().join("perdido.iso.part"), b"12345").unwrap();
+    std::fs::write(dir.path().join("completo.iso"), b"x").unwrap();
+    std::fs::create_dir_all(dir.path().join("github.com")).unwrap();
+    std::fs::write(dir.path().join("github.com/fonte.tar.gz.part"), b"1").unwrap();
+    std::fs::create_dir_all(dir.path().join(".cache")).unwrap();
+    std::fs::write(dir.path().join(".cache/oculto.part"), b"1").unwrap();
+
+    let orphan = find_orphan_parts(dir.path(), &[]).into_iter().find(|o| o.filename == "conhecido.iso").unwrap();
+    let known = adopt_orphan(&orphan, "https://exemplo.com/conhecido.iso");
+    let orphans = find_orphan_parts(dir.path(), &[known]);
+
+    let names: Vec<&str> = orphans.iter().map(|orphan| orphan.filename.as_str()).collect();
+    assert_eq!(names, vec!["github.com/fonte.tar.gz", "perdido.iso"]);
+    assert_eq!(orphans[1].size, 5);
+    assert_eq!(orphans[1].part_path, dir.path().join("perdido.iso.part"));
+}
+
+#[test]
+fn adopted_part_resumes_paused_from_its_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let part = dir.path().join("perdido.iso.part");
+    std::fs::write(&part, vec![0u8; 4096]).unwrap();
+    // Nem todo sistema de arquivos de teste aceita xattrs do usuário
+    let has_origin = provenance::write_origin(&part, "https://exemplo.com/perdido.iso", None).is_ok();
+
+    let orphans = find_orphan_parts(dir.path(), &[]);
+    assert_eq!(orphans.len(), 1);
+    if has_origin {
+        assert_eq!(orphans[0].url.as_deref(), Some("https://exemplo.com/perdido.iso"));
+    }
+
+    let record = adopt_orphan(&orphans[0], "https://exemplo.com/perdido.iso");
+    assert_eq!(record.filename, "perdido.iso");
+    assert_eq!(record.status, DownloadStatus::InProgress);
+    assert!(record.was_paused);
+    assert_eq!(record.downloaded_bytes, 4096);
+}
@@ -0,0 +1,52 @@
+// Testes dos comandos curl/wget equivalentes a um download
+use std::path::Path;
+use keepers::reproduce::{download_command, shell_quote, CommandTool};
+use keepers::storage::{DownloadOptions, DownloadRecord, DownloadStatus, ResponseDetails};
+
+fn record(url: &str, options: DownloadOptions) -> DownloadRecord {
+    DownloadRecord { status: DownloadStatus::Failed, options, ..DownloadRecord::new(url, "arquivo.iso") }
+}
+
+#[test]
+fn commands_carry_the_download_options() {
+    let options = DownloadOptions {
+        referer: Some("https://exemplo.com/página de teste".to_string()),
+        accept_invalid_certs: true,
+        use_tor: true,
+        ..Default::default()
+    };
+    let record = record("https://exemplo.com/arquivo.iso?token=a&b=1", options);
+    let output = Path::new("/home/eu/Downloads/arquivo.iso");
+
+    assert_eq!(
+        download_command(CommandTool::Curl, &record, output, Some("/etc/ca interna.pem")),
+        "curl --location --fail --referer 'https://exemplo.com/página de teste' --insecure \
+         --cacert '/etc/ca interna.pem' --proxy socks5h://127.0.0.1:9050 \
+         --output /home/eu/Downloads/arquivo.iso 'https://exemplo.com/arquivo.iso?token=a&b=1'"
+    );
+    assert_eq!(
+        download_command(CommandTool::Wget, &record, output, None),
+        "torsocks wget --referer='https://exemplo.com/página de teste' --no-check-certificate \
+         --output-document=/home/eu/Downloads/arquivo.iso 'https://exemplo.com/arquivo.iso?token=a&b=1'"
+    );
+}
+
+#[test]
+fn non_http_urls_use_the_address_that_answered() {
+    let mut record = record("ipfs://bafyexemplo/arquivo.iso", DownloadOptions::default());
+    record.response = Some(ResponseDetails {
+        url: "https://ipfs.io/ipfs/bafyexemplo/arquivo.iso".to_string(),
+        ..Default::default()
+    });
+
+    let command = download_command(CommandTool::Curl, &record, Path::new("saida.iso"), None);
+
+    assert!(command.ends_with(" https://ipfs.io/ipfs/bafyexemplo/arquivo.iso"), "{}", command);
+}
+
+#[test]
+fn shell_quote_escapes_single_quotes() {
+    assert_eq!(shell_quote("simples-1.0.tar.gz"), "simples-1.0.tar.gz");
+    assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    assert_eq!(shell_quote(""), "''");
+}
@@ -0,0 +1,87 @@
+// Testes dos links de compartilhamento e da página de confirmação do Google Drive
+use keepers::resolve::{builtin_resolvers, direct_url, drive_confirm_url, load_plugins, page_target, resolve_url};
+
+#[test]
+fn share_links_become_direct_urls() {
+    let drive = "https://drive.usercontent.google.com/download?id=1AbC-xyz&export=download";
+    assert_eq!(direct_url("https://drive.google.com/file/d/1AbC-xyz/view?usp=sharing").as_deref(), Some(drive));
+    assert_eq!(direct_url("https://drive.google.com/open?id=1AbC-xyz").as_deref(), Some(drive));
+    assert_eq!(direct_url("https://docs.google.com/uc?export=download&id=1AbC-xyz").as_deref(), Some(drive));
+
+    assert_eq!(
+        direct_url("https://www.dropbox.com/scl/fi/abc/dados.zip?rlkey=k1&dl=0").as_deref(),
+        Some("https://www.dropbox.com/scl/fi/abc/dados.zip?rlkey=k1&dl=1")
+    );
+    assert_eq!(direct_url("https://www.dropbox.com/s/abc/dados.zip?dl=1"), None);
+
+    assert_eq!(
+        direct_url("https://github.com/dono/repo/blob/main/docs/guia.pdf").as_deref(),
+        Some("https://raw.githubusercontent.com/dono/repo/main/docs/guia.pdf")
+    );
+    assert_eq!(direct_url("https://github.com/dono/repo"), None);
+    assert_eq!(direct_url("https://exemplo.com/arquivo.iso"), None);
+}
+
+#[test]
+fn drive_virus_warning_leads_to_the_confirmed_download() {
+    let page = "https://drive.usercontent.google.com/download?id=1AbC&export=download";
+    let html = r#"<form id="download-form" action="https://drive.usercontent.google.com/download" method="get">
+        <input type="submit" id="uc-download-link" class="goog-inline-block" value="Fazer o download mesmo assim">
+        <input type="hidden" name="id" value="1AbC"><input type="hidden" name="export" value="download">
+        <input type="hidden" name="confirm" value="t"><input type="hidden" name="uuid" value="9f1e&amp;x">
+        </form>"#;
+    assert_eq!(
+        drive_confirm_url(html, page).as_deref(),
+        Some("https://drive.usercontent.google.com/download?id=1AbC&export=download&confirm=t&uuid=9f1e%26x")
+    );
+
+    // Página antiga: link com o token
+    let old = r#"<a id="uc-download-link" href="/uc?export=download&amp;confirm=Xy12&amp;id=1AbC">Download</a>"#;
+    assert_eq!(
+        page_target(old, "https://drive.google.com/uc?id=1AbC&export=download").as_deref(),
+        Some("https://drive.google.com/uc?export=download&confirm=Xy12&id=1AbC")
+    );
+    // Outras páginas seguem o redirecionamento comum
+    let refresh = r#"<meta http-equiv="refresh" content="0;url=/arquivo.iso">"#;
+    assert_eq!(page_target(refresh, "https://exemplo.com/get").as_deref(), Some("https://exemplo.com/arquivo.iso"));
+    assert_eq!(drive_confirm_url("<p>nada aqui</p>", page), None);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn command_plugins_resolve_matching_urls() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("hoster.sh");
+    std::fs::write(
+        &script,
+        "#!/bin/sh\ncase \"$1\" in\n  */quebrado) echo 'arquivo removido' >&2; exit 1 ;;\nesac\n\
+         printf '{\"urls\": [\"https://cdn.hoster.example/%s\"], \"headers\": {\"Cookie\": \"s=1\"}}' \"${1##*/}\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::write(
+        dir.path().join("hoster.json"),
+        r#"{"name": "Hoster", "pattern": "^https://hoster\\.example/f/", "command": ["./hoster.sh"]}"#,
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("invalido.json"), "{\"name\": \"Sem comando\"}").unwrap();
+
+    let mut resolvers = load_plugins(dir.path());
+    assert_eq!(resolvers.iter().map(|r| r.name()).collect::<Vec<_>>(), vec!["Hoster"]);
+    resolvers.extend(builtin_resolvers());
+
+    let (name, result) = resolve_url(&resolvers, "https://hoster.example/f/abc").await.unwrap();
+    let resolved = result.unwrap();
+    assert_eq!(name, "Hoster");
+    assert_eq!(resolved.urls, vec!["https://cdn.hoster.example/abc"]);
+    assert_eq!(resolved.headers, vec![("Cookie".to_string(), "s=1".to_string())]);
+
+    let (_, result) = resolve_url(&resolvers, "https://hoster.example/f/quebrado").await.unwrap();
+    assert_eq!(result.unwrap_err(), "arquivo removido");
+
+    // Sem plugin para a URL: os embutidos assumem, e links comuns passam direto
+    let (name, _) = resolve_url(&resolvers, "https://www.dropbox.com/s/abc/a.zip?dl=0").await.unwrap();
+    assert_eq!(name, "Links de compartilhamento");
+    assert!(resolve_url(&resolvers, "https://exemplo.com/a.zip").await.is_none());
+}
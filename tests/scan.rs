@@ -0,0 +1,37 @@
+// Testes da verificação dos arquivos concluídos pelo antivírus
+use keepers::scan::{scan_file, scanner_command};
+use keepers::storage::ScanResult;
+
+fn command(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+#[test]
+fn command_line_is_split_into_program_and_arguments() {
+    assert_eq!(scanner_command("  clamscan   --no-summary "), command(&["clamscan", "--no-summary"]));
+    assert!(scanner_command("   ").is_empty());
+}
+
+#[tokio::test]
+async fn exit_code_decides_clean_or_infected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("arquivo.bin");
+    std::fs::write(&path, b"dados").unwrap();
+
+    // O caminho chega como último argumento ($0 no sh -c)
+    let clean = command(&["sh", "-c", "test -f \"$0\""]);
+    assert_eq!(scan_file(&clean, &path).await, Ok(ScanResult::Clean));
+    assert_eq!(scan_file(&command(&["false"]), &path).await, Ok(ScanResult::Infected));
+}
+
+#[tokio::test]
+async fn other_exit_codes_and_missing_scanner_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("arquivo.bin");
+    std::fs::write(&path, b"dados").unwrap();
+
+    let broken = command(&["sh", "-c", "echo banco de assinaturas ausente >&2; exit 2"]);
+    assert_eq!(scan_file(&broken, &path).await, Err("banco de assinaturas ausente".to_string()));
+    assert!(scan_file(&command(&["keepers-antivirus-inexistente"]), &path).await.is_err());
+    assert!(scan_file(&[], &path).await.is_err());
+}
@@ -0,0 +1,100 @@
+// Testes das expressões de agendamento e das versões de downloads recorrentes
+use chrono::{Local, TimeZone};
+use keepers::schedule::{
+    format_countdown, next_time_of_day, numbered_filename, push_version, version_path, versioned_filename, weekly_cron, CronSchedule,
+};
+use keepers::storage::{version_naming, DownloadOptions, Recurrence, VersionNaming};
+
+fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> chrono::DateTime<Local> {
+    Local.with_ymd_and_hms(y, mo, d, h, mi, 0).single().unwrap()
+}
+
+#[test]
+fn daily_schedule_runs_next_day_when_time_has_passed() {
+    let cron = CronSchedule::parse("30 3 * * *").unwrap();
+    assert_eq!(cron.next_after(local(2026, 3, 10, 2, 0)), Some(local(2026, 3, 10, 3, 30)));
+    assert_eq!(cron.next_after(local(2026, 3, 10, 3, 30)), Some(local(2026, 3, 11, 3, 30)));
+}
+
+#[test]
+fn steps_lists_and_weekdays() {
+    let every_six_hours = CronSchedule::parse("0 */6 * * *").unwrap();
+    assert_eq!(every_six_hours.next_after(local(2026, 3, 10, 7, 15)), Some(local(2026, 3, 10, 12, 0)));
+
+    // 10/03/2026 é uma terça; a próxima segunda ou sexta é 13/03
+    let weekdays = CronSchedule::parse("0 9 * * 1,5").unwrap();
+    assert_eq!(weekdays.next_after(local(2026, 3, 10, 10, 0)), Some(local(2026, 3, 13, 9, 0)));
+
+    // Domingo pode ser escrito como 0 ou 7
+    assert_eq!(CronSchedule::parse("0 0 * * 7"), CronSchedule::parse("0 0 * * 0"));
+
+    let weekly = CronSchedule::parse(&weekly_cron(local(2026, 3, 10, 8, 45))).unwrap();
+    assert_eq!(weekly.next_after(local(2026, 3, 10, 8, 45)), Some(local(2026, 3, 17, 8, 45)));
+}
+
+#[test]
+fn invalid_expressions_are_rejected() {
+    assert!(CronSchedule::parse("* * *").is_err());
+    assert!(CronSchedule::parse("60 * * * *").is_err());
+    assert!(CronSchedule::parse("*/0 * * * *").is_err());
+    assert!(CronSchedule::parse("0 0 31 2 *").unwrap().next_after(local(2026, 1, 1, 0, 0)).is_none());
+}
+
+#[test]
+fn versions_keep_only_the_most_recent() {
+    assert_eq!(versioned_filename("dados.csv", local(2026, 3, 10, 3, 0)), "dados-20260310-030000.csv");
+    assert_eq!(versioned_filename("LEIAME", local(2026, 3, 10, 3, 0)), "LEIAME-20260310-030000");
+
+    let mut versions = Vec::new();
+    assert!(push_version(&mut versions, "a".to_string(), 2).is_empty());
+    assert!(push_version(&mut versions, "b".to_string(), 2).is_empty());
+    assert_eq!(push_version(&mut versions, "c".to_string(), 2), vec!["a".to_string()]);
+    assert_eq!(versions, vec!["b".to_string(), "c".to_string()]);
+}
+
+#[test]
+fn pause_deadlines_and_countdowns() {
+    let now = local(2026, 10, 18, 14, 20);
+    assert_eq!(next_time_of_day("18:00", now), Some(local(2026, 10, 18, 18, 0)));
+    assert_eq!(next_time_of_day("9h", now), Some(local(2026, 10, 19, 9, 0)));
+    assert_eq!(next_time_of_day("25:00", now), None);
+    assert_eq!(next_time_of_day("amanhã", now), None);
+
+    assert_eq!(format_countdown(chrono::Duration::seconds(3723)), "1:02:03");
+    assert_eq!(format_countdown(chrono::Duration::seconds(3570)), "59:30");
+    assert_eq!(format_countdown(chrono::Duration::seconds(-5)), "00:00");
+}
+
+#[test]
+fn each_naming_strategy_picks_its_own_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dados.csv");
+    let at = local(2026, 3, 10, 3, 0);
+    assert_eq!(numbered_filename("dados.csv", 2), "dados-2.csv");
+
+    assert_eq!(version_path(&path, VersionNaming::Overwrite, at), path);
+    assert_eq!(version_path(&path, VersionNaming::Timestamp, at), dir.path().join("dados-20260310-030000.csv"));
+    assert_eq!(version_path(&path, VersionNaming::Counter, at), dir.path().join("dados-1.csv"));
+
+    // Nomes já ocupados na pasta não são sobrescritos
+    std::fs::write(dir.path().join("dados-1.csv"), b"v1").unwrap();
+    std::fs::write(dir.path().join("dados-20260310-030000.csv"), b"v1").unwrap();
+    assert_eq!(version_path(&path, VersionNaming::Counter, at), dir.path().join("dados-2.csv"));
+    assert_eq!(version_path(&path, VersionNaming::Timestamp, at), dir.path().join("dados-20260310-030000 (2).csv"));
+}
+
+#[test]
+fn naming_comes_from_the_download_then_the_config_then_the_feature() {
+    let mut options = DownloadOptions::default();
+    assert_eq!(version_naming(&options, Some(VersionNaming::Counter)), None);
+
+    options.sync = true;
+    assert_eq!(version_naming(&options, None), Some(VersionNaming::Overwrite));
+    assert_eq!(version_naming(&options, Some(VersionNaming::Counter)), Some(VersionNaming::Counter));
+
+    options.recurrence = Some(Recurrence { cron: "0 3 * * *".to_string(), keep_versions: 3 });
+    assert_eq!(version_naming(&options, None), Some(VersionNaming::Timestamp));
+
+    options.version_naming = Some(VersionNaming::Overwrite);
+    assert_eq!(version_naming(&options, Some(VersionNaming::Counter)), Some(VersionNaming::Overwrite));
+}
@@ -0,0 +1,80 @@
+// Testes dos ganchos em scripts Rhai
+use keepers::scripting::Hooks;
+
+fn hooks(sources: &[(&str, &str)]) -> Hooks {
+    Hooks::from_sources(sources.iter().map(|(name, source)| (name.to_string(), source.to_string())).collect())
+}
+
+#[test]
+fn scripts_rewrite_urls_in_order() {
+    let hooks = hooks(&[
+        ("01-espelho.rhai", r#"fn url_added(url) { url.replace("http://", "https://"); url }"#),
+        ("02-sem-rastreio.rhai", r#"fn url_added(url) { let i = url.index_of("?utm_"); if i < 0 { () } else { url.sub_string(0, i) } }"#),
+        ("03-quebrado.rhai", "fn url_added(url) {"),
+        ("04-laco.rhai", "fn url_added(url) { loop {} }"),
+    ]);
+
+    // O script que não compila fica de fora; o laço infinito é interrompido
+    assert_eq!(hooks.names(), vec!["01-espelho.rhai", "02-sem-rastreio.rhai", "04-laco.rhai"]);
+    assert_eq!(hooks.url_added("http://exemplo.com/a.iso?utm_source=x"), "https://exemplo.com/a.iso");
+    assert_eq!(hooks.url_added("https://exemplo.com/b.iso"), "https://exemplo.com/b.iso");
+}
+
+#[test]
+fn before_request_adds_headers_and_swaps_the_url() {
+    let hooks = hooks(&[
+        ("a.rhai", r#"fn before_request(url) { if url.contains("intranet") { #{ headers: #{ "Authorization": "Bearer x" } } } }"#),
+        ("b.rhai", r#"fn before_request(url) { #{ url: url + "?token=1", headers: #{ "authorization": "Bearer y", "X-App": 2 } } }"#),
+        ("c.rhai", "fn completed(path, url) { }"),
+    ]);
+
+    let (url, headers) = hooks.before_request("https://intranet.local/a.bin");
+    assert_eq!(url, "https://intranet.local/a.bin?token=1");
+    assert_eq!(
+        headers,
+        vec![("X-App".to_string(), "2".to_string()), ("authorization".to_string(), "Bearer y".to_string())]
+    );
+
+    assert!(Hooks::default().before_request("https://a.com/x").1.is_empty());
+}
+
+#[test]
+fn completed_can_post_process_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("relatorio.pdf");
+    std::fs::write(&file, b"pdf").unwrap();
+    let hooks = hooks(&[(
+        "organizar.rhai",
+        r#"fn completed(path, url) {
+            if path.ends_with(".pdf") && url.contains("empresa") { rename_file(path, path + ".arquivado"); }
+        }"#,
+    )]);
+
+    hooks.completed(&file, "https://outro.com/relatorio.pdf");
+    assert!(file.exists());
+    hooks.completed(&file, "https://empresa.com/relatorio.pdf");
+    assert!(!file.exists());
+    assert!(dir.path().join("relatorio.pdf.arquivado").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn run_is_cut_short_in_url_added() {
+    let hooks = hooks(&[("lento.rhai", r##"fn url_added(url) { let code = run("sleep", ["30"]); url + "#" + code }"##)]);
+    let started = std::time::Instant::now();
+    assert_eq!(hooks.url_added("https://a.com/x"), "https://a.com/x#-1");
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+}
+
+// O prazo vale para o evento inteiro, não para cada run()
+#[cfg(unix)]
+#[test]
+fn sequential_runs_share_the_url_added_deadline() {
+    let hooks = hooks(&[(
+        "lentos.rhai",
+        r##"fn url_added(url) { let a = run("sleep", ["4"]); let b = run("sleep", ["4"]); url + "#" + a + "," + b }"##,
+    )]);
+    let started = std::time::Instant::now();
+    assert_eq!(hooks.url_added("https://a.com/x"), "https://a.com/x#0,-1");
+    assert!(started.elapsed() < std::time::Duration::from_secs(7));
+}
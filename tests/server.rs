@@ -0,0 +1,627 @@
+// Testes de integração: motor completo contra um servidor HTTP local (axum)
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::Router;
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use keepers::engine::{run_download, start_download, DownloadMessage, DownloadState, DownloadTask, WaitReason};
+use keepers::failure::Failure;
+use keepers::http::{ClientOptions, ClientPool, KeepAliveSettings, ReqwestBackend, SharedBackend};
+use keepers::resolve::{Resolved, Resolver};
+use keepers::slots::DownloadSlots;
+use keepers::storage::{new_download_id, AppConfig, DownloadOptions, DownloadRecord, DownloadTemplate};
+use keepers::templates::apply_matching_template;
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Clone)]
+struct ServerState {
+    data: Arc<Vec<u8>>,
+    // Ranges recebidos em requisições GET (None = arquivo inteiro)
+    ranges: Arc<Mutex<Vec<Option<String>>>>,
+    truncated_gets: Arc<AtomicUsize>,
+    // Header Referer das requisições GET
+    referers: Arc<Mutex<Vec<Option<String>>>>,
+    // Conexões TCP aceitas
+    connections: Arc<AtomicUsize>,
+}
+
+// Listener que derruba as primeiras conexões para simular uma rede instável
+struct FlakyListener {
+    inner: TcpListener,
+    drop_first: usize,
+    accepted: usize,
+    kept: Arc<AtomicUsize>,
+}
+
+impl axum::serve::Listener for FlakyListener {
+    type Io = TcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            let (stream, addr) = axum::serve::Listener::accept(&mut self.inner).await;
+            self.accepted += 1;
+            if self.accepted > self.drop_first {
+                self.kept.fetch_add(1, Ordering::SeqCst);
+                return (stream, addr);
+            }
+            drop(stream);
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+fn parse_range(value: &str, len: usize) -> Option<(usize, usize)> {
+    let spec = value.strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+    let start: usize = start.parse().ok()?;
+    let end = if end.is_empty() { len - 1 } else { end.parse::<usize>().ok()?.min(len - 1) };
+    (start <= end).then_some((start, end))
+}
+
+fn serve_bytes(data: &[u8], request_headers: &HeaderMap, accept_ranges: bool) -> Response {
+    let range = request_headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| accept_ranges)
+        .and_then(|v| parse_range(v, data.len()));
+
+    let mut builder = Response::builder();
+    if accept_ranges {
+        builder = builder.header(header::ACCEPT_RANGES, "bytes");
+    }
+
+    match range {
+        Some((start, end)) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, data.len()))
+            .header(header::CONTENT_LENGTH, end - start + 1)
+            .body(Body::from(data[start..=end].to_vec()))
+            .unwrap(),
+        None => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, data.len())
+            .body(Body::from(data.to_vec()))
+            .unwrap(),
+    }
+}
+
+fn log_range(state: &ServerState, method: &Method, headers: &HeaderMap) {
+    if method == Method::HEAD {
+        return;
+    }
+    let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+    state.ranges.lock().unwrap().push(range);
+    let referer = headers.get(header::REFERER).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+    state.referers.lock().unwrap().push(referer);
+}
+
+async fn with_ranges(State(state): State<ServerState>, method: Method, headers: HeaderMap) -> Response {
+    log_range(&state, &method, &headers);
+    serve_bytes(&state.data, &headers, true)
+}
+
+async fn without_ranges(State(state): State<ServerState>, method: Method, headers: HeaderMap) -> Response {
+    log_range(&state, &method, &headers);
+    serve_bytes(&state.data, &headers, false)
+}
+
+// Só responde com o token que o resolvedor informa
+async fn private(State(state): State<ServerState>, method: Method, headers: HeaderMap) -> Response {
+    if headers.get("x-token").and_then(|v| v.to_str().ok()) != Some("segredo") {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    log_range(&state, &method, &headers);
+    serve_bytes(&state.data, &headers, true)
+}
+
+async fn redirect(Path(name): Path<String>) -> impl IntoResponse {
+    Redirect::temporary(&format!("/range/{}", name))
+}
+
+// Página "seu download começará em instantes" que leva ao arquivo por meta refresh
+async fn intermediate(Path(name): Path<String>) -> impl IntoResponse {
+    let html = format!(
+        "<html><head><meta http-equiv=\"refresh\" content=\"3; url=/range/{}\"></head>\
+         <body>Seu download começará em instantes</body></html>",
+        name
+    );
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html)
+}
+
+// Aviso de antivírus no formato do Google Drive: o formulário leva ao arquivo
+async fn confirm_page() -> impl IntoResponse {
+    let html = "<html><body><p>Não foi possível verificar se há vírus neste arquivo.</p>\
+        <form id=\"download-form\" action=\"/attachment\" method=\"get\">\
+        <input type=\"submit\" value=\"Fazer o download mesmo assim\">\
+        <input type=\"hidden\" name=\"id\" value=\"abc123\">\
+        <input type=\"hidden\" name=\"confirm\" value=\"t\">\
+        </form></body></html>";
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html)
+}
+
+// Arquivo com o nome real só no Content-Disposition
+async fn attachment(
+    State(state): State<ServerState>,
+    method: Method,
+    headers: HeaderMap,
+    axum::extract::RawQuery(query): axum::extract::RawQuery,
+) -> Response {
+    if query.as_deref() != Some("id=abc123&confirm=t") {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    log_range(&state, &method, &headers);
+    let mut response = serve_bytes(&state.data, &headers, true);
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        "attachment; filename=\"relatorio final.bin\"".parse().unwrap(),
+    );
+    response
+}
+
+// Primeira transferência é interrompida no meio; as seguintes respeitam Range
+async fn truncated(State(state): State<ServerState>, method: Method, headers: HeaderMap) -> Response {
+    log_range(&state, &method, &headers);
+    if method == Method::HEAD || state.truncated_gets.fetch_add(1, Ordering::SeqCst) > 0 {
+        return serve_bytes(&state.data, &headers, true);
+    }
+
+    // Espera a primeira metade ser enviada antes de derrubar a conexão
+    let half = Bytes::copy_from_slice(&state.data[..state.data.len() / 2]);
+    let stream = futures_util::stream::once(async move { Ok(half) }).chain(futures_util::stream::once(async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "conexão interrompida"))
+    }));
+    Response::builder()
+        .header(header::CONTENT_LENGTH, state.data.len())
+        .header(header::ACCEPT_RANGES, "bytes")
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
+
+async fn spawn_server(data: Vec<u8>, drop_first: usize) -> (SocketAddr, ServerState) {
+    let state = ServerState {
+        data: Arc::new(data),
+        ranges: Arc::new(Mutex::new(Vec::new())),
+        truncated_gets: Arc::new(AtomicUsize::new(0)),
+        referers: Arc::new(Mutex::new(Vec::new())),
+        connections: Arc::new(AtomicUsize::new(0)),
+    };
+
+    let app = Router::new()
+        .route("/range/{name}", get(with_ranges))
+        .route("/plain/{name}", get(without_ranges))
+        .route("/redirect/{name}", get(redirect))
+        .route("/truncated/{name}", get(truncated))
+        .route("/intermediate/{name}", get(intermediate))
+        .route("/private/{name}", get(private))
+        .route("/share", get(confirm_page))
+        .route("/attachment", get(attachment))
+        .with_state(state.clone());
+
+    let inner = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = inner.local_addr().unwrap();
+    let listener = FlakyListener { inner, drop_first, accepted: 0, kept: state.connections.clone() };
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    (addr, state)
+}
+
+fn sample_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 % 253) as u8).collect()
+}
+
+// Executa o motor completo (thread + runtime próprios) e devolve a mensagem final
+async fn run_engine(url: &str, dir: &std::path::Path) -> DownloadMessage {
+    run_engine_with(url, AppConfig {
+        download_directory: Some(dir.to_string_lossy().to_string()),
+        ..Default::default()
+    })
+    .await
+}
+
+async fn run_engine_with(url: &str, config: AppConfig) -> DownloadMessage {
+    run_engine_task(url, config, DownloadTask::new(new_download_id())).await
+}
+
+async fn run_engine_task(url: &str, config: AppConfig, task: DownloadTask) -> DownloadMessage {
+    run_engine_records(url, config, Arc::new(Mutex::new(task)), Vec::new()).await
+}
+
+// Como `run_engine_task`, com os registros que o motor consulta (opções do download)
+async fn run_engine_records(
+    url: &str,
+    config: AppConfig,
+    task: Arc<Mutex<DownloadTask>>,
+    records: Vec<DownloadRecord>,
+) -> DownloadMessage {
+    let (tx, rx) = async_channel::unbounded();
+    let config = Arc::new(Mutex::new(config));
+    let filename = url.rsplit('/').next().unwrap();
+    start_download(url, filename, tx, task, Arc::new(Mutex::new(records)), config);
+
+    tokio::time::timeout(Duration::from_secs(60), async {
+        while let Ok(msg) = rx.recv().await {
+            if !matches!(msg, DownloadMessage::Progress(..) | DownloadMessage::Status(_) | DownloadMessage::Waiting(_) | DownloadMessage::State(_)) {
+                return msg;
+            }
+        }
+        DownloadMessage::Error(Failure::other("canal fechado"))
+    })
+    .await
+    .expect("download não terminou a tempo")
+}
+
+#[tokio::test]
+async fn chunked_download_uses_parallel_ranges() {
+    let data = sample_data(3 * 1024 * 1024 + 17);
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let msg = run_engine(&format!("http://{}/range/big.bin", addr), dir.path()).await;
+
+    assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    assert_eq!(std::fs::read(dir.path().join("big.bin")).unwrap(), data);
+    let ranges = state.ranges.lock().unwrap();
+    assert!(ranges.len() > 1);
+    assert!(ranges.iter().all(|r| r.is_some()));
+}
+
+#[tokio::test]
+async fn sequential_download_when_ranges_unsupported() {
+    let data = sample_data(3 * 1024 * 1024);
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let msg = run_engine(&format!("http://{}/plain/seq.bin", addr), dir.path()).await;
+
+    assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    assert_eq!(std::fs::read(dir.path().join("seq.bin")).unwrap(), data);
+    assert_eq!(*state.ranges.lock().unwrap(), vec![None]);
+}
+
+#[tokio::test]
+async fn follows_redirects() {
+    let data = sample_data(256 * 1024);
+    let (addr, _state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let msg = run_engine(&format!("http://{}/redirect/moved.bin", addr), dir.path()).await;
+
+    assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    assert_eq!(std::fs::read(dir.path().join("moved.bin")).unwrap(), data);
+}
+
+#[tokio::test]
+async fn retries_dropped_connections() {
+    let data = sample_data(128 * 1024);
+    let (addr, _state) = spawn_server(data.clone(), 1).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let msg = run_engine(&format!("http://{}/range/flaky.bin", addr), dir.path()).await;
+
+    assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    assert_eq!(std::fs::read(dir.path().join("flaky.bin")).unwrap(), data);
+}
+
+#[tokio::test]
+async fn resumes_after_truncated_transfer() {
+    let data = sample_data(600 * 1024);
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("http://{}/truncated/partial.bin", addr);
+
+    let first = run_engine(&url, dir.path()).await;
+    assert!(matches!(first, DownloadMessage::Error(_)), "{:?}", first);
+    let partial_len = std::fs::metadata(dir.path().join("partial.bin.part")).unwrap().len();
+    assert!(partial_len > 0 && partial_len < data.len() as u64);
+
+    let second = run_engine(&url, dir.path()).await;
+    assert!(matches!(second, DownloadMessage::Complete), "{:?}", second);
+    assert_eq!(std::fs::read(dir.path().join("partial.bin")).unwrap(), data);
+    assert_eq!(
+        state.ranges.lock().unwrap().last().cloned().flatten(),
+        Some(format!("bytes={}-", partial_len))
+    );
+}
+
+#[tokio::test]
+async fn downloads_share_the_client_connections() {
+    let data = sample_data(256 * 1024);
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = AppConfig {
+        download_directory: Some(dir.path().to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    let clients = Arc::new(ClientPool::new());
+
+    for name in ["um.bin", "dois.bin", "tres.bin"] {
+        let mut task = DownloadTask::new(new_download_id());
+        task.clients = Some(clients.clone());
+        let msg = run_engine_task(&format!("http://{}/plain/{}", addr, name), config.clone(), task).await;
+        assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+        assert_eq!(std::fs::read(dir.path().join(name)).unwrap(), data);
+    }
+
+    // A conexão aberta no primeiro download serve aos seguintes
+    assert_eq!(state.connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn idle_connections_are_not_kept_when_reuse_is_off() {
+    let data = sample_data(64 * 1024);
+    let (addr, state) = spawn_server(data, 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = AppConfig {
+        download_directory: Some(dir.path().to_string_lossy().to_string()),
+        keep_alive: KeepAliveSettings { pool_idle_timeout_secs: 0, ..Default::default() },
+        ..Default::default()
+    };
+    let clients = Arc::new(ClientPool::new());
+
+    for name in ["um.bin", "dois.bin"] {
+        let mut task = DownloadTask::new(new_download_id());
+        task.clients = Some(clients.clone());
+        let msg = run_engine_task(&format!("http://{}/plain/{}", addr, name), config.clone(), task).await;
+        assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    }
+
+    // Consulta + GET de cada download em conexões novas
+    assert_eq!(state.connections.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn pac_script_sends_the_download_through_its_proxy() {
+    let data = sample_data(128 * 1024);
+    // O servidor de teste também atende como proxy HTTP (requisições com URL absoluta)
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let pac_path = dir.path().join("proxy.pac");
+    std::fs::write(
+        &pac_path,
+        format!(
+            "function FindProxyForURL(url, host) {{ return dnsDomainIs(host, '.interno.invalid') ? 'PROXY {}' : 'DIRECT'; }}",
+            addr
+        ),
+    )
+    .unwrap();
+
+    let mut config = AppConfig {
+        download_directory: Some(dir.path().to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    config.proxy.pac_url = pac_path.to_string_lossy().to_string();
+    let msg = run_engine_with("http://arquivos.interno.invalid/plain/via-pac.bin", config).await;
+
+    assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    assert_eq!(std::fs::read(dir.path().join("via-pac.bin")).unwrap(), data);
+    assert!(!state.ranges.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn pac_falls_back_to_the_next_proxy_when_one_does_not_connect() {
+    let data = sample_data(64 * 1024);
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    // Porta que acabou de ser liberada: ninguém atende
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let pac_path = dir.path().join("fallback.pac");
+    std::fs::write(
+        &pac_path,
+        format!("function FindProxyForURL(url, host) {{ return 'PROXY {}; PROXY {}; DIRECT'; }}", closed, addr),
+    )
+    .unwrap();
+
+    let mut config = AppConfig {
+        download_directory: Some(dir.path().to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    config.proxy.pac_url = pac_path.to_string_lossy().to_string();
+    let msg = run_engine_with("http://espelho.interno.invalid/plain/segundo-proxy.bin", config).await;
+
+    assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    assert_eq!(std::fs::read(dir.path().join("segundo-proxy.bin")).unwrap(), data);
+    assert!(!state.ranges.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn unreadable_ca_bundle_fails_before_connecting() {
+    let data = sample_data(64 * 1024);
+    let (addr, state) = spawn_server(data, 0).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let msg = run_engine_with(&format!("http://{}/range/ca.bin", addr), AppConfig {
+        download_directory: Some(dir.path().to_string_lossy().to_string()),
+        ca_bundle_path: Some(dir.path().join("inexistente.pem").to_string_lossy().to_string()),
+        ..Default::default()
+    })
+    .await;
+
+    assert!(matches!(&msg, DownloadMessage::Error(e) if e.message.contains("certificado CA")), "{:?}", msg);
+    assert!(state.ranges.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn intermediate_page_leads_to_the_file_with_the_referer() {
+    let data = sample_data(200 * 1024);
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let referer = "https://origem.example/lancamentos.html";
+    let backend: SharedBackend = Arc::new(
+        ReqwestBackend::with_options(&ClientOptions { referer: Some(referer.to_string()), ..Default::default() }).unwrap(),
+    );
+    let (tx, rx) = async_channel::unbounded();
+    let task = Arc::new(Mutex::new(DownloadTask::new(new_download_id())));
+    let url = format!("http://{}/intermediate/real.bin", addr);
+
+    run_download(backend, &url, "real.bin", dir.path(), &tx, &task, &Arc::new(Mutex::new(Vec::new()))).await;
+
+    let mut last = None;
+    while let Ok(msg) = rx.try_recv() {
+        last = Some(msg);
+    }
+    assert!(matches!(last, Some(DownloadMessage::Complete)), "{:?}", last);
+    assert_eq!(std::fs::read(dir.path().join("real.bin")).unwrap(), data);
+    let referers = state.referers.lock().unwrap();
+    assert!(!referers.is_empty());
+    assert!(referers.iter().all(|r| r.as_deref() == Some(referer)));
+}
+
+#[tokio::test]
+async fn confirm_form_is_submitted_and_the_server_name_is_used() {
+    let data = sample_data(300 * 1024);
+    let (addr, _state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let backend: SharedBackend = Arc::new(ReqwestBackend::new().unwrap());
+    let (tx, rx) = async_channel::unbounded();
+    let task = Arc::new(Mutex::new(DownloadTask::new(new_download_id())));
+    let url = format!("http://{}/share", addr);
+
+    run_download(backend, &url, "share", dir.path(), &tx, &task, &Arc::new(Mutex::new(Vec::new()))).await;
+
+    let messages: Vec<DownloadMessage> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+    assert!(messages.iter().any(|m| matches!(m, DownloadMessage::Renamed(name) if name == "relatorio final.bin")));
+    assert!(matches!(messages.last(), Some(DownloadMessage::Complete)), "{:?}", messages.last());
+    assert_eq!(std::fs::read(dir.path().join("relatorio final.bin")).unwrap(), data);
+    assert!(!dir.path().join("share").exists());
+}
+
+// Resolvedor de teste: "hoster://arquivo" vira um endereço morto e o real, com token
+struct HosterResolver {
+    addr: SocketAddr,
+}
+
+impl Resolver for HosterResolver {
+    fn name(&self) -> &str {
+        "Hoster"
+    }
+
+    fn resolve(&self, url: &str) -> BoxFuture<'static, Option<Result<Resolved, String>>> {
+        let resolved = url.starts_with("hoster://").then(|| {
+            Ok(Resolved {
+                urls: vec![format!("http://{}/removido", self.addr), format!("http://{}/private/arquivo.bin", self.addr)],
+                headers: vec![("X-Token".to_string(), "segredo".to_string())],
+            })
+        });
+        async move { resolved }.boxed()
+    }
+}
+
+#[tokio::test]
+async fn resolver_plugin_supplies_mirrors_and_headers() {
+    let data = sample_data(600 * 1024);
+    let (addr, state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let backend: SharedBackend = Arc::new(ReqwestBackend::new().unwrap());
+    let (tx, rx) = async_channel::unbounded();
+    let task = Arc::new(Mutex::new(DownloadTask::new(new_download_id())));
+    task.lock().unwrap().resolvers.insert(0, Arc::new(HosterResolver { addr }));
+
+    run_download(backend, "hoster://arquivo", "arquivo.bin", dir.path(), &tx, &task, &Arc::new(Mutex::new(Vec::new()))).await;
+
+    let messages: Vec<DownloadMessage> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+    assert!(matches!(messages.last(), Some(DownloadMessage::Complete)), "{:?}", messages.last());
+    assert_eq!(std::fs::read(dir.path().join("arquivo.bin")).unwrap(), data);
+    // O arquivo veio pela rota que exige o token
+    assert!(!state.ranges.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn template_sets_the_folder_and_connections_of_matching_urls() {
+    let data = sample_data(3 * 1024 * 1024);
+    let (addr, _state) = spawn_server(data.clone(), 0).await;
+    let default_dir = tempfile::tempdir().unwrap();
+    let template_dir = tempfile::tempdir().unwrap();
+    let url = format!("http://{}/range/modelo.bin", addr);
+    let config = AppConfig {
+        download_directory: Some(default_dir.path().to_string_lossy().to_string()),
+        templates: vec![DownloadTemplate {
+            pattern: "127.0.0.*".to_string(),
+            directory: template_dir.path().to_string_lossy().to_string(),
+            max_connections: Some(1),
+            speed_limit: None,
+        }],
+        ..AppConfig::default()
+    };
+    let mut options = DownloadOptions::default();
+    apply_matching_template(&config.templates, &url, &mut options).unwrap();
+    let task = Arc::new(Mutex::new(DownloadTask::new(new_download_id())));
+    let record = DownloadRecord {
+        id: task.lock().unwrap().id,
+        options,
+        ..DownloadRecord::new(&url, "modelo.bin")
+    };
+
+    let msg = run_engine_records(&url, config, task.clone(), vec![record]).await;
+
+    assert!(matches!(msg, DownloadMessage::Complete), "{:?}", msg);
+    assert_eq!(std::fs::read(template_dir.path().join("modelo.bin")).unwrap(), data);
+    assert!(!default_dir.path().join("modelo.bin").exists());
+    let task = task.lock().unwrap();
+    assert_eq!(task.max_connections, 1);
+    assert!(task.peak_connections <= 1, "{}", task.peak_connections);
+    assert!(task.events.iter().any(|event| event.text == "Modelo: 127.0.0.*"));
+}
+
+#[tokio::test]
+async fn downloads_past_the_active_limit_report_their_queue_position() {
+    let data = sample_data(256 * 1024);
+    let (addr, _state) = spawn_server(data.clone(), 0).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = AppConfig {
+        download_directory: Some(dir.path().to_string_lossy().to_string()),
+        ..AppConfig::default()
+    };
+    // Outro download ocupa a única vaga
+    let slots = Arc::new(DownloadSlots::new(Some(1)));
+    let running = new_download_id();
+    assert!(slots.try_acquire(running));
+    let mut task = DownloadTask::new(new_download_id());
+    task.slots = Some(slots.clone());
+    let task = Arc::new(Mutex::new(task));
+
+    let (tx, rx) = async_channel::unbounded();
+    let url = format!("http://{}/range/fila.bin", addr);
+    start_download(&url, "fila.bin", tx, task.clone(), Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(config)));
+
+    let first = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap();
+    assert!(matches!(first, DownloadMessage::State(DownloadState::Queued)), "{:?}", first);
+    let second = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap();
+    assert!(matches!(second, DownloadMessage::Waiting(WaitReason::Queued { position: 1 })), "{:?}", second);
+    assert!(!dir.path().join("fila.bin").exists());
+
+    slots.release(running);
+    let last = tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            match rx.recv().await {
+                Ok(DownloadMessage::Progress(..) | DownloadMessage::Status(_) | DownloadMessage::Waiting(_) | DownloadMessage::State(_)) => {}
+                Ok(msg) => return msg,
+                Err(_) => return DownloadMessage::Error(Failure::other("canal fechado")),
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert!(matches!(last, DownloadMessage::Complete), "{:?}", last);
+    assert_eq!(std::fs::read(dir.path().join("fila.bin")).unwrap(), data);
+    // Terminado, a vaga volta para a fila
+    while !task.lock().unwrap().finished {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(slots.active(), 0);
+    let events: Vec<String> = task.lock().unwrap().events.iter().map(|event| event.text.clone()).collect();
+    assert!(events.contains(&"Na fila (posição 1)".to_string()), "{:?}", events);
+}
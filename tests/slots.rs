@@ -0,0 +1,56 @@
+// Testes da fila do limite de downloads simultâneos
+use keepers::slots::DownloadSlots;
+use keepers::storage::new_download_id;
+
+#[test]
+fn downloads_past_the_limit_wait_in_arrival_order() {
+    let slots = DownloadSlots::new(Some(2));
+    let (a, b, c, d) = (new_download_id(), new_download_id(), new_download_id(), new_download_id());
+
+    assert!(slots.try_acquire(a));
+    assert!(slots.try_acquire(b));
+    assert!(!slots.try_acquire(c));
+    assert!(!slots.try_acquire(d));
+    assert_eq!(slots.position(c), Some(1));
+    assert_eq!(slots.position(d), Some(2));
+    assert_eq!(slots.position(a), None);
+
+    // A vaga liberada vai para o primeiro da fila, mesmo que o segundo pergunte antes
+    slots.release(a);
+    assert!(!slots.try_acquire(d));
+    assert!(slots.try_acquire(c));
+    assert_eq!(slots.position(d), Some(1));
+    assert_eq!(slots.active(), 2);
+}
+
+#[test]
+fn leaving_the_queue_moves_the_others_up() {
+    let slots = DownloadSlots::new(Some(1));
+    let (a, b, c) = (new_download_id(), new_download_id(), new_download_id());
+    assert!(slots.try_acquire(a));
+    assert!(!slots.try_acquire(b));
+    assert!(!slots.try_acquire(c));
+
+    // Cancelado enquanto esperava
+    slots.release(b);
+    assert_eq!(slots.position(c), Some(1));
+}
+
+#[test]
+fn raising_or_removing_the_limit_frees_the_queue() {
+    let slots = DownloadSlots::new(Some(1));
+    let (a, b, c) = (new_download_id(), new_download_id(), new_download_id());
+    assert!(slots.try_acquire(a));
+    assert!(!slots.try_acquire(b));
+    assert!(!slots.try_acquire(c));
+
+    slots.set_limit(Some(2));
+    assert!(slots.try_acquire(b));
+    assert!(!slots.try_acquire(c));
+
+    // 0 = sem limite
+    slots.set_limit(Some(0));
+    assert_eq!(slots.limit(), None);
+    assert!(slots.try_acquire(c));
+    assert_eq!(slots.active(), 3);
+}
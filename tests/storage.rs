@@ -0,0 +1,191 @@
+// Testes dos nomes e pastas dos arquivos baixados, da linha do tempo, dos backups dos registros, dos perfis e do modo portátil
+use chrono::{Duration, Utc};
+use keepers::storage::{
+    domain_folder, finished_directory, list_backups, list_profiles_in, portable_data_dir, profile_dir, valid_profile_name, load_downloads_from, missing_from_backup, persisted_records, push_event, read_backup, rotate_backup,
+    unique_name, find_network_profile, parse_header_lines, DownloadOptions, DownloadRecord, DownloadStatus, NetworkProfile, MAX_BACKUPS,
+    MAX_DOWNLOAD_EVENTS,
+};
+
+#[test]
+fn domain_folders_come_from_the_host() {
+    assert_eq!(domain_folder("https://www.github.com/user/repo/archive.zip").as_deref(), Some("github.com"));
+    assert_eq!(domain_folder("http://espelho.org:8080/pub/a.iso").as_deref(), Some("espelho.org"));
+    assert_eq!(domain_folder("http://[::1]/a.iso").as_deref(), Some("__1"));
+    // Sem site de origem: fica na raiz da pasta de downloads
+    assert_eq!(domain_folder("s3://bucket/chave.bin"), None);
+    assert_eq!(domain_folder("ipfs://bafybeigdyr/arquivo"), None);
+}
+
+#[test]
+fn unique_names_keep_the_subfolder_and_extension() {
+    let taken = ["github.com/app.zip", "github.com/app (2).zip"];
+    assert_eq!(
+        unique_name("github.com/app.zip", |name| taken.contains(&name)),
+        "github.com/app (3).zip"
+    );
+    assert_eq!(unique_name("github.com/app.zip", |_| false), "github.com/app.zip");
+}
+
+#[test]
+fn finished_folder_skips_downloads_with_their_own_folder() {
+    let mut options = DownloadOptions::default();
+    assert_eq!(finished_directory(&options, None), None);
+    assert_eq!(finished_directory(&options, Some("  ")), None);
+    assert_eq!(finished_directory(&options, Some("/srv/midia")), Some("/srv/midia".into()));
+
+    options.directory = Some("/dados/isos".to_string());
+    assert_eq!(finished_directory(&options, Some("/srv/midia")), None);
+}
+
+#[test]
+fn timeline_keeps_only_the_latest_events() {
+    let mut events = Vec::new();
+    for i in 0..MAX_DOWNLOAD_EVENTS + 5 {
+        push_event(&mut events, format!("evento {}", i));
+    }
+    assert_eq!(events.len(), MAX_DOWNLOAD_EVENTS);
+    assert_eq!(events[0].text, "evento 5");
+    assert_eq!(events.last().unwrap().text, format!("evento {}", MAX_DOWNLOAD_EVENTS + 4));
+}
+
+#[test]
+fn backups_rotate_at_most_once_per_interval() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = dir.path().join("downloads.json");
+    let backups = dir.path().join("backups");
+    std::fs::write(&data, "[]").unwrap();
+    let start = Utc::now();
+
+    assert!(rotate_backup(&data, &backups, start).unwrap());
+    assert!(!rotate_backup(&data, &backups, start + Duration::minutes(10)).unwrap());
+    for hour in 1..=MAX_BACKUPS as i64 + 3 {
+        assert!(rotate_backup(&data, &backups, start + Duration::hours(hour)).unwrap());
+    }
+
+    let kept = list_backups(&backups);
+    assert_eq!(kept.len(), MAX_BACKUPS);
+    assert_eq!(kept[0].created.timestamp(), (start + Duration::hours(MAX_BACKUPS as i64 + 3)).timestamp());
+    assert!(kept.windows(2).all(|pair| pair[0].created > pair[1].created));
+}
+
+#[test]
+fn restoring_adds_only_missing_records_paused() {
+    let dir = tempfile::tempdir().unwrap();
+    let kept = record("https://exemplo.com/a.iso", DownloadStatus::Completed);
+    let lost = record("https://exemplo.com/b.iso", DownloadStatus::InProgress);
+    let path = dir.path().join("downloads-20260101-120000.json");
+    std::fs::write(&path, serde_json::to_string(&vec![kept.clone(), lost.clone()]).unwrap()).unwrap();
+
+    let restored = missing_from_backup(read_backup(&path).unwrap(), &[kept]);
+
+    assert_eq!(restored.len(), 1);
+    assert_eq!(restored[0].id, lost.id);
+    assert!(restored[0].was_paused);
+    std::fs::write(&path, "{ quebrado").unwrap();
+    assert!(read_backup(&path).is_err());
+}
+
+#[test]
+fn corrupted_download_list_is_set_aside() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("downloads.json");
+    assert!(load_downloads_from(&path).unwrap().is_empty());
+
+    std::fs::write(&path, serde_json::to_string(&vec![record("https://exemplo.com/a.iso", DownloadStatus::Completed)]).unwrap()).unwrap();
+    assert_eq!(load_downloads_from(&path).unwrap().len(), 1);
+
+    std::fs::write(&path, "[{\"url\": ").unwrap();
+    let error = load_downloads_from(&path).unwrap_err();
+
+    assert!(error.contains("corrompida"), "{}", error);
+    assert!(!path.exists());
+    let damaged: Vec<_> = std::fs::read_dir(dir.path()).unwrap().flatten().map(|e| e.path()).collect();
+    assert_eq!(damaged.len(), 1);
+    assert!(damaged[0].to_string_lossy().contains("downloads.json.danificado-"));
+    assert!(error.contains(&damaged[0].display().to_string()));
+    assert_eq!(std::fs::read_to_string(&damaged[0]).unwrap(), "[{\"url\": ");
+}
+
+#[test]
+fn private_downloads_never_reach_the_saved_list() {
+    let public = record("https://exemplo.com/a.iso", DownloadStatus::InProgress);
+    let mut private = record("https://exemplo.com/segredo.iso", DownloadStatus::InProgress);
+    private.options.private = true;
+    let records = vec![public.clone(), private];
+
+    let saved: Vec<_> = persisted_records(&records).into_iter().map(|r| r.id).collect();
+
+    assert_eq!(saved, vec![public.id]);
+}
+
+fn record(url: &str, status: DownloadStatus) -> DownloadRecord {
+    DownloadRecord { status, ..DownloadRecord::new(url, url.rsplit('/').next().unwrap()) }
+}
+
+#[test]
+fn profiles_keep_their_data_in_separate_folders() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(profile_dir(dir.path(), None), dir.path());
+    assert_eq!(profile_dir(dir.path(), Some("trabalho")), dir.path().join("profiles").join("trabalho"));
+
+    assert!(list_profiles_in(dir.path()).is_empty());
+    for name in ["pessoal", "trabalho", "nome inválido"] {
+        std::fs::create_dir_all(dir.path().join("profiles").join(name)).unwrap();
+    }
+    std::fs::write(dir.path().join("profiles").join("arquivo"), b"").unwrap();
+    assert_eq!(list_profiles_in(dir.path()), vec!["pessoal".to_string(), "trabalho".to_string()]);
+}
+
+#[test]
+fn portable_mode_keeps_data_beside_the_executable() {
+    let dir = tempfile::tempdir().unwrap();
+    let exe = dir.path().join("keepers");
+    assert_eq!(portable_data_dir(&exe, false), None);
+    assert_eq!(portable_data_dir(&exe, true), Some(dir.path().join("keeper-data")));
+    // O marcador ao lado do executável liga o modo sem precisar do argumento
+    std::fs::write(dir.path().join("portable.txt"), b"").unwrap();
+    assert_eq!(portable_data_dir(&exe, false), Some(dir.path().join("keeper-data")));
+}
+
+#[test]
+fn profile_names_must_be_safe_folder_names() {
+    assert!(valid_profile_name("trabalho"));
+    assert!(valid_profile_name("cliente_2-beta"));
+    assert!(!valid_profile_name(""));
+    assert!(!valid_profile_name("../fora"));
+    assert!(!valid_profile_name("com espaço"));
+    assert!(!valid_profile_name(&"a".repeat(41)));
+}
+
+#[test]
+fn network_profile_headers_include_the_credentials() {
+    let profile = NetworkProfile {
+        name: "CDN com token".to_string(),
+        headers: vec![("X-Token".to_string(), "abc".to_string())],
+        username: "ana".to_string(),
+        password: "segredo".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        profile.request_headers(),
+        vec![
+            ("X-Token".to_string(), "abc".to_string()),
+            ("Authorization".to_string(), "Basic YW5hOnNlZ3JlZG8=".to_string()),
+        ]
+    );
+    assert!(NetworkProfile::default().request_headers().is_empty());
+
+    let profiles = vec![profile];
+    assert!(find_network_profile(&profiles, "CDN com token").is_some());
+    assert!(find_network_profile(&profiles, "VPN").is_none());
+}
+
+#[test]
+fn header_lines_are_parsed_and_validated() {
+    assert_eq!(
+        parse_header_lines("X-Token: a:b\n\n  Accept-Language :pt-BR  ").unwrap(),
+        vec![("X-Token".to_string(), "a:b".to_string()), ("Accept-Language".to_string(), "pt-BR".to_string())]
+    );
+    assert!(parse_header_lines("sem dois pontos").is_err());
+    assert!(parse_header_lines("Nome Errado: x").is_err());
+}
@@ -0,0 +1,71 @@
+// Testes das sugestões do diálogo de adicionar a partir do histórico
+use chrono::{Duration, Utc};
+use keepers::storage::{DownloadOptions, DownloadRecord, DownloadStatus};
+use keepers::suggestions::{apply_suggestion, suggest_for, Suggestion};
+
+fn record(url: &str, options: DownloadOptions, days_ago: i64) -> DownloadRecord {
+    DownloadRecord {
+        status: DownloadStatus::Completed,
+        date_added: Utc::now() - Duration::days(days_ago),
+        options,
+        ..DownloadRecord::new(url, url.rsplit('/').next().unwrap())
+    }
+}
+
+fn options(directory: &str, connections: usize) -> DownloadOptions {
+    DownloadOptions {
+        directory: Some(directory.to_string()),
+        max_connections: Some(connections),
+        ..DownloadOptions::default()
+    }
+}
+
+#[test]
+fn most_recent_download_from_the_same_host_wins() {
+    let records = vec![
+        record("https://espelho.org/a.iso", options("/dados/antigo", 4), 10),
+        record("https://Espelho.org/b.iso", options("/dados/isos", 8), 1),
+        record("https://outro.org/c.iso", options("/dados/outro", 2), 0),
+    ];
+
+    let suggestion = suggest_for(&records, "https://espelho.org/novo.iso").unwrap();
+    assert_eq!(suggestion.host, "espelho.org");
+    assert_eq!(suggestion.directory.as_deref(), Some("/dados/isos"));
+    assert_eq!(suggestion.max_connections, Some(8));
+    assert_eq!(suggestion.summary(), "pasta /dados/isos · 8 conexões");
+
+    assert_eq!(suggest_for(&records, "https://desconhecido.org/x.iso"), None);
+    assert_eq!(suggest_for(&records, "não é url"), None);
+}
+
+#[test]
+fn nothing_to_suggest_without_options_or_from_private_downloads() {
+    let private = DownloadOptions { private: true, ..options("/segredo", 4) };
+    let records = vec![
+        record("https://espelho.org/a.iso", private, 0),
+        record("https://simples.org/b.iso", DownloadOptions::default(), 0),
+    ];
+    assert_eq!(suggest_for(&records, "https://espelho.org/c.iso"), None);
+    assert_eq!(suggest_for(&records, "https://simples.org/c.iso"), None);
+}
+
+#[test]
+fn suggestion_only_fills_what_was_not_chosen() {
+    let suggestion = Suggestion {
+        host: "espelho.org".to_string(),
+        directory: Some("/dados/isos".to_string()),
+        subfolder: Some("linux".to_string()),
+        network_profile: Some("Empresa".to_string()),
+        max_connections: Some(8),
+        speed_limit: None,
+    };
+    let mut chosen = DownloadOptions { network_profile: Some("Casa".to_string()), ..DownloadOptions::default() };
+
+    apply_suggestion(&suggestion, &mut chosen);
+
+    assert_eq!(chosen.directory.as_deref(), Some("/dados/isos"));
+    assert_eq!(chosen.subfolder.as_deref(), Some("linux"));
+    assert_eq!(chosen.network_profile.as_deref(), Some("Casa"));
+    assert_eq!(chosen.max_connections, Some(8));
+    assert_eq!(chosen.speed_limit, None);
+}
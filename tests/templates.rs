@@ -0,0 +1,60 @@
+// Testes dos modelos de download por padrão de URL
+use keepers::storage::{DownloadOptions, DownloadTemplate};
+use keepers::templates::{apply_matching_template, matching_template, pattern_matches};
+
+fn template(pattern: &str, directory: &str, connections: Option<usize>, limit: Option<u64>) -> DownloadTemplate {
+    DownloadTemplate {
+        pattern: pattern.to_string(),
+        directory: directory.to_string(),
+        max_connections: connections,
+        speed_limit: limit,
+    }
+}
+
+#[test]
+fn host_patterns_match_the_server_only() {
+    assert!(pattern_matches("*.example.com", "https://cdn.Example.com/a.iso"));
+    assert!(pattern_matches("*.example.com", "http://a.b.example.com:8080/x"));
+    assert!(!pattern_matches("*.example.com", "https://example.com/a.iso"));
+    // O caminho não conta para padrões de servidor
+    assert!(!pattern_matches("*.example.com", "https://outro.org/cdn.example.com"));
+    assert!(pattern_matches("mirror?.org", "https://mirror1.org/a"));
+    assert!(!pattern_matches("", "https://example.com/a"));
+}
+
+#[test]
+fn url_patterns_match_the_whole_address() {
+    assert!(pattern_matches("https://releases.site/*.iso", "https://releases.site/v1/distro.iso"));
+    assert!(!pattern_matches("https://releases.site/*.iso", "https://releases.site/v1/distro.zip"));
+    // Pontos e outros caracteres valem literalmente
+    assert!(!pattern_matches("https://a.b/x.iso", "https://aXb/x.iso"));
+}
+
+#[test]
+fn the_first_matching_template_wins() {
+    let templates = vec![
+        template("*.example.com", "/data/example", Some(8), Some(5 * 1024 * 1024)),
+        template("*.com", "/data/com", Some(2), None),
+    ];
+    assert_eq!(matching_template(&templates, "https://dl.example.com/a").unwrap().directory, "/data/example");
+    assert_eq!(matching_template(&templates, "https://site.com/a").unwrap().directory, "/data/com");
+    assert!(matching_template(&templates, "https://site.org/a").is_none());
+}
+
+#[test]
+fn templates_keep_the_options_already_chosen() {
+    let templates = vec![template("*.example.com", "/data/example", Some(8), Some(5 * 1024 * 1024))];
+    let mut options = DownloadOptions { speed_limit: Some(1024), ..Default::default() };
+
+    let applied = apply_matching_template(&templates, "https://dl.example.com/a.iso", &mut options);
+
+    assert_eq!(applied.as_deref(), Some("*.example.com"));
+    assert_eq!(options.directory.as_deref(), Some("/data/example"));
+    assert_eq!(options.max_connections, Some(8));
+    assert_eq!(options.speed_limit, Some(1024));
+    assert_eq!(options.template.as_deref(), Some("*.example.com"));
+
+    let mut untouched = DownloadOptions::default();
+    assert!(apply_matching_template(&templates, "https://outro.org/a.iso", &mut untouched).is_none());
+    assert_eq!(untouched, DownloadOptions::default());
+}
@@ -0,0 +1,24 @@
+// Testes do limite de velocidade global
+use std::time::Duration;
+use keepers::throttle::SpeedLimiter;
+
+#[test]
+fn limiter_delays_bytes_beyond_the_rate() {
+    let limiter = SpeedLimiter::new(Some(1_000));
+    let first = limiter.reserve(500);
+    assert!(first > Duration::from_millis(450) && first <= Duration::from_millis(500), "{:?}", first);
+    // A dívida se acumula entre os downloads que dividem o limite
+    let second = limiter.reserve(500);
+    assert!(second > Duration::from_millis(950), "{:?}", second);
+
+    limiter.set_limit(None);
+    assert_eq!(limiter.limit(), None);
+    assert_eq!(limiter.reserve(1_000_000), Duration::ZERO);
+}
+
+#[test]
+fn zero_means_unlimited() {
+    let limiter = SpeedLimiter::new(Some(0));
+    assert_eq!(limiter.limit(), None);
+    assert_eq!(limiter.reserve(10_000), Duration::ZERO);
+}
@@ -0,0 +1,57 @@
+// Testes do aviso de versão nova (comparação de versões e resposta do GitHub)
+use keepers::updates::{is_newer, parse_release, parse_version, releases_api, Release, PROJECT_URL};
+
+#[test]
+fn versions_are_compared_part_by_part() {
+    assert_eq!(parse_version("v1.10.0"), Some(vec![1, 10, 0]));
+    assert_eq!(parse_version("2.0.1-beta"), Some(vec![2, 0, 1]));
+    assert_eq!(parse_version("nightly"), None);
+
+    assert!(is_newer("1.10.0", "1.9.3"));
+    assert!(is_newer("v2.0", "1.9.9"));
+    assert!(is_newer("1.0.1", "1.0.0"));
+    assert!(!is_newer("1.0.0", "1.0.0"));
+    assert!(!is_newer("0.9.0", "1.0.0"));
+    assert!(!is_newer("nightly", "1.0.0"));
+}
+
+#[test]
+fn latest_release_gives_the_version_and_changelog() {
+    let json = r#"{"tag_name": "v1.2.0", "html_url": "https://github.com/lkaranl/Keepers/releases/tag/v1.2.0",
+                   "draft": false, "prerelease": false, "body": "Novidades"}"#;
+    assert_eq!(
+        parse_release(json).unwrap(),
+        Some(Release {
+            version: "1.2.0".to_string(),
+            url: "https://github.com/lkaranl/Keepers/releases/tag/v1.2.0".to_string(),
+        })
+    );
+}
+
+#[test]
+fn drafts_and_prereleases_are_ignored() {
+    let draft = r#"{"tag_name": "v9.0.0", "html_url": "https://x", "draft": true}"#;
+    let prerelease = r#"{"tag_name": "v9.0.0-rc1", "html_url": "https://x", "prerelease": true}"#;
+    let odd_tag = r#"{"tag_name": "continuous", "html_url": "https://x"}"#;
+    assert_eq!(parse_release(draft).unwrap(), None);
+    assert_eq!(parse_release(prerelease).unwrap(), None);
+    assert_eq!(parse_release(odd_tag).unwrap(), None);
+    assert!(parse_release("<html>rate limit</html>").is_err());
+}
+
+#[test]
+fn release_api_follows_the_project_homepage() {
+    assert_eq!(
+        releases_api(PROJECT_URL).as_deref(),
+        Some("https://api.github.com/repos/lkaranl/Keepers/releases/latest")
+    );
+    assert_eq!(releases_api("https://gitlab.com/dono/repo"), None);
+
+    // O appdata não lê o Cargo.toml: os dois precisam apontar para o mesmo lugar
+    let appdata = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/usr/share/metainfo/org.karansluciano.keepers.appdata.xml"
+    ))
+    .unwrap();
+    assert!(appdata.contains(&format!("<url type=\"homepage\">{}</url>", PROJECT_URL)));
+}
@@ -0,0 +1,42 @@
+// Testes da escrita de chunks pelo io_uring (só no Linux, com o recurso ativo)
+#![cfg(all(target_os = "linux", feature = "io-uring"))]
+use bytes::Bytes;
+use keepers::engine::ChunkFile;
+use keepers::uring::UringWriter;
+
+#[tokio::test]
+async fn uring_writes_land_at_their_offsets() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("arquivo.part");
+    std::fs::write(&path, vec![0u8; 10]).unwrap();
+    // Kernel sem io_uring (ou bloqueado no contêiner): o motor usa o tokio::fs::File
+    let Ok(writer) = UringWriter::open(&path) else {
+        return;
+    };
+
+    let (first, second) = tokio::join!(
+        writer.write_at(6, Bytes::from_static(b"mundo")),
+        writer.write_at(0, Bytes::from_static(b"ola, "))
+    );
+    first.unwrap();
+    second.unwrap();
+    writer.write_at(5, Bytes::new()).await.unwrap();
+    writer.sync().await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"ola, \0mundo");
+}
+
+#[tokio::test]
+async fn chunk_file_writes_out_of_order_with_either_backend() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("arquivo.part");
+    std::fs::write(&path, vec![0u8; 8]).unwrap();
+
+    let file = ChunkFile::open(&path).await.unwrap();
+    file.write_at(4, Bytes::from_static(b"5678")).await.unwrap();
+    file.write_at(0, Bytes::from_static(b"1234")).await.unwrap();
+    file.flush().await.unwrap();
+    drop(file);
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"12345678");
+}
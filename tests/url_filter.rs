@@ -0,0 +1,32 @@
+// Testes das regras de URLs permitidas e bloqueadas
+use keepers::storage::UrlFilterSettings;
+use keepers::url_filter::{check_url, UrlFilter};
+
+fn settings(allow: &[&str], deny: &[&str]) -> UrlFilterSettings {
+    UrlFilterSettings {
+        allow: allow.iter().map(|rule| rule.to_string()).collect(),
+        deny: deny.iter().map(|rule| rule.to_string()).collect(),
+    }
+}
+
+#[test]
+fn without_rules_everything_passes() {
+    assert!(check_url(&UrlFilterSettings::default(), "https://qualquer.com/a.iso").is_ok());
+}
+
+#[test]
+fn deny_wins_over_allow() {
+    let filter = UrlFilter::new(&settings(&[r"^https://([a-z]+\.)?escola\.edu/"], &[r"\.exe$"])).unwrap();
+
+    assert!(filter.check("https://escola.edu/apostila.pdf").is_ok());
+    assert!(filter.check("HTTPS://Biblioteca.ESCOLA.edu/livro.epub").is_ok());
+    assert!(filter.check("https://escola.edu/jogo.EXE").unwrap_err().contains(r"\.exe$"));
+    assert!(filter.check("https://outro.com/apostila.pdf").is_err());
+}
+
+#[test]
+fn broken_rules_block_instead_of_allowing() {
+    let broken = settings(&[], &["(sem fechar"]);
+    assert!(UrlFilter::new(&broken).is_err());
+    assert!(check_url(&broken, "https://qualquer.com/a.iso").is_err());
+}
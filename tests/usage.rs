@@ -0,0 +1,84 @@
+// Testes do consumo mensal de dados e da cota
+use chrono::{Local, TimeZone};
+use keepers::storage::QuotaSettings;
+use keepers::usage::{month_key, quota_state, DataUsage, QuotaState, HOURLY_RETENTION_DAYS};
+
+fn local(y: i32, mo: u32, d: u32) -> chrono::DateTime<Local> {
+    Local.with_ymd_and_hms(y, mo, d, 12, 0, 0).single().unwrap()
+}
+
+#[test]
+fn usage_is_split_by_calendar_month() {
+    let mut usage = DataUsage::default();
+    usage.add(100, local(2026, 9, 30));
+    usage.add(250, local(2026, 10, 1));
+    usage.add(50, local(2026, 10, 18));
+
+    assert_eq!(usage.month_total(local(2026, 9, 1)), 100);
+    assert_eq!(usage.month_total(local(2026, 10, 31)), 300);
+    assert_eq!(usage.month_total(local(2026, 11, 1)), 0);
+    assert_eq!(month_key(local(2026, 3, 5)), "2026-03");
+}
+
+#[test]
+fn quota_warns_at_eighty_percent_and_blocks_when_exhausted() {
+    assert_eq!(quota_state(1_000, None), QuotaState::Normal);
+    assert_eq!(quota_state(799, Some(1_000)), QuotaState::Normal);
+    assert_eq!(quota_state(800, Some(1_000)), QuotaState::Warning);
+    assert_eq!(quota_state(1_000, Some(1_000)), QuotaState::Exhausted);
+
+    let now = local(2026, 10, 18);
+    let mut usage = DataUsage::default();
+    usage.add(2_000, now);
+    let mut quota = QuotaSettings {
+        monthly_limit: Some(1_000),
+        override_month: None,
+    };
+    assert!(quota.blocks_new_downloads(&usage, now));
+
+    // Ignorar vale só para o mês em que foi pedido
+    quota.override_month = Some(month_key(now));
+    assert!(!quota.blocks_new_downloads(&usage, now));
+    quota.override_month = Some("2026-09".to_string());
+    assert!(quota.blocks_new_downloads(&usage, now));
+}
+
+#[test]
+fn hourly_totals_feed_daily_and_weekly_charts() {
+    let at = |d: u32, h: u32| Local.with_ymd_and_hms(2026, 10, d, h, 30, 0).single().unwrap();
+    let mut usage = DataUsage::default();
+    usage.add(10, at(18, 0));
+    usage.add(20, at(18, 14));
+    usage.add(5, at(18, 14));
+    usage.add(7, at(16, 23));
+
+    let hours = usage.hourly_series(at(18, 0).date_naive());
+    assert_eq!(hours.len(), 24);
+    assert_eq!((hours[0], hours[14], hours[23]), (10, 25, 0));
+
+    let days = usage.daily_series(at(18, 0).date_naive(), 3);
+    let totals: Vec<u64> = days.iter().map(|(_, bytes)| *bytes).collect();
+    assert_eq!(totals, vec![7, 0, 35]);
+    assert_eq!(days[0].0, at(16, 0).date_naive());
+
+    // Horas antigas somem dos gráficos, mas o total do mês continua
+    usage.prune_hours(at(18, 0) + chrono::Duration::days(HOURLY_RETENTION_DAYS - 1));
+    assert_eq!(usage.hourly_series(at(16, 0).date_naive())[23], 0);
+    assert_eq!(usage.hourly_series(at(18, 0).date_naive())[14], 25);
+    assert_eq!(usage.month_total(at(1, 0)), 42);
+}
+
+#[test]
+fn session_usage_adds_to_the_saved_totals() {
+    let mut saved = DataUsage::default();
+    saved.add(1000, local(2026, 10, 3));
+    let mut session = DataUsage::default();
+    session.add(500, local(2026, 10, 3));
+    session.add(200, local(2026, 11, 1));
+
+    saved.merge(&session);
+
+    assert_eq!(saved.month_total(local(2026, 10, 20)), 1500);
+    assert_eq!(saved.month_total(local(2026, 11, 20)), 200);
+    assert_eq!(saved.hours.values().sum::<u64>(), 1700);
+}
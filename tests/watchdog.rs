@@ -0,0 +1,51 @@
+// Testes do tempo máximo e da velocidade mínima de um download
+use std::time::Duration;
+use keepers::storage::{DownloadOptions, MinSpeed};
+use keepers::watchdog::TransferWatch;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+#[test]
+fn downloads_without_a_policy_are_not_watched() {
+    assert!(TransferWatch::for_options(&DownloadOptions::default(), 0).is_none());
+    let zero = DownloadOptions {
+        max_minutes: Some(0),
+        min_speed: Some(MinSpeed { bytes_per_sec: 0, minutes: 10 }),
+        ..DownloadOptions::default()
+    };
+    assert!(TransferWatch::for_options(&zero, 0).is_none());
+}
+
+#[test]
+fn deadline_counts_only_active_time() {
+    let options = DownloadOptions { max_minutes: Some(30), ..DownloadOptions::default() };
+    let mut watch = TransferWatch::for_options(&options, 0).unwrap();
+    assert_eq!(watch.tick(20 * MINUTE, 1000), None);
+    assert_eq!(watch.tick(9 * MINUTE, 2000), None);
+    assert!(watch.tick(MINUTE, 3000).unwrap().contains("Tempo máximo"));
+}
+
+#[test]
+fn slow_transfer_fails_only_after_the_whole_window() {
+    let options = DownloadOptions {
+        min_speed: Some(MinSpeed { bytes_per_sec: 50 * 1024, minutes: 10 }),
+        ..DownloadOptions::default()
+    };
+    let mut watch = TransferWatch::for_options(&options, 0).unwrap();
+    // 100 KB/s nos primeiros 10 minutos
+    let mut downloaded = 0;
+    for _ in 0..10 {
+        downloaded += 100 * 1024 * 60;
+        assert_eq!(watch.tick(MINUTE, downloaded), None);
+    }
+    // Depois quase nada: a média dos últimos 10 minutos só cai abaixo de 50 KB/s no meio da janela
+    let mut reason = None;
+    let mut minutes = 0;
+    while reason.is_none() && minutes < 20 {
+        downloaded += 1024;
+        reason = watch.tick(MINUTE, downloaded);
+        minutes += 1;
+    }
+    assert!(reason.unwrap().contains("Abaixo de"));
+    assert!((5..=10).contains(&minutes), "desistiu após {} minutos", minutes);
+}